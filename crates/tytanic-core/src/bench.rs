@@ -0,0 +1,323 @@
+//! Benchmarking of unit tests against persisted baselines.
+//!
+//! A benchmark compiles and renders a test repeatedly and summarizes the
+//! durations of each step, the baseline of a test is stored in its test
+//! directory, see [`Baselines`].
+//!
+//! Typst memoizes compilations, so all caches are evicted before each
+//! iteration, such that each iteration measures a cold compilation.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::Instant;
+
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use typst::diag::Warned;
+use typst::syntax::Source;
+use typst::World;
+use tytanic_utils::result::io_not_found;
+use tytanic_utils::result::ResultEx;
+
+use crate::doc::compile;
+use crate::doc::compile::TestWorldAdapter;
+use crate::doc::compile::Warnings;
+use crate::doc::Document;
+use crate::project::Project;
+use crate::test::Id;
+
+/// The name of the baseline file within a test directory.
+pub const BENCH_FILE: &str = "bench.json";
+
+/// How many times a test is compiled when benchmarking it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iterations {
+    /// The number of iterations which are run before measuring.
+    pub warmup: usize,
+
+    /// The number of measured iterations, this must be at least `1`.
+    pub measured: usize,
+}
+
+impl Default for Iterations {
+    fn default() -> Self {
+        Self {
+            warmup: 1,
+            measured: 5,
+        }
+    }
+}
+
+/// Benchmarks the compilation of the given source, the output is rendered at
+/// the given pixel per pt ratio if it is given.
+///
+/// Returns the compilation error of the first failed iteration.
+///
+/// # Panics
+/// Panics if no iterations are measured.
+pub fn measure<'w, F>(
+    source: Source,
+    world: &'w dyn World,
+    iterations: Iterations,
+    pixel_per_pt: Option<f32>,
+    f: F,
+) -> Result<Measurement, compile::Error>
+where
+    F: for<'a> Fn(&'a mut TestWorldAdapter<'w>) -> &'a mut TestWorldAdapter<'w>,
+{
+    assert!(
+        iterations.measured != 0,
+        "must measure at least one iteration"
+    );
+
+    let mut compile_samples = Vec::with_capacity(iterations.measured);
+    let mut render_samples = Vec::with_capacity(iterations.measured);
+    let mut pages = 0;
+
+    for iteration in 0..iterations.warmup + iterations.measured {
+        comemo::evict(0);
+
+        let start = Instant::now();
+        let Warned { output, .. } = compile::compile(source.clone(), world, Warnings::Ignore, &f);
+        let doc = output?;
+        let compiled = start.elapsed();
+
+        pages = doc.pages.len();
+
+        let start = Instant::now();
+        if let Some(pixel_per_pt) = pixel_per_pt {
+            Document::render(doc, pixel_per_pt);
+        }
+        let rendered = start.elapsed();
+
+        if iteration >= iterations.warmup {
+            compile_samples.push(compiled);
+            render_samples.push(rendered);
+        }
+    }
+
+    Ok(Measurement {
+        iterations: iterations.measured,
+        pages,
+        compile: Stats::new(&mut compile_samples),
+        render: pixel_per_pt.map(|_| Stats::new(&mut render_samples)),
+    })
+}
+
+/// The result of benchmarking a single test.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Measurement {
+    iterations: usize,
+    pages: usize,
+    compile: Stats,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    render: Option<Stats>,
+}
+
+impl Measurement {
+    /// The number of measured iterations.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+
+    /// The number of pages of the compiled document.
+    pub fn pages(&self) -> usize {
+        self.pages
+    }
+
+    /// The durations of the compilation, this includes evaluation and layout.
+    pub fn compile(&self) -> &Stats {
+        &self.compile
+    }
+
+    /// The durations of rendering the pages, this is `None` for tests which
+    /// are not rendered.
+    pub fn render(&self) -> Option<&Stats> {
+        self.render.as_ref()
+    }
+
+    /// The median duration of an iteration, i.e. of compiling and rendering.
+    pub fn median(&self) -> Duration {
+        self.compile.median() + self.render.as_ref().map_or(Duration::ZERO, Stats::median)
+    }
+
+    /// The factor by which this measurement is slower than the given baseline
+    /// by their medians, a factor below `1.0` means it is faster.
+    pub fn slowdown(&self, baseline: &Self) -> f64 {
+        let baseline = baseline.median().as_secs_f64();
+        if baseline == 0.0 {
+            return 1.0;
+        }
+
+        self.median().as_secs_f64() / baseline
+    }
+}
+
+/// A summary of the durations of a step over all measured iterations, the
+/// durations are stored in microseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Stats {
+    min_us: u64,
+    median_us: u64,
+    mean_us: u64,
+    max_us: u64,
+}
+
+impl Stats {
+    /// Summarizes the given non-empty samples, these are sorted in place.
+    fn new(samples: &mut [Duration]) -> Self {
+        samples.sort();
+
+        let micros = |duration: Duration| duration.as_micros().try_into().unwrap_or(u64::MAX);
+        let total: Duration = samples.iter().sum();
+
+        let mid = samples.len() / 2;
+        let median = if samples.len() % 2 == 0 {
+            (samples[mid - 1] + samples[mid]) / 2
+        } else {
+            samples[mid]
+        };
+
+        Self {
+            min_us: micros(samples[0]),
+            median_us: micros(median),
+            mean_us: micros(total / samples.len() as u32),
+            max_us: micros(samples[samples.len() - 1]),
+        }
+    }
+
+    /// The shortest duration.
+    pub fn min(&self) -> Duration {
+        Duration::from_micros(self.min_us)
+    }
+
+    /// The median duration.
+    pub fn median(&self) -> Duration {
+        Duration::from_micros(self.median_us)
+    }
+
+    /// The mean duration.
+    pub fn mean(&self) -> Duration {
+        Duration::from_micros(self.mean_us)
+    }
+
+    /// The longest duration.
+    pub fn max(&self) -> Duration {
+        Duration::from_micros(self.max_us)
+    }
+}
+
+/// The baselines of a single test, instances of parametrized tests share a
+/// test directory, so they're keyed by their full id.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Baselines(BTreeMap<String, Measurement>);
+
+impl Baselines {
+    /// The path to the baseline file of the given test.
+    pub fn file(project: &Project, id: &Id) -> PathBuf {
+        project.unit_test_dir(id).join(BENCH_FILE)
+    }
+
+    /// Loads the baselines of the given test, returns empty baselines if none
+    /// were stored yet.
+    #[tracing::instrument(skip(project))]
+    pub fn load(project: &Project, id: &Id) -> Result<Self, Error> {
+        let Some(content) = fs::read_to_string(Self::file(project, id)).ignore(io_not_found)?
+        else {
+            return Ok(Self::default());
+        };
+
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    /// Stores these baselines for the given test.
+    #[tracing::instrument(skip(self, project))]
+    pub fn save(&self, project: &Project, id: &Id) -> Result<(), Error> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs::write(Self::file(project, id), content)?;
+
+        Ok(())
+    }
+
+    /// The baseline of the given test, if it has one.
+    pub fn get(&self, id: &Id) -> Option<&Measurement> {
+        self.0.get(id.as_str())
+    }
+
+    /// Sets the baseline of the given test.
+    pub fn set(&mut self, id: &Id, measurement: Measurement) {
+        self.0.insert(id.as_str().to_owned(), measurement);
+    }
+}
+
+/// Returned by [`Baselines::load`] and [`Baselines::save`].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The baselines could not be parsed or serialized.
+    #[error("the baselines could not be parsed or serialized")]
+    Json(#[from] serde_json::Error),
+
+    /// An io error occurred.
+    #[error("an io error occurred")]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let mut samples = [3, 1, 2, 10].map(Duration::from_millis);
+        let stats = Stats::new(&mut samples);
+
+        assert_eq!(stats.min(), Duration::from_millis(1));
+        assert_eq!(stats.median(), Duration::from_micros(2500));
+        assert_eq!(stats.mean(), Duration::from_millis(4));
+        assert_eq!(stats.max(), Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_stats_median() {
+        let median = |samples: &[u64]| {
+            Stats::new(
+                &mut samples
+                    .iter()
+                    .copied()
+                    .map(Duration::from_millis)
+                    .collect::<Vec<_>>(),
+            )
+            .median()
+        };
+
+        assert_eq!(median(&[5]), Duration::from_millis(5));
+        assert_eq!(median(&[4, 1]), Duration::from_micros(2500));
+        assert_eq!(median(&[7, 1, 3]), Duration::from_millis(3));
+        assert_eq!(median(&[8, 1, 2, 6, 4, 100]), Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_measurement_slowdown() {
+        let stats = |ms| Stats::new(&mut [Duration::from_millis(ms)]);
+        let baseline = Measurement {
+            iterations: 1,
+            pages: 1,
+            compile: stats(100),
+            render: Some(stats(100)),
+        };
+        let measurement = Measurement {
+            compile: stats(300),
+            ..baseline.clone()
+        };
+
+        assert_eq!(measurement.slowdown(&baseline), 2.0);
+        assert_eq!(baseline.slowdown(&measurement), 0.5);
+    }
+}
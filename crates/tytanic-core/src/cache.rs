@@ -0,0 +1,82 @@
+//! Files persisted in the project's [cache directory] across test runs.
+//!
+//! The cache directory is created on demand and ignored in the project's VCS,
+//! its files are stored as TOML, see [`History`] and [`Inputs`].
+//!
+//! [cache directory]: Project::cache_dir
+//! [`History`]: crate::history::History
+//! [`Inputs`]: crate::inputs::Inputs
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use thiserror::Error;
+use tytanic_utils::result::io_not_found;
+use tytanic_utils::result::ResultEx;
+
+use crate::project::Project;
+
+/// Creates the cache directory of the given project and ignores it in the
+/// project's VCS if necessary, returns the path to the cache directory.
+pub fn create_dir(project: &Project) -> io::Result<PathBuf> {
+    let dir = project.cache_dir();
+    tytanic_utils::fs::create_dir(&dir, true)?;
+
+    if let Some(vcs) = project.vcs() {
+        vcs.ignore_cache(project)?;
+    }
+
+    Ok(dir)
+}
+
+/// Loads the cache file with the given name, returns the default value if it
+/// wasn't persisted yet.
+pub(crate) fn load<T>(project: &Project, name: &str) -> Result<T, Error>
+where
+    T: DeserializeOwned + Default,
+{
+    let path = project.cache_dir().join(name);
+    let Some(content) = fs::read_to_string(&path).ignore(io_not_found)? else {
+        return Ok(T::default());
+    };
+
+    toml::from_str(&content).map_err(|source| Error::Parse { path, source })
+}
+
+/// Persists the given value into the cache file with the given name, see
+/// [`create_dir`].
+pub(crate) fn save<T: Serialize>(project: &Project, name: &str, value: &T) -> Result<(), Error> {
+    let content = toml::to_string(value)?;
+    fs::write(create_dir(project)?.join(name), content)?;
+
+    Ok(())
+}
+
+/// Returned by the functions persisting cache files, like
+/// [`History::load`] and [`History::save`].
+///
+/// [`History::load`]: crate::history::History::load
+/// [`History::save`]: crate::history::History::save
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A cache file could not be parsed.
+    #[error("the cache file {} could not be parsed", path.display())]
+    Parse {
+        /// The path to the cache file.
+        path: PathBuf,
+
+        /// The parser error.
+        source: toml::de::Error,
+    },
+
+    /// A cache file could not be serialized.
+    #[error("a cache file could not be serialized")]
+    Serialize(#[from] toml::ser::Error),
+
+    /// An io error occurred.
+    #[error("an io error occurred")]
+    Io(#[from] io::Error),
+}
@@ -0,0 +1,90 @@
+//! Cooperative cancellation of test runs.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A token used to cancel a test run cooperatively.
+///
+/// Cloned tokens share their state, cancelling one of them cancels all of
+/// them. Runners check the token before starting a test, compilations check it
+/// whenever they access a file and abort early once it's cancelled. Detached
+/// compilations are abandoned once it's cancelled, see
+/// [`compile_detached`][crate::doc::compile::compile_detached].
+///
+/// A token may additionally have a deadline after which it is considered
+/// cancelled, this is used to time out single tests.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Creates a new token which is not yet cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a clone of this token which is additionally cancelled once the
+    /// given deadline has passed.
+    ///
+    /// The deadline does not affect this token or its other clones.
+    pub fn with_deadline(&self, deadline: Instant) -> Self {
+        Self {
+            cancelled: Arc::clone(&self.cancelled),
+            deadline: Some(deadline),
+        }
+    }
+
+    /// Cancels this token and all of its clones.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether this token was cancelled or its deadline has passed.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.is_expired()
+    }
+
+    /// Whether the deadline of this token has passed.
+    pub fn is_expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_cancel_clones() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+
+        clone.cancel();
+        assert!(token.is_cancelled());
+        assert!(clone.is_cancelled());
+    }
+
+    #[test]
+    fn test_deadline() {
+        let token = CancellationToken::new();
+        let expired = token.with_deadline(Instant::now());
+        assert!(expired.is_expired());
+        assert!(expired.is_cancelled());
+        assert!(!token.is_cancelled());
+
+        let pending = token.with_deadline(Instant::now() + Duration::from_secs(60));
+        assert!(!pending.is_cancelled());
+
+        token.cancel();
+        assert!(pending.is_cancelled());
+        assert!(!pending.is_expired());
+    }
+}
@@ -1,5 +1,6 @@
 //! Reading and interpreting Tytanic configuration.
 
+use std::collections::BTreeMap;
 use std::fs;
 use std::io;
 
@@ -9,9 +10,23 @@ use thiserror::Error;
 use tytanic_utils::result::io_not_found;
 use tytanic_utils::result::ResultEx;
 
+use crate::doc;
+use crate::doc::compare::ColorSpace;
+use crate::doc::compare::Strategy;
+use crate::doc::compare::StrategyKind;
+use crate::doc::compare::DEFAULT_SSIM_THRESHOLD;
+use crate::doc::compile::Warnings;
+use crate::doc::render::Alpha;
+use crate::doc::render::Format;
+use crate::doc::Limits;
+use crate::test::Id;
+
 /// The key used to configure Tytanic in the manifest tool config.
 pub const MANIFEST_TOOL_KEY: &str = crate::TOOL_NAME;
 
+/// The default test root directory relative to the project root.
+pub const DEFAULT_UNIT_TESTS_ROOT: &str = "tests";
+
 /// The directory name for in which the user config can be found.
 pub const CONFIG_SUB_DIRECTORY: &str = crate::TOOL_NAME;
 
@@ -20,7 +35,15 @@ pub const CONFIG_SUB_DIRECTORY: &str = crate::TOOL_NAME;
 #[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
-pub struct SystemConfig {}
+pub struct SystemConfig {
+    /// The HTTP proxy to send network requests through.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    /// The hosts to reach without the proxy.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_proxy: Vec<String>,
+}
 
 impl SystemConfig {
     /// Reads the user config at its predefined location.
@@ -42,7 +65,8 @@ impl SystemConfig {
 }
 
 /// A project config, read from a project's manifest.
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct ProjectConfig {
@@ -53,8 +77,46 @@ pub struct ProjectConfig {
     pub unit_tests_root: String,
 
     /// The project wide defaults.
-    #[serde(rename = "default")]
+    #[serde(rename = "default", default)]
     pub defaults: ProjectDefaults,
+
+    /// The modules whose tests must not run concurrently with each other,
+    /// they may still run concurrently with tests of other modules.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub serial: Vec<String>,
+
+    /// Named profiles which can be selected using `--profile`.
+    #[serde(
+        rename = "profile",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub profiles: BTreeMap<String, ProjectProfile>,
+
+    /// Named test set expressions which can be used like built in test sets
+    /// without arguments, e.g. `smoke()`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub test_sets: BTreeMap<String, String>,
+
+    /// The commands run around the test suite and each test.
+    #[serde(default, skip_serializing_if = "ProjectHooks::is_empty")]
+    pub hooks: ProjectHooks,
+}
+
+impl ProjectConfig {
+    /// Returns the profile with the given name, if it exists.
+    pub fn profile(&self, name: &str) -> Option<&ProjectProfile> {
+        self.profiles.get(name)
+    }
+
+    /// Returns the outermost module configured in [`ProjectConfig::serial`]
+    /// which contains the given test, if there is one.
+    pub fn serial_module<'s>(&self, id: &'s Id) -> Option<&'s str> {
+        id.ancestors()
+            .skip(1)
+            .filter(|module| self.serial.iter().any(|serial| serial == module))
+            .last()
+    }
 }
 
 impl Default for ProjectConfig {
@@ -62,15 +124,83 @@ impl Default for ProjectConfig {
         Self {
             unit_tests_root: default_unit_tests_root(),
             defaults: ProjectDefaults::default(),
+            serial: vec![],
+            profiles: BTreeMap::new(),
+            test_sets: BTreeMap::new(),
+            hooks: ProjectHooks::default(),
         }
     }
 }
 
 fn default_unit_tests_root() -> String {
-    String::from("tests")
+    String::from(DEFAULT_UNIT_TESTS_ROOT)
+}
+
+/// A document manifest, read from the `tytanic.toml` file in the test root of
+/// projects which are not packages, like theses or reports.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct DocumentManifest {
+    /// The document of this project.
+    pub document: DocumentInfo,
+
+    /// The project config, like the `tool.tytanic` section of a package
+    /// manifest.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<ProjectConfig>,
+}
+
+/// The `document` section of a [`DocumentManifest`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct DocumentInfo {
+    /// The name of the document.
+    pub name: String,
+
+    /// The entrypoints of the document relative to the project root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entrypoints: Vec<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, Deserialize)]
+/// Shell commands which are run around the test suite and each unit test,
+/// see [`crate::hook`].
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProjectHooks {
+    /// The command run once before any test is run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prepare: Option<String>,
+
+    /// The command run before each unit test is run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prepare_each: Option<String>,
+
+    /// The command run after each unit test was run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleanup_each: Option<String>,
+
+    /// The command run once after all tests were run.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleanup: Option<String>,
+}
+
+impl ProjectHooks {
+    /// Whether no hooks are configured.
+    pub fn is_empty(&self) -> bool {
+        self.prepare.is_none()
+            && self.prepare_each.is_none()
+            && self.cleanup_each.is_none()
+            && self.cleanup.is_none()
+    }
+}
+
+/// The project wide defaults, used if no other value is given.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(deny_unknown_fields)]
 #[serde(rename_all = "kebab-case")]
 pub struct ProjectDefaults {
@@ -95,6 +225,107 @@ pub struct ProjectDefaults {
     /// Defaults to `0`.
     #[serde(default = "default_max_deviations")]
     pub max_deviations: usize,
+
+    /// The default maximum allowed difference in page width or height in
+    /// pixels for a comparison.
+    ///
+    /// Defaults to `0`.
+    #[serde(default = "default_max_size_delta")]
+    pub max_size_delta: u32,
+
+    /// The default color space in which pixels are compared.
+    ///
+    /// Defaults to `srgb`.
+    #[serde(default)]
+    pub color_space: ColorSpace,
+
+    /// The default strategy used to compare pages.
+    ///
+    /// Defaults to `simple`.
+    #[serde(default)]
+    pub compare_strategy: StrategyKind,
+
+    /// The default minimum structural similarity for the `ssim` strategy.
+    ///
+    /// Defaults to `0.99`.
+    #[serde(default = "default_threshold")]
+    pub threshold: f32,
+
+    /// The default handling of transparent pixels for comparisons and diff
+    /// images.
+    ///
+    /// Defaults to `channel`.
+    #[serde(default)]
+    pub alpha: Alpha,
+
+    /// Whether deviations which look like anti-aliasing artifacts are ignored
+    /// by the `simple` strategy.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub ignore_antialiasing: bool,
+
+    /// The format in which persistent references are stored.
+    ///
+    /// Defaults to `png`.
+    #[serde(default)]
+    pub reference_format: Format,
+
+    /// The maximum number of pages a test document may have, tests with more
+    /// pages fail without being rendered.
+    ///
+    /// Defaults to `1000`.
+    #[serde(default = "default_max_pages")]
+    pub max_pages: usize,
+
+    /// The maximum number of pixels a single page may have at the used pixel
+    /// per inch, tests with larger pages fail without being rendered.
+    ///
+    /// Defaults to `268435456` (`2^28`).
+    #[serde(default = "default_max_page_pixels")]
+    pub max_page_pixels: u64,
+
+    /// How to handle warnings.
+    ///
+    /// Defaults to `emit`.
+    #[serde(default)]
+    pub warnings: Warnings,
+
+    /// Whether persistent references are optimized when they are updated.
+    ///
+    /// Defaults to `true`.
+    #[serde(default = "default_optimize_refs")]
+    pub optimize_refs: bool,
+}
+
+impl ProjectDefaults {
+    /// The comparison strategy configured by these defaults.
+    pub fn strategy(&self) -> Strategy {
+        match self.compare_strategy {
+            StrategyKind::Simple => Strategy::Simple {
+                max_delta: self.max_delta,
+                max_deviation: self.max_deviations,
+                max_size_delta: self.max_size_delta,
+                color_space: self.color_space,
+                alpha: self.alpha,
+                ignore_antialiasing: self.ignore_antialiasing,
+                sample: None,
+            },
+            StrategyKind::Ssim => Strategy::Ssim {
+                threshold: self.threshold,
+                max_size_delta: self.max_size_delta,
+                alpha: self.alpha,
+            },
+        }
+    }
+
+    /// The document size limits configured by these defaults.
+    pub fn limits(&self) -> Limits {
+        Limits {
+            max_pages: self.max_pages,
+            max_page_pixels: self.max_page_pixels,
+        }
+    }
 }
 
 impl Default for ProjectDefaults {
@@ -104,6 +335,17 @@ impl Default for ProjectDefaults {
             ppi: default_ppi(),
             max_delta: default_max_delta(),
             max_deviations: default_max_deviations(),
+            max_size_delta: default_max_size_delta(),
+            color_space: ColorSpace::default(),
+            compare_strategy: StrategyKind::default(),
+            threshold: default_threshold(),
+            alpha: Alpha::default(),
+            ignore_antialiasing: false,
+            reference_format: Format::default(),
+            max_pages: default_max_pages(),
+            max_page_pixels: default_max_page_pixels(),
+            warnings: Warnings::default(),
+            optimize_refs: default_optimize_refs(),
         }
     }
 }
@@ -124,8 +366,189 @@ fn default_max_deviations() -> usize {
     0
 }
 
+fn default_max_size_delta() -> u32 {
+    0
+}
+
+fn default_threshold() -> f32 {
+    DEFAULT_SSIM_THRESHOLD
+}
+
+fn default_max_pages() -> usize {
+    doc::DEFAULT_MAX_PAGES
+}
+
+fn default_max_page_pixels() -> u64 {
+    doc::MAX_PAGE_PIXELS
+}
+
+fn default_optimize_refs() -> bool {
+    true
+}
+
+/// A named profile, this bundles options which would otherwise have to be
+/// passed on the command line.
+///
+/// Options which are not set fall back to the project wide defaults, options
+/// passed on the command line take precedence over the profile.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(deny_unknown_fields)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProjectProfile {
+    /// Overrides the default direction.
+    #[serde(rename = "dir", default, skip_serializing_if = "Option::is_none")]
+    pub direction: Option<Direction>,
+
+    /// Overrides the default pixel per inch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ppi: Option<f32>,
+
+    /// Overrides the default maximum allowed delta per pixel.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_delta: Option<u8>,
+
+    /// Overrides the default maximum allowed deviating pixels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_deviations: Option<usize>,
+
+    /// Overrides the default maximum allowed difference in page width or
+    /// height.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_size_delta: Option<u32>,
+
+    /// Overrides the default color space for comparisons.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_space: Option<ColorSpace>,
+
+    /// Overrides the default comparison strategy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compare_strategy: Option<StrategyKind>,
+
+    /// Overrides the default minimum structural similarity.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<f32>,
+
+    /// Overrides the default handling of transparent pixels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha: Option<Alpha>,
+
+    /// Overrides whether anti-aliasing artifacts are ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_antialiasing: Option<bool>,
+
+    /// Overrides the default reference format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_format: Option<Format>,
+
+    /// Overrides the default maximum number of pages.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_pages: Option<usize>,
+
+    /// Overrides the default maximum number of pixels per page.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_page_pixels: Option<u64>,
+
+    /// How to handle warnings, like `--warnings`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warnings: Option<Warnings>,
+
+    /// Whether to abort after the first failure, like `--[no-]fail-fast`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fail_fast: Option<bool>,
+
+    /// Whether to compare tests, like `--[no-]compare`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compare: Option<bool>,
+
+    /// Whether to export ephemeral documents, like
+    /// `--[no-]export-ephemeral`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_ephemeral: Option<bool>,
+
+    /// Whether to optimize persistent references, like
+    /// `--[no-]optimize-refs`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub optimize_refs: Option<bool>,
+
+    /// Whether to print a JSON report, like `--json`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<bool>,
+
+    /// The path to write a jUnit XML report to, like `--junit`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub junit: Option<String>,
+}
+
+impl ProjectProfile {
+    /// Applies the overridden defaults of this profile to the given project
+    /// wide defaults.
+    pub fn apply_defaults(&self, defaults: &mut ProjectDefaults) {
+        if let Some(direction) = self.direction {
+            defaults.direction = direction;
+        }
+
+        if let Some(ppi) = self.ppi {
+            defaults.ppi = ppi;
+        }
+
+        if let Some(max_delta) = self.max_delta {
+            defaults.max_delta = max_delta;
+        }
+
+        if let Some(max_deviations) = self.max_deviations {
+            defaults.max_deviations = max_deviations;
+        }
+
+        if let Some(max_size_delta) = self.max_size_delta {
+            defaults.max_size_delta = max_size_delta;
+        }
+
+        if let Some(color_space) = self.color_space {
+            defaults.color_space = color_space;
+        }
+
+        if let Some(compare_strategy) = self.compare_strategy {
+            defaults.compare_strategy = compare_strategy;
+        }
+
+        if let Some(threshold) = self.threshold {
+            defaults.threshold = threshold;
+        }
+
+        if let Some(alpha) = self.alpha {
+            defaults.alpha = alpha;
+        }
+
+        if let Some(ignore_antialiasing) = self.ignore_antialiasing {
+            defaults.ignore_antialiasing = ignore_antialiasing;
+        }
+
+        if let Some(reference_format) = self.reference_format {
+            defaults.reference_format = reference_format;
+        }
+
+        if let Some(max_pages) = self.max_pages {
+            defaults.max_pages = max_pages;
+        }
+
+        if let Some(max_page_pixels) = self.max_page_pixels {
+            defaults.max_page_pixels = max_page_pixels;
+        }
+
+        if let Some(warnings) = self.warnings {
+            defaults.warnings = warnings;
+        }
+
+        if let Some(optimize_refs) = self.optimize_refs {
+            defaults.optimize_refs = optimize_refs;
+        }
+    }
+}
+
 /// The reading direction of a document.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[serde(rename_all = "kebab-case")]
 pub enum Direction {
     /// The documents are generated left-to-right.
@@ -147,3 +570,107 @@ pub enum Error {
     #[error("an io error occurred")]
     Io(#[from] io::Error),
 }
+
+/// Generates a JSON Schema for the project config as found in the manifest
+/// tool table.
+#[cfg(feature = "schema")]
+pub fn project_config_schema() -> schemars::schema::RootSchema {
+    let mut schema = schemars::schema_for!(ProjectConfig);
+    schema.schema.metadata().title = Some(String::from("Tytanic project config"));
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_apply_defaults() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            [default]
+            max-delta = 2
+
+            [profile.ci]
+            max-deviations = 5
+            warnings = "promote"
+            fail-fast = false
+            "#,
+        )
+        .unwrap();
+
+        let profile = config.profile("ci").unwrap();
+        assert_eq!(profile.warnings, Some(Warnings::Promote));
+        assert_eq!(profile.fail_fast, Some(false));
+        assert!(config.profile("local").is_none());
+
+        let mut defaults = config.defaults.clone();
+        profile.apply_defaults(&mut defaults);
+        assert_eq!(defaults.max_delta, 2);
+        assert_eq!(defaults.max_deviations, 5);
+        assert_eq!(defaults.ppi, 144.0);
+        assert_eq!(defaults.warnings, Warnings::Promote);
+        assert!(defaults.optimize_refs);
+    }
+
+    #[test]
+    fn test_document_manifest() {
+        let manifest: DocumentManifest = toml::from_str(
+            r#"
+            [document]
+            name = "thesis"
+            entrypoints = ["main.typ", "appendix/main.typ"]
+
+            [config]
+            tests = "checks"
+
+            [config.default]
+            ppi = 72
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(manifest.document.name, "thesis");
+        assert_eq!(
+            manifest.document.entrypoints,
+            ["main.typ", "appendix/main.typ"]
+        );
+
+        let config = manifest.config.unwrap();
+        assert_eq!(config.unit_tests_root, "checks");
+        assert_eq!(config.defaults.ppi, 72.0);
+
+        let manifest: DocumentManifest = toml::from_str(
+            r#"
+            [document]
+            name = "report"
+            "#,
+        )
+        .unwrap();
+
+        assert!(manifest.document.entrypoints.is_empty());
+        assert!(manifest.config.is_none());
+    }
+
+    #[test]
+    fn test_serial_module() {
+        let config: ProjectConfig = toml::from_str(
+            r#"
+            serial = ["a", "a/b", "c/d"]
+
+            [default]
+            "#,
+        )
+        .unwrap();
+
+        let module = |id| {
+            config
+                .serial_module(&Id::new(id).unwrap())
+                .map(str::to_owned)
+        };
+        assert_eq!(module("a/b/c").as_deref(), Some("a"));
+        assert_eq!(module("c/d/e").as_deref(), Some("c/d"));
+        assert_eq!(module("c/e").as_deref(), None);
+        assert_eq!(module("a").as_deref(), None);
+    }
+}
@@ -0,0 +1,323 @@
+//! Line coverage of project sources.
+//!
+//! Sources are instrumented when they are loaded by a compilation with
+//! coverage enabled, see [`TestWorldAdapter::coverage`][adapter]. Each
+//! statement of a code block, each embedded expression in markup and each
+//! closure body is preceded by a call to a hidden function, which records the
+//! line it was inserted on when it is evaluated.
+//!
+//! Typst memoizes the evaluation of modules and function calls, so only
+//! whether a line was executed is recorded, not how often.
+//!
+//! [adapter]: crate::doc::compile::TestWorldAdapter::coverage
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use typst::foundations::func;
+use typst::foundations::Scope;
+use typst::syntax::ast;
+use typst::syntax::FileId;
+use typst::syntax::Source;
+use typst::syntax::Span;
+use typst::syntax::SyntaxKind;
+use typst::syntax::SyntaxNode;
+
+/// The name of the function inserted into instrumented sources.
+const PROBE: &str = "__tytanic_coverage";
+
+/// The sources instrumented in this process and the lines they executed.
+static FILES: LazyLock<Mutex<HashMap<FileId, Instrumented>>> = LazyLock::new(Mutex::default);
+
+/// An instrumented source.
+#[derive(Debug)]
+struct Instrumented {
+    /// The text of the source before instrumentation.
+    original: String,
+
+    /// The instrumented source.
+    source: Source,
+
+    /// The 1-based line of each probe.
+    lines: Vec<usize>,
+
+    /// Whether each probe was executed.
+    hits: Vec<bool>,
+}
+
+/// Returns the instrumented version of the given source, sources are
+/// instrumented once and re-instrumented if their text changes, which resets
+/// their coverage.
+pub fn instrument(source: &Source) -> Source {
+    let mut files = FILES.lock().unwrap();
+
+    if let Some(instrumented) = files.get(&source.id()) {
+        if instrumented.original == source.text() {
+            return instrumented.source.clone();
+        }
+    }
+
+    let (text, lines) = instrument_text(source);
+    let instrumented = Instrumented {
+        original: source.text().into(),
+        source: Source::new(source.id(), text),
+        hits: vec![false; lines.len()],
+        lines,
+    };
+
+    let source = instrumented.source.clone();
+    files.insert(source.id(), instrumented);
+    source
+}
+
+/// Defines the function called by instrumented sources in the given scope.
+pub fn define_probe(scope: &mut Scope) {
+    scope.define_func::<probe>();
+}
+
+/// Returns the coverage of all sources instrumented in this process.
+pub fn report() -> Report {
+    let files = FILES.lock().unwrap();
+
+    let files = files
+        .iter()
+        .map(|(id, instrumented)| {
+            let mut lines = BTreeMap::new();
+            for (&line, &hit) in instrumented.lines.iter().zip(&instrumented.hits) {
+                *lines.entry(line).or_default() |= hit;
+            }
+
+            (*id, FileReport { lines })
+        })
+        .collect();
+
+    Report { files }
+}
+
+/// The coverage of instrumented sources, see [`report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    files: BTreeMap<FileId, FileReport>,
+}
+
+impl Report {
+    /// The coverage of each instrumented file.
+    pub fn files(&self) -> &BTreeMap<FileId, FileReport> {
+        &self.files
+    }
+
+    /// Retains only the files for which the given predicate returns `true`.
+    pub fn retain<F: FnMut(FileId) -> bool>(&mut self, mut f: F) {
+        self.files.retain(|id, _| f(*id));
+    }
+
+    /// The number of executable lines in all files.
+    pub fn lines(&self) -> usize {
+        self.files.values().map(FileReport::lines).sum()
+    }
+
+    /// The number of executed lines in all files.
+    pub fn hits(&self) -> usize {
+        self.files.values().map(FileReport::hits).sum()
+    }
+
+    /// The ratio of executed lines in all files in the range `0.0..=1.0`, see
+    /// [`FileReport::ratio`].
+    pub fn ratio(&self) -> f64 {
+        match self.lines() {
+            0 => 1.0,
+            lines => self.hits() as f64 / lines as f64,
+        }
+    }
+}
+
+/// The coverage of a single file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileReport {
+    lines: BTreeMap<usize, bool>,
+}
+
+impl FileReport {
+    /// The executable 1-based lines of this file and whether they were
+    /// executed.
+    pub fn line_hits(&self) -> &BTreeMap<usize, bool> {
+        &self.lines
+    }
+
+    /// The number of executable lines.
+    pub fn lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// The number of executed lines.
+    pub fn hits(&self) -> usize {
+        self.lines.values().filter(|&&hit| hit).count()
+    }
+
+    /// The ratio of executed lines in the range `0.0..=1.0`, a file without
+    /// executable lines is fully covered.
+    pub fn ratio(&self) -> f64 {
+        match self.lines() {
+            0 => 1.0,
+            lines => self.hits() as f64 / lines as f64,
+        }
+    }
+}
+
+/// Records that the given probe was executed.
+#[func(name = "__tytanic_coverage")]
+fn probe(span: Span, probe: usize) {
+    let Some(id) = span.id() else {
+        return;
+    };
+
+    let mut files = FILES.lock().unwrap();
+    if let Some(hit) = files
+        .get_mut(&id)
+        .and_then(|instrumented| instrumented.hits.get_mut(probe))
+    {
+        *hit = true;
+    }
+}
+
+/// Inserts the probes into the text of the given source, returns the new text
+/// and the line of each probe.
+fn instrument_text(source: &Source) -> (String, Vec<usize>) {
+    let mut insertions = vec![];
+    collect(source.root(), 0, &mut insertions);
+
+    // NOTE(tinger): Closing braces must come before probes at the same offset,
+    // such that they close the preceding closure body.
+    insertions.sort_by_key(|insertion| (insertion.offset, !insertion.is_close()));
+
+    let mut text = String::with_capacity(source.text().len());
+    let mut lines = vec![];
+    let mut last = 0;
+
+    for insertion in insertions {
+        text.push_str(&source.text()[last..insertion.offset]);
+        last = insertion.offset;
+
+        let line = source.byte_to_line(insertion.offset).unwrap_or_default() + 1;
+        let probe = lines.len();
+
+        match insertion.kind {
+            InsertionKind::Markup => {
+                text.push_str(&format!("#{PROBE}({probe})"));
+                lines.push(line);
+            }
+            InsertionKind::Code => {
+                text.push_str(&format!("{PROBE}({probe}); "));
+                lines.push(line);
+            }
+            InsertionKind::Open => {
+                text.push_str(&format!("{{{PROBE}({probe}); "));
+                lines.push(line);
+            }
+            InsertionKind::Close => text.push('}'),
+        }
+    }
+
+    text.push_str(&source.text()[last..]);
+    (text, lines)
+}
+
+/// A probe or brace inserted into a source.
+#[derive(Debug, Clone, Copy)]
+struct Insertion {
+    offset: usize,
+    kind: InsertionKind,
+}
+
+impl Insertion {
+    fn is_close(&self) -> bool {
+        matches!(self.kind, InsertionKind::Close)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum InsertionKind {
+    /// A probe before an embedded expression in markup.
+    Markup,
+
+    /// A probe before a statement in a code block.
+    Code,
+
+    /// A brace and probe before a closure body which is not a code block.
+    Open,
+
+    /// The brace after a closure body which is not a code block.
+    Close,
+}
+
+/// Collects the insertions for the given node at the given offset.
+fn collect(node: &SyntaxNode, offset: usize, insertions: &mut Vec<Insertion>) {
+    let mut child_offset = offset;
+
+    let body = match node.kind() {
+        SyntaxKind::Closure => node.children().last().filter(|body| {
+            body.kind() != SyntaxKind::CodeBlock && body.cast::<ast::Expr>().is_some()
+        }),
+        _ => None,
+    };
+
+    for child in node.children() {
+        match node.kind() {
+            SyntaxKind::Markup if child.kind() == SyntaxKind::Hash => {
+                insertions.push(Insertion {
+                    offset: child_offset,
+                    kind: InsertionKind::Markup,
+                });
+            }
+            SyntaxKind::Code if child.cast::<ast::Expr>().is_some() => {
+                insertions.push(Insertion {
+                    offset: child_offset,
+                    kind: InsertionKind::Code,
+                });
+            }
+            _ => {}
+        }
+
+        if body.is_some_and(|body| std::ptr::eq(body, child)) {
+            insertions.push(Insertion {
+                offset: child_offset,
+                kind: InsertionKind::Open,
+            });
+            insertions.push(Insertion {
+                offset: child_offset + child.len(),
+                kind: InsertionKind::Close,
+            });
+        }
+
+        collect(child, child_offset, insertions);
+        child_offset += child.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::VirtualPath;
+
+    use super::*;
+
+    #[test]
+    fn test_instrument_text() {
+        let source = Source::new(
+            FileId::new(None, VirtualPath::new("instrument.typ")),
+            "a #x\n#let f(x) = x + 1\n#{\n  let y = 1\n  y\n}".into(),
+        );
+
+        let (text, lines) = instrument_text(&source);
+        assert_eq!(
+            text,
+            "a #__tytanic_coverage(0)#x\n\
+            #__tytanic_coverage(1)#let f(x) = {__tytanic_coverage(2); x + 1}\n\
+            #__tytanic_coverage(3)#{\n  \
+            __tytanic_coverage(4); let y = 1\n  \
+            __tytanic_coverage(5); y\n}"
+        );
+        assert_eq!(lines, [1, 2, 2, 3, 4, 5]);
+    }
+}
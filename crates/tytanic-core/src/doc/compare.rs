@@ -1,15 +1,28 @@
 //! Comparison of rendered pages.
 //!
-//! This currently only provides a single primitive comparison algorithm,
-//! [`Strategy::Simple`].
+//! This provides a primitive per-pixel comparison, [`Strategy::Simple`], and a
+//! structural comparison, [`Strategy::Ssim`], which tolerates sub-pixel
+//! rendering noise.
 
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
 use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
 
+use ecow::EcoString;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
 use tiny_skia::Pixmap;
+use tiny_skia::PremultipliedColorU8;
 use tytanic_utils::fmt::Term;
 
+use super::render::Alpha;
+use super::text::TextRun;
+use super::LoadError;
+
 /// A struct representing page size in pixels.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Size {
@@ -40,7 +53,73 @@ pub enum Strategy {
         /// The maximum allowed amount of pixels that can differ per page in
         /// accordance to `max_delta` before two pages are considered different.
         max_deviation: usize,
+
+        /// The maximum allowed difference in width or height between two pages
+        /// in pixels. Pages within this tolerance are compared on their common
+        /// top-left aligned region, the excess pixels are ignored.
+        max_size_delta: u32,
+
+        /// The color space in which the channels of two pixels are compared.
+        color_space: ColorSpace,
+
+        /// How transparent pixels are compared.
+        alpha: Alpha,
+
+        /// Whether deviations which look like anti-aliasing artifacts along
+        /// strong edges are ignored, see [`Deltas::ignoring_antialiasing`].
+        ignore_antialiasing: bool,
+
+        /// The maximum number of pixels compared on each page, larger pages
+        /// which are not exactly equal are compared on a deterministic sample
+        /// of this many pixels and their deviations are extrapolated. `None`
+        /// compares all pixels.
+        sample: Option<u64>,
     },
+
+    /// Compare the mean structural similarity (SSIM) of the luminance of two
+    /// pages, this tolerates small differences like those caused by font
+    /// hinting on different platforms while still detecting changes in
+    /// structure.
+    Ssim {
+        /// The minimum mean structural similarity in the range `0.0..=1.0`
+        /// before two pages are considered different, `1.0` requires
+        /// identical luminance.
+        threshold: f32,
+
+        /// The maximum allowed difference in width or height between two pages
+        /// in pixels. Pages within this tolerance are compared on their common
+        /// top-left aligned region, the excess pixels are ignored.
+        max_size_delta: u32,
+
+        /// How transparent pixels are compared.
+        alpha: Alpha,
+    },
+}
+
+impl Strategy {
+    /// The kind of this strategy.
+    pub fn kind(&self) -> StrategyKind {
+        match self {
+            Self::Simple { .. } => StrategyKind::Simple,
+            Self::Ssim { .. } => StrategyKind::Ssim,
+        }
+    }
+
+    /// The maximum allowed difference in width or height between two pages.
+    pub fn max_size_delta(&self) -> u32 {
+        match *self {
+            Self::Simple { max_size_delta, .. } | Self::Ssim { max_size_delta, .. } => {
+                max_size_delta
+            }
+        }
+    }
+
+    /// How transparent pixels are compared.
+    pub fn alpha(&self) -> Alpha {
+        match *self {
+            Self::Simple { alpha, .. } | Self::Ssim { alpha, .. } => alpha,
+        }
+    }
 }
 
 impl Default for Strategy {
@@ -48,8 +127,346 @@ impl Default for Strategy {
         Self::Simple {
             max_delta: 0,
             max_deviation: 0,
+            max_size_delta: 0,
+            color_space: ColorSpace::Srgb,
+            alpha: Alpha::Channel,
+            ignore_antialiasing: false,
+            sample: None,
+        }
+    }
+}
+
+/// The default minimum structural similarity of [`Strategy::Ssim`].
+pub const DEFAULT_SSIM_THRESHOLD: f32 = 0.99;
+
+/// The default number of pixels compared on each page of a sampled
+/// [`Strategy::Simple`] comparison.
+pub const DEFAULT_SAMPLE_SIZE: u64 = 1_000_000;
+
+/// The kind of a [`Strategy`], this is used to select a strategy in the config.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum StrategyKind {
+    /// Use [`Strategy::Simple`].
+    #[default]
+    Simple,
+
+    /// Use [`Strategy::Ssim`].
+    Ssim,
+}
+
+impl StrategyKind {
+    /// The name of this strategy as used in the config.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Simple => "simple",
+            Self::Ssim => "ssim",
+        }
+    }
+}
+
+impl Display for StrategyKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The color space in which pixels are compared by [`Strategy::Simple`].
+///
+/// Regardless of the color space, deltas are scaled to the range `0..=255`
+/// such that the same `max_delta` can be used for all of them.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorSpace {
+    /// Compare the raw sRGB channels of the pixels.
+    #[default]
+    Srgb,
+
+    /// Compare the channels in linear RGB, differences in dark colors weigh
+    /// less than in sRGB, this better reflects anti-aliased edges.
+    Linear,
+
+    /// Compare the Euclidean distance of the pixels in the perceptually
+    /// uniform Oklab color space.
+    Oklab,
+}
+
+impl ColorSpace {
+    /// The name of this color space as used in the config.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Srgb => "srgb",
+            Self::Linear => "linear",
+            Self::Oklab => "oklab",
         }
     }
+
+    /// Returns the delta between two pixels in this color space, the alpha
+    /// channel is always compared directly.
+    fn delta(self, a: PremultipliedColorU8, b: PremultipliedColorU8, linear: &[f32; 256]) -> u8 {
+        let alpha = u8::abs_diff(a.alpha(), b.alpha());
+
+        let color = match self {
+            Self::Srgb => [
+                u8::abs_diff(a.red(), b.red()),
+                u8::abs_diff(a.green(), b.green()),
+                u8::abs_diff(a.blue(), b.blue()),
+            ]
+            .into_iter()
+            .max()
+            .unwrap_or_default(),
+            Self::Linear => {
+                let [a, b] = [a, b].map(|c| to_linear(c, linear));
+                let delta = (0..3)
+                    .map(|idx| f32::abs(a[idx] - b[idx]))
+                    .fold(0.0, f32::max);
+
+                to_u8(delta)
+            }
+            Self::Oklab => {
+                let [a, b] = [a, b].map(|c| to_oklab(to_linear(c, linear)));
+                let delta = (0..3)
+                    .map(|idx| (a[idx] - b[idx]).powi(2))
+                    .sum::<f32>()
+                    .sqrt();
+
+                to_u8(delta)
+            }
+        };
+
+        u8::max(color, alpha)
+    }
+}
+
+impl Display for ColorSpace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Returns the lookup table from sRGB channel values to linear values.
+fn linear_table() -> [f32; 256] {
+    std::array::from_fn(|idx| {
+        let c = idx as f32 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    })
+}
+
+/// Converts a pixel to linear RGB, premultiplied with its alpha, such that
+/// fully transparent pixels are equal regardless of their color.
+fn to_linear(color: PremultipliedColorU8, linear: &[f32; 256]) -> [f32; 3] {
+    let alpha = color.alpha() as f32 / 255.0;
+    let color = color.demultiply();
+
+    [color.red(), color.green(), color.blue()].map(|c| linear[c as usize] * alpha)
+}
+
+/// Converts a linear RGB color to Oklab.
+fn to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = (0.41222146 * r + 0.53633255 * g + 0.051445995 * b).cbrt();
+    let m = (0.2119035 * r + 0.6806995 * g + 0.10739696 * b).cbrt();
+    let s = (0.08830246 * r + 0.28171885 * g + 0.6299787 * b).cbrt();
+
+    [
+        0.21045426 * l + 0.7936178 * m - 0.004072047 * s,
+        1.9779985 * l - 2.4285922 * m + 0.4505937 * s,
+        0.025904037 * l + 0.78277177 * m - 0.80867577 * s,
+    ]
+}
+
+/// Scales a delta in the range `0.0..=1.0` to `0..=255`.
+fn to_u8(delta: f32) -> u8 {
+    (delta * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+/// A rectangular region of a page in pixels which is excluded from comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Region {
+    /// The 0-based index of the page this region applies to, or `None` if it
+    /// applies to all pages.
+    pub page: Option<usize>,
+
+    /// The horizontal offset of the region from the left edge of the page.
+    pub x: u32,
+
+    /// The vertical offset of the region from the top edge of the page.
+    pub y: u32,
+
+    /// The width of the region.
+    pub width: u32,
+
+    /// The height of the region.
+    pub height: u32,
+}
+
+impl Region {
+    /// Whether the given pixel of the given page is inside this region.
+    fn contains(&self, page: usize, x: u32, y: u32) -> bool {
+        self.page.is_none_or(|p| p == page)
+            && (self.x..self.x.saturating_add(self.width)).contains(&x)
+            && (self.y..self.y.saturating_add(self.height)).contains(&y)
+    }
+}
+
+impl FromStr for Region {
+    type Err = ParseRegionError;
+
+    /// Parses a region of the form `x, y, width, height`, optionally prefixed
+    /// by a 1-based page number like `2: x, y, width, height`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (page, rest) = match s.split_once(':') {
+            Some((page, rest)) => {
+                let page: usize = page
+                    .trim()
+                    .parse()
+                    .map_err(|_| ParseRegionError::InvalidPage(page.trim().into()))?;
+
+                if page == 0 {
+                    return Err(ParseRegionError::InvalidPage(page.to_string()));
+                }
+
+                (Some(page - 1), rest)
+            }
+            None => (None, s),
+        };
+
+        let values = rest
+            .split(',')
+            .map(|val| {
+                val.trim()
+                    .parse()
+                    .map_err(|_| ParseRegionError::InvalidValue(val.trim().into()))
+            })
+            .collect::<Result<Vec<u32>, _>>()?;
+
+        let [x, y, width, height] = values[..] else {
+            return Err(ParseRegionError::Arity(values.len()));
+        };
+
+        Ok(Self {
+            page,
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+}
+
+/// Returned by [`Region::from_str`].
+#[derive(Debug, Error)]
+pub enum ParseRegionError {
+    /// The page number was not a positive integer.
+    #[error("invalid page number {0:?}, expected a positive integer")]
+    InvalidPage(String),
+
+    /// A coordinate or size was not a non-negative integer.
+    #[error("invalid value {0:?}, expected a non-negative integer")]
+    InvalidValue(String),
+
+    /// The region did not have exactly four values.
+    #[error("expected 4 values (x, y, width, height), found {0}")]
+    Arity(usize),
+}
+
+/// The regions of a document which are excluded from comparison, these are
+/// given as rectangular [`Region`]s or mask pages.
+///
+/// Mask pages are PNG images in which every pixel which is not fully
+/// transparent is excluded from comparison of the page with the same number.
+#[derive(Debug, Clone, Default)]
+pub struct Mask {
+    regions: Vec<Region>,
+    pages: BTreeMap<usize, Pixmap>,
+}
+
+impl Mask {
+    /// Loads the mask pages in the given directory, unlike documents these
+    /// don't need to be contiguous, an absent directory results in an empty
+    /// mask.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, LoadError> {
+        let dir = dir.as_ref();
+        if !dir.try_exists()? {
+            return Ok(Self::default());
+        }
+
+        let mut this = Self::default();
+        for (page, buffer) in super::load_pages(dir)? {
+            this.set_page(page - 1, buffer);
+        }
+
+        Ok(this)
+    }
+
+    /// Adds a region to this mask.
+    pub fn add_region(&mut self, region: Region) {
+        self.regions.push(region);
+    }
+
+    /// Sets the mask page for the page with the given 0-based index.
+    pub fn set_page(&mut self, idx: usize, page: Pixmap) {
+        self.pages.insert(idx, page);
+    }
+
+    /// Whether this mask excludes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty() && self.pages.is_empty()
+    }
+
+    /// Whether this mask may exclude pixels of the page with the given index.
+    pub fn covers(&self, idx: usize) -> bool {
+        self.regions
+            .iter()
+            .any(|region| region.page.is_none_or(|p| p == idx))
+            || self.pages.contains_key(&idx)
+    }
+
+    /// Whether the given pixel of the page with the given index is excluded.
+    pub fn is_masked(&self, idx: usize, x: u32, y: u32) -> bool {
+        self.regions.iter().any(|region| region.contains(idx, x, y))
+            || self
+                .pages
+                .get(&idx)
+                .and_then(|page| page.pixel(x, y))
+                .is_some_and(|pixel| pixel.alpha() != 0)
+    }
+
+    /// Clears all excluded pixels of the given page with the given index to
+    /// transparent.
+    pub fn apply(&self, idx: usize, page: &mut Pixmap) {
+        if !self.covers(idx) {
+            return;
+        }
+
+        let width = page.width();
+        for (pos, pixel) in page.pixels_mut().iter_mut().enumerate() {
+            let x = pos as u32 % width;
+            let y = pos as u32 / width;
+
+            if self.is_masked(idx, x, y) {
+                *pixel = PremultipliedColorU8::TRANSPARENT;
+            }
+        }
+    }
+}
+
+/// Whether two SVG pages are equal after normalization.
+///
+/// Pages are compared line by line ignoring surrounding whitespace and empty
+/// lines, such that line ending conversions or re-indentation by other tools
+/// don't cause spurious failures.
+pub fn svg(output: &str, reference: &str) -> bool {
+    fn lines(svg: &str) -> impl Iterator<Item = &str> {
+        svg.lines().map(str::trim).filter(|line| !line.is_empty())
+    }
+
+    lines(output).eq(lines(reference))
 }
 
 /// Compares two pages individually using the given strategy.
@@ -58,18 +475,178 @@ pub fn page(output: &Pixmap, reference: &Pixmap, strategy: Strategy) -> Result<(
         Strategy::Simple {
             max_delta,
             max_deviation,
-        } => page_simple(output, reference, max_delta, max_deviation),
+            max_size_delta,
+            color_space,
+            alpha,
+            ignore_antialiasing,
+            sample,
+        } => page_simple(
+            &composite(output, alpha),
+            &composite(reference, alpha),
+            max_delta,
+            max_deviation,
+            max_size_delta,
+            color_space,
+            ignore_antialiasing,
+            sample,
+        ),
+        Strategy::Ssim {
+            threshold,
+            max_size_delta,
+            alpha,
+        } => page_ssim(
+            &composite(output, alpha),
+            &composite(reference, alpha),
+            threshold,
+            max_size_delta,
+        ),
     }
 }
 
+/// Composites the given page according to `alpha` if necessary.
+fn composite(page: &Pixmap, alpha: Alpha) -> Cow<'_, Pixmap> {
+    alpha
+        .composite(page)
+        .map_or(Cow::Borrowed(page), Cow::Owned)
+}
+
 /// Compares two pages individually using [`Strategy::Simple`].
+#[allow(clippy::too_many_arguments)]
 fn page_simple(
     output: &Pixmap,
     reference: &Pixmap,
     max_delta: u8,
     max_deviation: usize,
+    max_size_delta: u32,
+    color_space: ColorSpace,
+    ignore_antialiasing: bool,
+    sample: Option<u64>,
+) -> Result<(), PageError> {
+    check_dimensions(output, reference, max_size_delta)?;
+
+    if let Some(sample) = sample {
+        let width = u32::min(output.width(), reference.width()) as u64;
+        let height = u32::min(output.height(), reference.height()) as u64;
+
+        if width * height > sample {
+            return page_sampled(
+                output,
+                reference,
+                max_delta,
+                max_deviation,
+                color_space,
+                ignore_antialiasing,
+                sample,
+            );
+        }
+    }
+
+    let deltas = if ignore_antialiasing {
+        Deltas::ignoring_antialiasing(output, reference, color_space)
+    } else {
+        Deltas::new(output, reference, color_space)
+    };
+
+    let deviations = deltas.deviations(max_delta);
+
+    if deviations > max_deviation {
+        return Err(PageError::SimpleDeviations { deviations });
+    }
+
+    Ok(())
+}
+
+/// Compares two pages which are larger than the sample size on a sample of
+/// their pixels.
+///
+/// Pages of equal size and content pass without sampling, otherwise every
+/// `n`-th pixel of their common region is compared, such that at most
+/// `sample` pixels are compared, and the deviations are extrapolated to the
+/// whole region.
+fn page_sampled(
+    output: &Pixmap,
+    reference: &Pixmap,
+    max_delta: u8,
+    max_deviation: usize,
+    color_space: ColorSpace,
+    ignore_antialiasing: bool,
+    sample: u64,
 ) -> Result<(), PageError> {
-    if output.width() != reference.width() || output.height() != reference.height() {
+    if output.width() == reference.width()
+        && output.height() == reference.height()
+        && output.data() == reference.data()
+    {
+        return Ok(());
+    }
+
+    let width = u32::min(output.width(), reference.width()) as usize;
+    let height = u32::min(output.height(), reference.height()) as usize;
+    let total = width * height;
+    let step = total.div_ceil(sample.max(1) as usize);
+
+    let linear = linear_table();
+
+    let mut samples = 0;
+    let mut deviations = 0;
+    for idx in (0..total).step_by(step) {
+        samples += 1;
+        let delta = delta_at(
+            output,
+            reference,
+            idx % width,
+            idx / width,
+            width,
+            height,
+            color_space,
+            ignore_antialiasing,
+            &linear,
+        );
+
+        if delta > max_delta {
+            deviations += 1;
+        }
+    }
+
+    let estimated = (deviations * total).div_ceil(samples);
+    if estimated > max_deviation {
+        return Err(PageError::SampledDeviations {
+            deviations,
+            samples,
+            estimated,
+        });
+    }
+
+    Ok(())
+}
+
+/// Compares two pages individually using [`Strategy::Ssim`].
+fn page_ssim(
+    output: &Pixmap,
+    reference: &Pixmap,
+    threshold: f32,
+    max_size_delta: u32,
+) -> Result<(), PageError> {
+    check_dimensions(output, reference, max_size_delta)?;
+
+    let ssim = ssim(output, reference);
+
+    if ssim < threshold {
+        return Err(PageError::Ssim { ssim, threshold });
+    }
+
+    Ok(())
+}
+
+/// Returns an error if the dimensions of the pages differ by more than
+/// `max_size_delta`.
+fn check_dimensions(
+    output: &Pixmap,
+    reference: &Pixmap,
+    max_size_delta: u32,
+) -> Result<(), PageError> {
+    if u32::abs_diff(output.width(), reference.width()) > max_size_delta
+        || u32::abs_diff(output.height(), reference.height()) > max_size_delta
+    {
         return Err(PageError::Dimensions {
             output: Size {
                 width: output.width(),
@@ -82,20 +659,650 @@ fn page_simple(
         });
     }
 
-    let deviations = Iterator::zip(output.pixels().iter(), reference.pixels().iter())
-        .filter(|(a, b)| {
-            u8::abs_diff(a.red(), b.red()) > max_delta
-                || u8::abs_diff(a.green(), b.green()) > max_delta
-                || u8::abs_diff(a.blue(), b.blue()) > max_delta
-                || u8::abs_diff(a.alpha(), b.alpha()) > max_delta
+    Ok(())
+}
+
+/// The side length of the square windows over which the structural similarity
+/// is computed.
+const SSIM_WINDOW: usize = 8;
+
+/// Computes the mean structural similarity of the luminance of two pages on
+/// their common top-left aligned region.
+///
+/// The similarity is computed on non-overlapping windows of 8x8 pixels, the
+/// windows at the right and bottom edges may be smaller. Transparent pixels are
+/// treated as white, the usual background of a page.
+pub fn ssim(output: &Pixmap, reference: &Pixmap) -> f32 {
+    const C1: f64 = (0.01 * 255.0) * (0.01 * 255.0);
+    const C2: f64 = (0.03 * 255.0) * (0.03 * 255.0);
+
+    let width = u32::min(output.width(), reference.width()) as usize;
+    let height = u32::min(output.height(), reference.height()) as usize;
+
+    if width == 0 || height == 0 {
+        return 1.0;
+    }
+
+    let mut total = 0.0;
+    let mut windows = 0;
+
+    for wy in (0..height).step_by(SSIM_WINDOW) {
+        for wx in (0..width).step_by(SSIM_WINDOW) {
+            let xs = wx..usize::min(wx + SSIM_WINDOW, width);
+            let ys = wy..usize::min(wy + SSIM_WINDOW, height);
+            let n = (xs.len() * ys.len()) as f64;
+
+            let (mut sum_a, mut sum_b) = (0.0, 0.0);
+            let (mut sum_aa, mut sum_bb, mut sum_ab) = (0.0, 0.0, 0.0);
+
+            for y in ys.clone() {
+                for x in xs.clone() {
+                    let a = luma(output, x, y);
+                    let b = luma(reference, x, y);
+
+                    sum_a += a;
+                    sum_b += b;
+                    sum_aa += a * a;
+                    sum_bb += b * b;
+                    sum_ab += a * b;
+                }
+            }
+
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let var_a = sum_aa / n - mean_a * mean_a;
+            let var_b = sum_bb / n - mean_b * mean_b;
+            let cov = sum_ab / n - mean_a * mean_b;
+
+            total += ((2.0 * mean_a * mean_b + C1) * (2.0 * cov + C2))
+                / ((mean_a * mean_a + mean_b * mean_b + C1) * (var_a + var_b + C2));
+            windows += 1;
+        }
+    }
+
+    (total / windows as f64) as f32
+}
+
+/// The luminance of the pixel at the given position as if composited over a
+/// white background.
+fn luma(pixmap: &Pixmap, x: usize, y: usize) -> f64 {
+    let pixel = pixmap.pixels()[y * pixmap.width() as usize + x];
+    let background = 255.0 - pixel.alpha() as f64;
+
+    0.299 * (pixel.red() as f64 + background)
+        + 0.587 * (pixel.green() as f64 + background)
+        + 0.114 * (pixel.blue() as f64 + background)
+}
+
+/// The neighbors of the given position within the common `width` and `height`
+/// of two pages, excluding the position itself.
+fn neighbors(
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    let xs = x.saturating_sub(1)..usize::min(x + 2, width);
+    let ys = y.saturating_sub(1)..usize::min(y + 2, height);
+
+    ys.flat_map(move |ny| xs.clone().map(move |nx| (nx, ny)))
+        .filter(move |&pos| pos != (x, y))
+}
+
+/// Whether the pixel at the given position of `pixmap` is likely an
+/// anti-aliasing artifact, `other` is the page it is compared against.
+fn is_antialiased(
+    pixmap: &Pixmap,
+    other: &Pixmap,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> bool {
+    let center = luma(pixmap, x, y);
+
+    let mut equal = 0;
+    let mut min = (0.0, None);
+    let mut max = (0.0, None);
+
+    for (nx, ny) in neighbors(x, y, width, height) {
+        let delta = luma(pixmap, nx, ny) - center;
+
+        if delta == 0.0 {
+            equal += 1;
+            // A pixel with more than two equal neighbors is part of a flat
+            // region rather than a gradient.
+            if equal > 2 {
+                return false;
+            }
+        } else if delta < min.0 {
+            min = (delta, Some((nx, ny)));
+        } else if delta > max.0 {
+            max = (delta, Some((nx, ny)));
+        }
+    }
+
+    // Without both a darker and a brighter neighbor this is not a gradient.
+    let (Some(darkest), Some(brightest)) = (min.1, max.1) else {
+        return false;
+    };
+
+    let is_flat = |pos: (usize, usize)| {
+        has_many_siblings(pixmap, pos, width, height)
+            && has_many_siblings(other, pos, width, height)
+    };
+
+    is_flat(darkest) || is_flat(brightest)
+}
+
+/// Whether the pixel at the given position has at least three neighbors of
+/// the exact same color.
+fn has_many_siblings(pixmap: &Pixmap, (x, y): (usize, usize), width: usize, height: usize) -> bool {
+    let stride = pixmap.width() as usize;
+    let pixels = pixmap.pixels();
+    let center = pixels[y * stride + x];
+
+    neighbors(x, y, width, height)
+        .filter(|&(nx, ny)| pixels[ny * stride + nx] == center)
+        .nth(2)
+        .is_some()
+}
+
+/// The distribution of per-pixel deltas between two pages, this can be used
+/// to find the thresholds of [`Strategy::Simple`] at which two pages would be
+/// considered equal.
+///
+/// The delta of a pixel is the largest difference of any of its channels in
+/// the given [`ColorSpace`], pages of different dimensions are compared on
+/// their common top-left aligned region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deltas {
+    size_delta: u32,
+    counts: [usize; 256],
+}
+
+impl Deltas {
+    /// Computes the deltas between the given pages.
+    pub fn new(output: &Pixmap, reference: &Pixmap, color_space: ColorSpace) -> Self {
+        Self::compute(output, reference, color_space, false)
+    }
+
+    /// Computes the deltas between the given pages like [`Deltas::new`], but
+    /// counts pixels which look like anti-aliasing artifacts as equal.
+    ///
+    /// A differing pixel is considered anti-aliased in either page if it lies
+    /// on a soft gradient between a darkest and brightest neighbor, at least
+    /// one of which is part of a flat region in both pages. This is the same
+    /// heuristic as that of [pixelmatch].
+    ///
+    /// [pixelmatch]: https://github.com/mapbox/pixelmatch
+    pub fn ignoring_antialiasing(
+        output: &Pixmap,
+        reference: &Pixmap,
+        color_space: ColorSpace,
+    ) -> Self {
+        Self::compute(output, reference, color_space, true)
+    }
+
+    fn compute(
+        output: &Pixmap,
+        reference: &Pixmap,
+        color_space: ColorSpace,
+        ignore_antialiasing: bool,
+    ) -> Self {
+        let mut counts = [0; 256];
+        for_each_delta(
+            output,
+            reference,
+            color_space,
+            ignore_antialiasing,
+            |_, _, delta| counts[delta as usize] += 1,
+        );
+
+        Self {
+            size_delta: u32::max(
+                u32::abs_diff(output.width(), reference.width()),
+                u32::abs_diff(output.height(), reference.height()),
+            ),
+            counts,
+        }
+    }
+
+    /// The larger difference in width or height of the pages, this is the
+    /// minimum `max_size_delta` required for the pages to be compared.
+    pub fn size_delta(&self) -> u32 {
+        self.size_delta
+    }
+
+    /// The number of pixels which were compared.
+    pub fn pixels(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// The number of deviations at the given `max_delta`.
+    pub fn deviations(&self, max_delta: u8) -> usize {
+        self.counts[max_delta as usize + 1..].iter().sum()
+    }
+
+    /// The minimum `max_delta` at which there are at most `max_deviation`
+    /// deviations.
+    pub fn min_delta(&self, max_deviation: usize) -> u8 {
+        (0..=u8::MAX)
+            .find(|&max_delta| self.deviations(max_delta) <= max_deviation)
+            .unwrap_or(u8::MAX)
+    }
+}
+
+/// Calls `f` with the position and delta of each pixel in the common top-left
+/// aligned region of the given pages, see [`Deltas`].
+fn for_each_delta<F: FnMut(usize, usize, u8)>(
+    output: &Pixmap,
+    reference: &Pixmap,
+    color_space: ColorSpace,
+    ignore_antialiasing: bool,
+    mut f: F,
+) {
+    let width = u32::min(output.width(), reference.width()) as usize;
+    let height = u32::min(output.height(), reference.height()) as usize;
+
+    let linear = linear_table();
+
+    for y in 0..height {
+        for x in 0..width {
+            let delta = delta_at(
+                output,
+                reference,
+                x,
+                y,
+                width,
+                height,
+                color_space,
+                ignore_antialiasing,
+                &linear,
+            );
+
+            f(x, y, delta);
+        }
+    }
+}
+
+/// Computes the delta of a single pixel within the common region of two pages.
+#[allow(clippy::too_many_arguments)]
+fn delta_at(
+    output: &Pixmap,
+    reference: &Pixmap,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    color_space: ColorSpace,
+    ignore_antialiasing: bool,
+    linear: &[f32; 256],
+) -> u8 {
+    let a = output.pixels()[y * output.width() as usize + x];
+    let b = reference.pixels()[y * reference.width() as usize + x];
+
+    if a == b
+        || (ignore_antialiasing
+            && (is_antialiased(output, reference, x, y, width, height)
+                || is_antialiased(reference, output, x, y, width, height)))
+    {
+        0
+    } else {
+        color_space.delta(a, b, linear)
+    }
+}
+
+/// The number of deviations within each square tile of a page, this is used
+/// to localize the differences between two pages.
+///
+/// Pixels are compared like in [`Strategy::Simple`], for [`Strategy::Ssim`]
+/// every differing pixel is counted as a deviation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    tile_size: u32,
+    columns: u32,
+    rows: u32,
+    counts: Vec<usize>,
+}
+
+impl Heatmap {
+    /// Counts the deviations between the given pages in tiles of the given
+    /// size in pixels, the tiles cover the common top-left aligned region of
+    /// both pages.
+    ///
+    /// # Panics
+    /// Panics if `tile_size` is zero.
+    pub fn new(output: &Pixmap, reference: &Pixmap, strategy: Strategy, tile_size: u32) -> Self {
+        assert!(tile_size != 0, "tile size must not be zero");
+
+        let output = composite(output, strategy.alpha());
+        let reference = composite(reference, strategy.alpha());
+
+        let (max_delta, color_space, ignore_antialiasing) = match strategy {
+            Strategy::Simple {
+                max_delta,
+                color_space,
+                ignore_antialiasing,
+                ..
+            } => (max_delta, color_space, ignore_antialiasing),
+            Strategy::Ssim { .. } => (0, ColorSpace::Srgb, false),
+        };
+
+        let width = u32::min(output.width(), reference.width());
+        let height = u32::min(output.height(), reference.height());
+        let columns = width.div_ceil(tile_size);
+        let rows = height.div_ceil(tile_size);
+
+        let mut counts = vec![0; columns as usize * rows as usize];
+        for_each_delta(
+            &output,
+            &reference,
+            color_space,
+            ignore_antialiasing,
+            |x, y, delta| {
+                if delta > max_delta {
+                    let column = x / tile_size as usize;
+                    let row = y / tile_size as usize;
+                    counts[row * columns as usize + column] += 1;
+                }
+            },
+        );
+
+        Self {
+            tile_size,
+            columns,
+            rows,
+            counts,
+        }
+    }
+
+    /// The width and height of each tile in pixels, tiles on the right and
+    /// bottom edge may be smaller.
+    pub fn tile_size(&self) -> u32 {
+        self.tile_size
+    }
+
+    /// The number of tile columns.
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// The number of tile rows.
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// The number of deviations within the tile at the given column and row.
+    pub fn get(&self, column: u32, row: u32) -> Option<usize> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+
+        self.counts
+            .get(row as usize * self.columns as usize + column as usize)
+            .copied()
+    }
+
+    /// The number of deviations in each row of tiles, from top to bottom.
+    pub fn row_counts(&self) -> impl Iterator<Item = &[usize]> {
+        self.counts.chunks(self.columns.max(1) as usize)
+    }
+
+    /// The total number of deviations on the page.
+    pub fn deviations(&self) -> usize {
+        self.counts.iter().sum()
+    }
+}
+
+/// The default number of columns of a [`DensityGrid`].
+pub const DENSITY_GRID_COLUMNS: u32 = 20;
+
+/// The default number of rows of a [`DensityGrid`].
+pub const DENSITY_GRID_ROWS: u32 = 10;
+
+/// The deviations of a page downsampled into a fixed grid of regions, this is
+/// used to tell local differences from whole page changes at a glance.
+///
+/// Unlike a [`Heatmap`], the regions are not square, but each page is split
+/// into the same number of columns and rows. Pixels are compared like in a
+/// [`Heatmap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DensityGrid {
+    columns: u32,
+    rows: u32,
+    deviations: Vec<usize>,
+    pixels: Vec<usize>,
+}
+
+impl DensityGrid {
+    /// Counts the deviations between the given pages in the given number of
+    /// columns and rows, the regions cover the common top-left aligned region
+    /// of both pages.
+    ///
+    /// Pages smaller than the grid get fewer columns or rows.
+    pub fn new(
+        output: &Pixmap,
+        reference: &Pixmap,
+        strategy: Strategy,
+        columns: u32,
+        rows: u32,
+    ) -> Self {
+        let output = composite(output, strategy.alpha());
+        let reference = composite(reference, strategy.alpha());
+
+        let (max_delta, color_space, ignore_antialiasing) = match strategy {
+            Strategy::Simple {
+                max_delta,
+                color_space,
+                ignore_antialiasing,
+                ..
+            } => (max_delta, color_space, ignore_antialiasing),
+            Strategy::Ssim { .. } => (0, ColorSpace::Srgb, false),
+        };
+
+        let width = u32::min(output.width(), reference.width());
+        let height = u32::min(output.height(), reference.height());
+        let columns = columns.min(width);
+        let rows = rows.min(height);
+
+        let len = columns as usize * rows as usize;
+        let mut deviations = vec![0; len];
+        let mut pixels = vec![0; len];
+        for_each_delta(
+            &output,
+            &reference,
+            color_space,
+            ignore_antialiasing,
+            |x, y, delta| {
+                let column = x * columns as usize / width as usize;
+                let row = y * rows as usize / height as usize;
+                let idx = row * columns as usize + column;
+
+                pixels[idx] += 1;
+                if delta > max_delta {
+                    deviations[idx] += 1;
+                }
+            },
+        );
+
+        Self {
+            columns,
+            rows,
+            deviations,
+            pixels,
+        }
+    }
+
+    /// The number of region columns.
+    pub fn columns(&self) -> u32 {
+        self.columns
+    }
+
+    /// The number of region rows.
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    /// The number of deviations within the region at the given column and
+    /// row.
+    pub fn deviations(&self, column: u32, row: u32) -> Option<usize> {
+        self.index(column, row).map(|idx| self.deviations[idx])
+    }
+
+    /// The fraction of deviating pixels within the region at the given
+    /// column and row, between 0 and 1.
+    pub fn density(&self, column: u32, row: u32) -> Option<f32> {
+        self.index(column, row)
+            .map(|idx| self.deviations[idx] as f32 / self.pixels[idx].max(1) as f32)
+    }
+
+    /// The fraction of deviating pixels in each row of regions, from top to
+    /// bottom.
+    pub fn row_densities(&self) -> impl Iterator<Item = Vec<f32>> + '_ {
+        (0..self.rows).map(|row| {
+            (0..self.columns)
+                .filter_map(|column| self.density(column, row))
+                .collect()
         })
-        .count();
+    }
 
-    if deviations > max_deviation {
-        return Err(PageError::SimpleDeviations { deviations });
+    fn index(&self, column: u32, row: u32) -> Option<usize> {
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+
+        Some(row as usize * self.columns as usize + column as usize)
     }
+}
 
-    Ok(())
+/// A rectangular region of a page in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bounds {
+    /// The horizontal offset of the region from the left edge.
+    pub x: u32,
+
+    /// The vertical offset of the region from the top edge.
+    pub y: u32,
+
+    /// The width of the region.
+    pub width: u32,
+
+    /// The height of the region.
+    pub height: u32,
+}
+
+impl Display for Bounds {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}+{}+{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+/// Detailed statistics about a failing page, these are reported in
+/// machine-readable results.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageStats {
+    /// The size of the output page.
+    pub output: Size,
+
+    /// The size of the reference page.
+    pub reference: Size,
+
+    /// The deviations of a full pixel comparison, this is `None` for pages
+    /// which failed without one, like those with different dimensions.
+    pub deviations: Option<DeviationStats>,
+}
+
+impl PageStats {
+    /// Collects the sizes of the given pages without comparing them.
+    pub fn new(output: &Pixmap, reference: &Pixmap) -> Self {
+        Self {
+            output: Size {
+                width: output.width(),
+                height: output.height(),
+            },
+            reference: Size {
+                width: reference.width(),
+                height: reference.height(),
+            },
+            deviations: None,
+        }
+    }
+
+    /// Collects the sizes of the given pages and compares all of their
+    /// pixels, see [`DeviationStats::new`].
+    pub fn with_deviations(output: &Pixmap, reference: &Pixmap, strategy: Strategy) -> Self {
+        Self {
+            deviations: Some(DeviationStats::new(output, reference, strategy)),
+            ..Self::new(output, reference)
+        }
+    }
+}
+
+/// The deviations found by a full pixel comparison of two pages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviationStats {
+    /// The amount of deviating pixels.
+    pub count: usize,
+
+    /// The maximum difference between a channel of two pixels, this includes
+    /// pixels which are within the tolerance of the strategy.
+    pub max_delta: u8,
+
+    /// The smallest region containing all deviating pixels, this is `None` if
+    /// no pixel deviated.
+    pub bounds: Option<Bounds>,
+}
+
+impl DeviationStats {
+    /// Compares all pixels of the common top-left aligned region of the given
+    /// pages, pixels are compared like in a [`Heatmap`].
+    pub fn new(output: &Pixmap, reference: &Pixmap, strategy: Strategy) -> Self {
+        let output = composite(output, strategy.alpha());
+        let reference = composite(reference, strategy.alpha());
+
+        let (max_delta, color_space, ignore_antialiasing) = match strategy {
+            Strategy::Simple {
+                max_delta,
+                color_space,
+                ignore_antialiasing,
+                ..
+            } => (max_delta, color_space, ignore_antialiasing),
+            Strategy::Ssim { .. } => (0, ColorSpace::Srgb, false),
+        };
+
+        let mut count = 0;
+        let mut max = 0;
+        let mut region: Option<[usize; 4]> = None;
+        for_each_delta(
+            &output,
+            &reference,
+            color_space,
+            ignore_antialiasing,
+            |x, y, delta| {
+                max = u8::max(max, delta);
+                if delta <= max_delta {
+                    return;
+                }
+
+                count += 1;
+                region = Some(match region {
+                    Some([x0, y0, x1, y1]) => [x0.min(x), y0.min(y), x1.max(x), y1.max(y)],
+                    None => [x, y, x, y],
+                });
+            },
+        );
+
+        Self {
+            count,
+            max_delta: max,
+            bounds: region.map(|[x0, y0, x1, y1]| Bounds {
+                x: x0 as u32,
+                y: y0 as u32,
+                width: (x1 - x0 + 1) as u32,
+                height: (y1 - y0 + 1) as u32,
+            }),
+        }
+    }
 }
 
 /// An error describing why a document comparison failed.
@@ -109,6 +1316,23 @@ pub struct Error {
 
     /// The page failures if there are any with their indices.
     pub pages: Vec<(usize, PageError)>,
+
+    /// The deviation densities of failing pages with their indices, this is
+    /// empty for pages which failed without a full pixel comparison, like
+    /// those with different dimensions.
+    pub densities: Vec<(usize, DensityGrid)>,
+
+    /// The statistics of failing pages with their indices, this is empty for
+    /// comparisons which don't compare pixels, like text comparisons.
+    pub stats: Vec<(usize, PageStats)>,
+}
+
+impl PageError {
+    /// Whether this error stems from a full pixel comparison, for which a
+    /// [`DensityGrid`] is meaningful.
+    pub fn has_density(&self) -> bool {
+        matches!(self, Self::SimpleDeviations { .. } | Self::Ssim { .. })
+    }
 }
 
 impl Display for Error {
@@ -163,6 +1387,72 @@ pub enum PageError {
         /// not match according to the visual strategy.
         deviations: usize,
     },
+
+    /// The pages differed according to a sampled [`Strategy::Simple`]
+    /// comparison.
+    #[error(
+        "content differed in {deviations} of {samples} sampled pixels, about {} {}",
+        estimated,
+        Term::simple("pixel").with(*estimated)
+    )]
+    SampledDeviations {
+        /// The amount of sampled pixels which deviated.
+        deviations: usize,
+
+        /// The amount of sampled pixels.
+        samples: usize,
+
+        /// The amount of deviations extrapolated to the whole page.
+        estimated: usize,
+    },
+
+    /// The pages differed according to [`Strategy::Ssim`].
+    #[error("structural similarity of {ssim:.4} was below {threshold}")]
+    Ssim {
+        /// The mean structural similarity of the pages.
+        ssim: f32,
+
+        /// The threshold the similarity was compared against.
+        threshold: f32,
+    },
+
+    /// The text layers of the pages differed, see [`TextLayer::compare`].
+    ///
+    /// [`TextLayer::compare`]: super::text::TextLayer::compare
+    #[error(
+        "text differed in {} {}",
+        mismatches,
+        Term::simple("run").with(*mismatches)
+    )]
+    Text {
+        /// The number of text runs which differed in content or position,
+        /// including surplus runs of either page.
+        mismatches: usize,
+
+        /// The first differing run of the output page, if any.
+        output: Option<TextRun>,
+
+        /// The first differing run of the reference page, if any.
+        reference: Option<TextRun>,
+    },
+
+    /// The fonts used on the pages differed, see [`pdf::compare`].
+    ///
+    /// [`pdf::compare`]: super::pdf::compare
+    #[error(
+        "fonts differed, {} missing and {} unexpected",
+        missing.len(),
+        unexpected.len()
+    )]
+    Fonts {
+        /// The font families used on the reference page, but not on the
+        /// output page.
+        missing: Vec<EcoString>,
+
+        /// The font families used on the output page, but not on the
+        /// reference page.
+        unexpected: Vec<EcoString>,
+    },
 }
 
 #[cfg(test)]
@@ -193,6 +1483,11 @@ mod tests {
             Strategy::Simple {
                 max_delta: 128,
                 max_deviation: 0,
+                max_size_delta: 0,
+                color_space: ColorSpace::Srgb,
+                alpha: Alpha::Channel,
+                ignore_antialiasing: false,
+                sample: None,
             },
         )
         .is_ok())
@@ -207,6 +1502,11 @@ mod tests {
             Strategy::Simple {
                 max_delta: 0,
                 max_deviation: 5,
+                max_size_delta: 0,
+                color_space: ColorSpace::Srgb,
+                alpha: Alpha::Channel,
+                ignore_antialiasing: false,
+                sample: None,
             },
         )
         .is_ok());
@@ -222,9 +1522,328 @@ mod tests {
                 Strategy::Simple {
                     max_delta: 0,
                     max_deviation: 0,
+                    max_size_delta: 0,
+                    color_space: ColorSpace::Srgb,
+                    alpha: Alpha::Channel,
+                    ignore_antialiasing: false,
+                    sample: None,
                 },
             ),
             Err(PageError::SimpleDeviations { deviations: 4 })
         ))
     }
+
+    #[test]
+    fn test_page_sampled() {
+        let a = Pixmap::new(100, 100).unwrap();
+        let mut b = a.clone();
+
+        let strategy = Strategy::Simple {
+            max_delta: 0,
+            max_deviation: 0,
+            max_size_delta: 0,
+            color_space: ColorSpace::Srgb,
+            alpha: Alpha::Channel,
+            ignore_antialiasing: false,
+            sample: Some(100),
+        };
+
+        assert!(page(&a, &b, strategy).is_ok());
+
+        let red = PremultipliedColorU8::from_rgba(128, 0, 0, 128).unwrap();
+        for pixel in &mut b.pixels_mut()[..5000] {
+            *pixel = red;
+        }
+
+        assert!(matches!(
+            page(&a, &b, strategy),
+            Err(PageError::SampledDeviations {
+                deviations: 50,
+                samples: 100,
+                estimated: 5000,
+            })
+        ));
+    }
+
+    #[test]
+    fn test_heatmap() {
+        let [output, reference] = images();
+
+        let heatmap = Heatmap::new(&output, &reference, Strategy::default(), 3);
+        assert_eq!((heatmap.columns(), heatmap.rows()), (4, 1));
+        assert_eq!(heatmap.get(0, 0), Some(3));
+        assert_eq!(heatmap.get(1, 0), Some(1));
+        assert_eq!(heatmap.get(2, 0), Some(0));
+        assert_eq!(heatmap.get(4, 0), None);
+        assert_eq!(heatmap.deviations(), 4);
+    }
+
+    #[test]
+    fn test_density_grid() {
+        let [output, reference] = images();
+
+        let grid = DensityGrid::new(&output, &reference, Strategy::default(), 5, 4);
+        assert_eq!((grid.columns(), grid.rows()), (5, 1));
+        assert_eq!(grid.deviations(0, 0), Some(2));
+        assert_eq!(grid.deviations(1, 0), Some(2));
+        assert_eq!(grid.deviations(2, 0), Some(0));
+        assert_eq!(grid.density(1, 0), Some(1.0));
+        assert_eq!(grid.density(4, 0), Some(0.0));
+        assert_eq!(grid.density(5, 0), None);
+        assert_eq!(
+            grid.row_densities().collect::<Vec<_>>(),
+            [vec![1.0, 1.0, 0.0, 0.0, 0.0]]
+        );
+    }
+
+    #[test]
+    fn test_page_stats() {
+        let [output, reference] = images();
+
+        let stats = PageStats::with_deviations(&output, &reference, Strategy::default());
+        assert_eq!(
+            stats.output,
+            Size {
+                width: 10,
+                height: 1
+            }
+        );
+        assert_eq!(stats.reference, stats.output);
+        assert_eq!(
+            stats.deviations,
+            Some(DeviationStats {
+                count: 4,
+                max_delta: 128,
+                bounds: Some(Bounds {
+                    x: 0,
+                    y: 0,
+                    width: 4,
+                    height: 1,
+                }),
+            })
+        );
+
+        let strategy = Strategy::Simple {
+            max_delta: 128,
+            max_deviation: 0,
+            max_size_delta: 0,
+            color_space: ColorSpace::Srgb,
+            alpha: Alpha::Channel,
+            ignore_antialiasing: false,
+            sample: None,
+        };
+        let deviations = DeviationStats::new(&output, &reference, strategy);
+        assert_eq!((deviations.count, deviations.max_delta), (0, 128));
+        assert_eq!(deviations.bounds, None);
+    }
+
+    #[test]
+    fn test_deltas() {
+        let [a, b] = images();
+        let deltas = Deltas::new(&a, &b, ColorSpace::Srgb);
+
+        assert_eq!(deltas.size_delta(), 0);
+        assert_eq!(deltas.pixels(), 10);
+        assert_eq!(deltas.deviations(0), 4);
+        assert_eq!(deltas.deviations(127), 4);
+        assert_eq!(deltas.deviations(128), 0);
+        assert_eq!(deltas.min_delta(0), 128);
+        assert_eq!(deltas.min_delta(4), 0);
+    }
+
+    #[test]
+    fn test_color_space_delta() {
+        let linear = linear_table();
+        let black = PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
+        let white = PremultipliedColorU8::from_rgba(255, 255, 255, 255).unwrap();
+        let dark = PremultipliedColorU8::from_rgba(20, 20, 20, 255).unwrap();
+        let light = PremultipliedColorU8::from_rgba(235, 235, 235, 255).unwrap();
+
+        for space in [ColorSpace::Srgb, ColorSpace::Linear, ColorSpace::Oklab] {
+            assert_eq!(space.delta(black, white, &linear), 255);
+            assert_eq!(space.delta(dark, dark, &linear), 0);
+        }
+
+        assert_eq!(ColorSpace::Srgb.delta(black, dark, &linear), 20);
+        assert_eq!(ColorSpace::Srgb.delta(white, light, &linear), 20);
+
+        // Linear RGB weighs differences in dark colors less than in light
+        // colors.
+        let dark_delta = ColorSpace::Linear.delta(black, dark, &linear);
+        let light_delta = ColorSpace::Linear.delta(white, light, &linear);
+        assert!(dark_delta < light_delta);
+        assert!(dark_delta < 20);
+
+        // Oklab is perceptually uniform, a small change in dark colors is
+        // more visible than in light colors.
+        assert!(ColorSpace::Oklab.delta(black, dark, &linear) > 20);
+    }
+
+    #[test]
+    fn test_page_simple_alpha() {
+        let a = Pixmap::new(10, 1).unwrap();
+        let mut b = a.clone();
+        b.fill(tiny_skia::Color::WHITE);
+
+        let strategy = |alpha| Strategy::Simple {
+            max_delta: 0,
+            max_deviation: 0,
+            max_size_delta: 0,
+            color_space: ColorSpace::Srgb,
+            alpha,
+            ignore_antialiasing: false,
+            sample: None,
+        };
+
+        assert!(page(&a, &b, strategy(Alpha::Channel)).is_err());
+        assert!(page(&a, &b, strategy(Alpha::White)).is_ok());
+        assert!(page(&a, &b, strategy(Alpha::Black)).is_err());
+    }
+
+    #[test]
+    fn test_page_simple_dimensions() {
+        let a = Pixmap::new(10, 10).unwrap();
+        let b = Pixmap::new(11, 10).unwrap();
+
+        assert!(matches!(
+            page(&a, &b, Strategy::default()),
+            Err(PageError::Dimensions { .. })
+        ));
+    }
+
+    #[test]
+    fn test_page_simple_below_max_size_delta() {
+        let [a, mut b] = images();
+        b = b
+            .clone_rect(tiny_skia::IntRect::from_xywh(0, 0, 9, 1).unwrap())
+            .unwrap();
+
+        assert!(matches!(
+            page(
+                &a,
+                &b,
+                Strategy::Simple {
+                    max_delta: 0,
+                    max_deviation: 0,
+                    max_size_delta: 1,
+                    color_space: ColorSpace::Srgb,
+                    alpha: Alpha::Channel,
+                    ignore_antialiasing: false,
+                    sample: None,
+                },
+            ),
+            Err(PageError::SimpleDeviations { deviations: 4 })
+        ));
+        assert!(page(
+            &a,
+            &b,
+            Strategy::Simple {
+                max_delta: 0,
+                max_deviation: 4,
+                max_size_delta: 1,
+                color_space: ColorSpace::Srgb,
+                alpha: Alpha::Channel,
+                ignore_antialiasing: false,
+                sample: None,
+            },
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_mask() {
+        let [mut a, mut b] = images();
+
+        let mut mask = Mask::default();
+        mask.add_region("0, 0, 2, 1".parse().unwrap());
+        mask.add_region("2: 2, 0, 2, 1".parse().unwrap());
+
+        mask.apply(0, &mut a);
+        mask.apply(0, &mut b);
+        assert!(matches!(
+            page(&a, &b, Strategy::default()),
+            Err(PageError::SimpleDeviations { deviations: 2 })
+        ));
+
+        let mut pixels = Pixmap::new(10, 1).unwrap();
+        pixels.pixels_mut()[2] = PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
+        pixels.pixels_mut()[3] = PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
+        mask.set_page(0, pixels);
+
+        mask.apply(0, &mut b);
+        assert!(page(&a, &b, Strategy::default()).is_ok());
+    }
+
+    #[test]
+    fn test_mask_sparse_pages() {
+        let mut pixels = Pixmap::new(1, 1).unwrap();
+        pixels.fill(tiny_skia::Color::BLACK);
+
+        let mut mask = Mask::default();
+        assert!(mask.is_empty());
+
+        mask.set_page(99_999, pixels);
+        assert!(!mask.is_empty());
+        assert_eq!(mask.pages.len(), 1);
+        assert!(mask.covers(99_999));
+        assert!(!mask.covers(0));
+        assert!(mask.is_masked(99_999, 0, 0));
+        assert!(!mask.is_masked(99_999, 1, 0));
+    }
+
+    #[test]
+    fn test_svg() {
+        let source = "<svg>\n  <path d=\"M 0 0\"/>\n</svg>\n";
+
+        assert!(svg(source, "<svg>\r\n<path d=\"M 0 0\"/>\r\n\r\n</svg>"));
+        assert!(!svg(source, "<svg>\n  <path d=\"M 0 1\"/>\n</svg>\n"));
+    }
+
+    #[test]
+    fn test_page_ssim() {
+        let [a, b] = images();
+        assert_eq!(ssim(&a, &a), 1.0);
+        assert!(ssim(&a, &b) < 1.0);
+
+        let strategy = |threshold| Strategy::Ssim {
+            threshold,
+            max_size_delta: 0,
+            alpha: Alpha::Channel,
+        };
+
+        assert!(page(&a, &a, strategy(1.0)).is_ok());
+        assert!(page(&a, &b, strategy(0.0)).is_ok());
+        assert!(matches!(
+            page(&a, &b, strategy(0.99)),
+            Err(PageError::Ssim { .. })
+        ));
+    }
+
+    #[test]
+    fn test_deltas_ignoring_antialiasing() {
+        let black = PremultipliedColorU8::from_rgba(0, 0, 0, 255).unwrap();
+        let gray = PremultipliedColorU8::from_rgba(128, 128, 128, 255).unwrap();
+
+        // A hard vertical edge between a black and a white half.
+        let mut reference = Pixmap::new(10, 10).unwrap();
+        reference.fill(tiny_skia::Color::WHITE);
+        for row in reference.pixels_mut().chunks_mut(10) {
+            row[..5].fill(black);
+        }
+
+        // The same edge with a column of anti-aliased pixels and a single
+        // stray pixel in the white half.
+        let mut output = reference.clone();
+        for row in output.pixels_mut().chunks_mut(10) {
+            row[5] = gray;
+        }
+        output.pixels_mut()[4 * 10 + 8] = black;
+
+        let deltas = Deltas::new(&output, &reference, ColorSpace::Srgb);
+        assert_eq!(deltas.deviations(0), 11);
+
+        let deltas = Deltas::ignoring_antialiasing(&output, &reference, ColorSpace::Srgb);
+        assert_eq!(deltas.deviations(0), 1);
+        assert_eq!(deltas.pixels(), 100);
+    }
 }
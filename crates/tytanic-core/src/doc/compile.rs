@@ -1,20 +1,32 @@
 //! Test document compilation and diagnostics handling.
 
+use std::collections::HashSet;
 use std::fmt::Debug;
+use std::panic;
 use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
 use std::sync::LazyLock;
+use std::sync::Mutex;
 use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
 
 use ecow::eco_format;
 use ecow::eco_vec;
 use ecow::EcoVec;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
+use typst::diag::FileError;
 use typst::diag::FileResult;
 use typst::diag::Severity;
 use typst::diag::SourceDiagnostic;
 use typst::diag::Warned;
 use typst::foundations::Bytes;
 use typst::foundations::Datetime;
+use typst::foundations::Dict;
 use typst::layout::PagedDocument;
 use typst::syntax::package::PackageSpec;
 use typst::syntax::FileId;
@@ -28,7 +40,10 @@ use typst::Library;
 use typst::World;
 use tytanic_utils::fmt::Term;
 
+use crate::cancel::CancellationToken;
+use crate::coverage;
 use crate::library::augmented_default_library;
+use crate::library::augmented_library;
 
 static AUGMENTED_LIBRARY: LazyLock<LazyHash<Library>> =
     LazyLock::new(|| LazyHash::new(augmented_default_library()));
@@ -42,7 +57,13 @@ pub struct TestWorldAdapter<'w> {
     source: Source,
     root_prefix: Option<PathBuf>,
     augment: bool,
+    inputs: Dict,
+    library: OnceLock<LazyHash<Library>>,
     package: Option<PackageSpec>,
+    installed: Option<(PackageSpec, PathBuf)>,
+    cancellation: Option<CancellationToken>,
+    coverage: bool,
+    accessed: Arc<Mutex<HashSet<FileId>>>,
     accessed_old: OnceLock<(PackageSpec, PackageSpec)>,
 }
 
@@ -59,6 +80,15 @@ impl TestWorldAdapter<'_> {
         self
     }
 
+    /// Set the inputs available as `sys.inputs`.
+    ///
+    /// This is used to compile instances of parametrized tests. If the inputs
+    /// are empty the base [`World`]'s standard library is used.
+    pub fn inputs(&mut self, value: Dict) -> &mut Self {
+        self.inputs = value;
+        self
+    }
+
     /// Add a root prefix to each [`FileId`].
     ///
     /// This can be used to allow template tests to access the correct files
@@ -80,10 +110,71 @@ impl TestWorldAdapter<'_> {
         self.package = value;
         self
     }
+
+    /// Set the given package spec to be used for accessing project files
+    /// outside of the given directory.
+    ///
+    /// This can be used to compile unit tests against the package as it would
+    /// be installed, files which are not part of the package bundle can't be
+    /// accessed. Files within the given directory, such as the test itself and
+    /// its assets, are still accessed in the project.
+    pub fn installed_package(&mut self, value: Option<(PackageSpec, PathBuf)>) -> &mut Self {
+        self.installed = value;
+        self
+    }
+
+    /// Set the token used to abort the compilation early.
+    ///
+    /// Once the token is cancelled, all file accesses fail, which causes the
+    /// compilation to finish early with an error. Callers should check the
+    /// token after compilation to distinguish this from regular failures.
+    pub fn cancellation(&mut self, value: Option<CancellationToken>) -> &mut Self {
+        self.cancellation = value;
+        self
+    }
+
+    /// Whether to record the line coverage of project sources.
+    ///
+    /// Typst sources of the project other than the test itself are
+    /// instrumented when they are loaded, see [`coverage`] for how to
+    /// retrieve the recorded coverage.
+    pub fn coverage(&mut self, value: bool) -> &mut Self {
+        self.coverage = value;
+        self
+    }
 }
 
 impl TestWorldAdapter<'_> {
+    fn check_cancelled(&self, id: FileId) -> FileResult<()> {
+        // NOTE(tinger): The accessed files only need to be tracked if the
+        // compilation can be cancelled at all.
+        let Some(token) = &self.cancellation else {
+            return Ok(());
+        };
+
+        // NOTE(tinger): The token is checked before locking, such that the
+        // lock is only held for as long as it takes to record the access.
+        let cancelled = token.is_cancelled();
+
+        // NOTE(tinger): Typst expects the world to be pure during a
+        // compilation, so files which were accessed before the cancellation
+        // must stay accessible, only new accesses fail.
+        let mut accessed = self.accessed.lock().unwrap();
+        if cancelled && !accessed.contains(&id) {
+            return Err(FileError::Other(Some("compilation was cancelled".into())));
+        }
+
+        accessed.insert(id);
+        Ok(())
+    }
+
     fn transform_id(&self, id: FileId) -> FileId {
+        if let Some((package, local)) = self.installed.as_ref() {
+            if id.package().is_none() && !id.vpath().as_rootless_path().starts_with(local) {
+                return FileId::new(Some(package.clone()), id.vpath().clone());
+            }
+        }
+
         let Some(this) = self.package.as_ref() else {
             return id;
         };
@@ -113,6 +204,23 @@ impl TestWorldAdapter<'_> {
 
 impl World for TestWorldAdapter<'_> {
     fn library(&self) -> &LazyHash<Library> {
+        if !self.inputs.is_empty() || self.coverage {
+            return self.library.get_or_init(|| {
+                let inputs = self.inputs.clone();
+                let mut library = if self.augment {
+                    augmented_library(|builder| builder.with_inputs(inputs))
+                } else {
+                    Library::builder().with_inputs(inputs).build()
+                };
+
+                if self.coverage {
+                    coverage::define_probe(library.global.scope_mut());
+                }
+
+                LazyHash::new(library)
+            });
+        }
+
         if self.augment {
             &AUGMENTED_LIBRARY
         } else {
@@ -140,16 +248,25 @@ impl World for TestWorldAdapter<'_> {
     }
 
     fn source(&self, id: FileId) -> FileResult<Source> {
+        self.check_cancelled(id)?;
         let id = self.transform_id(id);
 
         if id == self.source.id() {
             Ok(self.source.clone())
+        } else if self.coverage
+            && id.package().is_none()
+            && id.vpath().as_rootless_path().extension() == Some("typ".as_ref())
+        {
+            self.base
+                .source(id)
+                .map(|source| coverage::instrument(&source))
         } else {
             self.base.source(id)
         }
     }
 
     fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.check_cancelled(id)?;
         let id = self.transform_id(id);
 
         self.base.file(id)
@@ -165,7 +282,11 @@ impl World for TestWorldAdapter<'_> {
 }
 
 /// How to handle warnings during compilation.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
 pub enum Warnings {
     /// Ignore all warnings.
     Ignore,
@@ -184,6 +305,63 @@ pub enum Warnings {
 #[error("compilation failed with {} {}", .0.len(), Term::simple("error").with(.0.len()))]
 pub struct Error(pub EcoVec<SourceDiagnostic>);
 
+/// Compiles a source like [`compile`], but on a separate thread, such that
+/// the compilation can be abandoned once the given token is cancelled or its
+/// deadline passed. Returns `None` if the compilation was abandoned.
+///
+/// Typst has no way of interrupting an evaluation, the token is passed to the
+/// world, such that a cancelled compilation aborts early on its next file
+/// access, see [`TestWorldAdapter::cancellation`]. The thread of an abandoned
+/// compilation is leaked, compilations which don't access any more files, like
+/// those stuck in a non-terminating loop, keep running in the background until
+/// they finish or the process exits.
+///
+/// # Panics
+/// Panics with the payload of the compilation thread if it panicked.
+pub fn compile_detached<W, F>(
+    source: Source,
+    world: Arc<W>,
+    warnings: Warnings,
+    cancellation: CancellationToken,
+    f: F,
+) -> Option<Warned<Result<PagedDocument, Error>>>
+where
+    W: World + 'static,
+    F: for<'a, 'w> FnOnce(&'a mut TestWorldAdapter<'w>) -> &'a mut TestWorldAdapter<'w>
+        + Send
+        + 'static,
+{
+    let (tx, rx) = mpsc::channel();
+
+    let token = cancellation.clone();
+    let handle = thread::spawn(move || {
+        let res = compile(source, &*world, warnings, |w| {
+            f(w).cancellation(Some(token))
+        });
+
+        // NOTE(tinger): The receiver is gone if the compilation was
+        // abandoned.
+        _ = tx.send(res);
+    });
+
+    loop {
+        match rx.recv_timeout(CANCELLATION_POLL_INTERVAL) {
+            Ok(res) => return Some(res),
+            // NOTE(tinger): Dropping the handle detaches the thread.
+            Err(RecvTimeoutError::Timeout) if cancellation.is_cancelled() => return None,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => match handle.join() {
+                Ok(()) => unreachable!("compilation thread exits after sending its result"),
+                Err(payload) => panic::resume_unwind(payload),
+            },
+        }
+    }
+}
+
+/// How often [`compile_detached`] checks for the cancellation of a running
+/// compilation.
+const CANCELLATION_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
 /// Compiles a source with the given global world.
 ///
 /// This function compiles a test source by wrapping the provided [`World`]
@@ -204,7 +382,13 @@ where
         source,
         root_prefix: None,
         augment: false,
+        inputs: Dict::new(),
+        library: OnceLock::new(),
         package: None,
+        installed: None,
+        cancellation: None,
+        coverage: false,
+        accessed: Arc::default(),
         accessed_old: OnceLock::new(),
     };
 
@@ -266,12 +450,152 @@ where
 
 #[cfg(test)]
 mod tests {
+    use std::time::Instant;
+
+    use typst::foundations::IntoValue;
+
     use super::*;
     use crate::_dev::VirtualWorld;
 
     const TEST_PASS: &str = "Hello World";
     const TEST_WARN: &str = "#set text(font: \"foo\"); Hello World";
     const TEST_FAIL: &str = "#set text(font: \"foo\"); #panic()";
+    const TEST_LOOP: &str = "#for i in range(1000000) { for j in range(1000000) {} }";
+
+    #[test]
+    fn test_compile_detached() {
+        let world = Arc::new(VirtualWorld::default());
+
+        let Warned { output, .. } = compile_detached(
+            Source::detached(TEST_PASS),
+            Arc::clone(&world),
+            Warnings::Emit,
+            CancellationToken::new(),
+            |w| w,
+        )
+        .unwrap();
+        assert!(output.is_ok());
+
+        let token =
+            CancellationToken::new().with_deadline(Instant::now() + Duration::from_millis(100));
+        let compiled = compile_detached(
+            Source::detached(TEST_LOOP),
+            world,
+            Warnings::Emit,
+            token.clone(),
+            |w| w,
+        );
+        assert!(compiled.is_none());
+        assert!(token.is_expired());
+    }
+
+    #[test]
+    fn test_compile_detached_panic() {
+        let payload = panic::catch_unwind(|| {
+            compile_detached(
+                Source::detached(TEST_PASS),
+                Arc::new(VirtualWorld::default()),
+                Warnings::Emit,
+                CancellationToken::new(),
+                |_| panic!("configuration failed"),
+            )
+        })
+        .unwrap_err();
+
+        assert_eq!(payload.downcast_ref(), Some(&"configuration failed"));
+    }
+
+    #[test]
+    fn test_compile_cancelled_after_access() {
+        let world = VirtualWorld::default();
+        let token = CancellationToken::new();
+        let source = Source::detached(TEST_PASS);
+        let id = source.id();
+
+        let mut adapter = TestWorldAdapter {
+            base: &world,
+            source,
+            root_prefix: None,
+            augment: false,
+            inputs: Dict::new(),
+            library: OnceLock::new(),
+            package: None,
+            installed: None,
+            cancellation: Some(token.clone()),
+            coverage: false,
+            accessed: Arc::default(),
+            accessed_old: OnceLock::new(),
+        };
+        assert!(adapter.check_cancelled(id).is_ok());
+
+        token.cancel();
+        assert!(adapter.check_cancelled(id).is_ok());
+
+        let other = FileId::new_fake(VirtualPath::new("other.typ"));
+        assert!(adapter.check_cancelled(other).is_err());
+
+        adapter.cancellation = None;
+        assert!(adapter.check_cancelled(other).is_ok());
+    }
+
+    #[test]
+    fn test_installed_package_transform_id() {
+        let world = VirtualWorld::default();
+        let spec: PackageSpec = "@preview/foo:0.1.0".parse().unwrap();
+
+        let mut adapter = TestWorldAdapter {
+            base: &world,
+            source: Source::detached(TEST_PASS),
+            root_prefix: None,
+            augment: false,
+            inputs: Dict::new(),
+            library: OnceLock::new(),
+            package: None,
+            installed: None,
+            cancellation: None,
+            coverage: false,
+            accessed: Arc::default(),
+            accessed_old: OnceLock::new(),
+        };
+        adapter.installed_package(Some((spec.clone(), PathBuf::from("tests"))));
+
+        let lib = FileId::new(None, VirtualPath::new("src/lib.typ"));
+        let asset = FileId::new(None, VirtualPath::new("tests/a/image.png"));
+
+        assert_eq!(
+            adapter.transform_id(lib),
+            FileId::new(Some(spec), VirtualPath::new("src/lib.typ"))
+        );
+        assert_eq!(adapter.transform_id(asset), asset);
+    }
+
+    #[test]
+    fn test_compile_cancelled() {
+        let world = VirtualWorld::default();
+        let source = Source::detached(TEST_PASS);
+
+        let token = CancellationToken::new();
+        token.cancel();
+
+        let Warned { output, .. } = compile(source, &world, Warnings::Ignore, |w| {
+            w.cancellation(Some(token))
+        });
+
+        assert!(output.is_err());
+    }
+
+    #[test]
+    fn test_compile_inputs() {
+        let world = VirtualWorld::default();
+        let source = Source::detached("#assert.eq(sys.inputs.a, \"1\"); #catch(() => {})");
+
+        let Warned { output, .. } = compile(source, &world, Warnings::Ignore, |w| {
+            w.augment_standard_library(true)
+                .inputs(Dict::from_iter([("a".into(), "1".into_value())]))
+        });
+
+        assert!(output.is_ok());
+    }
 
     #[test]
     fn test_compile_pass_ignore_warnings() {
@@ -1,50 +1,133 @@
 //! On-disk management of reference and test documents.
 //!
-//! These documents are currently stored as individual pages in the PNG format.
+//! These documents are stored as individual pages in the PNG format or, for
+//! references, optionally in the SVG format, see [`render::Format`].
+//!
+//! Pages are always normalized into 8-bit RGBA when they are decoded, see
+//! [`decode_page`], this ensures that references written by other tools or
+//! older versions compare consistently with freshly rendered documents.
+//!
+//! Documents can additionally be stored as PDF, see [`pdf`].
+//!
+//! Documents with more than [`PAGES_PER_CHUNK`] pages are stored in numbered
+//! chunk directories, like `1/1.png` to `1/100.png` and `2/101.png`, to avoid
+//! slow enumeration of huge directories, see [`page_path`].
 
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
 use std::fs;
 use std::io;
 use std::iter;
 use std::path::Path;
+use std::path::PathBuf;
+use std::vec;
 
 use compile::TestWorldAdapter;
 use compile::Warnings;
 use ecow::EcoVec;
+use serde::Deserialize;
+use serde::Serialize;
 use thiserror::Error;
+use tiny_skia::ColorU8;
+use tiny_skia::IntSize;
 use tiny_skia::Pixmap;
 use typst::diag::Warned;
+use typst::layout::Page;
 use typst::layout::PagedDocument;
 use typst::syntax::Source;
 use typst::World;
 
+use self::compare::DensityGrid;
+use self::compare::Heatmap;
+use self::compare::Mask;
+use self::compare::PageStats;
 use self::compare::Strategy;
+use self::render::Alpha;
+use self::render::Format;
 use self::render::Origin;
+use self::text::TextLayer;
 
 pub mod compare;
 pub mod compile;
+pub mod pdf;
 pub mod render;
+pub mod text;
 
 /// The extension used in the page storage, each page is stored separately with it.
 pub const PAGE_EXTENSION: &str = "png";
 
+/// The extension used for pages stored as SVG, see [`Format::Svg`].
+pub const SVG_PAGE_EXTENSION: &str = "svg";
+
+/// The maximum number of pages stored directly in a document directory,
+/// documents with more pages are stored in chunk directories of this many
+/// pages each.
+pub const PAGES_PER_CHUNK: usize = 100;
+
+/// The maximum number of pixels of a single page which is decoded, this
+/// guards against exhausting memory on corrupt references.
+pub const MAX_PAGE_PIXELS: u64 = 1 << 28;
+
+/// The default maximum number of pages of a document which is rendered.
+pub const DEFAULT_MAX_PAGES: usize = 1000;
+
+/// The options used to optimize pages when saving them.
+#[cfg(feature = "optimize")]
+pub type OptimizeOptions = oxipng::Options;
+
+/// The options used to optimize pages when saving them.
+///
+/// Without the `optimize` feature pages can't be optimized, this type has no
+/// values such that only `None` can be passed where it is optional.
+#[cfg(not(feature = "optimize"))]
+#[derive(Debug, Clone)]
+pub enum OptimizeOptions {}
+
+/// The signature every PNG file starts with.
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// The prefix of Git LFS pointer files, these are stored in place of the
+/// actual file if it was not fetched from the LFS server.
+const LFS_POINTER_PREFIX: &[u8] = b"version https://git-lfs.github.com/spec/";
+
 /// A document that was rendered from an in-memory compilation, or loaded from disk.
 #[derive(Debug, Clone)]
 pub struct Document {
     doc: Option<Box<PagedDocument>>,
+    svgs: Option<SvgPages>,
+    text: Option<TextLayer>,
     buffers: EcoVec<Pixmap>,
 }
 
+/// The sources of a document which was loaded from SVG pages.
+#[derive(Debug, Clone)]
+struct SvgPages {
+    sources: EcoVec<String>,
+    pixel_per_pt: f32,
+}
+
 impl Document {
     /// Creates a new document from the given buffers.
     pub fn new<I: IntoIterator<Item = Pixmap>>(buffers: I) -> Self {
         Self {
             doc: None,
+            svgs: None,
+            text: None,
             buffers: buffers.into_iter().collect(),
         }
     }
 
+    /// Sets the text layer of this document, this is used for documents which
+    /// were compiled elsewhere, see [`Document::text_layer`].
+    pub fn with_text_layer(mut self, text: TextLayer) -> Self {
+        self.text = Some(text);
+        self
+    }
+
     /// Compiles and renders a new document from the given source.
     pub fn compile<'w, F>(
         source: Source,
@@ -76,6 +159,8 @@ impl Document {
 
         Self {
             doc: Some(doc),
+            svgs: None,
+            text: None,
             buffers,
         }
     }
@@ -84,51 +169,122 @@ impl Document {
     /// document will have no inner document set because it was created only
     /// from pixel buffers.
     ///
-    /// Diff images are created pair-wise in order using [`render::page_diff`].
-    pub fn render_diff(base: &Self, change: &Self, origin: Origin) -> Self {
+    /// Diff images are created pair-wise in order using [`render::page_diff`],
+    /// the pages are composited according to `alpha` first.
+    pub fn render_diff(base: &Self, change: &Self, origin: Origin, alpha: Alpha) -> Self {
         let buffers = iter::zip(&base.buffers, &change.buffers)
-            .map(|(base, change)| render::page_diff(base, change, origin))
+            .map(
+                |(base, change)| match (alpha.composite(base), alpha.composite(change)) {
+                    (Some(base), Some(change)) => render::page_diff(&base, &change, origin),
+                    _ => render::page_diff(base, change, origin),
+                },
+            )
             .collect();
 
-        Self { doc: None, buffers }
+        Self {
+            doc: None,
+            svgs: None,
+            text: None,
+            buffers,
+        }
     }
 
-    /// Collects the reference document in the given directory.
-    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref()))]
-    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, LoadError> {
-        let mut buffers = BTreeMap::new();
+    /// Counts the pages of the document in the given directory without
+    /// decoding them.
+    pub fn count_pages<P: AsRef<Path>>(dir: P) -> io::Result<usize> {
+        let dir = dir.as_ref();
 
-        for entry in fs::read_dir(dir)? {
-            let entry = entry?;
-            let path = entry.path();
+        let pages: BTreeSet<_> = page_paths(dir, PAGE_EXTENSION)?
+            .into_keys()
+            .chain(page_paths(dir, SVG_PAGE_EXTENSION)?.into_keys())
+            .collect();
 
-            if !entry.file_type()?.is_file() {
-                tracing::trace!(entry = ?path, "ignoring non-file entry in reference directory");
-                continue;
+        Ok(pages.len())
+    }
+
+    /// Returns a lazy iterator over the PNG pages of the document in the given
+    /// directory, each page is only read and decoded when it is reached.
+    ///
+    /// Unlike [`Document::load`], the pages are not checked for gaps.
+    pub fn pages<P: AsRef<Path>>(dir: P) -> io::Result<Pages> {
+        Ok(Pages {
+            paths: page_paths(dir.as_ref(), PAGE_EXTENSION)?
+                .into_iter()
+                .collect::<Vec<_>>()
+                .into_iter(),
+        })
+    }
+
+    /// Loads a single page of the document in the given directory by its
+    /// 1-based page number, SVG pages are rasterized using the given pixel
+    /// per pt ratio. Returns `None` if there is no such page.
+    pub fn load_page<P: AsRef<Path>>(
+        dir: P,
+        num: usize,
+        pixel_per_pt: f32,
+    ) -> Result<Option<Pixmap>, LoadError> {
+        let dir = dir.as_ref();
+
+        // NOTE(tinger): We don't know the page count without enumerating the
+        // directory, so we check both layouts.
+        for len in [num, PAGES_PER_CHUNK + 1] {
+            let path = page_path(dir, num, len, PAGE_EXTENSION);
+            if path.try_exists()? {
+                return load_page_file(path, |data| decode_page(&data)).map(Some);
             }
 
-            if path.extension().is_none()
-                || path.extension().is_some_and(|ext| ext != PAGE_EXTENSION)
-            {
-                tracing::trace!(entry = ?path, "ignoring non-PNG entry in reference directory");
-                continue;
+            let path = page_path(dir, num, len, SVG_PAGE_EXTENSION);
+            if path.try_exists()? {
+                return load_page_file(path, |data| {
+                    let source = String::from_utf8(data).map_err(|_| DecodeError::NotSvg)?;
+                    decode_svg_page(&source, pixel_per_pt)
+                })
+                .map(Some);
             }
+        }
+
+        Ok(None)
+    }
 
-            let Some(page) = path
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .and_then(|s| s.parse().ok())
-                .filter(|&num| num != 0)
-            else {
-                tracing::trace!(
-                    entry = ?path,
-                    "ignoring non-numeric or invalid filename in reference directory",
-                );
-                continue;
+    /// Collects the reference document in the given directory, SVG pages are
+    /// rasterized using [`render::DEFAULT_PIXEL_PER_PT`].
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, LoadError> {
+        Self::load_with(dir, render::DEFAULT_PIXEL_PER_PT)
+    }
+
+    /// Collects the reference document in the given directory, SVG pages are
+    /// rasterized using the given pixel per pt ratio.
+    ///
+    /// A document is loaded from SVG pages if the directory contains any,
+    /// otherwise it is loaded from PNG pages.
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref()))]
+    pub fn load_with<P: AsRef<Path>>(dir: P, pixel_per_pt: f32) -> Result<Self, LoadError> {
+        let dir = dir.as_ref();
+
+        let sources = load_numbered(dir, SVG_PAGE_EXTENSION, |data| {
+            String::from_utf8(data).map_err(|_| DecodeError::NotSvg)
+        })?;
+
+        let (svgs, buffers) = if sources.is_empty() {
+            (None, load_pages(dir)?)
+        } else {
+            let mut buffers = BTreeMap::new();
+            for (&page, source) in &sources {
+                let buffer =
+                    decode_svg_page(source, pixel_per_pt).map_err(|error| LoadError::Page {
+                        path: page_path(dir, page, sources.len(), SVG_PAGE_EXTENSION),
+                        error,
+                    })?;
+                buffers.insert(page, buffer);
+            }
+
+            let svgs = SvgPages {
+                sources: sources.into_values().collect(),
+                pixel_per_pt,
             };
 
-            buffers.insert(page, Pixmap::load_png(path)?);
-        }
+            (Some(svgs), buffers)
+        };
 
         // Check we got pages starting at 1.
         match buffers.first_key_value() {
@@ -156,38 +312,42 @@ impl Document {
             doc: None,
             // NOTE(tinger): the pages are ordered by key and must not have any
             // page keys missing
+            svgs,
+            text: TextLayer::load(dir).map_err(LoadError::Text)?,
             buffers: buffers.into_values().collect(),
         })
     }
 
-    /// Saves a single page within the given directory with the given 1-based page
-    /// number.
-    ///
-    /// # Panics
-    /// Panics if `num == 0`.
+    /// Saves the pages within the given directory, one page at a time, see
+    /// [`page_path`] for the layout.
     #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref()))]
     pub fn save<P: AsRef<Path>>(
         &self,
         dir: P,
-        optimize_options: Option<&oxipng::Options>,
+        optimize_options: Option<&OptimizeOptions>,
     ) -> Result<(), SaveError> {
         tracing::trace!(?optimize_options, "using optimize options");
 
+        let len = self.buffers.len();
         for (num, page) in self
             .buffers
             .iter()
             .enumerate()
             .map(|(idx, page)| (idx + 1, page))
         {
-            let path = dir
-                .as_ref()
-                .join(num.to_string())
-                .with_extension(PAGE_EXTENSION);
+            let path = page_path(dir.as_ref(), num, len, PAGE_EXTENSION);
+            create_chunk_dir(&path)?;
 
             if let Some(options) = optimize_options {
-                let buffer = page.encode_png()?;
-                let optimized = oxipng::optimize_from_memory(&buffer, options)?;
-                fs::write(path, optimized)?;
+                #[cfg(feature = "optimize")]
+                {
+                    let buffer = page.encode_png()?;
+                    let optimized = oxipng::optimize_from_memory(&buffer, options)?;
+                    fs::write(path, optimized)?;
+                }
+
+                #[cfg(not(feature = "optimize"))]
+                match *options {}
             } else {
                 page.save_png(path)?;
             }
@@ -195,6 +355,64 @@ impl Document {
 
         Ok(())
     }
+
+    /// Saves the pages within the given directory in the given format.
+    ///
+    /// Saving as SVG requires the document to be created from an in-memory
+    /// compilation or loaded from SVG pages, see [`Document::has_svgs`].
+    pub fn save_as<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        format: Format,
+        optimize_options: Option<&OptimizeOptions>,
+    ) -> Result<(), SaveError> {
+        match format {
+            Format::Png => self.save(dir, optimize_options),
+            Format::Svg => self.save_svg(dir),
+        }
+    }
+
+    /// Saves the pages within the given directory as SVG, see
+    /// [`Document::save_as`].
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref()))]
+    pub fn save_svg<P: AsRef<Path>>(&self, dir: P) -> Result<(), SaveError> {
+        if !self.has_svgs() {
+            return Err(SaveError::NoSvg);
+        }
+
+        let len = self.buffers.len();
+        for idx in 0..len {
+            let path = page_path(dir.as_ref(), idx + 1, len, SVG_PAGE_EXTENSION);
+            create_chunk_dir(&path)?;
+
+            let svg = self.svg(idx).ok_or(SaveError::NoSvg)?;
+            fs::write(path, svg.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Saves the text layer of this document within the given directory, see
+    /// [`Document::text_layer`].
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref()))]
+    pub fn save_text<P: AsRef<Path>>(&self, dir: P) -> Result<(), SaveError> {
+        self.text_layer().ok_or(SaveError::NoText)?.save(dir)?;
+
+        Ok(())
+    }
+
+    /// Exports this document to PDF within the given directory, see
+    /// [`pdf::PDF_FILE`].
+    ///
+    /// This requires the document to be created from an in-memory
+    /// compilation.
+    #[tracing::instrument(skip_all, fields(dir = ?dir.as_ref()))]
+    pub fn save_pdf<P: AsRef<Path>>(&self, dir: P) -> Result<(), SaveError> {
+        let doc = self.doc.as_deref().ok_or(SaveError::NoDocument)?;
+        fs::write(dir.as_ref().join(pdf::PDF_FILE), pdf::export(doc)?)?;
+
+        Ok(())
+    }
 }
 
 impl Document {
@@ -207,6 +425,59 @@ impl Document {
     pub fn buffers(&self) -> &[Pixmap] {
         &self.buffers
     }
+
+    /// Whether the SVG sources of the pages of this document are available,
+    /// this is the case if it was created from an in-memory compilation or
+    /// loaded from SVG pages.
+    ///
+    /// Without the `svg` feature, documents created from an in-memory
+    /// compilation have no SVG sources.
+    pub fn has_svgs(&self) -> bool {
+        (cfg!(feature = "svg") && self.doc.is_some()) || self.svgs.is_some()
+    }
+
+    /// The SVG source of the page with the given 0-based index, if available,
+    /// see [`Document::has_svgs`].
+    pub fn svg(&self, idx: usize) -> Option<Cow<'_, str>> {
+        if let Some(svgs) = &self.svgs {
+            return svgs.sources.get(idx).map(|s| Cow::Borrowed(s.as_str()));
+        }
+
+        #[cfg(feature = "svg")]
+        let svg = |page: &Page| Some(Cow::Owned(typst_svg::svg(page)));
+
+        #[cfg(not(feature = "svg"))]
+        let svg = |_: &Page| None;
+
+        self.doc
+            .as_ref()
+            .and_then(|doc| doc.pages.get(idx))
+            .and_then(svg)
+    }
+
+    /// The text layer of this document, if available, this is the case if it
+    /// was created from an in-memory compilation, loaded from a directory
+    /// containing a stored text layer or given one explicitly.
+    pub fn text_layer(&self) -> Option<Cow<'_, TextLayer>> {
+        if let Some(text) = &self.text {
+            return Some(Cow::Borrowed(text));
+        }
+
+        self.doc
+            .as_deref()
+            .map(|doc| Cow::Owned(TextLayer::extract(doc)))
+    }
+
+    /// Clears the pixels excluded by the given mask on all pages.
+    pub fn apply_mask(&mut self, mask: &Mask) {
+        if mask.is_empty() {
+            return;
+        }
+
+        for (idx, page) in self.buffers.make_mut().iter_mut().enumerate() {
+            mask.apply(idx, page);
+        }
+    }
 }
 
 impl Document {
@@ -218,29 +489,700 @@ impl Document {
         references: &Self,
         strategy: Strategy,
     ) -> Result<(), compare::Error> {
-        let output_len = outputs.buffers.len();
-        let reference_len = references.buffers.len();
+        Self::compare_masked(outputs, references, strategy, &Mask::default())
+    }
 
-        let mut page_errors = Vec::with_capacity(Ord::min(output_len, reference_len));
+    /// Compares two documents using the given strategy, masked pixels are
+    /// cleared on both pages before comparing them.
+    ///
+    /// Comparisons are created pair-wise in order using [`compare::page`].
+    pub fn compare_masked(
+        outputs: &Self,
+        references: &Self,
+        strategy: Strategy,
+        mask: &Mask,
+    ) -> Result<(), compare::Error> {
+        let reference_len = references.buffers.len();
+        let mut comparison = Comparison::new(outputs.buffers.len(), reference_len);
 
         for (idx, (a, b)) in iter::zip(&outputs.buffers, &references.buffers).enumerate() {
-            if let Err(err) = compare::page(a, b, strategy) {
-                page_errors.push((idx, err));
+            if let Some(a) = comparable_output(outputs, references.svgs.as_ref(), idx, a, mask) {
+                comparison.page(idx, a, Cow::Borrowed(b), strategy, mask);
             }
         }
 
-        if !page_errors.is_empty() || output_len != reference_len {
-            page_errors.shrink_to_fit();
+        comparison.finish()
+    }
+
+    /// Compares a document against the PNG reference pages of the given lazy
+    /// iterator, see [`Document::pages`], such that only one reference page is
+    /// decoded at a time.
+    ///
+    /// Pages are compared like in [`Document::compare_masked`]. Returns an
+    /// outer error if the reference pages have gaps or a page could not be
+    /// loaded.
+    pub fn compare_pages(
+        outputs: &Self,
+        references: Pages,
+        strategy: Strategy,
+        mask: &Mask,
+    ) -> Result<Result<(), compare::Error>, LoadError> {
+        let reference_len = references.len();
+
+        let contiguous = references
+            .paths
+            .as_slice()
+            .iter()
+            .enumerate()
+            .all(|(idx, (num, _))| *num == idx + 1);
+
+        if reference_len == 0 || !contiguous {
+            return Err(LoadError::MissingPages(
+                references.paths.map(|(num, _)| num).collect(),
+            ));
+        }
+
+        let mut comparison = Comparison::new(outputs.buffers.len(), reference_len);
+
+        for (idx, (a, b)) in iter::zip(&outputs.buffers, references).enumerate() {
+            let (_, b) = b?;
+            comparison.page(idx, Cow::Borrowed(a), Cow::Owned(b), strategy, mask);
+        }
+
+        Ok(comparison.finish())
+    }
+
+    /// Counts the deviations between each pair of pages in tiles of the given
+    /// size in pixels, see [`compare::Heatmap`].
+    ///
+    /// Pages are compared like in [`Document::compare_masked`], pages without
+    /// a counterpart are skipped.
+    pub fn heatmaps(
+        outputs: &Self,
+        references: &Self,
+        strategy: Strategy,
+        mask: &Mask,
+        tile_size: u32,
+    ) -> Vec<Heatmap> {
+        iter::zip(&outputs.buffers, &references.buffers)
+            .enumerate()
+            .map(|(idx, (a, b))| {
+                let a = comparable_output(outputs, references.svgs.as_ref(), idx, a, mask)
+                    .unwrap_or(Cow::Borrowed(b));
+
+                if mask.covers(idx) {
+                    let [mut a, mut b] = [a.into_owned(), b.clone()];
+                    mask.apply(idx, &mut a);
+                    mask.apply(idx, &mut b);
+                    Heatmap::new(&a, &b, strategy, tile_size)
+                } else {
+                    Heatmap::new(&a, b, strategy, tile_size)
+                }
+            })
+            .collect()
+    }
+}
+
+/// Collects the page errors of a comparison, see [`Document::compare_masked`].
+struct Comparison {
+    output: usize,
+    reference: usize,
+    pages: Vec<(usize, compare::PageError)>,
+    densities: Vec<(usize, DensityGrid)>,
+    stats: Vec<(usize, PageStats)>,
+}
+
+impl Comparison {
+    fn new(output: usize, reference: usize) -> Self {
+        Self {
+            output,
+            reference,
+            pages: Vec::with_capacity(Ord::min(output, reference)),
+            densities: vec![],
+            stats: vec![],
+        }
+    }
+
+    /// Compares the pages at the given index, masked pixels are cleared on
+    /// both pages first.
+    fn page(
+        &mut self,
+        idx: usize,
+        a: Cow<'_, Pixmap>,
+        b: Cow<'_, Pixmap>,
+        strategy: Strategy,
+        mask: &Mask,
+    ) {
+        let (a, b) = if mask.covers(idx) {
+            let [mut a, mut b] = [a.into_owned(), b.into_owned()];
+            mask.apply(idx, &mut a);
+            mask.apply(idx, &mut b);
+            (Cow::Owned(a), Cow::Owned(b))
+        } else {
+            (a, b)
+        };
+
+        if let Err(err) = compare::page(&a, &b, strategy) {
+            // NOTE(tinger): Sampled comparisons are used to avoid a full
+            // pass over huge pages, so we don't do one for the density.
+            if err.has_density() {
+                self.stats
+                    .push((idx, PageStats::with_deviations(&a, &b, strategy)));
+                self.densities.push((
+                    idx,
+                    DensityGrid::new(
+                        &a,
+                        &b,
+                        strategy,
+                        compare::DENSITY_GRID_COLUMNS,
+                        compare::DENSITY_GRID_ROWS,
+                    ),
+                ));
+            } else {
+                self.stats.push((idx, PageStats::new(&a, &b)));
+            }
+
+            self.pages.push((idx, err));
+        }
+    }
+
+    fn finish(mut self) -> Result<(), compare::Error> {
+        if !self.pages.is_empty() || self.output != self.reference {
+            self.pages.shrink_to_fit();
             return Err(compare::Error {
-                output: output_len,
-                reference: reference_len,
-                pages: page_errors,
+                output: self.output,
+                reference: self.reference,
+                pages: self.pages,
+                densities: self.densities,
+                stats: self.stats,
             });
         }
 
         Ok(())
     }
 }
+
+/// A lazy iterator over the pages of a document on disk with their 1-based
+/// page numbers, see [`Document::pages`].
+#[derive(Debug)]
+pub struct Pages {
+    paths: vec::IntoIter<(usize, PathBuf)>,
+}
+
+impl Iterator for Pages {
+    type Item = Result<(usize, Pixmap), LoadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (num, path) = self.paths.next()?;
+        Some(load_page_file(path, |data| decode_page(&data)).map(|page| (num, page)))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.paths.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Pages {}
+
+/// Returns the path of the page with the given 1-based number of a document
+/// with the given number of pages.
+///
+/// Pages of documents with more than [`PAGES_PER_CHUNK`] pages are placed in
+/// numbered chunk directories, the page `num` is placed in the chunk
+/// `(num - 1) / PAGES_PER_CHUNK + 1`. The page files are always named after
+/// their page number.
+///
+/// # Panics
+/// Panics if `num == 0`.
+pub fn page_path(dir: &Path, num: usize, len: usize, extension: &str) -> PathBuf {
+    assert!(num != 0, "page numbers are 1-based");
+
+    let mut path = dir.to_path_buf();
+    if len > PAGES_PER_CHUNK {
+        path.push(((num - 1) / PAGES_PER_CHUNK + 1).to_string());
+    }
+    path.push(num.to_string());
+    path.set_extension(extension);
+    path
+}
+
+/// Collects the paths of all numbered pages with the given extension in the
+/// given directory and its chunk directories by their 1-based page number,
+/// the pages are not read.
+pub fn page_paths(dir: &Path, extension: &str) -> io::Result<BTreeMap<usize, PathBuf>> {
+    let mut paths = BTreeMap::new();
+    collect_page_paths(dir, extension, true, &mut paths)?;
+    Ok(paths)
+}
+
+fn collect_page_paths(
+    dir: &Path,
+    extension: &str,
+    chunks: bool,
+    paths: &mut BTreeMap<usize, PathBuf>,
+) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if entry.file_type()?.is_dir() {
+            if chunks && parse_number(&path, false).is_some() {
+                collect_page_paths(&path, extension, false, paths)?;
+            } else {
+                tracing::trace!(entry = ?path, "ignoring non-chunk directory in page directory");
+            }
+            continue;
+        }
+
+        if path.extension().is_none_or(|ext| ext != extension) {
+            tracing::trace!(entry = ?path, extension, "ignoring entry with other extension in page directory");
+            continue;
+        }
+
+        let Some(page) = parse_number(&path, true) else {
+            tracing::trace!(
+                entry = ?path,
+                "ignoring non-numeric or invalid filename in page directory",
+            );
+            continue;
+        };
+
+        paths.insert(page, path);
+    }
+
+    Ok(())
+}
+
+/// Parses the non-zero number of a page file or chunk directory.
+fn parse_number(path: &Path, stem: bool) -> Option<usize> {
+    let name = if stem {
+        path.file_stem()
+    } else {
+        path.file_name()
+    };
+
+    name.and_then(|s| s.to_str())
+        .and_then(|s| s.parse().ok())
+        .filter(|&num| num != 0)
+}
+
+/// Creates the chunk directory of the given page path if necessary.
+fn create_chunk_dir(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(parent) => fs::create_dir_all(parent),
+        None => Ok(()),
+    }
+}
+
+/// Loads all numbered pages in the given directory by their 1-based page
+/// number, the pages are not checked for gaps.
+fn load_pages(dir: &Path) -> Result<BTreeMap<usize, Pixmap>, LoadError> {
+    Document::pages(dir)?.collect()
+}
+
+/// Returns the output page at the given index in the form in which it can be
+/// compared to the reference page, returns `None` if the pages are known to be
+/// equal.
+///
+/// SVG references are rasterized differently from rendered pages, so unless
+/// the sources are equal the output is compared after rasterizing it the same
+/// way.
+fn comparable_output<'a>(
+    outputs: &Document,
+    svgs: Option<&SvgPages>,
+    idx: usize,
+    output: &'a Pixmap,
+    mask: &Mask,
+) -> Option<Cow<'a, Pixmap>> {
+    let (Some(svgs), Some(source)) = (svgs, outputs.svg(idx)) else {
+        return Some(Cow::Borrowed(output));
+    };
+
+    if !mask.covers(idx)
+        && svgs
+            .sources
+            .get(idx)
+            .is_some_and(|reference| compare::svg(&source, reference))
+    {
+        return None;
+    }
+
+    match decode_svg_page(&source, svgs.pixel_per_pt) {
+        Ok(buffer) => Some(Cow::Owned(buffer)),
+        Err(err) => {
+            tracing::warn!(idx, %err, "couldn't rasterize output page");
+            Some(Cow::Borrowed(output))
+        }
+    }
+}
+
+/// Loads all numbered files with the given extension in the given directory
+/// by their 1-based page number using the given decoder.
+fn load_numbered<T, F>(
+    dir: &Path,
+    extension: &str,
+    mut decode: F,
+) -> Result<BTreeMap<usize, T>, LoadError>
+where
+    F: FnMut(Vec<u8>) -> Result<T, DecodeError>,
+{
+    page_paths(dir, extension)?
+        .into_iter()
+        .map(|(page, path)| Ok((page, load_page_file(path, &mut decode)?)))
+        .collect()
+}
+
+/// Reads and decodes a single page file using the given decoder.
+fn load_page_file<T, F>(path: PathBuf, decode: F) -> Result<T, LoadError>
+where
+    F: FnOnce(Vec<u8>) -> Result<T, DecodeError>,
+{
+    let data = fs::read(&path)?;
+    if is_lfs_pointer(&data) {
+        return Err(LoadError::NotMaterialized(path));
+    }
+
+    decode(data).map_err(|error| LoadError::Page { path, error })
+}
+
+/// Limits on the size of documents which are rendered, these guard against
+/// exhausting memory when rendering runaway documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Limits {
+    /// The maximum number of pages.
+    pub max_pages: usize,
+
+    /// The maximum number of pixels of a single page.
+    pub max_page_pixels: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_pages: DEFAULT_MAX_PAGES,
+            max_page_pixels: MAX_PAGE_PIXELS,
+        }
+    }
+}
+
+impl Limits {
+    /// Checks whether the given document can be rendered at the given pixel
+    /// per pt without exceeding these limits.
+    pub fn check(&self, doc: &PagedDocument, pixel_per_pt: f32) -> Result<(), LimitError> {
+        if doc.pages.len() > self.max_pages {
+            return Err(LimitError::TooManyPages {
+                pages: doc.pages.len(),
+                max: self.max_pages,
+            });
+        }
+
+        for (idx, page) in doc.pages.iter().enumerate() {
+            let size = PageSize::rendered(page, pixel_per_pt);
+            if size.pixels() > self.max_page_pixels {
+                return Err(LimitError::PageTooLarge {
+                    page: idx + 1,
+                    size,
+                    max: self.max_page_pixels,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returned by [`Limits::check`].
+#[derive(Debug, Clone, Error, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum LimitError {
+    /// The document had too many pages.
+    #[error("the document has {pages} pages, but at most {max} are allowed")]
+    TooManyPages {
+        /// The number of pages of the document.
+        pages: usize,
+
+        /// The maximum number of pages.
+        max: usize,
+    },
+
+    /// A page of the document had too many pixels.
+    #[error("page {page} would be {size} pixels, but at most {max} pixels are allowed")]
+    PageTooLarge {
+        /// The 1-based page number.
+        page: usize,
+
+        /// The size the page would be rendered at.
+        size: PageSize,
+
+        /// The maximum number of pixels per page.
+        max: u64,
+    },
+}
+
+/// Whether the given file contents are a Git LFS pointer instead of the
+/// actual file.
+pub fn is_lfs_pointer(data: &[u8]) -> bool {
+    data.starts_with(LFS_POINTER_PREFIX)
+}
+
+/// Decodes a single page from the given PNG data.
+///
+/// The page is normalized into 8-bit RGBA regardless of the stored color type
+/// and bit depth, palettes and grayscale are expanded, 16-bit channels are
+/// reduced to 8-bit and color profile, gamma and chromaticity chunks are
+/// ignored, the pixel data is always interpreted as sRGB.
+///
+/// Pages with more than [`MAX_PAGE_PIXELS`] pixels are rejected before they
+/// are decoded.
+pub fn decode_page(data: &[u8]) -> Result<Pixmap, DecodeError> {
+    if !data.starts_with(PNG_SIGNATURE) {
+        return Err(DecodeError::NotPng);
+    }
+
+    let mut decoder = png::Decoder::new(data);
+    decoder.set_transformations(
+        png::Transformations::EXPAND | png::Transformations::STRIP_16 | png::Transformations::ALPHA,
+    );
+
+    let size = decoder
+        .read_header_info()
+        .map(|info| PageSize {
+            width: info.width,
+            height: info.height,
+        })
+        .map_err(|err| DecodeError::from_png(err, None))?;
+
+    if size.pixels() > MAX_PAGE_PIXELS {
+        return Err(DecodeError::TooLarge { size });
+    }
+
+    let mut reader = decoder
+        .read_info()
+        .map_err(|err| DecodeError::from_png(err, Some(size)))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|err| DecodeError::from_png(err, Some(size)))?;
+    buffer.truncate(info.buffer_size());
+
+    if info.bit_depth != png::BitDepth::Eight {
+        return Err(DecodeError::Unsupported {
+            size,
+            reason: "unsupported bit depth",
+        });
+    }
+
+    let int_size = IntSize::from_wh(info.width, info.height).ok_or(DecodeError::Unsupported {
+        size,
+        reason: "invalid size",
+    })?;
+
+    let rgba: Vec<_> = match info.color_type {
+        png::ColorType::Rgba => buffer
+            .chunks_exact(4)
+            .map(|p| ColorU8::from_rgba(p[0], p[1], p[2], p[3]))
+            .collect(),
+        png::ColorType::Rgb => buffer
+            .chunks_exact(3)
+            .map(|p| ColorU8::from_rgba(p[0], p[1], p[2], u8::MAX))
+            .collect(),
+        png::ColorType::GrayscaleAlpha => buffer
+            .chunks_exact(2)
+            .map(|p| ColorU8::from_rgba(p[0], p[0], p[0], p[1]))
+            .collect(),
+        png::ColorType::Grayscale => buffer
+            .iter()
+            .map(|&g| ColorU8::from_rgba(g, g, g, u8::MAX))
+            .collect(),
+        png::ColorType::Indexed => {
+            return Err(DecodeError::Unsupported {
+                size,
+                reason: "unexpanded palette",
+            })
+        }
+    };
+
+    let data = rgba
+        .into_iter()
+        .flat_map(|color| {
+            let color = color.premultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+
+    Pixmap::from_vec(data, int_size).ok_or(DecodeError::Unsupported {
+        size,
+        reason: "invalid size",
+    })
+}
+
+/// Rasterizes an SVG page using the given pixel per pt ratio.
+///
+/// The page is rasterized using `resvg`, which differs slightly from the
+/// rendering of `typst-render` in how edges are anti-aliased.
+///
+/// Without the `svg` feature this always returns [`DecodeError::SvgDisabled`].
+#[cfg(not(feature = "svg"))]
+pub fn decode_svg_page(_source: &str, _pixel_per_pt: f32) -> Result<Pixmap, DecodeError> {
+    Err(DecodeError::SvgDisabled)
+}
+
+/// Rasterizes an SVG page using the given pixel per pt ratio.
+///
+/// The page is rasterized using `resvg`, which differs slightly from the
+/// rendering of `typst-render` in how edges are anti-aliased.
+#[cfg(feature = "svg")]
+pub fn decode_svg_page(source: &str, pixel_per_pt: f32) -> Result<Pixmap, DecodeError> {
+    use resvg::usvg;
+
+    // NOTE(tinger): `typst-svg` specifies the page size in pt, which `usvg`
+    // converts to CSS pixels at 96 per inch.
+    const PT_PER_PX: f32 = 0.75;
+
+    let tree = usvg::Tree::from_str(source, &usvg::Options::default())?;
+
+    let scale = pixel_per_pt * PT_PER_PX;
+    let size = PageSize {
+        width: (tree.size().width() * scale).round().max(1.0) as u32,
+        height: (tree.size().height() * scale).round().max(1.0) as u32,
+    };
+
+    if size.pixels() > MAX_PAGE_PIXELS {
+        return Err(DecodeError::TooLarge { size });
+    }
+
+    let mut pixmap = Pixmap::new(size.width, size.height).ok_or(DecodeError::Unsupported {
+        size,
+        reason: "invalid size",
+    })?;
+
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+
+    Ok(pixmap)
+}
+
+/// The size of a page in pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PageSize {
+    /// The width of the page.
+    pub width: u32,
+
+    /// The height of the page.
+    pub height: u32,
+}
+
+impl PageSize {
+    /// The size the given page is rendered at with the given pixel per pt.
+    pub fn rendered(page: &Page, pixel_per_pt: f32) -> Self {
+        // NOTE(tinger): This must match the rounding of `typst_render::render`.
+        let size = page.frame.size();
+        Self {
+            width: (pixel_per_pt * size.x.to_pt() as f32).round().max(1.0) as u32,
+            height: (pixel_per_pt * size.y.to_pt() as f32).round().max(1.0) as u32,
+        }
+    }
+
+    /// The total number of pixels of the page.
+    pub fn pixels(self) -> u64 {
+        u64::from(self.width) * u64::from(self.height)
+    }
+}
+
+impl Display for PageSize {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Returned by [`decode_page`].
+#[derive(Debug, Error)]
+pub enum DecodeError {
+    /// The data was not a PNG image.
+    #[error("the file is not a PNG image")]
+    NotPng,
+
+    /// The data was not a UTF-8 encoded SVG image.
+    #[error("the image is not an SVG")]
+    NotSvg,
+
+    /// The SVG image could not be parsed.
+    #[cfg(feature = "svg")]
+    #[error("the image is not a valid SVG")]
+    Svg(#[from] resvg::usvg::Error),
+
+    /// The image is an SVG, but SVG support was disabled.
+    #[cfg(not(feature = "svg"))]
+    #[error("SVG images are not supported without the `svg` feature")]
+    SvgDisabled,
+
+    /// The data ended before the image was complete.
+    #[error("the image is truncated")]
+    Truncated {
+        /// The size of the page, if the header could be read.
+        size: Option<PageSize>,
+    },
+
+    /// The image exceeded [`MAX_PAGE_PIXELS`].
+    #[error("the image is too large ({size})")]
+    TooLarge {
+        /// The size of the page.
+        size: PageSize,
+    },
+
+    /// The image was valid, but has a format we don't support.
+    #[error("the image has an unsupported format ({reason})")]
+    Unsupported {
+        /// The size of the page.
+        size: PageSize,
+
+        /// Why the image is not supported.
+        reason: &'static str,
+    },
+
+    /// The image was otherwise corrupt.
+    #[error("the image is corrupt")]
+    Invalid {
+        /// The size of the page, if the header could be read.
+        size: Option<PageSize>,
+
+        /// The underlying decoding error.
+        #[source]
+        error: png::DecodingError,
+    },
+}
+
+impl DecodeError {
+    fn from_png(error: png::DecodingError, size: Option<PageSize>) -> Self {
+        match error {
+            png::DecodingError::IoError(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                Self::Truncated { size }
+            }
+            png::DecodingError::LimitsExceeded => match size {
+                Some(size) => Self::TooLarge { size },
+                None => Self::Invalid { size, error },
+            },
+            error => Self::Invalid { size, error },
+        }
+    }
+
+    /// The size of the page, if it could be determined.
+    pub fn size(&self) -> Option<PageSize> {
+        match self {
+            Self::NotPng | Self::NotSvg => None,
+            #[cfg(feature = "svg")]
+            Self::Svg(_) => None,
+            #[cfg(not(feature = "svg"))]
+            Self::SvgDisabled => None,
+            Self::Truncated { size } | Self::Invalid { size, .. } => *size,
+            Self::TooLarge { size } | Self::Unsupported { size, .. } => Some(*size),
+        }
+    }
+}
+
 /// Returned by [`Document::load`].
 #[derive(Debug, Error)]
 pub enum LoadError {
@@ -249,9 +1191,25 @@ pub enum LoadError {
     #[error("one or more pages were missing, found: {0:?}")]
     MissingPages(BTreeSet<usize>),
 
+    /// A page was a Git LFS pointer, i.e. it was not fetched from the LFS
+    /// server.
+    #[error("page {0:?} is a Git LFS pointer")]
+    NotMaterialized(PathBuf),
+
     /// A page could not be decoded.
-    #[error("a page could not be decoded")]
-    Page(#[from] png::DecodingError),
+    #[error("page {path:?} could not be decoded")]
+    Page {
+        /// The path of the page.
+        path: PathBuf,
+
+        /// The reason the page could not be decoded.
+        #[source]
+        error: DecodeError,
+    },
+
+    /// The stored text layer could not be loaded.
+    #[error("the text layer could not be loaded")]
+    Text(#[source] text::Error),
 
     /// An io error occurred.
     #[error("an io error occurred")]
@@ -262,6 +1220,7 @@ pub enum LoadError {
 #[derive(Debug, Error)]
 pub enum SaveError {
     /// A page could not be optimized.
+    #[cfg(feature = "optimize")]
     #[error("a page could not be optimized")]
     Optimize(#[from] oxipng::PngError),
 
@@ -269,6 +1228,28 @@ pub enum SaveError {
     #[error("a page could not be encoded")]
     Page(#[from] png::EncodingError),
 
+    /// The document was saved as SVG, but the sources of its pages were not
+    /// available.
+    #[error("the document has no SVG pages")]
+    NoSvg,
+
+    /// The text layer was saved, but it was not available.
+    #[error("the document has no text layer")]
+    NoText,
+
+    /// The text layer could not be saved.
+    #[error("the text layer could not be saved")]
+    Text(#[from] text::Error),
+
+    /// The document was exported to PDF, but it was not created from an
+    /// in-memory compilation.
+    #[error("the document was not compiled in memory")]
+    NoDocument,
+
+    /// The document could not be exported to PDF.
+    #[error("the document could not be exported to PDF")]
+    Pdf(#[from] pdf::Error),
+
     /// An IO error occurred.
     #[error("an io error occurred")]
     Io(#[from] io::Error),
@@ -280,11 +1261,49 @@ mod tests {
     use tytanic_utils::fs::TempTestEnv;
 
     use super::*;
+    use crate::_dev::VirtualWorld;
+
+    #[test]
+    fn test_limits_check() {
+        let world = VirtualWorld::default();
+        let source = Source::detached("#set page(width: 100pt, height: 100pt)\nA #pagebreak() B");
+        let doc = compile::compile(source, &world, Warnings::Ignore, |w| w)
+            .output
+            .unwrap();
+
+        let limits = Limits {
+            max_pages: 2,
+            max_page_pixels: 200 * 200,
+        };
+
+        assert!(limits.check(&doc, 2.0).is_ok());
+        assert!(matches!(
+            Limits {
+                max_pages: 1,
+                ..limits
+            }
+            .check(&doc, 2.0),
+            Err(LimitError::TooManyPages { pages: 2, max: 1 })
+        ));
+        assert!(matches!(
+            limits.check(&doc, 3.0),
+            Err(LimitError::PageTooLarge {
+                page: 1,
+                size: PageSize {
+                    width: 300,
+                    height: 300
+                },
+                ..
+            })
+        ));
+    }
 
     #[test]
     fn test_document_save() {
         let doc = Document {
             doc: None,
+            svgs: None,
+            text: None,
             buffers: eco_vec![Pixmap::new(10, 10).unwrap(); 3],
         };
 
@@ -320,4 +1339,202 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_document_save_load_chunked() {
+        let len = PAGES_PER_CHUNK + 2;
+        let doc = Document::new((1..=len as u32).map(|width| Pixmap::new(width, 1).unwrap()));
+
+        TempTestEnv::run_no_check(
+            |root| root,
+            |root| {
+                doc.save(root, None).unwrap();
+                assert!(root.join("1/1.png").exists());
+                assert!(root.join("1/100.png").exists());
+                assert!(root.join("2/101.png").exists());
+                assert!(!root.join("1.png").exists());
+
+                assert_eq!(Document::count_pages(root).unwrap(), len);
+
+                let loaded = Document::load(root).unwrap();
+                assert_eq!(loaded.buffers(), doc.buffers());
+
+                let mut pages = Document::pages(root).unwrap();
+                assert_eq!(pages.len(), len);
+                let (num, page) = pages.nth(100).unwrap().unwrap();
+                assert_eq!((num, page.width()), (101, 101));
+
+                let page = Document::load_page(root, 102, 1.0).unwrap().unwrap();
+                assert_eq!(page.width(), 102);
+                assert!(Document::load_page(root, 103, 1.0).unwrap().is_none());
+            },
+        );
+    }
+
+    #[test]
+    fn test_document_compare_pages() {
+        let mut white = Pixmap::new(10, 10).unwrap();
+        white.fill(tiny_skia::Color::WHITE);
+        let black = Pixmap::new(10, 10).unwrap();
+
+        let doc = Document::new([white.clone(), black.clone()]);
+        let strategy = Strategy::default();
+        let mask = Mask::default();
+
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("ref/1.png", white.encode_png().unwrap())
+                    .setup_file("ref/2.png", black.encode_png().unwrap())
+                    .setup_file("gap/1.png", white.encode_png().unwrap())
+                    .setup_file("gap/3.png", black.encode_png().unwrap())
+            },
+            |root| {
+                let pages = Document::pages(root.join("ref")).unwrap();
+                assert!(Document::compare_pages(&doc, pages, strategy, &mask)
+                    .unwrap()
+                    .is_ok());
+
+                let other = Document::new([white.clone(), white.clone(), black.clone()]);
+                let pages = Document::pages(root.join("ref")).unwrap();
+                let error = Document::compare_pages(&other, pages, strategy, &mask)
+                    .unwrap()
+                    .unwrap_err();
+                assert_eq!((error.output, error.reference), (3, 2));
+                assert_eq!(error.pages.len(), 1);
+                assert_eq!(error.pages[0].0, 1);
+
+                let pages = Document::pages(root.join("gap")).unwrap();
+                assert!(matches!(
+                    Document::compare_pages(&doc, pages, strategy, &mask),
+                    Err(LoadError::MissingPages(pages)) if pages == BTreeSet::from([1, 3])
+                ));
+            },
+        );
+    }
+
+    #[test]
+    fn test_page_path() {
+        let dir = Path::new("ref");
+        assert_eq!(page_path(dir, 3, 3, "png"), Path::new("ref/3.png"));
+        assert_eq!(page_path(dir, 100, 250, "png"), Path::new("ref/1/100.png"));
+        assert_eq!(page_path(dir, 101, 250, "svg"), Path::new("ref/2/101.svg"));
+    }
+
+    #[test]
+    #[cfg(feature = "svg")]
+    fn test_document_svg() {
+        let world = VirtualWorld::default();
+        let compile = |text| {
+            let source = Source::detached(format!("#set page(width: 50pt, height: 30pt)\n{text}"));
+            let doc = compile::compile(source, &world, Warnings::Ignore, |w| w)
+                .output
+                .unwrap();
+            Document::render(doc, 2.0)
+        };
+
+        let output = compile("A #pagebreak() B");
+        let changed = compile("A #pagebreak() C");
+        let strategy = Strategy::default();
+
+        TempTestEnv::run_no_check(
+            |root| root,
+            |root| {
+                output.save_as(root, Format::Svg, None).unwrap();
+                assert!(root.join("2.svg").exists());
+                assert!(!root.join("1.png").exists());
+
+                let reference = Document::load_with(root, 2.0).unwrap();
+                assert_eq!(reference.buffers().len(), 2);
+                assert_eq!(reference.buffers()[0].width(), 100);
+                assert!(Document::compare(&output, &reference, strategy).is_ok());
+
+                let err = Document::compare(&changed, &reference, strategy).unwrap_err();
+                assert_eq!(err.pages.len(), 1);
+                assert_eq!(err.pages[0].0, 1);
+
+                assert!(matches!(
+                    Document::new([]).save_svg(root),
+                    Err(SaveError::NoSvg)
+                ));
+            },
+        );
+    }
+
+    #[test]
+    fn test_document_load_lfs_pointer() {
+        let buffer = Pixmap::new(10, 10).unwrap();
+        let pointer = "\
+version https://git-lfs.github.com/spec/v1
+oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393
+size 12345
+";
+
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("1.png", buffer.encode_png().unwrap())
+                    .setup_file("2.png", pointer)
+            },
+            |root| {
+                let err = Document::load(root).unwrap_err();
+                assert!(matches!(err, LoadError::NotMaterialized(path) if path.ends_with("2.png")));
+            },
+        );
+    }
+
+    #[test]
+    fn test_decode_page_normalizes_grayscale_16() {
+        let mut data = vec![];
+        {
+            let mut encoder = png::Encoder::new(&mut data, 2, 1);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Sixteen);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&[0xff, 0xff, 0x00, 0x00]).unwrap();
+        }
+
+        let page = decode_page(&data).unwrap();
+
+        assert_eq!(page.width(), 2);
+        assert_eq!(page.height(), 1);
+        assert_eq!(page.data(), &[255, 255, 255, 255, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn test_decode_page_errors() {
+        let data = Pixmap::new(4, 4).unwrap().encode_png().unwrap();
+
+        assert!(matches!(decode_page(b"GIF89a"), Err(DecodeError::NotPng)));
+        assert!(matches!(
+            decode_page(&data[..data.len() / 2]),
+            Err(DecodeError::Truncated {
+                size: Some(PageSize {
+                    width: 4,
+                    height: 4
+                })
+            })
+        ));
+
+        // Only the header is written, the size must be rejected before the
+        // missing image data is noticed.
+        let mut data = vec![];
+        let writer = png::Encoder::new(&mut data, 20_000, 20_000)
+            .write_header()
+            .unwrap();
+        drop(writer);
+
+        assert!(matches!(
+            decode_page(&data),
+            Err(DecodeError::TooLarge { .. })
+        ));
+    }
+
+    #[test]
+    fn test_decode_page_matches_encoded() {
+        let mut pixmap = Pixmap::new(4, 4).unwrap();
+        pixmap.fill(tiny_skia::Color::from_rgba8(12, 34, 56, 128));
+
+        let page = decode_page(&pixmap.encode_png().unwrap()).unwrap();
+
+        assert_eq!(page, pixmap);
+    }
 }
@@ -0,0 +1,153 @@
+//! Export of documents to PDF and their structural comparison.
+//!
+//! Exported PDF files are not compared byte by byte, their layout changes with
+//! every version of `typst-pdf`. Instead, documents are compared by the
+//! structure which ends up in the PDF: their page count, the fonts embedded
+//! for each page and their text layer, see [`compare()`].
+
+use std::iter;
+
+use ecow::EcoVec;
+use thiserror::Error;
+use typst::diag::SourceDiagnostic;
+use typst::layout::PagedDocument;
+
+use super::compare;
+use super::compare::PageError;
+use super::text::TextLayer;
+
+/// The name of the file within a page directory in which the document is
+/// stored as PDF.
+pub const PDF_FILE: &str = "document.pdf";
+
+/// Exports the given document to PDF.
+#[cfg(feature = "pdf")]
+pub fn export(doc: &PagedDocument) -> Result<Vec<u8>, Error> {
+    typst_pdf::pdf(doc, &typst_pdf::PdfOptions::default()).map_err(Error::Export)
+}
+
+/// Exports the given document to PDF.
+///
+/// Without the `pdf` feature documents can't be exported, this always
+/// returns [`Error::PdfDisabled`].
+#[cfg(not(feature = "pdf"))]
+pub fn export(_doc: &PagedDocument) -> Result<Vec<u8>, Error> {
+    Err(Error::PdfDisabled)
+}
+
+/// Compares the structure of two documents by their text layers, pages must
+/// use the same fonts and their text layers must match within the given
+/// tolerance in pt, see [`TextLayer::compare`].
+pub fn compare(
+    output: &TextLayer,
+    reference: &TextLayer,
+    tolerance: f64,
+) -> Result<(), compare::Error> {
+    let mut error = match TextLayer::compare(output, reference, tolerance) {
+        Ok(()) => compare::Error {
+            output: output.pages().len(),
+            reference: reference.pages().len(),
+            pages: vec![],
+            densities: vec![],
+            stats: vec![],
+        },
+        Err(error) => error,
+    };
+
+    for (idx, (a, b)) in iter::zip(output.fonts(), reference.fonts()).enumerate() {
+        let missing: Vec<_> = b.difference(a).cloned().collect();
+        let unexpected: Vec<_> = a.difference(b).cloned().collect();
+
+        if missing.is_empty() && unexpected.is_empty() {
+            continue;
+        }
+
+        error.pages.push((
+            idx,
+            PageError::Fonts {
+                missing,
+                unexpected,
+            },
+        ));
+    }
+
+    // NOTE(tinger): Text differences are more useful than font differences,
+    // so they come first for each page.
+    error.pages.sort_by_key(|(idx, _)| *idx);
+
+    if !error.pages.is_empty() || error.output != error.reference {
+        return Err(error);
+    }
+
+    Ok(())
+}
+
+/// Returned by [`export`].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The export emitted errors.
+    #[error("the document could not be exported to PDF: {}", messages(.0))]
+    Export(EcoVec<SourceDiagnostic>),
+
+    /// The document can't be exported without the `pdf` feature.
+    #[cfg(not(feature = "pdf"))]
+    #[error("the document could not be exported to PDF, the pdf feature is disabled")]
+    PdfDisabled,
+}
+
+/// Joins the messages of the given diagnostics.
+fn messages(errors: &[SourceDiagnostic]) -> String {
+    errors
+        .iter()
+        .map(|error| error.message.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::Source;
+
+    use super::*;
+    use crate::_dev::VirtualWorld;
+    use crate::doc::compile;
+    use crate::doc::compile::Warnings;
+
+    fn document(source: &str) -> PagedDocument {
+        let world = VirtualWorld::default();
+        compile::compile(Source::detached(source), &world, Warnings::Ignore, |w| w)
+            .output
+            .unwrap()
+    }
+
+    #[test]
+    #[cfg(feature = "pdf")]
+    fn test_export() {
+        let pdf = export(&document("Hello #pagebreak() World")).unwrap();
+
+        assert!(pdf.starts_with(b"%PDF-"));
+    }
+
+    #[test]
+    fn test_compare() {
+        let base = TextLayer::extract(&document("Hello"));
+        let font = TextLayer::extract(&document("#set text(font: \"DejaVu Sans Mono\")\nHello"));
+        let text = TextLayer::extract(&document("Hallo"));
+        let pages = TextLayer::extract(&document("Hello #pagebreak() World"));
+
+        assert!(compare(&base, &base, 0.0).is_ok());
+
+        let error = compare(&font, &base, 100.0).unwrap_err();
+        assert!(matches!(
+            &error.pages[..],
+            [(0, PageError::Fonts { missing, unexpected })]
+                if missing == &["Libertinus Serif"] && unexpected == &["DejaVu Sans Mono"]
+        ));
+
+        let error = compare(&text, &base, 0.0).unwrap_err();
+        assert!(matches!(&error.pages[..], [(0, PageError::Text { .. })]));
+
+        let error = compare(&pages, &base, 0.0).unwrap_err();
+        assert_eq!((error.output, error.reference), (2, 1));
+    }
+}
@@ -2,6 +2,8 @@
 
 use std::cmp::Ordering;
 
+use serde::Deserialize;
+use serde::Serialize;
 use tiny_skia::BlendMode;
 use tiny_skia::FilterQuality;
 use tiny_skia::Pixmap;
@@ -52,6 +54,97 @@ impl Origin {
     }
 }
 
+/// How transparent pixels are handled when comparing pages and rendering
+/// diffs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Alpha {
+    /// Compare the alpha channel like the color channels, pages are left as
+    /// is.
+    #[default]
+    Channel,
+
+    /// Composite pages over a white background.
+    White,
+
+    /// Composite pages over a black background.
+    Black,
+
+    /// Composite pages over a checkerboard of light and dark gray squares.
+    Checker,
+}
+
+/// The format in which persistent reference pages are stored.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    /// Rendered PNG pages, this is the default.
+    #[default]
+    Png,
+
+    /// SVG pages exported using `typst-svg`, these are rasterized for
+    /// comparisons and diffs.
+    Svg,
+}
+
+impl Format {
+    /// The file extension of pages in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Svg => "svg",
+        }
+    }
+}
+
+/// The size of the squares of [`Alpha::Checker`] in pixels.
+const CHECKER_SIZE: u32 = 8;
+
+impl Alpha {
+    /// Composites the given page over the background of this mode, returns
+    /// `None` for [`Alpha::Channel`].
+    pub fn composite(self, page: &Pixmap) -> Option<Pixmap> {
+        let mut composite = Pixmap::new(page.width(), page.height())?;
+
+        match self {
+            Self::Channel => return None,
+            Self::White => composite.fill(tiny_skia::Color::WHITE),
+            Self::Black => composite.fill(tiny_skia::Color::BLACK),
+            Self::Checker => {
+                let light = tiny_skia::ColorU8::from_rgba(204, 204, 204, 255).premultiply();
+                let dark = tiny_skia::ColorU8::from_rgba(153, 153, 153, 255).premultiply();
+
+                let width = composite.width();
+                for (idx, px) in composite.pixels_mut().iter_mut().enumerate() {
+                    let (x, y) = (idx as u32 % width, idx as u32 / width);
+                    *px = if (x / CHECKER_SIZE + y / CHECKER_SIZE) % 2 == 0 {
+                        light
+                    } else {
+                        dark
+                    };
+                }
+            }
+        }
+
+        composite.draw_pixmap(
+            0,
+            0,
+            page.as_ref(),
+            &PixmapPaint {
+                opacity: 1.0,
+                blend_mode: BlendMode::SourceOver,
+                quality: FilterQuality::Nearest,
+            },
+            Transform::identity(),
+            None,
+        );
+
+        Some(composite)
+    }
+}
+
 /// The factor used to convert pixel per pt to pixel per inch.
 pub const PPP_TO_PPI_FACTOR: f32 = 72.0;
 
@@ -129,6 +222,28 @@ pub fn page_diff(base: &Pixmap, change: &Pixmap, origin: Origin) -> Pixmap {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_alpha_composite() {
+        let mut page = Pixmap::new(16, 1).unwrap();
+        page.pixels_mut()[0] = tiny_skia::ColorU8::from_rgba(255, 0, 0, 255).premultiply();
+
+        assert!(Alpha::Channel.composite(&page).is_none());
+
+        let white = Alpha::White.composite(&page).unwrap();
+        assert_eq!(white.pixel(0, 0).unwrap().demultiply().red(), 255);
+        assert_eq!(white.pixel(0, 0).unwrap().demultiply().green(), 0);
+        assert_eq!(white.pixel(1, 0).unwrap().demultiply().green(), 255);
+        assert_eq!(white.pixel(1, 0).unwrap().alpha(), 255);
+
+        let black = Alpha::Black.composite(&page).unwrap();
+        assert_eq!(black.pixel(1, 0).unwrap().red(), 0);
+        assert_eq!(black.pixel(1, 0).unwrap().alpha(), 255);
+
+        let checker = Alpha::Checker.composite(&page).unwrap();
+        assert_eq!(checker.pixel(1, 0).unwrap().red(), 204);
+        assert_eq!(checker.pixel(8, 0).unwrap().red(), 153);
+    }
+
     #[test]
     fn test_page_diff_top_left() {
         let mut base = Pixmap::new(10, 10).unwrap();
@@ -0,0 +1,271 @@
+//! Comparison of the text layer of documents.
+//!
+//! The text layer of a document is the sequence of positioned text runs on
+//! each of its pages, comparing it instead of the rendered pages is robust
+//! against differences in rendering, like font hinting and anti-aliasing,
+//! while still catching changes in wording, numbering or placement.
+//!
+//! The text layer also contains the families of the fonts used on each page,
+//! these are only compared structurally, see [`pdf::compare`].
+//!
+//! [`pdf::compare`]: super::pdf::compare
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fs;
+use std::io;
+use std::iter;
+use std::path::Path;
+
+use ecow::EcoString;
+use serde::Deserialize;
+use serde::Serialize;
+use thiserror::Error;
+use typst::layout::Frame;
+use typst::layout::FrameItem;
+use typst::layout::PagedDocument;
+use typst::layout::Transform;
+use tytanic_utils::result::io_not_found;
+use tytanic_utils::result::ResultEx;
+
+use super::compare;
+use super::compare::PageError;
+
+/// The name of the file within a page directory in which the text layer of
+/// the document is stored.
+pub const TEXT_FILE: &str = "text.json";
+
+/// The default maximum distance in pt by which the position of a text run
+/// may differ.
+pub const DEFAULT_TOLERANCE: f64 = 1.0;
+
+/// A run of text at a position on a page.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TextRun {
+    /// The horizontal position of the start of the run's baseline in pt.
+    pub x: f64,
+
+    /// The vertical position of the start of the run's baseline in pt.
+    pub y: f64,
+
+    /// The text of the run.
+    pub text: EcoString,
+}
+
+impl TextRun {
+    /// Whether the other run has the same text and is within the given
+    /// tolerance in pt of this run.
+    pub fn matches(&self, other: &Self, tolerance: f64) -> bool {
+        self.text == other.text
+            && (self.x - other.x).abs() <= tolerance
+            && (self.y - other.y).abs() <= tolerance
+    }
+}
+
+impl Display for TextRun {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} at ({:.2}pt, {:.2}pt)", self.text, self.x, self.y)
+    }
+}
+
+/// The text runs of each page of a document in the order in which they were
+/// laid out.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct TextLayer {
+    pages: Vec<Vec<TextRun>>,
+
+    // NOTE(tinger): Text layers stored by older versions don't contain fonts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    fonts: Vec<BTreeSet<EcoString>>,
+}
+
+impl TextLayer {
+    /// Extracts the text layer of the given document.
+    pub fn extract(doc: &PagedDocument) -> Self {
+        let (pages, fonts) = doc
+            .pages
+            .iter()
+            .map(|page| {
+                let mut runs = vec![];
+                let mut fonts = BTreeSet::new();
+                collect(&page.frame, Transform::identity(), &mut runs, &mut fonts);
+                (runs, fonts)
+            })
+            .unzip();
+
+        Self { pages, fonts }
+    }
+
+    /// Loads the text layer stored in the given directory, returns `None` if
+    /// there is none.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Option<Self>, Error> {
+        let Some(content) =
+            fs::read_to_string(dir.as_ref().join(TEXT_FILE)).ignore(io_not_found)?
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(serde_json::from_str(&content)?))
+    }
+
+    /// Stores this text layer in the given directory.
+    pub fn save<P: AsRef<Path>>(&self, dir: P) -> Result<(), Error> {
+        let mut content = serde_json::to_string_pretty(self)?;
+        content.push('\n');
+        fs::write(dir.as_ref().join(TEXT_FILE), content)?;
+
+        Ok(())
+    }
+
+    /// The text runs of each page.
+    pub fn pages(&self) -> &[Vec<TextRun>] {
+        &self.pages
+    }
+
+    /// The families of the fonts used on each page, this is empty for text
+    /// layers stored by older versions.
+    pub fn fonts(&self) -> &[BTreeSet<EcoString>] {
+        &self.fonts
+    }
+
+    /// Compares the text layers of two documents, text runs must have the
+    /// same text and be within the given tolerance in pt of each other.
+    pub fn compare(output: &Self, reference: &Self, tolerance: f64) -> Result<(), compare::Error> {
+        let mut page_errors = vec![];
+
+        for (idx, (a, b)) in iter::zip(&output.pages, &reference.pages).enumerate() {
+            let mut mismatches = a.len().abs_diff(b.len());
+            let mut first = None;
+
+            for (a, b) in iter::zip(a, b) {
+                if !a.matches(b, tolerance) {
+                    mismatches += 1;
+                    first.get_or_insert((Some(a), Some(b)));
+                }
+            }
+
+            if mismatches == 0 {
+                continue;
+            }
+
+            // NOTE(tinger): If all common runs match, the first mismatch is
+            // the first surplus run of either page.
+            let (output, reference) = first.unwrap_or_else(|| (a.get(b.len()), b.get(a.len())));
+
+            page_errors.push((
+                idx,
+                PageError::Text {
+                    mismatches,
+                    output: output.cloned(),
+                    reference: reference.cloned(),
+                },
+            ));
+        }
+
+        if !page_errors.is_empty() || output.pages.len() != reference.pages.len() {
+            return Err(compare::Error {
+                output: output.pages.len(),
+                reference: reference.pages.len(),
+                pages: page_errors,
+                densities: vec![],
+                stats: vec![],
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Collects the text runs and font families of the given frame and its
+/// groups.
+fn collect(frame: &Frame, ts: Transform, runs: &mut Vec<TextRun>, fonts: &mut BTreeSet<EcoString>) {
+    for (pos, item) in frame.items() {
+        match item {
+            FrameItem::Group(group) => {
+                let ts = ts
+                    .pre_concat(Transform::translate(pos.x, pos.y))
+                    .pre_concat(group.transform);
+                collect(&group.frame, ts, runs, fonts);
+            }
+            FrameItem::Text(text) => {
+                let pos = pos.transform(ts);
+                runs.push(TextRun {
+                    x: round(pos.x.to_pt()),
+                    y: round(pos.y.to_pt()),
+                    text: text.text.clone(),
+                });
+                fonts.insert(text.font.info().family.as_str().into());
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Rounds a position to hundredths of a pt, such that stored text layers
+/// don't change because of floating point noise.
+fn round(pt: f64) -> f64 {
+    (pt * 100.0).round() / 100.0
+}
+
+/// Returned by [`TextLayer::load`] and [`TextLayer::save`].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The text layer could not be parsed or serialized.
+    #[error("the text layer could not be parsed or serialized")]
+    Json(#[from] serde_json::Error),
+
+    /// An io error occurred.
+    #[error("an io error occurred")]
+    Io(#[from] io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use typst::syntax::Source;
+
+    use super::*;
+    use crate::_dev::VirtualWorld;
+    use crate::doc::compile;
+    use crate::doc::compile::Warnings;
+
+    fn layer(source: &str) -> TextLayer {
+        let world = VirtualWorld::default();
+        let doc = compile::compile(Source::detached(source), &world, Warnings::Ignore, |w| w)
+            .output
+            .unwrap();
+
+        TextLayer::extract(&doc)
+    }
+
+    #[test]
+    fn test_extract() {
+        let layer = layer("#set page(width: 100pt, height: 100pt, margin: 10pt)\nHello");
+
+        assert_eq!(layer.pages().len(), 1);
+        assert_eq!(layer.pages()[0].len(), 1);
+        assert_eq!(layer.pages()[0][0].text, "Hello");
+        assert_eq!(layer.pages()[0][0].x, 10.0);
+        assert_eq!(layer.fonts().len(), 1);
+        assert!(layer.fonts()[0].contains("Libertinus Serif"));
+    }
+
+    #[test]
+    fn test_compare() {
+        let base = layer("#set page(width: 100pt, height: 100pt)\nHello");
+        let moved = layer("#set page(width: 100pt, height: 100pt)\n#h(0.5pt)Hello");
+        let changed = layer("#set page(width: 100pt, height: 100pt)\nHallo");
+
+        assert!(TextLayer::compare(&base, &base, 0.0).is_ok());
+        assert!(TextLayer::compare(&moved, &base, DEFAULT_TOLERANCE).is_ok());
+        assert!(TextLayer::compare(&moved, &base, 0.0).is_err());
+
+        let error = TextLayer::compare(&changed, &base, DEFAULT_TOLERANCE).unwrap_err();
+        assert_eq!(error.pages.len(), 1);
+        assert!(matches!(
+            &error.pages[0].1,
+            PageError::Text { mismatches: 1, .. }
+        ));
+    }
+}
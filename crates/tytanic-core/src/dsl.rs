@@ -4,14 +4,33 @@
 //! [reference]: https://typst-community.github.io/tytanic/reference/test-sets/index.html
 //! [guide]: https://typst-community.github.io/tytanic/guides/test-sets.html
 
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use ecow::EcoString;
+use tytanic_filter::ast::Dur;
 use tytanic_filter::ast::Id;
+use tytanic_filter::ast::Num;
+use tytanic_filter::ast::Str;
 use tytanic_filter::eval;
 use tytanic_filter::eval::Context;
 use tytanic_filter::eval::Error;
+use tytanic_filter::eval::Eval;
 use tytanic_filter::eval::Func;
+use tytanic_filter::eval::Metric;
 use tytanic_filter::eval::Set;
 use tytanic_filter::eval::Value;
 
+use tytanic_utils::result::io_not_found;
+use tytanic_utils::result::ResultEx;
+
+use crate::doc::Document;
+use crate::history::History;
+use crate::project::Project;
+use crate::test::unit::Kind;
+use crate::test::Annotation;
 use crate::test::Test;
 
 impl eval::Test for Test {
@@ -37,6 +56,13 @@ pub fn context() -> Context<Test> {
         ("compile-only", built_in::compile_only_ctor),
         ("ephemeral", built_in::ephemeral_ctor),
         ("persistent", built_in::persistent_ctor),
+        ("tag", built_in::tag_ctor),
+        ("kind", built_in::kind_ctor),
+        ("annotation", built_in::annotation_ctor),
+        ("has-annotation", built_in::has_annotation_ctor),
+        ("starts-with", built_in::starts_with_ctor),
+        ("ends-with", built_in::ends_with_ctor),
+        ("lower", built_in::lower),
     ];
 
     for (id, func) in functions {
@@ -46,6 +72,118 @@ pub fn context() -> Context<Test> {
     ctx
 }
 
+/// The names of the functions which are only bound in [`project_context`].
+const PROJECT_FUNCTIONS: [&str; 5] = ["mtime", "previously-failed", "changed", "duration", "pages"];
+
+/// Whether the given name can be used for a test set defined in the project
+/// config, i.e. whether it is a valid identifier which isn't already bound by
+/// [`project_context`].
+pub fn is_valid_test_set_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    valid && !PROJECT_FUNCTIONS.contains(&name) && context().resolve(name).is_err()
+}
+
+/// Creates the context used by Tytanic for the given project, this contains
+/// the bindings of [`context`], those which require access to the project on
+/// disk and the test sets defined in the project config.
+pub fn project_context(project: &Project) -> Context<Test> {
+    let mut ctx = context();
+
+    for (name, expr) in &project.config().test_sets {
+        if !is_valid_test_set_name(name) {
+            tracing::warn!(?name, "ignoring invalid test set name");
+            continue;
+        }
+
+        ctx.bind(
+            Id(name.into()),
+            Value::Func(Func::new(test_set_alias(name.into(), expr.into()))),
+        );
+    }
+
+    let project = project.clone();
+    {
+        let project = project.clone();
+        ctx.bind(
+            Id("mtime".into()),
+            Value::Func(Func::new(move |ctx, args| {
+                built_in::mtime_ctor(&project, ctx, args)
+            })),
+        );
+    }
+
+    {
+        let project = project.clone();
+        ctx.bind(
+            Id("previously-failed".into()),
+            Value::Func(Func::new(move |ctx, args| {
+                built_in::previously_failed_ctor(&project, ctx, args)
+            })),
+        );
+    }
+
+    {
+        let project = project.clone();
+        ctx.bind(
+            Id("changed".into()),
+            Value::Func(Func::new(move |ctx, args| {
+                built_in::changed_ctor(&project, ctx, args)
+            })),
+        );
+    }
+
+    {
+        let project = project.clone();
+        ctx.bind(
+            Id("duration".into()),
+            Value::Func(Func::new(move |ctx, args| {
+                built_in::duration_ctor(&project, ctx, args)
+            })),
+        );
+    }
+
+    ctx.bind(
+        Id("pages".into()),
+        Value::Func(Func::new(move |ctx, args| {
+            built_in::pages_ctor(&project, ctx, args)
+        })),
+    );
+
+    ctx
+}
+
+/// Creates the function for a test set defined in the project config, the
+/// expression is parsed and evaluated each time the function is called.
+fn test_set_alias(
+    name: EcoString,
+    expr: EcoString,
+) -> impl Fn(&Context<Test>, &[Value<Test>]) -> Result<Value<Test>, Error> + Send + Sync + 'static {
+    move |ctx, args| {
+        Func::expect_no_args(&name, ctx, args)?;
+
+        let parsed = tytanic_filter::ast::parse(&expr).map_err(|err| Error::Custom(err.into()))?;
+
+        // NOTE(tinger): The test set is shadowed while evaluating its own
+        // expression, such that cycles produce an error instead of a stack
+        // overflow.
+        let mut inner = ctx.clone();
+        let cycle = name.clone();
+        inner.bind(
+            Id(name.clone()),
+            Value::Func(Func::new(move |_, _| {
+                Err(Error::Custom(
+                    format!("test set {cycle:?} refers to itself").into(),
+                ))
+            })),
+        );
+
+        parsed.eval(&inner)
+    }
+}
+
 /// Function definitions for the Tytanic test set DSL default evaluation
 /// context.
 pub mod built_in {
@@ -170,4 +308,316 @@ pub mod built_in {
                 .is_some_and(|unit| unit.kind().is_persistent()))
         })
     }
+
+    /// The constructor function for the test set returned by [`tag`].
+    pub fn tag_ctor(ctx: &Context<Test>, args: &[Value<Test>]) -> Result<Value<Test>, Error> {
+        let [tag]: [Str; 1] = Func::expect_args_exact("tag", ctx, args)?;
+        if !Annotation::is_valid_tag(tag.as_str()) {
+            return Err(Error::Custom(
+                format!("invalid tag {:?}", tag.as_str()).into(),
+            ));
+        }
+
+        Ok(Value::Set(self::tag(tag.as_str().into())))
+    }
+
+    /// Constructs the `tag(name)` test set. A test set which contains all
+    /// unit tests with the given tag.
+    pub fn tag(tag: EcoString) -> Set<Test> {
+        Set::new(move |_, test: &Test| {
+            Ok(test.as_unit_test().is_some_and(|unit| unit.has_tag(&tag)))
+        })
+    }
+
+    /// The constructor function for the test set returned by [`kind`].
+    pub fn kind_ctor(ctx: &Context<Test>, args: &[Value<Test>]) -> Result<Value<Test>, Error> {
+        let [kind]: [Str; 1] = Func::expect_args_exact("kind", ctx, args)?;
+        let kinds = [Kind::CompileOnly, Kind::Ephemeral, Kind::Persistent];
+        let Some(kind) = kinds.into_iter().find(|k| k.as_str() == kind.as_str()) else {
+            return Err(Error::Custom(
+                format!(
+                    "unknown test kind {:?}, expected one of compile-only, ephemeral or persistent",
+                    kind.as_str(),
+                )
+                .into(),
+            ));
+        };
+
+        Ok(Value::Set(self::kind(kind)))
+    }
+
+    /// Constructs the `kind(name)` test set. A test set which contains all
+    /// unit tests of the given kind.
+    pub fn kind(kind: Kind) -> Set<Test> {
+        Set::new(move |_, test: &Test| {
+            Ok(test.as_unit_test().is_some_and(|unit| unit.kind() == kind))
+        })
+    }
+
+    /// The constructor function for the test set returned by [`annotation`].
+    pub fn annotation_ctor(
+        ctx: &Context<Test>,
+        args: &[Value<Test>],
+    ) -> Result<Value<Test>, Error> {
+        let ([name], arg): ([Str; 1], Vec<Str>) = Func::expect_args_min("annotation", ctx, args)?;
+
+        let source = match arg.as_slice() {
+            [] => format!("[{}]", name.as_str()),
+            [arg] => format!("[{}: {}]", name.as_str(), arg.as_str()),
+            _ => {
+                return Err(Error::InvalidArgumentCount {
+                    func: "annotation".into(),
+                    expected: 2,
+                    is_min: false,
+                    found: args.len(),
+                })
+            }
+        };
+
+        let annotation = source.parse().map_err(|err| Error::Custom(Box::new(err)))?;
+
+        Ok(Value::Set(self::annotation(annotation)))
+    }
+
+    /// Constructs the `annotation(name, arg)` test set. A test set which
+    /// contains all unit tests with an annotation equal to the given one.
+    pub fn annotation(annotation: Annotation) -> Set<Test> {
+        Set::new(move |_, test: &Test| {
+            Ok(test
+                .as_unit_test()
+                .is_some_and(|unit| unit.annotations().contains(&annotation)))
+        })
+    }
+
+    /// The constructor function for the test set returned by
+    /// [`has_annotation`].
+    pub fn has_annotation_ctor(
+        ctx: &Context<Test>,
+        args: &[Value<Test>],
+    ) -> Result<Value<Test>, Error> {
+        let [name]: [Str; 1] = Func::expect_args_exact("has-annotation", ctx, args)?;
+        let Some(name) = Annotation::NAMES
+            .iter()
+            .find(|known| **known == name.as_str())
+        else {
+            return Err(Error::Custom(
+                format!(
+                    "unknown annotation {:?}, expected one of {}",
+                    name.as_str(),
+                    Annotation::NAMES.join(", "),
+                )
+                .into(),
+            ));
+        };
+
+        Ok(Value::Set(has_annotation(name)))
+    }
+
+    /// Constructs the `has-annotation(name)` test set. A test set which
+    /// contains all unit tests with an annotation of the given name,
+    /// regardless of its argument.
+    pub fn has_annotation(name: &'static str) -> Set<Test> {
+        Set::new(move |_, test: &Test| {
+            Ok(test
+                .as_unit_test()
+                .is_some_and(|unit| unit.annotations().iter().any(|annot| annot.name() == name)))
+        })
+    }
+
+    /// The constructor function for the test set returned by [`starts_with`].
+    pub fn starts_with_ctor(
+        ctx: &Context<Test>,
+        args: &[Value<Test>],
+    ) -> Result<Value<Test>, Error> {
+        let [prefix]: [Str; 1] = Func::expect_args_exact("starts-with", ctx, args)?;
+        Ok(Value::Set(starts_with(prefix.into_inner())))
+    }
+
+    /// Constructs the `starts-with(prefix)` test set. A test set which
+    /// contains all tests whose identifier starts with the given prefix.
+    pub fn starts_with(prefix: EcoString) -> Set<Test> {
+        Set::new(move |_, test: &Test| Ok(test.id().as_str().starts_with(prefix.as_str())))
+    }
+
+    /// The constructor function for the test set returned by [`ends_with`].
+    pub fn ends_with_ctor(ctx: &Context<Test>, args: &[Value<Test>]) -> Result<Value<Test>, Error> {
+        let [suffix]: [Str; 1] = Func::expect_args_exact("ends-with", ctx, args)?;
+        Ok(Value::Set(ends_with(suffix.into_inner())))
+    }
+
+    /// Constructs the `ends-with(suffix)` test set. A test set which contains
+    /// all tests whose identifier ends with the given suffix.
+    pub fn ends_with(suffix: EcoString) -> Set<Test> {
+        Set::new(move |_, test: &Test| Ok(test.id().as_str().ends_with(suffix.as_str())))
+    }
+
+    /// The `lower(string)` function. Returns the given string in lowercase,
+    /// e.g. to compare it case-insensitively with a lowercase identifier.
+    pub fn lower(ctx: &Context<Test>, args: &[Value<Test>]) -> Result<Value<Test>, Error> {
+        let [string]: [Str; 1] = Func::expect_args_exact("lower", ctx, args)?;
+        Ok(Value::Str(Str::from(string.as_str().to_lowercase())))
+    }
+
+    /// The constructor function for the test set returned by [`mtime`].
+    pub fn mtime_ctor(
+        project: &Project,
+        ctx: &Context<Test>,
+        args: &[Value<Test>],
+    ) -> Result<Value<Test>, Error> {
+        // NOTE(tinger): Strings are still accepted, they predate duration
+        // literals.
+        let within = match args {
+            [Value::Dur(within)] => within.0,
+            _ => {
+                let [within]: [Str; 1] = Func::expect_args_exact("mtime", ctx, args)?;
+                tytanic_utils::time::parse_duration(within.as_str()).ok_or_else(|| {
+                    Error::Custom(format!("invalid duration {:?}", within.as_str()).into())
+                })?
+            }
+        };
+
+        Ok(Value::Set(mtime(project.clone(), within)))
+    }
+
+    /// Constructs the `mtime(duration)` test set. A test set which contains
+    /// all tests whose sources were modified within the given duration.
+    pub fn mtime(project: Project, within: Duration) -> Set<Test> {
+        let cutoff = SystemTime::now()
+            .checked_sub(within)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        Set::new(move |_, test: &Test| {
+            let modified = test
+                .last_modified(&project)
+                .map_err(|err| Error::Custom(err.into()))?;
+
+            Ok(modified >= cutoff)
+        })
+    }
+
+    /// The constructor function for the test set returned by [`changed`].
+    pub fn changed_ctor(
+        project: &Project,
+        ctx: &Context<Test>,
+        args: &[Value<Test>],
+    ) -> Result<Value<Test>, Error> {
+        let base = match args.len() {
+            0 => None,
+            1 => {
+                let [base]: [Str; 1] = Func::expect_args_exact("changed", ctx, args)?;
+                Some(base)
+            }
+            found => {
+                return Err(Error::InvalidArgumentCount {
+                    func: "changed".into(),
+                    expected: 1,
+                    is_min: false,
+                    found,
+                })
+            }
+        };
+
+        let vcs = project.vcs().ok_or_else(|| {
+            Error::Custom("`changed()` requires the project to be in a repository".into())
+        })?;
+
+        let files = vcs
+            .changed_files(base.as_ref().map(Str::as_str))
+            .map_err(|err| Error::Custom(err.into()))?;
+
+        Ok(Value::Set(changed(
+            project.clone(),
+            files.into_iter().collect(),
+        )))
+    }
+
+    /// Constructs the `changed(base)` test set. A test set which contains all
+    /// unit tests whose test script, reference script or reference images are
+    /// among the given changed files.
+    pub fn changed(project: Project, files: BTreeSet<PathBuf>) -> Set<Test> {
+        Set::new(move |_, test: &Test| {
+            let Some(unit) = test.as_unit_test() else {
+                return Ok(false);
+            };
+
+            let script = project.unit_test_script(unit.id());
+            let ref_script = project.unit_test_ref_script(unit.id());
+            let ref_dir = project.unit_test_ref_dir(unit.id());
+
+            Ok(files
+                .iter()
+                .any(|file| *file == script || *file == ref_script || file.starts_with(&ref_dir)))
+        })
+    }
+
+    /// The constructor function for the test set returned by
+    /// [`previously_failed`].
+    pub fn previously_failed_ctor(
+        project: &Project,
+        ctx: &Context<Test>,
+        args: &[Value<Test>],
+    ) -> Result<Value<Test>, Error> {
+        Func::expect_no_args("previously-failed", ctx, args)?;
+        let history = History::load(project).map_err(|err| Error::Custom(err.into()))?;
+        Ok(Value::Set(previously_failed(&history)))
+    }
+
+    /// Constructs the `previously-failed()` test set. A test set which
+    /// contains all tests which failed in their last run according to the
+    /// given history.
+    pub fn previously_failed(history: &History) -> Set<Test> {
+        let failed: BTreeSet<String> = history.failed_tests().map(Into::into).collect();
+        Set::new(move |_, test: &Test| Ok(failed.contains(test.id().as_str())))
+    }
+
+    /// The constructor function for the metric returned by [`duration`].
+    pub fn duration_ctor(
+        project: &Project,
+        ctx: &Context<Test>,
+        args: &[Value<Test>],
+    ) -> Result<Value<Test>, Error> {
+        Func::expect_no_args("duration", ctx, args)?;
+        let history = History::load(project).map_err(|err| Error::Custom(err.into()))?;
+        Ok(Value::Metric(duration(history)))
+    }
+
+    /// Constructs the `duration()` metric. A metric which is the duration of
+    /// the last run of a test according to the given history, tests which
+    /// were not run before have no duration.
+    pub fn duration(history: History) -> Metric<Test> {
+        Metric::new(move |_, test: &Test| {
+            Ok(history.duration(test.id()).map(|d| Value::Dur(Dur(d))))
+        })
+    }
+
+    /// The constructor function for the metric returned by [`pages`].
+    pub fn pages_ctor(
+        project: &Project,
+        ctx: &Context<Test>,
+        args: &[Value<Test>],
+    ) -> Result<Value<Test>, Error> {
+        Func::expect_no_args("pages", ctx, args)?;
+        Ok(Value::Metric(pages(project.clone())))
+    }
+
+    /// Constructs the `pages()` metric. A metric which is the number of pages
+    /// of the persistent reference of a unit test, other tests have no page
+    /// count.
+    pub fn pages(project: Project) -> Metric<Test> {
+        Metric::new(move |_, test: &Test| {
+            let Some(unit) = test.as_unit_test() else {
+                return Ok(None);
+            };
+
+            if !unit.kind().is_persistent() {
+                return Ok(None);
+            }
+
+            let pages = Document::count_pages(project.unit_test_ref_dir(unit.id()))
+                .ignore(io_not_found)
+                .map_err(|err| Error::Custom(err.into()))?;
+
+            Ok(pages.map(|pages| Value::Num(Num(pages))))
+        })
+    }
 }
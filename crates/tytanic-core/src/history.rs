@@ -0,0 +1,153 @@
+//! Persisting test run data across test runs.
+//!
+//! The history is stored in the project's [cache directory] and currently
+//! contains the duration and outcome of the last run of each test, these are
+//! used to estimate the duration of future test runs and to re-run the tests
+//! which failed.
+//!
+//! [cache directory]: Project::cache_dir
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::cache;
+use crate::project::Project;
+use crate::suite::SuiteResult;
+use crate::test::Id;
+
+/// The name of the history file within the cache directory.
+pub const HISTORY_FILE: &str = "history.toml";
+
+/// The persisted history of previous test runs.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct History {
+    /// The durations of the last run of each test in milliseconds.
+    #[serde(default)]
+    durations: BTreeMap<String, u64>,
+
+    /// The tests which failed in their last run.
+    #[serde(default)]
+    failed: BTreeSet<String>,
+}
+
+impl History {
+    /// The path to the history file of the given project.
+    pub fn file(project: &Project) -> PathBuf {
+        project.cache_dir().join(HISTORY_FILE)
+    }
+
+    /// Loads the history of the given project, returns an empty history if
+    /// none was persisted yet.
+    #[tracing::instrument(skip_all)]
+    pub fn load(project: &Project) -> Result<Self, cache::Error> {
+        cache::load(project, HISTORY_FILE)
+    }
+
+    /// Persists this history for the given project, this creates the cache
+    /// directory and ignores it in the project's VCS if necessary.
+    #[tracing::instrument(skip_all)]
+    pub fn save(&self, project: &Project) -> Result<(), cache::Error> {
+        cache::save(project, HISTORY_FILE, self)
+    }
+}
+
+impl History {
+    /// The duration of the last run of the given test, if it was run before.
+    pub fn duration(&self, id: &Id) -> Option<Duration> {
+        self.durations
+            .get(id.as_str())
+            .copied()
+            .map(Duration::from_millis)
+    }
+
+    /// Whether the given test failed in its last run.
+    pub fn failed(&self, id: &Id) -> bool {
+        self.failed.contains(id.as_str())
+    }
+
+    /// The tests which failed in their last run.
+    pub fn failed_tests(&self) -> impl Iterator<Item = &str> {
+        self.failed.iter().map(String::as_str)
+    }
+
+    /// Estimates the total duration of running the given tests.
+    ///
+    /// Tests without a recorded duration are assumed to take as long as the
+    /// average of the recorded ones. Returns `None` if none of the tests have
+    /// a recorded duration.
+    pub fn estimate<'i, I>(&self, ids: I) -> Option<Duration>
+    where
+        I: IntoIterator<Item = &'i Id>,
+    {
+        let mut known = 0;
+        let mut unknown = 0;
+        let mut total = Duration::ZERO;
+
+        for id in ids {
+            match self.duration(id) {
+                Some(duration) => {
+                    known += 1;
+                    total += duration;
+                }
+                None => unknown += 1,
+            }
+        }
+
+        if known == 0 {
+            return None;
+        }
+
+        Some(total + total / known * unknown)
+    }
+
+    /// Records the durations and outcomes of all tests which were run in the
+    /// given result, tests which were skipped, filtered or cancelled are not
+    /// recorded.
+    pub fn record(&mut self, result: &SuiteResult) {
+        for (id, test) in result.results() {
+            if test.is_fail() {
+                self.failed.insert(id.as_str().to_owned());
+            } else if test.is_pass() {
+                self.failed.remove(id.as_str());
+            }
+
+            if test.is_pass() || test.is_fail() {
+                self.durations.insert(
+                    id.as_str().to_owned(),
+                    test.duration().as_millis().try_into().unwrap_or(u64::MAX),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_estimate() {
+        let history = History {
+            durations: BTreeMap::from([("a".into(), 100), ("b".into(), 300)]),
+            failed: BTreeSet::new(),
+        };
+
+        let a = Id::new("a").unwrap();
+        let b = Id::new("b").unwrap();
+        let c = Id::new("c").unwrap();
+
+        assert_eq!(history.estimate([&a]), Some(Duration::from_millis(100)));
+        assert_eq!(
+            history.estimate([&a, &b, &c]),
+            Some(Duration::from_millis(600))
+        );
+        assert_eq!(history.estimate([&c]), None);
+        assert_eq!(history.estimate([]), None);
+    }
+}
@@ -0,0 +1,223 @@
+//! Running the hook commands configured in the project config.
+//!
+//! Hooks are shell commands which are run in the project root around a test
+//! run and around each unit test, see [`ProjectHooks`]. They can be used to
+//! generate fixture data before tests are compiled or to convert their outputs
+//! afterwards.
+
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::io;
+use std::process::Command;
+use std::process::ExitStatus;
+
+use ecow::EcoString;
+use thiserror::Error;
+
+use crate::config::ProjectHooks;
+use crate::project::Project;
+use crate::test::UnitTest;
+
+/// The environment variable containing the path to the project root.
+pub const PROJECT_ROOT_VAR: &str = "TT_PROJECT_ROOT";
+
+/// The environment variable containing the identifier of the current test.
+pub const TEST_ID_VAR: &str = "TT_TEST_ID";
+
+/// The environment variable containing the path to the directory of the
+/// current test.
+pub const TEST_DIR_VAR: &str = "TT_TEST_DIR";
+
+/// The point in a test run at which a hook is run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Hook {
+    /// Run once before any test.
+    Prepare,
+
+    /// Run before each unit test.
+    PrepareEach,
+
+    /// Run after each unit test.
+    CleanupEach,
+
+    /// Run once after all tests.
+    Cleanup,
+}
+
+impl Hook {
+    /// The name of this hook as used in the project config.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Prepare => "prepare",
+            Self::PrepareEach => "prepare-each",
+            Self::CleanupEach => "cleanup-each",
+            Self::Cleanup => "cleanup",
+        }
+    }
+
+    /// Whether this hook is run for each unit test.
+    pub fn is_each(self) -> bool {
+        matches!(self, Self::PrepareEach | Self::CleanupEach)
+    }
+
+    /// The command configured for this hook, if any.
+    pub fn command(self, hooks: &ProjectHooks) -> Option<&str> {
+        match self {
+            Self::Prepare => hooks.prepare.as_deref(),
+            Self::PrepareEach => hooks.prepare_each.as_deref(),
+            Self::CleanupEach => hooks.cleanup_each.as_deref(),
+            Self::Cleanup => hooks.cleanup.as_deref(),
+        }
+    }
+}
+
+impl Display for Hook {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Runs the given hook of the project if it is configured, the test must be
+/// given for hooks which are run for each unit test.
+///
+/// The command is run by the system shell in the project root, its output is
+/// captured such that it doesn't interfere with the reporter, the standard
+/// error is attached to the error if the command fails.
+///
+/// # Panics
+/// Panics if a test is given for a hook which is not run for each test or vice
+/// versa.
+#[tracing::instrument(skip(project, test), fields(test = ?test.map(UnitTest::id)))]
+pub fn run(project: &Project, hook: Hook, test: Option<&UnitTest>) -> Result<(), Error> {
+    assert_eq!(
+        hook.is_each(),
+        test.is_some(),
+        "a test must be given exactly for hooks which are run for each test",
+    );
+
+    let Some(command) = hook.command(&project.config().hooks) else {
+        return Ok(());
+    };
+
+    let mut process = shell(command);
+    process
+        .current_dir(project.root())
+        .env(PROJECT_ROOT_VAR, project.root());
+
+    if let Some(test) = test {
+        process
+            .env(TEST_ID_VAR, test.id().as_str())
+            .env(TEST_DIR_VAR, project.unit_test_dir(test.id()));
+    }
+
+    tracing::debug!(%hook, command, "running hook");
+    let output = process.output().map_err(|error| Error::Io {
+        hook,
+        command: command.into(),
+        error,
+    })?;
+
+    if !output.status.success() {
+        return Err(Error::Failed {
+            hook,
+            command: command.into(),
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).trim().into(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Creates a process which runs the given command using the system shell.
+fn shell(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.args(["/C", command]);
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.args(["-c", command]);
+        process
+    }
+}
+
+/// Returned by [`run`].
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The hook command could not be started.
+    #[error("the {hook} hook `{command}` could not be started")]
+    Io {
+        /// The hook which could not be started.
+        hook: Hook,
+
+        /// The command of the hook.
+        command: EcoString,
+
+        /// The error which occurred when starting the command.
+        #[source]
+        error: io::Error,
+    },
+
+    /// The hook command exited unsuccessfully.
+    #[error("the {hook} hook `{command}` failed with {status}: {stderr}")]
+    Failed {
+        /// The hook which failed.
+        hook: Hook,
+
+        /// The command of the hook.
+        command: EcoString,
+
+        /// The exit status of the command.
+        status: ExitStatus,
+
+        /// The captured standard error of the command.
+        stderr: EcoString,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use tytanic_utils::fs::TempTestEnv;
+
+    use super::*;
+    use crate::config::ProjectConfig;
+    use crate::test::unit::Kind;
+    use crate::test::Id;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run() {
+        TempTestEnv::run_no_check(
+            |root| root.setup_dir("tests/a"),
+            |root| {
+                let project = Project::new(root).with_config(ProjectConfig {
+                    hooks: ProjectHooks {
+                        prepare: Some("echo oops >&2; exit 1".into()),
+                        prepare_each: Some(r#"echo "$TT_TEST_ID" > "$TT_TEST_DIR/id.txt""#.into()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+                let test = UnitTest::new_test(Id::new("a").unwrap(), Kind::CompileOnly);
+
+                run(&project, Hook::PrepareEach, Some(&test)).unwrap();
+                run(&project, Hook::Cleanup, None).unwrap();
+                assert!(matches!(
+                    run(&project, Hook::Prepare, None),
+                    Err(Error::Failed {
+                        hook: Hook::Prepare,
+                        stderr,
+                        ..
+                    }) if stderr == "oops"
+                ));
+
+                assert_eq!(
+                    std::fs::read_to_string(root.join("tests/a/id.txt")).unwrap(),
+                    "a\n"
+                );
+            },
+        );
+    }
+}
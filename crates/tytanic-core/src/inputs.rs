@@ -0,0 +1,303 @@
+//! Tracking of the inputs of unit tests across test runs.
+//!
+//! The inputs of a unit test are the files in its test directory, except for
+//! its temporary directories, and the project files outside of the unit test
+//! root, like the package sources and template. Their hashes are stored in the
+//! project's [cache directory] for each test which passed, such that tests
+//! whose inputs didn't change since can be skipped.
+//!
+//! The hashes are only valid for the options they were recorded with, if the
+//! options change, all tests are considered changed.
+//!
+//! [cache directory]: Project::cache_dir
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use tytanic_utils::result::io_not_found;
+use tytanic_utils::result::ResultEx;
+
+use crate::cache;
+use crate::project::Project;
+use crate::suite::SuiteResult;
+use crate::test::Id;
+use crate::test::UnitTest;
+
+/// The name of the input hashes file within the cache directory.
+pub const INPUTS_FILE: &str = "inputs.toml";
+
+/// A hex encoded SHA-256 hash of the inputs of a test or of the options it was
+/// run with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct InputHash(String);
+
+impl InputHash {
+    fn from_hasher(hasher: Sha256) -> Self {
+        Self(to_hex(hasher))
+    }
+
+    /// Hashes a description of the options tests are run with, the version of
+    /// this crate is included, such that updates invalidate all hashes.
+    pub fn options(options: &str) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update([0]);
+        hasher.update(options);
+        Self::from_hasher(hasher)
+    }
+
+    /// Hashes the project files outside of the unit test root, hidden files
+    /// and directories are skipped.
+    #[tracing::instrument(skip_all)]
+    pub fn project(project: &Project) -> io::Result<Self> {
+        let skip = BTreeSet::from([project.unit_tests_root()]);
+
+        let mut hasher = Sha256::new();
+        hash_dir(
+            &mut hasher,
+            project.root(),
+            project.root(),
+            &mut skip_or_read(&skip),
+        )?;
+        Ok(Self::from_hasher(hasher))
+    }
+
+    /// Hashes the inputs of the given unit test, the project hash is combined
+    /// with the files in its test directory.
+    #[tracing::instrument(skip(project, project_hash, test), fields(test = ?test.id()))]
+    pub fn unit_test(project: &Project, project_hash: &Self, test: &UnitTest) -> io::Result<Self> {
+        // NOTE(tinger): The temporary directories of all instances of a
+        // parametrized test are skipped.
+        let base = test.id().without_params();
+        let dir = project.unit_test_dir(&base);
+        let skip = BTreeSet::from([
+            project.unit_test_out_dir(&base),
+            project.unit_test_diff_dir(&base),
+        ]);
+
+        let mut hasher = Sha256::new();
+        hasher.update(&project_hash.0);
+        hasher.update([0]);
+        hasher.update(test.id().as_str());
+        hasher.update([0]);
+        hash_dir(&mut hasher, &dir, &dir, &mut skip_or_read(&skip))?;
+        Ok(Self::from_hasher(hasher))
+    }
+
+    /// The hex encoded hash.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Skips the given paths and hashes the contents of all other entries.
+fn skip_or_read(skip: &BTreeSet<PathBuf>) -> impl FnMut(&Path) -> io::Result<HashEntry> + '_ {
+    |path| {
+        Ok(if skip.contains(path) {
+            HashEntry::Skip
+        } else {
+            HashEntry::Contents
+        })
+    }
+}
+
+/// How an entry of a directory is hashed by [`hash_dir`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HashEntry {
+    /// The entry is skipped entirely.
+    Skip,
+
+    /// Only the path of the entry is hashed, directories are recursed into.
+    Path,
+
+    /// The path and the contents of the entry are hashed, directories are
+    /// recursed into.
+    Contents,
+}
+
+/// Hashes the paths of all entries in the given directory relative to the
+/// given root in a stable order, hidden entries are skipped. The given
+/// function decides how each other entry is hashed.
+pub(crate) fn hash_dir<F>(hasher: &mut Sha256, root: &Path, dir: &Path, f: &mut F) -> io::Result<()>
+where
+    F: FnMut(&Path) -> io::Result<HashEntry>,
+{
+    let Some(entries) = fs::read_dir(dir).ignore(io_not_found)? else {
+        return Ok(());
+    };
+
+    let mut entries = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        let hidden = path
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+
+        if hidden {
+            continue;
+        }
+
+        let entry = f(&path)?;
+        if entry == HashEntry::Skip {
+            continue;
+        }
+
+        let rel = path.strip_prefix(root).unwrap_or(&path);
+        hasher.update(rel.to_string_lossy().replace('\\', "/"));
+        hasher.update([0]);
+
+        if path.is_dir() {
+            hasher.update("/");
+            hash_dir(hasher, root, &path, f)?;
+        } else if entry == HashEntry::Contents {
+            hasher.update(fs::read(&path)?);
+        }
+        hasher.update([0]);
+    }
+
+    Ok(())
+}
+
+/// Finalizes the given hasher into a hex encoded hash.
+pub(crate) fn to_hex(hasher: Sha256) -> String {
+    format!("{:x}", hasher.finalize())
+}
+
+/// The persisted input hashes of the unit tests which passed in previous test
+/// runs.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Inputs {
+    /// The hash of the options the tests were run with.
+    #[serde(default)]
+    options: Option<InputHash>,
+
+    /// The input hashes of the last passing run of each test.
+    #[serde(default)]
+    tests: BTreeMap<String, InputHash>,
+}
+
+impl Inputs {
+    /// The path to the input hashes file of the given project.
+    pub fn file(project: &Project) -> PathBuf {
+        project.cache_dir().join(INPUTS_FILE)
+    }
+
+    /// Loads the input hashes of the given project, returns empty input
+    /// hashes if none were persisted yet.
+    #[tracing::instrument(skip_all)]
+    pub fn load(project: &Project) -> Result<Self, cache::Error> {
+        cache::load(project, INPUTS_FILE)
+    }
+
+    /// Persists these input hashes for the given project, this creates the
+    /// cache directory and ignores it in the project's VCS if necessary.
+    #[tracing::instrument(skip_all)]
+    pub fn save(&self, project: &Project) -> Result<(), cache::Error> {
+        cache::save(project, INPUTS_FILE, self)
+    }
+}
+
+impl Inputs {
+    /// Whether the given test passed with the given inputs and options in a
+    /// previous run.
+    pub fn is_unchanged(&self, options: &InputHash, id: &Id, hash: &InputHash) -> bool {
+        self.options.as_ref() == Some(options) && self.tests.get(id.as_str()) == Some(hash)
+    }
+
+    /// Records the input hashes of all tests which passed in the given result,
+    /// tests which failed are forgotten, such that they are run again.
+    ///
+    /// If the options differ from those of the previous runs, all previously
+    /// recorded hashes are forgotten.
+    pub fn record(
+        &mut self,
+        options: InputHash,
+        hashes: &BTreeMap<Id, InputHash>,
+        result: &SuiteResult,
+    ) {
+        if self.options.as_ref() != Some(&options) {
+            self.tests.clear();
+            self.options = Some(options);
+        }
+
+        for (id, test) in result.results() {
+            if test.is_fail() {
+                self.tests.remove(id.as_str());
+            } else if let Some(hash) = hashes.get(id).filter(|_| test.is_pass()) {
+                self.tests.insert(id.as_str().to_owned(), hash.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tytanic_utils::fs::TempTestEnv;
+
+    use super::*;
+    use crate::test::unit::Kind;
+
+    #[test]
+    fn test_input_hash_unit_test() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("src/lib.typ", "#let x = 1")
+                    .setup_file("tests/a/test.typ", "Hello")
+                    .setup_file("tests/a/out/1.png", "a")
+            },
+            |root| {
+                let project = Project::new(root);
+                let test = UnitTest::new_test(Id::new("a").unwrap(), Kind::CompileOnly);
+
+                let hash = |project: &Project| {
+                    let project_hash = InputHash::project(project).unwrap();
+                    InputHash::unit_test(project, &project_hash, &test).unwrap()
+                };
+
+                let original = hash(&project);
+                assert_eq!(hash(&project), original);
+
+                // Temporary directories are not inputs.
+                fs::write(root.join("tests/a/out/1.png"), "b").unwrap();
+                assert_eq!(hash(&project), original);
+
+                fs::write(root.join("tests/a/test.typ"), "Hallo").unwrap();
+                let changed_test = hash(&project);
+                assert_ne!(changed_test, original);
+
+                fs::write(root.join("src/lib.typ"), "#let x = 2").unwrap();
+                assert_ne!(hash(&project), changed_test);
+            },
+        );
+    }
+
+    #[test]
+    fn test_inputs_is_unchanged() {
+        let options = InputHash::options("a");
+        let id = Id::new("a").unwrap();
+        let hash = InputHash("0".into());
+
+        let inputs = Inputs {
+            options: Some(options.clone()),
+            tests: BTreeMap::from([("a".into(), hash.clone())]),
+        };
+
+        assert!(inputs.is_unchanged(&options, &id, &hash));
+        assert!(!inputs.is_unchanged(&InputHash::options("b"), &id, &hash));
+        assert!(!inputs.is_unchanged(&options, &id, &InputHash("1".into())));
+        assert!(!inputs.is_unchanged(&options, &Id::new("b").unwrap(), &hash));
+    }
+}
@@ -1,8 +1,30 @@
 //! The core library of the Tytanic test runner.
+//!
+//! # Features
+//! - `optimize` (default): Optimizing pages when saving them using
+//!   [`oxipng`](https://docs.rs/oxipng), see [`doc::OptimizeOptions`].
+//! - `pdf` (default): Exporting documents to PDF using
+//!   [`typst-pdf`](https://docs.rs/typst-pdf), see [`doc::pdf::export`].
+//! - `schema` (default): Deriving JSON schemas for the project config, see
+//!   `config::project_config_schema`.
+//! - `svg` (default): Exporting pages as SVG using
+//!   [`typst-svg`](https://docs.rs/typst-svg) and rasterizing SVG references
+//!   using [`resvg`](https://docs.rs/resvg), see [`doc::Document::svg`],
+//!   [`doc::Document::has_svgs`] and [`doc::decode_svg_page`].
+//!
+//! Embedders which only collect and filter tests can disable the default
+//! features.
 
+pub mod bench;
+pub mod cache;
+pub mod cancel;
 pub mod config;
+pub mod coverage;
 pub mod doc;
 pub mod dsl;
+pub mod history;
+pub mod hook;
+pub mod inputs;
 pub mod library;
 pub mod project;
 pub mod suite;
@@ -1,6 +1,9 @@
 //! Discovering, loading and managing typst projects.
 
 use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
 use std::fs;
 use std::io;
 use std::ops::Deref;
@@ -8,6 +11,7 @@ use std::path::Component;
 use std::path::Path;
 use std::path::PathBuf;
 
+use ecow::eco_format;
 use ecow::EcoString;
 use serde::Deserialize;
 use thiserror::Error;
@@ -16,7 +20,12 @@ use typst::syntax::package::PackageSpec;
 use tytanic_utils::result::io_not_found;
 use tytanic_utils::result::ResultEx;
 
+use crate::config::DocumentManifest;
 use crate::config::ProjectConfig;
+use crate::config::ProjectDefaults;
+use crate::config::DEFAULT_UNIT_TESTS_ROOT;
+use crate::dsl;
+use crate::hook::Hook;
 use crate::test::Id;
 use crate::TOOL_NAME;
 
@@ -29,6 +38,15 @@ pub use vcs::Vcs;
 /// automatically.
 pub const MANIFEST_FILE: &str = "typst.toml";
 
+/// The name of the document manifest file which is used to discover the
+/// project root of projects which are not packages, it is placed in the
+/// default test root.
+pub const DOCUMENT_MANIFEST_FILE: &str = "tytanic.toml";
+
+/// The name of the directory within the test root in which data is persisted
+/// across test runs.
+pub const CACHE_DIRECTORY: &str = ".tytanic-cache";
+
 /// Represents a "shallow" unloaded project, it contains the base paths required
 /// to load a project.
 #[derive(Debug, Clone)]
@@ -95,25 +113,53 @@ impl ShallowProject {
 
         Ok(Some(Self { root: project, vcs }))
     }
+
+    /// Attempt to discover the root of the repository containing a directory
+    /// and use it as the project root.
+    ///
+    /// This is used as a fallback for projects without a manifest, like
+    /// theses or reports, returns `None` if `dir` is not inside a repository.
+    #[tracing::instrument(skip(dir) fields(dir = ?dir.as_ref()), ret)]
+    pub fn discover_vcs_root<P: AsRef<Path>>(dir: P) -> Result<Option<Self>, io::Error> {
+        for dir in dir.as_ref().ancestors() {
+            if let Some(kind) = Vcs::exists_at(dir)? {
+                tracing::debug!(vcs = ?kind, root = ?dir, "found vcs");
+                return Ok(Some(Self {
+                    root: dir.to_path_buf(),
+                    vcs: Some(Vcs::new(dir.to_path_buf(), kind)),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
 }
 
 impl ShallowProject {
     /// Loads the manifest, configuration, and unit test template of a project.
+    ///
+    /// The document manifest is only loaded if there is no package manifest.
     #[tracing::instrument]
     pub fn load(self) -> Result<Project, LoadError> {
         let manifest = self.parse_manifest()?;
-        let config = manifest
-            .as_ref()
-            .map(|m| self.parse_config(m))
-            .transpose()?
-            .flatten()
-            .unwrap_or_default();
+        let document = match manifest {
+            Some(_) => None,
+            None => self.parse_document_manifest()?,
+        };
+
+        let config = match (&manifest, &document) {
+            (Some(manifest), _) => self.parse_config(manifest)?,
+            (None, Some(document)) => document.config.clone(),
+            (None, None) => None,
+        }
+        .unwrap_or_default();
 
         let unit_test_template = self.read_unit_test_template(&config)?;
 
         Ok(Project {
             base: self,
             manifest,
+            document,
             config,
             unit_test_template,
         })
@@ -136,6 +182,23 @@ impl ShallowProject {
         Ok(manifest)
     }
 
+    /// Parses the document manifest if it exists. Returns `None` if no
+    /// document manifest is found.
+    #[tracing::instrument]
+    pub fn parse_document_manifest(&self) -> Result<Option<DocumentManifest>, ManifestError> {
+        let manifest: Option<DocumentManifest> = fs::read_to_string(self.document_manifest_file())
+            .ignore(io_not_found)?
+            .as_deref()
+            .map(toml::from_str)
+            .transpose()?;
+
+        if let Some(manifest) = &manifest {
+            validate_document_manifest(manifest)?;
+        }
+
+        Ok(manifest)
+    }
+
     /// Parses the manifest config from the tool section. Returns `None` if no
     /// tool section found.
     #[tracing::instrument]
@@ -149,7 +212,8 @@ impl ShallowProject {
             .get(TOOL_NAME)
             .cloned()
             .map(ProjectConfig::deserialize)
-            .transpose()?;
+            .transpose()
+            .map_err(|err| self.locate_config_error(err))?;
 
         if let Some(config) = &config {
             validate_config(config)?;
@@ -158,6 +222,30 @@ impl ShallowProject {
         Ok(config)
     }
 
+    /// Re-parses the tool section of the manifest file to attach the location
+    /// of the error to the given config error, the tool section is parsed
+    /// from a value which doesn't retain its location in the manifest.
+    ///
+    /// Returns the given error if it couldn't be located.
+    fn locate_config_error(&self, error: toml::de::Error) -> toml::de::Error {
+        #[derive(Deserialize)]
+        struct Manifest {
+            #[serde(rename = "tool")]
+            _tool: Tool,
+        }
+
+        #[derive(Deserialize)]
+        struct Tool {
+            #[serde(rename = "tytanic")]
+            _config: ProjectConfig,
+        }
+
+        fs::read_to_string(self.manifest_file())
+            .ok()
+            .and_then(|content| toml::from_str::<Manifest>(&content).err())
+            .unwrap_or(error)
+    }
+
     /// Reads the project's unit test template if it exists. Returns `None` if
     /// no template was found.
     #[tracing::instrument]
@@ -186,6 +274,16 @@ impl ShallowProject {
         self.root.join(MANIFEST_FILE)
     }
 
+    /// Returns the path to the document manifest (`tests/tytanic.toml`).
+    ///
+    /// This is always in the default test root, as the test root may be
+    /// configured in the document manifest itself.
+    pub fn document_manifest_file(&self) -> PathBuf {
+        let mut path = self.root.join(DEFAULT_UNIT_TESTS_ROOT);
+        path.push(DOCUMENT_MANIFEST_FILE);
+        path
+    }
+
     /// Returns the path to the VCS root.
     ///
     /// The VCS root is used for properly handling non-persistent storage of
@@ -202,6 +300,7 @@ impl ShallowProject {
 pub struct Project {
     base: ShallowProject,
     manifest: Option<PackageManifest>,
+    document: Option<DocumentManifest>,
     config: ProjectConfig,
     unit_test_template: Option<String>,
 }
@@ -215,6 +314,7 @@ impl Project {
                 vcs: None,
             },
             manifest: None,
+            document: None,
             config: ProjectConfig::default(),
             unit_test_template: None,
         }
@@ -232,6 +332,12 @@ impl Project {
         self
     }
 
+    /// Attach a parsed document manifest to this project.
+    pub fn with_document(mut self, document: Option<DocumentManifest>) -> Self {
+        self.document = document;
+        self
+    }
+
     /// Attach a parsed project config to this project.
     pub fn with_config(mut self, config: ProjectConfig) -> Self {
         self.config = config;
@@ -251,6 +357,14 @@ impl Project {
             return Ok(true);
         }
 
+        if dir
+            .join(DEFAULT_UNIT_TESTS_ROOT)
+            .join(DOCUMENT_MANIFEST_FILE)
+            .try_exists()?
+        {
+            return Ok(true);
+        }
+
         Ok(false)
     }
 }
@@ -266,6 +380,22 @@ impl Project {
         self.manifest.as_ref()
     }
 
+    /// The parsed document manifest, this is only available for projects
+    /// without a package manifest.
+    pub fn document(&self) -> Option<&DocumentManifest> {
+        self.document.as_ref()
+    }
+
+    /// Returns the paths to the entrypoints of the document, this is empty for
+    /// projects without a document manifest.
+    pub fn document_entrypoints(&self) -> Vec<PathBuf> {
+        self.document
+            .iter()
+            .flat_map(|d| &d.document.entrypoints)
+            .map(|entrypoint| self.root().join(entrypoint))
+            .collect()
+    }
+
     /// A package spec for this package itself, this is used by template tests
     /// refer to themselves without attempting to download the package.
     pub fn package_spec(&self) -> Option<PackageSpec> {
@@ -324,6 +454,52 @@ impl Project {
             })
     }
 
+    /// Returns whether the given path, relative to the project root, is
+    /// excluded from the package bundle by the exclude patterns of the
+    /// manifest.
+    ///
+    /// A path is also excluded if any of its ancestors are excluded, patterns
+    /// which exclude the contents of a directory like `tests/**` are treated
+    /// as excluding the directory itself. Returns `false` if the project has
+    /// no manifest.
+    pub fn is_excluded<P: AsRef<Path>>(&self, path: P) -> bool {
+        let Some(manifest) = &self.manifest else {
+            return false;
+        };
+
+        let patterns: Vec<_> = manifest
+            .package
+            .exclude
+            .iter()
+            .filter_map(|pattern| {
+                let pattern = pattern.trim_start_matches("./").trim_start_matches('/');
+                let pattern = pattern.strip_suffix("/**").unwrap_or(pattern);
+                let pattern = pattern.trim_end_matches('/');
+
+                glob::Pattern::new(pattern)
+                    .inspect_err(|err| tracing::warn!(?pattern, ?err, "invalid exclude pattern"))
+                    .ok()
+            })
+            .collect();
+
+        path.as_ref()
+            .ancestors()
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+            .any(|ancestor| {
+                patterns
+                    .iter()
+                    .any(|pattern| pattern.matches_path(ancestor))
+            })
+    }
+
+    /// Returns the path to the cache directory, this is used to persist data
+    /// like test durations across test runs.
+    pub fn cache_dir(&self) -> PathBuf {
+        let mut dir = self.unit_tests_root();
+        dir.push(CACHE_DIRECTORY);
+        dir
+    }
+
     /// Returns the path to the unit test template, that is, the source template to
     /// use when generating new unit tests.
     pub fn unit_test_template_file(&self) -> PathBuf {
@@ -333,9 +509,11 @@ impl Project {
     }
 
     /// Create a path to the test directory for the given identifier.
+    ///
+    /// All instances of a parametrized test share the test directory.
     pub fn unit_test_dir(&self, id: &Id) -> PathBuf {
         let mut dir = self.unit_tests_root();
-        dir.extend(id.components());
+        dir.extend(id.without_params().components());
         dir
     }
 
@@ -354,9 +532,14 @@ impl Project {
     }
 
     /// Create a path to the reference directory for the given identifier.
+    ///
+    /// Instances of a parametrized test have their own sub directory named
+    /// after their parameters, this is also the case for the output and
+    /// difference directories.
     pub fn unit_test_ref_dir(&self, id: &Id) -> PathBuf {
         let mut dir = self.unit_test_dir(id);
         dir.push("ref");
+        dir.extend(id.params_str());
         dir
     }
 
@@ -364,6 +547,17 @@ impl Project {
     pub fn unit_test_out_dir(&self, id: &Id) -> PathBuf {
         let mut dir = self.unit_test_dir(id);
         dir.push("out");
+        dir.extend(id.params_str());
+        dir
+    }
+
+    /// Create a path to the mask directory for the given identifier.
+    ///
+    /// Unlike the reference directory, the mask directory is shared by all
+    /// instances of a parametrized test.
+    pub fn unit_test_mask_dir(&self, id: &Id) -> PathBuf {
+        let mut dir = self.unit_test_dir(id);
+        dir.push("mask");
         dir
     }
 
@@ -371,6 +565,7 @@ impl Project {
     pub fn unit_test_diff_dir(&self, id: &Id) -> PathBuf {
         let mut dir = self.unit_test_dir(id);
         dir.push("diff");
+        dir.extend(id.params_str());
         dir
     }
 }
@@ -419,10 +614,54 @@ fn validate_manifest(manifest: &PackageManifest) -> Result<(), ValidationError>
     Ok(())
 }
 
+fn validate_document_manifest(manifest: &DocumentManifest) -> Result<(), ValidationError> {
+    let DocumentManifest { document, config } = manifest;
+
+    let mut error = ValidationError {
+        errors: BTreeMap::new(),
+    };
+
+    if document.name.trim().is_empty() {
+        error
+            .errors
+            .insert("document.name".into(), ValidationErrorCause::EmptyName);
+    }
+
+    for (idx, entrypoint) in document.entrypoints.iter().enumerate() {
+        if !is_trivial_path(entrypoint) {
+            error.errors.insert(
+                eco_format!("document.entrypoints[{idx}]"),
+                ValidationErrorCause::NonTrivialPath,
+            );
+        }
+    }
+
+    // NOTE(tinger): The config keys are reported relative to the document
+    // manifest, not the config table.
+    if let Some(Err(config)) = config.as_ref().map(validate_config) {
+        error.errors.extend(
+            config
+                .errors
+                .into_iter()
+                .map(|(key, cause)| (eco_format!("config.{key}"), cause)),
+        );
+    }
+
+    if !error.errors.is_empty() {
+        return Err(error);
+    }
+
+    Ok(())
+}
+
 fn validate_config(config: &ProjectConfig) -> Result<(), ValidationError> {
     let ProjectConfig {
         unit_tests_root,
-        defaults: _,
+        defaults,
+        serial,
+        profiles,
+        test_sets,
+        hooks,
     } = config;
 
     let mut error = ValidationError {
@@ -435,6 +674,53 @@ fn validate_config(config: &ProjectConfig) -> Result<(), ValidationError> {
             .insert("tests".into(), ValidationErrorCause::NonTrivialPath);
     }
 
+    validate_defaults(defaults, "default", &mut error);
+    for (name, profile) in profiles {
+        let mut defaults = defaults.clone();
+        profile.apply_defaults(&mut defaults);
+        validate_defaults(&defaults, &eco_format!("profile.{name}"), &mut error);
+    }
+
+    for (idx, module) in serial.iter().enumerate() {
+        if !Id::is_valid(module) {
+            error.errors.insert(
+                eco_format!("serial[{idx}]"),
+                ValidationErrorCause::InvalidId,
+            );
+        }
+    }
+
+    for (name, expr) in test_sets {
+        if !dsl::is_valid_test_set_name(name) {
+            error.errors.insert(
+                eco_format!("test-sets.{name}"),
+                ValidationErrorCause::InvalidTestSetName,
+            );
+        } else if tytanic_filter::ast::parse(expr).is_err() {
+            error.errors.insert(
+                eco_format!("test-sets.{name}"),
+                ValidationErrorCause::InvalidTestSet,
+            );
+        }
+    }
+
+    for hook in [
+        Hook::Prepare,
+        Hook::PrepareEach,
+        Hook::CleanupEach,
+        Hook::Cleanup,
+    ] {
+        if hook
+            .command(hooks)
+            .is_some_and(|command| command.trim().is_empty())
+        {
+            error.errors.insert(
+                eco_format!("hooks.{hook}"),
+                ValidationErrorCause::EmptyCommand,
+            );
+        }
+    }
+
     if !error.errors.is_empty() {
         return Err(error);
     }
@@ -442,7 +728,28 @@ fn validate_config(config: &ProjectConfig) -> Result<(), ValidationError> {
     Ok(())
 }
 
-fn is_trivial_path<P: AsRef<Path>>(path: P) -> bool {
+/// Validates the values of the given defaults, errors are reported for the
+/// keys in the given table.
+fn validate_defaults(defaults: &ProjectDefaults, table: &str, error: &mut ValidationError) {
+    let mut check = |key: &str, valid: bool| {
+        // NOTE(tinger): Profiles inherit the defaults, invalid defaults are
+        // only reported once.
+        if !valid && !error.errors.contains_key(&eco_format!("default.{key}")) {
+            error.errors.insert(
+                eco_format!("{table}.{key}"),
+                ValidationErrorCause::OutOfRange,
+            );
+        }
+    };
+
+    check("ppi", defaults.ppi.is_finite() && defaults.ppi > 0.0);
+    check("threshold", (0.0..=1.0).contains(&defaults.threshold));
+    check("max-pages", defaults.max_pages != 0);
+    check("max-page-pixels", defaults.max_page_pixels != 0);
+}
+
+/// Whether the given path is relative and contains only normal components.
+pub(crate) fn is_trivial_path<P: AsRef<Path>>(path: P) -> bool {
     let path = path.as_ref();
     path.is_relative() && path.components().all(|c| matches!(c, Component::Normal(_)))
 }
@@ -477,6 +784,39 @@ pub enum ValidationErrorCause {
     /// A path was not trivial when it must be, i.e. it contained components
     /// such as `.` or `..`.
     NonTrivialPath,
+
+    /// A module was not a valid test identifier.
+    InvalidId,
+
+    /// A test set name was not a valid identifier or was the name of a built
+    /// in test set.
+    InvalidTestSetName,
+
+    /// A test set expression could not be parsed.
+    InvalidTestSet,
+
+    /// A number was outside of its valid range.
+    OutOfRange,
+
+    /// A hook command was empty.
+    EmptyCommand,
+
+    /// A document name was empty.
+    EmptyName,
+}
+
+impl Display for ValidationErrorCause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NonTrivialPath => "path must be relative and must not contain `.` or `..`",
+            Self::InvalidId => "not a valid test identifier",
+            Self::InvalidTestSetName => "not a valid identifier or the name of a built-in test set",
+            Self::InvalidTestSet => "not a valid test set expression",
+            Self::OutOfRange => "value is out of range",
+            Self::EmptyCommand => "command must not be empty",
+            Self::EmptyName => "name must not be empty",
+        })
+    }
 }
 
 /// Returned by [`ShallowProject::parse_config`].
@@ -513,10 +853,12 @@ pub enum ManifestError {
 
 #[cfg(test)]
 mod tests {
+    use tytanic_utils::typst::PackageInfoBuilder;
     use tytanic_utils::typst::PackageManifestBuilder;
     use tytanic_utils::typst::TemplateInfoBuilder;
 
     use super::*;
+    use crate::config::ProjectProfile;
 
     #[test]
     fn test_template_paths() {
@@ -580,6 +922,51 @@ mod tests {
             project.unit_test_diff_dir(&id),
             PathBuf::from_iter(["root", "foo", "a", "b", "diff"])
         );
+
+        let id = Id::new("a/b[x=1,y=2]").unwrap();
+        assert_eq!(
+            project.unit_test_script(&id),
+            PathBuf::from_iter(["root", "foo", "a", "b", "test.typ"])
+        );
+        assert_eq!(
+            project.unit_test_ref_dir(&id),
+            PathBuf::from_iter(["root", "foo", "a", "b", "ref", "x=1,y=2"])
+        );
+        assert_eq!(
+            project.unit_test_out_dir(&id),
+            PathBuf::from_iter(["root", "foo", "a", "b", "out", "x=1,y=2"])
+        );
+    }
+
+    #[test]
+    fn test_is_excluded() {
+        let project = Project::new("root");
+        assert!(!project.is_excluded("tests"));
+
+        for (patterns, excluded) in [
+            (&["tests"][..], true),
+            (&["/tests/"][..], true),
+            (&["./tests/**"][..], true),
+            (&["tests/*/ref"][..], false),
+            (&["*.png", "docs"][..], false),
+        ] {
+            let project = Project::new("root").with_manifest(Some(
+                PackageManifestBuilder::new()
+                    .package(
+                        PackageInfoBuilder::new()
+                            .exclude(patterns.iter().copied())
+                            .build(),
+                    )
+                    .build(),
+            ));
+
+            assert_eq!(project.is_excluded("tests"), excluded, "{patterns:?}");
+            assert_eq!(
+                project.is_excluded("tests/a/test.typ"),
+                excluded,
+                "{patterns:?}"
+            );
+        }
     }
 
     #[test]
@@ -634,4 +1021,58 @@ mod tests {
             &ValidationErrorCause::NonTrivialPath
         );
     }
+
+    #[test]
+    fn test_validation_test_sets() {
+        let config = ProjectConfig {
+            test_sets: BTreeMap::from([
+                ("smoke".into(), "tag(\"smoke\") | regex:^unit/".into()),
+                ("all".into(), "none()".into()),
+                ("1st".into(), "all()".into()),
+                ("broken".into(), "all() &".into()),
+            ]),
+            ..Default::default()
+        };
+
+        let config = validate_config(&config).unwrap_err();
+
+        assert_eq!(config.errors.len(), 3);
+        assert_eq!(
+            config.errors.get("test-sets.all").unwrap(),
+            &ValidationErrorCause::InvalidTestSetName
+        );
+        assert_eq!(
+            config.errors.get("test-sets.1st").unwrap(),
+            &ValidationErrorCause::InvalidTestSetName
+        );
+        assert_eq!(
+            config.errors.get("test-sets.broken").unwrap(),
+            &ValidationErrorCause::InvalidTestSet
+        );
+    }
+
+    #[test]
+    fn test_validation_defaults() {
+        let mut config = ProjectConfig::default();
+        config.defaults.ppi = 0.0;
+        config.profiles.insert(
+            "ci".into(),
+            ProjectProfile {
+                threshold: Some(1.5),
+                ..Default::default()
+            },
+        );
+
+        let config = validate_config(&config).unwrap_err();
+
+        assert_eq!(config.errors.len(), 2);
+        assert_eq!(
+            config.errors.get("default.ppi").unwrap(),
+            &ValidationErrorCause::OutOfRange
+        );
+        assert_eq!(
+            config.errors.get("profile.ci.threshold").unwrap(),
+            &ValidationErrorCause::OutOfRange
+        );
+    }
 }
@@ -4,6 +4,9 @@
 //! not managed by the VCS of the user. Currently supports `.gitignore` and
 //! `.hgignore` based VCS' as well as auto discovery of Git, Mercurial and
 //! Jujutsu through their hidden repository directories.
+//!
+//! The files which changed in the working copy are queried by running the
+//! command line tool of the VCS, see [`Vcs::changed_files`].
 
 use std::fmt;
 use std::fmt::Debug;
@@ -12,6 +15,7 @@ use std::fs;
 use std::io;
 use std::path::Path;
 use std::path::PathBuf;
+use std::process::Command;
 
 use super::Project;
 use crate::test::UnitTest;
@@ -114,6 +118,96 @@ impl Vcs {
         Ok(())
     }
 
+    /// Ignore the cache directory of the project.
+    #[tracing::instrument(skip(project))]
+    pub fn ignore_cache(&self, project: &Project) -> io::Result<()> {
+        let mut content = format!("{IGNORE_HEADER}\n\n");
+
+        let file = project.cache_dir().join(match self.kind {
+            Kind::Git => GITIGNORE_NAME,
+            Kind::Mercurial => {
+                content.push_str("syntax: glob\n");
+                HGIGNORE_NAME
+            }
+        });
+
+        content.push_str("**\n");
+        fs::write(file, content)?;
+
+        Ok(())
+    }
+
+    /// Returns the absolute paths of the files which differ from the given
+    /// revision, or from the last commit if none is given. This includes
+    /// staged, unstaged and untracked files, but not ignored files.
+    ///
+    /// This runs `git`, `jj` or `hg` in the root of the repository, Jujutsu
+    /// is only used if the repository is not colocated with Git.
+    #[tracing::instrument(ret)]
+    pub fn changed_files(&self, base: Option<&str>) -> io::Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+
+        match self.kind {
+            Kind::Git if !self.root.join(".git").exists() => {
+                let mut args = vec!["diff", "--name-only"];
+                if let Some(base) = base {
+                    args.extend(["--from", base]);
+                }
+                paths.extend(self.run("jj", &args)?);
+            }
+            Kind::Git => {
+                let git = |args: &[&str]| {
+                    self.run("git", &[&["-c", "core.quotePath=false"], args].concat())
+                };
+
+                // NOTE(tinger): A repository without commits has no `HEAD` to
+                // compare against, so all of its tracked files are new.
+                let has_head = git(&["rev-parse", "--verify", "--quiet", "HEAD"]).is_ok();
+
+                match base {
+                    Some(base) => paths.extend(git(&["diff", "--name-only", base, "--"])?),
+                    None if has_head => paths.extend(git(&["diff", "--name-only", "HEAD", "--"])?),
+                    None => paths.extend(git(&["ls-files", "--cached"])?),
+                }
+
+                paths.extend(git(&["ls-files", "--others", "--exclude-standard"])?);
+            }
+            Kind::Mercurial => {
+                let mut args = vec!["status", "--no-status"];
+                if let Some(base) = base {
+                    args.extend(["--rev", base]);
+                }
+                paths.extend(self.run("hg", &args)?);
+            }
+        }
+
+        Ok(paths.into_iter().map(|path| self.root.join(path)).collect())
+    }
+
+    /// Runs the given VCS command in the root of the repository and returns
+    /// the lines of its output.
+    fn run(&self, program: &str, args: &[&str]) -> io::Result<Vec<String>> {
+        let output = Command::new(program)
+            .args(args)
+            .current_dir(&self.root)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "`{program} {}` failed with {}: {}",
+                args.join(" "),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim(),
+            )));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(Into::into)
+            .collect())
+    }
+
     #[tracing::instrument(skip(project, test), fields(test = ?test.id()))]
     pub fn unignore(&self, project: &Project, test: &UnitTest) -> io::Result<()> {
         let file = project.unit_test_dir(test.id()).join(match self.kind {
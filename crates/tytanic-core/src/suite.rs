@@ -5,21 +5,33 @@
 use std::collections::btree_map;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
 use std::fs;
 use std::io;
+use std::mem;
 use std::path::Path;
+use std::path::PathBuf;
 use std::time::Duration;
 use std::time::Instant;
 
+use ecow::EcoString;
+use sha2::Digest;
+use sha2::Sha256;
 use thiserror::Error;
 use tytanic_filter::eval;
 use tytanic_filter::ExpressionFilter;
 use tytanic_utils::fmt::Term;
 use tytanic_utils::result::io_not_found;
 use tytanic_utils::result::ResultEx;
+use unicode_normalization::UnicodeNormalization;
 use uuid::Uuid;
 
+use crate::inputs;
+use crate::inputs::HashEntry;
 use crate::project::Project;
+use crate::test::matrix::MATRIX_FILE;
 use crate::test::unit::LoadError;
 use crate::test::Id;
 use crate::test::ParseIdError;
@@ -33,6 +45,8 @@ use crate::TemplateTest;
 pub struct Suite {
     tests: BTreeMap<Id, Test>,
     nested: BTreeMap<Id, Test>,
+    invalid: BTreeSet<PathBuf>,
+    collisions: Vec<BTreeSet<PathBuf>>,
 }
 
 impl Suite {
@@ -41,6 +55,8 @@ impl Suite {
         Self {
             tests: BTreeMap::new(),
             nested: BTreeMap::new(),
+            invalid: BTreeSet::new(),
+            collisions: Vec::new(),
         }
     }
 
@@ -61,6 +77,7 @@ impl Suite {
         };
 
         tracing::debug!(?root, "test root found, collecting top level entries");
+        let mut dirs = vec![];
         for entry in read_dir {
             let entry = entry?;
 
@@ -71,8 +88,10 @@ impl Suite {
                     .expect("entry must be in full");
 
                 this.collect_dir(project, rel)?;
+                dirs.push(rel.to_path_buf());
             }
         }
+        this.collect_collisions(dirs);
 
         let without_leafs: BTreeSet<_> = this
             .tests
@@ -113,21 +132,21 @@ impl Suite {
             return Ok(());
         }
 
-        let id = match Id::new_from_path(dir) {
-            Ok(id) => id,
+        tracing::trace!(?dir, "checking for test");
+        match Id::new_from_path(dir) {
+            Ok(id) => self.collect_test(project, id)?,
             Err(err) => {
-                tracing::error!(?dir, ?err, "ignoring test with invalid id");
-                return Ok(());
+                if abs.join("test.typ").try_exists()? {
+                    // NOTE(tinger): This includes directories using the
+                    // reserved prefix, like those colliding with `@template`.
+                    tracing::error!(?dir, ?err, "ignoring test with invalid id");
+                    self.invalid.insert(dir.to_path_buf());
+                }
             }
-        };
-
-        tracing::trace!(?dir, "checking for test");
-        if let Some(test) = UnitTest::load(project, id.clone())? {
-            tracing::debug!(id = %test.id(), "collected test");
-            self.tests.insert(id, Test::Unit(test));
         }
 
         tracing::trace!(?dir, "collecting sub directories");
+        let mut dirs = vec![];
         for entry in fs::read_dir(&abs)? {
             let entry = entry?;
 
@@ -138,6 +157,54 @@ impl Suite {
                     .expect("entry must be in full");
 
                 self.collect_dir(project, rel)?;
+                dirs.push(rel.to_path_buf());
+            }
+        }
+        self.collect_collisions(dirs);
+
+        Ok(())
+    }
+
+    /// Records groups of sibling directories whose names are equal when
+    /// ignoring case and Unicode normalization.
+    fn collect_collisions(&mut self, dirs: Vec<PathBuf>) {
+        let mut groups = BTreeMap::<String, BTreeSet<PathBuf>>::new();
+
+        for dir in dirs {
+            let Some(name) = dir.file_name() else {
+                continue;
+            };
+
+            let name = name.to_string_lossy();
+            if name.starts_with('.') {
+                continue;
+            }
+
+            groups.entry(collision_key(&name)).or_default().insert(dir);
+        }
+
+        for group in groups.into_values() {
+            if group.len() > 1 {
+                tracing::warn!(?group, "found colliding directories");
+                self.collisions.push(group);
+            }
+        }
+    }
+
+    /// Collect the test with the given id, if it exists.
+    fn collect_test(&mut self, project: &Project, id: Id) -> Result<(), Error> {
+        if let Some(test) = UnitTest::load(project, id.clone())? {
+            let matrix = test.load_matrix(project)?;
+
+            if matrix.is_empty() {
+                tracing::debug!(id = %test.id(), "collected test");
+                self.tests.insert(id, Test::Unit(test));
+            } else {
+                for params in matrix.combinations() {
+                    let test = test.with_params(params)?;
+                    tracing::debug!(id = %test.id(), "collected test instance");
+                    self.tests.insert(test.id().clone(), Test::Unit(test));
+                }
             }
         }
 
@@ -174,6 +241,23 @@ impl Suite {
         &self.nested
     }
 
+    /// The directories of tests which were ignored because their path is not
+    /// a valid [`Id`], relative to the test root.
+    pub fn invalid(&self) -> &BTreeSet<PathBuf> {
+        &self.invalid
+    }
+
+    /// Groups of directories in the test root which have the same parent and
+    /// whose names differ only by case or Unicode normalization, relative to
+    /// the test root.
+    ///
+    /// Only one directory of each group can be checked out on case or
+    /// normalization insensitive file systems, like those of Windows or
+    /// macOS.
+    pub fn collisions(&self) -> &[BTreeSet<PathBuf>] {
+        &self.collisions
+    }
+
     /// Returns the test with the given id.
     pub fn get(&self, id: &Id) -> Option<&Test> {
         self.tests.get(id)
@@ -198,6 +282,27 @@ impl Suite {
 impl Suite {
     /// Apply a filter to a suite.
     pub fn filter(self, filter: Filter) -> Result<FilteredSuite, FilterError> {
+        self.filter_with(filter, None)
+    }
+
+    /// Apply a filter to a suite, the membership of tests which didn't change
+    /// since the last time the filter was applied with the given cache is
+    /// not evaluated again.
+    ///
+    /// See [`FilterCache`] for when a cache can be shared.
+    pub fn filter_cached(
+        self,
+        filter: Filter,
+        cache: &mut FilterCache,
+    ) -> Result<FilteredSuite, FilterError> {
+        self.filter_with(filter, Some(cache))
+    }
+
+    fn filter_with(
+        self,
+        filter: Filter,
+        mut cache: Option<&mut FilterCache>,
+    ) -> Result<FilteredSuite, FilterError> {
         tracing::warn!(
             "ignoring {} nested tests while filtering",
             self.nested.len()
@@ -208,8 +313,17 @@ impl Suite {
 
         match &filter {
             Filter::TestSet(expr) => {
+                if let Some(cache) = &mut cache {
+                    cache.prepare(expr.input(), &self);
+                }
+
                 for (id, test) in &self.tests {
-                    if expr.contains(test)? {
+                    let contained = match &mut cache {
+                        Some(cache) => cache.contains(expr, test)?,
+                        None => expr.contains(test)?,
+                    };
+
+                    if contained {
                         matched.tests.insert(id.clone(), test.clone());
                     } else {
                         filtered.tests.insert(id.clone(), test.clone());
@@ -319,6 +433,155 @@ pub enum Filter {
     Explicit(BTreeSet<Id>),
 }
 
+/// A cache of the test set membership of the tests in a suite, this is used
+/// to avoid evaluating test sets again when the same filter is applied
+/// repeatedly, like in watch mode.
+///
+/// A test's membership is reused as long as the test set expression and the
+/// test itself are unchanged. A cache must therefore only be used with filters
+/// created from the same evaluation context, not with filters which were
+/// derived from another filter of the same expression, or whose test set
+/// depends on the state of the project on disk at the time it is applied.
+#[derive(Debug, Default, Clone)]
+pub struct FilterCache {
+    expression: EcoString,
+    tests: BTreeMap<Id, (Test, bool)>,
+}
+
+impl FilterCache {
+    /// Creates a new empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forgets all cached memberships if the expression changed and forgets
+    /// the memberships of tests which are no longer in the given suite.
+    fn prepare(&mut self, expression: &str, suite: &Suite) {
+        if self.expression != expression {
+            self.expression = expression.into();
+            self.tests.clear();
+        }
+
+        self.tests.retain(|id, _| suite.contains(id));
+    }
+
+    /// Whether the given test is contained in the given test set, this is
+    /// only evaluated if the test changed since it was last cached.
+    fn contains(
+        &mut self,
+        expr: &ExpressionFilter<Test>,
+        test: &Test,
+    ) -> Result<bool, eval::Error> {
+        if let Some((cached, contained)) = self.tests.get(test.id()) {
+            if cached == test {
+                return Ok(*contained);
+            }
+        }
+
+        let contained = expr.contains(test)?;
+        self.tests
+            .insert(test.id().clone(), (test.clone(), contained));
+
+        Ok(contained)
+    }
+
+    /// Forgets the cached memberships of all tests for which the given
+    /// predicate returns `false`.
+    pub fn retain<F: FnMut(&Id) -> bool>(&mut self, mut f: F) {
+        self.tests.retain(|id, _| f(id));
+    }
+
+    /// The number of tests whose membership is cached.
+    pub fn len(&self) -> usize {
+        self.tests.len()
+    }
+
+    /// Whether no memberships are cached.
+    pub fn is_empty(&self) -> bool {
+        self.tests.is_empty()
+    }
+}
+
+/// A cheap fingerprint of the tests of a project, this is a hex encoded
+/// SHA-256 hash of everything [`Suite::collect`] depends on.
+///
+/// This includes the manifest, the project config and the paths of all files
+/// and directories in the unit test root, but only the contents of test
+/// scripts and matrix files, references and temporary directories are not
+/// read. If the fingerprint of a project didn't change, collecting its tests
+/// again results in the same suite.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fingerprint(String);
+
+impl Fingerprint {
+    /// Computes the fingerprint of the given project's tests.
+    #[tracing::instrument(skip_all)]
+    pub fn compute(project: &Project) -> io::Result<Self> {
+        let mut hasher = Sha256::new();
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        hasher.update([0]);
+
+        if let Some(manifest) = fs::read(project.manifest_file()).ignore(io_not_found)? {
+            hasher.update(manifest);
+        }
+        hasher.update([0]);
+
+        if let Some(manifest) = fs::read(project.document_manifest_file()).ignore(io_not_found)? {
+            hasher.update(manifest);
+        }
+        hasher.update([0]);
+
+        // NOTE(tinger): The config is not necessarily read from the manifest.
+        hasher.update(format!("{:?}", project.config()));
+        hasher.update([0]);
+
+        let root = project.unit_tests_root();
+
+        // NOTE(tinger): Only the temporary directories of actual tests are
+        // skipped, a directory called `out` may still contain tests.
+        let is_temporary = |path: &Path| -> io::Result<bool> {
+            let Some(dir) = path.parent() else {
+                return Ok(false);
+            };
+
+            match Id::new_from_path(dir.strip_prefix(&root).unwrap_or(dir)) {
+                Ok(id)
+                    if path == project.unit_test_out_dir(&id)
+                        || path == project.unit_test_diff_dir(&id) =>
+                {
+                    dir.join("test.typ").try_exists()
+                }
+                _ => Ok(false),
+            }
+        };
+
+        inputs::hash_dir(&mut hasher, &root, &root, &mut |path| {
+            let name = path.file_name().unwrap_or_default();
+
+            Ok(if is_temporary(path)? {
+                HashEntry::Skip
+            } else if name == "test.typ" || name == MATRIX_FILE {
+                HashEntry::Contents
+            } else {
+                HashEntry::Path
+            })
+        })?;
+
+        Ok(Self(inputs::to_hex(hasher)))
+    }
+
+    /// The hex encoded hash.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Fingerprint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
 /// A suite of tests with a filter applied to it.
 #[derive(Debug, Clone)]
 pub struct FilteredSuite {
@@ -348,6 +611,18 @@ impl FilteredSuite {
     pub fn filtered(&self) -> &Suite {
         &self.filtered
     }
+
+    /// Filters out the matched tests for which the given predicate returns
+    /// `false` in addition to the filter.
+    pub fn retain<F: FnMut(&Test) -> bool>(&mut self, mut f: F) {
+        let (matched, filtered): (BTreeMap<_, _>, BTreeMap<_, _>) =
+            mem::take(&mut self.matched.tests)
+                .into_iter()
+                .partition(|(_, test)| f(test));
+
+        self.matched.tests = matched;
+        self.filtered.tests.extend(filtered);
+    }
 }
 
 /// Returned by [`Suite::filter`].
@@ -382,6 +657,12 @@ pub enum Error {
     Io(#[from] io::Error),
 }
 
+/// Returns the key under which two directory names are considered the same on
+/// case and normalization insensitive file systems.
+fn collision_key(name: &str) -> String {
+    name.nfc().collect::<String>().to_lowercase()
+}
+
 /// The result of a test suite run, this contains results for all tests in a
 /// suite, including filtered and not-yet-run tests, as well as cached values
 /// for the number of filtered, passed and failed tests.
@@ -394,7 +675,9 @@ pub struct SuiteResult {
     failed: usize,
     timestamp: Instant,
     duration: Duration,
+    estimate: Option<Duration>,
     results: BTreeMap<Id, TestResult>,
+    meta: BTreeMap<EcoString, EcoString>,
 }
 
 impl SuiteResult {
@@ -410,6 +693,7 @@ impl SuiteResult {
             failed: 0,
             timestamp: Instant::now(),
             duration: Duration::ZERO,
+            estimate: None,
             results: suite
                 .matched()
                 .tests()
@@ -421,6 +705,7 @@ impl SuiteResult {
                         .map(|test| (test.id().clone(), TestResult::filtered())),
                 )
                 .collect(),
+            meta: BTreeMap::new(),
         }
     }
 }
@@ -478,6 +763,15 @@ impl SuiteResult {
         self.duration
     }
 
+    /// The estimated duration of the suite run, if it was set.
+    ///
+    /// See [`History::estimate`].
+    ///
+    /// [`History::estimate`]: crate::history::History::estimate
+    pub fn estimate(&self) -> Option<Duration> {
+        self.estimate
+    }
+
     /// The individual test results.
     ///
     /// This contains results for all tests in the a suite, not just those added
@@ -486,10 +780,53 @@ impl SuiteResult {
         &self.results
     }
 
+    /// The total number of warnings emitted by all tests in the suite.
+    pub fn warnings(&self) -> usize {
+        self.results
+            .values()
+            .map(|test| test.warnings().len())
+            .sum()
+    }
+
+    /// The run-level metadata attached to this result.
+    ///
+    /// This is not interpreted by the runner, but can be used to correlate a
+    /// run with external information like a CI job or a commit.
+    pub fn meta(&self) -> &BTreeMap<EcoString, EcoString> {
+        &self.meta
+    }
+
     /// Whether this suite can be considered a complete pass.
     pub fn is_complete_pass(&self) -> bool {
         self.expected() == self.passed()
     }
+
+    /// The results of the tests grouped by their top-level test directory,
+    /// i.e. the first component of their id, filtered tests are not included.
+    pub fn modules(&self) -> BTreeMap<&str, GroupResult> {
+        let mut groups = BTreeMap::<_, GroupResult>::new();
+
+        for (id, test) in &self.results {
+            if test.is_filtered() {
+                continue;
+            }
+
+            let module = id.components().next().expect("id is always non-empty");
+            let group = groups.entry(module).or_default();
+
+            if test.is_pass() {
+                group.passed += 1;
+            } else if test.is_fail() {
+                group.failed += 1;
+            } else {
+                group.skipped += 1;
+            }
+
+            group.duration += test.duration();
+        }
+
+        groups
+    }
 }
 
 impl SuiteResult {
@@ -506,33 +843,122 @@ impl SuiteResult {
         self.duration = self.timestamp.elapsed();
     }
 
+    /// Sets the estimated duration of the suite run.
+    pub fn set_estimate(&mut self, estimate: Option<Duration>) {
+        self.estimate = estimate;
+    }
+
     /// Add a test result.
     ///
     /// - This should only add results for each test once, otherwise the test
     ///   will be counted multiple times.
-    /// - The results should also only contain failures or passes, cancellations
-    ///   and filtered results are ignored, as these are pre-filled when the
-    ///   result is constructed.
+    /// - The results should also only contain failures, passes or cancelled
+    ///   tests, skipped and filtered results are ignored, as these are
+    ///   pre-filled when the result is constructed.
+    /// - Cancelled tests are neither counted as passed nor failed, they are
+    ///   counted as skipped.
     pub fn set_test_result(&mut self, id: Id, result: TestResult) {
         debug_assert!(self.results.contains_key(&id));
-        debug_assert!(result.is_pass() || result.is_fail());
+        debug_assert!(result.is_pass() || result.is_fail() || result.is_cancelled());
 
         if result.is_pass() {
             self.passed += 1;
-        } else {
+        } else if result.is_fail() {
             self.failed += 1;
         }
 
         self.results.insert(id, result);
     }
+
+    /// Attach a metadata entry to this result, returns the old value if there
+    /// was one.
+    pub fn set_meta<K, V>(&mut self, key: K, value: V) -> Option<EcoString>
+    where
+        K: Into<EcoString>,
+        V: Into<EcoString>,
+    {
+        self.meta.insert(key.into(), value.into())
+    }
+}
+
+/// The summarized results of a group of tests, see [`SuiteResult::modules`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GroupResult {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+    duration: Duration,
+}
+
+impl GroupResult {
+    /// The number of tests in this group which passed.
+    pub fn passed(&self) -> usize {
+        self.passed
+    }
+
+    /// The number of tests in this group which failed.
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// The number of tests in this group which were not run.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// The sum of the durations of the tests in this group.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// The status of this group rolled up from its tests, a group fails if
+    /// any of its tests failed and is skipped if none of its tests were run.
+    pub fn status(&self) -> GroupStatus {
+        if self.failed != 0 {
+            GroupStatus::Failed
+        } else if self.passed != 0 {
+            GroupStatus::Passed
+        } else {
+            GroupStatus::Skipped
+        }
+    }
+}
+
+/// The rolled up status of a [`GroupResult`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GroupStatus {
+    /// At least one test passed and none failed.
+    Passed,
+
+    /// At least one test failed.
+    Failed,
+
+    /// No test was run.
+    Skipped,
+}
+
+impl GroupStatus {
+    /// The name of this status as used in reports.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Passed => "pass",
+            Self::Failed => "fail",
+            Self::Skipped => "skip",
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
     use ecow::eco_vec;
     use tytanic_utils::fs::TempTestEnv;
 
     use super::*;
+    use crate::dsl;
     use crate::test::unit::Kind;
     use crate::test::Annotation;
 
@@ -579,6 +1005,40 @@ mod tests {
                     assert_eq!(test.annotations(), &annotations[..]);
                     assert_eq!(test.kind(), kind);
                 }
+
+                assert_eq!(
+                    suite.invalid.iter().collect::<Vec<_>>(),
+                    [Path::new("ignored!")]
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/a/test.typ", "Hello World")
+                    .setup_file("tests/b/test.typ", "Hello World")
+                    .setup_file("tests/b/ref.typ", "Hello World")
+            },
+            |root| {
+                let project = Project::new(root);
+                let fingerprint = || Fingerprint::compute(&project).unwrap();
+                let original = fingerprint();
+
+                // temporary directories and references are not part of it
+                std::fs::create_dir_all(root.join("tests/a/out")).unwrap();
+                std::fs::write(root.join("tests/a/out/1.png"), "").unwrap();
+                std::fs::write(root.join("tests/b/ref.typ"), "Bye World").unwrap();
+                assert_eq!(fingerprint(), original);
+
+                std::fs::write(root.join("tests/a/test.typ"), "/// [skip]").unwrap();
+                let changed = fingerprint();
+                assert_ne!(changed, original);
+
+                std::fs::remove_file(root.join("tests/b/ref.typ")).unwrap();
+                assert_ne!(fingerprint(), changed);
             },
         );
     }
@@ -604,4 +1064,105 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_collect_collisions() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/Foo/test.typ", "Hello World")
+                    .setup_file("tests/foo/test.typ", "Hello World")
+                    .setup_file("tests/bar/caf\u{e9}/test.typ", "Hello World")
+                    .setup_file("tests/bar/cafe\u{301}/test.typ", "Hello World")
+                    .setup_file("tests/bar/qux/test.typ", "Hello World")
+            },
+            |root| {
+                let project = Project::new(root);
+                let suite = Suite::collect(&project).unwrap();
+
+                assert_eq!(
+                    suite.collisions(),
+                    [
+                        BTreeSet::from([
+                            PathBuf::from("bar/caf\u{e9}"),
+                            PathBuf::from("bar/cafe\u{301}"),
+                        ]),
+                        BTreeSet::from([PathBuf::from("Foo"), PathBuf::from("foo")]),
+                    ]
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_collect_matrix() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/file/test.typ", "Hello World")
+                    .setup_file("tests/file/params.toml", "a = [1, 2]")
+                    .setup_file("tests/annot/test.typ", "/// [matrix: b = [\"x\"]]\nHello")
+                    .setup_file("tests/annot/params.toml", "a = 1")
+            },
+            |root| {
+                let project = Project::new(root);
+                let suite = Suite::collect(&project).unwrap();
+
+                assert_eq!(
+                    suite.tests.keys().map(Id::as_str).collect::<Vec<_>>(),
+                    ["annot[a=1,b=x]", "file[a=1]", "file[a=2]"]
+                );
+            },
+        );
+    }
+    #[test]
+    fn test_filter_cached() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("tests/a/test.typ", "Hello")
+                    .setup_file("tests/b/test.typ", "/// [skip]\nHello")
+            },
+            |root| {
+                let project = Project::new(root);
+                let evaluated = Arc::new(AtomicUsize::new(0));
+
+                let mut ctx = dsl::context();
+                {
+                    let evaluated = Arc::clone(&evaluated);
+                    ctx.bind(
+                        tytanic_filter::ast::Id("counted".into()),
+                        eval::Set::new(move |_, _: &Test| {
+                            evaluated.fetch_add(1, Ordering::Relaxed);
+                            Ok(true)
+                        }),
+                    );
+                }
+
+                let filter =
+                    Filter::TestSet(ExpressionFilter::new(ctx, "counted ~ skip()").unwrap());
+                let mut cache = FilterCache::new();
+
+                let run = |cache: &mut FilterCache| {
+                    let suite = Suite::collect(&project).unwrap();
+                    let suite = suite.filter_cached(filter.clone(), cache).unwrap();
+                    suite
+                        .matched()
+                        .tests
+                        .keys()
+                        .map(Id::as_str)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                };
+
+                assert_eq!(run(&mut cache), "a");
+                assert_eq!(evaluated.load(Ordering::Relaxed), 2);
+
+                assert_eq!(run(&mut cache), "a");
+                assert_eq!(evaluated.load(Ordering::Relaxed), 2);
+
+                // Only the changed test is evaluated again.
+                fs::write(root.join("tests/a/test.typ"), "/// [skip]\nHello").unwrap();
+                assert_eq!(run(&mut cache), "");
+                assert_eq!(evaluated.load(Ordering::Relaxed), 3);
+            },
+        );
+    }
 }
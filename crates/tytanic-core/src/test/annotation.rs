@@ -20,12 +20,19 @@
 //! ```
 
 use std::str::FromStr;
+use std::time::Duration;
 
 use ecow::EcoString;
 use ecow::EcoVec;
 use thiserror::Error;
+use tytanic_utils::time;
 
+use super::matrix::Matrix;
 use crate::config::Direction;
+use crate::doc::compare::Region;
+use crate::doc::compare::DEFAULT_SAMPLE_SIZE;
+use crate::doc::text::DEFAULT_TOLERANCE;
+use crate::project::is_trivial_path;
 
 /// An error which may occur while parsing an annotation.
 #[derive(Debug, Error)]
@@ -62,6 +69,9 @@ pub enum Annotation {
     /// The skip annotation, this adds a test to the built in `skip` test set.
     Skip,
 
+    /// The tags of a test, these are used by the built in `tag` test set.
+    Tag(EcoVec<EcoString>),
+
     /// The direction to use for diffing the documents.
     Dir(Direction),
 
@@ -73,9 +83,93 @@ pub enum Annotation {
 
     /// The maximum allowed amount of deviations to use for comparison.
     MaxDeviations(usize),
+
+    /// The maximum allowed difference in page width or height in pixels to
+    /// use for comparison.
+    MaxSizeDelta(u32),
+
+    /// The parameter matrix to expand this test into multiple instances with.
+    Matrix(Matrix),
+
+    /// The time after which compilation of this test is aborted.
+    Timeout(Duration),
+
+    /// The test is expected to fail, with an optional reason.
+    XFail(Option<EcoString>),
+
+    /// A region of the pages which is excluded from comparison.
+    Mask(Region),
+
+    /// Compare using the structural similarity with an optional threshold.
+    Ssim(Option<f32>),
+
+    /// Compare pages larger than the given number of pixels on a sample of
+    /// their pixels.
+    Sample(u64),
+
+    /// Compare the text layers of the documents instead of their pixels, with
+    /// the tolerance in pt by which the position of text runs may differ.
+    CompareText(f64),
+
+    /// Compare the structure of the documents as it ends up in a PDF export
+    /// instead of their pixels, with the tolerance in pt by which the
+    /// position of text runs may differ, see [`doc::pdf::compare`].
+    ///
+    /// [`doc::pdf::compare`]: crate::doc::pdf::compare
+    ComparePdf(f64),
+
+    /// A document entrypoint relative to the project root which is compiled
+    /// instead of the test script.
+    Entrypoint(EcoString),
+
+    /// An input available as `sys.inputs` with its key and value, parameters
+    /// of a matrix take precedence.
+    Input(EcoString, EcoString),
 }
 
 impl Annotation {
+    /// The identifiers of all annotations.
+    pub const NAMES: &'static [&'static str] = &[
+        "skip",
+        "tag",
+        "dir",
+        "ppi",
+        "max-delta",
+        "max-deviations",
+        "max-size-delta",
+        "matrix",
+        "timeout",
+        "xfail",
+        "mask",
+        "ssim",
+        "sample",
+        "compare",
+        "entrypoint",
+        "input",
+    ];
+
+    /// The identifier of this annotation, this is one of [`Annotation::NAMES`].
+    pub fn name(&self) -> &'static str {
+        match self {
+            Annotation::Skip => "skip",
+            Annotation::Tag(_) => "tag",
+            Annotation::Dir(_) => "dir",
+            Annotation::Ppi(_) => "ppi",
+            Annotation::MaxDelta(_) => "max-delta",
+            Annotation::MaxDeviations(_) => "max-deviations",
+            Annotation::MaxSizeDelta(_) => "max-size-delta",
+            Annotation::Matrix(_) => "matrix",
+            Annotation::Timeout(_) => "timeout",
+            Annotation::XFail(_) => "xfail",
+            Annotation::Mask(_) => "mask",
+            Annotation::Ssim(_) => "ssim",
+            Annotation::Sample(_) => "sample",
+            Annotation::CompareText(_) | Annotation::ComparePdf(_) => "compare",
+            Annotation::Entrypoint(_) => "entrypoint",
+            Annotation::Input(_, _) => "input",
+        }
+    }
+
     /// Collects all annotations found within a test's source code.
     pub fn collect(source: &str) -> Result<EcoVec<Self>, ParseAnnotationError> {
         // Skip regular comments and leading empty lines.
@@ -96,6 +190,15 @@ impl Annotation {
 
         lines.map(str::parse).collect()
     }
+
+    /// Whether the given string is a valid tag, i.e. a non-empty string of
+    /// alphanumeric characters, `-` and `_`.
+    pub fn is_valid_tag(tag: &str) -> bool {
+        !tag.is_empty()
+            && tag
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    }
 }
 
 impl FromStr for Annotation {
@@ -123,6 +226,23 @@ impl FromStr for Annotation {
                     Ok(Annotation::Skip)
                 }
             }
+            "tag" => match arg.filter(|arg| !arg.is_empty()) {
+                Some(arg) => {
+                    let tags: EcoVec<EcoString> =
+                        arg.split(',').map(|tag| tag.trim().into()).collect();
+
+                    match tags.iter().find(|tag| !Annotation::is_valid_tag(tag)) {
+                        Some(tag) => Err(ParseAnnotationError::Other(
+                            format!(
+                                "invalid tag {tag:?}, expected alphanumeric characters, `-` or `_`"
+                            )
+                            .into(),
+                        )),
+                        None => Ok(Annotation::Tag(tags)),
+                    }
+                }
+                None => Err(ParseAnnotationError::MissingArg("tag")),
+            },
             "dir" => match arg {
                 Some(arg) => match arg.trim() {
                     "ltr" => Ok(Annotation::Dir(Direction::Ltr)),
@@ -154,6 +274,105 @@ impl FromStr for Annotation {
                 },
                 None => Err(ParseAnnotationError::MissingArg("max-deviations")),
             },
+            "max-size-delta" => match arg {
+                Some(arg) => match arg.trim().parse() {
+                    Ok(arg) => Ok(Annotation::MaxSizeDelta(arg)),
+                    Err(err) => Err(ParseAnnotationError::Other(err.into())),
+                },
+                None => Err(ParseAnnotationError::MissingArg("max-size-delta")),
+            },
+            "matrix" => match arg {
+                Some(arg) => match arg.parse() {
+                    Ok(arg) => Ok(Annotation::Matrix(arg)),
+                    Err(err) => Err(ParseAnnotationError::Other(Box::new(err))),
+                },
+                None => Err(ParseAnnotationError::MissingArg("matrix")),
+            },
+            "xfail" => Ok(Annotation::XFail(
+                arg.filter(|arg| !arg.is_empty()).map(EcoString::from),
+            )),
+            "mask" => match arg {
+                Some(arg) => match arg.parse() {
+                    Ok(arg) => Ok(Annotation::Mask(arg)),
+                    Err(err) => Err(ParseAnnotationError::Other(Box::new(err))),
+                },
+                None => Err(ParseAnnotationError::MissingArg("mask")),
+            },
+            "ssim" => match arg.filter(|arg| !arg.is_empty()) {
+                Some(arg) => match arg.parse() {
+                    Ok(arg) if (0.0..=1.0).contains(&arg) => Ok(Annotation::Ssim(Some(arg))),
+                    Ok(_) => Err(ParseAnnotationError::Other(
+                        format!("invalid threshold {arg:?}, expected a value between 0 and 1")
+                            .into(),
+                    )),
+                    Err(err) => Err(ParseAnnotationError::Other(Box::new(err))),
+                },
+                None => Ok(Annotation::Ssim(None)),
+            },
+            "sample" => match arg.filter(|arg| !arg.is_empty()) {
+                Some(arg) => match arg.replace('_', "").parse() {
+                    Ok(arg) if arg > 0 => Ok(Annotation::Sample(arg)),
+                    Ok(_) => Err(ParseAnnotationError::Other(
+                        "invalid sample size 0, expected a positive number of pixels".into(),
+                    )),
+                    Err(err) => Err(ParseAnnotationError::Other(Box::new(err))),
+                },
+                None => Ok(Annotation::Sample(DEFAULT_SAMPLE_SIZE)),
+            },
+            "compare" => match arg.map(|arg| arg.split_once(',').unwrap_or((arg, ""))) {
+                Some((mode, tolerance)) if matches!(mode.trim(), "text" | "pdf") => {
+                    let annot = match mode.trim() {
+                        "text" => Annotation::CompareText,
+                        _ => Annotation::ComparePdf,
+                    };
+
+                    match tolerance.trim().trim_end_matches("pt").trim() {
+                        "" => Ok(annot(DEFAULT_TOLERANCE)),
+                        tolerance => match tolerance.parse() {
+                            Ok(tolerance) if tolerance >= 0.0 => Ok(annot(tolerance)),
+                            _ => Err(ParseAnnotationError::Other(
+                                format!("invalid tolerance {tolerance:?}, expected a length in pt")
+                                    .into(),
+                            )),
+                        },
+                    }
+                }
+                Some((mode, _)) => Err(ParseAnnotationError::Other(
+                    format!("invalid comparison mode {mode:?}, expected text or pdf").into(),
+                )),
+                None => Err(ParseAnnotationError::MissingArg("compare")),
+            },
+            "entrypoint" => match arg.filter(|arg| !arg.is_empty()) {
+                Some(arg) if is_trivial_path(arg) => Ok(Annotation::Entrypoint(arg.into())),
+                Some(arg) => Err(ParseAnnotationError::Other(
+                    format!(
+                        "invalid entrypoint {arg:?}, expected a path relative to the project root"
+                    )
+                    .into(),
+                )),
+                None => Err(ParseAnnotationError::MissingArg("entrypoint")),
+            },
+            "input" => match arg.filter(|arg| !arg.is_empty()) {
+                Some(arg) => match arg.split_once('=') {
+                    Some((key, value)) if !key.trim().is_empty() => {
+                        Ok(Annotation::Input(key.trim().into(), value.trim().into()))
+                    }
+                    _ => Err(ParseAnnotationError::Other(
+                        format!("invalid input {arg:?}, expected `key=value`").into(),
+                    )),
+                },
+                None => Err(ParseAnnotationError::MissingArg("input")),
+            },
+            "timeout" => match arg {
+                Some(arg) => match time::parse_duration(arg) {
+                    Some(arg) => Ok(Annotation::Timeout(arg)),
+                    None => Err(ParseAnnotationError::Other(
+                        format!("invalid duration {arg:?}, expected a duration like 30s or 2m")
+                            .into(),
+                    )),
+                },
+                None => Err(ParseAnnotationError::MissingArg("timeout")),
+            },
             _ => Err(ParseAnnotationError::Unknown(id.into())),
         }
     }
@@ -161,8 +380,27 @@ impl FromStr for Annotation {
 
 #[cfg(test)]
 mod tests {
+    use ecow::eco_vec;
+
     use super::*;
 
+    #[test]
+    fn test_annotation_name() {
+        for (source, name) in [
+            ("[skip]", "skip"),
+            ("[tag: slow]", "tag"),
+            ("[max-delta: 1]", "max-delta"),
+            ("[compare: text]", "compare"),
+            ("[compare: pdf]", "compare"),
+            ("[entrypoint: main.typ]", "entrypoint"),
+            ("[input: lang=en]", "input"),
+        ] {
+            let annot = Annotation::from_str(source).unwrap();
+            assert_eq!(annot.name(), name);
+            assert!(Annotation::NAMES.contains(&name));
+        }
+    }
+
     #[test]
     fn test_annotation_from_str() {
         assert_eq!(Annotation::from_str("[skip]").unwrap(), Annotation::Skip);
@@ -194,6 +432,104 @@ mod tests {
             Annotation::from_str("[ppi: 42.5]").unwrap(),
             Annotation::Ppi(42.5)
         );
+        assert_eq!(
+            Annotation::from_str("[max-size-delta: 1]").unwrap(),
+            Annotation::MaxSizeDelta(1)
+        );
+        assert_eq!(
+            Annotation::from_str("[tag: slow, pdf]").unwrap(),
+            Annotation::Tag(eco_vec!["slow".into(), "pdf".into()])
+        );
+        assert!(Annotation::from_str("[tag]").is_err());
+        assert!(Annotation::from_str("[tag: slow,]").is_err());
+        assert!(Annotation::from_str("[tag: very slow]").is_err());
+        assert_eq!(
+            Annotation::from_str("[sample]").unwrap(),
+            Annotation::Sample(DEFAULT_SAMPLE_SIZE)
+        );
+        assert_eq!(
+            Annotation::from_str("[sample: 250_000]").unwrap(),
+            Annotation::Sample(250_000)
+        );
+        assert!(Annotation::from_str("[sample: 0]").is_err());
+        assert_eq!(
+            Annotation::from_str("[matrix: a = [1, 2]]").unwrap(),
+            Annotation::Matrix(Matrix::parse_toml("a = [1, 2]").unwrap())
+        );
+        assert_eq!(
+            Annotation::from_str("[timeout: 1m30s]").unwrap(),
+            Annotation::Timeout(Duration::from_secs(90))
+        );
+        assert!(Annotation::from_str("[timeout: soon]").is_err());
+        assert_eq!(
+            Annotation::from_str("[entrypoint: chapters/main.typ]").unwrap(),
+            Annotation::Entrypoint("chapters/main.typ".into())
+        );
+        assert!(Annotation::from_str("[entrypoint: ../main.typ]").is_err());
+        assert!(Annotation::from_str("[entrypoint: /main.typ]").is_err());
+        assert_eq!(
+            Annotation::from_str("[input: draft = false]").unwrap(),
+            Annotation::Input("draft".into(), "false".into())
+        );
+        assert!(Annotation::from_str("[input: draft]").is_err());
+        assert!(Annotation::from_str("[input: =false]").is_err());
+        assert_eq!(
+            Annotation::from_str("[mask: 10, 20, 30, 40]").unwrap(),
+            Annotation::Mask(Region {
+                page: None,
+                x: 10,
+                y: 20,
+                width: 30,
+                height: 40,
+            })
+        );
+        assert_eq!(
+            Annotation::from_str("[mask: 2: 0, 0, 5, 5]").unwrap(),
+            Annotation::Mask(Region {
+                page: Some(1),
+                x: 0,
+                y: 0,
+                width: 5,
+                height: 5,
+            })
+        );
+        assert!(Annotation::from_str("[mask: 0: 0, 0, 5, 5]").is_err());
+        assert!(Annotation::from_str("[mask: 0, 0, 5]").is_err());
+        assert_eq!(
+            Annotation::from_str("[ssim]").unwrap(),
+            Annotation::Ssim(None)
+        );
+        assert_eq!(
+            Annotation::from_str("[ssim: 0.95]").unwrap(),
+            Annotation::Ssim(Some(0.95))
+        );
+        assert!(Annotation::from_str("[ssim: 2]").is_err());
+        assert_eq!(
+            Annotation::from_str("[compare: text]").unwrap(),
+            Annotation::CompareText(DEFAULT_TOLERANCE)
+        );
+        assert_eq!(
+            Annotation::from_str("[compare: text, 2.5pt]").unwrap(),
+            Annotation::CompareText(2.5)
+        );
+        assert_eq!(
+            Annotation::from_str("[compare: pdf]").unwrap(),
+            Annotation::ComparePdf(DEFAULT_TOLERANCE)
+        );
+        assert_eq!(
+            Annotation::from_str("[compare: pdf, 0pt]").unwrap(),
+            Annotation::ComparePdf(0.0)
+        );
+        assert!(Annotation::from_str("[compare: pixels]").is_err());
+        assert!(Annotation::from_str("[compare: text, far]").is_err());
+        assert_eq!(
+            Annotation::from_str("[xfail]").unwrap(),
+            Annotation::XFail(None)
+        );
+        assert_eq!(
+            Annotation::from_str("[xfail: typst#1234]").unwrap(),
+            Annotation::XFail(Some("typst#1234".into()))
+        );
     }
 
     #[test]
@@ -12,6 +12,7 @@ use std::path::PathBuf;
 use std::str::FromStr;
 use std::sync::LazyLock;
 
+use ecow::eco_format;
 use ecow::EcoString;
 use thiserror::Error;
 
@@ -25,6 +26,11 @@ const _TEMPLATE: &str = "@template";
 /// Each part of the path must be a simple id containing only ASCII
 /// alpha-numeric characters, dashes `-` or underscores `_` and start with an
 /// alphabetic character. This restriction may be lifted in the future.
+///
+/// Instances of parametrized tests have their parameters appended to the last
+/// component, like `a/b[x=1,y=2]`, keys follow the same rules as components,
+/// values may additionally start with a digit and contain dots `.` or plus
+/// signs `+`.
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize)]
 pub struct Id(EcoString);
 
@@ -32,6 +38,13 @@ impl Id {
     /// The test component separator.
     pub const SEPARATOR: &'static str = "/";
 
+    /// The separator between the parameters of a test instance.
+    pub const PARAM_SEPARATOR: &'static str = ",";
+
+    /// The prefix of reserved ids, these are used by built-in tests which
+    /// don't correspond to a test directory.
+    pub const RESERVED_PREFIX: char = '@';
+
     /// The unique special template identifier.
     pub const TEMPLATE: &'static str = _TEMPLATE;
 }
@@ -48,7 +61,8 @@ impl Id {
     ///
     /// All components must start at least one ASCII alphabetic letter and
     /// contain only ASCII alphanumeric characters, underscores, and minuses.
-    /// The only exception is the special template test identifier `@template`.
+    /// The only exceptions are the ids of [reserved][Reserved] built-in tests
+    /// like `@template`.
     ///
     /// # Examples
     /// ```
@@ -108,6 +122,35 @@ impl Id {
         inner(path.as_ref())
     }
 
+    /// Turns this path into a valid id by sanitizing each of its components
+    /// using [`Id::sanitize_component`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use tytanic_core::test::Id;
+    /// let id = Id::new_sanitized_from_path("my tests/1. intro")?;
+    /// assert_eq!(id.as_str(), "my-tests/t1-intro");
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the path contained no normal components.
+    pub fn new_sanitized_from_path<P: AsRef<Path>>(path: P) -> Result<Self, ParseIdError> {
+        let mut id = String::new();
+
+        for component in path.as_ref().components() {
+            if let Component::Normal(comp) = component {
+                if !id.is_empty() {
+                    id.push_str(Id::SEPARATOR);
+                }
+
+                id.push_str(&Self::sanitize_component(comp.to_string_lossy()));
+            }
+        }
+
+        Self::new(id)
+    }
+
     /// Turns this string into an id without validating it.
     ///
     /// # Safety
@@ -128,22 +171,66 @@ impl Id {
     /// assert!( Id::is_valid("a/b"));
     /// assert!( Id::is_valid("a"));
     /// assert!( Id::is_valid("@template"));
+    /// assert!( Id::is_valid("a/b[x=1,y=2]"));
+    /// assert!(!Id::is_valid("@unknown"));  // unknown reserved id
     /// assert!(!Id::is_valid("a//b"));  // empty component
     /// assert!(!Id::is_valid("a/"));    // empty component
+    /// assert!(!Id::is_valid("a[]"));   // empty parameters
     /// ```
     pub fn is_valid<S: AsRef<str>>(string: S) -> bool {
         Self::validate(string).is_ok()
     }
 
     fn validate<S: AsRef<str>>(string: S) -> Result<(), ParseIdError> {
-        if string.as_ref() == Self::TEMPLATE {
-            return Ok(());
+        if string.as_ref().starts_with(Self::RESERVED_PREFIX) {
+            return Reserved::resolve(string.as_ref())
+                .map(|_| ())
+                .ok_or_else(|| ParseIdError::UnknownReserved(string.as_ref().into()));
         }
 
-        for fragment in string.as_ref().split(Self::SEPARATOR) {
+        let (base, params) = split_params(string.as_ref());
+
+        for fragment in base.split(Self::SEPARATOR) {
             Self::validate_component(fragment)?;
         }
 
+        if let Some(params) = params {
+            for param in params.split(Self::PARAM_SEPARATOR) {
+                let (key, value) = param.split_once('=').ok_or(ParseIdError::InvalidParam)?;
+                Self::validate_param(key, value)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the given key and value form a valid parameter.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tytanic_core::test::Id;
+    /// assert!( Id::is_param_valid("a", "1"));
+    /// assert!( Id::is_param_valid("a", "1.5"));
+    /// assert!(!Id::is_param_valid("1", "a"));  // invalid key
+    /// assert!(!Id::is_param_valid("a", "a b"));  // invalid value
+    /// assert!(!Id::is_param_valid("a", ""));  // empty value
+    /// ```
+    pub fn is_param_valid<K: AsRef<str>, V: AsRef<str>>(key: K, value: V) -> bool {
+        Self::validate_param(key, value).is_ok()
+    }
+
+    fn validate_param<K: AsRef<str>, V: AsRef<str>>(key: K, value: V) -> Result<(), ParseIdError> {
+        Self::validate_component(key).map_err(|_| ParseIdError::InvalidParam)?;
+
+        let value = value.as_ref();
+        if value.is_empty()
+            || !value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+'))
+        {
+            return Err(ParseIdError::InvalidParam);
+        }
+
         Ok(())
     }
 
@@ -161,6 +248,45 @@ impl Id {
         Self::validate_component(component).is_ok()
     }
 
+    /// Turns an arbitrary string into a valid id component.
+    ///
+    /// Valid components are returned unchanged. Otherwise, invalid characters
+    /// are replaced with dashes `-`, consecutive dashes are collapsed and
+    /// leading or trailing dashes and underscores are removed. Components
+    /// which don't start with an ASCII alphabetic character are prefixed with
+    /// `t`.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tytanic_core::test::Id;
+    /// assert_eq!(Id::sanitize_component("valid"), "valid");
+    /// assert_eq!(Id::sanitize_component("my test"), "my-test");
+    /// assert_eq!(Id::sanitize_component("1. intro"), "t1-intro");
+    /// assert_eq!(Id::sanitize_component("?!"), "t");
+    /// ```
+    pub fn sanitize_component<S: AsRef<str>>(component: S) -> EcoString {
+        let component = component.as_ref();
+        if Self::is_component_valid(component) {
+            return component.into();
+        }
+
+        let mut sanitized = EcoString::new();
+        for c in component.chars() {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                sanitized.push(c);
+            } else if !sanitized.is_empty() && !sanitized.ends_with('-') {
+                sanitized.push('-');
+            }
+        }
+
+        let trimmed = sanitized.trim_matches(['-', '_']);
+        if trimmed.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            trimmed.into()
+        } else {
+            eco_format!("t{trimmed}")
+        }
+    }
+
     // TODO(tinger): This seems to be the culprit of the 100% doc tests.
     fn validate_component<S: AsRef<str>>(component: S) -> Result<(), ParseIdError> {
         let component = component.as_ref();
@@ -169,6 +295,10 @@ impl Id {
             return Err(ParseIdError::Empty);
         }
 
+        if component.starts_with(Self::RESERVED_PREFIX) {
+            return Err(ParseIdError::Reserved);
+        }
+
         let mut chars = component.chars().peekable();
         if !chars.next().unwrap().is_ascii_alphabetic() {
             return Err(ParseIdError::InvalidFragment);
@@ -185,6 +315,25 @@ impl Id {
 }
 
 impl Id {
+    /// The built-in test this id refers to, if it is a reserved id.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tytanic_core::test::Reserved;
+    /// # use tytanic_core::test::Id;
+    /// assert_eq!(Id::template().reserved(), Some(Reserved::Template));
+    /// assert_eq!(Id::new("a/b")?.reserved(), None);
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn reserved(&self) -> Option<Reserved> {
+        Reserved::resolve(self.as_str())
+    }
+
+    /// Whether this is the id of a built-in test, see [`Id::reserved`].
+    pub fn is_reserved(&self) -> bool {
+        self.as_str().starts_with(Self::RESERVED_PREFIX)
+    }
+
     /// The full id as a `str`, this string is never empty.
     pub fn as_str(&self) -> &str {
         self.0.as_str()
@@ -275,6 +424,139 @@ impl Id {
             ))
         }
     }
+
+    /// Whether this id is an instance of a parametrized test.
+    pub fn has_params(&self) -> bool {
+        split_params(self.as_str()).1.is_some()
+    }
+
+    /// The parameters of this id, this is empty for ids which are not
+    /// instances of a parametrized test.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tytanic_core::test::Id;
+    /// let id = Id::new("a/b[x=1,y=2]")?;
+    /// let mut params = id.params();
+    /// assert_eq!(params.next(), Some(("x", "1")));
+    /// assert_eq!(params.next(), Some(("y", "2")));
+    /// assert_eq!(params.next(), None);
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn params(&self) -> Params<'_> {
+        Params {
+            rest: split_params(self.as_str()).1.unwrap_or(""),
+        }
+    }
+
+    /// The parameters of this id as they appear in it, without the
+    /// surrounding brackets.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tytanic_core::test::Id;
+    /// let id = Id::new("a/b[x=1,y=2]")?;
+    /// assert_eq!(id.params_str(), Some("x=1,y=2"));
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn params_str(&self) -> Option<&str> {
+        split_params(self.as_str()).1
+    }
+
+    /// This id without its parameters, this is the id of the test an instance
+    /// was created from.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tytanic_core::test::Id;
+    /// let id = Id::new("a/b[x=1,y=2]")?;
+    /// assert_eq!(id.without_params().as_str(), "a/b");
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn without_params(&self) -> Id {
+        match split_params(self.as_str()) {
+            (base, Some(_)) => Id(base.into()),
+            (_, None) => self.clone(),
+        }
+    }
+
+    /// Creates the id of an instance of this test with the given parameters,
+    /// replacing any existing parameters.
+    ///
+    /// # Examples
+    /// ```
+    /// # use tytanic_core::test::Id;
+    /// let id = Id::new("a/b")?.with_params([("x", "1"), ("y", "2")])?;
+    /// assert_eq!(id.as_str(), "a/b[x=1,y=2]");
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if a parameter wasn't valid.
+    pub fn with_params<'a, I>(&self, params: I) -> Result<Self, ParseIdError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut id = EcoString::from(split_params(self.as_str()).0);
+
+        for (idx, (key, value)) in params.into_iter().enumerate() {
+            Self::validate_param(key, value)?;
+
+            id.push_str(if idx == 0 { "[" } else { Self::PARAM_SEPARATOR });
+            id.push_str(key);
+            id.push('=');
+            id.push_str(value);
+        }
+
+        if id.len() != split_params(self.as_str()).0.len() {
+            id.push(']');
+        }
+
+        Ok(Self(id))
+    }
+}
+
+/// A built-in test with a reserved id, these ids start with
+/// [`Id::RESERVED_PREFIX`] and can't be used by test directories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Reserved {
+    /// The template test, see [`Id::TEMPLATE`].
+    Template,
+}
+
+impl Reserved {
+    /// All built-in tests.
+    pub const ALL: &'static [Self] = &[Self::Template];
+
+    /// Resolves the built-in test with the given id.
+    pub fn resolve(id: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|reserved| reserved.name() == id)
+    }
+
+    /// The reserved id of this built-in test as a `str`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Template => Id::TEMPLATE,
+        }
+    }
+
+    /// The reserved id of this built-in test.
+    pub fn id(self) -> Id {
+        match self {
+            Self::Template => Id::template(),
+        }
+    }
+}
+
+/// Splits the given id into its base and its parameters, if it has any.
+fn split_params(id: &str) -> (&str, Option<&str>) {
+    match id.strip_suffix(']').and_then(|rest| rest.split_once('[')) {
+        Some((base, params)) => (base, Some(params)),
+        None => (id, None),
+    }
 }
 
 impl Id {
@@ -456,6 +738,30 @@ impl DoubleEndedIterator for Components<'_> {
     }
 }
 
+/// Returned by [`Id::params`].
+#[derive(Debug)]
+pub struct Params<'id> {
+    rest: &'id str,
+}
+
+impl<'id> Iterator for Params<'id> {
+    type Item = (&'id str, &'id str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rest.is_empty() {
+            return None;
+        }
+
+        let (param, rest) = self
+            .rest
+            .split_once(Id::PARAM_SEPARATOR)
+            .unwrap_or((self.rest, ""));
+        self.rest = rest;
+
+        param.split_once('=')
+    }
+}
+
 /// Returned by [`Id::new`][new] and [`Id::new_from_path`][new_from_path].
 ///
 /// [new]: super::Id::new
@@ -469,12 +775,59 @@ pub enum ParseIdError {
     /// An id contained empty or no fragments.
     #[error("id contained empty or no fragments")]
     Empty,
+
+    /// An id contained an invalid parameter.
+    #[error("id contained an invalid parameter")]
+    InvalidParam,
+
+    /// An id component started with the reserved prefix `@`.
+    #[error("id component started with the reserved prefix `@`")]
+    Reserved,
+
+    /// An id used the reserved prefix `@`, but was not a built-in test.
+    #[error("id {0:?} used the reserved prefix `@`, but was not a built-in test")]
+    UnknownReserved(EcoString),
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_reserved() {
+        assert_eq!(
+            Id::new(Id::TEMPLATE).unwrap().reserved(),
+            Some(Reserved::Template)
+        );
+        assert!(Id::template().is_reserved());
+        assert!(!Id::new("a/b").unwrap().is_reserved());
+
+        assert!(matches!(
+            Id::new("@unknown"),
+            Err(ParseIdError::UnknownReserved(_))
+        ));
+        assert!(matches!(Id::new("a/@b"), Err(ParseIdError::Reserved)));
+        assert!(matches!(
+            Id::new_from_path("@template"),
+            Err(ParseIdError::Reserved)
+        ));
+    }
+
+    #[test]
+    fn test_sanitize_component() {
+        for raw in ["a", "a b", "_a", "-a-", "1", "über", "a..b", "", "Ab_c-d"] {
+            assert!(
+                Id::is_component_valid(Id::sanitize_component(raw)),
+                "{raw:?}"
+            );
+        }
+
+        assert_eq!(Id::sanitize_component("a  b"), "a-b");
+        assert_eq!(Id::sanitize_component("über"), "ber");
+        assert_eq!(Id::sanitize_component("-_a_-"), "a");
+        assert_eq!(Id::sanitize_component("a_"), "a_");
+    }
+
     #[test]
     fn test_ancestors() {
         assert_eq!(
@@ -526,5 +879,31 @@ mod tests {
         assert!(Id::new("a ").is_err());
         assert!(Id::new("1a").is_err());
         assert!(Id::new("").is_err());
+
+        assert!(Id::new("a[]").is_err());
+        assert!(Id::new("a[x]").is_err());
+        assert!(Id::new("a[x=]").is_err());
+        assert!(Id::new("a[x=1,]").is_err());
+        assert!(Id::new("a[x=1]/b").is_err());
+        assert!(Id::new("a[x=a/b]").is_err());
+    }
+
+    #[test]
+    fn test_params() {
+        let id = Id::new("a/b[x=1,y=foo-bar]").unwrap();
+        assert!(id.has_params());
+        assert_eq!(id.name(), "b[x=1,y=foo-bar]");
+        assert_eq!(id.module(), "a");
+        assert_eq!(id.without_params().as_str(), "a/b");
+        assert_eq!(
+            id.params().collect::<Vec<_>>(),
+            [("x", "1"), ("y", "foo-bar")]
+        );
+
+        assert_eq!(id.with_params([("z", "2")]).unwrap().as_str(), "a/b[z=2]");
+        assert_eq!(id.with_params([]).unwrap().as_str(), "a/b");
+        assert!(id.with_params([("z", "a b")]).is_err());
+
+        assert!(!Id::new("a/b").unwrap().has_params());
     }
 }
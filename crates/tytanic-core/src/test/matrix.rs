@@ -0,0 +1,231 @@
+//! Parameter matrices of data driven tests.
+//!
+//! A unit test with a parameter matrix is expanded into one test instance per
+//! combination of parameter values, each instance is compiled with its
+//! parameters in `sys.inputs`. The matrix is defined in a `params.toml` file
+//! next to the test script or using a `matrix` annotation:
+//!
+//! ```toml
+//! lang = ["en", "de"]
+//! size = [10, 12]
+//! ```
+//!
+//! ```typst
+//! /// [matrix: lang = ["en", "de"], size = [10, 12]]
+//! ```
+
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use ecow::EcoString;
+use ecow::EcoVec;
+use thiserror::Error;
+
+use super::Id;
+
+/// The name of the file in a test directory which defines its parameter
+/// matrix.
+pub const MATRIX_FILE: &str = "params.toml";
+
+/// A matrix of test parameters, maps each parameter key to its values.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Matrix {
+    params: BTreeMap<EcoString, EcoVec<EcoString>>,
+}
+
+impl Matrix {
+    /// Creates a new empty matrix.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a matrix from the contents of a [`MATRIX_FILE`].
+    pub fn parse_toml(toml: &str) -> Result<Self, ParseMatrixError> {
+        Self::from_table(toml::from_str(toml)?)
+    }
+
+    fn from_table(table: toml::Table) -> Result<Self, ParseMatrixError> {
+        let mut this = Self::new();
+
+        for (key, value) in table {
+            let values = match value {
+                toml::Value::Array(values) => values
+                    .into_iter()
+                    .map(|value| to_param_value(&key, value))
+                    .collect::<Result<_, _>>()?,
+                value => EcoVec::from([to_param_value(&key, value)?]),
+            };
+
+            this.insert(key.into(), values)?;
+        }
+
+        Ok(this)
+    }
+
+    /// Adds the values of a parameter.
+    ///
+    /// # Errors
+    /// Returns an error if the parameter already exists, has no values or if
+    /// any of them are invalid.
+    pub fn insert(
+        &mut self,
+        key: EcoString,
+        values: EcoVec<EcoString>,
+    ) -> Result<(), ParseMatrixError> {
+        if values.is_empty() {
+            return Err(ParseMatrixError::Empty(key));
+        }
+
+        for value in &values {
+            if !Id::is_param_valid(&key, value) {
+                return Err(ParseMatrixError::Invalid {
+                    key,
+                    value: value.clone(),
+                });
+            }
+        }
+
+        if self.params.contains_key(&key) {
+            return Err(ParseMatrixError::Duplicate(key));
+        }
+
+        self.params.insert(key, values);
+        Ok(())
+    }
+
+    /// Adds all parameters of the given matrix to this one.
+    ///
+    /// # Errors
+    /// Returns an error if a parameter exists in both matrices.
+    pub fn extend(&mut self, other: Matrix) -> Result<(), ParseMatrixError> {
+        for (key, values) in other.params {
+            self.insert(key, values)?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether this matrix has no parameters.
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+
+    /// The parameter keys and their values, ordered by key.
+    pub fn params(&self) -> &BTreeMap<EcoString, EcoVec<EcoString>> {
+        &self.params
+    }
+
+    /// All combinations of parameter values, each combination is ordered by
+    /// key. An empty matrix has no combinations.
+    pub fn combinations(&self) -> Vec<Vec<(&str, &str)>> {
+        if self.is_empty() {
+            return vec![];
+        }
+
+        let mut combinations = vec![vec![]];
+        for (key, values) in &self.params {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination: Vec<(&str, &str)>| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((key.as_str(), value.as_str()));
+                        combination
+                    })
+                })
+                .collect();
+        }
+
+        combinations
+    }
+}
+
+impl FromStr for Matrix {
+    type Err = ParseMatrixError;
+
+    /// Parses the argument of a `matrix` annotation, this is a TOML inline
+    /// table without braces.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut table: toml::Table = toml::from_str(&format!("matrix = {{ {s} }}"))?;
+
+        match table.remove("matrix") {
+            Some(toml::Value::Table(table)) => Self::from_table(table),
+            _ => unreachable!("the matrix is always an inline table"),
+        }
+    }
+}
+
+fn to_param_value(key: &str, value: toml::Value) -> Result<EcoString, ParseMatrixError> {
+    match value {
+        toml::Value::String(value) => Ok(value.into()),
+        toml::Value::Integer(value) => Ok(value.to_string().into()),
+        toml::Value::Float(value) => Ok(value.to_string().into()),
+        toml::Value::Boolean(value) => Ok(value.to_string().into()),
+        _ => Err(ParseMatrixError::Invalid {
+            key: key.into(),
+            value: value.to_string().into(),
+        }),
+    }
+}
+
+/// Returned by [`Matrix::parse_toml`] and [`Matrix::insert`].
+#[derive(Debug, Error)]
+pub enum ParseMatrixError {
+    /// The matrix was not valid TOML.
+    #[error("the parameter matrix was not valid TOML")]
+    Toml(#[from] toml::de::Error),
+
+    /// A parameter had no values.
+    #[error("the parameter {0:?} had no values")]
+    Empty(EcoString),
+
+    /// A parameter was defined more than once.
+    #[error("the parameter {0:?} was defined more than once")]
+    Duplicate(EcoString),
+
+    /// A parameter key or value was invalid.
+    #[error("the parameter {key:?} had an invalid key or value {value:?}")]
+    Invalid { key: EcoString, value: EcoString },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml() {
+        let matrix = Matrix::parse_toml("b = [1, 2]\na = \"x\"\nc = [true]").unwrap();
+        assert_eq!(
+            matrix.combinations(),
+            [
+                [("a", "x"), ("b", "1"), ("c", "true")],
+                [("a", "x"), ("b", "2"), ("c", "true")],
+            ]
+        );
+
+        assert!(Matrix::parse_toml("a = []").is_err());
+        assert!(Matrix::parse_toml("a = [\"x y\"]").is_err());
+        assert!(Matrix::parse_toml("a = [[1]]").is_err());
+        assert!(Matrix::parse_toml("1a = [1]").is_err());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let matrix: Matrix = "lang = [\"en\", \"de\"], size = [10, 12]".parse().unwrap();
+        assert_eq!(matrix.combinations().len(), 4);
+        assert_eq!(matrix.combinations()[1], [("lang", "en"), ("size", "12")]);
+
+        assert!(Matrix::new().combinations().is_empty());
+    }
+
+    #[test]
+    fn test_extend_duplicate() {
+        let mut matrix = Matrix::parse_toml("a = [1]").unwrap();
+        assert!(matrix
+            .extend(Matrix::parse_toml("b = [1]").unwrap())
+            .is_ok());
+        assert!(matrix
+            .extend(Matrix::parse_toml("a = [2]").unwrap())
+            .is_err());
+    }
+}
@@ -1,18 +1,26 @@
 //! Test loading and on-disk manipulation.
 
 use std::fmt::Debug;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
 use std::time::Duration;
 use std::time::Instant;
+use std::time::SystemTime;
 
 use ecow::eco_vec;
+use ecow::EcoString;
 use ecow::EcoVec;
 use typst::diag::SourceDiagnostic;
 
 use crate::doc::compare;
 use crate::doc::compile;
+use crate::doc::LimitError;
+use crate::project::Project;
 
 mod annotation;
 mod id;
+pub mod matrix;
 pub mod template;
 pub mod unit;
 
@@ -20,6 +28,7 @@ pub use self::annotation::Annotation;
 pub use self::annotation::ParseAnnotationError;
 pub use self::id::Id;
 pub use self::id::ParseIdError;
+pub use self::id::Reserved;
 pub use self::template::Test as TemplateTest;
 pub use self::unit::Test as UnitTest;
 
@@ -57,6 +66,14 @@ impl Test {
             Test::Template(test) => Some(test),
         }
     }
+
+    /// The last modification time of the sources of this test.
+    pub fn last_modified(&self, project: &Project) -> io::Result<SystemTime> {
+        match self {
+            Test::Unit(test) => test.last_modified(project),
+            Test::Template(test) => test.last_modified(project),
+        }
+    }
 }
 
 /// The stage of a single test run.
@@ -83,6 +100,18 @@ pub enum Stage {
     /// The test passed compilation, but failed comparison.
     FailedComparison(compare::Error),
 
+    /// The test passed compilation, but its document exceeded the size
+    /// [`Limits`] and was not rendered.
+    ///
+    /// [`Limits`]: crate::doc::Limits
+    FailedRendering {
+        /// The inner error.
+        error: LimitError,
+
+        /// Whether this was a rendering failure of the reference.
+        reference: bool,
+    },
+
     /// The test passed compilation, but did not run comparison.
     PassedCompilation,
 
@@ -94,6 +123,65 @@ pub enum Stage {
         /// Whether the references were optimized.
         optimized: bool,
     },
+
+    /// The test was started, but cancelled before it finished.
+    Cancelled,
+
+    /// The compilation of the test exceeded its timeout and was aborted.
+    TimedOut {
+        /// The timeout which was exceeded.
+        timeout: Duration,
+
+        /// Whether this was a timeout of the reference compilation.
+        reference: bool,
+    },
+
+    /// The test was expected to fail and did so.
+    ExpectedFailure {
+        /// The reason given for the expected failure, if any.
+        reason: Option<EcoString>,
+
+        /// The stage at which the test failed.
+        failure: Box<Stage>,
+    },
+
+    /// The test was expected to fail, but passed.
+    UnexpectedPass {
+        /// The reason given for the expected failure, if any.
+        reason: Option<EcoString>,
+    },
+}
+
+/// A non-fatal failure to write a temporary artifact of a test, such as its
+/// output or difference documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArtifactWarning {
+    path: PathBuf,
+    message: EcoString,
+}
+
+impl ArtifactWarning {
+    /// Creates a new artifact warning for the given path.
+    pub fn new<P, M>(path: P, message: M) -> Self
+    where
+        P: Into<PathBuf>,
+        M: Into<EcoString>,
+    {
+        Self {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// The path of the artifact which could not be written.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The reason the artifact could not be written.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
 }
 
 /// The result of a single test run.
@@ -101,6 +189,8 @@ pub enum Stage {
 pub struct TestResult {
     stage: Stage,
     warnings: EcoVec<SourceDiagnostic>,
+    artifact_warnings: EcoVec<ArtifactWarning>,
+    pages: Option<usize>,
     timestamp: Instant,
     duration: Duration,
 }
@@ -115,6 +205,8 @@ impl TestResult {
         Self {
             stage: Stage::Skipped,
             warnings: eco_vec![],
+            artifact_warnings: eco_vec![],
+            pages: None,
             timestamp: Instant::now(),
             duration: Duration::ZERO,
         }
@@ -126,6 +218,8 @@ impl TestResult {
         Self {
             stage: Stage::Filtered,
             warnings: eco_vec![],
+            artifact_warnings: eco_vec![],
+            pages: None,
             timestamp: Instant::now(),
             duration: Duration::ZERO,
         }
@@ -143,6 +237,16 @@ impl TestResult {
         &self.warnings
     }
 
+    /// The warnings about temporary artifacts which could not be written.
+    pub fn artifact_warnings(&self) -> &[ArtifactWarning] {
+        &self.artifact_warnings
+    }
+
+    /// The number of pages of the test document, if it was compiled.
+    pub fn pages(&self) -> Option<usize> {
+        self.pages
+    }
+
     /// The timestamp at which the suite run started.
     pub fn timestamp(&self) -> Instant {
         self.timestamp
@@ -158,16 +262,25 @@ impl TestResult {
         matches!(&self.stage, Stage::Skipped)
     }
 
+    /// Whether the test was started, but cancelled before it finished.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(&self.stage, Stage::Cancelled)
+    }
+
     /// Whether the test was filtered out.
     pub fn is_filtered(&self) -> bool {
         matches!(&self.stage, Stage::Filtered)
     }
 
-    /// Whether the test passed compilation and/or comparison/update.
+    /// Whether the test passed compilation and/or comparison/update, or
+    /// failed as expected.
     pub fn is_pass(&self) -> bool {
         matches!(
             &self.stage,
-            Stage::PassedCompilation | Stage::PassedComparison | Stage::Updated { .. }
+            Stage::PassedCompilation
+                | Stage::PassedComparison
+                | Stage::Updated { .. }
+                | Stage::ExpectedFailure { .. }
         )
     }
 
@@ -175,7 +288,11 @@ impl TestResult {
     pub fn is_fail(&self) -> bool {
         matches!(
             &self.stage,
-            Stage::FailedCompilation { .. } | Stage::FailedComparison(..),
+            Stage::FailedCompilation { .. }
+                | Stage::FailedComparison(..)
+                | Stage::FailedRendering { .. }
+                | Stage::TimedOut { .. }
+                | Stage::UnexpectedPass { .. },
         )
     }
 
@@ -223,6 +340,12 @@ impl TestResult {
         };
     }
 
+    /// Sets the kind for this test to a rendering failure of the test or
+    /// reference.
+    pub fn set_failed_rendering(&mut self, error: LimitError, reference: bool) {
+        self.stage = Stage::FailedRendering { error, reference };
+    }
+
     /// Sets the kind for this test to a test comparison pass.
     pub fn set_passed_comparison(&mut self) {
         self.stage = Stage::PassedComparison;
@@ -233,6 +356,32 @@ impl TestResult {
         self.stage = Stage::FailedComparison(error);
     }
 
+    /// Sets the kind for this test to a cancellation.
+    pub fn set_cancelled(&mut self) {
+        self.stage = Stage::Cancelled;
+    }
+
+    /// Sets the kind for this test to a compilation timeout of the test or
+    /// reference.
+    pub fn set_timed_out(&mut self, timeout: Duration, reference: bool) {
+        self.stage = Stage::TimedOut { timeout, reference };
+    }
+
+    /// Sets the kind for this test to an expected failure, this wraps the
+    /// current stage, which should be a failure.
+    pub fn set_expected_failure(&mut self, reason: Option<EcoString>) {
+        let failure = std::mem::take(&mut self.stage);
+        self.stage = Stage::ExpectedFailure {
+            reason,
+            failure: Box::new(failure),
+        };
+    }
+
+    /// Sets the kind for this test to an unexpected pass.
+    pub fn set_unexpected_pass(&mut self, reason: Option<EcoString>) {
+        self.stage = Stage::UnexpectedPass { reason };
+    }
+
     /// Sets the kind for this test to a test update.
     pub fn set_updated(&mut self, optimized: bool) {
         self.stage = Stage::Updated { optimized };
@@ -245,6 +394,23 @@ impl TestResult {
     {
         self.warnings = warnings.into();
     }
+
+    /// Sets the number of pages of the test document.
+    pub fn set_pages(&mut self, pages: usize) {
+        self.pages = Some(pages);
+    }
+
+    /// Adds a warning about a temporary artifact which could not be written,
+    /// warnings for paths which already have a warning are ignored.
+    pub fn add_artifact_warning(&mut self, warning: ArtifactWarning) {
+        if self
+            .artifact_warnings
+            .iter()
+            .all(|w| w.path != warning.path)
+        {
+            self.artifact_warnings.push(warning);
+        }
+    }
 }
 
 impl Default for TestResult {
@@ -1,5 +1,7 @@
 use std::fs;
 use std::io;
+use std::path::Path;
+use std::time::SystemTime;
 
 use typst::syntax::FileId;
 use typst::syntax::Source;
@@ -50,4 +52,28 @@ impl Test {
             fs::read_to_string(test_script)?,
         ))
     }
+
+    /// The last modification time of any file within the template directory.
+    #[tracing::instrument(skip(project))]
+    pub fn last_modified(&self, project: &Project) -> io::Result<SystemTime> {
+        fn walk(path: &Path, last: &mut SystemTime) -> io::Result<()> {
+            let meta = fs::metadata(path)?;
+            *last = (*last).max(meta.modified()?);
+
+            if meta.is_dir() {
+                for entry in fs::read_dir(path)? {
+                    walk(&entry?.path(), last)?;
+                }
+            }
+
+            Ok(())
+        }
+
+        let mut last = SystemTime::UNIX_EPOCH;
+        if let Some(root) = project.template_root() {
+            walk(&root, &mut last)?;
+        }
+
+        Ok(last)
+    }
 }
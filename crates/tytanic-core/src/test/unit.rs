@@ -5,23 +5,42 @@ use std::fs;
 use std::fs::File;
 use std::io;
 use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+use std::time::SystemTime;
 
 use ecow::EcoString;
 use ecow::EcoVec;
 use thiserror::Error;
+use typst::foundations::Dict;
+use typst::foundations::Str;
+use typst::foundations::Value;
 use typst::syntax::FileId;
 use typst::syntax::Source;
 use typst::syntax::VirtualPath;
 
+use super::matrix::Matrix;
+use super::matrix::ParseMatrixError;
+use super::matrix::MATRIX_FILE;
 use super::Annotation;
 use super::Id;
 use super::ParseAnnotationError;
+use super::ParseIdError;
 use crate::doc;
+use crate::doc::compare::Mask;
+use crate::doc::render;
+use crate::doc::render::Format;
 use crate::doc::Document;
+use crate::doc::OptimizeOptions;
+use crate::doc::Pages;
 use crate::doc::SaveError;
 use crate::project::Project;
 use crate::project::Vcs;
 
+/// The names of the files in a test directory which contain notes about the
+/// test, in order of precedence.
+pub const NOTES_FILES: [&str; 2] = ["README.md", "notes.md"];
+
 // NOTE(tinger): The order of ignoring and deleting/creating documents is not
 // random, this is specifically for VCS like jj with active watchman triggers
 // and auto snapshotting.
@@ -34,6 +53,14 @@ pub const DEFAULT_TEST_INPUT: &str = include_str!("default-test.typ");
 /// The default test output as an encoded PNG.
 pub const DEFAULT_TEST_OUTPUT: &[u8] = include_bytes!("default-test.png");
 
+/// Loads the source of a document entrypoint relative to the project root.
+pub fn load_entrypoint(project: &Project, entrypoint: &str) -> io::Result<Source> {
+    Ok(Source::new(
+        FileId::new(None, VirtualPath::new(entrypoint)),
+        fs::read_to_string(project.root().join(entrypoint))?,
+    ))
+}
+
 /// References for a test.
 #[derive(Debug, Clone)]
 pub enum Reference {
@@ -48,7 +75,7 @@ pub enum Reference {
 
         /// The optimization options to use when storing the document, `None`
         /// disabled optimization.
-        opt: Option<Box<oxipng::Options>>,
+        opt: Option<Box<OptimizeOptions>>,
     },
 }
 
@@ -132,9 +159,14 @@ impl Test {
             return Ok(None);
         }
 
+        // NOTE(tinger): All instances of a parametrized test share the same
+        // kind, regardless of whether their own references exist yet.
         let kind = if project.unit_test_ref_script(&id).try_exists()? {
             Kind::Ephemeral
-        } else if project.unit_test_ref_dir(&id).try_exists()? {
+        } else if project
+            .unit_test_ref_dir(&id.without_params())
+            .try_exists()?
+        {
             Kind::Persistent
         } else {
             Kind::CompileOnly
@@ -170,6 +202,85 @@ impl Test {
     pub fn is_skip(&self) -> bool {
         self.annotations.contains(&Annotation::Skip)
     }
+
+    /// The tags of this test given by its `tag` annotations.
+    pub fn tags(&self) -> impl Iterator<Item = &EcoString> {
+        self.annotations.iter().flat_map(|annot| match annot {
+            Annotation::Tag(tags) => tags.as_slice(),
+            _ => &[],
+        })
+    }
+
+    /// Whether this test has the given tag.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags().any(|t| t == tag)
+    }
+
+    /// The timeout of this test's `timeout` annotation, if it has one.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.annotations.iter().rev().find_map(|annot| match annot {
+            Annotation::Timeout(timeout) => Some(*timeout),
+            _ => None,
+        })
+    }
+
+    /// The position tolerance of this test's `compare: text` or `compare: pdf`
+    /// annotation, if its text layer is compared instead of its pixels.
+    pub fn text_tolerance(&self) -> Option<f64> {
+        self.annotations.iter().rev().find_map(|annot| match annot {
+            Annotation::CompareText(tolerance) | Annotation::ComparePdf(tolerance) => {
+                Some(*tolerance)
+            }
+            _ => None,
+        })
+    }
+
+    /// Whether this test's documents are compared by their PDF structure, see
+    /// [`doc::pdf::compare`].
+    ///
+    /// [`doc::pdf::compare`]: crate::doc::pdf::compare
+    pub fn compares_pdf(&self) -> bool {
+        self.annotations.iter().rev().find_map(|annot| match annot {
+            Annotation::CompareText(_) => Some(false),
+            Annotation::ComparePdf(_) => Some(true),
+            _ => None,
+        }) == Some(true)
+    }
+
+    /// The document entrypoint this test compiles instead of its test script,
+    /// if it has an `entrypoint` annotation.
+    pub fn entrypoint(&self) -> Option<&str> {
+        self.annotations.iter().rev().find_map(|annot| match annot {
+            Annotation::Entrypoint(entrypoint) => Some(entrypoint.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Creates an instance of this test with the given parameters.
+    pub fn with_params<'a, I>(&self, params: I) -> Result<Self, ParseIdError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        Ok(Self {
+            id: self.id.with_params(params)?,
+            ..self.clone()
+        })
+    }
+
+    /// The inputs this test is compiled with, these are the pinned inputs of
+    /// its `input` annotations and the parameters of its id and available as
+    /// `sys.inputs`.
+    pub fn inputs(&self) -> Dict {
+        let pinned = self.annotations.iter().filter_map(|annot| match annot {
+            Annotation::Input(key, value) => Some((key.as_str(), value.as_str())),
+            _ => None,
+        });
+
+        pinned
+            .chain(self.id.params())
+            .map(|(key, value)| (Str::from(key), Value::Str(Str::from(value))))
+            .collect()
+    }
 }
 
 impl Test {
@@ -266,12 +377,15 @@ impl Test {
     }
 
     /// Creates the persistent reference document of this test.
+    ///
+    /// The reference is stored in the configured reference format, documents
+    /// without SVG pages are always stored as PNG.
     #[tracing::instrument(skip(project, reference, optimize_options))]
     pub fn create_reference_document(
         &self,
         project: &Project,
         reference: &Document,
-        optimize_options: Option<&oxipng::Options>,
+        optimize_options: Option<&OptimizeOptions>,
     ) -> Result<(), SaveError> {
         // NOTE(tinger): if there are already more pages than we want to create,
         // the surplus pages would persist and make every comparison fail due to
@@ -280,7 +394,34 @@ impl Test {
 
         let ref_dir = project.unit_test_ref_dir(&self.id);
         tytanic_utils::fs::create_dir(&ref_dir, true)?;
-        reference.save(&ref_dir, optimize_options)?;
+
+        let format = match project.config().defaults.reference_format {
+            Format::Svg if !reference.has_svgs() => {
+                tracing::warn!(id = %self.id, "reference has no SVG pages, storing it as PNG");
+                Format::Png
+            }
+            format => format,
+        };
+        reference.save_as(&ref_dir, format, optimize_options)?;
+
+        if self.text_tolerance().is_some() {
+            match reference.text_layer() {
+                Some(text) => text.save(&ref_dir)?,
+                None => tracing::warn!(id = %self.id, "reference has no text layer"),
+            }
+        }
+
+        // NOTE(tinger): The PDF is only stored for inspection, the structure
+        // is compared using the text layer.
+        if self.compares_pdf() {
+            match reference.save_pdf(&ref_dir) {
+                Ok(()) => {}
+                Err(SaveError::NoDocument) => {
+                    tracing::warn!(id = %self.id, "reference was not compiled in memory")
+                }
+                Err(err) => return Err(err),
+            }
+        }
 
         Ok(())
     }
@@ -362,7 +503,7 @@ impl Test {
         project: &Project,
         vcs: Option<&Vcs>,
         reference: &Document,
-        optimize_options: Option<&oxipng::Options>,
+        optimize_options: Option<&OptimizeOptions>,
     ) -> Result<(), SaveError> {
         self.kind = Kind::Persistent;
 
@@ -393,9 +534,14 @@ impl Test {
         Ok(())
     }
 
-    /// Loads the test script source of this test.
+    /// Loads the test script source of this test, or the source of its
+    /// document entrypoint if it has one.
     #[tracing::instrument(skip(project))]
     pub fn load_source(&self, project: &Project) -> io::Result<Source> {
+        if let Some(entrypoint) = self.entrypoint() {
+            return load_entrypoint(project, entrypoint);
+        }
+
         let test_script = project.unit_test_script(&self.id);
 
         Ok(Source::new(
@@ -433,16 +579,179 @@ impl Test {
         )))
     }
 
+    /// Loads the first paragraph of the notes of this test, these are read from
+    /// a `README.md` or `notes.md` file in the test directory.
+    ///
+    /// Leading headings are skipped and the lines of the paragraph are joined
+    /// by spaces. Returns `None` if there are no notes.
+    #[tracing::instrument(skip(project))]
+    pub fn load_notes(&self, project: &Project) -> io::Result<Option<String>> {
+        let dir = project.unit_test_dir(&self.id);
+
+        for name in NOTES_FILES {
+            let content = match fs::read_to_string(dir.join(name)) {
+                Ok(content) => content,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+
+            let paragraph = content
+                .lines()
+                .map(str::trim)
+                .skip_while(|line| line.is_empty() || line.starts_with('#'))
+                .take_while(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            return Ok(Some(paragraph).filter(|p| !p.is_empty()));
+        }
+
+        Ok(None)
+    }
+
+    /// Loads the parameter matrix of this test, this combines the [`MATRIX_FILE`]
+    /// in the test directory with the `matrix` annotation. The matrix is empty
+    /// if this test is not parametrized.
+    #[tracing::instrument(skip(project))]
+    pub fn load_matrix(&self, project: &Project) -> Result<Matrix, LoadError> {
+        let mut matrix = match fs::read_to_string(project.unit_test_dir(&self.id).join(MATRIX_FILE))
+        {
+            Ok(toml) => Matrix::parse_toml(&toml)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Matrix::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        for annot in &self.annotations {
+            if let Annotation::Matrix(other) = annot {
+                matrix.extend(other.clone())?;
+            }
+        }
+
+        Ok(matrix)
+    }
+
     /// Loads the test document of this test.
     #[tracing::instrument(skip(project))]
     pub fn load_document(&self, project: &Project) -> Result<Document, doc::LoadError> {
         Document::load(project.unit_test_out_dir(&self.id))
     }
 
-    /// Loads the persistent reference document of this test.
-    #[tracing::instrument(skip(project))]
+    /// Loads the persistent reference document of this test, SVG pages are
+    /// rasterized using [`render::DEFAULT_PIXEL_PER_PT`].
     pub fn load_reference_document(&self, project: &Project) -> Result<Document, doc::LoadError> {
-        Document::load(project.unit_test_ref_dir(&self.id))
+        self.load_reference_document_with(project, render::DEFAULT_PIXEL_PER_PT)
+    }
+
+    /// Loads the persistent reference document of this test, SVG pages are
+    /// rasterized using the given pixel per pt ratio.
+    #[tracing::instrument(skip(project))]
+    pub fn load_reference_document_with(
+        &self,
+        project: &Project,
+        pixel_per_pt: f32,
+    ) -> Result<Document, doc::LoadError> {
+        let dir = project.unit_test_ref_dir(&self.id);
+
+        // NOTE(tinger): Instances of parametrized tests don't have their own
+        // reference directory until they're first updated.
+        if self.id.has_params() && !dir.try_exists()? {
+            return Err(doc::LoadError::MissingPages(Default::default()));
+        }
+
+        Document::load_with(dir, pixel_per_pt)
+    }
+
+    /// Returns a lazy iterator over the PNG reference pages of this test, see
+    /// [`Document::pages`].
+    ///
+    /// Returns `None` if the reference document can't be compared page by
+    /// page, i.e. if it is stored as SVG or compared by its text layer.
+    pub fn load_reference_pages(&self, project: &Project) -> Result<Option<Pages>, doc::LoadError> {
+        let dir = project.unit_test_ref_dir(&self.id);
+
+        if self.id.has_params() && !dir.try_exists()? {
+            return Err(doc::LoadError::MissingPages(Default::default()));
+        }
+
+        if self.text_tolerance().is_some()
+            || !doc::page_paths(&dir, doc::SVG_PAGE_EXTENSION)?.is_empty()
+        {
+            return Ok(None);
+        }
+
+        Ok(Some(Document::pages(dir)?))
+    }
+
+    /// Loads the comparison mask of this test, this consists of the mask pages
+    /// in its mask directory and the regions of its mask annotations.
+    pub fn load_mask(&self, project: &Project) -> Result<Mask, doc::LoadError> {
+        let mut mask = Mask::load(project.unit_test_mask_dir(&self.id))?;
+
+        for annot in &self.annotations {
+            if let Annotation::Mask(region) = annot {
+                mask.add_region(*region);
+            }
+        }
+
+        Ok(mask)
+    }
+
+    /// The last modification time of the sources of this test, this includes
+    /// the test script, the reference script, the reference document and the
+    /// mask pages, but not temporary directories or nested tests.
+    #[tracing::instrument(skip(project))]
+    pub fn last_modified(&self, project: &Project) -> io::Result<SystemTime> {
+        fn modified(path: &Path) -> io::Result<Option<SystemTime>> {
+            match fs::metadata(path) {
+                Ok(meta) => Ok(Some(meta.modified()?)),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err),
+            }
+        }
+
+        let mut last = modified(&project.unit_test_script(&self.id))?;
+
+        if self.kind.is_ephemeral() {
+            last = last.max(modified(&project.unit_test_ref_script(&self.id))?);
+        }
+
+        if self.kind.is_persistent() {
+            let dir = project.unit_test_ref_dir(&self.id);
+            last = last.max(modified(&dir)?);
+
+            // NOTE(tinger): Instances of parametrized tests may not have
+            // references yet, long documents store their pages in chunk
+            // directories.
+            match doc::page_paths(&dir, doc::PAGE_EXTENSION) {
+                Ok(paths) => {
+                    for path in paths
+                        .values()
+                        .chain(doc::page_paths(&dir, doc::SVG_PAGE_EXTENSION)?.values())
+                    {
+                        last = last.max(modified(path)?);
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        if !self.kind.is_compile_only() {
+            let dir = project.unit_test_mask_dir(&self.id);
+            last = last.max(modified(&dir)?);
+
+            match fs::read_dir(dir) {
+                Ok(entries) => {
+                    for entry in entries {
+                        last = last.max(modified(&entry?.path())?);
+                    }
+                }
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(last.unwrap_or(SystemTime::UNIX_EPOCH))
     }
 }
 
@@ -469,6 +778,10 @@ pub enum LoadError {
     #[error("an error occurred while parsing a test annotation")]
     Annotation(#[from] ParseAnnotationError),
 
+    /// An error occurred while parsing the parameter matrix.
+    #[error("an error occurred while parsing the parameter matrix")]
+    Matrix(#[from] ParseMatrixError),
+
     /// An IO error occurred.
     #[error("an io error occurred")]
     Io(#[from] io::Error),
@@ -654,4 +967,74 @@ mod tests {
             },
         );
     }
+
+    #[test]
+    fn test_load_entrypoint() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("main.typ", "Thesis").setup_file(
+                    "tests/thesis/test.typ",
+                    "/// [entrypoint: main.typ]\n/// [input: draft=false]\n",
+                )
+            },
+            |root| {
+                let project = Project::new(root);
+
+                let test = Test::load(&project, id("thesis")).unwrap().unwrap();
+                assert_eq!(test.entrypoint(), Some("main.typ"));
+                assert_eq!(
+                    test.inputs(),
+                    Dict::from_iter([("draft".into(), Value::Str("false".into()))])
+                );
+
+                let source = test.load_source(&project).unwrap();
+                assert_eq!(source.text(), "Thesis");
+                assert_eq!(
+                    source.id().vpath().resolve(root).unwrap(),
+                    root.join("main.typ")
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn test_load_notes() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file_empty("tests/none/test.typ")
+                    .setup_file_empty("tests/readme/test.typ")
+                    .setup_file(
+                        "tests/readme/README.md",
+                        "# Readme\n\nFirst paragraph\nspanning lines.\n\nSecond paragraph.",
+                    )
+                    .setup_file("tests/readme/notes.md", "Ignored")
+                    .setup_file_empty("tests/notes/test.typ")
+                    .setup_file("tests/notes/notes.md", "Some notes\n")
+            },
+            |root| {
+                let project = Project::new(root);
+
+                assert_eq!(
+                    test("none", Kind::CompileOnly)
+                        .load_notes(&project)
+                        .unwrap(),
+                    None,
+                );
+                assert_eq!(
+                    test("readme", Kind::CompileOnly)
+                        .load_notes(&project)
+                        .unwrap()
+                        .as_deref(),
+                    Some("First paragraph spanning lines."),
+                );
+                assert_eq!(
+                    test("notes", Kind::CompileOnly)
+                        .load_notes(&project)
+                        .unwrap()
+                        .as_deref(),
+                    Some("Some notes"),
+                );
+            },
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use super::Dur;
 use super::Id;
 use super::Num;
 use super::Pat;
@@ -17,6 +18,9 @@ pub enum Atom {
     /// A number literal.
     Num(Num),
 
+    /// A duration literal.
+    Dur(Dur),
+
     /// A string literal.
     Str(Str),
 
@@ -29,6 +33,7 @@ impl<T: Test> Eval<T> for Atom {
         Ok(match self {
             Self::Id(id) => id.eval(ctx)?,
             Self::Num(n) => Value::Num(*n),
+            Self::Dur(d) => Value::Dur(*d),
             Self::Str(s) => Value::Str(s.clone()),
             Self::Pat(pat) => pat.eval(ctx)?,
         })
@@ -0,0 +1,94 @@
+use std::fmt::Debug;
+use std::time::Duration;
+
+use ecow::eco_vec;
+use pest::iterators::Pair;
+
+use super::Error;
+use super::PairExt;
+use super::Rule;
+use crate::eval;
+use crate::eval::Context;
+use crate::eval::Eval;
+use crate::eval::Test;
+use crate::eval::TryFromValue;
+use crate::eval::Type;
+use crate::eval::Value;
+
+/// A duration literal node like `5s` or `1m30s`.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Dur(pub Duration);
+
+impl Debug for Dur {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl From<Duration> for Dur {
+    fn from(value: Duration) -> Self {
+        Self(value)
+    }
+}
+
+impl From<Dur> for Duration {
+    fn from(value: Dur) -> Self {
+        value.0
+    }
+}
+
+impl<T: Test> Eval<T> for Dur {
+    fn eval(&self, _ctx: &Context<T>) -> Result<Value<T>, eval::Error> {
+        Ok(Value::Dur(*self))
+    }
+}
+
+impl<T> TryFromValue<T> for Dur {
+    fn try_from_value(value: Value<T>) -> Result<Self, eval::Error> {
+        Ok(match value {
+            Value::Dur(dur) => dur,
+            _ => {
+                return Err(eval::Error::TypeMismatch {
+                    expected: eco_vec![Type::Dur],
+                    found: value.as_type(),
+                })
+            }
+        })
+    }
+}
+
+impl Dur {
+    pub(super) fn parse(pair: Pair<'_, Rule>) -> Result<Self, Error> {
+        pair.expect_rules(&[Rule::dur_inner])?;
+
+        let mut dur = Duration::ZERO;
+        let mut num = 0;
+
+        for pair in pair.into_inner() {
+            match pair.as_rule() {
+                Rule::num_part => {
+                    num = pair
+                        .as_str()
+                        .parse()
+                        .map_err(|err| Error::Other(Box::new(err)))?;
+                }
+                Rule::dur_unit => {
+                    let unit = match pair.as_str() {
+                        "ms" => Duration::from_millis(1),
+                        "s" => Duration::from_secs(1),
+                        "m" => Duration::from_secs(60),
+                        "h" => Duration::from_secs(60 * 60),
+                        "d" => Duration::from_secs(60 * 60 * 24),
+                        "w" => Duration::from_secs(60 * 60 * 24 * 7),
+                        unit => unreachable!("parser should ensure this is a unit, got {unit:?}"),
+                    };
+
+                    dur += unit * num;
+                }
+                _ => pair.expect_rules(&[Rule::num_part, Rule::dur_unit])?,
+            }
+        }
+
+        Ok(Self(dur))
+    }
+}
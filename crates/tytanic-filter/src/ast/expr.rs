@@ -1,9 +1,11 @@
+use std::cmp::Ordering;
 use std::sync::Arc;
 
 use pest::iterators::Pair;
 use pest::pratt_parser::PrattParser;
 
 use super::Atom;
+use super::Dur;
 use super::Error;
 use super::Func;
 use super::Id;
@@ -14,6 +16,7 @@ use super::Str;
 use crate::eval;
 use crate::eval::Context;
 use crate::eval::Eval;
+use crate::eval::Metric;
 use crate::eval::Set;
 use crate::eval::Test;
 use crate::eval::Value;
@@ -40,6 +43,35 @@ pub enum InfixOp {
     /// The symmetric difference/xor operator. Matches the symbols `xor` and
     /// `^`.
     SymDiff,
+
+    /// The less than operator. Matches the symbol `<`.
+    Lt,
+
+    /// The less than or equal operator. Matches the symbol `<=`.
+    Le,
+
+    /// The greater than operator. Matches the symbol `>`.
+    Gt,
+
+    /// The greater than or equal operator. Matches the symbol `>=`.
+    Ge,
+
+    /// The equal operator. Matches the symbol `==`.
+    Eq,
+
+    /// The not equal operator. Matches the symbol `!=`.
+    Ne,
+}
+
+impl InfixOp {
+    /// Whether this operator compares metrics rather than combining test
+    /// sets.
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            Self::Lt | Self::Le | Self::Gt | Self::Ge | Self::Eq | Self::Ne
+        )
+    }
 }
 
 /// An expression node.
@@ -87,8 +119,24 @@ impl<T: Test> Eval<T> for Expr {
                     PrefixOp::Not => Set::expr_comp(set),
                 }))
             }
+            Self::Infix { op, lhs, rhs } if op.is_comparison() => {
+                // Comparison operators are only valid for metrics, numbers
+                // and durations.
+                let lhs: Metric<T> = lhs.eval(ctx)?.expect_type()?;
+                let rhs: Metric<T> = rhs.eval(ctx)?.expect_type()?;
+
+                Ok(Value::Set(match op {
+                    InfixOp::Lt => Set::expr_compare(lhs, rhs, Ordering::is_lt),
+                    InfixOp::Le => Set::expr_compare(lhs, rhs, Ordering::is_le),
+                    InfixOp::Gt => Set::expr_compare(lhs, rhs, Ordering::is_gt),
+                    InfixOp::Ge => Set::expr_compare(lhs, rhs, Ordering::is_ge),
+                    InfixOp::Eq => Set::expr_compare(lhs, rhs, Ordering::is_eq),
+                    InfixOp::Ne => Set::expr_compare(lhs, rhs, Ordering::is_ne),
+                    _ => unreachable!("unhandled comparison operator {op:?}"),
+                }))
+            }
             Self::Infix { op, lhs, rhs } => {
-                // Binary infix operator is only valid for test sets.
+                // Other binary infix operators are only valid for test sets.
                 let lhs: Set<T> = lhs.eval(ctx)?.expect_type()?;
                 let rhs: Set<T> = rhs.eval(ctx)?.expect_type()?;
 
@@ -97,6 +145,7 @@ impl<T: Test> Eval<T> for Expr {
                     InfixOp::Inter => Set::expr_inter(lhs, rhs, []),
                     InfixOp::Diff => Set::expr_diff(lhs, rhs),
                     InfixOp::SymDiff => Set::expr_sym_diff(lhs, rhs),
+                    _ => unreachable!("unhandled set operator {op:?}"),
                 }))
             }
         }
@@ -114,6 +163,7 @@ impl Expr {
                         Expr::Atom(Atom::Str(Str::parse(primary)?))
                     }
                     Rule::num_inner => Expr::Atom(Atom::Num(Num::parse(primary)?)),
+                    Rule::dur_inner => Expr::Atom(Atom::Dur(Dur::parse(primary)?)),
                     Rule::func => Expr::Func(Func::parse(primary, pratt)?),
                     Rule::expr => Self::parse(primary, pratt)?,
                     x => unreachable!("unhandled primary expression {x:?}"),
@@ -10,6 +10,7 @@ use thiserror::Error;
 use tytanic_utils::fmt::Separators;
 
 mod atom;
+mod dur;
 mod expr;
 mod func;
 mod glob;
@@ -24,6 +25,7 @@ mod str;
 use parser::Rule;
 
 pub use self::atom::Atom;
+pub use self::dur::Dur;
 pub use self::expr::Expr;
 pub use self::expr::InfixOp;
 pub use self::expr::PrefixOp;
@@ -49,6 +51,12 @@ pub(super) static PRATT_PARSER: LazyLock<PrattParser<Rule>> = LazyLock::new(|| {
         .op(Op::infix(Rule::infix_op_caret, Assoc::Left)
             | Op::infix(Rule::infix_op_xor, Assoc::Left))
         .op(Op::prefix(Rule::prefix_op_excl) | Op::prefix(Rule::prefix_op_not))
+        .op(Op::infix(Rule::infix_op_lt, Assoc::Left)
+            | Op::infix(Rule::infix_op_le, Assoc::Left)
+            | Op::infix(Rule::infix_op_gt, Assoc::Left)
+            | Op::infix(Rule::infix_op_ge, Assoc::Left)
+            | Op::infix(Rule::infix_op_eq, Assoc::Left)
+            | Op::infix(Rule::infix_op_ne, Assoc::Left))
 });
 
 /// Parse the given input into a test set expression.
@@ -181,6 +189,7 @@ impl<'a> PairExt<'a> for Pair<'a, Rule> {
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
+    use std::time::Duration;
 
     use ecow::eco_vec;
 
@@ -226,6 +235,51 @@ mod tests {
         assert_eq!(parse("1_000").unwrap(), Expr::Atom(Atom::Num(1000.into())));
     }
 
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(
+            parse("5s").unwrap(),
+            Expr::Atom(Atom::Dur(Dur(Duration::from_secs(5))))
+        );
+        assert_eq!(
+            parse("1m30s").unwrap(),
+            Expr::Atom(Atom::Dur(Dur(Duration::from_secs(90))))
+        );
+        assert_eq!(
+            parse("250ms").unwrap(),
+            Expr::Atom(Atom::Dur(Dur(Duration::from_millis(250))))
+        );
+    }
+
+    #[test]
+    fn test_parse_comparison_expression() {
+        assert_eq!(
+            parse("!a() > 5s & b() <= 10").unwrap(),
+            Expr::Infix {
+                op: InfixOp::Inter,
+                lhs: Arc::new(Expr::Prefix {
+                    op: PrefixOp::Not,
+                    expr: Arc::new(Expr::Infix {
+                        op: InfixOp::Gt,
+                        lhs: Arc::new(Expr::Func(Func {
+                            id: Id("a".into()),
+                            args: eco_vec![],
+                        })),
+                        rhs: Arc::new(Expr::Atom(Atom::Dur(Dur(Duration::from_secs(5))))),
+                    }),
+                }),
+                rhs: Arc::new(Expr::Infix {
+                    op: InfixOp::Le,
+                    lhs: Arc::new(Expr::Func(Func {
+                        id: Id("b".into()),
+                        args: eco_vec![],
+                    })),
+                    rhs: Arc::new(Expr::Atom(Atom::Num(Num(10)))),
+                }),
+            }
+        );
+    }
+
     #[test]
     fn test_parse_pattern_string() {
         assert_eq!(
@@ -238,6 +292,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_pattern_case_insensitive() {
+        let pat = |input| match parse(input).unwrap() {
+            Expr::Atom(Atom::Pat(pat)) => pat,
+            expr => panic!("expected pattern, got {expr:?}"),
+        };
+
+        assert!(pat("ig:Foo/*").is_match("foo/bar"));
+        assert!(pat("ir:^FOO").is_match("foo/bar"));
+        assert!(pat("ie:Foo/Bar").is_match("foo/bar"));
+        assert!(!pat("g:Foo/*").is_match("foo/bar"));
+        assert!(!pat("e:Foo/Bar").is_match("foo/bar"));
+    }
+
     #[test]
     fn test_parse_pattern_raw() {
         assert_eq!(
@@ -27,6 +27,12 @@ impl Rule {
             Rule::infix_op_amper | Rule::infix_op_and => InfixOp::Inter,
             Rule::infix_op_tilde | Rule::infix_op_diff => InfixOp::Diff,
             Rule::infix_op_caret | Rule::infix_op_xor => InfixOp::SymDiff,
+            Rule::infix_op_lt => InfixOp::Lt,
+            Rule::infix_op_le => InfixOp::Le,
+            Rule::infix_op_gt => InfixOp::Gt,
+            Rule::infix_op_ge => InfixOp::Ge,
+            Rule::infix_op_eq => InfixOp::Eq,
+            Rule::infix_op_ne => InfixOp::Ne,
             _ => return None,
         })
     }
@@ -50,6 +56,12 @@ impl Rule {
             Rule::infix_op_and => "literal intersection op",
             Rule::infix_op_diff => "literal difference op",
             Rule::infix_op_or => "literal union op",
+            Rule::infix_op_lt => "less than op",
+            Rule::infix_op_le => "less than or equal op",
+            Rule::infix_op_gt => "greater than op",
+            Rule::infix_op_ge => "greater than or equal op",
+            Rule::infix_op_eq => "equal op",
+            Rule::infix_op_ne => "not equal op",
             Rule::id => "identifier",
             Rule::func | Rule::func_args | Rule::func_args_inner => "function arguments",
             Rule::func_args_sep => "comma",
@@ -60,6 +72,9 @@ impl Rule {
             Rule::pat_kind_glob => "glob pattern kind",
             Rule::pat_kind_regex => "regex pattern kind",
             Rule::pat_kind_exact => "exact pattern kind",
+            Rule::pat_kind_iglob => "case-insensitive glob pattern kind",
+            Rule::pat_kind_iregex => "case-insensitive regex pattern kind",
+            Rule::pat_kind_iexact => "case-insensitive exact pattern kind",
             Rule::pat_inner | Rule::pat_pat => "pattern",
             Rule::pat_raw_lit
             | Rule::pat_raw_term_outer
@@ -82,6 +97,8 @@ impl Rule {
             Rule::num | Rule::num_inner => "number",
             Rule::num_part => "digit",
             Rule::num_sep => "underscore",
+            Rule::dur | Rule::dur_inner => "duration",
+            Rule::dur_unit => "duration unit",
             Rule::WHITESPACE => "whitespace",
         }
     }
@@ -104,6 +121,12 @@ impl Rule {
             Rule::infix_op_and => "and",
             Rule::infix_op_diff => "diff",
             Rule::infix_op_or => "or",
+            Rule::infix_op_lt => "<",
+            Rule::infix_op_le => "<=",
+            Rule::infix_op_gt => ">",
+            Rule::infix_op_ge => ">=",
+            Rule::infix_op_eq => "==",
+            Rule::infix_op_ne => "!=",
             Rule::id => "<ident>",
             Rule::func | Rule::func_args | Rule::func_args_inner => "<args>",
             Rule::func_args_sep => "<comma>",
@@ -114,6 +137,9 @@ impl Rule {
             Rule::pat_kind_glob => "glob",
             Rule::pat_kind_regex => "regex",
             Rule::pat_kind_exact => "exact",
+            Rule::pat_kind_iglob => "iglob",
+            Rule::pat_kind_iregex => "iregex",
+            Rule::pat_kind_iexact => "iexact",
             Rule::pat_inner | Rule::pat_pat => "<pattern>",
             Rule::pat_raw_lit
             | Rule::pat_raw_term_outer
@@ -136,6 +162,8 @@ impl Rule {
             Rule::num | Rule::num_inner => "<number>",
             Rule::num_part => "<digit>",
             Rule::num_sep => "_",
+            Rule::dur | Rule::dur_inner => "<duration>",
+            Rule::dur_unit => "<unit>",
             Rule::WHITESPACE => "<WHITESPACE>",
         }
     }
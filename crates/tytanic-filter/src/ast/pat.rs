@@ -27,6 +27,10 @@ pub enum Pat {
 
     /// An exact pattern literal.
     Exact(Str),
+
+    /// A case-insensitive pattern literal, regex patterns are case-insensitive
+    /// by their `(?i)` flag.
+    CaseInsensitive(Box<Pat>),
 }
 
 impl std::fmt::Debug for Pat {
@@ -35,6 +39,10 @@ impl std::fmt::Debug for Pat {
             Pat::Glob(glob) => ("glob", glob.as_str()),
             Pat::Regex(regex) => ("regex", regex.as_str()),
             Pat::Exact(pat) => ("exact", pat.as_str()),
+            Pat::CaseInsensitive(pat) => {
+                write!(f, "i")?;
+                return pat.fmt(f);
+            }
         };
 
         write!(f, "{prefix}:{pat:?}")
@@ -48,6 +56,17 @@ impl Pat {
             Self::Glob(pat) => pat.is_match(id),
             Self::Regex(regex) => regex.is_match(id),
             Self::Exact(pat) => id.as_ref() == pat.as_str(),
+            Self::CaseInsensitive(pat) => match &**pat {
+                Self::Glob(pat) => pat.matches_with(
+                    id.as_ref(),
+                    glob::MatchOptions {
+                        case_sensitive: false,
+                        ..Default::default()
+                    },
+                ),
+                Self::Exact(pat) => id.as_ref().to_lowercase() == pat.as_str().to_lowercase(),
+                pat => pat.is_match(id),
+            },
         }
     }
 }
@@ -78,6 +97,12 @@ impl Pat {
             "g" | "glob" => Self::Glob(Glob::new(&pat)?),
             "r" | "regex" => Self::Regex(Regex::new(&pat)?),
             "e" | "exact" => Self::Exact(pat),
+            "ig" | "iglob" => Self::CaseInsensitive(Box::new(Self::Glob(Glob::new(&pat)?))),
+            "ir" | "iregex" => Self::CaseInsensitive(Box::new(Self::Regex(Regex::new(format!(
+                "(?i){}",
+                pat.as_str()
+            ))?))),
+            "ie" | "iexact" => Self::CaseInsensitive(Box::new(Self::Exact(pat))),
             _ => unreachable!("unhandled kind: {kind:?}"),
         })
     }
@@ -0,0 +1,85 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+
+use ecow::eco_vec;
+
+use super::Context;
+use super::Error;
+use super::Test;
+use super::TryFromValue;
+use super::Type;
+use super::Value;
+use crate::ast::Dur;
+use crate::ast::Num;
+
+/// The backing implementation for a [`Metric`].
+type MetricImpl<T> =
+    Arc<dyn Fn(&Context<T>, &T) -> Result<Option<Value<T>>, Error> + Send + Sync + 'static>;
+
+/// A metric, this measures a number or duration of a test, like its number
+/// of pages or how long it took to run. Metrics are compared against other
+/// metrics or literals to create test sets.
+#[derive(Clone)]
+pub struct Metric<T>(MetricImpl<T>);
+
+impl<T> Metric<T> {
+    /// Create a new metric with the given implementation.
+    pub fn new<F>(f: F) -> Self
+    where
+        F: Fn(&Context<T>, &T) -> Result<Option<Value<T>>, Error> + Send + Sync + 'static,
+    {
+        Self(Arc::new(f) as _)
+    }
+
+    /// Measures the given test, returns `None` if the metric has no value for
+    /// this test.
+    pub fn measure(&self, ctx: &Context<T>, test: &T) -> Result<Option<Value<T>>, Error> {
+        (self.0)(ctx, test)
+    }
+}
+
+impl<T> Debug for Metric<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Metric").field(&..).finish()
+    }
+}
+
+impl<T: Test> Metric<T> {
+    /// Construct a metric which is the given number for all tests.
+    ///
+    /// This is the metric created from number literals when they are compared.
+    pub fn coerce_num(num: Num) -> Self {
+        Self::new(move |_, _| Ok(Some(Value::Num(num))))
+    }
+
+    /// Construct a metric which is the given duration for all tests.
+    ///
+    /// This is the metric created from duration literals when they are
+    /// compared.
+    pub fn coerce_dur(dur: Dur) -> Self {
+        Self::new(move |_, _| Ok(Some(Value::Dur(dur))))
+    }
+}
+
+impl<T: Test> TryFromValue<T> for Metric<T> {
+    fn try_from_value(value: Value<T>) -> Result<Self, Error> {
+        Ok(match value {
+            Value::Metric(metric) => metric,
+            Value::Num(num) => Self::coerce_num(num),
+            Value::Dur(dur) => Self::coerce_dur(dur),
+            _ => {
+                return Err(Error::TypeMismatch {
+                    expected: eco_vec![Type::Num, Type::Dur, Type::Metric],
+                    found: value.as_type(),
+                })
+            }
+        })
+    }
+}
+
+/// Ensure Metric<T> is thread safe if T is.
+#[allow(dead_code)]
+fn assert_traits() {
+    tytanic_utils::assert::send::<Metric<()>>();
+    tytanic_utils::assert::sync::<Metric<()>>();
+}
@@ -12,10 +12,12 @@ use tytanic_utils::fmt::Term;
 use super::ast::Id;
 
 mod func;
+mod metric;
 mod set;
 mod value;
 
 pub use self::func::Func;
+pub use self::metric::Metric;
 pub use self::set::Set;
 pub use self::value::TryFromValue;
 pub use self::value::Type;
@@ -72,6 +74,11 @@ impl<T> Context<T> {
             .ok_or_else(|| Error::UnknownBinding { id: id.into() })
     }
 
+    /// The identifiers of all bindings in this context.
+    pub fn ids(&self) -> impl Iterator<Item = &Id> {
+        self.bindings.keys()
+    }
+
     /// Find similar bindings to the given identifier.
     pub fn find_similar(&self, id: &str) -> Vec<Id> {
         self.bindings
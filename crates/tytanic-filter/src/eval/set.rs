@@ -1,3 +1,4 @@
+use std::cmp::Ordering;
 use std::fmt::Debug;
 use std::sync::Arc;
 
@@ -5,6 +6,7 @@ use ecow::eco_vec;
 
 use super::Context;
 use super::Error;
+use super::Metric;
 use super::Test;
 use super::TryFromValue;
 use super::Type;
@@ -119,6 +121,37 @@ impl<T: 'static> Set<T> {
     }
 }
 
+impl<T: Test> Set<T> {
+    /// Construct a set which contains all tests for which the ordering of the
+    /// given metrics satisfies the given predicate, tests for which either
+    /// metric has no value are not contained.
+    ///
+    /// This is the test set created by `a < b`, `a == b` and so on.
+    pub fn expr_compare<F>(a: Metric<T>, b: Metric<T>, f: F) -> Self
+    where
+        F: Fn(Ordering) -> bool + Send + Sync + 'static,
+    {
+        Self::new(move |ctx, test| {
+            let (Some(a), Some(b)) = (a.measure(ctx, test)?, b.measure(ctx, test)?) else {
+                return Ok(false);
+            };
+
+            let ordering = match (&a, &b) {
+                (Value::Num(a), Value::Num(b)) => a.cmp(b),
+                (Value::Dur(a), Value::Dur(b)) => a.cmp(b),
+                _ => {
+                    return Err(Error::TypeMismatch {
+                        expected: eco_vec![a.as_type()],
+                        found: b.as_type(),
+                    })
+                }
+            };
+
+            Ok(f(ordering))
+        })
+    }
+}
+
 impl<T> TryFromValue<T> for Set<T> {
     fn try_from_value(value: Value<T>) -> Result<Self, Error> {
         Ok(match value {
@@ -1,6 +1,8 @@
 use super::Error;
 use super::Func;
+use super::Metric;
 use super::Set;
+use crate::ast::Dur;
 use crate::ast::Num;
 use crate::ast::Str;
 
@@ -21,6 +23,12 @@ pub enum Value<T> {
 
     /// A string.
     Str(Str),
+
+    /// A duration.
+    Dur(Dur),
+
+    /// A per-test metric.
+    Metric(Metric<T>),
 }
 
 impl<T> Value<T> {
@@ -32,6 +40,8 @@ impl<T> Value<T> {
             Value::Func(_) => Type::Func,
             Value::Num(_) => Type::Num,
             Value::Str(_) => Type::Str,
+            Value::Dur(_) => Type::Dur,
+            Value::Metric(_) => Type::Metric,
         }
     }
 
@@ -68,6 +78,18 @@ impl<T> From<Str> for Value<T> {
     }
 }
 
+impl<T> From<Dur> for Value<T> {
+    fn from(value: Dur) -> Self {
+        Self::Dur(value)
+    }
+}
+
+impl<T> From<Metric<T>> for Value<T> {
+    fn from(value: Metric<T>) -> Self {
+        Self::Metric(value)
+    }
+}
+
 /// A trait for types which can be unwrapped from a [`Value`].
 pub trait TryFromValue<T>: Sized {
     fn try_from_value(value: Value<T>) -> Result<Self, Error>;
@@ -90,6 +112,12 @@ pub enum Type {
 
     /// A string.
     Str,
+
+    /// A duration.
+    Dur,
+
+    /// A per-test metric.
+    Metric,
 }
 
 impl Type {
@@ -101,6 +129,8 @@ impl Type {
             Self::Func => "function",
             Self::Num => "number",
             Self::Str => "string",
+            Self::Dur => "duration",
+            Self::Metric => "metric",
         }
     }
 }
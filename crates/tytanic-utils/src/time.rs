@@ -0,0 +1,75 @@
+//! Helper functions and types for durations and timestamps.
+
+use std::time::Duration;
+
+/// Parses a human readable duration like `90s`, `15m`, `2h`, `1d` or `1w`.
+/// Multiple components can be combined like in `1h30m`, a number without a
+/// unit is interpreted as seconds.
+///
+/// Returns `None` if the input is empty or not a valid duration.
+///
+/// # Examples
+/// ```
+/// # use std::time::Duration;
+/// # use tytanic_utils::time::parse_duration;
+/// assert_eq!(parse_duration("90"), Some(Duration::from_secs(90)));
+/// assert_eq!(parse_duration("15m"), Some(Duration::from_secs(15 * 60)));
+/// assert_eq!(parse_duration("1h30m"), Some(Duration::from_secs(90 * 60)));
+/// assert_eq!(parse_duration("1d"), Some(Duration::from_secs(24 * 60 * 60)));
+/// assert_eq!(parse_duration("1x"), None);
+/// ```
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let mut rest = input.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut total = Duration::ZERO;
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+
+        if digits == 0 {
+            return None;
+        }
+
+        let value: u64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let units = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+
+        let factor = match &rest[..units] {
+            "" | "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            "w" => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+        rest = &rest[units..];
+
+        total = total.checked_add(Duration::from_secs(value.checked_mul(factor)?))?;
+    }
+
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("0"), Some(Duration::ZERO));
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("2w"), Some(Duration::from_secs(14 * 86400)));
+        assert_eq!(parse_duration("1d2h"), Some(Duration::from_secs(93600)));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("1hh"), None);
+    }
+}
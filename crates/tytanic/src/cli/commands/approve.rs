@@ -0,0 +1,130 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use tytanic_core::doc::Document;
+use tytanic_core::dsl;
+use tytanic_core::suite::Filter;
+use tytanic_core::test::Id;
+use tytanic_filter::eval;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use super::FilterOptions;
+use super::OptimizeRefsSwitch;
+use super::Switch;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+use crate::runner;
+use crate::ui;
+use crate::DEFAULT_OPTIMIZE_OPTIONS;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "approve-args")]
+pub struct Args {
+    #[command(flatten)]
+    pub optimize_refs: OptimizeRefsSwitch,
+
+    #[command(flatten)]
+    pub filter: FilterOptions,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    ctx.ensure_not_package_dir(&project)?;
+    let filter = match ctx.filter(&project, &args.filter)? {
+        Filter::TestSet(set) => Filter::TestSet(
+            set.map(|set| eval::Set::expr_inter(set, dsl::built_in::persistent(), [])),
+        ),
+        Filter::Explicit(explicit) => {
+            if explicit.contains(&Id::template()) {
+                writeln!(ctx.ui.error()?, "Cannot approve template test")?;
+                eyre::bail!(OperationFailure);
+            }
+
+            Filter::Explicit(explicit)
+        }
+    };
+
+    let suite = ctx.collect_tests_with_filter(&project, filter, &args.filter)?;
+    let optimize = args
+        .optimize_refs
+        .get_or(Some(project.config().defaults.optimize_refs));
+
+    let mut illegal = vec![];
+    let mut missing = vec![];
+    let mut stale = vec![];
+    let mut approved = 0;
+
+    for test in suite.matched() {
+        let Some(test) = test.as_unit_test().filter(|t| t.kind().is_persistent()) else {
+            illegal.push(test.id());
+            continue;
+        };
+
+        let out_dir = project.unit_test_out_dir(test.id());
+
+        let hash = match fs::read_to_string(out_dir.join(runner::SOURCE_HASH_FILE)) {
+            Ok(hash) => hash,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                missing.push(test.id());
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if hash.trim() != runner::source_hash(&project, test)? {
+            stale.push(test.id());
+            continue;
+        }
+
+        let Ok(output) = Document::load(&out_dir) else {
+            missing.push(test.id());
+            continue;
+        };
+
+        test.create_reference_document(
+            &project,
+            &output,
+            optimize.then_some(&*DEFAULT_OPTIMIZE_OPTIONS),
+        )?;
+        approved += 1;
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Approved ")?;
+    cwrite!(colored(w, Color::Green), "{approved}")?;
+    writeln!(w, " {}", Term::simple("test").with(approved))?;
+    drop(w);
+
+    for (ids, message) in [
+        (
+            &illegal,
+            "Cannot approve tests without persistent references:",
+        ),
+        (&missing, "No output found for tests, run them first:"),
+        (
+            &stale,
+            "Tests changed since their last run, run them again:",
+        ),
+    ] {
+        if ids.is_empty() {
+            continue;
+        }
+
+        let mut w = ctx.ui.warn()?;
+        writeln!(w, "{message}")?;
+        for id in ids {
+            ui::write_test_id(&mut w, id)?;
+            writeln!(w)?;
+        }
+    }
+
+    if !illegal.is_empty() || !missing.is_empty() || !stale.is_empty() {
+        eyre::bail!(OperationFailure);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use std::io::Write;
+
+use color_eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use termcolor::Color;
+use tytanic_core::bench;
+use tytanic_core::bench::Baselines;
+use tytanic_core::bench::Iterations;
+use tytanic_core::doc::render;
+use tytanic_core::test::Annotation;
+use tytanic_utils::fmt::Term;
+
+use super::CompileOptions;
+use super::Context;
+use super::FilterOptions;
+use crate::cli::TestFailure;
+use crate::cwrite;
+use crate::ui;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "bench-args")]
+pub struct Args {
+    /// The number of iterations to run before measuring each test.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub warmup: usize,
+
+    /// The number of measured iterations of each test.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 5,
+        value_parser = clap::value_parser!(u32).range(1..),
+    )]
+    pub iterations: u32,
+
+    /// The factor by which a test must be slower than its baseline to be
+    /// reported as regressed.
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.25)]
+    pub max_slowdown: f64,
+
+    /// Fail if any test regressed instead of only warning about it.
+    #[arg(long)]
+    pub deny_slowdown: bool,
+
+    /// Store the measurements as the new baselines of all benchmarked tests.
+    ///
+    /// Tests without a baseline always store their measurement.
+    #[arg(long)]
+    pub save: bool,
+
+    /// The pixel-per-inch value to render tests at.
+    ///
+    /// Defaults to `144.0`, can be configured in the manifest.
+    #[arg(long)]
+    pub ppi: Option<f32>,
+
+    #[command(flatten)]
+    pub compile: CompileOptions,
+
+    #[command(flatten)]
+    pub filter: FilterOptions,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite =
+        ctx.collect_tests_with_filter(&project, ctx.filter(&project, &args.filter)?, &args.filter)?;
+    let world = ctx.world(&args.compile)?;
+
+    let iterations = Iterations {
+        warmup: args.warmup,
+        measured: args.iterations as usize,
+    };
+    let ppi = args.ppi.unwrap_or(project.config().defaults.ppi);
+
+    let mut failed = 0;
+    let mut regressed = 0;
+    let mut saved = 0;
+
+    // NOTE(tinger): Tests are benchmarked one after another, running them in
+    // parallel would skew the measurements.
+    for test in suite.matched().unit_tests() {
+        let mut ppi = ppi;
+        for annot in test.annotations().iter() {
+            if let Annotation::Ppi(set) = annot {
+                ppi = *set;
+            }
+        }
+
+        let pixel_per_pt = (!test.kind().is_compile_only()).then(|| render::ppi_to_ppp(ppi));
+
+        let source = test.load_source(&project)?;
+        let measurement = bench::measure(source, &world, iterations, pixel_per_pt, |w| {
+            w.augment_standard_library(true).inputs(test.inputs())
+        });
+
+        let mut w = ctx.ui.stderr();
+        ui::write_test_id(&mut w, test.id())?;
+
+        let measurement = match measurement {
+            Ok(measurement) => measurement,
+            Err(_) => {
+                write!(w, " ")?;
+                cwrite!(bold_colored(w, Color::Red), "failed to compile")?;
+                writeln!(w)?;
+                failed += 1;
+                continue;
+            }
+        };
+
+        write!(w, " compile ")?;
+        cwrite!(bold(w), "{:.2?}", measurement.compile().median())?;
+        if let Some(render) = measurement.render() {
+            write!(w, ", render ")?;
+            cwrite!(bold(w), "{:.2?}", render.median())?;
+        }
+
+        let mut baselines = Baselines::load(&project, test.id())
+            .wrap_err_with(|| format!("loading baselines of {}", test.id()))?;
+
+        match baselines.get(test.id()) {
+            Some(baseline) => {
+                let slowdown = measurement.slowdown(baseline);
+                let color = if slowdown > args.max_slowdown {
+                    regressed += 1;
+                    Color::Red
+                } else if slowdown > 1.0 {
+                    Color::Yellow
+                } else {
+                    Color::Green
+                };
+
+                write!(w, " (")?;
+                cwrite!(colored(w, color), "{:+.1}%", (slowdown - 1.0) * 100.0)?;
+                write!(w, " vs. {:.2?})", baseline.median())?;
+            }
+            None => write!(w, " (no baseline)")?,
+        }
+        writeln!(w)?;
+
+        if args.save || baselines.get(test.id()).is_none() {
+            baselines.set(test.id(), measurement);
+            baselines
+                .save(&project, test.id())
+                .wrap_err_with(|| format!("saving baselines of {}", test.id()))?;
+            saved += 1;
+        }
+    }
+
+    let mut w = ctx.ui.stderr();
+    writeln!(w)?;
+    if saved != 0 {
+        write!(w, "Saved ")?;
+        cwrite!(bold(w), "{saved}")?;
+        writeln!(w, " {}", Term::simple("baseline").with(saved))?;
+    }
+    drop(w);
+
+    if failed != 0 {
+        writeln!(
+            ctx.ui.error()?,
+            "{failed} {} failed to compile",
+            Term::simple("test").with(failed),
+        )?;
+        eyre::bail!(TestFailure);
+    }
+
+    if regressed != 0 {
+        let message = format!(
+            "{regressed} {} slower than {}x their baseline",
+            Term::simple("test").with(regressed),
+            args.max_slowdown,
+        );
+
+        if args.deny_slowdown {
+            writeln!(ctx.ui.error()?, "{message}")?;
+            eyre::bail!(TestFailure);
+        }
+
+        writeln!(ctx.ui.warn()?, "{message}")?;
+    }
+
+    Ok(())
+}
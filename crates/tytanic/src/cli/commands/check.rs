@@ -0,0 +1,117 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use typst::diag::Warned;
+use typst::syntax::FileId;
+use typst::syntax::Source;
+use typst::syntax::VirtualPath;
+use tytanic_core::doc::compile;
+use tytanic_utils::fmt::Term;
+
+use super::CompileOptions;
+use super::Context;
+use crate::cli::OperationFailure;
+use crate::cli::TestFailure;
+use crate::cwrite;
+use crate::ui;
+use crate::world::SystemWorld;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "check-args")]
+pub struct Args {
+    #[command(flatten)]
+    pub compile: CompileOptions,
+
+    /// The Typst file to compile.
+    #[arg(value_name = "PATH")]
+    pub path: PathBuf,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let world = ctx.world(&args.compile)?;
+
+    let (source, rel) = load_source(ctx, &world, &args.path)?;
+
+    // NOTE(tinger): We augment the standard library such that test scripts
+    // can be checked too.
+    let Warned { output, warnings } = compile::compile(
+        source,
+        &world,
+        args.compile.warnings(&project.config().defaults),
+        |w| w.augment_standard_library(true),
+    );
+
+    let errors = output.as_ref().err().map(|error| &error.0[..]);
+
+    ui::write_diagnostics(
+        &mut ctx.ui.stderr(),
+        ctx.ui.diagnostic_config(),
+        &world,
+        &warnings,
+        errors.unwrap_or_default(),
+    )?;
+
+    match output {
+        Ok(doc) => {
+            let mut w = ctx.ui.stderr();
+            cwrite!(bold_colored(w, Color::Green), "Compiled")?;
+            write!(w, " {} with ", rel.display())?;
+            cwrite!(bold(w), "{}", doc.pages.len())?;
+            write!(w, " {} and ", Term::simple("page").with(doc.pages.len()))?;
+            cwrite!(bold(w), "{}", warnings.len())?;
+            writeln!(w, " {}", Term::simple("warning").with(warnings.len()))?;
+
+            Ok(())
+        }
+        Err(error) => {
+            writeln!(
+                ctx.ui.error()?,
+                "Compilation of {} failed with {} {}",
+                rel.display(),
+                error.0.len(),
+                Term::simple("error").with(error.0.len()),
+            )?;
+
+            eyre::bail!(TestFailure);
+        }
+    }
+}
+
+/// Loads the given file as a source within the root of the given world,
+/// returns it along with its path relative to the root.
+pub fn load_source(
+    ctx: &Context,
+    world: &SystemWorld,
+    path: &Path,
+) -> eyre::Result<(Source, PathBuf)> {
+    let abs = match path.canonicalize() {
+        Ok(abs) => abs,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            writeln!(ctx.ui.error()?, "File '{}' not found", path.display())?;
+            eyre::bail!(OperationFailure);
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let Ok(rel) = abs.strip_prefix(world.root()) else {
+        writeln!(
+            ctx.ui.error()?,
+            "File '{}' must be inside the project root",
+            path.display()
+        )?;
+        eyre::bail!(OperationFailure);
+    };
+
+    let source = Source::new(
+        FileId::new(None, VirtualPath::new(rel)),
+        fs::read_to_string(&abs)?,
+    );
+
+    Ok((source, rel.to_path_buf()))
+}
@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use color_eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use serde::Deserialize;
+use termcolor::Color;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::TestFailure;
+use crate::cwrite;
+use crate::json::DurationJson;
+
+/// The minimum absolute slowdown for a test to be considered regressed, this
+/// avoids reporting noise in very fast tests.
+const MIN_SLOWDOWN: Duration = Duration::from_millis(10);
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "compare-runs-args")]
+pub struct Args {
+    /// The JSON report of the baseline run.
+    #[arg(value_name = "BASE")]
+    pub base: PathBuf,
+
+    /// The JSON report of the run to compare against the baseline.
+    #[arg(value_name = "HEAD")]
+    pub head: PathBuf,
+
+    /// The factor by which a test must be slower than in the baseline to be
+    /// reported as regressed.
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.5)]
+    pub max_slowdown: f64,
+}
+
+/// The parts of a JSON report we need for comparison.
+#[derive(Debug, Deserialize)]
+struct RunJson {
+    id: String,
+    tests: Vec<RunTestJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RunTestJson {
+    id: String,
+    stage: String,
+    duration: DurationJson,
+}
+
+impl RunTestJson {
+    fn is_fail(&self) -> bool {
+        self.stage.starts_with("failed-")
+    }
+
+    fn is_pass(&self) -> bool {
+        self.stage.starts_with("passed-") || self.stage == "updated"
+    }
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let base = load(&args.base)?;
+    let head = load(&args.head)?;
+
+    let base_tests: BTreeMap<_, _> = base.tests.iter().map(|t| (t.id.as_str(), t)).collect();
+    let head_tests: BTreeMap<_, _> = head.tests.iter().map(|t| (t.id.as_str(), t)).collect();
+
+    let mut failing = vec![];
+    let mut passing = vec![];
+    let mut regressed = vec![];
+
+    for (id, new) in &head_tests {
+        let Some(old) = base_tests.get(id) else {
+            continue;
+        };
+
+        if old.is_pass() && new.is_fail() {
+            failing.push(*id);
+        } else if old.is_fail() && new.is_pass() {
+            passing.push(*id);
+        }
+
+        let old = Duration::from(&old.duration);
+        let new = Duration::from(&new.duration);
+        if new > old.mul_f64(args.max_slowdown) && new - old >= MIN_SLOWDOWN {
+            regressed.push((*id, old, new));
+        }
+    }
+
+    let added = head_tests
+        .keys()
+        .filter(|id| !base_tests.contains_key(*id))
+        .count();
+    let removed = base_tests
+        .keys()
+        .filter(|id| !head_tests.contains_key(*id))
+        .count();
+
+    let mut w = ctx.ui.stderr();
+
+    write!(w, "Comparing run ")?;
+    cwrite!(bold(w), "{}", head.id)?;
+    write!(w, " against ")?;
+    cwrite!(bold(w), "{}", base.id)?;
+    writeln!(w)?;
+    writeln!(w)?;
+
+    for (ids, message, color) in [
+        (&failing, "Newly failing tests:", Color::Red),
+        (&passing, "Newly passing tests:", Color::Green),
+    ] {
+        if ids.is_empty() {
+            continue;
+        }
+
+        writeln!(w, "{message}")?;
+        for id in ids {
+            cwrite!(colored(w, color), "  {id}\n")?;
+        }
+        writeln!(w)?;
+    }
+
+    if !regressed.is_empty() {
+        writeln!(w, "Slower tests:")?;
+        for (id, old, new) in &regressed {
+            cwrite!(colored(w, Color::Yellow), "  {id}")?;
+            writeln!(w, " ({old:.2?} -> {new:.2?})")?;
+        }
+        writeln!(w)?;
+    }
+
+    cwrite!(bold(w), "{}", failing.len())?;
+    write!(w, " newly ")?;
+    cwrite!(colored(w, Color::Red), "failing")?;
+    write!(w, ", ")?;
+    cwrite!(bold(w), "{}", passing.len())?;
+    write!(w, " newly ")?;
+    cwrite!(colored(w, Color::Green), "passing")?;
+    write!(w, ", ")?;
+    cwrite!(bold(w), "{}", regressed.len())?;
+    write!(w, " ")?;
+    cwrite!(colored(w, Color::Yellow), "slower")?;
+
+    for (len, kind) in [(added, "added"), (removed, "removed")] {
+        if len != 0 {
+            write!(w, ", ")?;
+            cwrite!(bold(w), "{len}")?;
+            write!(w, " {} {kind}", Term::simple("test").with(len))?;
+        }
+    }
+    writeln!(w)?;
+
+    if !failing.is_empty() {
+        eyre::bail!(TestFailure);
+    }
+
+    Ok(())
+}
+
+fn load(path: &Path) -> eyre::Result<RunJson> {
+    let file = File::open(path).wrap_err_with(|| format!("couldn't open {path:?}"))?;
+    serde_json::from_reader(BufReader::new(file))
+        .wrap_err_with(|| format!("couldn't parse JSON report {path:?}"))
+}
@@ -0,0 +1,144 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use termcolor::Color;
+use typst::diag::Warned;
+use tytanic_core::coverage;
+use tytanic_core::doc::compile;
+use tytanic_core::doc::compile::Warnings;
+use tytanic_utils::fmt::Term;
+
+use super::CompileOptions;
+use super::Context;
+use super::FilterOptions;
+use crate::coverage::file_path;
+use crate::cwrite;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "coverage-args")]
+pub struct Args {
+    /// Write an LCOV tracefile of the coverage to the given path.
+    #[arg(long, value_name = "PATH")]
+    pub lcov: Option<PathBuf>,
+
+    /// Write a Cobertura XML report of the coverage to the given path.
+    #[arg(long, value_name = "PATH")]
+    pub cobertura: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub compile: CompileOptions,
+
+    #[command(flatten)]
+    pub filter: FilterOptions,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite =
+        ctx.collect_tests_with_filter(&project, ctx.filter(&project, &args.filter)?, &args.filter)?;
+    let world = ctx.world(&args.compile)?;
+
+    // NOTE(tinger): Only the test scripts are compiled, references don't
+    // exercise the project sources in a meaningful way.
+    let mut failed = 0;
+    for test in suite.matched().unit_tests() {
+        let source = test.load_source(&project)?;
+        let Warned { output, .. } = compile::compile(source, &world, Warnings::Ignore, |w| {
+            w.augment_standard_library(true)
+                .inputs(test.inputs())
+                .coverage(true)
+        });
+
+        if output.is_err() {
+            failed += 1;
+        }
+    }
+
+    let tests_root = project.unit_tests_root();
+    let mut report = coverage::report();
+    report.retain(|id| {
+        !project
+            .root()
+            .join(id.vpath().as_rootless_path())
+            .starts_with(&tests_root)
+    });
+
+    if failed != 0 {
+        let mut w = ctx.ui.warn()?;
+        cwrite!(colored(w, Color::Red), "{failed}")?;
+        writeln!(
+            w,
+            " {} failed to compile, coverage may be incomplete",
+            Term::simple("test").with(failed),
+        )?;
+    }
+
+    if let Some(path) = &args.lcov {
+        let file = File::create(path)
+            .wrap_err_with(|| format!("creating LCOV report {}", path.display()))?;
+        crate::coverage::write_lcov(BufWriter::new(file), &report)?;
+    }
+
+    if let Some(path) = &args.cobertura {
+        let file = File::create(path)
+            .wrap_err_with(|| format!("creating Cobertura report {}", path.display()))?;
+        crate::coverage::write_cobertura(BufWriter::new(file), project.root(), &report)?;
+    }
+
+    if report.files().is_empty() {
+        writeln!(
+            ctx.ui.stderr(),
+            "No project sources were loaded by the tests"
+        )?;
+        return Ok(());
+    }
+
+    let paths: Vec<_> = report.files().keys().map(|id| file_path(*id)).collect();
+    let pad = paths.iter().map(String::len).max().unwrap_or_default();
+
+    let mut w = ctx.ui.stderr();
+    for (path, file) in paths.iter().zip(report.files().values()) {
+        write_line(&mut w, path, pad, file.hits(), file.lines(), file.ratio())?;
+    }
+
+    writeln!(w)?;
+    write_line(
+        &mut w,
+        "Total",
+        pad,
+        report.hits(),
+        report.lines(),
+        report.ratio(),
+    )?;
+
+    Ok(())
+}
+
+/// Writes a single line of the coverage summary.
+fn write_line<W: termcolor::WriteColor>(
+    mut w: W,
+    path: &str,
+    pad: usize,
+    hits: usize,
+    lines: usize,
+    ratio: f64,
+) -> eyre::Result<()> {
+    let color = if ratio >= 0.8 {
+        Color::Green
+    } else if ratio >= 0.5 {
+        Color::Yellow
+    } else {
+        Color::Red
+    };
+
+    cwrite!(colored(w, Color::Cyan), "{path: <pad$}")?;
+    write!(w, " {hits: >5}/{lines: <5} ")?;
+    cwrite!(bold_colored(w, color), "{: >6.2}%", ratio * 100.0)?;
+    writeln!(w)?;
+
+    Ok(())
+}
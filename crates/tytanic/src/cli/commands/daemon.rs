@@ -0,0 +1,41 @@
+use std::io::Write;
+use std::net::TcpListener;
+
+use color_eyre::eyre;
+use termcolor::Color;
+
+use super::Context;
+use crate::cwrite;
+use crate::remote;
+use crate::remote::Roots;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "daemon-args")]
+pub struct Args {
+    /// The address to listen on.
+    ///
+    /// There is no authentication, the daemon should only listen on the
+    /// loopback interface.
+    #[arg(long, value_name = "ADDR", default_value = remote::DAEMON_ADDR)]
+    pub listen: String,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let listener = TcpListener::bind(&args.listen)?;
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Listening on ")?;
+    cwrite!(colored(w, Color::Cyan), "{}", listener.local_addr()?)?;
+    writeln!(w, ", press Ctrl+C to stop")?;
+    drop(w);
+
+    remote::serve(
+        listener,
+        ctx.args.font.clone(),
+        ctx.args.package.clone(),
+        Roots::Any,
+        true,
+    )?;
+
+    Ok(())
+}
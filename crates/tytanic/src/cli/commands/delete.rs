@@ -23,8 +23,9 @@ pub struct Args {
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let project = ctx.project()?;
+    ctx.ensure_not_package_dir(&project)?;
 
-    let filter = match ctx.filter(&args.filter)? {
+    let filter = match ctx.filter(&project, &args.filter)? {
         Filter::TestSet(set) => {
             Filter::TestSet(set.map(|set| eval::Set::expr_diff(set, dsl::built_in::template())))
         }
@@ -38,7 +39,7 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
         }
     };
 
-    let suite = ctx.collect_tests_with_filter(&project, filter)?;
+    let suite = ctx.collect_tests_with_filter(&project, filter, &args.filter)?;
 
     if suite.matched().len() > 1 {
         if let Filter::TestSet(set) = suite.filter() {
@@ -0,0 +1,464 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use typst::foundations::Dict;
+use tytanic_core::config::DocumentInfo;
+use tytanic_core::config::DocumentManifest;
+use tytanic_core::test::unit::Reference;
+use tytanic_core::test::Id;
+use tytanic_core::test::UnitTest;
+use tytanic_core::Project;
+use tytanic_utils::fmt::Term;
+
+use super::new::persistent_reference;
+use super::CompileOptions;
+use super::Context;
+use super::ExportOptions;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+use crate::ui;
+
+/// The path of the CI workflow created by the package template, relative to
+/// the project root.
+const CI_WORKFLOW_PATH: &str = ".github/workflows/tests.yml";
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "init-args")]
+pub struct Args {
+    /// The template to scaffold the test suite from.
+    ///
+    /// This is either one of the built-in templates `minimal`, `package` or
+    /// `thesis`, or the path to a directory which is copied into the test
+    /// root.
+    #[arg(long, default_value = "minimal", value_name = "TEMPLATE")]
+    pub template: InitTemplate,
+
+    #[command(flatten)]
+    pub compile: CompileOptions,
+
+    #[command(flatten)]
+    pub export: ExportOptions,
+}
+
+/// The template used for scaffolding a test suite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InitTemplate {
+    /// A single compile-only test.
+    Minimal,
+
+    /// An example test of each kind and a CI workflow.
+    Package,
+
+    /// Persistent tests for the layout of a larger document.
+    Thesis,
+
+    /// A user-provided directory which is copied into the test root.
+    Dir(PathBuf),
+}
+
+impl FromStr for InitTemplate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "minimal" => Self::Minimal,
+            "package" => Self::Package,
+            "thesis" => Self::Thesis,
+            _ => {
+                let path = PathBuf::from(s);
+                if !path.is_dir() {
+                    return Err(format!(
+                        "expected one of minimal, package or thesis, or a directory, got {s:?}"
+                    ));
+                }
+
+                Self::Dir(path)
+            }
+        })
+    }
+}
+
+/// The reference of a built-in template test.
+enum TemplateReference {
+    CompileOnly,
+    Ephemeral(&'static str),
+    Persistent,
+}
+
+/// A test of a built-in template.
+struct TemplateTest {
+    id: &'static str,
+    source: &'static str,
+    reference: TemplateReference,
+}
+
+const MINIMAL: &[TemplateTest] = &[TemplateTest {
+    id: "example",
+    source: "\
+/// Synopsis:
+/// A compile-only test, this fails if the document doesn't compile.
+
+#set page(width: 120pt, height: auto, margin: 10pt)
+
+= Example
+Hello World
+",
+    reference: TemplateReference::CompileOnly,
+}];
+
+const PACKAGE: &[TemplateTest] = &[
+    TemplateTest {
+        id: "examples/compile-only",
+        source: "\
+/// Synopsis:
+/// A compile-only test, use assertions to check the behavior of your code.
+
+#assert.eq(1 + 1, 2)
+#assert.ne(str(1), \"one\")
+",
+        reference: TemplateReference::CompileOnly,
+    },
+    TemplateTest {
+        id: "examples/ephemeral",
+        source: "\
+/// Synopsis:
+/// An ephemeral test, the output is compared to the output of `ref.typ`.
+
+#set page(width: 120pt, height: auto, margin: 10pt)
+
+#let greet(name) = [Hello #name!]
+#greet(\"World\")
+",
+        reference: TemplateReference::Ephemeral(
+            "\
+#set page(width: 120pt, height: auto, margin: 10pt)
+
+Hello World!
+",
+        ),
+    },
+    TemplateTest {
+        id: "examples/persistent",
+        source: "\
+/// Synopsis:
+/// A persistent test, the output is compared to the stored references in
+/// `ref`, these can be updated using `tt update`.
+
+#set page(width: 120pt, height: auto, margin: 10pt)
+
+= Persistent
+#lorem(20)
+",
+        reference: TemplateReference::Persistent,
+    },
+];
+
+const THESIS: &[TemplateTest] = &[
+    TemplateTest {
+        id: "layout/title-page",
+        source: "\
+/// Synopsis:
+/// Checks the layout of the title page.
+
+#set page(width: 200pt, height: 280pt)
+#set align(center + horizon)
+
+#text(2em, weight: \"bold\")[Title of the Thesis]
+
+#v(1em)
+Jane Doe
+",
+        reference: TemplateReference::Persistent,
+    },
+    TemplateTest {
+        id: "layout/headings",
+        source: "\
+/// Synopsis:
+/// Checks the numbering and spacing of headings.
+
+#set page(width: 200pt, height: auto, margin: 10pt)
+#set heading(numbering: \"1.1\")
+
+= Introduction
+#lorem(10)
+
+== Motivation
+#lorem(10)
+",
+        reference: TemplateReference::Persistent,
+    },
+    TemplateTest {
+        id: "layout/outline",
+        source: "\
+/// Synopsis:
+/// Checks that the outline contains all headings.
+
+#set heading(numbering: \"1.1\")
+
+#outline()
+
+= Introduction
+= Conclusion
+
+#context assert.eq(query(heading.where(outlined: true)).len(), 2)
+",
+        reference: TemplateReference::CompileOnly,
+    },
+];
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+
+    let len = match &args.template {
+        InitTemplate::Minimal => init_builtin(ctx, &project, args, MINIMAL)?,
+        InitTemplate::Package => {
+            let len = init_builtin(ctx, &project, args, PACKAGE)?;
+            init_ci_workflow(ctx, &project)?;
+            len
+        }
+        InitTemplate::Thesis => {
+            let len = init_builtin(ctx, &project, args, THESIS)?;
+            init_document_manifest(ctx, &project)?;
+            len
+        }
+        InitTemplate::Dir(dir) => init_dir(ctx, &project, dir)?,
+    };
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Initialized ")?;
+    cwrite!(colored(w, Color::Green), "{len}")?;
+    writeln!(w, " {}", Term::simple("test").with(len))?;
+
+    let mut w = ctx.ui.hint()?;
+    write!(w, "Run the tests using ")?;
+    cwrite!(colored(w, Color::Cyan), "tt run")?;
+    writeln!(w)?;
+
+    Ok(())
+}
+
+fn init_builtin(
+    ctx: &mut Context,
+    project: &Project,
+    args: &Args,
+    tests: &[TemplateTest],
+) -> eyre::Result<usize> {
+    let suite = ctx.collect_tests(project)?;
+
+    let ids = tests
+        .iter()
+        .map(|test| Id::new(test.id))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let existing: Vec<_> = ids.iter().filter(|id| suite.contains(id)).collect();
+    if !existing.is_empty() {
+        let mut w = ctx.ui.error()?;
+        writeln!(w, "These tests already exist:")?;
+        for id in existing {
+            write!(w, "  ")?;
+            ui::write_test_id(&mut w, id)?;
+            writeln!(w)?;
+        }
+        eyre::bail!(OperationFailure);
+    }
+
+    for (test, id) in tests.iter().zip(ids) {
+        let reference = match test.reference {
+            TemplateReference::CompileOnly => None,
+            TemplateReference::Ephemeral(reference) => Some(Reference::Ephemeral(reference.into())),
+            TemplateReference::Persistent => {
+                let path = project.unit_test_script(&id);
+                let path = path
+                    .strip_prefix(project.root())
+                    .expect("test is in project root");
+
+                Some(persistent_reference(
+                    ctx,
+                    project,
+                    path,
+                    test.source,
+                    Dict::new(),
+                    &args.compile,
+                    &args.export,
+                )?)
+            }
+        };
+
+        UnitTest::create(project, project.vcs(), id, test.source, reference)?;
+    }
+
+    Ok(tests.len())
+}
+
+fn init_ci_workflow(ctx: &mut Context, project: &Project) -> eyre::Result<()> {
+    let path = project.root().join(CI_WORKFLOW_PATH);
+
+    if path.try_exists()? {
+        let mut w = ctx.ui.warn()?;
+        write!(w, "Not creating CI workflow, ")?;
+        cwrite!(colored(w, Color::Cyan), "{CI_WORKFLOW_PATH}")?;
+        writeln!(w, " already exists")?;
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        tytanic_utils::fs::create_dir(parent, true)?;
+    }
+
+    fs::write(
+        &path,
+        format!(
+            "\
+name: Tests
+on:
+  push:
+    branches: [ main ]
+  pull_request:
+    branches: [ main ]
+
+jobs:
+  tests:
+    runs-on: ubuntu-latest
+    steps:
+      - name: Checkout
+        uses: actions/checkout@v4
+
+      - name: Install tytanic
+        uses: taiki-e/cache-cargo-install-action@v2
+        with:
+          tool: tytanic@{}
+
+      - name: Run test suite
+        run: tt run --no-fail-fast
+
+      - name: Archive artifacts
+        uses: actions/upload-artifact@v4
+        if: always()
+        with:
+          name: artifacts
+          path: |
+            {tests}/**/diff/*.png
+            {tests}/**/out/*.png
+            {tests}/**/ref/*.png
+          retention-days: 5
+",
+            env!("CARGO_PKG_VERSION"),
+            tests = project.config().unit_tests_root,
+        ),
+    )?;
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Created CI workflow at ")?;
+    cwrite!(colored(w, Color::Cyan), "{CI_WORKFLOW_PATH}")?;
+    writeln!(w)?;
+
+    Ok(())
+}
+
+/// Creates a document manifest for projects without any manifest, such that
+/// the project root is discovered without `--root`.
+fn init_document_manifest(ctx: &mut Context, project: &Project) -> eyre::Result<()> {
+    if project.manifest().is_some() || project.document().is_some() {
+        return Ok(());
+    }
+
+    let name = project
+        .root()
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| String::from("document"));
+
+    // NOTE(tinger): We only guess the most common entrypoint, others can be
+    // added by the user.
+    let entrypoints = ["main.typ"]
+        .into_iter()
+        .filter(|entrypoint| project.root().join(entrypoint).is_file())
+        .map(String::from)
+        .collect();
+
+    let manifest = DocumentManifest {
+        document: DocumentInfo { name, entrypoints },
+        config: None,
+    };
+
+    let path = project.document_manifest_file();
+    fs::write(&path, toml::to_string(&manifest)?)?;
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Created document manifest at ")?;
+    let path = path.strip_prefix(project.root()).unwrap_or(&path);
+    cwrite!(colored(w, Color::Cyan), "{}", path.display())?;
+    writeln!(w)?;
+
+    Ok(())
+}
+
+fn init_dir(ctx: &mut Context, project: &Project, dir: &Path) -> eyre::Result<usize> {
+    let root = project.unit_tests_root();
+
+    let mut files = vec![];
+    collect_files(dir, Path::new(""), &mut files)?;
+
+    let existing: Vec<_> = files
+        .iter()
+        .filter(|file| root.join(file).exists())
+        .collect();
+
+    if !existing.is_empty() {
+        let mut w = ctx.ui.error()?;
+        writeln!(w, "These files already exist:")?;
+        for file in existing {
+            writeln!(w, "  {}", root.join(file).display())?;
+        }
+        eyre::bail!(OperationFailure);
+    }
+
+    let before = ctx.collect_tests(project)?;
+
+    for file in &files {
+        let target = root.join(file);
+        if let Some(parent) = target.parent() {
+            tytanic_utils::fs::create_dir(parent, true)?;
+        }
+        fs::copy(dir.join(file), target)?;
+    }
+
+    let suite = ctx.collect_tests(project)?;
+
+    let mut len = 0;
+    for test in suite.unit_tests() {
+        if before.contains(test.id()) {
+            continue;
+        }
+
+        if let Some(vcs) = project.vcs() {
+            vcs.ignore(project, test)?;
+        }
+
+        len += 1;
+    }
+
+    Ok(len)
+}
+
+/// Collects the paths of all files in the given directory relative to it.
+fn collect_files(root: &Path, rel: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(root.join(rel))? {
+        let entry = entry?;
+        let rel = rel.join(entry.file_name());
+
+        if entry.file_type()?.is_dir() {
+            collect_files(root, &rel, files)?;
+        } else {
+            files.push(rel);
+        }
+    }
+
+    Ok(())
+}
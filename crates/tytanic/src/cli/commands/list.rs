@@ -24,7 +24,8 @@ pub struct Args {
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let project = ctx.project()?;
-    let suite = ctx.collect_tests_with_filter(&project, ctx.filter(&args.filter)?)?;
+    let suite =
+        ctx.collect_tests_with_filter(&project, ctx.filter(&project, &args.filter)?, &args.filter)?;
 
     if args.json {
         serde_json::to_writer_pretty(
@@ -72,6 +73,11 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
                     write!(w, " ")?;
                     cwrite!(bold_colored(w, Color::Cyan), "skip")?;
                 }
+
+                if let Some(notes) = test.load_notes(&project)? {
+                    writeln!(w)?;
+                    cwrite!(italic(w), "  {notes}")?;
+                }
             }
             Test::Template(_) => {
                 cwrite!(bold_colored(w, Color::Magenta), "{: <12}", "template")?;
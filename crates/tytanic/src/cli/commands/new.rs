@@ -1,9 +1,15 @@
+use std::borrow::Cow;
+use std::fs;
 use std::io::Write;
 use std::ops::Not;
+use std::path::Path;
 
 use color_eyre::eyre;
 use termcolor::Color;
 use typst::diag::Warned;
+use typst::foundations::Dict;
+use typst::foundations::Str;
+use typst::foundations::Value;
 use typst_syntax::FileId;
 use typst_syntax::Source;
 use typst_syntax::VirtualPath;
@@ -12,8 +18,10 @@ use tytanic_core::doc::Document;
 use tytanic_core::test::unit::Kind;
 use tytanic_core::test::unit::Reference;
 use tytanic_core::test::unit::DEFAULT_TEST_INPUT;
+use tytanic_core::test::Annotation;
 use tytanic_core::test::Id;
 use tytanic_core::test::UnitTest;
+use tytanic_core::Project;
 
 use super::CompileOptions;
 use super::Context;
@@ -55,18 +63,46 @@ pub struct Args {
     #[command(flatten)]
     pub export: ExportOptions,
 
+    /// Create a test which compiles the given document entrypoint instead of
+    /// its own test script.
+    ///
+    /// The path is relative to the project root. The test script only holds
+    /// the `entrypoint` and `input` annotations, such that the full rendered
+    /// output of the document is compared.
+    #[arg(long = "for", value_name = "PATH")]
+    pub entrypoint: Option<String>,
+
+    /// Pin an input of the entrypoint test available as `sys.inputs` (can be
+    /// repeated).
+    #[arg(
+        long = "input",
+        value_name = "KEY=VALUE",
+        value_parser = parse_input,
+        requires = "entrypoint"
+    )]
+    pub inputs: Vec<(String, String)>,
+
     /// The name of the new test.
     #[arg(value_name = "NAME")]
     pub test: Id,
 }
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
-    if args.test == Id::template() {
-        writeln!(ctx.ui.error()?, "Cannot create template test")?;
+    if args.test.is_reserved() {
+        writeln!(ctx.ui.error()?, "Cannot create built-in test {}", args.test)?;
+        eyre::bail!(OperationFailure);
+    }
+
+    if args.test.has_params() {
+        writeln!(
+            ctx.ui.error()?,
+            "Cannot create a test instance, parametrize the test using a parameter matrix instead"
+        )?;
         eyre::bail!(OperationFailure);
     }
 
     let project = ctx.project()?;
+    ctx.ensure_not_package_dir(&project)?;
     let suite = ctx.collect_tests(&project)?;
 
     if suite.contains(&args.test) {
@@ -91,68 +127,65 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
         args.kind.into_native()
     };
 
-    let source = project
-        .unit_test_template()
-        .filter(|_| args.template.get_or_default())
-        .unwrap_or(DEFAULT_TEST_INPUT);
+    let source = match &args.entrypoint {
+        Some(entrypoint) => Cow::Owned(entrypoint_script(
+            ctx,
+            &project,
+            kind,
+            entrypoint,
+            &args.inputs,
+        )?),
+        None => Cow::Borrowed(
+            project
+                .unit_test_template()
+                .filter(|_| args.template.get_or_default())
+                .unwrap_or(DEFAULT_TEST_INPUT),
+        ),
+    };
 
     let reference = match kind {
         Kind::CompileOnly => None,
-        Kind::Ephemeral => Some(Reference::Ephemeral(source.into())),
-        Kind::Persistent => {
-            let world = ctx.world(&args.compile)?;
-            let path = project.unit_test_template_file();
+        Kind::Ephemeral => Some(Reference::Ephemeral(source.as_ref().into())),
+        Kind::Persistent => match &args.entrypoint {
+            Some(entrypoint) => {
+                let inputs = args
+                    .inputs
+                    .iter()
+                    .map(|(key, value)| {
+                        (Str::from(key.as_str()), Value::Str(value.as_str().into()))
+                    })
+                    .collect();
 
-            let path = path
-                .strip_prefix(project.root())
-                .expect("template is in project root");
+                Some(persistent_reference(
+                    ctx,
+                    &project,
+                    Path::new(entrypoint),
+                    &fs::read_to_string(project.root().join(entrypoint))?,
+                    inputs,
+                    &args.compile,
+                    &args.export,
+                )?)
+            }
+            None => {
+                let path = project.unit_test_template_file();
+                let path = path
+                    .strip_prefix(project.root())
+                    .expect("template is in project root");
 
-            let Warned { output, warnings } = Document::compile(
-                Source::new(FileId::new(None, VirtualPath::new(path)), source.into()),
-                &world,
-                ppi_to_ppp(args.export.ppi.unwrap_or(project.config().defaults.ppi)),
-                args.compile.warnings.into_native(),
-                // NOTE(tinger): We only use augmentation here because package
-                // rerouting should not happen for unit tests.
-                |w| w.augment_standard_library(true),
-            );
-
-            let doc = match output {
-                Ok(doc) => {
-                    ui::write_diagnostics(
-                        &mut ctx.ui.stderr(),
-                        ctx.ui.diagnostic_config(),
-                        &world,
-                        &warnings,
-                        &[],
-                    )?;
-                    doc
-                }
-                Err(err) => {
-                    ui::write_diagnostics(
-                        &mut ctx.ui.stderr(),
-                        ctx.ui.diagnostic_config(),
-                        &world,
-                        &warnings,
-                        &err.0,
-                    )?;
-                    eyre::bail!(OperationFailure);
-                }
-            };
-
-            Some(Reference::Persistent {
-                doc,
-                opt: args
-                    .export
-                    .optimize_refs
-                    .get_or_default()
-                    .not()
-                    .then(|| Box::new(DEFAULT_OPTIMIZE_OPTIONS.clone())),
-            })
-        }
+                Some(persistent_reference(
+                    ctx,
+                    &project,
+                    path,
+                    &source,
+                    Dict::new(),
+                    &args.compile,
+                    &args.export,
+                )?)
+            }
+        },
     };
 
-    UnitTest::create(&project, vcs, id, source, reference)?;
+    UnitTest::create(&project, vcs, id, &source, reference)?;
 
     let mut w = ctx.ui.stderr();
 
@@ -161,3 +194,118 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
 
     Ok(())
 }
+
+/// Returns the test script of a test compiling the given document entrypoint
+/// with the given pinned inputs, fails if the entrypoint doesn't exist.
+fn entrypoint_script(
+    ctx: &Context,
+    project: &Project,
+    kind: Kind,
+    entrypoint: &str,
+    inputs: &[(String, String)],
+) -> eyre::Result<String> {
+    if kind.is_ephemeral() {
+        writeln!(
+            ctx.ui.error()?,
+            "Cannot create an ephemeral test for an entrypoint, the reference would compile the same document"
+        )?;
+        eyre::bail!(OperationFailure);
+    }
+
+    let mut script = format!("/// [entrypoint: {entrypoint}]\n");
+    for (key, value) in inputs {
+        script.push_str(&format!("/// [input: {key}={value}]\n"));
+    }
+    script.push_str(
+        "\n// This test compiles the document entrypoint above instead of this script.\n",
+    );
+
+    // NOTE(tinger): This validates the entrypoint path like the annotation
+    // would when the test is loaded.
+    if Annotation::collect(&script).is_err() {
+        writeln!(
+            ctx.ui.error()?,
+            "Entrypoint {entrypoint} must be a path relative to the project root"
+        )?;
+        eyre::bail!(OperationFailure);
+    }
+
+    if !project.root().join(entrypoint).is_file() {
+        writeln!(ctx.ui.error()?, "Entrypoint {entrypoint} not found")?;
+        eyre::bail!(OperationFailure);
+    }
+
+    Ok(script)
+}
+
+fn parse_input(raw: &str) -> Result<(String, String), String> {
+    let (key, value) = raw
+        .split_once('=')
+        .ok_or_else(|| "input must be of the form `key=value`".to_string())?;
+
+    let key = key.trim();
+    if key.is_empty() {
+        return Err("input key must not be empty".into());
+    }
+
+    Ok((key.into(), value.trim().into()))
+}
+
+/// Compiles the given source into a persistent reference, reports diagnostics
+/// and fails if compilation fails.
+///
+/// The path is used as the virtual path of the source and must be relative to
+/// the project root, the inputs are available as `sys.inputs`.
+pub fn persistent_reference(
+    ctx: &Context,
+    project: &Project,
+    path: &Path,
+    source: &str,
+    inputs: Dict,
+    compile: &CompileOptions,
+    export: &ExportOptions,
+) -> eyre::Result<Reference> {
+    let world = ctx.world(compile)?;
+
+    let Warned { output, warnings } = Document::compile(
+        Source::new(FileId::new(None, VirtualPath::new(path)), source.into()),
+        &world,
+        ppi_to_ppp(export.ppi.unwrap_or(project.config().defaults.ppi)),
+        compile.warnings(&project.config().defaults),
+        // NOTE(tinger): We only use augmentation here because package
+        // rerouting should not happen for unit tests.
+        |w| w.augment_standard_library(true).inputs(inputs),
+    );
+
+    let doc = match output {
+        Ok(doc) => {
+            ui::write_diagnostics(
+                &mut ctx.ui.stderr(),
+                ctx.ui.diagnostic_config(),
+                &world,
+                &warnings,
+                &[],
+            )?;
+            doc
+        }
+        Err(err) => {
+            ui::write_diagnostics(
+                &mut ctx.ui.stderr(),
+                ctx.ui.diagnostic_config(),
+                &world,
+                &warnings,
+                &err.0,
+            )?;
+            eyre::bail!(OperationFailure);
+        }
+    };
+
+    Ok(Reference::Persistent {
+        doc,
+        opt: export
+            .optimize_refs
+            .get_or(Some(project.config().defaults.optimize_refs))
+            .not()
+            .then(|| Box::new(DEFAULT_OPTIMIZE_OPTIONS.clone())),
+    })
+}
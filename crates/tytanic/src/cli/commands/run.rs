@@ -1,7 +1,20 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::Arc;
+
 use color_eyre::eyre;
-use tytanic_core::doc::compare::Strategy;
+use tempdir::TempDir;
 use tytanic_core::doc::render;
 use tytanic_core::doc::render::Origin;
+use tytanic_core::history::History;
+use tytanic_core::inputs::InputHash;
+use tytanic_core::inputs::Inputs;
+use tytanic_core::project::Project;
+use tytanic_core::test::Stage;
+use tytanic_core::test::Test;
+use tytanic_core::Id;
+use tytanic_core::TOOL_NAME;
+use tytanic_utils::fmt::Term;
 
 use super::CompareOptions;
 use super::CompileOptions;
@@ -10,14 +23,27 @@ use super::Direction;
 use super::ExportOptions;
 use super::FilterOptions;
 use super::OptionDelegate;
+use super::OutputFormat;
+use super::ReportOptions;
 use super::RunnerOptions;
 use super::Switch;
+use crate::cli::OperationFailure;
 use crate::cli::TestFailure;
 use crate::cli::CANCELLED;
+use crate::container;
+use crate::container::Container;
+use crate::controls::Controls;
+use crate::kit;
+use crate::package;
+use crate::remote;
+use crate::remote::PendingTest;
+use crate::remote::Request;
+use crate::remote::WorkerPool;
 use crate::report::Reporter;
 use crate::runner::Action;
 use crate::runner::Runner;
 use crate::runner::RunnerConfig;
+use crate::ui;
 
 #[derive(clap::Args, Debug, Clone)]
 #[group(id = "run-args")]
@@ -36,12 +62,307 @@ pub struct Args {
 
     #[command(flatten)]
     pub filter: FilterOptions,
+
+    #[command(flatten)]
+    pub report: ReportOptions,
+
+    /// Copy the command to update all tests which failed comparison to the
+    /// clipboard.
+    ///
+    /// The clipboard is accessed through the terminal, if stderr is not a
+    /// terminal the command is printed to stdout on a single line instead.
+    #[arg(long)]
+    pub copy_hints: bool,
+
+    /// Open the diff images of tests which failed comparison with the default
+    /// application of the system.
+    ///
+    /// The diff directory of each failed test is opened, at most 5 are opened
+    /// to not flood the desktop, the others can be opened using `tt show`.
+    #[arg(long)]
+    pub open_diff: bool,
+
+    /// Show small previews of the reference, output and diff images of tests
+    /// which failed comparison.
+    ///
+    /// Previews are only shown if stderr is a terminal supporting the kitty,
+    /// iTerm2 or sixel graphics protocols, the protocol is detected from the
+    /// environment.
+    #[arg(long)]
+    pub preview: bool,
+
+    /// Fail the test run if any warnings were emitted, even if all tests
+    /// passed.
+    ///
+    /// Unlike `--warnings promote`, this doesn't turn warnings into errors, so
+    /// tests still run and compare as usual, only the exit code changes.
+    #[arg(long)]
+    pub fail_on_warnings: bool,
+
+    /// Only run tests whose inputs changed since they last passed.
+    ///
+    /// The inputs of a test are the files in its test directory and the
+    /// project files outside of the test root. Their hashes are stored in the
+    /// cache directory by runs using this option, changing the compile,
+    /// compare, export or font options invalidates all stored hashes. Template
+    /// tests are always run.
+    #[arg(long)]
+    pub only_changed: bool,
+
+    /// Compile unit tests against the package as it would be installed.
+    ///
+    /// The package is packed into a temporary package directory honoring the
+    /// exclude patterns of the manifest, unit tests then access all files
+    /// outside of the test directory through the installed package instead of
+    /// the project. Local packages are not available in this mode.
+    #[arg(long)]
+    pub installed: bool,
+
+    /// Run unit tests on the given remote workers (experimental).
+    ///
+    /// Workers are started using `tt worker serve` and are given as a comma
+    /// separated list of hosts with an optional port. The project is sent to
+    /// each worker, which compile and render the tests, comparison and
+    /// exports still happen locally. There is no authentication, only use
+    /// this within trusted networks.
+    #[arg(
+        long,
+        value_name = "HOSTS",
+        value_delimiter = ',',
+        conflicts_with = "installed"
+    )]
+    pub workers: Vec<String>,
+
+    /// Run unit tests on the local daemon at the given address.
+    ///
+    /// The daemon is started using `tt daemon` and keeps fonts, packages and
+    /// unchanged project files loaded between runs, the project is accessed
+    /// in place. Defaults to the default address of the daemon.
+    #[arg(
+        long,
+        value_name = "ADDR",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = remote::DAEMON_ADDR,
+        conflicts_with_all = ["installed", "workers"],
+    )]
+    pub daemon: Option<String>,
+
+    /// Where to compile unit tests.
+    ///
+    /// Either `local` or `docker[:IMAGE]`, the latter runs a worker inside a
+    /// container with the project mounted read-only and compiles all unit
+    /// tests in it, such that references are created and compared with the
+    /// exact fonts and packages of the image. Defaults to the image of the
+    /// current release.
+    #[arg(
+        long,
+        value_name = "BACKEND",
+        default_value = "local",
+        value_parser = parse_backend,
+        conflicts_with_all = ["installed", "workers", "daemon"],
+    )]
+    pub backend: Backend,
+}
+
+/// Where to compile unit tests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backend {
+    /// Compile on this machine or the given workers.
+    Local,
+
+    /// Compile in a container of the given image.
+    Docker(String),
+}
+
+fn parse_backend(raw: &str) -> Result<Backend, String> {
+    match raw {
+        "local" => Ok(Backend::Local),
+        "docker" => Ok(Backend::Docker(container::DEFAULT_IMAGE.into())),
+        _ => match raw.strip_prefix("docker:") {
+            Some(image) if !image.is_empty() => Ok(Backend::Docker(image.into())),
+            _ => Err("backend must be `local`, `docker` or `docker:IMAGE`".into()),
+        },
+    }
 }
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let project = ctx.project()?;
-    let suite = ctx.collect_tests_with_filter(&project, ctx.filter(&args.filter)?)?;
-    let world = ctx.world(&args.compile)?;
+    let profile = ctx.profile(&project);
+    let mut suite =
+        ctx.collect_tests_with_filter(&project, ctx.filter(&project, &args.filter)?, &args.filter)?;
+
+    // NOTE(tinger): The hashes are only computed when they're needed, hashing
+    // all project files can take a while for large projects.
+    let only_changed = if args.only_changed {
+        let options = InputHash::options(&format!(
+            "{:?}",
+            (
+                &args.compile,
+                &args.compare,
+                &args.export,
+                &ctx.args.font,
+                &profile,
+                args.installed,
+                &args.backend,
+            ),
+        ));
+
+        let inputs = Inputs::load(&project).unwrap_or_else(|err| {
+            tracing::warn!(?err, "couldn't load input hashes");
+            Inputs::default()
+        });
+
+        let project_hash = InputHash::project(&project)?;
+        let hashes = suite
+            .matched()
+            .unit_tests()
+            .map(|test| {
+                Ok((
+                    test.id().clone(),
+                    InputHash::unit_test(&project, &project_hash, test)?,
+                ))
+            })
+            .collect::<eyre::Result<BTreeMap<_, _>>>()?;
+
+        let before = suite.matched().len();
+        suite.retain(|test| match test {
+            Test::Unit(test) => !inputs.is_unchanged(&options, test.id(), &hashes[test.id()]),
+            Test::Template(_) => true,
+        });
+
+        let unchanged = before - suite.matched().len();
+        if unchanged != 0 {
+            writeln!(
+                ctx.ui.stderr(),
+                "Skipping {unchanged} unchanged {}",
+                Term::simple("test").with(unchanged),
+            )?;
+        }
+
+        Some((inputs, options, hashes))
+    } else {
+        None
+    };
+
+    // NOTE(tinger): The temporary directory must outlive the test run.
+    let mut installed = None;
+    let world = if args.installed {
+        let Some(package) = project.package_spec() else {
+            writeln!(
+                ctx.ui.error()?,
+                "Cannot install a project without a manifest"
+            )?;
+            eyre::bail!(OperationFailure);
+        };
+
+        let dir = TempDir::new(TOOL_NAME)?;
+        let files = package::pack(
+            &project,
+            &dir.path()
+                .join(package.namespace.as_str())
+                .join(package.name.as_str())
+                .join(package.version.to_string()),
+        )?;
+        tracing::debug!(?package, files, "installed package");
+
+        let mut package_options = ctx.args.package.clone();
+        package_options.package_path = Some(dir.path().to_path_buf());
+
+        installed = Some((package, dir));
+        kit::world(
+            ctx.root()?,
+            &ctx.args.font,
+            ctx.package_store(&package_options),
+            &args.compile,
+        )?
+        .with_relative_names(ctx.args.output.format == OutputFormat::QuietErrors)
+    } else if args.daemon.is_some() && suite.matched().template_test().is_none() {
+        // NOTE(tinger): All tests are compiled by the daemon, so we don't need
+        // to search for fonts, which is the bulk of a cold start.
+        kit::world_without_fonts(
+            ctx.root()?,
+            ctx.package_store(&ctx.args.package),
+            &args.compile,
+        )?
+        .with_relative_names(ctx.args.output.format == OutputFormat::QuietErrors)
+    } else {
+        ctx.world(&args.compile)?
+    };
+
+    let pixel_per_pt = render::ppi_to_ppp(args.export.ppi.unwrap_or(project.config().defaults.ppi));
+
+    // NOTE(tinger): The container must outlive the test run.
+    let container = match &args.backend {
+        Backend::Local => None,
+        Backend::Docker(image) => {
+            tracing::debug!(%image, "starting container");
+            match Container::start(image, project.root()) {
+                Ok(container) => Some(container),
+                Err(err) => {
+                    writeln!(ctx.ui.error()?, "Couldn't start container: {err:#}")?;
+                    eyre::bail!(OperationFailure);
+                }
+            }
+        }
+    };
+
+    let workers = match (&container, &args.daemon) {
+        (Some(container), _) => vec![container.addr().to_owned()],
+        (None, Some(daemon)) => vec![daemon.clone()],
+        (None, None) => args.workers.clone(),
+    };
+
+    let pool = if workers.is_empty() {
+        None
+    } else {
+        let warnings = args.compile.warnings(&project.config().defaults);
+
+        // NOTE(tinger): The container has the project mounted, so we don't
+        // need to send it.
+        let (files, root) = match (&container, &args.daemon) {
+            (Some(_), _) => (vec![], Some(container::PROJECT_ROOT.to_owned())),
+            (None, Some(_)) => (vec![], Some(project.root().display().to_string())),
+            (None, None) => (remote::collect_files(&project, suite.inner())?, None),
+        };
+
+        tracing::debug!(?workers, files = files.len(), "starting worker pool");
+        let tests = suite
+            .matched()
+            .unit_tests()
+            .map(|test| PendingTest {
+                id: test.id().clone(),
+                pixel_per_pt: remote::pixel_per_pt(test, pixel_per_pt),
+                timeout: test.timeout().or(args.runner.timeout),
+                serial: project.config().serial_module(test.id()).map(Into::into),
+            })
+            .collect();
+
+        let pool = WorkerPool::start(
+            &workers,
+            Request::Project {
+                version: env!("CARGO_PKG_VERSION").into(),
+                files,
+                root,
+                timestamp: args.compile.timestamp.timestamp(),
+                warnings,
+                limits: project.config().defaults.limits(),
+            },
+            tests,
+            &CANCELLED,
+        );
+
+        if let Err(errors) = pool.wait_ready() {
+            let mut w = ctx.ui.error()?;
+            writeln!(w, "Couldn't connect to any worker")?;
+            for error in errors {
+                writeln!(w, "{error}")?;
+            }
+            eyre::bail!(OperationFailure);
+        }
+
+        Some(pool)
+    };
 
     let origin = match args
         .export
@@ -53,52 +374,181 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
         Direction::Rtl => Origin::TopRight,
     };
 
-    let pixel_per_pt = render::ppi_to_ppp(args.export.ppi.unwrap_or(project.config().defaults.ppi));
-
-    let max_delta = args
+    let alpha = args
         .compare
-        .max_delta
-        .unwrap_or(project.config().defaults.max_delta);
+        .alpha
+        .map(OptionDelegate::into_native)
+        .unwrap_or(project.config().defaults.alpha);
 
-    let max_deviation = args
-        .compare
-        .max_deviations
-        .unwrap_or(project.config().defaults.max_deviations);
+    let live = ctx.ui.can_live_report()
+        && ctx.args.output.verbose == 0
+        && ctx.args.output.format == OutputFormat::Human;
+
+    // Keyboard controls are only useful if we can show their effect.
+    let controls = if live && ctx.ui.can_prompt() {
+        Controls::enable()
+            .inspect_err(|err| tracing::warn!(?err, "couldn't enable keyboard controls"))
+            .ok()
+    } else {
+        None
+    };
 
-    let runner = Runner::new(
+    let world = Arc::new(world);
+    let mut runner = Runner::new(
         &project,
         &suite,
         &world,
         RunnerConfig {
-            warnings: args.compile.warnings.into_native(),
-            optimize: args.export.optimize_refs.get_or_default(),
-            fail_fast: args.runner.fail_fast.get_or_default(),
+            warnings: args.compile.warnings(&project.config().defaults),
+            optimize: args
+                .export
+                .optimize_refs
+                .get_or(Some(project.config().defaults.optimize_refs)),
+            fail_fast: args.runner.fail_fast.get_or(profile.fail_fast),
             pixel_per_pt,
+            alpha,
+            limits: project.config().defaults.limits(),
             strategy: args
                 .compare
                 .compare
-                .get_or_default()
-                .then_some(Strategy::Simple {
-                    max_delta,
-                    max_deviation,
-                }),
-            export_ephemeral: args.export.export_ephemeral.get_or_default(),
+                .get_or(profile.compare)
+                .then_some(args.compare.strategy(&project.config().defaults)),
+            export_ephemeral: args
+                .export
+                .export_ephemeral
+                .get_or(profile.export_ephemeral),
             origin,
+            heatmap: args.export.diff_heatmap,
+            export_pdf: args.export.pdf(),
             action: Action::Run,
             cancellation: &CANCELLED,
+            timeout: args.runner.timeout,
+            meta: args.report.meta(),
+            controls: controls.as_ref(),
+            installed: installed.as_ref().map(|(package, _)| package.clone()),
+            remote: pool.as_ref(),
+            dependencies: None,
         },
     );
 
-    let reporter = Reporter::new(
-        ctx.ui,
-        &world,
-        ctx.ui.can_live_report() && ctx.args.output.verbose == 0,
-    );
+    let mut history = History::load(&project).unwrap_or_else(|err| {
+        tracing::warn!(?err, "couldn't load test history");
+        History::default()
+    });
+
+    runner
+        .result
+        .set_estimate(history.estimate(suite.matched().tests().map(Test::id)));
+
+    let preview = if args.preview {
+        let protocol = ctx.ui.image_protocol();
+        if protocol.is_none() {
+            writeln!(
+                ctx.ui.warn()?,
+                "Terminal doesn't support inline images, previews are not shown"
+            )?;
+        }
+        protocol
+    } else {
+        None
+    };
+
+    let reporter = Reporter::new(ctx.ui, &project, &world, live, ctx.args.output.verbose > 0)
+        .with_progress(ctx.progress()?)
+        .with_format(ctx.args.output.format)
+        .with_summary_by(args.report.summary_by)
+        .with_passes(!ctx.args.output.ci)
+        .with_preview(preview);
     let result = runner.run(&reporter)?;
+    drop(controls);
+
+    history.record(&result);
+    if let Err(err) = history.save(&project) {
+        tracing::warn!(?err, "couldn't save test history");
+    }
+
+    if let Some((mut inputs, options, hashes)) = only_changed {
+        inputs.record(options, &hashes, &result);
+        if let Err(err) = inputs.save(&project) {
+            tracing::warn!(?err, "couldn't save input hashes");
+        }
+    }
+
+    args.report
+        .write_reports(ctx, &project, suite.inner(), &profile, &result)?;
+
+    let updatable: Vec<_> = result
+        .results()
+        .iter()
+        .filter(|(_, test)| matches!(test.stage(), Stage::FailedComparison(_)))
+        .filter(|(id, _)| {
+            suite
+                .matched()
+                .get(id)
+                .and_then(Test::as_unit_test)
+                .is_some_and(|test| test.kind().is_persistent())
+        })
+        .map(|(id, _)| id)
+        .collect();
+
+    reporter.report_update_hint(&updatable, args.copy_hints)?;
+
+    if args.open_diff {
+        let failed: Vec<_> = result
+            .results()
+            .iter()
+            .filter(|(_, test)| matches!(test.stage(), Stage::FailedComparison(_)))
+            .map(|(id, _)| id)
+            .collect();
+
+        open_diffs(ctx, &project, &failed)?;
+    }
 
     if !result.is_complete_pass() {
         eyre::bail!(TestFailure);
     }
 
+    if args.fail_on_warnings && result.warnings() != 0 {
+        writeln!(
+            ctx.ui.error()?,
+            "Tests emitted {} {}",
+            result.warnings(),
+            Term::simple("warning").with(result.warnings()),
+        )?;
+        eyre::bail!(TestFailure);
+    }
+
+    Ok(())
+}
+
+/// The maximum number of diff directories opened by `--open-diff`.
+const MAX_OPENED_DIFFS: usize = 5;
+
+/// Opens the diff directories of the given tests, up to [`MAX_OPENED_DIFFS`].
+fn open_diffs(ctx: &mut Context, project: &Project, failed: &[&Id]) -> eyre::Result<()> {
+    for id in failed.iter().take(MAX_OPENED_DIFFS) {
+        let dir = project.unit_test_diff_dir(id);
+        if !dir.try_exists()? {
+            continue;
+        }
+
+        if let Err(err) = ui::open_path(&dir) {
+            writeln!(
+                ctx.ui.warn()?,
+                "Couldn't open {}: {err}",
+                dir.strip_prefix(project.root()).unwrap_or(&dir).display(),
+            )?;
+        }
+    }
+
+    if failed.len() > MAX_OPENED_DIFFS {
+        let rest = failed.len() - MAX_OPENED_DIFFS;
+        writeln!(
+            ctx.ui.hint()?,
+            "Didn't open the diff images of {rest} more {}, use `tt show --diff <TEST>` to open them",
+            Term::simple("test").with(rest),
+        )?;
+    }
+
     Ok(())
 }
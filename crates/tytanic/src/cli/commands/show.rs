@@ -0,0 +1,101 @@
+use std::io::Write;
+
+use clap_complete::ArgValueCandidates;
+use color_eyre::eyre;
+use termcolor::Color;
+use tytanic_core::test::Id;
+
+use super::Context;
+use crate::cli::complete;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+use crate::ui;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "show-args")]
+pub struct Args {
+    /// The test to show.
+    #[arg(value_name = "TEST", add = ArgValueCandidates::new(complete::test_ids))]
+    pub test: Id,
+
+    /// Open the output directory.
+    #[arg(long)]
+    pub out: bool,
+
+    /// Open the reference directory.
+    #[arg(long, name = "ref")]
+    pub ref_: bool,
+
+    /// Open the diff directory.
+    #[arg(long)]
+    pub diff: bool,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests(&project)?;
+
+    let Some(test) = suite.get(&args.test).and_then(|test| test.as_unit_test()) else {
+        writeln!(ctx.ui.error()?, "Unit test {} not found", args.test)?;
+        eyre::bail!(OperationFailure);
+    };
+
+    // NOTE(tinger): If no directory is selected explicitly, all of them are
+    // opened.
+    let all = !(args.out || args.ref_ || args.diff);
+    let dirs = [
+        (args.out, project.unit_test_out_dir(test.id())),
+        (args.ref_, project.unit_test_ref_dir(test.id())),
+        (args.diff, project.unit_test_diff_dir(test.id())),
+    ];
+
+    let mut opened = 0;
+    for (selected, dir) in dirs {
+        if !(all || selected) {
+            continue;
+        }
+
+        let display = dir.strip_prefix(project.root()).unwrap_or(&dir);
+        if !dir.try_exists()? {
+            if !all {
+                writeln!(ctx.ui.warn()?, "{} doesn't exist", display.display())?;
+            }
+            continue;
+        }
+
+        if let Err(err) = ui::open_path(&dir) {
+            writeln!(
+                ctx.ui.error()?,
+                "Couldn't open {}: {err}",
+                display.display()
+            )?;
+            eyre::bail!(OperationFailure);
+        }
+
+        let mut w = ctx.ui.stderr();
+        write!(w, "Opened ")?;
+        ui::write_file_link(&mut w, &dir, |mut w| {
+            cwrite!(colored(w, Color::Cyan), "{}", display.display())
+        })?;
+        writeln!(w)?;
+        opened += 1;
+    }
+
+    if opened == 0 {
+        writeln!(
+            ctx.ui.error()?,
+            "Test {} has no directories to open",
+            args.test,
+        )?;
+
+        if all || args.out {
+            let mut w = ctx.ui.hint()?;
+            write!(w, "Run the test using ")?;
+            cwrite!(colored(w, Color::Cyan), "tt run {}", args.test)?;
+            writeln!(w, " to create its output")?;
+        }
+        eyre::bail!(OperationFailure);
+    }
+
+    Ok(())
+}
@@ -2,8 +2,10 @@ use std::io::Write;
 
 use color_eyre::eyre;
 use termcolor::Color;
+use tytanic_core::suite::Fingerprint;
 use tytanic_core::test::unit::Kind;
 
+use super::util::fix_excludes;
 use super::Context;
 use crate::cwrite;
 use crate::json::ProjectJson;
@@ -14,10 +16,25 @@ pub struct Args {
     /// Print a JSON describing the project to stdout.
     #[arg(long)]
     pub json: bool,
+
+    /// Print a fingerprint of the test suite to stdout.
+    ///
+    /// The fingerprint changes whenever collecting the tests may result in a
+    /// different suite, like when tests are added or their annotations
+    /// change. Tests are not collected, which makes this cheap enough to be
+    /// used as a cache key in scripts.
+    #[arg(long, conflicts_with = "json")]
+    pub fingerprint: bool,
 }
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let project = ctx.project()?;
+
+    if args.fingerprint {
+        writeln!(ctx.ui.stdout(), "{}", Fingerprint::compute(&project)?)?;
+        return Ok(());
+    }
+
     let suite = ctx.collect_tests(&project)?;
 
     let delim_open = " ┌ ";
@@ -45,6 +62,10 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
         cwrite!(bold_colored(w, Color::Cyan), "{}", package.name)?;
         write!(w, ":")?;
         cwrite!(bold_colored(w, Color::Cyan), "{}", package.version)?;
+    } else if let Some(document) = project.document().map(|d| &d.document) {
+        write!(w, "{:>align$}{}", "Project", delim_open)?;
+        cwrite!(bold_colored(w, Color::Cyan), "{}", document.name)?;
+        write!(w, " (document)")?;
     } else {
         write!(w, "{:>align$}{}", "Project", delim_open)?;
         cwrite!(bold_colored(w, Color::Yellow), "none")?;
@@ -100,6 +121,28 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
         cwrite!(bold_colored(w, Color::Yellow), "{compile_only}")?;
         writeln!(w, " compile-only")?;
     }
+    drop(w);
+
+    if project.manifest().is_some() {
+        let missing = fix_excludes::missing_excludes(&project, &suite)?;
+
+        if !missing.is_empty() {
+            let mut w = ctx.ui.warn()?;
+            writeln!(
+                w,
+                "The manifest doesn't exclude test artifacts or large references from the package bundle, missing:"
+            )?;
+            for pattern in &missing {
+                writeln!(w, "  {pattern}")?;
+            }
+            drop(w);
+
+            let mut w = ctx.ui.hint()?;
+            write!(w, "Run ")?;
+            cwrite!(colored(w, Color::Cyan), "tt util fix-excludes")?;
+            writeln!(w, " to add them")?;
+        }
+    }
 
     Ok(())
 }
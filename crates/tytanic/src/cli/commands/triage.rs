@@ -0,0 +1,390 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use tytanic_core::config::ProjectDefaults;
+use tytanic_core::doc::compare::ColorSpace;
+use tytanic_core::doc::compare::Deltas;
+use tytanic_core::doc::render::Alpha;
+use tytanic_core::doc::Document;
+use tytanic_core::test::Annotation;
+use tytanic_core::test::UnitTest;
+use tytanic_utils::fmt::Term;
+
+use super::AlphaOption;
+use super::ColorSpaceOption;
+use super::Context;
+use super::FilterOptions;
+use super::IgnoreAntialiasingSwitch;
+use super::OptionDelegate;
+use super::Switch;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+use crate::runner;
+use crate::ui;
+
+/// The padding to use for annotations of triaged tests.
+const TRIAGE_ANNOT_PADDING: usize = 10;
+
+/// The progressively looser deltas at which deviations are reported.
+const DELTA_STEPS: &[u8] = &[2, 4, 8, 16, 32, 64, 128, u8::MAX];
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "triage-args")]
+pub struct Args {
+    /// The color space in which pixels are compared.
+    ///
+    /// Defaults to `srgb`, can be configured in the manifest.
+    #[arg(long)]
+    pub color_space: Option<ColorSpaceOption>,
+
+    /// How transparent pixels are handled.
+    ///
+    /// Defaults to `channel`, can be configured in the manifest.
+    #[arg(long)]
+    pub alpha: Option<AlphaOption>,
+
+    #[command(flatten)]
+    pub ignore_antialiasing: IgnoreAntialiasingSwitch,
+
+    #[command(flatten)]
+    pub filter: FilterOptions,
+}
+
+/// The thresholds a test is compared with.
+#[derive(Debug, Clone, Copy)]
+struct Thresholds {
+    max_delta: u8,
+    max_deviation: usize,
+    max_size_delta: u32,
+    color_space: ColorSpace,
+    alpha: Alpha,
+    ignore_antialiasing: bool,
+}
+
+impl Thresholds {
+    fn new(defaults: &ProjectDefaults, args: &Args, test: &UnitTest) -> Self {
+        let mut this = Self {
+            max_delta: defaults.max_delta,
+            max_deviation: defaults.max_deviations,
+            max_size_delta: defaults.max_size_delta,
+            color_space: args
+                .color_space
+                .map(OptionDelegate::into_native)
+                .unwrap_or(defaults.color_space),
+            alpha: args
+                .alpha
+                .map(OptionDelegate::into_native)
+                .unwrap_or(defaults.alpha),
+            ignore_antialiasing: args
+                .ignore_antialiasing
+                .get_or(Some(defaults.ignore_antialiasing)),
+        };
+
+        for annot in test.annotations() {
+            match annot {
+                Annotation::MaxDelta(set) => this.max_delta = *set,
+                Annotation::MaxDeviations(set) => this.max_deviation = *set,
+                Annotation::MaxSizeDelta(set) => this.max_size_delta = *set,
+                _ => {}
+            }
+        }
+
+        this
+    }
+}
+
+/// The result of re-comparing a single test.
+enum Triage {
+    /// The test passes at its thresholds.
+    Pass,
+
+    /// The test can't pass at any threshold because the page count differs.
+    PageCount { output: usize, reference: usize },
+
+    /// The test fails at its thresholds.
+    Fail {
+        /// The deltas of the worst page at each step.
+        steps: Vec<(u8, usize)>,
+        /// The minimum `max_delta` at the test's `max_deviation`.
+        min_delta: u8,
+        /// The deviations at the test's `max_delta`.
+        deviations: usize,
+        /// The number of compared pixels of the page with the most
+        /// deviations.
+        pixels: usize,
+        /// The minimum `max_size_delta`.
+        size_delta: u32,
+    },
+}
+
+fn triage(output: &Document, reference: &Document, thresholds: Thresholds) -> Triage {
+    let output = output.buffers();
+    let reference = reference.buffers();
+
+    if output.len() != reference.len() {
+        return Triage::PageCount {
+            output: output.len(),
+            reference: reference.len(),
+        };
+    }
+
+    let deltas_fn = if thresholds.ignore_antialiasing {
+        Deltas::ignoring_antialiasing
+    } else {
+        Deltas::new
+    };
+
+    let deltas: Vec<_> = output
+        .iter()
+        .zip(reference)
+        .map(|(output, reference)| {
+            match (
+                thresholds.alpha.composite(output),
+                thresholds.alpha.composite(reference),
+            ) {
+                (Some(output), Some(reference)) => {
+                    deltas_fn(&output, &reference, thresholds.color_space)
+                }
+                _ => deltas_fn(output, reference, thresholds.color_space),
+            }
+        })
+        .collect();
+
+    let size_delta = deltas.iter().map(Deltas::size_delta).max().unwrap_or(0);
+    let min_delta = deltas
+        .iter()
+        .map(|deltas| deltas.min_delta(thresholds.max_deviation))
+        .max()
+        .unwrap_or(0);
+
+    if size_delta <= thresholds.max_size_delta && min_delta <= thresholds.max_delta {
+        return Triage::Pass;
+    }
+
+    let (deviations, pixels) = deltas
+        .iter()
+        .map(|deltas| (deltas.deviations(thresholds.max_delta), deltas.pixels()))
+        .max()
+        .unwrap_or((0, 0));
+
+    let mut steps = vec![];
+    for &step in DELTA_STEPS
+        .iter()
+        .filter(|&&step| step > thresholds.max_delta)
+    {
+        let deviations = deltas
+            .iter()
+            .map(|deltas| deltas.deviations(step))
+            .max()
+            .unwrap_or(0);
+
+        steps.push((step, deviations));
+        if deviations == 0 {
+            break;
+        }
+    }
+
+    Triage::Fail {
+        steps,
+        min_delta,
+        deviations,
+        pixels,
+        size_delta,
+    }
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let filter = ctx.filter(&project, &args.filter)?;
+    let suite = ctx.collect_tests_with_filter(&project, filter, &args.filter)?;
+    let defaults = &project.config().defaults;
+
+    let mut missing = vec![];
+    let mut stale = vec![];
+    let mut passed = 0;
+    let mut failed = 0;
+
+    for test in suite.matched().unit_tests() {
+        if test.kind().is_compile_only() {
+            continue;
+        }
+
+        let out_dir = project.unit_test_out_dir(test.id());
+
+        let hash = match fs::read_to_string(out_dir.join(runner::SOURCE_HASH_FILE)) {
+            Ok(hash) => hash,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                missing.push(test.id());
+                continue;
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        if hash.trim() != runner::source_hash(&project, test)? {
+            stale.push(test.id());
+            continue;
+        }
+
+        let (Ok(mut output), Ok(mut reference)) = (
+            Document::load(&out_dir),
+            Document::load(project.unit_test_ref_dir(test.id())),
+        ) else {
+            missing.push(test.id());
+            continue;
+        };
+
+        let mask = test.load_mask(&project)?;
+        output.apply_mask(&mask);
+        reference.apply_mask(&mask);
+
+        let thresholds = Thresholds::new(defaults, args, test);
+        let triage = triage(&output, &reference, thresholds);
+
+        if let Triage::Pass = triage {
+            passed += 1;
+            continue;
+        }
+
+        failed += 1;
+        let mut w = ui::annotated(ctx.ui.stderr(), "fail", Color::Red, TRIAGE_ANNOT_PADDING)?;
+        ui::write_test_id(&mut w, test.id())?;
+        writeln!(w)?;
+
+        match triage {
+            Triage::Pass => unreachable!(),
+            Triage::PageCount { output, reference } => {
+                writeln!(
+                    w,
+                    "Expected {reference} {}, got {output} {}, can't pass at any threshold",
+                    Term::simple("page").with(reference),
+                    Term::simple("page").with(output),
+                )?;
+            }
+            Triage::Fail {
+                steps,
+                min_delta,
+                deviations,
+                pixels,
+                size_delta,
+            } => {
+                if size_delta > thresholds.max_size_delta {
+                    write!(w, "Pages differ in size by {size_delta}px, requires ")?;
+                    cwrite!(colored(w, Color::Cyan), "--max-size-delta {size_delta}")?;
+                    writeln!(w)?;
+                }
+
+                write!(w, "Passes with ")?;
+                cwrite!(colored(w, Color::Cyan), "--max-delta {min_delta}")?;
+                write!(w, " or ")?;
+                cwrite!(colored(w, Color::Cyan), "--max-deviations {deviations}")?;
+                writeln!(
+                    w,
+                    " ({:.2}% of pixels)",
+                    deviations as f64 / pixels.max(1) as f64 * 100.0,
+                )?;
+
+                write!(w, "Deviations at max-delta {}: ", thresholds.max_delta)?;
+                write!(w, "{deviations}")?;
+                for (step, deviations) in steps {
+                    write!(w, ", {step}: {deviations}")?;
+                }
+                writeln!(w)?;
+            }
+        }
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Triaged ")?;
+    cwrite!(bold(w), "{}", passed + failed)?;
+    write!(w, " {}, ", Term::simple("test").with(passed + failed))?;
+    cwrite!(colored(w, Color::Red), "{failed}")?;
+    write!(w, " failed, ")?;
+    cwrite!(colored(w, Color::Green), "{passed}")?;
+    writeln!(w, " passed")?;
+    drop(w);
+
+    for (ids, message) in [
+        (&missing, "No output found for tests, run them first:"),
+        (
+            &stale,
+            "Tests changed since their last run, run them again:",
+        ),
+    ] {
+        if ids.is_empty() {
+            continue;
+        }
+
+        let mut w = ctx.ui.warn()?;
+        writeln!(w, "{message}")?;
+        for id in ids {
+            ui::write_test_id(&mut w, id)?;
+            writeln!(w)?;
+        }
+    }
+
+    if !missing.is_empty() || !stale.is_empty() {
+        eyre::bail!(OperationFailure);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tiny_skia::Pixmap;
+    use tiny_skia::PremultipliedColorU8;
+
+    use super::*;
+
+    #[test]
+    fn test_triage() {
+        let output = Pixmap::new(10, 1).unwrap();
+        let mut reference = output.clone();
+        let gray = PremultipliedColorU8::from_rgba(10, 10, 10, 10).unwrap();
+        reference.pixels_mut()[..4].fill(gray);
+
+        let output = Document::new([output]);
+        let reference = Document::new([reference]);
+
+        let thresholds = Thresholds {
+            max_delta: 1,
+            max_deviation: 0,
+            max_size_delta: 0,
+            color_space: ColorSpace::Srgb,
+            alpha: Alpha::Channel,
+            ignore_antialiasing: false,
+        };
+
+        let Triage::Fail {
+            steps,
+            min_delta,
+            deviations,
+            pixels,
+            size_delta,
+        } = triage(&output, &reference, thresholds)
+        else {
+            panic!("expected failure");
+        };
+
+        assert_eq!(min_delta, 10);
+        assert_eq!(deviations, 4);
+        assert_eq!(pixels, 10);
+        assert_eq!(size_delta, 0);
+        assert_eq!(steps, [(2, 4), (4, 4), (8, 4), (16, 0)]);
+
+        assert!(matches!(
+            triage(
+                &output,
+                &reference,
+                Thresholds {
+                    max_delta: 10,
+                    ..thresholds
+                }
+            ),
+            Triage::Pass
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+use std::io::Write;
+use std::sync::Arc;
+
+use color_eyre::eyre;
+use tytanic_core::doc::render;
+use tytanic_core::doc::render::Origin;
+
+use super::CompareOptions;
+use super::CompileOptions;
+use super::Context;
+use super::Direction;
+use super::ExportOptions;
+use super::FilterOptions;
+use super::OptionDelegate;
+use super::Switch;
+use crate::cli::OperationFailure;
+use crate::cli::CANCELLED;
+use crate::runner::Action;
+use crate::runner::RunnerConfig;
+use crate::tui::App;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "tui-args")]
+pub struct Args {
+    #[command(flatten)]
+    pub compile: CompileOptions,
+
+    #[command(flatten)]
+    pub compare: CompareOptions,
+
+    #[command(flatten)]
+    pub export: ExportOptions,
+
+    #[command(flatten)]
+    pub filter: FilterOptions,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    if !ctx.ui.can_live_report() {
+        writeln!(ctx.ui.error()?, "The TUI requires an interactive terminal")?;
+        eyre::bail!(OperationFailure);
+    }
+
+    let project = ctx.project()?;
+    let profile = ctx.profile(&project);
+    let suite =
+        ctx.collect_tests_with_filter(&project, ctx.filter(&project, &args.filter)?, &args.filter)?;
+    let world = Arc::new(ctx.world(&args.compile)?);
+
+    let origin = match args
+        .export
+        .dir
+        .map(OptionDelegate::into_native)
+        .unwrap_or(project.config().defaults.direction)
+    {
+        Direction::Ltr => Origin::TopLeft,
+        Direction::Rtl => Origin::TopRight,
+    };
+
+    let pixel_per_pt = render::ppi_to_ppp(args.export.ppi.unwrap_or(project.config().defaults.ppi));
+
+    let alpha = args
+        .compare
+        .alpha
+        .map(OptionDelegate::into_native)
+        .unwrap_or(project.config().defaults.alpha);
+
+    let config = RunnerConfig {
+        warnings: args.compile.warnings(&project.config().defaults),
+        optimize: args
+            .export
+            .optimize_refs
+            .get_or(Some(project.config().defaults.optimize_refs)),
+        fail_fast: false,
+        pixel_per_pt,
+        alpha,
+        limits: project.config().defaults.limits(),
+        strategy: args
+            .compare
+            .compare
+            .get_or(profile.compare)
+            .then_some(args.compare.strategy(&project.config().defaults)),
+        // NOTE(tinger): We always export in the TUI, the artifacts are what
+        // the user will want to look at.
+        export_ephemeral: true,
+        origin,
+        heatmap: args.export.diff_heatmap,
+        export_pdf: args.export.pdf(),
+        action: Action::Run,
+        cancellation: &CANCELLED,
+        timeout: None,
+        meta: Default::default(),
+        controls: None,
+        installed: None,
+        remote: None,
+        dependencies: None,
+    };
+
+    App::new(&project, &suite, &world, config).run()
+}
@@ -1,13 +1,24 @@
+use std::io::Read;
 use std::io::Write;
+use std::sync::Arc;
 
 use color_eyre::eyre;
-use tytanic_core::doc::compare::Strategy;
+use color_eyre::eyre::WrapErr;
+use termcolor::Color;
+use tytanic_core::doc;
 use tytanic_core::doc::render;
 use tytanic_core::doc::render::Origin;
+use tytanic_core::doc::Document;
 use tytanic_core::dsl;
+use tytanic_core::project::Project;
 use tytanic_core::suite::Filter;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Stage;
+use tytanic_core::test::Test;
+use tytanic_core::FilteredSuite;
 use tytanic_core::Id;
 use tytanic_filter::eval;
+use tytanic_utils::fmt::Term;
 
 use super::CompareOptions;
 use super::CompileOptions;
@@ -16,16 +27,26 @@ use super::Direction;
 use super::ExportOptions;
 use super::FilterOptions;
 use super::OptionDelegate;
+use super::OutputFormat;
+use super::ReportOptions;
 use super::RunnerOptions;
 use super::Switch;
 use crate::cli::OperationFailure;
 use crate::cli::TestFailure;
 use crate::cli::CANCELLED;
+use crate::controls::Controls;
+use crate::cwrite;
+use crate::kit;
+use crate::report;
 use crate::report::Reporter;
 use crate::runner::Action;
 use crate::runner::Runner;
 use crate::runner::RunnerConfig;
 use crate::ui;
+use crate::DEFAULT_OPTIMIZE_OPTIONS;
+
+/// The maximum size of a single downloaded reference page in bytes.
+const MAX_DOWNLOAD_SIZE: u64 = 64 * 1024 * 1024;
 
 #[derive(clap::Args, Debug, Clone)]
 #[group(id = "update-args")]
@@ -45,14 +66,36 @@ pub struct Args {
     #[command(flatten)]
     pub filter: FilterOptions,
 
+    #[command(flatten)]
+    pub report: ReportOptions,
+
     /// Update all included tests, even if they didn't fail.
     #[arg(long)]
     pub force: bool,
+
+    /// Download the references from the given URL instead of rendering them.
+    ///
+    /// The pages of each test are downloaded from
+    /// `<URL>/<TEST>/out/<PAGE>.png`, such that the URL can point to the test
+    /// root of a published CI artifact. The tests are neither compiled nor
+    /// compared, all matched tests are updated.
+    #[arg(long, value_name = "URL", conflicts_with = "force")]
+    pub from_url: Option<String>,
+
+    /// Review each test whose output differs from its references.
+    ///
+    /// The tests are run first, then the differences of each failing test
+    /// are shown and it can be accepted, rejected or skipped. Only accepted
+    /// tests have their references updated.
+    #[arg(long, conflicts_with_all = ["force", "from_url"])]
+    pub interactive: bool,
 }
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let project = ctx.project()?;
-    let filter = match ctx.filter(&args.filter)? {
+    ctx.ensure_not_package_dir(&project)?;
+    let profile = ctx.profile(&project);
+    let filter = match ctx.filter(&project, &args.filter)? {
         Filter::TestSet(set) => Filter::TestSet(
             set.map(|set| eval::Set::expr_inter(set, dsl::built_in::persistent(), [])),
         ),
@@ -66,7 +109,7 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
         }
     };
 
-    let suite = ctx.collect_tests_with_filter(&project, filter)?;
+    let suite = ctx.collect_tests_with_filter(&project, filter, &args.filter)?;
 
     let mut illegal_tests = vec![];
     for test in suite.matched() {
@@ -88,7 +131,23 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
         eyre::bail!(OperationFailure);
     }
 
-    let world = ctx.world(&args.compile)?;
+    if let Some(url) = &args.from_url {
+        let optimize = args
+            .export
+            .optimize_refs
+            .get_or(Some(project.config().defaults.optimize_refs));
+        return update_from_url(ctx, &project, &suite, url, optimize);
+    }
+
+    if args.interactive && !ctx.ui.can_prompt() {
+        writeln!(
+            ctx.ui.error()?,
+            "Cannot review tests, the output is not connected to a terminal"
+        )?;
+        eyre::bail!(OperationFailure);
+    }
+
+    let world = Arc::new(ctx.world(&args.compile)?);
 
     let origin = match args
         .export
@@ -102,46 +161,83 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
 
     let pixel_per_pt = render::ppi_to_ppp(args.export.ppi.unwrap_or(project.config().defaults.ppi));
 
-    let max_delta = args
+    let alpha = args
         .compare
-        .max_delta
-        .unwrap_or(project.config().defaults.max_delta);
+        .alpha
+        .map(OptionDelegate::into_native)
+        .unwrap_or(project.config().defaults.alpha);
 
-    let max_deviation = args
-        .compare
-        .max_deviations
-        .unwrap_or(project.config().defaults.max_deviations);
+    let live = ctx.ui.can_live_report()
+        && ctx.args.output.verbose == 0
+        && ctx.args.output.format == OutputFormat::Human;
+
+    // Keyboard controls are only useful if we can show their effect.
+    let controls = if live && ctx.ui.can_prompt() {
+        Controls::enable()
+            .inspect_err(|err| tracing::warn!(?err, "couldn't enable keyboard controls"))
+            .ok()
+    } else {
+        None
+    };
 
     let runner = Runner::new(
         &project,
         &suite,
         &world,
         RunnerConfig {
-            warnings: args.compile.warnings.into_native(),
-            optimize: args.export.optimize_refs.get_or_default(),
-            fail_fast: args.runner.fail_fast.get_or_default(),
+            warnings: args.compile.warnings(&project.config().defaults),
+            optimize: args
+                .export
+                .optimize_refs
+                .get_or(Some(project.config().defaults.optimize_refs)),
+            fail_fast: !args.interactive && args.runner.fail_fast.get_or(profile.fail_fast),
             pixel_per_pt,
-            strategy: args
-                .compare
-                .compare
-                .get_or_default()
-                .then_some(Strategy::Simple {
-                    max_delta,
-                    max_deviation,
-                }),
-            export_ephemeral: args.export.export_ephemeral.get_or_default(),
+            alpha,
+            limits: project.config().defaults.limits(),
+            strategy: (args.interactive || args.compare.compare.get_or(profile.compare))
+                .then_some(args.compare.strategy(&project.config().defaults)),
+            // NOTE(tinger): Reviewing requires the output and difference
+            // documents of the failing tests.
+            export_ephemeral: args.interactive
+                || args
+                    .export
+                    .export_ephemeral
+                    .get_or(profile.export_ephemeral),
             origin,
-            action: Action::Update { force: args.force },
+            heatmap: args.export.diff_heatmap,
+            export_pdf: args.export.pdf(),
+            action: if args.interactive {
+                Action::Run
+            } else {
+                Action::Update { force: args.force }
+            },
             cancellation: &CANCELLED,
+            timeout: args.runner.timeout,
+            meta: args.report.meta(),
+            controls: controls.as_ref(),
+            installed: None,
+            remote: None,
+            dependencies: None,
         },
     );
 
-    let reporter = Reporter::new(
-        ctx.ui,
-        &world,
-        ctx.ui.can_live_report() && ctx.args.output.verbose == 0,
-    );
+    let reporter = Reporter::new(ctx.ui, &project, &world, live, ctx.args.output.verbose > 0)
+        .with_progress(ctx.progress()?)
+        .with_format(ctx.args.output.format)
+        .with_summary_by(args.report.summary_by);
     let result = runner.run(&reporter)?;
+    drop(controls);
+
+    args.report
+        .write_reports(ctx, &project, suite.inner(), &profile, &result)?;
+
+    if args.interactive && !CANCELLED.is_cancelled() {
+        let optimize = args
+            .export
+            .optimize_refs
+            .get_or(Some(project.config().defaults.optimize_refs));
+        return review(ctx, &project, &suite, &result, optimize);
+    }
 
     if !result.is_complete_pass() {
         eyre::bail!(TestFailure);
@@ -149,3 +245,235 @@ pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
 
     Ok(())
 }
+
+/// The decision for a single test while reviewing.
+enum Review {
+    Accept,
+    Reject,
+    Skip,
+    Quit,
+}
+
+/// Prompts for the review of each test which failed comparison and updates
+/// the references of the accepted tests.
+fn review(
+    ctx: &mut Context,
+    project: &Project,
+    suite: &FilteredSuite,
+    result: &SuiteResult,
+    optimize: bool,
+) -> eyre::Result<()> {
+    let mut accepted = 0;
+    let mut rejected = 0;
+    let mut skipped = 0;
+    let mut quit = false;
+
+    for (id, test_result) in result.results() {
+        let Stage::FailedComparison(error) = test_result.stage() else {
+            continue;
+        };
+
+        let Some(test) = suite.matched().get(id).and_then(Test::as_unit_test) else {
+            continue;
+        };
+
+        if quit {
+            skipped += 1;
+            continue;
+        }
+
+        let out_dir = project.unit_test_out_dir(id);
+        let diff_dir = project.unit_test_diff_dir(id);
+
+        {
+            let mut w = ui::annotated(ctx.ui.stderr(), "review", Color::Cyan, None)?;
+            ui::write_test_id(&mut w, id)?;
+            writeln!(w)?;
+            report::write_compare_error(&mut w, error)?;
+            report::write_density_grids(&mut w, error)?;
+
+            if diff_dir.try_exists()? {
+                write!(w, "Diff images at ")?;
+                let display = diff_dir.strip_prefix(project.root()).unwrap_or(&diff_dir);
+                ui::write_file_link(&mut w, &diff_dir, |mut w| {
+                    cwrite!(colored(w, Color::Cyan), "{}", display.display())
+                })?;
+                writeln!(w)?;
+            }
+        }
+
+        match prompt_review(ctx)? {
+            Review::Accept => {
+                let output = Document::load(&out_dir)
+                    .wrap_err_with(|| format!("couldn't load output document of test {id}"))?;
+                test.create_reference_document(
+                    project,
+                    &output,
+                    optimize.then_some(&*DEFAULT_OPTIMIZE_OPTIONS),
+                )?;
+                accepted += 1;
+            }
+            Review::Reject => {
+                tytanic_utils::fs::remove_dir(&out_dir, true)?;
+                tytanic_utils::fs::remove_dir(&diff_dir, true)?;
+                rejected += 1;
+            }
+            Review::Skip => skipped += 1,
+            Review::Quit => {
+                quit = true;
+                skipped += 1;
+            }
+        }
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Accepted ")?;
+    cwrite!(colored(w, Color::Green), "{accepted}")?;
+    write!(w, " {}, rejected ", Term::simple("test").with(accepted))?;
+    cwrite!(colored(w, Color::Red), "{rejected}")?;
+    write!(w, ", skipped ")?;
+    cwrite!(colored(w, Color::Yellow), "{skipped}")?;
+    writeln!(w)?;
+    drop(w);
+
+    if result.failed() > accepted {
+        eyre::bail!(TestFailure);
+    }
+
+    Ok(())
+}
+
+/// Prompts for the review decision of a single test until a valid answer is
+/// given.
+fn prompt_review(ctx: &Context) -> eyre::Result<Review> {
+    loop {
+        let answer = ctx.ui.prompt_with(|w| {
+            write!(w, "Update references? [a]ccept, [r]eject, [s]kip, [q]uit: ")
+        })?;
+
+        match answer.to_ascii_lowercase().as_str() {
+            "a" | "accept" => return Ok(Review::Accept),
+            "r" | "reject" => return Ok(Review::Reject),
+            "s" | "skip" => return Ok(Review::Skip),
+            "q" | "quit" => return Ok(Review::Quit),
+            _ => writeln!(
+                ctx.ui.error()?,
+                "Expected one of a, r, s or q, got {answer:?}"
+            )?,
+        }
+    }
+}
+
+/// Updates the references of all matched tests with the pages downloaded from
+/// the given base URL.
+fn update_from_url(
+    ctx: &mut Context,
+    project: &Project,
+    suite: &FilteredSuite,
+    base: &str,
+    optimize: bool,
+) -> eyre::Result<()> {
+    let base = base.trim_end_matches('/');
+    let agent = kit::agent_from_args(&ctx.args.package, base)?;
+    let max_pages = project.config().defaults.max_pages;
+
+    let mut missing = vec![];
+    let mut failed = vec![];
+    let mut updated = 0;
+
+    for test in suite.matched().unit_tests() {
+        match download_reference(&agent, base, test.id(), max_pages) {
+            Ok(Some(reference)) => {
+                test.create_reference_document(
+                    project,
+                    &reference,
+                    optimize.then_some(&*DEFAULT_OPTIMIZE_OPTIONS),
+                )?;
+                updated += 1;
+            }
+            Ok(None) => missing.push(test.id()),
+            Err(err) => failed.push((test.id(), err)),
+        }
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Updated ")?;
+    cwrite!(colored(w, Color::Green), "{updated}")?;
+    write!(w, " {} from ", Term::simple("test").with(updated))?;
+    cwrite!(colored(w, Color::Cyan), "{base}")?;
+    writeln!(w)?;
+    drop(w);
+
+    if !missing.is_empty() {
+        let mut w = ctx.ui.warn()?;
+        writeln!(w, "No references found for tests:")?;
+        for id in &missing {
+            ui::write_test_id(&mut w, id)?;
+            writeln!(w)?;
+        }
+    }
+
+    if !failed.is_empty() {
+        let mut w = ctx.ui.error()?;
+        writeln!(w, "Couldn't download references for tests:")?;
+        for (id, err) in &failed {
+            ui::write_test_id(&mut w, id)?;
+            write!(w, ": {err}")?;
+            for cause in err.chain().skip(1) {
+                write!(w, ": {cause}")?;
+            }
+            writeln!(w)?;
+        }
+    }
+
+    if !missing.is_empty() || !failed.is_empty() {
+        eyre::bail!(OperationFailure);
+    }
+
+    Ok(())
+}
+
+/// Downloads the pages of a single reference document, returns `None` if the
+/// first page doesn't exist.
+fn download_reference(
+    agent: &ureq::Agent,
+    base: &str,
+    id: &Id,
+    max_pages: usize,
+) -> eyre::Result<Option<Document>> {
+    let mut pages = vec![];
+
+    for page in 1.. {
+        let url = format!("{base}/{id}/out/{page}.png");
+
+        let response = match agent.get(&url).call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => break,
+            Err(err) => return Err(err.into()),
+        };
+
+        if page > max_pages {
+            eyre::bail!("reference has more than {max_pages} pages");
+        }
+
+        let mut data = vec![];
+        response
+            .into_reader()
+            .take(MAX_DOWNLOAD_SIZE + 1)
+            .read_to_end(&mut data)
+            .wrap_err_with(|| format!("couldn't download {url}"))?;
+
+        if data.len() as u64 > MAX_DOWNLOAD_SIZE {
+            eyre::bail!("{url} is larger than {MAX_DOWNLOAD_SIZE} bytes");
+        }
+
+        let page = doc::decode_page(&data).wrap_err_with(|| format!("couldn't decode {url}"))?;
+        pages.push(page);
+    }
+
+    if pages.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(Document::new(pages)))
+}
@@ -0,0 +1,282 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use tytanic_utils::fmt::Bytes;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::commands::parse_duration;
+use crate::cwrite;
+use crate::kit;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-cache-args")]
+pub struct Args {
+    /// The sub command to run.
+    #[command(subcommand)]
+    pub cmd: Command,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Show the size and last use of the cached packages.
+    #[command()]
+    Info,
+
+    /// Remove cached packages which were not used recently.
+    #[command()]
+    Prune(PruneArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-cache-prune-args")]
+pub struct PruneArgs {
+    /// Remove packages which were not used within the given duration.
+    ///
+    /// Accepts durations like `12h`, `90d` or `2w`.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub older_than: Duration,
+
+    /// Only list the packages which would be removed.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+impl Command {
+    pub fn run(&self, ctx: &mut Context) -> eyre::Result<()> {
+        match self {
+            Command::Info => info(ctx),
+            Command::Prune(args) => prune(ctx, args),
+        }
+    }
+}
+
+/// A package version in the package cache.
+#[derive(Debug)]
+struct CachedPackage {
+    /// The spec of the package like `preview/name:0.1.0`.
+    spec: String,
+
+    /// The directory of the package version.
+    path: PathBuf,
+
+    /// The total size of all files of the package.
+    size: u64,
+
+    /// The last time any file of the package was accessed or modified.
+    last_used: SystemTime,
+}
+
+impl CachedPackage {
+    /// The time since this package was last used.
+    fn age(&self, now: SystemTime) -> Duration {
+        now.duration_since(self.last_used).unwrap_or_default()
+    }
+}
+
+/// Collects all package versions in the given package cache, sorted by their
+/// spec.
+fn collect(cache_dir: &Path) -> io::Result<Vec<CachedPackage>> {
+    let mut packages = vec![];
+
+    for namespace in sub_dirs(cache_dir)? {
+        for name in sub_dirs(&namespace)? {
+            for version in sub_dirs(&name)? {
+                let (size, last_used) = scan(&version)?;
+
+                packages.push(CachedPackage {
+                    spec: format!(
+                        "{}/{}:{}",
+                        file_name(&namespace),
+                        file_name(&name),
+                        file_name(&version),
+                    ),
+                    path: version,
+                    size,
+                    last_used,
+                });
+            }
+        }
+    }
+
+    packages.sort_by(|a, b| a.spec.cmp(&b.spec));
+    Ok(packages)
+}
+
+/// Returns the sub directories of the given directory, a missing directory
+/// has none.
+fn sub_dirs(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+
+    let mut dirs = vec![];
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.path());
+        }
+    }
+
+    Ok(dirs)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Returns the total size and the last access or modification time of the
+/// files in the given directory.
+///
+/// Only the modification time of directories is considered, their access time
+/// is updated by reading them, including when scanning the cache.
+fn scan(dir: &Path) -> io::Result<(u64, SystemTime)> {
+    let mut size = 0;
+    let mut last_used = fs::metadata(dir)?
+        .modified()
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+
+        if metadata.is_dir() {
+            let (dir_size, dir_last_used) = scan(&entry.path())?;
+            size += dir_size;
+            last_used = last_used.max(dir_last_used);
+        } else {
+            size += metadata.len();
+            last_used = last_used.max(self::last_used(&metadata));
+        }
+    }
+
+    Ok((size, last_used))
+}
+
+// NOTE(tinger): Access times depend on the file system, with the common
+// `relatime` mount option they are updated at most once a day, which is precise
+// enough for pruning. On file systems without access times packages are
+// considered used when they were downloaded.
+fn last_used(metadata: &fs::Metadata) -> SystemTime {
+    let accessed = metadata.accessed().ok();
+    let modified = metadata.modified().ok();
+
+    accessed.max(modified).unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Formats the age of a package for display.
+fn write_age(w: &mut impl Write, age: Duration) -> io::Result<()> {
+    let days = age.as_secs() / (24 * 60 * 60);
+
+    match days {
+        0 => write!(w, "used today"),
+        _ => write!(w, "used {days} {} ago", Term::simple("day").with(days)),
+    }
+}
+
+fn cache_dir(ctx: &Context) -> eyre::Result<PathBuf> {
+    let store = kit::package_store_from_args(&ctx.args.package);
+
+    let Some(dir) = store.cache_dir() else {
+        eyre::bail!("couldn't determine the package cache directory");
+    };
+
+    Ok(dir.to_path_buf())
+}
+
+fn info(ctx: &mut Context) -> eyre::Result<()> {
+    let cache_dir = cache_dir(ctx)?;
+    let packages = collect(&cache_dir)?;
+    let now = SystemTime::now();
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Package cache at ")?;
+    cwrite!(colored(w, Color::Cyan), "{}", cache_dir.display())?;
+    writeln!(w)?;
+
+    let width = packages.iter().map(|p| p.spec.len()).max().unwrap_or(0);
+    for package in &packages {
+        write!(w, "  ")?;
+        cwrite!(bold(w), "{:<width$}", package.spec)?;
+        write!(w, " {:>10}  ", Bytes(package.size).to_string())?;
+        write_age(&mut w, package.age(now))?;
+        writeln!(w)?;
+    }
+
+    let size = packages.iter().map(|p| p.size).sum();
+    cwrite!(colored(w, Color::Green), "{}", packages.len())?;
+    write!(w, " {}, ", Term::simple("package").with(packages.len()))?;
+    cwrite!(colored(w, Color::Green), "{}", Bytes(size))?;
+    writeln!(w, " in total")?;
+
+    Ok(())
+}
+
+fn prune(ctx: &mut Context, args: &PruneArgs) -> eyre::Result<()> {
+    let cache_dir = cache_dir(ctx)?;
+    let now = SystemTime::now();
+
+    let stale: Vec<_> = collect(&cache_dir)?
+        .into_iter()
+        .filter(|package| package.age(now) > args.older_than)
+        .collect();
+
+    let mut w = ctx.ui.stderr();
+    for package in &stale {
+        if !args.dry_run {
+            tytanic_utils::fs::remove_dir(&package.path, true)?;
+            remove_empty_parents(&cache_dir, &package.path)?;
+        }
+
+        write!(w, "  ")?;
+        cwrite!(bold(w), "{}", package.spec)?;
+        write!(w, " ({}, ", Bytes(package.size))?;
+        write_age(&mut w, package.age(now))?;
+        writeln!(w, ")")?;
+    }
+
+    let size = stale.iter().map(|p| p.size).sum();
+    write!(
+        w,
+        "{} ",
+        if args.dry_run {
+            "Would remove"
+        } else {
+            "Removed"
+        }
+    )?;
+    cwrite!(colored(w, Color::Green), "{}", stale.len())?;
+    write!(w, " {} (", Term::simple("package").with(stale.len()))?;
+    cwrite!(colored(w, Color::Green), "{}", Bytes(size))?;
+    writeln!(w, ")")?;
+
+    Ok(())
+}
+
+/// Removes the now empty name and namespace directories of a removed package.
+fn remove_empty_parents(cache_dir: &Path, path: &Path) -> io::Result<()> {
+    for dir in path.ancestors().skip(1) {
+        if dir == cache_dir || !dir.starts_with(cache_dir) {
+            break;
+        }
+
+        if fs::read_dir(dir)?.next().is_some() {
+            break;
+        }
+
+        fs::remove_dir(dir)?;
+    }
+
+    Ok(())
+}
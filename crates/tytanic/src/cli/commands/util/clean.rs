@@ -21,7 +21,8 @@ pub struct Args {
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
     let project = ctx.project()?;
-    let suite = ctx.collect_tests_with_filter(&project, ctx.filter(&args.filter)?)?;
+    let suite =
+        ctx.collect_tests_with_filter(&project, ctx.filter(&project, &args.filter)?, &args.filter)?;
 
     let mut temp = 0;
     let mut persistent = 0;
@@ -1,7 +1,9 @@
 use clap::CommandFactory;
+use clap_complete::env::Shells;
 use clap_complete::Shell;
 use color_eyre::eyre;
 
+use crate::cli::complete::COMPLETE_VAR;
 use crate::cli::Context;
 
 #[derive(clap::Args, Debug, Clone)]
@@ -10,17 +12,31 @@ pub struct Args {
     /// The shell to complete the arguments for.
     #[arg()]
     shell: Shell,
+
+    /// Generate completions which don't call back into Tytanic.
+    ///
+    /// These don't complete test identifiers, test sets or profiles, but
+    /// they can be stored in a file without being regenerated when Tytanic is
+    /// updated.
+    #[arg(long = "static")]
+    static_: bool,
 }
 
 pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
-    let mut cmd = crate::CliArguments::command();
-
-    clap_complete::generate(
-        args.shell,
-        &mut cmd,
-        std::env!("CARGO_BIN_NAME"),
-        &mut ctx.ui.stdout(),
-    );
+    let bin = std::env!("CARGO_BIN_NAME");
+
+    if args.static_ {
+        let mut cmd = crate::CliArguments::command();
+        clap_complete::generate(args.shell, &mut cmd, bin, &mut ctx.ui.stdout());
+        return Ok(());
+    }
+
+    let shells = Shells::builtins();
+    let completer = shells
+        .completer(&args.shell.to_string())
+        .expect("all clap shells are supported");
+
+    completer.write_registration(COMPLETE_VAR, bin, bin, bin, &mut ctx.ui.stdout())?;
 
     Ok(())
 }
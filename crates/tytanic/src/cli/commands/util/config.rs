@@ -0,0 +1,38 @@
+use std::io::Write;
+
+use color_eyre::eyre;
+use tytanic_core::config;
+
+use super::Context;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-config-args")]
+pub struct Args {
+    /// The sub command to run.
+    #[command(subcommand)]
+    pub cmd: Command,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Print a JSON Schema for the `tool.tytanic` manifest section.
+    ///
+    /// This can be used by editors to provide autocompletion and validation
+    /// for the Tytanic config in `typst.toml`.
+    #[command()]
+    Schema,
+}
+
+impl Command {
+    pub fn run(&self, ctx: &mut Context) -> eyre::Result<()> {
+        match self {
+            Command::Schema => {
+                let mut w = ctx.ui.stdout();
+                serde_json::to_writer_pretty(&mut w, &config::project_config_schema())?;
+                writeln!(w)?;
+
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::Write;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use tytanic_core::doc::LoadError;
+use tytanic_core::test::Id;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cwrite;
+use crate::ui;
+
+pub fn run(ctx: &mut Context) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests(&project)?;
+
+    let mut sources: BTreeMap<String, Vec<Id>> = BTreeMap::new();
+    let mut references: BTreeMap<u128, Vec<Id>> = BTreeMap::new();
+
+    for test in suite.unit_tests() {
+        let source = fs::read_to_string(project.unit_test_script(test.id()))?;
+        sources
+            .entry(normalize(&source))
+            .or_default()
+            .push(test.id().clone());
+
+        if !test.kind().is_persistent() {
+            continue;
+        }
+
+        // NOTE(tinger): Tests without stored references are reported by
+        // other commands, we simply skip them here.
+        let reference = match test.load_reference_document(&project) {
+            Ok(reference) => reference,
+            Err(LoadError::MissingPages(_) | LoadError::NotMaterialized(_)) => continue,
+            Err(LoadError::Io(err)) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err.into()),
+        };
+
+        if reference.buffers().is_empty() {
+            continue;
+        }
+
+        let pages: Vec<_> = reference
+            .buffers()
+            .iter()
+            .map(|page| (page.width(), page.height(), page.data()))
+            .collect();
+
+        references
+            .entry(typst::utils::hash128(&pages))
+            .or_default()
+            .push(test.id().clone());
+    }
+
+    let sources: Vec<_> = sources.into_values().filter(|ids| ids.len() > 1).collect();
+    let references: Vec<_> = references
+        .into_values()
+        .filter(|ids| ids.len() > 1)
+        .collect();
+
+    if sources.is_empty() && references.is_empty() {
+        writeln!(ctx.ui.stderr(), "No duplicate tests found")?;
+        return Ok(());
+    }
+
+    let mut w = ctx.ui.stderr();
+
+    for (groups, kind) in [(&sources, "sources"), (&references, "references")] {
+        if groups.is_empty() {
+            continue;
+        }
+
+        writeln!(w, "Tests with identical {kind}:")?;
+        for (idx, ids) in groups.iter().enumerate() {
+            if idx != 0 {
+                writeln!(w)?;
+            }
+
+            for id in ids {
+                write!(w, "  ")?;
+                ui::write_test_id(&mut w, id)?;
+                writeln!(w)?;
+            }
+        }
+        writeln!(w)?;
+    }
+
+    let len = sources.len() + references.len();
+    write!(w, "Found ")?;
+    cwrite!(colored(w, Color::Yellow), "{len}")?;
+    writeln!(
+        w,
+        " {} of duplicate tests, consider consolidating them",
+        Term::simple("group").with(len),
+    )?;
+
+    Ok(())
+}
+
+/// Normalizes a test source by removing leading and trailing whitespace of the
+/// whole source and trailing whitespace of each line.
+fn normalize(source: &str) -> String {
+    source
+        .trim()
+        .lines()
+        .map(str::trim_end)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,48 @@
+use std::io::Write;
+
+use color_eyre::eyre;
+use termcolor::Color;
+
+use super::Context;
+use crate::cli::deprecation::Kind;
+use crate::cli::deprecation::DEPRECATIONS;
+use crate::cwrite;
+use crate::json::DeprecationJson;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-deprecations-args")]
+pub struct Args {
+    /// Print a JSON describing the deprecations to stdout.
+    #[arg(long)]
+    pub json: bool,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    if args.json {
+        serde_json::to_writer_pretty(
+            ctx.ui.stdout(),
+            &DEPRECATIONS
+                .iter()
+                .map(DeprecationJson::new)
+                .collect::<Vec<_>>(),
+        )?;
+        return Ok(());
+    }
+
+    let mut w = ctx.ui.stderr();
+    for deprecation in DEPRECATIONS {
+        cwrite!(colored(w, Color::Cyan), "{deprecation}")?;
+        write!(w, " -> ")?;
+        match deprecation.kind {
+            Kind::Command => cwrite!(colored(w, Color::Cyan), "{}", deprecation.replacement)?,
+            Kind::Flag => cwrite!(colored(w, Color::Cyan), "--{}", deprecation.replacement)?,
+        }
+        writeln!(
+            w,
+            " (deprecated since {}, removed in {})",
+            deprecation.since, deprecation.removal,
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use toml_edit::DocumentMut;
+use tytanic_core::doc;
+use tytanic_core::project::Project;
+use tytanic_core::suite::Suite;
+use tytanic_core::test::Id;
+use tytanic_utils::fmt::Term;
+use tytanic_utils::result::io_not_found;
+use tytanic_utils::result::ResultEx;
+
+use super::Context;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-fix-excludes-args")]
+pub struct Args {
+    /// Confirm the changes to the manifest without prompting.
+    #[arg(long)]
+    pub confirm: bool,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+
+    if project.manifest().is_none() {
+        writeln!(ctx.ui.error()?, "Missing or invalid package manifest")?;
+        eyre::bail!(OperationFailure);
+    }
+
+    let suite = ctx.collect_tests(&project)?;
+    let missing = missing_excludes(&project, &suite)?;
+
+    let mut w = ctx.ui.stderr();
+
+    if missing.is_empty() {
+        writeln!(
+            w,
+            "Test artifacts and large references are already excluded"
+        )?;
+        return Ok(());
+    }
+
+    writeln!(w, "These exclude patterns would be added:")?;
+    for pattern in &missing {
+        write!(w, "  ")?;
+        cwrite!(colored(w, Color::Cyan), "{pattern}")?;
+        writeln!(w)?;
+    }
+    writeln!(w)?;
+    drop(w);
+
+    let confirm = if args.confirm {
+        true
+    } else if ctx.ui.can_prompt() {
+        ctx.ui
+            .prompt_yes_no("Add these patterns to the manifest?", true)?
+    } else {
+        let mut w = ctx.ui.hint()?;
+        write!(w, "Use ")?;
+        cwrite!(colored(w, Color::Cyan), "--confirm")?;
+        writeln!(w, " to add them to the manifest")?;
+
+        return Ok(());
+    };
+
+    if !confirm {
+        return Ok(());
+    }
+
+    add_excludes(ctx, &project, &missing)?;
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Added ")?;
+    cwrite!(colored(w, Color::Green), "{}", missing.len())?;
+    writeln!(
+        w,
+        " exclude {}",
+        Term::simple("pattern").with(missing.len())
+    )?;
+
+    Ok(())
+}
+
+/// The total size in bytes above which the references of a test are
+/// considered large and should be excluded from the bundle.
+pub const LARGE_REFERENCE_SIZE: u64 = 1024 * 1024;
+
+/// Returns the recommended exclude patterns which are missing from the
+/// manifest.
+///
+/// These exclude the temporary output and difference documents of all tests
+/// and the references of tests which are larger than [`LARGE_REFERENCE_SIZE`].
+/// No patterns are returned if the test root is already excluded.
+pub fn missing_excludes(project: &Project, suite: &Suite) -> io::Result<Vec<String>> {
+    let is_excluded = |path: &Path| project.is_excluded(relative(project, path));
+
+    let root = project.unit_tests_root();
+    if is_excluded(&root) {
+        return Ok(vec![]);
+    }
+
+    let mut missing = vec![];
+
+    for (name, dir) in [
+        (
+            "out",
+            Project::unit_test_out_dir as fn(&Project, &Id) -> PathBuf,
+        ),
+        ("diff", Project::unit_test_diff_dir),
+    ] {
+        if suite
+            .unit_tests()
+            .any(|test| !is_excluded(&dir(project, test.id())))
+        {
+            missing.push(format!("{}/**/{name}", relative_pattern(project, &root)));
+        }
+    }
+
+    for test in suite.unit_tests() {
+        if !test.kind().is_persistent() {
+            continue;
+        }
+
+        let dir = project.unit_test_ref_dir(test.id());
+        if is_excluded(&dir) {
+            continue;
+        }
+
+        let mut size = 0;
+        for ext in [doc::PAGE_EXTENSION, doc::SVG_PAGE_EXTENSION] {
+            for path in doc::page_paths(&dir, ext)
+                .ignore(io_not_found)?
+                .into_iter()
+                .flat_map(|paths| paths.into_values())
+            {
+                size += fs::metadata(path)?.len();
+            }
+        }
+
+        if size > LARGE_REFERENCE_SIZE {
+            missing.push(relative_pattern(project, &dir));
+        }
+    }
+
+    Ok(missing)
+}
+
+/// Returns the given path relative to the project root.
+fn relative<'p>(project: &Project, path: &'p Path) -> &'p Path {
+    path.strip_prefix(project.root()).unwrap_or(path)
+}
+
+/// Returns the given path relative to the project root with forward slashes,
+/// such that it can be used as an exclude pattern.
+fn relative_pattern(project: &Project, path: &Path) -> String {
+    relative(project, path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Appends the given exclude patterns to the package section of the manifest,
+/// preserving its formatting.
+pub fn add_excludes(ctx: &Context, project: &Project, patterns: &[String]) -> eyre::Result<()> {
+    if patterns.is_empty() {
+        return Ok(());
+    }
+
+    let path = project.manifest_file();
+    let mut manifest: DocumentMut = fs::read_to_string(&path)?.parse()?;
+
+    let Some(package) = manifest
+        .get_mut("package")
+        .and_then(|package| package.as_table_like_mut())
+    else {
+        writeln!(ctx.ui.error()?, "Manifest has no package section")?;
+        eyre::bail!(OperationFailure);
+    };
+
+    let exclude = package
+        .entry("exclude")
+        .or_insert_with(|| toml_edit::value(toml_edit::Array::new()));
+
+    let Some(exclude) = exclude.as_array_mut() else {
+        writeln!(ctx.ui.error()?, "Manifest exclude field is not an array")?;
+        eyre::bail!(OperationFailure);
+    };
+
+    for pattern in patterns {
+        exclude.push(pattern.as_str());
+    }
+
+    fs::write(&path, manifest.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,226 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use termcolor::WriteColor;
+use tytanic_core::suite::Suite;
+use tytanic_core::test::Id;
+use tytanic_utils::fmt::Term;
+
+use crate::cli::Context;
+use crate::cwrite;
+use crate::ui;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-fix-ids-args")]
+pub struct Args {
+    /// Confirm the renames without prompting.
+    #[arg(long)]
+    pub confirm: bool,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = Suite::collect(&project)?;
+    let root = project.unit_tests_root();
+
+    let mut w = ctx.ui.stderr();
+
+    if suite.invalid().is_empty() {
+        writeln!(w, "All test identifiers are valid")?;
+        return Ok(());
+    }
+
+    let renames = renames(&root, &suite)?;
+    let has_collision = renames.values().any(|(_, collision)| *collision);
+
+    writeln!(w, "These tests would be renamed:")?;
+    write_renames(&mut w, &renames)?;
+    writeln!(w)?;
+    drop(w);
+
+    if has_collision {
+        let mut w = ctx.ui.hint()?;
+        cwrite!(bold_colored(w, Color::Red), "*")?;
+        writeln!(
+            w,
+            " denotes paths which were excluded because of another test or directory with the same name."
+        )?;
+        writeln!(w, "These must be renamed manually")?;
+    }
+
+    let confirm = if args.confirm {
+        true
+    } else if ctx.ui.can_prompt() {
+        ctx.ui.prompt_yes_no("Rename these tests?", false)?
+    } else {
+        writeln!(ctx.ui.warn()?, "Make sure to back up your code!")?;
+
+        let mut w = ctx.ui.hint()?;
+        write!(w, "Use ")?;
+        cwrite!(colored(w, Color::Cyan), "--confirm")?;
+        writeln!(w, " to rename the tests automatically")?;
+
+        return Ok(());
+    };
+
+    if !confirm {
+        return Ok(());
+    }
+
+    let mut renamed = 0;
+    for (path, (_, collision)) in &renames {
+        if *collision {
+            continue;
+        }
+
+        rename_test(&root, path)?;
+        renamed += 1;
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Renamed ")?;
+    cwrite!(bold(w), "{renamed}")?;
+    writeln!(w, " {}", Term::simple("test").with(renamed))?;
+
+    Ok(())
+}
+
+/// Writes the given renames, one per line.
+pub fn write_renames(
+    mut w: &mut dyn WriteColor,
+    renames: &BTreeMap<PathBuf, (Id, bool)>,
+) -> io::Result<()> {
+    for (path, (new, collision)) in renames {
+        if *collision {
+            cwrite!(bold_colored(w, Color::Red), "*")?;
+            write!(w, " ")?;
+        } else {
+            write!(w, "  ")?;
+        }
+        cwrite!(colored(w, Color::Yellow), "{}", path.display())?;
+        write!(w, " -> ")?;
+        ui::write_test_id(w, new)?;
+        writeln!(w)?;
+    }
+
+    Ok(())
+}
+
+/// Returns the sanitized id for each invalid test directory in the suite and
+/// whether it collides with another test or directory.
+pub fn renames(root: &Path, suite: &Suite) -> eyre::Result<BTreeMap<PathBuf, (Id, bool)>> {
+    let mut renames = BTreeMap::new();
+    let mut counts = BTreeMap::<Id, usize>::new();
+
+    for path in suite.invalid() {
+        let new = Id::new_sanitized_from_path(path)?;
+        *counts.entry(new.clone()).or_default() += 1;
+        renames.insert(path.clone(), new);
+    }
+
+    renames
+        .into_iter()
+        .map(|(path, new)| {
+            let collision = counts[&new] > 1
+                || suite.contains(&new)
+                || suite.nested().contains_key(&new)
+                || target_exists(root, &path)?;
+
+            Ok((path, (new, collision)))
+        })
+        .collect()
+}
+
+/// Whether renaming any invalid component of the given path would overwrite
+/// an existing directory.
+fn target_exists(root: &Path, path: &Path) -> io::Result<bool> {
+    let mut old = root.to_path_buf();
+    let mut new = root.to_path_buf();
+
+    for component in path.components() {
+        let Component::Normal(component) = component else {
+            continue;
+        };
+
+        old.push(component);
+        new.push(Id::sanitize_component(component.to_string_lossy()).as_str());
+
+        if old != new && new.try_exists()? {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Renames each invalid component of the given test directory.
+///
+/// Components which are shared with previously renamed tests are skipped.
+fn rename_test(root: &Path, path: &Path) -> io::Result<()> {
+    let mut current = root.to_path_buf();
+
+    for component in path.components() {
+        let Component::Normal(component) = component else {
+            continue;
+        };
+
+        let old = current.join(component);
+        current.push(Id::sanitize_component(component.to_string_lossy()).as_str());
+
+        if old != current && old.try_exists()? {
+            fs::rename(&old, &current)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes the given groups of colliding directories, each directory but the
+/// first is followed by a suggested new name which is not yet taken.
+pub fn write_collisions(
+    mut w: &mut dyn WriteColor,
+    root: &Path,
+    collisions: &[BTreeSet<PathBuf>],
+) -> io::Result<()> {
+    for group in collisions {
+        let mut taken = BTreeSet::new();
+
+        for (idx, path) in group.iter().enumerate() {
+            write!(w, "  ")?;
+            cwrite!(
+                colored(w, Color::Yellow),
+                "{}",
+                path.to_string_lossy().escape_debug()
+            )?;
+
+            if idx != 0 {
+                let name = path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let name = Id::sanitize_component(name);
+
+                let new = (2..)
+                    .map(|n| path.with_file_name(format!("{name}-{n}")))
+                    .find(|new| !taken.contains(new) && !root.join(new).exists())
+                    .expect("there is always a free name");
+
+                write!(w, " -> ")?;
+                cwrite!(colored(w, Color::Cyan), "{}", new.display())?;
+                taken.insert(new);
+            }
+
+            writeln!(w)?;
+        }
+    }
+
+    Ok(())
+}
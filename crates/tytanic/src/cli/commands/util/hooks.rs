@@ -0,0 +1,217 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command as Process;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use tytanic_core::dsl;
+use tytanic_core::project::VcsKind;
+use tytanic_filter::ExpressionFilter;
+
+use super::Context;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+
+/// The marker comment used to recognize hooks installed by us.
+const HOOK_MARKER: &str = "# installed by tytanic";
+
+/// The test set expression run by hooks by default, the tests affected by the
+/// changes which are about to be committed or pushed.
+const DEFAULT_EXPRESSION: &str = r#"changed("HEAD")"#;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-hooks-args")]
+pub struct Args {
+    /// The sub command to run.
+    #[command(subcommand)]
+    pub cmd: Command,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Install a Git hook which runs the test suite.
+    ///
+    /// The hook compiles the selected tests without comparing them and is
+    /// skipped if Tytanic is not installed or the project no longer exists.
+    #[command()]
+    Install(InstallArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-hooks-install-args")]
+pub struct InstallArgs {
+    /// The hook to install.
+    #[arg(long, value_name = "HOOK", default_value = "pre-commit")]
+    pub hook: Hook,
+
+    /// The test set expression selecting the tests to run in the hook.
+    ///
+    /// Defaults to the tests affected by uncommitted changes.
+    #[arg(short, long, default_value = DEFAULT_EXPRESSION, value_name = "EXPR")]
+    pub expression: String,
+
+    /// Overwrite an existing hook which was not installed by Tytanic.
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// A Git hook.
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum Hook {
+    /// Run before each commit.
+    PreCommit,
+
+    /// Run before each push.
+    PrePush,
+}
+
+impl Hook {
+    /// The file name of this hook.
+    pub fn name(self) -> &'static str {
+        match self {
+            Hook::PreCommit => "pre-commit",
+            Hook::PrePush => "pre-push",
+        }
+    }
+}
+
+impl Command {
+    pub fn run(&self, ctx: &mut Context) -> eyre::Result<()> {
+        match self {
+            Command::Install(args) => install(ctx, args),
+        }
+    }
+}
+
+fn install(ctx: &mut Context, args: &InstallArgs) -> eyre::Result<()> {
+    let project = ctx.project()?;
+
+    // NOTE(tinger): Ensure the expression is valid before we install a hook
+    // which would fail on every commit.
+    ExpressionFilter::new(dsl::project_context(&project), &args.expression)?;
+
+    let Some(vcs) = project.vcs().filter(|vcs| vcs.kind() == VcsKind::Git) else {
+        writeln!(
+            ctx.ui.error()?,
+            "Hooks can only be installed in Git repositories"
+        )?;
+        eyre::bail!(OperationFailure);
+    };
+
+    // NOTE(tinger): This respects `core.hooksPath` and worktrees, we fall
+    // back to the default location if git is not available.
+    let hooks_dir = Process::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .current_dir(vcs.root())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            vcs.root()
+                .join(String::from_utf8_lossy(&output.stdout).trim())
+        })
+        .unwrap_or_else(|| vcs.root().join(".git").join("hooks"));
+
+    let path = hooks_dir.join(args.hook.name());
+
+    if !args.force {
+        if let Ok(existing) = fs::read_to_string(&path) {
+            if !existing.contains(HOOK_MARKER) {
+                writeln!(
+                    ctx.ui.error()?,
+                    "A {} hook already exists at {}",
+                    args.hook.name(),
+                    path.display(),
+                )?;
+
+                let mut w = ctx.ui.hint()?;
+                write!(w, "Use ")?;
+                cwrite!(colored(w, Color::Cyan), "--force")?;
+                writeln!(w, " to overwrite it")?;
+
+                eyre::bail!(OperationFailure);
+            }
+        }
+    }
+
+    let root = project
+        .root()
+        .strip_prefix(vcs.root())
+        .unwrap_or(project.root());
+
+    tytanic_utils::fs::create_dir(&hooks_dir, true)?;
+    fs::write(&path, hook_script(root.to_path_buf(), &args.expression))?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Installed ")?;
+    cwrite!(bold(w), "{}", args.hook.name())?;
+    write!(w, " hook at ")?;
+    cwrite!(colored(w, Color::Cyan), "{}", path.display())?;
+    writeln!(w)?;
+
+    Ok(())
+}
+
+/// Returns the script of a hook running the given test set in the project at
+/// the given path relative to the repository root.
+fn hook_script(root: PathBuf, expression: &str) -> String {
+    let quote = |s: &str| format!("'{}'", s.replace('\'', r"'\''"));
+
+    let root = root
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    format!(
+        "\
+#!/bin/sh
+{HOOK_MARKER}, reinstall using `tt util hooks install`
+
+# Skip if tytanic is not installed.
+command -v tt > /dev/null 2>&1 || exit 0
+
+cd \"$(git rev-parse --show-toplevel)\"/{root} 2> /dev/null || exit 0
+
+# Skip if this is no longer a tytanic project.
+tt status > /dev/null 2>&1 || exit 0
+
+exec tt run --no-compare --no-fail-fast --format quiet-errors --expression {expression}
+",
+        root = quote(if root.is_empty() { "." } else { &root }),
+        expression = quote(expression),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_script() {
+        let script = hook_script(PathBuf::from("packages/foo"), "mod('it''s')");
+
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains(HOOK_MARKER));
+        assert!(script.contains("cd \"$(git rev-parse --show-toplevel)\"/'packages/foo'"));
+        assert!(script.contains("--expression 'mod('\\''it'\\'''\\''s'\\'')'"));
+    }
+
+    #[test]
+    fn test_hook_script_default() {
+        let script = hook_script(PathBuf::new(), DEFAULT_EXPRESSION);
+
+        assert!(script.contains("cd \"$(git rev-parse --show-toplevel)\"/'.'"));
+        assert!(script.ends_with(
+            "exec tt run --no-compare --no-fail-fast --format quiet-errors \
+            --expression 'changed(\"HEAD\")'\n"
+        ));
+    }
+}
@@ -0,0 +1,264 @@
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use typst::diag::Warned;
+use tytanic_core::doc;
+use tytanic_core::doc::compare::Strategy;
+use tytanic_core::doc::compare::StrategyKind;
+use tytanic_core::doc::compile::Warnings;
+use tytanic_core::doc::render;
+use tytanic_core::doc::render::Origin;
+use tytanic_core::doc::Document;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::commands::check;
+use crate::cli::commands::parse_threshold;
+use crate::cli::commands::AlphaOption;
+use crate::cli::commands::ColorSpaceOption;
+use crate::cli::commands::CompareStrategyOption;
+use crate::cli::commands::CompileOptions;
+use crate::cli::commands::Direction;
+use crate::cli::commands::DirectionOption;
+use crate::cli::commands::IgnoreAntialiasingSwitch;
+use crate::cli::commands::OptionDelegate;
+use crate::cli::commands::Switch;
+use crate::cli::OperationFailure;
+use crate::cli::TestFailure;
+use crate::cwrite;
+use crate::report;
+use crate::ui;
+use crate::world::SystemWorld;
+
+/// The padding to use for annotations of the comparison.
+const IMGDIFF_ANNOT_PADDING: usize = 8;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-imgdiff-args")]
+pub struct Args {
+    #[command(flatten)]
+    pub compile: CompileOptions,
+
+    /// The first document, a Typst file, a PNG page or a directory of
+    /// numbered PNG pages.
+    ///
+    /// This document is treated as the output in the comparison.
+    #[arg(value_name = "A")]
+    pub a: PathBuf,
+
+    /// The second document, a Typst file, a PNG page or a directory of
+    /// numbered PNG pages.
+    ///
+    /// This document is treated as the reference in the comparison.
+    #[arg(value_name = "B")]
+    pub b: PathBuf,
+
+    /// Write the difference pages to the given directory.
+    #[arg(long, value_name = "DIR")]
+    pub diff: Option<PathBuf>,
+
+    /// The pixel-per-inch value to use for rendering Typst files.
+    ///
+    /// Defaults to `144.0`, can be configured in the manifest.
+    #[arg(long)]
+    pub ppi: Option<f32>,
+
+    /// The document direction, used to align pages with different dimensions
+    /// in difference pages.
+    ///
+    /// Defaults to `ltr`, can be configured in the manifest.
+    #[arg(long)]
+    pub dir: Option<DirectionOption>,
+
+    /// The maximum allowed delta per pixel.
+    ///
+    /// Defaults to `1`, can be configured in the manifest.
+    #[arg(long)]
+    pub max_delta: Option<u8>,
+
+    /// The maximum allowed deviations per page.
+    ///
+    /// Defaults to `0`, can be configured in the manifest.
+    #[arg(long)]
+    pub max_deviations: Option<usize>,
+
+    /// The maximum allowed difference in page width or height in pixels.
+    ///
+    /// Defaults to `0`, can be configured in the manifest.
+    #[arg(long, value_name = "PX")]
+    pub max_size_delta: Option<u32>,
+
+    /// The color space in which pixels are compared.
+    ///
+    /// Defaults to `srgb`, can be configured in the manifest.
+    #[arg(long)]
+    pub color_space: Option<ColorSpaceOption>,
+
+    /// How transparent pixels are handled.
+    ///
+    /// Defaults to `channel`, can be configured in the manifest.
+    #[arg(long)]
+    pub alpha: Option<AlphaOption>,
+
+    /// The strategy used to compare pages.
+    ///
+    /// Defaults to `simple`, can be configured in the manifest.
+    #[arg(long, value_name = "STRATEGY")]
+    pub compare_strategy: Option<CompareStrategyOption>,
+
+    /// The minimum structural similarity of two pages for the `ssim`
+    /// strategy, between `0.0` and `1.0`.
+    ///
+    /// Defaults to `0.99`, can be configured in the manifest.
+    #[arg(long, value_parser = parse_threshold)]
+    pub threshold: Option<f32>,
+
+    #[command(flatten)]
+    pub ignore_antialiasing: IgnoreAntialiasingSwitch,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let world = ctx.world(&args.compile)?;
+    let defaults = &project.config().defaults;
+
+    let pixel_per_pt = render::ppi_to_ppp(args.ppi.unwrap_or(defaults.ppi));
+    let warnings = args.compile.warnings(defaults);
+
+    let a = load(ctx, &world, &args.a, pixel_per_pt, warnings)?;
+    let b = load(ctx, &world, &args.b, pixel_per_pt, warnings)?;
+
+    let alpha = args
+        .alpha
+        .map(OptionDelegate::into_native)
+        .unwrap_or(defaults.alpha);
+
+    let max_size_delta = args.max_size_delta.unwrap_or(defaults.max_size_delta);
+
+    let strategy = match args
+        .compare_strategy
+        .map(OptionDelegate::into_native)
+        .unwrap_or(defaults.compare_strategy)
+    {
+        StrategyKind::Simple => Strategy::Simple {
+            max_delta: args.max_delta.unwrap_or(defaults.max_delta),
+            max_deviation: args.max_deviations.unwrap_or(defaults.max_deviations),
+            max_size_delta,
+            color_space: args
+                .color_space
+                .map(OptionDelegate::into_native)
+                .unwrap_or(defaults.color_space),
+            alpha,
+            ignore_antialiasing: args
+                .ignore_antialiasing
+                .get_or(Some(defaults.ignore_antialiasing)),
+            sample: None,
+        },
+        StrategyKind::Ssim => Strategy::Ssim {
+            threshold: args.threshold.unwrap_or(defaults.threshold),
+            max_size_delta,
+            alpha,
+        },
+    };
+
+    if let Some(dir) = &args.diff {
+        let origin = match args
+            .dir
+            .map(OptionDelegate::into_native)
+            .unwrap_or(defaults.direction)
+        {
+            Direction::Ltr => Origin::TopLeft,
+            Direction::Rtl => Origin::TopRight,
+        };
+
+        tytanic_utils::fs::ensure_empty_dir(dir, true)?;
+        Document::render_diff(&a, &b, origin, alpha).save(dir, None)?;
+    }
+
+    let Err(error) = Document::compare(&a, &b, strategy) else {
+        let pages = a.buffers().len();
+
+        let mut w = ctx.ui.stderr();
+        write!(w, "Documents ")?;
+        cwrite!(bold_colored(w, Color::Green), "matched")?;
+        writeln!(w, " ({pages} {})", Term::simple("page").with(pages))?;
+
+        return Ok(());
+    };
+
+    let mut w = ui::annotated(
+        ctx.ui.stderr(),
+        "differed",
+        Color::Red,
+        IMGDIFF_ANNOT_PADDING,
+    )?;
+    report::write_compare_error(&mut w, &error)?;
+    report::write_density_grids(&mut w, &error)?;
+
+    eyre::bail!(TestFailure);
+}
+
+/// Loads, or compiles and renders the document at the given path.
+fn load(
+    ctx: &Context,
+    world: &SystemWorld,
+    path: &Path,
+    pixel_per_pt: f32,
+    warnings: Warnings,
+) -> eyre::Result<Document> {
+    if path.is_dir() {
+        return Ok(Document::load(path)?);
+    }
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("typ") => {
+            let (source, _) = check::load_source(ctx, world, path)?;
+
+            let Warned { output, warnings } =
+                Document::compile(source, world, pixel_per_pt, warnings, |w| {
+                    w.augment_standard_library(true)
+                });
+
+            let errors = output.as_ref().err().map(|error| &error.0[..]);
+            ui::write_diagnostics(
+                &mut ctx.ui.stderr(),
+                ctx.ui.diagnostic_config(),
+                world,
+                &warnings,
+                errors.unwrap_or_default(),
+            )?;
+
+            let Ok(doc) = output else {
+                writeln!(
+                    ctx.ui.error()?,
+                    "Compilation of '{}' failed",
+                    path.display()
+                )?;
+                eyre::bail!(OperationFailure);
+            };
+
+            Ok(doc)
+        }
+        Some("png") => Ok(Document::new([doc::decode_page(&fs::read(path)?)?])),
+        Some("pdf") => {
+            writeln!(
+                ctx.ui.error()?,
+                "PDF documents can't be rendered, '{}' must be exported as PNG pages",
+                path.display()
+            )?;
+            eyre::bail!(OperationFailure);
+        }
+        _ => {
+            writeln!(
+                ctx.ui.error()?,
+                "Unsupported document '{}', expected a Typst file, a PNG page or a directory of PNG pages",
+                path.display()
+            )?;
+            eyre::bail!(OperationFailure);
+        }
+    }
+}
@@ -3,11 +3,25 @@ use color_eyre::eyre;
 use super::Context;
 
 pub mod about;
+pub mod cache;
 pub mod clean;
 pub mod completion;
+pub mod config;
+pub mod dedupe;
+pub mod deprecations;
+pub mod fix_excludes;
+pub mod fix_ids;
 pub mod fonts;
+pub mod hooks;
+pub mod imgdiff;
 pub mod manpage;
 pub mod migrate;
+pub mod mv_module;
+pub mod prepublish;
+pub mod refs;
+pub mod report_environment;
+pub mod rewrite_imports;
+pub mod unused_assets;
 pub mod vcs;
 
 #[derive(clap::Args, Debug, Clone)]
@@ -24,14 +38,51 @@ pub enum Command {
     #[command()]
     About,
 
+    /// Package cache related commands.
+    #[command()]
+    Cache(cache::Args),
+
     /// Remove test output artifacts.
     #[command()]
     Clean(clean::Args),
 
     /// Generate completions.
-    #[command()]
+    ///
+    /// By default, the completions call back into Tytanic to complete test
+    /// identifiers, test set functions and profiles of the current project,
+    /// they should be regenerated on shell startup, e.g. using
+    /// `source <(tt util completion bash)`.
+    #[command(visible_alias = "completions")]
     Completion(completion::Args),
 
+    /// Config related commands.
+    #[command()]
+    Config(config::Args),
+
+    /// Find tests with identical sources or references.
+    ///
+    /// Duplicate tests are candidates for consolidation.
+    #[command()]
+    Dedupe,
+
+    /// List the deprecated sub command aliases and flags.
+    ///
+    /// Each is listed with its replacement, the version it was deprecated in
+    /// and the version it will be removed in.
+    #[command()]
+    Deprecations(deprecations::Args),
+
+    /// Add the recommended exclude patterns to the manifest.
+    ///
+    /// These exclude the temporary output and difference documents of tests
+    /// and large references from the package bundle.
+    #[command()]
+    FixExcludes(fix_excludes::Args),
+
+    /// Rename test directories whose paths are not valid test identifiers.
+    #[command()]
+    FixIds(fix_ids::Args),
+
     /// Generate a man page for Tytanic.
     #[command()]
     Manpage(manpage::Args),
@@ -40,10 +91,60 @@ pub enum Command {
     #[command()]
     Fonts(fonts::Args),
 
+    /// Git hook related commands.
+    #[command()]
+    Hooks(hooks::Args),
+
+    /// Compare two documents outside of the test suite.
+    ///
+    /// Each document is a Typst file, which is compiled and rendered, a single
+    /// PNG page or a directory of numbered PNG pages.
+    #[command()]
+    Imgdiff(imgdiff::Args),
+
     /// Migrate the test structure to the new version.
     #[command()]
     Migrate(migrate::Args),
 
+    /// Move all tests of a module into another module.
+    #[command()]
+    MvModule(mv_module::Args),
+
+    /// Check whether the package is ready to be published.
+    ///
+    /// This validates the manifest, runs the test suite without system fonts,
+    /// local packages or a custom timestamp and checks that all references
+    /// are up to date.
+    #[command()]
+    Prepublish,
+
+    /// Persistent reference related commands.
+    #[command()]
+    Refs(refs::Args),
+
+    /// Print details about the environment for bug reports.
+    ///
+    /// This prints the platform, versions, enabled features and the size and
+    /// used features of the current project as a Markdown list which can be
+    /// pasted into the "Additional Context" section of an issue. No names or
+    /// paths are included and nothing is sent anywhere.
+    #[command()]
+    ReportEnvironment,
+
+    /// Rewrite imports in all test scripts.
+    ///
+    /// This can be used to switch the tests between importing the package
+    /// through a path and through its package spec around releases.
+    #[command()]
+    RewriteImports(rewrite_imports::Args),
+
+    /// Find files in test directories which are not used by any test.
+    ///
+    /// This compiles all tests and reports files which are not part of the
+    /// test layout and were never read during compilation.
+    #[command()]
+    UnusedAssets(unused_assets::Args),
+
     /// Vcs related commands.
     #[command()]
     Vcs(vcs::Args),
@@ -53,11 +154,25 @@ impl Command {
     pub fn run(&self, ctx: &mut Context) -> eyre::Result<()> {
         match self {
             Command::About => about::run(ctx),
+            Command::Cache(args) => args.cmd.run(ctx),
             Command::Clean(args) => clean::run(ctx, args),
             Command::Completion(args) => completion::run(ctx, args),
+            Command::Config(args) => args.cmd.run(ctx),
+            Command::Dedupe => dedupe::run(ctx),
+            Command::Deprecations(args) => deprecations::run(ctx, args),
+            Command::FixExcludes(args) => fix_excludes::run(ctx, args),
+            Command::FixIds(args) => fix_ids::run(ctx, args),
             Command::Manpage(args) => manpage::run(ctx, args),
             Command::Fonts(args) => fonts::run(ctx, args),
+            Command::Hooks(args) => args.cmd.run(ctx),
+            Command::Imgdiff(args) => imgdiff::run(ctx, args),
             Command::Migrate(args) => migrate::run(ctx, args),
+            Command::MvModule(args) => mv_module::run(ctx, args),
+            Command::Prepublish => prepublish::run(ctx),
+            Command::Refs(args) => args.cmd.run(ctx),
+            Command::ReportEnvironment => report_environment::run(ctx),
+            Command::RewriteImports(args) => rewrite_imports::run(ctx, args),
+            Command::UnusedAssets(args) => unused_assets::run(ctx, args),
             Command::Vcs(args) => args.cmd.run(ctx),
         }
     }
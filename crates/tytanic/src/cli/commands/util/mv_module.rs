@@ -0,0 +1,189 @@
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use color_eyre::eyre::WrapErr;
+use termcolor::Color;
+use tytanic_core::test::Id;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+use crate::ui;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-mv-module-args")]
+pub struct Args {
+    /// The module to move the tests out of.
+    #[arg(value_name = "OLD")]
+    pub old: Id,
+
+    /// The module to move the tests into.
+    #[arg(value_name = "NEW")]
+    pub new: Id,
+
+    /// Only show which tests would be moved.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests(&project)?;
+
+    if args.new == args.old || args.new.ancestors().any(|a| a == args.old.as_str()) {
+        writeln!(
+            ctx.ui.error()?,
+            "Cannot move module {} into itself",
+            args.old,
+        )?;
+        eyre::bail!(OperationFailure);
+    }
+
+    let prefix = format!("{}/", args.old);
+
+    let mut mappings = vec![];
+    for test in suite.unit_tests() {
+        let Some(rest) = test.id().as_str().strip_prefix(&prefix) else {
+            continue;
+        };
+
+        let new = Id::new(format!("{}/{rest}", args.new))?;
+        let collision = suite.contains(&new);
+        mappings.push((test.id().clone(), new, collision));
+    }
+
+    if mappings.is_empty() {
+        writeln!(ctx.ui.error()?, "Module {} contains no tests", args.old)?;
+        eyre::bail!(OperationFailure);
+    }
+
+    let mut w = ctx.ui.stderr();
+
+    if args.dry_run {
+        writeln!(w, "These tests would be moved:")?;
+    } else {
+        writeln!(w, "Moving tests:")?;
+    }
+
+    let mut has_collision = false;
+    for (old, new, collision) in &mappings {
+        if *collision {
+            cwrite!(bold_colored(w, Color::Red), "*")?;
+            write!(w, " ")?;
+        } else {
+            write!(w, "  ")?;
+        }
+        ui::write_test_id(&mut w, old)?;
+        write!(w, " -> ")?;
+        ui::write_test_id(&mut w, new)?;
+        writeln!(w)?;
+
+        has_collision |= collision;
+    }
+
+    writeln!(w)?;
+    drop(w);
+
+    if has_collision {
+        let mut w = ctx.ui.error()?;
+        cwrite!(bold_colored(w, Color::Red), "*")?;
+        writeln!(w, " denotes tests which collide with an existing test")?;
+        writeln!(w, "No tests were moved")?;
+        eyre::bail!(OperationFailure);
+    }
+
+    if args.dry_run {
+        return Ok(());
+    }
+
+    let old_dir = project.unit_test_dir(&args.old);
+    let new_dir = project.unit_test_dir(&args.new);
+
+    // NOTE(tinger): The module is always moved as a whole, this moves nested
+    // tests along with their parents and keeps shared files next to the tests
+    // using them. If the target module already exists the directories are
+    // merged instead.
+    if !new_dir.try_exists()? {
+        if let Some(parent) = new_dir.parent() {
+            tytanic_utils::fs::create_dir(parent, true)?;
+        }
+
+        fs::rename(&old_dir, &new_dir)
+            .wrap_err_with(|| format!("moving {old_dir:?} to {new_dir:?}"))?;
+    } else {
+        let mut moved = vec![];
+        if let Err(err) = merge_dir(&old_dir, &new_dir, &mut moved) {
+            for (from, to) in moved.into_iter().rev() {
+                let res = from
+                    .parent()
+                    .map(|parent| tytanic_utils::fs::create_dir(parent, true))
+                    .transpose()
+                    .and_then(|_| fs::rename(&to, &from));
+
+                if let Err(err) = res {
+                    tracing::error!(?from, ?to, ?err, "couldn't roll back move");
+                }
+            }
+
+            return Err(err).wrap_err_with(|| format!("moving {old_dir:?} to {new_dir:?}"));
+        }
+
+        fs::remove_dir(&old_dir).wrap_err_with(|| format!("removing {old_dir:?}"))?;
+    }
+
+    if let Some(vcs) = project.vcs() {
+        let suite = ctx.collect_tests(&project)?;
+
+        for (_, new, _) in &mappings {
+            if let Some(test) = suite.get(new).and_then(|test| test.as_unit_test()) {
+                vcs.ignore(&project, test)?;
+            }
+        }
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Moved ")?;
+    cwrite!(colored(w, Color::Green), "{}", mappings.len())?;
+    writeln!(w, " {}", Term::simple("test").with(mappings.len()))?;
+
+    Ok(())
+}
+
+/// Moves the entries of the directory `from` into the directory `to`,
+/// directories which exist in both are merged recursively and
+/// removed from `from` once they are empty.
+///
+/// Each moved entry is added to `moved` such that the moves can be rolled back
+/// if an error occurs.
+fn merge_dir(from: &Path, to: &Path, moved: &mut Vec<(PathBuf, PathBuf)>) -> io::Result<()> {
+    tytanic_utils::fs::create_dir(to, true)?;
+
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let src = entry.path();
+        let dst = to.join(entry.file_name());
+
+        if !dst.try_exists()? {
+            fs::rename(&src, &dst)?;
+            moved.push((src, dst));
+            continue;
+        }
+
+        if !entry.file_type()?.is_dir() || !dst.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{dst:?} already exists"),
+            ));
+        }
+
+        merge_dir(&src, &dst, moved)?;
+        _ = fs::remove_dir(&src);
+    }
+
+    Ok(())
+}
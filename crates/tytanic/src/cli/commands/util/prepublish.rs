@@ -0,0 +1,315 @@
+use std::fs;
+use std::io::Write;
+use std::sync::Arc;
+
+use chrono::DateTime;
+use color_eyre::eyre;
+use termcolor::Color;
+use typst::syntax::package::PackageVersion;
+use typst_kit::fonts::FontSearcher;
+use typst_kit::package::PackageStorage;
+use tytanic_core::doc::compile::Warnings;
+use tytanic_core::doc::render;
+use tytanic_core::doc::render::Origin;
+use tytanic_core::dsl;
+use tytanic_core::project::Project;
+use tytanic_core::suite::Filter;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Stage;
+use tytanic_filter::eval;
+use tytanic_filter::ExpressionFilter;
+use tytanic_utils::fmt::Term;
+
+use super::fix_excludes;
+use super::Context;
+use crate::cli::commands::Direction;
+use crate::cli::OperationFailure;
+use crate::cli::CANCELLED;
+use crate::cwrite;
+use crate::kit;
+use crate::registry::PackageStore;
+use crate::report::Reporter;
+use crate::runner::Action;
+use crate::runner::Runner;
+use crate::runner::RunnerConfig;
+use crate::ui;
+use crate::world::SystemWorld;
+
+/// The alignment of the check status annotations, matches the test reports.
+const ANNOTATION_ALIGN: usize = 10;
+
+/// A single check of the readiness report.
+struct Check {
+    passed: bool,
+    message: String,
+}
+
+impl Check {
+    fn new(passed: bool, message: impl Into<String>) -> Self {
+        Self {
+            passed,
+            message: message.into(),
+        }
+    }
+}
+
+pub fn run(ctx: &mut Context) -> eyre::Result<()> {
+    let shallow = ctx.shallow_project()?;
+
+    let Some(manifest) = fs::read_to_string(shallow.manifest_file())
+        .ok()
+        .and_then(|content| content.parse::<toml::Table>().ok())
+    else {
+        writeln!(ctx.ui.error()?, "Missing or invalid package manifest")?;
+        eyre::bail!(OperationFailure);
+    };
+
+    let mut checks = vec![];
+
+    // NOTE(tinger): We check the version on the raw manifest, an invalid
+    // version would otherwise fail loading the project without explaining
+    // why it's rejected.
+    let version = manifest
+        .get("package")
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.as_str());
+
+    let version_check = check_version(version);
+    let valid_version = version_check.passed;
+    checks.push(version_check);
+
+    if !valid_version {
+        report(ctx, &checks)?;
+        eyre::bail!(OperationFailure);
+    }
+
+    let project = ctx.project()?;
+    checks.push(check_entrypoint(&project));
+    checks.push(check_excluded(&project));
+
+    let suite = ctx.collect_tests(&project)?;
+    let missing = fix_excludes::missing_excludes(&project, &suite)?;
+    if !project.is_excluded(&project.config().unit_tests_root) {
+        checks.push(check_artifacts_excluded(&missing));
+    }
+
+    let result = run_hermetic(ctx, &project)?;
+    checks.extend(check_result(&result));
+
+    report(ctx, &checks)?;
+
+    if checks.iter().any(|check| !check.passed) {
+        eyre::bail!(OperationFailure);
+    }
+
+    Ok(())
+}
+
+fn check_version(version: Option<&str>) -> Check {
+    let Some(version) = version else {
+        return Check::new(false, "Manifest has no package version");
+    };
+
+    if version.contains(['-', '+']) {
+        return Check::new(
+            false,
+            format!("Version {version} has a pre-release or build suffix, these are not supported by Typst Universe"),
+        );
+    }
+
+    match version.parse::<PackageVersion>() {
+        Ok(version) => Check::new(true, format!("Version {version} is valid")),
+        Err(err) => Check::new(false, format!("Version {version} is invalid: {err}")),
+    }
+}
+
+fn check_entrypoint(project: &Project) -> Check {
+    let entrypoint = project
+        .manifest()
+        .map(|manifest| manifest.package.entrypoint.as_str())
+        .unwrap_or_default();
+
+    if project.root().join(entrypoint).is_file() {
+        Check::new(true, format!("Entrypoint {entrypoint} exists"))
+    } else {
+        Check::new(false, format!("Entrypoint {entrypoint} does not exist"))
+    }
+}
+
+fn check_excluded(project: &Project) -> Check {
+    let tests = &project.config().unit_tests_root;
+
+    if project.is_excluded(tests) {
+        Check::new(
+            true,
+            format!("Test directory {tests}/ is excluded from the bundle"),
+        )
+    } else {
+        Check::new(
+            false,
+            format!("Test directory {tests}/ is not excluded from the bundle, add it to package.exclude"),
+        )
+    }
+}
+
+fn check_artifacts_excluded(missing: &[String]) -> Check {
+    if missing.is_empty() {
+        Check::new(true, "Test artifacts and large references are excluded")
+    } else {
+        Check::new(
+            false,
+            format!(
+                "Test artifacts or large references are not excluded, missing {}, run tt util fix-excludes to add them",
+                missing.join(", "),
+            ),
+        )
+    }
+}
+
+fn check_result(result: &SuiteResult) -> Vec<Check> {
+    let mut checks = vec![];
+
+    let outdated = result
+        .results()
+        .values()
+        .filter(|test| matches!(test.stage(), Stage::FailedComparison(_)))
+        .count();
+
+    let failed = result.failed() - outdated;
+
+    if failed == 0 {
+        checks.push(Check::new(
+            true,
+            format!(
+                "{} {} passed without system fonts or local packages",
+                result.passed(),
+                Term::simple("test").with(result.passed()),
+            ),
+        ));
+    } else {
+        checks.push(Check::new(
+            false,
+            format!(
+                "{failed} {} failed without system fonts or local packages",
+                Term::simple("test").with(failed),
+            ),
+        ));
+    }
+
+    if outdated == 0 {
+        checks.push(Check::new(true, "References are up to date"));
+    } else {
+        checks.push(Check::new(
+            false,
+            format!(
+                "References of {outdated} {} are out of date",
+                Term::simple("test").with(outdated),
+            ),
+        ));
+    }
+
+    checks
+}
+
+/// Runs the full test suite without system fonts, local packages or a custom
+/// timestamp, these are not available when the package is used from Typst
+/// Universe.
+fn run_hermetic(ctx: &Context, project: &Project) -> eyre::Result<SuiteResult> {
+    // NOTE(tinger): Skipped tests are excluded like in a regular run.
+    let filter = ExpressionFilter::new(dsl::project_context(project), "all()")?
+        .map(|set| eval::Set::expr_diff(set, dsl::built_in::skip()));
+    let suite = ctx
+        .collect_tests(project)?
+        .filter(Filter::TestSet(filter))?;
+
+    let mut searcher = FontSearcher::new();
+    searcher.include_system_fonts(false);
+
+    #[cfg(feature = "embed-fonts")]
+    searcher.include_embedded_fonts(true);
+
+    let world = SystemWorld::new(
+        project.root().to_path_buf(),
+        searcher.search(),
+        PackageStore::new(
+            PackageStorage::new(
+                ctx.args.package.package_cache_path.clone(),
+                None,
+                kit::downloader_from_args(&ctx.args.package),
+            ),
+            ctx.args.package.clone(),
+        )
+        .with_recording(ctx.recording.clone()),
+        DateTime::UNIX_EPOCH,
+    )?;
+
+    let defaults = &project.config().defaults;
+
+    let world = Arc::new(world);
+    let runner = Runner::new(
+        project,
+        &suite,
+        &world,
+        RunnerConfig {
+            warnings: Warnings::Emit,
+            optimize: false,
+            fail_fast: false,
+            pixel_per_pt: render::ppi_to_ppp(defaults.ppi),
+            alpha: defaults.alpha,
+            limits: defaults.limits(),
+            strategy: Some(defaults.strategy()),
+            export_ephemeral: true,
+            origin: match defaults.direction {
+                Direction::Ltr => Origin::TopLeft,
+                Direction::Rtl => Origin::TopRight,
+            },
+            heatmap: None,
+            export_pdf: false,
+            action: Action::Run,
+            cancellation: &CANCELLED,
+            timeout: None,
+            meta: Default::default(),
+            controls: None,
+            installed: None,
+            remote: None,
+            dependencies: None,
+        },
+    );
+
+    let reporter = Reporter::new(ctx.ui, project, &world, false, false);
+    runner.run(&reporter)
+}
+
+fn report(ctx: &Context, checks: &[Check]) -> eyre::Result<()> {
+    let mut w = ctx.ui.stderr();
+
+    writeln!(w)?;
+    cwrite!(bold(w), "Publish readiness\n")?;
+
+    for check in checks {
+        let (header, color) = if check.passed {
+            ("ok", Color::Green)
+        } else {
+            ("fail", Color::Red)
+        };
+
+        let mut w = ui::annotated(&mut w, header, color, ANNOTATION_ALIGN)?;
+        writeln!(w, "{}", check.message)?;
+    }
+
+    writeln!(w)?;
+
+    if checks.iter().all(|check| check.passed) {
+        cwrite!(
+            bold_colored(w, Color::Green),
+            "Package is ready to be published\n"
+        )?;
+    } else {
+        cwrite!(
+            bold_colored(w, Color::Red),
+            "Package is not ready to be published\n"
+        )?;
+    }
+
+    Ok(())
+}
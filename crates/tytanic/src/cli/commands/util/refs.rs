@@ -0,0 +1,281 @@
+use std::fs;
+use std::io::Write;
+
+use chrono::DateTime;
+use chrono::Local;
+use clap_complete::ArgValueCandidates;
+use color_eyre::eyre;
+use sha2::Digest;
+use sha2::Sha256;
+use termcolor::Color;
+use tytanic_core::doc;
+use tytanic_core::doc::render;
+use tytanic_core::doc::Document;
+use tytanic_core::doc::PAGE_EXTENSION;
+use tytanic_core::doc::SVG_PAGE_EXTENSION;
+use tytanic_core::test::Id;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::commands::CompileOptions;
+use crate::cli::commands::FilterOptions;
+use crate::cli::commands::OptimizeRefsSwitch;
+use crate::cli::commands::Switch;
+use crate::cli::complete;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+use crate::DEFAULT_OPTIMIZE_OPTIONS;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-refs-args")]
+pub struct Args {
+    /// The sub command to run.
+    #[command(subcommand)]
+    pub cmd: Command,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Rewrite persistent references in the normalized 8-bit RGBA format.
+    ///
+    /// This can be used to migrate references which were created by other
+    /// tools or older versions and contain other bit depths, color types or
+    /// color profiles.
+    #[command()]
+    Normalize(NormalizeArgs),
+
+    /// Print details about the persistent references of a test.
+    ///
+    /// This includes the page count, dimensions, file sizes, checksums and
+    /// metadata of each page, as well as whether the dimensions match the
+    /// current output of the test.
+    #[command()]
+    Inspect(InspectArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-refs-normalize-args")]
+pub struct NormalizeArgs {
+    #[command(flatten)]
+    pub optimize_refs: OptimizeRefsSwitch,
+
+    #[command(flatten)]
+    pub filter: FilterOptions,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-refs-inspect-args")]
+pub struct InspectArgs {
+    /// The test to inspect.
+    #[arg(value_name = "TEST", add = ArgValueCandidates::new(complete::test_ids))]
+    pub test: Id,
+
+    /// The pixel-per-inch value to use for rendering the current output.
+    ///
+    /// Defaults to `144.0`, can be configured in the manifest.
+    #[arg(long)]
+    pub ppi: Option<f32>,
+
+    #[command(flatten)]
+    pub compile: CompileOptions,
+}
+
+impl Command {
+    pub fn run(&self, ctx: &mut Context) -> eyre::Result<()> {
+        match self {
+            Command::Normalize(args) => normalize(ctx, args),
+            Command::Inspect(args) => inspect(ctx, args),
+        }
+    }
+}
+
+fn normalize(ctx: &mut Context, args: &NormalizeArgs) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite =
+        ctx.collect_tests_with_filter(&project, ctx.filter(&project, &args.filter)?, &args.filter)?;
+
+    let optimize_options = args
+        .optimize_refs
+        .get_or(Some(project.config().defaults.optimize_refs))
+        .then_some(&*DEFAULT_OPTIMIZE_OPTIONS);
+
+    let mut len = 0;
+    for test in suite.matched().unit_tests() {
+        if !test.kind().is_persistent() {
+            continue;
+        }
+
+        let reference = test.load_reference_document(&project)?;
+        test.create_reference_document(&project, &reference, optimize_options)?;
+        len += 1;
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Normalized references for ")?;
+    cwrite!(colored(w, Color::Green), "{len}")?;
+    writeln!(w, " {}", Term::simple("test").with(len))?;
+
+    Ok(())
+}
+
+fn inspect(ctx: &mut Context, args: &InspectArgs) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests(&project)?;
+
+    let Some(test) = suite.get(&args.test).and_then(|test| test.as_unit_test()) else {
+        writeln!(ctx.ui.error()?, "Unit test {} not found", args.test)?;
+        eyre::bail!(OperationFailure);
+    };
+
+    if !test.kind().is_persistent() {
+        writeln!(
+            ctx.ui.error()?,
+            "Test {} is not persistent and has no stored references",
+            args.test,
+        )?;
+        eyre::bail!(OperationFailure);
+    }
+
+    let world = ctx.world(&args.compile)?;
+    let ppi = args.ppi.unwrap_or(project.config().defaults.ppi);
+
+    let output = Document::compile(
+        test.load_source(&project)?,
+        &world,
+        render::ppi_to_ppp(ppi),
+        args.compile.warnings(&project.config().defaults),
+        |w| w.augment_standard_library(true),
+    )
+    .output
+    .ok();
+
+    let dir = project.unit_test_ref_dir(test.id());
+
+    let mut pages: Vec<_> = doc::page_paths(&dir, PAGE_EXTENSION)?
+        .into_iter()
+        .chain(doc::page_paths(&dir, SVG_PAGE_EXTENSION)?)
+        .collect();
+    pages.sort();
+
+    let mut w = ctx.ui.stderr();
+
+    write!(w, "Test: ")?;
+    cwrite!(bold_colored(w, Color::Cyan), "{}", test.id())?;
+    writeln!(w)?;
+
+    let display = dir.strip_prefix(project.root()).unwrap_or(&dir);
+    writeln!(w, "Directory: {}", display.display())?;
+    writeln!(w, "Pages: {}", pages.len())?;
+
+    write!(w, "Output: ")?;
+    match &output {
+        Some(output) => writeln!(
+            w,
+            "{} {} at {ppi} ppi",
+            output.buffers().len(),
+            Term::simple("page").with(output.buffers().len()),
+        )?,
+        None => cwrite!(colored(w, Color::Red), "failed to compile\n")?,
+    }
+
+    for (num, path) in pages {
+        let data = fs::read(&path)?;
+
+        writeln!(w)?;
+        cwrite!(bold(w), "Page {num}")?;
+        writeln!(
+            w,
+            " ({})",
+            path.strip_prefix(&dir).unwrap_or(&path).display()
+        )?;
+
+        if path
+            .extension()
+            .is_some_and(|ext| ext == SVG_PAGE_EXTENSION)
+        {
+            writeln!(w, "  Format: SVG")?;
+            writeln!(w, "  Size: {} bytes", data.len())?;
+            writeln!(w, "  SHA-256: {:x}", Sha256::digest(&data))?;
+            continue;
+        }
+
+        let decoder = png::Decoder::new(data.as_slice());
+        let reader = match decoder.read_info() {
+            Ok(reader) => reader,
+            Err(err) => {
+                write!(w, "  Error: ")?;
+                cwrite!(colored(w, Color::Red), "{err}\n")?;
+                continue;
+            }
+        };
+        let info = reader.info();
+
+        write!(w, "  Dimensions: {}x{} px", info.width, info.height)?;
+        let output_page = output
+            .as_ref()
+            .and_then(|output| output.buffers().get(num - 1));
+        match output_page {
+            Some(page) if page.width() == info.width && page.height() == info.height => {
+                write!(w, " (")?;
+                cwrite!(colored(w, Color::Green), "matches output")?;
+                writeln!(w, ")")?;
+            }
+            Some(page) => {
+                write!(w, " (")?;
+                cwrite!(
+                    colored(w, Color::Red),
+                    "output is {}x{} px",
+                    page.width(),
+                    page.height(),
+                )?;
+                writeln!(w, ")")?;
+            }
+            None if output.is_some() => {
+                write!(w, " (")?;
+                cwrite!(colored(w, Color::Red), "not in output")?;
+                writeln!(w, ")")?;
+            }
+            None => writeln!(w)?,
+        }
+
+        write!(w, "  PPI: ")?;
+        let inferred = output
+            .as_ref()
+            .and_then(|output| output.doc())
+            .and_then(|doc| doc.pages.get(num - 1))
+            .map(|page| info.width as f64 / page.frame.width().to_pt() * 72.0);
+        match (info.pixel_dims, inferred) {
+            (Some(dims), _) if dims.unit == png::Unit::Meter => {
+                writeln!(w, "{:.0}", dims.xppu as f64 * 0.0254)?;
+            }
+            (_, Some(inferred)) => writeln!(w, "{inferred:.0} (inferred from output)")?,
+            _ => writeln!(w, "unknown")?,
+        }
+
+        writeln!(
+            w,
+            "  Format: {}-bit {:?}, {}",
+            info.bit_depth as u8,
+            info.color_type,
+            if info.icc_profile.is_some() {
+                "with color profile"
+            } else {
+                "no color profile"
+            },
+        )?;
+
+        writeln!(w, "  Size: {} bytes", data.len())?;
+        writeln!(w, "  SHA-256: {:x}", Sha256::digest(&data))?;
+
+        if let Ok(modified) = fs::metadata(&path).and_then(|meta| meta.modified()) {
+            let modified = DateTime::<Local>::from(modified);
+            writeln!(w, "  Modified: {}", modified.format("%Y-%m-%d %H:%M:%S"))?;
+        }
+
+        for chunk in &info.uncompressed_latin1_text {
+            writeln!(w, "  {}: {}", chunk.keyword, chunk.text)?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::io::Write;
+
+use color_eyre::eyre;
+use tytanic_core::project::Project;
+use tytanic_core::project::ShallowProject;
+use tytanic_core::suite::Suite;
+use tytanic_core::test::unit::Kind;
+
+use super::Context;
+
+/// The features this binary was compiled with.
+const FEATURES: &[(&str, bool)] = &[
+    ("embed-fonts", cfg!(feature = "embed-fonts")),
+    ("tui", cfg!(feature = "tui")),
+    ("vendor-openssl", cfg!(feature = "vendor-openssl")),
+];
+
+pub fn run(ctx: &mut Context) -> eyre::Result<()> {
+    // NOTE(tinger): This must work outside of projects and for projects which
+    // fail to load, those are likely the ones users report bugs for.
+    let project =
+        ShallowProject::discover(ctx.root()?, ctx.args.root.is_some())?.map(ShallowProject::load);
+
+    let mut w = ctx.ui.stdout();
+    writeln!(w, "## Additional Context")?;
+    writeln!(w, "- Platform: {} ({})", env::consts::OS, env::consts::ARCH)?;
+    writeln!(w, "- Version: {}", env!("CARGO_PKG_VERSION"))?;
+    writeln!(w, "- Typst Version: {}", env!("TYTANIC_TYPST_VERSION"))?;
+    writeln!(
+        w,
+        "- Features: {}",
+        list(FEATURES.iter().filter(|(_, on)| *on).map(|(name, _)| *name)),
+    )?;
+    writeln!(
+        w,
+        "- CI: {}",
+        if ctx.args.output.ci || env::var_os("CI").is_some() {
+            "yes"
+        } else {
+            "no"
+        },
+    )?;
+
+    match project {
+        None => writeln!(w, "- Project: none")?,
+        Some(Err(err)) => writeln!(w, "- Project: failed to load ({err})")?,
+        Some(Ok(project)) => write_project(&mut w, &project)?,
+    }
+
+    Ok(())
+}
+
+/// Writes the anonymized details of a project, this doesn't include any names
+/// or paths.
+fn write_project<W: Write>(mut w: W, project: &Project) -> eyre::Result<()> {
+    let kind = match project.manifest() {
+        Some(manifest) if manifest.template.is_some() => "template package",
+        Some(_) => "package",
+        None if project.document().is_some() => "document",
+        None => "no manifest",
+    };
+    writeln!(w, "- Project: {kind}")?;
+
+    writeln!(
+        w,
+        "- Vcs: {}",
+        project.vcs().map_or("none".into(), |vcs| vcs.to_string()),
+    )?;
+
+    let config = project.config();
+    let mut used = vec![];
+    if !config.profiles.is_empty() {
+        used.push(format!("{} profiles", config.profiles.len()));
+    }
+    if !config.test_sets.is_empty() {
+        used.push(format!("{} test sets", config.test_sets.len()));
+    }
+    if !config.serial.is_empty() {
+        used.push(format!("{} serial modules", config.serial.len()));
+    }
+    if !config.hooks.is_empty() {
+        used.push("hooks".into());
+    }
+    writeln!(w, "- Config: {}", list(used))?;
+
+    let suite = match Suite::collect(project) {
+        Ok(suite) => suite,
+        Err(err) => {
+            writeln!(w, "- Tests: failed to collect ({err})")?;
+            return Ok(());
+        }
+    };
+
+    let mut persistent = 0;
+    let mut ephemeral = 0;
+    let mut compile_only = 0;
+    let mut annotations = BTreeSet::new();
+    for test in suite.unit_tests() {
+        match test.kind() {
+            Kind::Persistent => persistent += 1,
+            Kind::Ephemeral => ephemeral += 1,
+            Kind::CompileOnly => compile_only += 1,
+        }
+
+        annotations.extend(test.annotations().iter().map(|annot| annot.name()));
+    }
+
+    writeln!(
+        w,
+        "- Tests: {persistent} persistent, {ephemeral} ephemeral, {compile_only} compile-only, {} template",
+        suite.template_test().map_or(0, |_| 1),
+    )?;
+    writeln!(w, "- Annotations: {}", list(annotations))?;
+
+    Ok(())
+}
+
+/// Joins the given items by commas, or returns `none` if there are none.
+fn list<I: IntoIterator<Item = S>, S: AsRef<str>>(items: I) -> String {
+    let items: Vec<_> = items
+        .into_iter()
+        .map(|item| item.as_ref().to_owned())
+        .collect();
+
+    if items.is_empty() {
+        "none".into()
+    } else {
+        items.join(", ")
+    }
+}
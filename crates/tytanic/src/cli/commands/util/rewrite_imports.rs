@@ -0,0 +1,164 @@
+use std::fs;
+use std::io::Write;
+use std::ops::Range;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use typst::syntax::ast;
+use typst::syntax::LinkedNode;
+use typst::syntax::SyntaxKind;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-rewrite-imports-args")]
+pub struct Args {
+    /// The import path or package spec to replace.
+    #[arg(long, value_name = "IMPORT")]
+    pub from: String,
+
+    /// The import path or package spec to replace it with.
+    #[arg(long, value_name = "IMPORT")]
+    pub to: String,
+
+    /// Only show the changes which would be made.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    if args.from == args.to {
+        writeln!(ctx.ui.error()?, "The imports to rewrite are identical")?;
+        eyre::bail!(OperationFailure);
+    }
+
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests(&project)?;
+
+    let mut paths = vec![];
+    for test in suite.unit_tests() {
+        paths.push(project.unit_test_script(test.id()));
+
+        if test.kind().is_ephemeral() {
+            paths.push(project.unit_test_ref_script(test.id()));
+        }
+    }
+
+    let mut files = 0;
+    let mut imports = 0;
+
+    for path in paths {
+        let source = fs::read_to_string(&path)?;
+        let ranges = find_imports(&source, &args.from);
+        if ranges.is_empty() {
+            continue;
+        }
+
+        let rewritten = rewrite(&source, &ranges, &args.to);
+
+        if args.dry_run {
+            let mut w = ctx.ui.stderr();
+            let display = path.strip_prefix(project.root()).unwrap_or(&path);
+            cwrite!(bold(w), "{}\n", display.display())?;
+
+            for (old, new) in source.lines().zip(rewritten.lines()) {
+                if old != new {
+                    cwrite!(colored(w, Color::Red), "- {old}\n")?;
+                    cwrite!(colored(w, Color::Green), "+ {new}\n")?;
+                }
+            }
+            writeln!(w)?;
+        } else {
+            fs::write(&path, rewritten)?;
+        }
+
+        files += 1;
+        imports += ranges.len();
+    }
+
+    let mut w = ctx.ui.stderr();
+    if args.dry_run {
+        write!(w, "Would rewrite ")?;
+    } else {
+        write!(w, "Rewrote ")?;
+    }
+    cwrite!(colored(w, Color::Green), "{imports}")?;
+    write!(w, " {} in ", Term::simple("import").with(imports))?;
+    cwrite!(colored(w, Color::Green), "{files}")?;
+    writeln!(w, " {}", Term::simple("file").with(files))?;
+
+    Ok(())
+}
+
+/// Returns the byte ranges of all string literals which are used as the source
+/// of an import and are equal to the given import.
+fn find_imports(source: &str, import: &str) -> Vec<Range<usize>> {
+    fn visit(node: &LinkedNode, import: &str, ranges: &mut Vec<Range<usize>>) {
+        if let Some(ast::Expr::Import(module)) = node.cast::<ast::Expr>() {
+            if matches!(module.source(), ast::Expr::Str(str) if str.get() == import) {
+                if let Some(str) = node.children().find(|c| c.kind() == SyntaxKind::Str) {
+                    ranges.push(str.range());
+                }
+            }
+        }
+
+        for child in node.children() {
+            visit(&child, import, ranges);
+        }
+    }
+
+    let root = typst::syntax::parse(source);
+    let mut ranges = vec![];
+    visit(&LinkedNode::new(&root), import, &mut ranges);
+    ranges
+}
+
+/// Replaces the given ranges in the source with the given import as a string
+/// literal.
+fn rewrite(source: &str, ranges: &[Range<usize>], import: &str) -> String {
+    let literal = format!("\"{}\"", import.replace('\\', "\\\\").replace('"', "\\\""));
+
+    let mut rewritten = String::with_capacity(source.len());
+    let mut last = 0;
+    for range in ranges {
+        rewritten.push_str(&source[last..range.start]);
+        rewritten.push_str(&literal);
+        last = range.end;
+    }
+    rewritten.push_str(&source[last..]);
+
+    rewritten
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_imports() {
+        let source = "\
+#import \"../src/lib.typ\": foo
+#import \"../src/lib.typ\"
+#import \"../src/other.typ\": bar
+#let path = \"../src/lib.typ\"
+#include \"../src/lib.typ\"
+";
+
+        let ranges = find_imports(source, "../src/lib.typ");
+        assert_eq!(ranges.len(), 2);
+
+        assert_eq!(
+            rewrite(source, &ranges, "@preview/mypkg:0.3.0"),
+            "\
+#import \"@preview/mypkg:0.3.0\": foo
+#import \"@preview/mypkg:0.3.0\"
+#import \"../src/other.typ\": bar
+#let path = \"../src/lib.typ\"
+#include \"../src/lib.typ\"
+"
+        );
+    }
+}
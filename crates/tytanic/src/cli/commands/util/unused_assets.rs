@@ -0,0 +1,173 @@
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use termcolor::Color;
+use typst::diag::Warned;
+use tytanic_core::bench::Baselines;
+use tytanic_core::doc::compile;
+use tytanic_core::doc::compile::Warnings;
+use tytanic_core::project::Project;
+use tytanic_core::test::unit::NOTES_FILES;
+use tytanic_core::test::UnitTest;
+use tytanic_utils::fmt::Term;
+
+use super::Context;
+use crate::cli::commands::CompileOptions;
+use crate::cli::OperationFailure;
+use crate::cwrite;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "util-unused-assets-args")]
+pub struct Args {
+    /// Delete the unused files.
+    #[arg(long)]
+    pub delete: bool,
+
+    #[command(flatten)]
+    pub compile: CompileOptions,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let suite = ctx.collect_tests(&project)?;
+    let world = ctx.world(&args.compile)?;
+
+    // NOTE(tinger): The world is not reset between compilations, so it
+    // accumulates the dependencies of all tests.
+    let mut failed = 0;
+    for test in suite.unit_tests() {
+        let sources = [
+            Some(test.load_source(&project)?),
+            test.load_reference_source(&project)?,
+        ];
+
+        for source in sources.into_iter().flatten() {
+            let Warned { output, .. } = compile::compile(source, &world, Warnings::Ignore, |w| {
+                w.augment_standard_library(true)
+            });
+
+            if output.is_err() {
+                failed += 1;
+            }
+        }
+    }
+
+    let dependencies: HashSet<_> = world.dependencies().into_iter().collect();
+
+    let mut unused = BTreeSet::new();
+    for test in suite.unit_tests() {
+        for file in collect_assets(&project, test)? {
+            if !dependencies.contains(&file) {
+                unused.insert(file);
+            }
+        }
+    }
+
+    if failed != 0 {
+        let mut w = ctx.ui.warn()?;
+        cwrite!(colored(w, Color::Red), "{failed}")?;
+        writeln!(
+            w,
+            " {} failed to compile, some used files may be reported as unused",
+            Term::simple("document").with(failed),
+        )?;
+    }
+
+    if unused.is_empty() {
+        writeln!(ctx.ui.stderr(), "No unused files found")?;
+        return Ok(());
+    }
+
+    let mut w = ctx.ui.stderr();
+    writeln!(w, "These files are probably unused:")?;
+    for file in &unused {
+        let display = file.strip_prefix(project.root()).unwrap_or(file);
+        cwrite!(colored(w, Color::Cyan), "  {}\n", display.display())?;
+    }
+    writeln!(w)?;
+    drop(w);
+
+    if !args.delete {
+        let mut w = ctx.ui.hint()?;
+        write!(w, "Run ")?;
+        cwrite!(colored(w, Color::Cyan), "tt util unused-assets --delete")?;
+        writeln!(w, " to delete them")?;
+        return Ok(());
+    }
+
+    if failed != 0 {
+        writeln!(
+            ctx.ui.error()?,
+            "Not deleting any files, some documents failed to compile"
+        )?;
+        eyre::bail!(OperationFailure);
+    }
+
+    for file in &unused {
+        fs::remove_file(file)?;
+    }
+
+    let mut w = ctx.ui.stderr();
+    write!(w, "Deleted ")?;
+    cwrite!(colored(w, Color::Green), "{}", unused.len())?;
+    writeln!(w, " {}", Term::simple("file").with(unused.len()))?;
+
+    Ok(())
+}
+
+/// Collects all files in the directory of the given test which are not part of
+/// the test layout, i.e. the scripts, notes, benchmark baselines and the
+/// reference, output and difference directories.
+fn collect_assets(project: &Project, test: &UnitTest) -> io::Result<Vec<PathBuf>> {
+    let id = test.id();
+    let mut layout = vec![
+        project.unit_test_script(id),
+        project.unit_test_ref_script(id),
+        project.unit_test_ref_dir(id),
+        project.unit_test_out_dir(id),
+        project.unit_test_diff_dir(id),
+        Baselines::file(project, id),
+    ];
+    layout.extend(
+        NOTES_FILES
+            .iter()
+            .map(|name| project.unit_test_dir(id).join(name)),
+    );
+
+    let mut files = vec![];
+    for entry in fs::read_dir(project.unit_test_dir(id))? {
+        let path = entry?.path();
+        if !layout.contains(&path) {
+            collect_files(&path, &mut files)?;
+        }
+    }
+
+    Ok(files)
+}
+
+/// Collects the given path if it's a non-hidden file or all such files within
+/// it if it's a directory.
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    {
+        return Ok(());
+    }
+
+    if path.is_dir() {
+        for entry in fs::read_dir(path)? {
+            collect_files(&entry?.path(), files)?;
+        }
+    } else {
+        files.push(path.to_path_buf());
+    }
+
+    Ok(())
+}
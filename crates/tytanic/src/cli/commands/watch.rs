@@ -0,0 +1,299 @@
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::Duration;
+
+use color_eyre::eyre;
+use notify_debouncer_mini::new_debouncer;
+use notify_debouncer_mini::notify::RecursiveMode;
+use notify_debouncer_mini::DebouncedEvent;
+use termcolor::Color;
+use tytanic_core::doc::render;
+use tytanic_core::doc::render::Origin;
+use tytanic_core::project::Project;
+use tytanic_core::suite::Filter;
+use tytanic_core::suite::FilterCache;
+use tytanic_core::suite::Fingerprint;
+use tytanic_core::suite::Suite;
+use tytanic_core::test::Test;
+use tytanic_core::Id;
+use tytanic_utils::fmt::Term;
+
+use super::CompareOptions;
+use super::CompileOptions;
+use super::Context;
+use super::Direction;
+use super::ExportOptions;
+use super::FilterOptions;
+use super::OptionDelegate;
+use super::OutputFormat;
+use super::RunnerOptions;
+use super::Switch;
+use crate::cli::CANCELLED;
+use crate::cwrite;
+use crate::report::Reporter;
+use crate::runner::Action;
+use crate::runner::Dependencies;
+use crate::runner::Runner;
+use crate::runner::RunnerConfig;
+
+/// The number of compilations after which unused cache entries are evicted.
+const EVICT_AGE: usize = 10;
+
+/// How long to wait for further changes before re-running tests, this groups
+/// the many events emitted when an editor saves a file or a branch is checked
+/// out.
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How often to check whether watching was cancelled while no changes occur.
+const CANCELLATION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "watch-args")]
+pub struct Args {
+    #[command(flatten)]
+    pub compile: CompileOptions,
+
+    #[command(flatten)]
+    pub compare: CompareOptions,
+
+    #[command(flatten)]
+    pub export: ExportOptions,
+
+    #[command(flatten)]
+    pub runner: RunnerOptions,
+
+    #[command(flatten)]
+    pub filter: FilterOptions,
+}
+
+pub fn run(ctx: &mut Context, args: &Args) -> eyre::Result<()> {
+    let project = ctx.project()?;
+    let profile = ctx.profile(&project);
+    let filter = ctx.filter(&project, &args.filter)?;
+
+    // NOTE(tinger): The world is kept between runs, such that unchanged files
+    // are not read again and compilations are incremental.
+    let world = Arc::new(ctx.world(&args.compile)?);
+
+    let origin = match args
+        .export
+        .dir
+        .map(OptionDelegate::into_native)
+        .unwrap_or(project.config().defaults.direction)
+    {
+        Direction::Ltr => Origin::TopLeft,
+        Direction::Rtl => Origin::TopRight,
+    };
+
+    let pixel_per_pt = render::ppi_to_ppp(args.export.ppi.unwrap_or(project.config().defaults.ppi));
+
+    let alpha = args
+        .compare
+        .alpha
+        .map(OptionDelegate::into_native)
+        .unwrap_or(project.config().defaults.alpha);
+
+    let live = ctx.ui.can_live_report()
+        && ctx.args.output.verbose == 0
+        && ctx.args.output.format == OutputFormat::Human;
+
+    let (tx, rx) = mpsc::channel();
+    let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, tx)?;
+    debouncer
+        .watcher()
+        .watch(project.root(), RecursiveMode::Recursive)?;
+
+    let dependencies = RefCell::new(Dependencies::new());
+    let mut changed: Option<BTreeSet<PathBuf>> = None;
+    let mut cache = FilterCache::new();
+    let mut collected: Option<(Fingerprint, Suite)> = None;
+
+    loop {
+        // NOTE(tinger): Test sets like `pages()` read a test's directory when
+        // they're applied, so tests whose directory changed are evaluated
+        // again.
+        if let Some(changed) = &changed {
+            cache.retain(|id| {
+                let dir = project.unit_test_dir(id);
+                !changed.iter().any(|path| path.starts_with(&dir))
+            });
+        }
+
+        // NOTE(tinger): Most changes only touch the sources or references of
+        // existing tests, in which case the tests don't need to be collected
+        // again.
+        let fingerprint = Fingerprint::compute(&project)?;
+        let tests = match collected {
+            Some((last, tests)) if last == fingerprint => tests,
+            _ => ctx.collect_tests(&project)?,
+        };
+        collected = Some((fingerprint, tests.clone()));
+
+        let suite = tests.filter_cached(filter.clone(), &mut cache)?;
+
+        // Forget tests which no longer exist.
+        dependencies
+            .borrow_mut()
+            .retain(|id, _| suite.matched().contains(id));
+
+        let affected = match &changed {
+            Some(changed) => affected(&project, suite.matched(), &dependencies.borrow(), changed),
+            None => suite
+                .matched()
+                .tests()
+                .map(|test| test.id().clone())
+                .collect(),
+        };
+
+        if !affected.is_empty() {
+            let suite = suite.matched().clone().filter(Filter::Explicit(affected))?;
+
+            let runner = Runner::new(
+                &project,
+                &suite,
+                &world,
+                RunnerConfig {
+                    warnings: args.compile.warnings(&project.config().defaults),
+                    optimize: args
+                        .export
+                        .optimize_refs
+                        .get_or(Some(project.config().defaults.optimize_refs)),
+                    fail_fast: args.runner.fail_fast.get_or(profile.fail_fast),
+                    pixel_per_pt,
+                    alpha,
+                    limits: project.config().defaults.limits(),
+                    strategy: args
+                        .compare
+                        .compare
+                        .get_or(profile.compare)
+                        .then_some(args.compare.strategy(&project.config().defaults)),
+                    export_ephemeral: args
+                        .export
+                        .export_ephemeral
+                        .get_or(profile.export_ephemeral),
+                    origin,
+                    heatmap: args.export.diff_heatmap,
+                    export_pdf: args.export.pdf(),
+                    action: Action::Run,
+                    cancellation: &CANCELLED,
+                    timeout: args.runner.timeout,
+                    meta: Default::default(),
+                    controls: None,
+                    installed: None,
+                    remote: None,
+                    dependencies: Some(&dependencies),
+                },
+            );
+
+            let reporter =
+                Reporter::new(ctx.ui, &project, &world, live, ctx.args.output.verbose > 0)
+                    .with_format(ctx.args.output.format);
+            runner.run(&reporter)?;
+
+            // NOTE(tinger): The artifacts of this run must not trigger
+            // another run, their events arrive once the debounce timeout
+            // passed.
+            while rx.recv_timeout(DEBOUNCE_TIMEOUT * 2).is_ok() {}
+        }
+
+        comemo::evict(EVICT_AGE);
+
+        let mut w = ctx.ui.stderr();
+        cwrite!(colored(w, Color::Cyan), "Watching")?;
+        writeln!(w, " for changes, press Ctrl+C to stop")?;
+        drop(w);
+
+        let files = loop {
+            if CANCELLED.is_cancelled() {
+                return Ok(());
+            }
+
+            let events = match rx.recv_timeout(CANCELLATION_POLL_INTERVAL) {
+                Ok(events) => events?,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => {
+                    eyre::bail!("file watcher stopped unexpectedly")
+                }
+            };
+
+            let files = changed_files(&project, suite.inner(), events);
+            if !files.is_empty() {
+                break files;
+            }
+        };
+
+        let mut w = ctx.ui.stderr();
+        write!(w, "Detected changes in ")?;
+        cwrite!(bold(w), "{}", files.len())?;
+        writeln!(w, " {}", Term::simple("file").with(files.len()))?;
+        drop(w);
+
+        changed = Some(files);
+    }
+}
+
+/// Returns the tests which are affected by the given changed files.
+///
+/// A test is affected if it accessed a changed file in its last run, if a
+/// file in its directory changed or if it wasn't run before.
+fn affected(
+    project: &Project,
+    suite: &Suite,
+    dependencies: &Dependencies,
+    changed: &BTreeSet<PathBuf>,
+) -> BTreeSet<Id> {
+    suite
+        .tests()
+        .filter(|test| {
+            let Some(dependencies) = dependencies.get(test.id()) else {
+                return true;
+            };
+
+            if dependencies.iter().any(|path| changed.contains(path)) {
+                return true;
+            }
+
+            match test {
+                Test::Unit(test) => {
+                    let dir = project.unit_test_dir(test.id());
+                    changed.iter().any(|path| path.starts_with(&dir))
+                }
+                Test::Template(_) => false,
+            }
+        })
+        .map(|test| test.id().clone())
+        .collect()
+}
+
+/// Returns the changed files of the given events, hidden directories and the
+/// temporary directories of tests are skipped.
+fn changed_files(
+    project: &Project,
+    suite: &Suite,
+    events: Vec<DebouncedEvent>,
+) -> BTreeSet<PathBuf> {
+    let mut skip = BTreeSet::new();
+    for test in suite.unit_tests() {
+        skip.insert(project.unit_test_out_dir(test.id()));
+        skip.insert(project.unit_test_diff_dir(test.id()));
+    }
+
+    events
+        .into_iter()
+        .map(|event| event.path)
+        .filter(|path| {
+            let hidden = path
+                .strip_prefix(project.root())
+                .unwrap_or(path)
+                .components()
+                .any(|c| c.as_os_str().to_string_lossy().starts_with('.'));
+
+            !hidden && !skip.iter().any(|dir| path.starts_with(dir))
+        })
+        .collect()
+}
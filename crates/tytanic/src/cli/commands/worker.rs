@@ -0,0 +1,75 @@
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::PathBuf;
+
+use color_eyre::eyre;
+use termcolor::Color;
+
+use super::Context;
+use crate::cwrite;
+use crate::remote;
+use crate::remote::Roots;
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "worker-args")]
+pub struct Args {
+    /// The sub command to run.
+    #[command(subcommand)]
+    pub cmd: Command,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Serve test requests from coordinators (experimental).
+    ///
+    /// There is no authentication, only use this within trusted networks.
+    #[command()]
+    Serve(ServeArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(id = "worker-serve-args")]
+pub struct ServeArgs {
+    /// The address to listen on.
+    ///
+    /// Listen on `0.0.0.0` to accept coordinators from other hosts.
+    #[arg(long, value_name = "ADDR", default_value_t = format!("127.0.0.1:{}", remote::DEFAULT_PORT))]
+    pub listen: String,
+
+    /// Accept projects given by a path within this directory instead of
+    /// receiving their files.
+    ///
+    /// This is used by workers in containers with the project mounted,
+    /// coordinators can read any file within this directory.
+    #[arg(long, value_name = "DIR")]
+    pub allow_root: Option<PathBuf>,
+}
+
+impl Command {
+    pub fn run(&self, ctx: &mut Context) -> eyre::Result<()> {
+        match self {
+            Command::Serve(args) => {
+                let listener = TcpListener::bind(&args.listen)?;
+
+                let mut w = ctx.ui.stderr();
+                write!(w, "Listening on ")?;
+                cwrite!(colored(w, Color::Cyan), "{}", listener.local_addr()?)?;
+                writeln!(w)?;
+                drop(w);
+
+                remote::serve(
+                    listener,
+                    ctx.args.font.clone(),
+                    ctx.args.package.clone(),
+                    args.allow_root
+                        .clone()
+                        .map(Roots::Within)
+                        .unwrap_or(Roots::None),
+                    false,
+                )?;
+
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Dynamic shell completions for values which depend on the current project.
+//!
+//! These are used by the completion scripts printed by `tt util completion`,
+//! which call back into Tytanic while completing. The project is discovered
+//! like it would be without `--root`, the `TYPST_ROOT` variable is respected.
+
+use std::env;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+
+use clap_complete::CompletionCandidate;
+use tytanic_core::dsl;
+use tytanic_core::project::Project;
+use tytanic_core::project::ShallowProject;
+use tytanic_core::suite::Suite;
+
+/// The environment variable used by the completion scripts to request
+/// completions.
+pub const COMPLETE_VAR: &str = "TT_COMPLETE";
+
+/// Loads the current project, errors are ignored, there is no way to report
+/// them while completing.
+fn project() -> Option<Project> {
+    let root = env::var_os("TYPST_ROOT").map(PathBuf::from);
+    let search_manifest = root.is_some();
+    let root = match root {
+        Some(root) => root,
+        None => env::current_dir().ok()?,
+    };
+
+    ShallowProject::discover(root, search_manifest)
+        .ok()??
+        .load()
+        .ok()
+}
+
+/// Completes the identifiers of the tests in the current project.
+pub fn test_ids() -> Vec<CompletionCandidate> {
+    let Some(project) = project() else {
+        return vec![];
+    };
+
+    let Ok(suite) = Suite::collect(&project) else {
+        return vec![];
+    };
+
+    suite
+        .tests()
+        .map(|test| CompletionCandidate::new(test.id().as_str()))
+        .collect()
+}
+
+/// Completes the profiles configured in the current project.
+pub fn profiles() -> Vec<CompletionCandidate> {
+    let Some(project) = project() else {
+        return vec![];
+    };
+
+    project
+        .config()
+        .profiles
+        .keys()
+        .map(CompletionCandidate::new)
+        .collect()
+}
+
+/// Completes the name of the test set function at the end of the given test
+/// set expression, this includes the test sets configured in the current
+/// project.
+pub fn test_sets(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return vec![];
+    };
+
+    let start = current
+        .rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .map_or(0, |idx| idx + 1);
+    let (expr, name) = current.split_at(start);
+
+    let ctx = match project() {
+        Some(project) => dsl::project_context(&project),
+        None => dsl::context(),
+    };
+
+    ctx.ids()
+        .filter(|id| id.as_str().starts_with(name))
+        .map(|id| CompletionCandidate::new(format!("{expr}{}(", id.as_str())))
+        .collect()
+}
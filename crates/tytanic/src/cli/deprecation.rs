@@ -0,0 +1,199 @@
+//! The registry of deprecated parts of the command line interface.
+//!
+//! All deprecated sub command aliases and flags are registered in
+//! [`DEPRECATIONS`], such that they are reported consistently, can be denied
+//! using `--deny-deprecated` and are listed by `tt util deprecations`.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::io;
+use std::io::Write;
+
+use termcolor::Color;
+
+use crate::cwrite;
+use crate::ui;
+use crate::ui::Ui;
+
+/// The kind of a deprecated part of the command line interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A sub command or sub command alias.
+    Command,
+
+    /// A long flag or option.
+    // NOTE(tinger): No flags are deprecated at the moment.
+    #[allow(dead_code)]
+    Flag,
+}
+
+impl Kind {
+    /// The name of this kind, as used in the JSON output.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Command => "command",
+            Self::Flag => "flag",
+        }
+    }
+}
+
+/// A deprecated part of the command line interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deprecation {
+    /// The kind of the deprecated part.
+    pub kind: Kind,
+
+    /// The deprecated name, flags are given without their leading dashes.
+    pub name: &'static str,
+
+    /// The name which should be used instead.
+    pub replacement: &'static str,
+
+    /// The version in which this was deprecated.
+    pub since: &'static str,
+
+    /// The version in which this will be removed.
+    pub removal: &'static str,
+}
+
+impl Deprecation {
+    /// Whether the given arguments use this deprecated part, the arguments
+    /// must not include the program name.
+    ///
+    /// Sub commands are only detected if they are the first argument which
+    /// isn't a flag.
+    pub fn is_used_by(&self, args: &[OsString]) -> bool {
+        let args = args
+            .iter()
+            .map(|arg| arg.to_string_lossy())
+            .take_while(|arg| arg != "--");
+
+        match self.kind {
+            Kind::Command => args
+                .into_iter()
+                .find(|arg| !arg.starts_with('-'))
+                .is_some_and(|arg| arg == self.name),
+            Kind::Flag => args.into_iter().any(|arg| {
+                arg.strip_prefix("--").is_some_and(|flag| {
+                    flag == self.name
+                        || flag
+                            .strip_prefix(self.name)
+                            .is_some_and(|rest| rest.starts_with('='))
+                })
+            }),
+        }
+    }
+}
+
+impl Display for Deprecation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Kind::Command => write!(f, "{}", self.name),
+            Kind::Flag => write!(f, "--{}", self.name),
+        }
+    }
+}
+
+/// All deprecated parts of the command line interface.
+pub static DEPRECATIONS: &[Deprecation] = &[
+    Deprecation {
+        kind: Kind::Command,
+        name: "add",
+        replacement: "new",
+        since: "0.1.2",
+        removal: "0.3.0",
+    },
+    Deprecation {
+        kind: Kind::Command,
+        name: "remove",
+        replacement: "delete",
+        since: "0.1.2",
+        removal: "0.3.0",
+    },
+    Deprecation {
+        kind: Kind::Command,
+        name: "rm",
+        replacement: "delete",
+        since: "0.1.2",
+        removal: "0.3.0",
+    },
+];
+
+/// Returns the deprecated parts used by the given arguments, the arguments
+/// must not include the program name.
+pub fn used(args: &[OsString]) -> Vec<&'static Deprecation> {
+    DEPRECATIONS
+        .iter()
+        .filter(|deprecation| deprecation.is_used_by(args))
+        .collect()
+}
+
+/// Reports the given deprecated parts as warnings, or as errors if they are
+/// denied.
+pub fn report(ui: &Ui, used: &[&Deprecation], deny: bool) -> io::Result<()> {
+    for deprecation in used {
+        let mut w = if deny {
+            ui::error(ui.stderr())?
+        } else {
+            ui::warn(ui.stderr())?
+        };
+        match deprecation.kind {
+            Kind::Command => write!(w, "Sub command alias ")?,
+            Kind::Flag => write!(w, "Flag ")?,
+        }
+        cwrite!(colored(w, Color::Cyan), "{deprecation}")?;
+        writeln!(
+            w,
+            " is deprecated since {} and will be removed in {}",
+            deprecation.since, deprecation.removal,
+        )?;
+        drop(w);
+
+        let mut w = ui.hint()?;
+        write!(w, "Use ")?;
+        match deprecation.kind {
+            Kind::Command => cwrite!(colored(w, Color::Cyan), "{}", deprecation.replacement)?,
+            Kind::Flag => cwrite!(colored(w, Color::Cyan), "--{}", deprecation.replacement)?,
+        }
+        writeln!(w, " instead")?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_is_used_by() {
+        let command = Deprecation {
+            kind: Kind::Command,
+            name: "add",
+            replacement: "new",
+            since: "0.1.0",
+            removal: "0.2.0",
+        };
+        let flag = Deprecation {
+            kind: Kind::Flag,
+            name: "old",
+            replacement: "new",
+            since: "0.1.0",
+            removal: "0.2.0",
+        };
+
+        assert!(command.is_used_by(&args(&["--verbose", "add", "foo"])));
+        assert!(!command.is_used_by(&args(&["new", "add"])));
+
+        assert!(flag.is_used_by(&args(&["run", "--old"])));
+        assert!(flag.is_used_by(&args(&["run", "--old=1"])));
+        assert!(!flag.is_used_by(&args(&["run", "--older"])));
+        assert!(!flag.is_used_by(&args(&["run", "--", "--old"])));
+    }
+}
@@ -1,42 +1,60 @@
 use std::env;
 use std::io;
 use std::io::Write;
+use std::path::Path;
 use std::path::PathBuf;
-use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
 use color_eyre::eyre;
 use color_eyre::eyre::WrapErr;
 use commands::CompileOptions;
+use once_cell::sync::Lazy;
 use termcolor::Color;
+use termcolor::WriteColor;
 use thiserror::Error;
+use tytanic_core::cancel::CancellationToken;
+use tytanic_core::config::ProjectProfile;
 use tytanic_core::doc;
 use tytanic_core::dsl;
+use tytanic_core::history::History;
 use tytanic_core::project::ConfigError;
 use tytanic_core::project::ManifestError;
 use tytanic_core::project::Project;
 use tytanic_core::project::ShallowProject;
+use tytanic_core::project::ValidationError;
 use tytanic_core::suite::Filter;
 use tytanic_core::suite::FilterError;
 use tytanic_core::suite::FilteredSuite;
 use tytanic_core::suite::Suite;
 use tytanic_core::test;
+use tytanic_core::test::Id;
 use tytanic_core::test::ParseIdError;
 use tytanic_filter::eval;
 use tytanic_filter::ExpressionFilter;
+use tytanic_utils::fmt::Term;
 
+use self::commands::util::fix_ids;
 use self::commands::CliArguments;
 use self::commands::FilterOptions;
+use self::commands::OutputFormat;
+use self::commands::PackageOptions;
 use self::commands::Switch;
+use crate::crash;
 use crate::cwrite;
 use crate::kit;
+use crate::progress::Progress;
+use crate::registry::PackageStore;
+use crate::registry::Recording;
 use crate::ui;
 use crate::ui::Ui;
 use crate::world::SystemWorld;
 
 pub mod commands;
+pub mod complete;
+pub mod deprecation;
 
-/// Whether we received a signal we can gracefully exit from.
-pub static CANCELLED: AtomicBool = AtomicBool::new(false);
+/// Cancelled once we received a signal we can gracefully exit from.
+pub static CANCELLED: Lazy<CancellationToken> = Lazy::new(CancellationToken::new);
 
 /// Tytanic exited successfully.
 pub const EXIT_OK: u8 = 0;
@@ -55,6 +73,18 @@ pub const EXIT_ERROR: u8 = 3;
 #[error("an operation failed")]
 pub struct OperationFailure;
 
+/// Writing to an output path given by the user failed, this is a graceful
+/// error rather than a bug.
+#[derive(Debug, Error)]
+#[error("couldn't write {}: {source}", path.display())]
+pub struct OutputError {
+    /// The path which couldn't be written.
+    pub path: PathBuf,
+
+    /// The underlying error.
+    pub source: io::Error,
+}
+
 /// A test failure.
 #[derive(Debug, Error)]
 #[error("one or more test failed")]
@@ -66,11 +96,22 @@ pub struct Context<'a> {
 
     /// The terminal UI.
     pub ui: &'a Ui,
+
+    /// The packages used by this invocation, if they are recorded.
+    pub recording: Option<Arc<Recording>>,
 }
 
 impl<'a> Context<'a> {
     pub fn new(args: &'a CliArguments, ui: &'a Ui) -> Self {
-        Self { args, ui }
+        Self {
+            args,
+            ui,
+            recording: args
+                .package
+                .record_packages
+                .is_some()
+                .then(|| Arc::new(Recording::new())),
+        }
     }
 }
 
@@ -87,6 +128,16 @@ impl Context<'_> {
     }
 }
 
+/// Writes the invalid keys of a validation error and their causes.
+fn write_validation_error<W: WriteColor>(mut w: W, error: &ValidationError) -> io::Result<()> {
+    for (key, cause) in &error.errors {
+        cwrite!(colored(w, Color::Cyan), "{key}")?;
+        writeln!(w, ": {cause}")?;
+    }
+
+    Ok(())
+}
+
 // TODO(tinger): Cache these values.
 impl Context<'_> {
     /// Resolve the current root.
@@ -105,47 +156,177 @@ impl Context<'_> {
         })
     }
 
-    /// Discover the current and ensure it is initialized.
+    /// Discover the current project without loading it.
     #[tracing::instrument(skip_all)]
-    pub fn project(&self) -> eyre::Result<Project> {
+    pub fn shallow_project(&self) -> eyre::Result<ShallowProject> {
         let root = self.root()?;
 
-        let Some(project) = ShallowProject::discover(root, self.args.root.is_some())? else {
-            writeln!(self.ui.error()?, "Must be in a typst project")?;
+        if let Some(project) = ShallowProject::discover(&root, self.args.root.is_some())? {
+            return Ok(project);
+        }
 
-            let mut w = self.ui.hint()?;
-            write!(w, "You can pass the project root using ")?;
-            cwrite!(colored(w, Color::Cyan), "--root <path>")?;
+        // NOTE(tinger): Documents which aren't packages, like theses or
+        // reports, don't have a manifest, for those we fall back to the root
+        // of the repository they're in.
+        let fallback = ShallowProject::discover_vcs_root(&root)?;
+        if let Some(project) = fallback.clone() {
+            if self.args.allow_no_manifest {
+                tracing::debug!(root = ?project.root(), "using vcs root as project root");
+                return Ok(project);
+            }
+
+            if self.ui.can_prompt()
+                && self.ui.prompt_yes_no(
+                    format_args!(
+                        "No typst.toml found, use the repository root {} as the project root?",
+                        project.root().display(),
+                    ),
+                    false,
+                )?
+            {
+                return Ok(project);
+            }
+        }
+
+        writeln!(self.ui.error()?, "Must be in a typst project")?;
+
+        let mut w = self.ui.hint()?;
+        write!(w, "You can pass the project root using ")?;
+        cwrite!(colored(w, Color::Cyan), "--root <path>")?;
+        writeln!(w)?;
+
+        if let Some(project) = fallback {
+            write!(
+                w,
+                "You can use the repository root {} using ",
+                project.root().display()
+            )?;
+            cwrite!(colored(w, Color::Cyan), "--allow-no-manifest")?;
             writeln!(w)?;
+        }
+        eyre::bail!(OperationFailure);
+    }
+
+    /// Ensures that the given project is not a downloaded or vendored package,
+    /// this must be called by commands which modify tests.
+    pub fn ensure_not_package_dir(&self, project: &Project) -> eyre::Result<()> {
+        let store = kit::package_store_from_args(&self.args.package);
+        let Some(dir) = package_dir(&store, project.root()) else {
+            return Ok(());
+        };
+
+        writeln!(
+            self.ui.error()?,
+            "Refusing to modify the tests of a downloaded or vendored package"
+        )?;
+
+        let mut w = self.ui.hint()?;
+        writeln!(
+            w,
+            "The project root is inside the package directory {}",
+            dir.display()
+        )?;
+        writeln!(w, "Copy the package elsewhere to work on its tests")?;
+
+        eyre::bail!(OperationFailure);
+    }
+
+    /// Discover the current project and ensure it is initialized.
+    #[tracing::instrument(skip_all)]
+    pub fn project(&self) -> eyre::Result<Project> {
+        let project = self.shallow_project()?.load()?;
+        crash::set_project(&project);
+
+        let Some(name) = &self.args.profile else {
+            return Ok(project);
+        };
+
+        let Some(profile) = project.config().profile(name) else {
+            writeln!(self.ui.error()?, "Profile '{name}' not found")?;
+
+            let mut profiles = project.config().profiles.keys().peekable();
+            if profiles.peek().is_some() {
+                let mut w = self.ui.hint()?;
+                write!(w, "Available profiles are ")?;
+                for (idx, profile) in profiles.enumerate() {
+                    if idx != 0 {
+                        write!(w, ", ")?;
+                    }
+                    cwrite!(colored(w, Color::Cyan), "{profile}")?;
+                }
+                writeln!(w)?;
+            }
+
             eyre::bail!(OperationFailure);
         };
 
-        Ok(project.load()?)
+        let mut config = project.config().clone();
+        profile.apply_defaults(&mut config.defaults);
+
+        Ok(project.with_config(config))
+    }
+
+    /// Returns the selected profile of the given project, or an empty profile
+    /// if none was selected.
+    ///
+    /// The project must have been loaded using [`Context::project`].
+    pub fn profile(&self, project: &Project) -> ProjectProfile {
+        self.args
+            .profile
+            .as_deref()
+            .and_then(|name| project.config().profile(name))
+            .cloned()
+            .unwrap_or_default()
     }
 
     /// Create a new filter from given arguments.
     #[tracing::instrument(skip_all)]
-    pub fn filter(&self, filter: &FilterOptions) -> eyre::Result<Filter> {
+    pub fn filter(&self, project: &Project, filter: &FilterOptions) -> eyre::Result<Filter> {
+        if filter.explain || self.args.output.verbose > 0 {
+            let mut w = self.ui.hint()?;
+            write!(w, "Effective test set: ")?;
+            cwrite!(colored(w, Color::Cyan), "{}", filter.effective_expression())?;
+            writeln!(w)?;
+        }
+
         if !filter.tests.is_empty() {
             Ok(Filter::Explicit(filter.tests.iter().cloned().collect()))
         } else {
-            let ctx = dsl::context();
+            let ctx = dsl::project_context(project);
             let mut set = ExpressionFilter::new(ctx, &filter.expression)?;
 
             if filter.skip.get_or_default() {
                 set = set.map(|set| eval::Set::expr_diff(set, dsl::built_in::skip()));
             }
 
+            if let Some(since) = filter.since {
+                set = set.map(|set| {
+                    eval::Set::expr_inter(set, dsl::built_in::mtime(project.clone(), since), [])
+                });
+            }
+
+            if filter.last_failed {
+                let history = History::load(project).wrap_err("loading test history")?;
+                set = set.map(|set| {
+                    eval::Set::expr_inter(set, dsl::built_in::previously_failed(&history), [])
+                });
+            }
+
             Ok(Filter::TestSet(set))
         }
     }
 
     /// Collect and filter tests for the given project.
+    ///
+    /// If the filter is explicit, a warning is emitted for each given test
+    /// which is marked as skipped, these are removed if `--respect-skip` was
+    /// passed.
     #[tracing::instrument(skip_all)]
     pub fn collect_tests_with_filter(
         &self,
         project: &Project,
         filter: Filter,
+        options: &FilterOptions,
     ) -> eyre::Result<FilteredSuite> {
         let suite = self.collect_tests(project)?;
 
@@ -153,7 +334,39 @@ impl Context<'_> {
             writeln!(self.ui.warn()?, "Suite is empty")?;
         }
 
-        let suite = suite.filter(filter)?;
+        let explicit = matches!(filter, Filter::Explicit(_));
+        let mut suite = suite.filter(filter)?;
+
+        // NOTE(tinger): Explicit tests imply `--no-skip`, users are often
+        // surprised by this, so we make it visible.
+        if explicit {
+            let skipped: Vec<_> = suite
+                .matched()
+                .unit_tests()
+                .filter(|test| test.is_skip())
+                .map(|test| test.id().clone())
+                .collect();
+
+            for id in &skipped {
+                let mut w = self.ui.warn()?;
+                write!(w, "Test ")?;
+                ui::write_test_id(&mut w, id)?;
+                if options.respect_skip {
+                    writeln!(w, " is marked as skipped and was left out")?;
+                } else {
+                    writeln!(w, " is marked as skipped, but was given explicitly")?;
+                }
+            }
+
+            if options.respect_skip {
+                suite.retain(|test| !skipped.contains(test.id()));
+            } else if !skipped.is_empty() {
+                writeln!(
+                    self.ui.hint()?,
+                    "Pass --respect-skip to leave out skipped tests"
+                )?;
+            }
+        }
 
         if suite.matched().is_empty() {
             writeln!(self.ui.warn()?, "Test set matched no tests")?;
@@ -185,18 +398,144 @@ impl Context<'_> {
             writeln!(w, " to automatically move the tests")?;
         }
 
+        if !suite.invalid().is_empty() {
+            let len = suite.invalid().len();
+            writeln!(
+                self.ui.warn()?,
+                "Found {len} {} with invalid {}",
+                Term::simple("test").with(len),
+                Term::simple("identifier").with(len),
+            )?;
+
+            let renames = fix_ids::renames(&project.unit_tests_root(), &suite)?;
+            fix_ids::write_renames(&mut self.ui.stderr(), &renames)?;
+
+            writeln!(self.ui.hint()?, "These tests will be ignored")?;
+
+            let reserved = suite.invalid().iter().any(|path| {
+                path.components().any(|component| {
+                    component
+                        .as_os_str()
+                        .to_string_lossy()
+                        .starts_with(Id::RESERVED_PREFIX)
+                })
+            });
+
+            if reserved {
+                let mut w = self.ui.hint()?;
+                write!(w, "Identifiers starting with ")?;
+                cwrite!(colored(w, Color::Cyan), "{}", Id::RESERVED_PREFIX)?;
+                write!(w, " are reserved for built-in tests like ")?;
+                cwrite!(colored(w, Color::Cyan), "{}", Id::TEMPLATE)?;
+                writeln!(w)?;
+            }
+
+            let mut w = self.ui.hint()?;
+            write!(w, "You can run ")?;
+            cwrite!(colored(w, Color::Cyan), "tt util fix-ids")?;
+            writeln!(w, " to rename the tests")?;
+        }
+
+        if !suite.collisions().is_empty() {
+            let len = suite.collisions().len();
+            writeln!(
+                self.ui.error()?,
+                "Found {len} {} of test directories which differ only by case or Unicode normalization",
+                Term::simple("group").with(len),
+            )?;
+
+            fix_ids::write_collisions(
+                &mut self.ui.stderr(),
+                &project.unit_tests_root(),
+                suite.collisions(),
+            )?;
+
+            writeln!(
+                self.ui.hint()?,
+                "These directories can't be checked out on case-insensitive file systems like those of Windows or macOS"
+            )?;
+            writeln!(
+                self.ui.hint()?,
+                "Rename all but one directory of each group, for example as suggested above"
+            )?;
+        }
+
         Ok(suite)
     }
 
+    /// Open the progress event writer if a file descriptor was given, or
+    /// stdout if `--format json-lines` was given.
+    pub fn progress(&self) -> eyre::Result<Option<Progress>> {
+        let Some(fd) = self.args.output.progress_fd else {
+            return Ok((self.args.output.format == OutputFormat::JsonLines).then(Progress::stdout));
+        };
+
+        #[cfg(unix)]
+        {
+            // SAFETY: We never close the file descriptor and it's not used
+            // anywhere else.
+            match unsafe { Progress::from_raw_fd(fd) } {
+                Ok(progress) => Ok(Some(progress)),
+                Err(err) => {
+                    writeln!(
+                        self.ui.error()?,
+                        "Cannot write progress to file descriptor {fd}: {err}"
+                    )?;
+                    eyre::bail!(OperationFailure);
+                }
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            _ = fd;
+            writeln!(
+                self.ui.error()?,
+                "--progress-fd is only supported on unix platforms"
+            )?;
+            eyre::bail!(OperationFailure);
+        }
+    }
+
     /// Create a SystemWorld from the given args.
     #[tracing::instrument(skip_all)]
     pub fn world(&self, compile_options: &CompileOptions) -> eyre::Result<SystemWorld> {
-        kit::world(
+        let world = kit::world(
             self.root()?,
             &self.args.font,
-            &self.args.package,
+            self.package_store(&self.args.package),
             compile_options,
-        )
+        )?;
+
+        Ok(world.with_relative_names(self.args.output.format == OutputFormat::QuietErrors))
+    }
+
+    /// Create a package store for the given options, which records the used
+    /// packages if requested.
+    pub fn package_store(&self, package_options: &PackageOptions) -> PackageStore {
+        kit::package_store_from_args(package_options).with_recording(self.recording.clone())
+    }
+
+    /// Write the recorded packages to the archive given by
+    /// `--record-packages`.
+    fn write_recording(&self) -> eyre::Result<()> {
+        let (Some(recording), Some(path)) = (&self.recording, &self.args.package.record_packages)
+        else {
+            return Ok(());
+        };
+
+        let packages = recording
+            .write(path)
+            .wrap_err_with(|| format!("couldn't write recording {}", path.display()))?;
+
+        let mut w = self.ui.stderr();
+        write!(w, "Recorded ")?;
+        cwrite!(colored(w, Color::Green), "{packages}")?;
+        write!(w, " {} to ", Term::simple("package").with(packages))?;
+        cwrite!(colored(w, Color::Cyan), "{}", path.display())?;
+        writeln!(w)?;
+
+        Ok(())
     }
 }
 
@@ -204,7 +543,10 @@ impl Context<'_> {
     /// Run the parsed command and report errors as UI messages.
     #[tracing::instrument(skip_all)]
     pub fn run(&mut self) -> eyre::Result<()> {
-        let Err(error) = self.args.cmd.run(self) else {
+        let result = self.args.cmd.run(self);
+        self.write_recording()?;
+
+        let Err(error) = result else {
             return Ok(());
         };
 
@@ -223,6 +565,42 @@ impl Context<'_> {
                 eyre::bail!(OperationFailure);
             }
 
+            if let Some(doc::LoadError::NotMaterialized(path)) = error.downcast_ref() {
+                let mut w = self.ui.error()?;
+                write!(w, "Reference ")?;
+                cwrite!(colored(w, Color::Cyan), "{}", path.display())?;
+                writeln!(w, " is not materialized")?;
+
+                let mut w = self.ui.hint()?;
+                write!(w, "It is a Git LFS pointer, run ")?;
+                cwrite!(colored(w, Color::Cyan), "git lfs pull")?;
+                writeln!(w, " to fetch it")?;
+
+                eyre::bail!(OperationFailure);
+            }
+
+            if let Some(doc::LoadError::Page { path, error }) = error.downcast_ref() {
+                let mut w = self.ui.error()?;
+                write!(w, "Reference ")?;
+                cwrite!(colored(w, Color::Cyan), "{}", path.display())?;
+                write!(w, " could not be decoded, {error}")?;
+                match (error, error.size()) {
+                    (doc::DecodeError::TooLarge { .. }, _) | (_, None) => writeln!(w)?,
+                    (_, Some(size)) => writeln!(w, " (size {size})")?,
+                }
+
+                if let doc::DecodeError::Invalid { error, .. } = error {
+                    writeln!(self.ui.hint()?, "The decoder reported: {error}")?;
+                }
+
+                let mut w = self.ui.hint()?;
+                write!(w, "If the reference is corrupt, run ")?;
+                cwrite!(colored(w, Color::Cyan), "tt update")?;
+                writeln!(w, " to regenerate it")?;
+
+                eyre::bail!(OperationFailure);
+            }
+
             // TODO(tinger): Attach test id.
             if let Some(error) = error.downcast_ref::<ParseIdError>() {
                 match error {
@@ -232,6 +610,15 @@ impl Context<'_> {
                     ParseIdError::Empty => {
                         writeln!(self.ui.error()?, "A test identifier must not be empty")?;
                     }
+                    ParseIdError::InvalidParam => {
+                        writeln!(self.ui.error()?, "A test parameter must be of the form key=value, where the value contains only alphanumeric characters, hyphens, underscores, dots and plus signs")?;
+                    }
+                    ParseIdError::Reserved => {
+                        writeln!(self.ui.error()?, "A test identifier must not start with '{}', this prefix is reserved for built-in tests", Id::RESERVED_PREFIX)?;
+                    }
+                    ParseIdError::UnknownReserved(id) => {
+                        writeln!(self.ui.error()?, "Unknown built-in test '{id}'")?;
+                    }
                 }
 
                 eyre::bail!(OperationFailure);
@@ -243,6 +630,15 @@ impl Context<'_> {
                 eyre::bail!(OperationFailure);
             }
 
+            // TODO(tinger): Attach test id.
+            if let Some(error) = error.downcast_ref::<test::matrix::ParseMatrixError>() {
+                writeln!(
+                    self.ui.error()?,
+                    "Couldn't parse parameter matrix:\n{error}"
+                )?;
+                eyre::bail!(OperationFailure);
+            }
+
             if let Some(error) = error.downcast_ref::<ManifestError>() {
                 match error {
                     ManifestError::Parse(error) => {
@@ -250,7 +646,9 @@ impl Context<'_> {
                         eyre::bail!(OperationFailure);
                     }
                     ManifestError::Invalid(error) => {
-                        writeln!(self.ui.error()?, "Failed to validate manifest:\n{error}")?;
+                        let mut w = self.ui.error()?;
+                        writeln!(w, "Failed to validate manifest:")?;
+                        write_validation_error(&mut w, error)?;
                         eyre::bail!(OperationFailure);
                     }
                     _ => {}
@@ -264,7 +662,9 @@ impl Context<'_> {
                         eyre::bail!(OperationFailure);
                     }
                     ConfigError::Invalid(error) => {
-                        writeln!(self.ui.error()?, "Failed to validate config:\n{error}")?;
+                        let mut w = self.ui.error()?;
+                        writeln!(w, "Failed to validate config:")?;
+                        write_validation_error(&mut w, error)?;
                         eyre::bail!(OperationFailure);
                     }
                     _ => {}
@@ -275,6 +675,10 @@ impl Context<'_> {
                 match error {
                     tytanic_filter::Error::Parse(error) => {
                         writeln!(self.ui.error()?, "Couldn't parse test set:\n{error}")?;
+
+                        if let tytanic_filter::ast::Error::Regex(error) = error {
+                            writeln!(self.ui.hint()?, "{error}")?;
+                        }
                     }
                     tytanic_filter::Error::Eval(error) => {
                         writeln!(self.ui.error()?, "Couldn't evaluate test set:\n{error}")?;
@@ -307,3 +711,28 @@ impl Context<'_> {
         eyre::bail!(error);
     }
 }
+
+/// Returns the package directory containing the given project root, if it is
+/// in the package cache or a vendored namespace of the package data
+/// directory.
+///
+/// The `local` namespace of the data directory is not included, packages
+/// are commonly developed in it.
+fn package_dir(store: &PackageStore, root: &Path) -> Option<PathBuf> {
+    let canonicalize = |path: &Path| path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let root = canonicalize(root);
+
+    if let Some(cache) = store.cache_dir().map(canonicalize) {
+        if root.starts_with(&cache) {
+            return Some(cache);
+        }
+    }
+
+    let data = store.data_dir().map(canonicalize)?;
+    let namespace = root.strip_prefix(&data).ok()?.components().next()?;
+    if namespace.as_os_str() == "local" {
+        return None;
+    }
+
+    Some(data.join(namespace))
+}
@@ -0,0 +1,141 @@
+//! Running remote workers inside of containers.
+//!
+//! A single container is started per test run, it runs `tt worker serve` with
+//! the project mounted read-only and is removed once the container handle is
+//! dropped.
+
+use std::path::Path;
+use std::process::Command;
+use std::process::Stdio;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use color_eyre::eyre;
+use color_eyre::eyre::WrapErr;
+
+use crate::remote;
+
+/// The image used if no image is given, this is the image of the current
+/// release.
+pub static DEFAULT_IMAGE: &str = concat!(
+    "ghcr.io/typst-community/tytanic:v",
+    env!("CARGO_PKG_VERSION")
+);
+
+/// The path at which the project is mounted inside the container.
+pub const PROJECT_ROOT: &str = "/project";
+
+/// How long to wait for the worker in the container to start.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A running container with a worker inside of it.
+#[derive(Debug)]
+pub struct Container {
+    id: String,
+    addr: String,
+}
+
+impl Container {
+    /// Starts a container from the given image with the given project root
+    /// mounted read-only and waits until its worker is ready.
+    pub fn start(image: &str, root: &Path) -> eyre::Result<Self> {
+        let id = docker(&[
+            "run",
+            "--rm",
+            "--detach",
+            "--publish",
+            &format!("127.0.0.1::{}", remote::DEFAULT_PORT),
+            "--volume",
+            &format!("{}:{PROJECT_ROOT}:ro", root.display()),
+            image,
+            "worker",
+            "serve",
+            "--listen",
+            &format!("0.0.0.0:{}", remote::DEFAULT_PORT),
+            "--allow-root",
+            PROJECT_ROOT,
+        ])?;
+
+        // NOTE(tinger): Construct the handle first, such that the container is
+        // removed if it fails to start.
+        let mut this = Self {
+            id,
+            addr: String::new(),
+        };
+
+        this.addr = docker(&["port", &this.id, &format!("{}/tcp", remote::DEFAULT_PORT)])?
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+
+        tracing::debug!(id = %this.id, addr = %this.addr, "started container");
+
+        // NOTE(tinger): The published port accepts connections before the
+        // worker listens on it, so we wait for the worker to report instead.
+        let start = Instant::now();
+        loop {
+            let output = Command::new("docker")
+                .args(["logs", &this.id])
+                .output()
+                .wrap_err("couldn't read container logs")?;
+
+            if String::from_utf8_lossy(&output.stderr).contains("Listening on") {
+                break;
+            }
+
+            if start.elapsed() > STARTUP_TIMEOUT {
+                eyre::bail!(
+                    "worker in container didn't start within {}s:\n{}",
+                    STARTUP_TIMEOUT.as_secs(),
+                    String::from_utf8_lossy(&output.stderr).trim(),
+                );
+            }
+
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        Ok(this)
+    }
+
+    /// The address of the worker in this container.
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+}
+
+impl Drop for Container {
+    fn drop(&mut self) {
+        tracing::debug!(id = %self.id, "removing container");
+
+        let res = Command::new("docker")
+            .args(["rm", "--force", &self.id])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status();
+
+        if let Err(err) = res {
+            tracing::warn!(id = %self.id, ?err, "couldn't remove container");
+        }
+    }
+}
+
+/// Runs a docker command and returns its trimmed stdout.
+fn docker(args: &[&str]) -> eyre::Result<String> {
+    let output = Command::new("docker")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .wrap_err("couldn't run docker")?;
+
+    if !output.status.success() {
+        eyre::bail!(
+            "docker {} failed: {}",
+            args[0],
+            String::from_utf8_lossy(&output.stderr).trim(),
+        );
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
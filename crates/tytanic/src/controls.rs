@@ -0,0 +1,127 @@
+//! Keyboard controls for ongoing test runs.
+//!
+//! While the controls are active the terminal is switched into a
+//! non-canonical input mode, this means key presses are available immediately
+//! without waiting for a line break, but output processing is left untouched
+//! so the regular reporting is not affected.
+
+use std::io;
+use std::time::Duration;
+
+use crossterm::event;
+use crossterm::event::Event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEventKind;
+
+/// A control issued by the user during a test run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Control {
+    /// Toggle whether the run stops after the first failure.
+    ToggleFailFast,
+
+    /// Pause or resume the scheduling of new tests.
+    TogglePause,
+
+    /// Print an interim summary.
+    Summary,
+
+    /// Gracefully cancel the run after the current test.
+    Cancel,
+}
+
+impl Control {
+    /// The control for the given key.
+    pub fn from_key(key: KeyCode) -> Option<Self> {
+        Some(match key {
+            KeyCode::Char('f') => Self::ToggleFailFast,
+            KeyCode::Char('p') => Self::TogglePause,
+            KeyCode::Char('s') => Self::Summary,
+            KeyCode::Char('q') => Self::Cancel,
+            _ => return None,
+        })
+    }
+}
+
+/// Keyboard controls for a test run, restores the terminal input mode on
+/// drop.
+#[derive(Debug)]
+pub struct Controls {
+    #[cfg(unix)]
+    original: libc::termios,
+}
+
+impl Controls {
+    /// Enables the keyboard controls by switching the terminal into
+    /// non-canonical input mode.
+    pub fn enable() -> io::Result<Self> {
+        #[cfg(unix)]
+        {
+            // SAFETY: termios is a plain C struct and is fully initialized by
+            // tcgetattr before being read.
+            unsafe {
+                let mut original = std::mem::zeroed::<libc::termios>();
+                if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+
+                let mut raw = original;
+                raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+                raw.c_cc[libc::VMIN] = 1;
+                raw.c_cc[libc::VTIME] = 0;
+
+                if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+
+                Ok(Self { original })
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            // NOTE(tinger): On Windows raw mode only affects the console
+            // input mode, so output is not affected.
+            crossterm::terminal::enable_raw_mode()?;
+            Ok(Self {})
+        }
+    }
+
+    /// Returns the next control if one was issued, does not block.
+    pub fn poll(&self) -> io::Result<Option<Control>> {
+        self.wait(Duration::ZERO)
+    }
+
+    /// Returns the next control if one was issued within the given timeout.
+    pub fn wait(&self, timeout: Duration) -> io::Result<Option<Control>> {
+        while event::poll(timeout)? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+
+                if let Some(control) = Control::from_key(key.code) {
+                    return Ok(Some(control));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl Drop for Controls {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        {
+            // SAFETY: We restore the attributes retrieved in enable.
+            unsafe {
+                libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original);
+            }
+        }
+
+        #[cfg(not(unix))]
+        {
+            _ = crossterm::terminal::disable_raw_mode();
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! LCOV and Cobertura writers for the line coverage of project sources.
+//!
+//! Both formats contain one entry per instrumented file with its path relative
+//! to the project root, lines are reported as executed once or not at all, see
+//! [`tytanic_core::coverage`].
+
+use std::io;
+use std::io::Write;
+use std::path::Path;
+
+use chrono::Utc;
+use typst::syntax::FileId;
+use tytanic_core::coverage::FileReport;
+use tytanic_core::coverage::Report;
+
+use crate::markup::escape;
+
+/// The path of a file relative to the project root.
+pub fn file_path(id: FileId) -> String {
+    id.vpath()
+        .as_rootless_path()
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+/// Writes an LCOV tracefile for the given report.
+pub fn write_lcov<W: Write>(mut w: W, report: &Report) -> io::Result<()> {
+    for (id, file) in report.files() {
+        writeln!(w, "TN:")?;
+        writeln!(w, "SF:{}", file_path(*id))?;
+        for (line, hit) in file.line_hits() {
+            writeln!(w, "DA:{line},{}", u8::from(*hit))?;
+        }
+        writeln!(w, "LF:{}", file.lines())?;
+        writeln!(w, "LH:{}", file.hits())?;
+        writeln!(w, "end_of_record")?;
+    }
+
+    Ok(())
+}
+
+/// Writes a Cobertura XML report for the given report, the project root is
+/// written as the only source directory.
+pub fn write_cobertura<W: Write>(mut w: W, root: &Path, report: &Report) -> io::Result<()> {
+    let rate = report.ratio();
+
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        w,
+        r#"<coverage line-rate="{rate:.4}" branch-rate="0" lines-covered="{}" lines-valid="{}" branches-covered="0" branches-valid="0" complexity="0" version="{}" timestamp="{}">"#,
+        report.hits(),
+        report.lines(),
+        env!("CARGO_PKG_VERSION"),
+        Utc::now().timestamp_millis(),
+    )?;
+    writeln!(w, "  <sources>")?;
+    writeln!(
+        w,
+        "    <source>{}</source>",
+        escape(&root.to_string_lossy())
+    )?;
+    writeln!(w, "  </sources>")?;
+    writeln!(w, "  <packages>")?;
+    writeln!(
+        w,
+        r#"    <package name="." line-rate="{rate:.4}" branch-rate="0" complexity="0">"#
+    )?;
+    writeln!(w, "      <classes>")?;
+
+    for (id, file) in report.files() {
+        write_class(&mut w, &file_path(*id), file)?;
+    }
+
+    writeln!(w, "      </classes>")?;
+    writeln!(w, "    </package>")?;
+    writeln!(w, "  </packages>")?;
+    writeln!(w, "</coverage>")?;
+
+    Ok(())
+}
+
+/// Writes a single file as a Cobertura class.
+fn write_class<W: Write>(w: &mut W, path: &str, file: &FileReport) -> io::Result<()> {
+    let path = escape(path);
+
+    writeln!(
+        w,
+        r#"        <class name="{path}" filename="{path}" line-rate="{:.4}" branch-rate="0" complexity="0">"#,
+        file.ratio(),
+    )?;
+    writeln!(w, "          <methods/>")?;
+    writeln!(w, "          <lines>")?;
+    for (line, hit) in file.line_hits() {
+        writeln!(
+            w,
+            r#"            <line number="{line}" hits="{}"/>"#,
+            u8::from(*hit)
+        )?;
+    }
+    writeln!(w, "          </lines>")?;
+    writeln!(w, "        </class>")?;
+
+    Ok(())
+}
@@ -0,0 +1,240 @@
+//! Crash reports for panics and unexpected errors.
+//!
+//! A crash report contains the error or panic message, a backtrace, the
+//! command line, versions, the ID of the current test run and the last log
+//! lines. They are written into the `crash` directory inside the project's
+//! [cache directory] (`tests/.tytanic-cache/crash`), or into the temporary
+//! directory outside of projects, such that users can attach them to bug
+//! reports.
+//!
+//! The cache directory is used instead of a separate `tests/.tytanic`
+//! directory, it's the only directory owned by Tytanic within a project and is
+//! already ignored by the VCS, such that crash reports never end up in
+//! commits.
+//!
+//! [cache directory]: Project::cache_dir
+
+use std::backtrace::Backtrace;
+use std::collections::VecDeque;
+use std::env;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::panic;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+use chrono::Utc;
+use color_eyre::eyre;
+use once_cell::sync::Lazy;
+use tracing::field::Field;
+use tracing::field::Visit;
+use tracing::Event;
+use tracing::Subscriber;
+use tracing_subscriber::layer;
+use tracing_subscriber::Layer;
+use tytanic_core::cache;
+use tytanic_core::project::Project;
+use tytanic_core::TOOL_NAME;
+
+/// The name of the crash report directory within the cache directory.
+pub const CRASH_DIRECTORY: &str = "crash";
+
+/// The number of log lines included in a crash report.
+const LOG_LINES: usize = 100;
+
+/// The state needed to write a crash report at any point.
+#[derive(Debug, Default)]
+struct State {
+    project: Option<Project>,
+    run_id: Option<String>,
+    log: VecDeque<String>,
+}
+
+static STATE: Lazy<Mutex<State>> = Lazy::new(Default::default);
+
+fn with_state<T>(f: impl FnOnce(&mut State) -> T) -> T {
+    // NOTE(tinger): The state may be poisoned by the panic we're reporting.
+    f(&mut STATE.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// Sets the project into whose cache directory crash reports are written.
+pub fn set_project(project: &Project) {
+    with_state(|state| state.project = Some(project.clone()));
+}
+
+/// Sets the ID of the current test run.
+pub fn set_run_id<D: Display>(id: D) {
+    with_state(|state| state.run_id = Some(id.to_string()));
+}
+
+/// Installs a panic hook which writes a crash report before running the
+/// previously installed hook.
+pub fn install() {
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let backtrace = Backtrace::force_capture();
+        let path = write("Panic", &info.to_string(), Some(&backtrace));
+
+        previous(info);
+
+        // NOTE(tinger): The UI is not available here, panics may happen while
+        // it's locked.
+        match path {
+            Ok(path) => eprintln!("A crash report was written to {}", path.display()),
+            Err(err) => eprintln!("A crash report could not be written: {err}"),
+        }
+        eprintln!(
+            "Please consider reporting this at {}/issues/new?template=bug_report.md",
+            env!("CARGO_PKG_REPOSITORY"),
+        );
+    }));
+}
+
+/// The environment variable which triggers an internal error (`error`) or a
+/// panic (`panic`) at the start of a test run in debug builds.
+pub const DEBUG_CRASH_VAR: &str = "TYTANIC_DEBUG_CRASH";
+
+/// Triggers an internal error or panic if requested by [`DEBUG_CRASH_VAR`],
+/// this allows testing crash reports without a real bug.
+#[cfg(debug_assertions)]
+pub fn debug_trigger() -> eyre::Result<()> {
+    match env::var(DEBUG_CRASH_VAR).as_deref() {
+        Ok("error") => eyre::bail!("internal error requested by {DEBUG_CRASH_VAR}"),
+        Ok("panic") => panic!("panic requested by {DEBUG_CRASH_VAR}"),
+        _ => Ok(()),
+    }
+}
+
+/// Crashes can only be triggered in debug builds, this does nothing.
+#[cfg(not(debug_assertions))]
+pub fn debug_trigger() -> eyre::Result<()> {
+    Ok(())
+}
+
+/// Writes a crash report for an unexpected error and returns its path.
+pub fn write_error<E: Display>(error: &E) -> io::Result<PathBuf> {
+    // NOTE(tinger): The alternate display includes the whole error chain
+    // without the colors of the debug representation.
+    write("Error", &format!("{error:#}"), None)
+}
+
+/// Writes a crash report and returns its path.
+fn write(kind: &str, message: &str, backtrace: Option<&Backtrace>) -> io::Result<PathBuf> {
+    let now = Utc::now();
+
+    let (project, content) = with_state(|state| {
+        let mut content = String::new();
+
+        // NOTE(tinger): Writing into a string can't fail.
+        let _ = writeln!(content, "# {TOOL_NAME} crash report");
+        let _ = writeln!(content, "Time: {}", now.to_rfc3339());
+        let _ = writeln!(content, "Version: {}", env!("CARGO_PKG_VERSION"));
+        let _ = writeln!(content, "Typst Version: {}", env!("TYTANIC_TYPST_VERSION"));
+        let _ = writeln!(
+            content,
+            "Platform: {} ({})",
+            env::consts::OS,
+            env::consts::ARCH
+        );
+        let _ = writeln!(
+            content,
+            "Command line: {}",
+            env::args().collect::<Vec<_>>().join(" "),
+        );
+        let _ = writeln!(
+            content,
+            "Run ID: {}",
+            state.run_id.as_deref().unwrap_or("none"),
+        );
+
+        let _ = writeln!(content, "\n## {kind}\n{message}");
+
+        if let Some(backtrace) = backtrace {
+            let _ = writeln!(content, "\n## Backtrace\n{backtrace}");
+        }
+
+        let _ = writeln!(content, "\n## Log");
+        for line in &state.log {
+            let _ = writeln!(content, "{line}");
+        }
+
+        (state.project.clone(), content)
+    });
+
+    // NOTE(tinger): This is done without holding the lock, as it may emit log
+    // events itself.
+    let dir = match &project {
+        Some(project) => cache::create_dir(project)?.join(CRASH_DIRECTORY),
+        None => env::temp_dir().join(format!("{TOOL_NAME}-{CRASH_DIRECTORY}")),
+    };
+
+    tytanic_utils::fs::create_dir(&dir, true)?;
+    let path = dir.join(format!("{}.txt", now.format("%Y-%m-%dT%H-%M-%S%.3f")));
+    fs::write(&path, content)?;
+
+    Ok(path)
+}
+
+/// A tracing layer which keeps the last log lines for crash reports.
+pub struct LogLayer;
+
+impl<S: Subscriber> Layer<S> for LogLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: layer::Context<'_, S>) {
+        let meta = event.metadata();
+        let mut line = format!("{} {}:", meta.level(), meta.target());
+        event.record(&mut LineVisitor(&mut line));
+
+        with_state(|state| {
+            if state.log.len() == LOG_LINES {
+                state.log.pop_front();
+            }
+            state.log.push_back(line);
+        });
+    }
+}
+
+/// Appends the fields of an event to a log line.
+struct LineVisitor<'a>(&'a mut String);
+
+impl Visit for LineVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, " {value:?}");
+        } else {
+            let _ = write!(self.0, " {}={value:?}", field.name());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    #[test]
+    fn test_log_layer() {
+        let subscriber = tracing_subscriber::registry().with(LogLayer);
+        tracing::subscriber::with_default(subscriber, || {
+            for i in 0..=LOG_LINES {
+                tracing::info!(i, "line");
+            }
+        });
+
+        with_state(|state| {
+            assert_eq!(state.log.len(), LOG_LINES);
+            assert_eq!(
+                state.log.front().unwrap(),
+                "INFO tt::crash::tests: line i=1",
+            );
+            assert_eq!(
+                state.log.back().unwrap(),
+                &format!("INFO tt::crash::tests: line i={LOG_LINES}"),
+            );
+        });
+    }
+}
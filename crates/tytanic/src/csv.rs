@@ -0,0 +1,88 @@
+//! A CSV writer for per-test results of a test run.
+//!
+//! The report contains a header and one row per test which was not filtered,
+//! ordered by test id, such that it can be loaded into a spreadsheet as is.
+
+use std::borrow::Cow;
+use std::io;
+use std::io::Write;
+
+use tytanic_core::doc::compare::PageError;
+use tytanic_core::suite::Suite;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Stage;
+use tytanic_core::test::Test;
+
+use crate::json::stage_str;
+
+/// The columns of the report.
+const HEADER: [&str; 6] = ["id", "kind", "stage", "duration", "deviations", "pages"];
+
+/// Writes a CSV report for the given suite result.
+pub fn write<W: Write>(mut w: W, suite: &Suite, result: &SuiteResult) -> io::Result<()> {
+    writeln!(w, "{}", HEADER.join(","))?;
+
+    for (id, test) in result.results() {
+        if test.is_filtered() {
+            continue;
+        }
+
+        let kind = match suite.get(id) {
+            Some(Test::Unit(unit)) => unit.kind().as_str(),
+            Some(Test::Template(_)) => "template",
+            None => "",
+        };
+
+        let deviations = match test.stage() {
+            Stage::FailedComparison(error) => Some(
+                error
+                    .pages
+                    .iter()
+                    .map(|(_, error)| match error {
+                        PageError::SimpleDeviations { deviations } => *deviations,
+                        PageError::SampledDeviations { estimated, .. } => *estimated,
+                        PageError::Dimensions { .. }
+                        | PageError::Ssim { .. }
+                        | PageError::Text { .. }
+                        | PageError::Fonts { .. } => 0,
+                    })
+                    .sum::<usize>(),
+            ),
+            _ => None,
+        };
+
+        writeln!(
+            w,
+            "{},{},{},{:.3},{},{}",
+            escape(id.as_str()),
+            kind,
+            stage_str(test.stage()),
+            test.duration().as_secs_f64(),
+            deviations.map(|d| d.to_string()).unwrap_or_default(),
+            test.pages().map(|p| p.to_string()).unwrap_or_default(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Quotes a field if it contains a separator, quote or line break.
+fn escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a/b"), "a/b");
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("a\"b"), "\"a\"\"b\"");
+    }
+}
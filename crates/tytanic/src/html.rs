@@ -0,0 +1,236 @@
+//! A self-contained HTML report writer for test runs.
+//!
+//! The report contains the status, duration and diagnostics of each test, the
+//! output, reference and difference pages of failed tests are embedded as
+//! base64 encoded PNG images, such that the report can be viewed without the
+//! project.
+
+use std::io;
+use std::io::Write;
+
+use termcolor::NoColor;
+use typst::diag::SourceDiagnostic;
+use tytanic_core::project::Project;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Stage;
+use tytanic_core::test::TestResult;
+use tytanic_core::Id;
+use tytanic_utils::fmt::Term;
+
+use crate::json::stage_str;
+use crate::markup::escape;
+use crate::report::write_compare_error;
+use crate::ui::Indented;
+use crate::upload::read_pages;
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; }
+td, th { padding: 0.2em 0.8em; text-align: left; }
+.test { border-left: 0.3em solid #888; margin: 0.5em 0; padding: 0.3em 0.8em; }
+.test.pass { border-color: #2a2; }
+.test.fail { border-color: #d22; }
+.test.skip { border-color: #888; }
+.status { font-weight: bold; }
+.pass .status { color: #2a2; }
+.fail .status { color: #d22; }
+.duration { color: #888; }
+pre { background: #f4f4f4; padding: 0.5em; overflow-x: auto; }
+.pages { display: flex; flex-wrap: wrap; gap: 1em; }
+.pages figure { margin: 0; }
+.pages img { max-width: 20em; border: 1px solid #ccc; }
+"#;
+
+/// Writes an HTML report for the given suite result.
+pub fn write<W: Write>(
+    mut w: W,
+    name: &str,
+    project: &Project,
+    result: &SuiteResult,
+) -> io::Result<()> {
+    writeln!(w, "<!DOCTYPE html>")?;
+    writeln!(w, r#"<html lang="en">"#)?;
+    writeln!(w, "<head>")?;
+    writeln!(w, r#"<meta charset="utf-8">"#)?;
+    writeln!(w, "<title>Test report for {}</title>", escape(name))?;
+    writeln!(w, "<style>{STYLE}</style>")?;
+    writeln!(w, "</head>")?;
+    writeln!(w, "<body>")?;
+    writeln!(w, "<h1>Test report for {}</h1>", escape(name))?;
+
+    writeln!(w, "<table>")?;
+    writeln!(w, "<tr><th>Run</th><td>{}</td></tr>", result.id())?;
+    writeln!(
+        w,
+        "<tr><th>Tests</th><td>{} passed, {} failed, {} skipped, {} filtered</td></tr>",
+        result.passed(),
+        result.failed(),
+        result.skipped(),
+        result.filtered(),
+    )?;
+    writeln!(
+        w,
+        "<tr><th>Duration</th><td>{:.3}s</td></tr>",
+        result.duration().as_secs_f64(),
+    )?;
+    for (key, val) in result.meta() {
+        writeln!(
+            w,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            escape(key),
+            escape(val),
+        )?;
+    }
+    writeln!(w, "</table>")?;
+
+    for (id, test) in result.results() {
+        if test.is_filtered() {
+            continue;
+        }
+
+        let class = if test.is_fail() {
+            "fail"
+        } else if test.is_pass() {
+            "pass"
+        } else {
+            "skip"
+        };
+
+        writeln!(
+            w,
+            r#"<section class="test {class}" id="{}">"#,
+            escape(id.as_str())
+        )?;
+        writeln!(
+            w,
+            r#"<h3><span class="status">{}</span> {} <span class="duration">{:.3}s</span></h3>"#,
+            stage_str(test.stage()),
+            escape(id.as_str()),
+            test.duration().as_secs_f64(),
+        )?;
+
+        write_details(&mut w, test)?;
+
+        if test.is_fail() {
+            write_pages(&mut w, project, id)?;
+        }
+
+        writeln!(w, "</section>")?;
+    }
+
+    writeln!(w, "</body>")?;
+    writeln!(w, "</html>")?;
+
+    Ok(())
+}
+
+/// Writes the collapsible errors and warnings of a test.
+fn write_details<W: Write>(w: &mut W, test: &TestResult) -> io::Result<()> {
+    let errors = test.errors().unwrap_or_default();
+
+    if !errors.is_empty() {
+        writeln!(w, "<details open>")?;
+        writeln!(
+            w,
+            "<summary>{} {}</summary>",
+            errors.len(),
+            Term::simple("error").with(errors.len()),
+        )?;
+        write_diagnostics(w, errors)?;
+        writeln!(w, "</details>")?;
+    } else if let Some(message) = failure_message(test.stage()) {
+        writeln!(w, "<pre>{}</pre>", escape(&message))?;
+    }
+
+    if !test.warnings().is_empty() {
+        writeln!(w, "<details>")?;
+        writeln!(
+            w,
+            "<summary>{} {}</summary>",
+            test.warnings().len(),
+            Term::simple("warning").with(test.warnings().len()),
+        )?;
+        write_diagnostics(w, test.warnings())?;
+        writeln!(w, "</details>")?;
+    }
+
+    Ok(())
+}
+
+/// The message of a failure which is not a compilation failure.
+fn failure_message(stage: &Stage) -> Option<String> {
+    Some(match stage {
+        Stage::FailedComparison(error) => {
+            let mut buf = vec![];
+            _ = write_compare_error(&mut Indented::new(NoColor::new(&mut buf), 0), error);
+            String::from_utf8_lossy(&buf).trim_end().to_owned()
+        }
+        Stage::FailedRendering { error, .. } => error.to_string(),
+        Stage::TimedOut { timeout, .. } => format!("exceeded timeout of {timeout:?}"),
+        Stage::UnexpectedPass { reason } => match reason {
+            Some(reason) => format!("test passed, but was expected to fail: {reason}"),
+            None => "test passed, but was expected to fail".into(),
+        },
+        Stage::ExpectedFailure { reason, .. } => match reason {
+            Some(reason) => format!("failed as expected: {reason}"),
+            None => "failed as expected".into(),
+        },
+        _ => return None,
+    })
+}
+
+fn write_diagnostics<W: Write>(w: &mut W, diags: &[SourceDiagnostic]) -> io::Result<()> {
+    write!(w, "<pre>")?;
+    for diag in diags {
+        writeln!(w, "{}", escape(&diag.message))?;
+        for hint in &diag.hints {
+            writeln!(w, "  hint: {}", escape(hint))?;
+        }
+    }
+    writeln!(w, "</pre>")?;
+
+    Ok(())
+}
+
+/// Writes the output, reference and difference pages of a test as inline
+/// images.
+fn write_pages<W: Write>(w: &mut W, project: &Project, id: &Id) -> io::Result<()> {
+    let out = read_pages(&project.unit_test_out_dir(id))?;
+    let reference = read_pages(&project.unit_test_ref_dir(id))?;
+    let diff = read_pages(&project.unit_test_diff_dir(id))?;
+
+    let pages = out.len().max(reference.len()).max(diff.len());
+    if pages == 0 {
+        return Ok(());
+    }
+
+    writeln!(w, "<details open>")?;
+    writeln!(
+        w,
+        "<summary>{pages} {}</summary>",
+        Term::simple("page").with(pages),
+    )?;
+    for page in 0..pages {
+        writeln!(w, r#"<div class="pages">"#)?;
+        for (label, pages) in [
+            ("output", &out),
+            ("reference", &reference),
+            ("difference", &diff),
+        ] {
+            let Some(data) = pages.get(page) else {
+                continue;
+            };
+
+            writeln!(
+                w,
+                r#"<figure><img src="data:image/png;base64,{data}" alt="{label} page {}"><figcaption>{label} page {}</figcaption></figure>"#,
+                page + 1,
+                page + 1,
+            )?;
+        }
+        writeln!(w, "</div>")?;
+    }
+    writeln!(w, "</details>")?;
+
+    Ok(())
+}
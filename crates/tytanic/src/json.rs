@@ -1,31 +1,58 @@
 //! Common report PODs for stable JSON representation of internal entities.
 
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Duration;
 
+use ecow::EcoString;
+use serde::Deserialize;
 use serde::Serialize;
 use typst_syntax::package::PackageManifest;
 use typst_syntax::package::PackageVersion;
+use tytanic_core::doc::compare;
+use tytanic_core::doc::compare::Bounds;
+use tytanic_core::doc::compare::Heatmap;
+use tytanic_core::doc::compare::PageError;
+use tytanic_core::doc::compare::PageStats;
+use tytanic_core::doc::compare::Size;
 use tytanic_core::project::Project;
+use tytanic_core::suite::GroupResult;
 use tytanic_core::suite::Suite;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Stage;
 use tytanic_core::test::Test;
+use tytanic_core::test::TestResult;
 use tytanic_core::TemplateTest;
 use tytanic_core::UnitTest;
 
+use crate::cli::commands::SummaryBy;
+use crate::cli::deprecation::Deprecation;
+use crate::cli::deprecation::Kind;
+
 #[derive(Debug, Serialize)]
 pub struct ProjectJson<'m, 's> {
     pub package: Option<PackageJson<'m>>,
+    pub document: Option<DocumentJson<'m>>,
     pub vcs: Option<String>,
     pub tests: Vec<UnitTestJson<'s>>,
     pub template_test: Option<TemplateTestJson<'s>>,
 }
 
 impl<'m, 's> ProjectJson<'m, 's> {
-    pub fn new(project: &Project, manifest: Option<&'m PackageManifest>, suite: &'s Suite) -> Self {
+    pub fn new(
+        project: &'m Project,
+        manifest: Option<&'m PackageManifest>,
+        suite: &'s Suite,
+    ) -> Self {
         Self {
             package: manifest.map(|m| PackageJson {
                 name: &m.package.name,
                 version: &m.package.version,
             }),
+            document: project.document().map(|d| DocumentJson {
+                name: &d.document.name,
+                entrypoints: &d.document.entrypoints,
+            }),
             vcs: project.vcs().map(|vcs| vcs.to_string()),
             tests: suite
                 .unit_tests()
@@ -44,6 +71,12 @@ pub struct PackageJson<'p> {
     pub version: &'p PackageVersion,
 }
 
+#[derive(Debug, Serialize)]
+pub struct DocumentJson<'d> {
+    pub name: &'d str,
+    pub entrypoints: &'d [String],
+}
+
 #[derive(Debug, Serialize)]
 #[serde(tag = "type", content = "test")]
 pub enum TestJson<'t> {
@@ -68,7 +101,9 @@ pub struct UnitTestJson<'t> {
     pub id: &'t str,
     pub kind: &'static str,
     pub is_skip: bool,
+    pub tags: Vec<&'t str>,
     pub path: PathBuf,
+    pub notes: Option<String>,
 }
 
 impl<'t> UnitTestJson<'t> {
@@ -77,7 +112,9 @@ impl<'t> UnitTestJson<'t> {
             id: test.id().as_str(),
             kind: test.kind().as_str(),
             is_skip: test.is_skip(),
+            tags: test.tags().map(EcoString::as_str).collect(),
             path: project.unit_test_dir(test.id()),
+            notes: test.load_notes(project).ok().flatten(),
         }
     }
 }
@@ -110,15 +147,374 @@ pub struct FontJson<'f> {
     pub variants: Vec<FontVariantJson>,
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Serialize)]
+pub struct DeprecationJson {
+    pub kind: &'static str,
+    pub name: String,
+    pub replacement: String,
+    pub since: &'static str,
+    pub removal: &'static str,
+}
+
+impl DeprecationJson {
+    pub fn new(deprecation: &Deprecation) -> Self {
+        let prefix = match deprecation.kind {
+            Kind::Command => "",
+            Kind::Flag => "--",
+        };
+
+        Self {
+            kind: deprecation.kind.name(),
+            name: format!("{prefix}{}", deprecation.name),
+            replacement: format!("{prefix}{}", deprecation.replacement),
+            since: deprecation.since,
+            removal: deprecation.removal,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SuiteResultJson<'r> {
+    pub id: String,
+    pub total: usize,
+    pub filtered: usize,
+    pub passed: usize,
+    pub failed: FailedJson,
+    pub skipped: usize,
+    pub duration: DurationJson,
+    pub estimate: Option<DurationJson>,
+    pub meta: BTreeMap<&'r str, &'r str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub groups: Option<BTreeMap<&'r str, GroupResultJson>>,
+    pub tests: Vec<TestResultJson<'r>>,
+}
+
+impl<'r> SuiteResultJson<'r> {
+    pub fn new(result: &'r SuiteResult) -> Self {
+        Self {
+            id: result.id().to_string(),
+            total: result.total(),
+            filtered: result.filtered(),
+            passed: result.passed(),
+            failed: FailedJson::new(result),
+            skipped: result.skipped(),
+            duration: result.duration().into(),
+            estimate: result.estimate().map(Into::into),
+            meta: result
+                .meta()
+                .iter()
+                .map(|(key, val)| (key.as_str(), val.as_str()))
+                .collect(),
+            groups: None,
+            tests: result
+                .results()
+                .iter()
+                .filter(|(_, test)| !test.is_filtered())
+                .map(|(id, test)| TestResultJson::new(id.as_str(), test))
+                .collect(),
+        }
+    }
+}
+
+impl<'r> SuiteResultJson<'r> {
+    /// Creates a report which additionally contains the results grouped as
+    /// given, if any.
+    pub fn grouped(result: &'r SuiteResult, summary_by: Option<SummaryBy>) -> Self {
+        let mut this = Self::new(result);
+        this.groups = summary_by.map(|summary_by| match summary_by {
+            SummaryBy::Module => result
+                .modules()
+                .into_iter()
+                .map(|(name, group)| (name, GroupResultJson::new(&group)))
+                .collect(),
+        });
+        this
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupResultJson {
+    pub status: &'static str,
+    pub passed: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub duration: DurationJson,
+}
+
+impl GroupResultJson {
+    pub fn new(group: &GroupResult) -> Self {
+        Self {
+            status: group.status().name(),
+            passed: group.passed(),
+            failed: group.failed(),
+            skipped: group.skipped(),
+            duration: group.duration().into(),
+        }
+    }
+}
+
+/// The per-tile deviations of the pages of a test, this is written next to
+/// its difference pages.
+#[derive(Debug, Serialize)]
+pub struct HeatmapJson {
+    pub tile_size: u32,
+    pub pages: Vec<PageHeatmapJson>,
+}
+
+impl HeatmapJson {
+    pub fn new(tile_size: u32, heatmaps: &[Heatmap]) -> Self {
+        Self {
+            tile_size,
+            pages: heatmaps
+                .iter()
+                .enumerate()
+                .map(|(idx, heatmap)| PageHeatmapJson {
+                    page: idx + 1,
+                    columns: heatmap.columns(),
+                    rows: heatmap.rows(),
+                    deviations: heatmap.deviations(),
+                    tiles: heatmap.row_counts().map(<[_]>::to_vec).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageHeatmapJson {
+    pub page: usize,
+    pub columns: u32,
+    pub rows: u32,
+    pub deviations: usize,
+    pub tiles: Vec<Vec<usize>>,
+}
+
+/// A summary of a suite result without any run specific data like ids or
+/// durations, such that it is reproducible.
+#[derive(Debug, Serialize)]
+pub struct SummaryJson<'r> {
+    pub total: usize,
+    pub filtered: usize,
+    pub passed: usize,
+    pub failed: FailedJson,
+    pub skipped: usize,
+    pub failures: Vec<&'r str>,
+}
+
+impl<'r> SummaryJson<'r> {
+    pub fn new(result: &'r SuiteResult) -> Self {
+        Self {
+            total: result.total(),
+            filtered: result.filtered(),
+            passed: result.passed(),
+            failed: FailedJson::new(result),
+            skipped: result.skipped(),
+            failures: result
+                .results()
+                .iter()
+                .filter(|(_, test)| test.is_fail())
+                .map(|(id, _)| id.as_str())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestResultJson<'r> {
+    pub id: &'r str,
+    pub stage: &'static str,
+    pub warnings: usize,
+    pub duration: DurationJson,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<ComparisonJson>,
+}
+
+impl<'r> TestResultJson<'r> {
+    pub fn new(id: &'r str, result: &TestResult) -> Self {
+        Self {
+            id,
+            stage: stage_str(result.stage()),
+            warnings: result.warnings().len(),
+            duration: result.duration().into(),
+            comparison: match result.stage() {
+                Stage::FailedComparison(error) => Some(ComparisonJson::new(error)),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// The details of a failed comparison, pages are 1-based.
+#[derive(Debug, Serialize)]
+pub struct ComparisonJson {
+    pub output: usize,
+    pub reference: usize,
+    pub pages: Vec<PageComparisonJson>,
+}
+
+impl ComparisonJson {
+    pub fn new(error: &compare::Error) -> Self {
+        Self {
+            output: error.output,
+            reference: error.reference,
+            pages: error
+                .pages
+                .iter()
+                .map(|(idx, page_error)| {
+                    let stats = error
+                        .stats
+                        .iter()
+                        .find(|(page, _)| page == idx)
+                        .map(|(_, stats)| stats);
+
+                    PageComparisonJson::new(idx + 1, page_error, stats)
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageComparisonJson {
+    pub page: usize,
+    pub error: String,
+    pub output: Option<SizeJson>,
+    pub reference: Option<SizeJson>,
+    pub deviations: Option<usize>,
+    pub max_delta: Option<u8>,
+    pub bounds: Option<BoundsJson>,
+}
+
+impl PageComparisonJson {
+    pub fn new(page: usize, error: &PageError, stats: Option<&PageStats>) -> Self {
+        let deviations = stats.and_then(|stats| stats.deviations.as_ref());
+
+        Self {
+            page,
+            error: error.to_string(),
+            output: stats.map(|stats| stats.output.into()),
+            reference: stats.map(|stats| stats.reference.into()),
+            deviations: deviations.map(|deviations| deviations.count),
+            max_delta: deviations.map(|deviations| deviations.max_delta),
+            bounds: deviations
+                .and_then(|deviations| deviations.bounds)
+                .map(Into::into),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SizeJson {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl From<Size> for SizeJson {
+    fn from(value: Size) -> Self {
+        Self {
+            width: value.width,
+            height: value.height,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BoundsJson {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl From<Bounds> for BoundsJson {
+    fn from(value: Bounds) -> Self {
+        Self {
+            x: value.x,
+            y: value.y,
+            width: value.width,
+            height: value.height,
+        }
+    }
+}
+
+/// The stable name of a test stage.
+pub fn stage_str(stage: &Stage) -> &'static str {
+    match stage {
+        Stage::Skipped => "skipped",
+        Stage::Filtered => "filtered",
+        Stage::FailedCompilation {
+            reference: false, ..
+        } => "failed-compilation",
+        Stage::FailedCompilation {
+            reference: true, ..
+        } => "failed-reference-compilation",
+        Stage::FailedComparison(_) => "failed-comparison",
+        Stage::FailedRendering {
+            reference: false, ..
+        } => "failed-rendering",
+        Stage::FailedRendering {
+            reference: true, ..
+        } => "failed-reference-rendering",
+        Stage::PassedCompilation => "passed-compilation",
+        Stage::PassedComparison => "passed-comparison",
+        Stage::Updated { .. } => "updated",
+        Stage::Cancelled => "cancelled",
+        Stage::TimedOut {
+            reference: false, ..
+        } => "timed-out",
+        Stage::TimedOut {
+            reference: true, ..
+        } => "timed-out-reference",
+        Stage::ExpectedFailure { .. } => "expected-failure",
+        Stage::UnexpectedPass { .. } => "unexpected-pass",
+    }
+}
+
+#[derive(Debug, Serialize)]
 pub struct FailedJson {
     pub compilation: usize,
     pub comparison: usize,
     pub otherwise: usize,
 }
 
-#[derive(Serialize)]
+impl FailedJson {
+    pub fn new(result: &SuiteResult) -> Self {
+        let mut failed = Self {
+            compilation: 0,
+            comparison: 0,
+            otherwise: 0,
+        };
+
+        for test in result.results().values() {
+            match test.stage() {
+                Stage::FailedCompilation { .. } => failed.compilation += 1,
+                Stage::FailedComparison(_) => failed.comparison += 1,
+                _ => {}
+            }
+        }
+
+        failed.otherwise = result.failed() - failed.compilation - failed.comparison;
+        failed
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DurationJson {
     pub seconds: u64,
     pub nanoseconds: u32,
 }
+
+impl From<&DurationJson> for Duration {
+    fn from(value: &DurationJson) -> Self {
+        Duration::new(value.seconds, value.nanoseconds)
+    }
+}
+
+impl From<Duration> for DurationJson {
+    fn from(value: Duration) -> Self {
+        Self {
+            seconds: value.as_secs(),
+            nanoseconds: value.subsec_nanos(),
+        }
+    }
+}
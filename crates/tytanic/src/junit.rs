@@ -0,0 +1,181 @@
+//! A minimal jUnit XML writer for test run reports.
+//!
+//! This follows the de-facto schema understood by most CI providers, the
+//! suite is written as a single `<testsuite>` within `<testsuites>`, unit
+//! tests are grouped by their module using the `classname` attribute.
+
+use std::fmt::Write as _;
+use std::io;
+use std::io::Write;
+
+use tytanic_core::doc::compare;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Stage;
+
+use crate::markup::escape;
+
+/// Writes a jUnit XML report for the given suite result.
+pub fn write<W: Write>(mut w: W, name: &str, result: &SuiteResult) -> io::Result<()> {
+    let time = result.duration().as_secs_f64();
+    let tests = result.expected();
+    let failures = result.failed();
+    let skipped = result.skipped();
+
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        w,
+        r#"<testsuites name="{}" tests="{tests}" failures="{failures}" skipped="{skipped}" time="{time:.3}">"#,
+        escape(name),
+    )?;
+    writeln!(
+        w,
+        r#"  <testsuite name="{}" id="{}" tests="{tests}" failures="{failures}" skipped="{skipped}" time="{time:.3}">"#,
+        escape(name),
+        result.id(),
+    )?;
+
+    if !result.meta().is_empty() {
+        writeln!(w, "    <properties>")?;
+        for (key, val) in result.meta() {
+            writeln!(
+                w,
+                r#"      <property name="{}" value="{}"/>"#,
+                escape(key),
+                escape(val),
+            )?;
+        }
+        writeln!(w, "    </properties>")?;
+    }
+
+    for (id, test) in result.results() {
+        if test.is_filtered() {
+            continue;
+        }
+
+        write!(
+            w,
+            r#"    <testcase name="{}" classname="{}" time="{:.3}""#,
+            escape(id.as_str()),
+            escape(Some(id.module()).filter(|m| !m.is_empty()).unwrap_or(name)),
+            test.duration().as_secs_f64(),
+        )?;
+
+        match test.stage() {
+            Stage::Skipped | Stage::Cancelled => {
+                writeln!(w, ">")?;
+                writeln!(w, "      <skipped/>")?;
+            }
+            Stage::FailedCompilation { error, reference } => {
+                writeln!(w, ">")?;
+                let message = if *reference {
+                    "reference compilation failed"
+                } else {
+                    "compilation failed"
+                };
+
+                let mut body = String::new();
+                for diag in &error.0 {
+                    _ = writeln!(body, "{}", diag.message);
+                }
+
+                writeln!(
+                    w,
+                    r#"      <failure message="{message}" type="compilation">{}</failure>"#,
+                    escape(&body),
+                )?;
+            }
+            Stage::FailedComparison(error) => {
+                writeln!(w, ">")?;
+                write_comparison_properties(&mut w, error)?;
+                writeln!(
+                    w,
+                    r#"      <failure message="comparison failed" type="comparison">{}</failure>"#,
+                    escape(&error.to_string()),
+                )?;
+            }
+            Stage::FailedRendering { error, reference } => {
+                writeln!(w, ">")?;
+                let message = if *reference {
+                    "reference document too large"
+                } else {
+                    "document too large"
+                };
+
+                writeln!(
+                    w,
+                    r#"      <failure message="{message}" type="rendering">{}</failure>"#,
+                    escape(&error.to_string()),
+                )?;
+            }
+            Stage::TimedOut { timeout, reference } => {
+                writeln!(w, ">")?;
+                let message = if *reference {
+                    "reference compilation timed out"
+                } else {
+                    "compilation timed out"
+                };
+
+                writeln!(
+                    w,
+                    r#"      <failure message="{message}" type="timeout">{}</failure>"#,
+                    escape(&format!("exceeded timeout of {timeout:?}")),
+                )?;
+            }
+            Stage::UnexpectedPass { reason } => {
+                writeln!(w, ">")?;
+                writeln!(
+                    w,
+                    r#"      <failure message="test passed unexpectedly" type="unexpected-pass">{}</failure>"#,
+                    escape(reason.as_deref().unwrap_or_default()),
+                )?;
+            }
+            _ => {
+                writeln!(w, "/>")?;
+                continue;
+            }
+        }
+
+        writeln!(w, "    </testcase>")?;
+    }
+
+    writeln!(w, "  </testsuite>")?;
+    writeln!(w, "</testsuites>")?;
+
+    Ok(())
+}
+
+/// Writes the statistics of the failing pages of a comparison as test case
+/// properties, like `page.1.deviations`, pages are 1-based.
+fn write_comparison_properties<W: Write>(mut w: W, error: &compare::Error) -> io::Result<()> {
+    if error.stats.is_empty() {
+        return Ok(());
+    }
+
+    writeln!(w, "      <properties>")?;
+    for (idx, stats) in &error.stats {
+        let page = idx + 1;
+        let mut properties = vec![
+            ("output-size", stats.output.to_string()),
+            ("reference-size", stats.reference.to_string()),
+        ];
+
+        if let Some(deviations) = &stats.deviations {
+            properties.push(("deviations", deviations.count.to_string()));
+            properties.push(("max-delta", deviations.max_delta.to_string()));
+            if let Some(bounds) = deviations.bounds {
+                properties.push(("bounds", bounds.to_string()));
+            }
+        }
+
+        for (key, val) in properties {
+            writeln!(
+                w,
+                r#"        <property name="page.{page}.{key}" value="{}"/>"#,
+                escape(&val),
+            )?;
+        }
+    }
+    writeln!(w, "      </properties>")?;
+
+    Ok(())
+}
@@ -1,6 +1,21 @@
+use std::fs;
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use color_eyre::eyre;
+#[cfg(not(feature = "rustls"))]
+use native_tls::Certificate;
+#[cfg(not(feature = "rustls"))]
+use native_tls::TlsConnector;
+#[cfg(feature = "rustls")]
+use rustls::pki_types::pem::PemObject;
+#[cfg(feature = "rustls")]
+use rustls::pki_types::CertificateDer;
+#[cfg(feature = "rustls")]
+use rustls::ClientConfig;
+#[cfg(feature = "rustls")]
+use rustls::RootCertStore;
+use typst::text::FontBook;
 use typst_kit::download::Downloader;
 use typst_kit::fonts::FontSearcher;
 use typst_kit::fonts::Fonts;
@@ -10,41 +25,148 @@ use crate::cli::commands::CompileOptions;
 use crate::cli::commands::FontOptions;
 use crate::cli::commands::PackageOptions;
 use crate::cli::commands::Switch;
+use crate::proxy;
+use crate::registry::PackageStore;
 use crate::world::SystemWorld;
 
-#[tracing::instrument(skip(font_options, package_options, compile_options))]
+#[tracing::instrument(skip(font_options, package_store, compile_options))]
 pub fn world(
     project_root: PathBuf,
     font_options: &FontOptions,
-    package_options: &PackageOptions,
+    package_store: PackageStore,
     compile_options: &CompileOptions,
 ) -> eyre::Result<SystemWorld> {
     let world = SystemWorld::new(
         project_root,
         fonts_from_args(font_options),
-        package_storage_from_args(package_options),
+        package_store,
         compile_options.timestamp,
     )?;
 
     Ok(world)
 }
 
+/// Creates a world without any fonts, this is used when documents are compiled
+/// elsewhere and the world is only needed to report diagnostics.
+#[tracing::instrument(skip(package_store, compile_options))]
+pub fn world_without_fonts(
+    project_root: PathBuf,
+    package_store: PackageStore,
+    compile_options: &CompileOptions,
+) -> eyre::Result<SystemWorld> {
+    let fonts = Fonts {
+        book: FontBook::new(),
+        fonts: vec![],
+    };
+
+    Ok(SystemWorld::new(
+        project_root,
+        fonts,
+        package_store,
+        compile_options.timestamp,
+    )?)
+}
+
+fn user_agent() -> String {
+    format!("{}/{}", tytanic_core::TOOL_NAME, env!("CARGO_PKG_VERSION"))
+}
+
+// NOTE(tinger): The downloader is only passed to the package storage of
+// typst-kit, which requires one, packages are downloaded by our package store
+// using the agent below.
 #[tracing::instrument]
 pub fn downloader_from_args(args: &PackageOptions) -> Downloader {
-    let agent = format!("{}/{}", tytanic_core::TOOL_NAME, env!("CARGO_PKG_VERSION"));
-
     match args.certificate.clone() {
-        Some(path) => Downloader::with_path(agent, path),
-        None => Downloader::new(agent),
+        Some(path) => Downloader::with_path(user_agent(), path),
+        None => Downloader::new(user_agent()),
+    }
+}
+
+/// Creates an HTTP agent for requests to the given URL, respecting the
+/// configured proxy and custom certificates.
+#[tracing::instrument]
+pub fn agent_from_args(args: &PackageOptions, url: &str) -> eyre::Result<ureq::Agent> {
+    let mut builder = ureq::AgentBuilder::new().user_agent(&user_agent());
+
+    if let Some(proxy) = proxy::for_url(args, url)? {
+        builder = builder.proxy(proxy.to_ureq()?);
+
+        if url.starts_with("http:") {
+            if let Some(authorization) = proxy::Authorization::new(&proxy) {
+                builder = builder.middleware(authorization);
+            }
+        }
+    }
+
+    if let Some(timeout) = args.download_timeout {
+        builder = builder.timeout(timeout);
+    }
+
+    Ok(with_tls(builder, args)?.build())
+}
+
+/// Configures the TLS backend of an HTTP agent, adding the custom certificate
+/// if one is configured.
+///
+/// This uses rustls with the Mozilla root certificates if the `rustls` feature
+/// is enabled and native-tls with the system root certificates otherwise.
+#[cfg(feature = "rustls")]
+fn with_tls(
+    builder: ureq::AgentBuilder,
+    args: &PackageOptions,
+) -> eyre::Result<ureq::AgentBuilder> {
+    let mut roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+
+    if let Some(path) = &args.certificate {
+        let pem = fs::read(path)?;
+        let mut certificates = CertificateDer::pem_slice_iter(&pem).peekable();
+        if certificates.peek().is_none() {
+            eyre::bail!("no certificate found in {path:?}");
+        }
+
+        for certificate in certificates {
+            roots.add(certificate?)?;
+        }
+    }
+
+    let config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+    Ok(builder.tls_config(Arc::new(config)))
+}
+
+/// Configures the TLS backend of an HTTP agent, adding the custom certificate
+/// if one is configured.
+///
+/// This uses rustls with the Mozilla root certificates if the `rustls` feature
+/// is enabled and native-tls with the system root certificates otherwise.
+#[cfg(not(feature = "rustls"))]
+fn with_tls(
+    builder: ureq::AgentBuilder,
+    args: &PackageOptions,
+) -> eyre::Result<ureq::AgentBuilder> {
+    let mut tls = TlsConnector::builder();
+    if let Some(path) = &args.certificate {
+        tls.add_root_certificate(Certificate::from_pem(&fs::read(path)?)?);
     }
+
+    Ok(builder.tls_connector(Arc::new(tls.build()?)))
 }
 
 #[tracing::instrument]
-pub fn package_storage_from_args(args: &PackageOptions) -> PackageStorage {
-    PackageStorage::new(
-        args.package_cache_path.clone(),
-        args.package_path.clone(),
-        downloader_from_args(args),
+pub fn package_store_from_args(args: &PackageOptions) -> PackageStore {
+    PackageStore::new(
+        PackageStorage::new(
+            args.package_cache_path.clone(),
+            args.package_path.clone(),
+            downloader_from_args(args),
+        ),
+        args.clone(),
     )
 }
 
@@ -61,3 +183,53 @@ pub fn fonts_from_args(args: &FontOptions) -> Fonts {
     tracing::debug!(fonts = ?fonts.fonts.len(), "collected fonts");
     fonts
 }
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use tytanic_utils::fs::TempTestEnv;
+
+    use super::*;
+
+    /// A self-signed certificate.
+    const CERTIFICATE: &str = "\
+-----BEGIN CERTIFICATE-----
+MIIBhTCCASugAwIBAgIUJvs1P3y0iFXYSMg97FXOf+naw+4wCgYIKoZIzj0EAwIw
+FzEVMBMGA1UEAwwMdHl0YW5pYyB0ZXN0MCAXDTI2MTAxNzAxNTkyM1oYDzIxMjYw
+OTIzMDE1OTIzWjAXMRUwEwYDVQQDDAx0eXRhbmljIHRlc3QwWTATBgcqhkjOPQIB
+BggqhkjOPQMBBwNCAAQUzEtOlQEhKMdGxk+LvzyJzGWtmO/ym6rjhuZUXGRRwF06
+KVrFW7jHTGtNZw+cox3B83sSdwzchkROGaIxoB/0o1MwUTAdBgNVHQ4EFgQU7cyF
+BbUdxLey3qct7xt8N8b2rbUwHwYDVR0jBBgwFoAU7cyFBbUdxLey3qct7xt8N8b2
+rbUwDwYDVR0TAQH/BAUwAwEB/zAKBggqhkjOPQQDAgNIADBFAiEAksXfwvvyIgQw
+4drmUeOhs5SIduZBU2bxz6RtZ5KSvs4CIECUt4CUtL74OKlKFtJBxhmvx3BNLXW/
+n4f/aq8c1f+T
+-----END CERTIFICATE-----
+";
+
+    #[derive(Parser)]
+    struct Args {
+        #[command(flatten)]
+        package: PackageOptions,
+    }
+
+    #[test]
+    fn test_agent_from_args_certificate() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file("valid.pem", CERTIFICATE)
+                    .setup_file("invalid.pem", "not a certificate")
+            },
+            |root| {
+                let agent = |name: &str| {
+                    let path = root.join(name);
+                    let args = Args::parse_from(["tt", "--certificate", path.to_str().unwrap()]);
+                    agent_from_args(&args.package, "https://packages.typst.org")
+                };
+
+                assert!(agent("valid.pem").is_ok());
+                assert!(agent("invalid.pem").is_err());
+                assert!(agent("missing.pem").is_err());
+            },
+        );
+    }
+}
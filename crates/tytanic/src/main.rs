@@ -4,33 +4,55 @@ use std::env;
 use std::io;
 use std::io::Write;
 use std::process::ExitCode;
-use std::sync::atomic::Ordering;
 
+use clap::CommandFactory;
 use clap::Parser;
+use clap_complete::CompleteEnv;
 use codespan_reporting::term;
 use color_eyre::eyre;
+use color_eyre::eyre::WrapErr;
 use once_cell::sync::Lazy;
-use termcolor::Color;
 use termcolor::StandardStream;
 use termcolor::WriteColor;
 use tracing::level_filters::LevelFilter;
 use tracing_subscriber::filter::Targets;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
 use tracing_tree::HierarchicalLayer;
+use tytanic_core::config::SystemConfig;
+use tytanic_core::hook;
 
 use crate::cli::commands::CliArguments;
+use crate::cli::deprecation;
 use crate::cli::Context;
 use crate::cli::OperationFailure;
+use crate::cli::OutputError;
 use crate::cli::TestFailure;
 use crate::ui::Ui;
 
 mod cli;
+mod container;
+mod controls;
+mod coverage;
+mod crash;
+mod csv;
+mod html;
 mod json;
+mod junit;
 mod kit;
+mod markup;
+mod package;
+mod progress;
+mod proxy;
+mod registry;
+mod remote;
 mod report;
 mod runner;
+#[cfg(feature = "tui")]
+mod tui;
 mod ui;
+mod upload;
 mod world;
 
 /// The default optimization options to use.
@@ -48,11 +70,20 @@ fn main() -> ExitCode {
 }
 
 fn main_impl() -> eyre::Result<ExitCode> {
-    let args = CliArguments::parse();
+    // NOTE(tinger): This exits if we were called by a completion script.
+    CompleteEnv::with_factory(CliArguments::command)
+        .var(cli::complete::COMPLETE_VAR)
+        .complete();
+
+    let mut args = CliArguments::parse();
 
     color_eyre::install()?;
+    crash::install();
 
     let cc = match args.output.color {
+        // NOTE(tinger): Many CI providers emulate a terminal, but don't
+        // render colors in their logs.
+        clap::ColorChoice::Auto if args.output.ci => termcolor::ColorChoice::Never,
         clap::ColorChoice::Auto => termcolor::ColorChoice::Auto,
         clap::ColorChoice::Always => termcolor::ColorChoice::Always,
         clap::ColorChoice::Never => termcolor::ColorChoice::Never,
@@ -66,39 +97,14 @@ fn main_impl() -> eyre::Result<ExitCode> {
             tab_width: 2,
             ..Default::default()
         },
-    );
-
-    // Emit deprecation warning for aliases
-    if let Some(arg) = env::args().skip(1).find(|arg| !arg.starts_with('-')) {
-        match &arg[..] {
-            "remove" | "rm" => {
-                let mut w = ui.warn()?;
-                write!(w, "Sub command alias ")?;
-                cwrite!(colored(w, Color::Cyan), "remove")?;
-                write!(w, "|")?;
-                cwrite!(colored(w, Color::Cyan), "rm")?;
-                writeln!(w, " is deprecated")?;
-                drop(w);
-
-                let mut w = ui.hint()?;
-                write!(w, "Use ")?;
-                cwrite!(colored(w, Color::Cyan), "delete")?;
-                writeln!(w, " instead")?;
-            }
-            "add" => {
-                let mut w = ui.warn()?;
-                write!(w, "Sub command alias ")?;
-                cwrite!(colored(w, Color::Cyan), "add")?;
-                writeln!(w, " is deprecated")?;
-                drop(w);
-
-                let mut w = ui.hint()?;
-                write!(w, "Use ")?;
-                cwrite!(colored(w, Color::Cyan), "new")?;
-                writeln!(w, " instead")?;
-            }
-            _ => {}
-        }
+    )
+    .with_interactive(!args.output.ci);
+
+    let deprecated = deprecation::used(&env::args_os().skip(1).collect::<Vec<_>>());
+    deprecation::report(&ui, &deprecated, args.deny_deprecated)?;
+    if args.deny_deprecated && !deprecated.is_empty() {
+        ui.flush()?;
+        return Ok(ExitCode::from(cli::EXIT_OPERATION_FAILURE));
     }
 
     // This is a hack, termcolor does not expose any way for us to easily reuse
@@ -112,23 +118,30 @@ fn main_impl() -> eyre::Result<ExitCode> {
             // through termcolor::StandardStream.
             HierarchicalLayer::new(4)
                 .with_targets(true)
-                .with_ansi(tracing_ansi),
+                .with_ansi(tracing_ansi)
+                .with_filter(Targets::new().with_target(
+                    tytanic_core::TOOL_NAME,
+                    match args.output.verbose {
+                        0 => LevelFilter::OFF,
+                        1 => LevelFilter::ERROR,
+                        2 => LevelFilter::WARN,
+                        3 => LevelFilter::INFO,
+                        4 => LevelFilter::DEBUG,
+                        5.. => LevelFilter::TRACE,
+                    },
+                )),
+        )
+        // NOTE(tinger): The last log lines are always kept for crash reports,
+        // regardless of the verbosity.
+        .with(
+            crash::LogLayer.with_filter(
+                Targets::new().with_target(tytanic_core::TOOL_NAME, LevelFilter::DEBUG),
+            ),
         )
-        .with(Targets::new().with_target(
-            tytanic_core::TOOL_NAME,
-            match args.output.verbose {
-                0 => LevelFilter::OFF,
-                1 => LevelFilter::ERROR,
-                2 => LevelFilter::WARN,
-                3 => LevelFilter::INFO,
-                4 => LevelFilter::DEBUG,
-                5.. => LevelFilter::TRACE,
-            },
-        ))
         .init();
 
     if let Err(err) = ctrlc::set_handler(|| {
-        cli::CANCELLED.store(true, Ordering::SeqCst);
+        cli::CANCELLED.cancel();
     }) {
         writeln!(ui.error()?, "couldn't register ctrl-c handler:\n{err}")?;
         writeln!(
@@ -137,6 +150,10 @@ fn main_impl() -> eyre::Result<ExitCode> {
         )?;
     }
 
+    if let Some(config) = SystemConfig::collect_user().wrap_err("couldn't read user config")? {
+        args.package.apply_config(&config)?;
+    }
+
     if let Some(jobs) = args.jobs {
         rayon::ThreadPoolBuilder::new()
             .num_threads(jobs)
@@ -159,6 +176,19 @@ fn main_impl() -> eyre::Result<ExitCode> {
                 if let Some(OperationFailure) = cause.downcast_ref() {
                     break 'err cli::EXIT_OPERATION_FAILURE;
                 }
+
+                // NOTE(tinger): Failing hooks are user errors, not bugs.
+                if let Some(err) = cause.downcast_ref::<hook::Error>() {
+                    writeln!(ctx.ui.error()?, "{err}")?;
+                    break 'err cli::EXIT_OPERATION_FAILURE;
+                }
+
+                // NOTE(tinger): Missing directories or permissions of user
+                // supplied output paths are user errors too.
+                if let Some(err) = cause.downcast_ref::<OutputError>() {
+                    writeln!(ctx.ui.error()?, "{err}")?;
+                    break 'err cli::EXIT_OPERATION_FAILURE;
+                }
             }
 
             // FIXME(tinger): https://github.com/serde-rs/json/issues/1169
@@ -183,6 +213,11 @@ fn main_impl() -> eyre::Result<ExitCode> {
                 std::env!("CARGO_PKG_REPOSITORY")
             )?;
 
+            match crash::write_error(&err) {
+                Ok(path) => writeln!(w, "Attach the crash report at {}", path.display())?,
+                Err(err) => writeln!(w, "A crash report could not be written: {err}")?,
+            }
+
             cli::EXIT_OPERATION_FAILURE
         }
     };
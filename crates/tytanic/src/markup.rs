@@ -0,0 +1,35 @@
+//! Helpers shared by the XML and HTML report writers.
+
+/// Escapes the given string for use in XML or HTML attributes and text.
+///
+/// Newlines are escaped as character references, such that they are kept in
+/// XML attribute values.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a/b"), "a/b");
+        assert_eq!(escape("<a href=\"b\">"), "&lt;a href=&quot;b&quot;&gt;");
+        assert_eq!(escape("a & 'b'\nc"), "a &amp; &apos;b&apos;&#10;c");
+    }
+}
@@ -0,0 +1,44 @@
+//! Packing a project into a package bundle.
+//!
+//! This is used to test a package as it would be installed from a package
+//! registry, rather than through the files in the project.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use tytanic_core::project::Project;
+
+/// Copies the files of the project which are not excluded by the manifest
+/// into the given package directory, returns the number of copied files.
+///
+/// Hidden files and directories, such as VCS directories, are skipped.
+pub fn pack(project: &Project, dir: &Path) -> io::Result<usize> {
+    pack_dir(project, Path::new(""), dir)
+}
+
+fn pack_dir(project: &Project, rel: &Path, dir: &Path) -> io::Result<usize> {
+    tytanic_utils::fs::create_dir(dir.join(rel), true)?;
+
+    let mut len = 0;
+    for entry in fs::read_dir(project.root().join(rel))? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+
+        let rel = rel.join(entry.file_name());
+        if project.is_excluded(&rel) {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            len += pack_dir(project, &rel, dir)?;
+        } else {
+            fs::copy(entry.path(), dir.join(&rel))?;
+            len += 1;
+        }
+    }
+
+    Ok(len)
+}
@@ -0,0 +1,238 @@
+//! Machine readable progress events written as newline delimited JSON.
+
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::mem::ManuallyDrop;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use typst::diag::Severity;
+use typst::diag::SourceDiagnostic;
+use typst::World;
+use typst::WorldExt;
+use tytanic_core::project::Project;
+use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Id;
+use tytanic_core::test::Stage;
+use tytanic_core::test::Test;
+use tytanic_core::test::TestResult;
+
+use crate::json::DurationJson;
+use crate::json::TestResultJson;
+use crate::world::SystemWorld;
+
+/// A single progress event.
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum ProgressEvent<'r> {
+    /// A test run was started.
+    Start {
+        id: String,
+        total: usize,
+        filtered: usize,
+        /// The estimated duration of the run based on previous runs.
+        estimate: Option<DurationJson>,
+    },
+
+    /// A test was started.
+    TestStart { id: &'r str },
+
+    /// A test has finished.
+    Test {
+        /// The number of tests which have finished so far, including this one.
+        done: usize,
+        /// The number of tests which will be run.
+        total: usize,
+        result: TestResultJson<'r>,
+        /// The errors and warnings of the test.
+        diagnostics: Vec<DiagnosticJson>,
+        /// The page directories of a failed comparison.
+        pages: Option<PagesJson>,
+    },
+
+    /// A test run has ended.
+    End {
+        id: String,
+        passed: usize,
+        failed: usize,
+        skipped: usize,
+        filtered: usize,
+        duration: DurationJson,
+    },
+}
+
+/// A diagnostic emitted by a test.
+#[derive(Debug, Serialize)]
+pub struct DiagnosticJson {
+    pub severity: &'static str,
+    pub message: String,
+    pub hints: Vec<String>,
+    /// The location of the diagnostic as `path:line:column`, relative to the
+    /// project root.
+    pub location: Option<String>,
+}
+
+impl DiagnosticJson {
+    pub fn new(world: &SystemWorld, diag: &SourceDiagnostic) -> Self {
+        let location = diag.span.id().and_then(|id| {
+            let range = world.range(diag.span)?;
+            let source = world.source(id).ok()?;
+            let line = source.byte_to_line(range.start)? + 1;
+            let column = source.byte_to_column(range.start)? + 1;
+
+            Some(format!(
+                "{}:{line}:{column}",
+                id.vpath().as_rootless_path().display(),
+            ))
+        });
+
+        Self {
+            severity: match diag.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            },
+            message: diag.message.to_string(),
+            hints: diag.hints.iter().map(ToString::to_string).collect(),
+            location,
+        }
+    }
+}
+
+/// The page directories of a unit test which failed its comparison.
+#[derive(Debug, Serialize)]
+pub struct PagesJson {
+    pub output: PathBuf,
+    pub reference: PathBuf,
+    pub diff: PathBuf,
+}
+
+/// A writer for progress events to a user provided file descriptor or stdout.
+pub struct Progress {
+    sink: Mutex<Box<dyn Write + Send>>,
+    state: Mutex<(usize, usize)>,
+}
+
+/// A file which is not closed when dropped.
+struct BorrowedFile(ManuallyDrop<File>);
+
+impl Write for BorrowedFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl Progress {
+    /// Creates a progress writer for stdout.
+    pub fn stdout() -> Self {
+        Self {
+            sink: Mutex::new(Box::new(io::stdout())),
+            state: Mutex::new((0, 0)),
+        }
+    }
+
+    /// Creates a progress writer for the given file descriptor, returns an
+    /// error if the file descriptor is not open.
+    ///
+    /// # Safety
+    /// The file descriptor must not be closed for the lifetime of the progress
+    /// writer.
+    #[cfg(unix)]
+    pub unsafe fn from_raw_fd(fd: i32) -> io::Result<Self> {
+        use std::os::fd::FromRawFd;
+
+        // NOTE(tinger): The descriptor is owned by the caller, so we must not
+        // close it once we're done.
+        let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+        file.metadata()?;
+
+        Ok(Self {
+            sink: Mutex::new(Box::new(BorrowedFile(file))),
+            state: Mutex::new((0, 0)),
+        })
+    }
+}
+
+impl Progress {
+    /// Emits the start event of a test run.
+    pub fn start(&self, result: &SuiteResult) -> io::Result<()> {
+        let total = result.total() - result.filtered();
+        *self.state.lock().unwrap() = (0, total);
+
+        self.emit(&ProgressEvent::Start {
+            id: result.id().to_string(),
+            total,
+            filtered: result.filtered(),
+            estimate: result.estimate().map(Into::into),
+        })
+    }
+
+    /// Emits the start event of a single test.
+    pub fn test_start(&self, id: &Id) -> io::Result<()> {
+        self.emit(&ProgressEvent::TestStart { id: id.as_str() })
+    }
+
+    /// Emits the event of a finished test.
+    pub fn test(
+        &self,
+        project: &Project,
+        world: &SystemWorld,
+        test: &Test,
+        result: &TestResult,
+    ) -> io::Result<()> {
+        let (done, total) = {
+            let mut state = self.state.lock().unwrap();
+            state.0 += 1;
+            *state
+        };
+
+        let diagnostics = result
+            .errors()
+            .unwrap_or_default()
+            .iter()
+            .chain(result.warnings())
+            .map(|diag| DiagnosticJson::new(world, diag))
+            .collect();
+
+        let pages = match (test, result.stage()) {
+            (Test::Unit(test), Stage::FailedComparison(_)) => Some(PagesJson {
+                output: project.unit_test_out_dir(test.id()),
+                reference: project.unit_test_ref_dir(test.id()),
+                diff: project.unit_test_diff_dir(test.id()),
+            }),
+            _ => None,
+        };
+
+        self.emit(&ProgressEvent::Test {
+            done,
+            total,
+            result: TestResultJson::new(test.id().as_str(), result),
+            diagnostics,
+            pages,
+        })
+    }
+
+    /// Emits the end event of a test run.
+    pub fn end(&self, result: &SuiteResult) -> io::Result<()> {
+        self.emit(&ProgressEvent::End {
+            id: result.id().to_string(),
+            passed: result.passed(),
+            failed: result.failed(),
+            skipped: result.skipped(),
+            filtered: result.filtered(),
+            duration: result.duration().into(),
+        })
+    }
+
+    fn emit(&self, event: &ProgressEvent) -> io::Result<()> {
+        let mut sink = self.sink.lock().unwrap();
+        serde_json::to_writer(&mut *sink, event)?;
+        writeln!(sink)?;
+        sink.flush()
+    }
+}
@@ -0,0 +1,331 @@
+//! Downloading of packages from the package registry or its mirrors.
+//!
+//! Unlike the downloader of `typst-kit`, failed downloads are retried with an
+//! exponential backoff and each configured mirror is tried in order before
+//! falling back to the default registry.
+//!
+//! The packages used in a run can be recorded into an archive, which can be
+//! replayed later to serve the exact same packages without network access.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use ecow::eco_format;
+use ecow::EcoString;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use once_cell::sync::OnceCell;
+use tempdir::TempDir;
+use typst::diag::PackageError;
+use typst::diag::PackageResult;
+use typst::syntax::package::PackageSpec;
+use typst_kit::package::PackageStorage;
+use typst_kit::package::DEFAULT_NAMESPACE;
+use typst_kit::package::DEFAULT_REGISTRY;
+use tytanic_core::TOOL_NAME;
+
+use crate::cli::commands::PackageOptions;
+use crate::kit;
+
+/// The delay before the first retry, this is doubled for each further retry.
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Holds information about where packages are stored and downloads missing
+/// packages of the default namespace.
+#[derive(Debug)]
+pub struct PackageStore {
+    storage: PackageStorage,
+    options: PackageOptions,
+    recording: Option<Arc<Recording>>,
+    replay: OnceCell<Result<TempDir, EcoString>>,
+}
+
+impl PackageStore {
+    /// Creates a new package store with the given storage locations and
+    /// download options.
+    pub fn new(storage: PackageStorage, options: PackageOptions) -> Self {
+        Self {
+            storage,
+            options,
+            recording: None,
+            replay: OnceCell::new(),
+        }
+    }
+
+    /// Records all packages resolved by this store into the given recording.
+    pub fn with_recording(mut self, recording: Option<Arc<Recording>>) -> Self {
+        self.recording = recording;
+        self
+    }
+
+    /// The directory in which downloaded packages are cached.
+    pub fn cache_dir(&self) -> Option<&Path> {
+        self.storage.package_cache_path()
+    }
+
+    /// The directory in which local and vendored packages are stored.
+    pub fn data_dir(&self) -> Option<&Path> {
+        self.storage.package_path()
+    }
+
+    /// The registries which are tried in order when downloading a package,
+    /// these are the configured mirrors followed by the default registry.
+    pub fn registries(&self) -> impl Iterator<Item = &str> {
+        self.options
+            .package_mirrors
+            .iter()
+            .map(|mirror| mirror.trim_end_matches('/'))
+            .chain([DEFAULT_REGISTRY])
+    }
+
+    /// Make a package available on disk, downloading it if necessary.
+    pub fn prepare_package(&self, spec: &PackageSpec) -> PackageResult<PathBuf> {
+        let dir = match &self.options.replay_packages {
+            Some(archive) => self.replay_package(archive, spec)?,
+            None => self.resolve_package(spec)?,
+        };
+
+        if let Some(recording) = &self.recording {
+            recording.record(spec, &dir);
+        }
+
+        Ok(dir)
+    }
+
+    /// Serves a package from the given recorded archive, which is extracted
+    /// on first use.
+    fn replay_package(&self, archive: &Path, spec: &PackageSpec) -> PackageResult<PathBuf> {
+        let extracted = self
+            .replay
+            .get_or_init(|| {
+                let dir = TempDir::new(TOOL_NAME).map_err(|err| eco_format!("{err}"))?;
+                let file = File::open(archive).map_err(|err| {
+                    eco_format!("couldn't open recording {}: {err}", archive.display())
+                })?;
+
+                tar::Archive::new(GzDecoder::new(file))
+                    .unpack(dir.path())
+                    .map_err(|err| eco_format!("malformed recording: {err}"))?;
+
+                tracing::debug!(archive = ?archive, dir = ?dir.path(), "extracted recording");
+                Ok(dir)
+            })
+            .as_ref()
+            .map_err(|err| PackageError::Other(Some(err.clone())))?;
+
+        let dir = extracted.path().join(subdir(spec));
+        if !dir.exists() {
+            tracing::warn!(?spec, "package not in recording");
+            return Err(PackageError::NotFound(spec.clone()));
+        }
+
+        Ok(dir)
+    }
+
+    /// Resolves a package from the local packages, the package cache or the
+    /// registries.
+    fn resolve_package(&self, spec: &PackageSpec) -> PackageResult<PathBuf> {
+        let subdir = subdir(spec);
+
+        if let Some(packages_dir) = self.storage.package_path() {
+            let dir = packages_dir.join(&subdir);
+            if dir.exists() {
+                return Ok(dir);
+            }
+        }
+
+        if let Some(cache_dir) = self.storage.package_cache_path() {
+            let dir = cache_dir.join(&subdir);
+            if dir.exists() {
+                return Ok(dir);
+            }
+
+            if spec.namespace == DEFAULT_NAMESPACE {
+                let data = self.download(spec)?;
+
+                let decompressed = GzDecoder::new(data.as_slice());
+                tar::Archive::new(decompressed)
+                    .unpack(&dir)
+                    .map_err(|err| {
+                        fs::remove_dir_all(&dir).ok();
+                        PackageError::MalformedArchive(Some(eco_format!("{err}")))
+                    })?;
+
+                return Ok(dir);
+            }
+        }
+
+        Err(PackageError::NotFound(spec.clone()))
+    }
+
+    /// Downloads the archive of the given package, trying each registry in
+    /// order.
+    fn download(&self, spec: &PackageSpec) -> PackageResult<Vec<u8>> {
+        let mut attempts = vec![];
+        let mut failed = false;
+
+        for registry in self.registries() {
+            let url = format!(
+                "{registry}/{}/{}-{}.tar.gz",
+                spec.namespace, spec.name, spec.version,
+            );
+
+            match self.download_url(&url) {
+                Ok(data) => return Ok(data),
+                Err(Attempt::NotFound) => attempts.push((url, "not found".into())),
+                Err(Attempt::Failed(err)) => {
+                    failed = true;
+                    attempts.push((url, err));
+                }
+            }
+        }
+
+        if !failed {
+            return Err(PackageError::NotFound(spec.clone()));
+        }
+
+        let mut message = EcoString::from("tried ");
+        for (idx, (url, err)) in attempts.iter().enumerate() {
+            if idx != 0 {
+                message.push_str("; ");
+            }
+            _ = write!(message, "{url}: {err}");
+        }
+
+        tracing::warn!(?spec, ?attempts, "failed to download package");
+        Err(PackageError::NetworkFailed(Some(message)))
+    }
+
+    /// Downloads the given URL, retrying transient failures.
+    fn download_url(&self, url: &str) -> Result<Vec<u8>, Attempt> {
+        let agent = kit::agent_from_args(&self.options, url)
+            .map_err(|err| Attempt::Failed(eco_format!("{err}")))?;
+
+        let mut last = EcoString::new();
+        for attempt in 0..=self.options.download_retries {
+            if attempt != 0 {
+                let backoff = backoff(attempt);
+                tracing::debug!(url, attempt, ?backoff, "retrying download");
+                thread::sleep(backoff);
+            }
+
+            let response = match agent.get(url).call() {
+                Ok(response) => response,
+                Err(ureq::Error::Status(404, _)) => return Err(Attempt::NotFound),
+                Err(ureq::Error::Status(code, _)) if !is_transient(code) => {
+                    return Err(Attempt::Failed(eco_format!("status {code}")));
+                }
+                Err(ureq::Error::Status(code, _)) => {
+                    last = eco_format!("status {code}");
+                    continue;
+                }
+                Err(ureq::Error::Transport(err)) => {
+                    last = match err.message() {
+                        Some(message) => eco_format!("{}: {message}", err.kind()),
+                        None => eco_format!("{}", err.kind()),
+                    };
+                    continue;
+                }
+            };
+
+            let mut data = vec![];
+            match response.into_reader().read_to_end(&mut data) {
+                Ok(_) => return Ok(data),
+                Err(err) => last = eco_format!("{err}"),
+            }
+        }
+
+        Err(Attempt::Failed(last))
+    }
+}
+
+/// The packages resolved during a run, see `--record-packages`.
+#[derive(Debug, Default)]
+pub struct Recording {
+    packages: Mutex<BTreeMap<String, PathBuf>>,
+}
+
+impl Recording {
+    /// Creates a new empty recording.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the directory a package was resolved to.
+    fn record(&self, spec: &PackageSpec, dir: &Path) {
+        let mut packages = self.packages.lock().unwrap();
+        packages
+            .entry(subdir(spec))
+            .or_insert_with(|| dir.to_path_buf());
+    }
+
+    /// Writes the recorded packages into a gzip compressed tar archive at the
+    /// given path, returns the number of packages written.
+    ///
+    /// The archive uses the same `<namespace>/<name>/<version>` layout as the
+    /// package cache.
+    pub fn write(&self, path: &Path) -> io::Result<usize> {
+        let packages = self.packages.lock().unwrap();
+
+        let file = BufWriter::new(File::create(path)?);
+        let mut builder = tar::Builder::new(GzEncoder::new(file, flate2::Compression::default()));
+        builder.follow_symlinks(true);
+
+        for (subdir, dir) in packages.iter() {
+            builder.append_dir_all(subdir, dir)?;
+        }
+
+        builder.into_inner()?.finish()?;
+
+        Ok(packages.len())
+    }
+}
+
+/// The path of a package relative to a package directory.
+fn subdir(spec: &PackageSpec) -> String {
+    format!("{}/{}/{}", spec.namespace, spec.name, spec.version)
+}
+
+/// The outcome of a failed download from a single registry.
+enum Attempt {
+    /// The registry doesn't have the package.
+    NotFound,
+
+    /// All attempts failed, contains the last error.
+    Failed(EcoString),
+}
+
+/// Whether a download which failed with the given status code may succeed
+/// when retried.
+fn is_transient(code: u16) -> bool {
+    code == 408 || code == 429 || code >= 500
+}
+
+/// The delay before the given 1-based retry.
+fn backoff(retry: u32) -> Duration {
+    INITIAL_BACKOFF.saturating_mul(1 << retry.saturating_sub(1).min(16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::from_millis(500));
+        assert_eq!(backoff(2), Duration::from_secs(1));
+        assert_eq!(backoff(3), Duration::from_secs(2));
+        assert_eq!(backoff(100), INITIAL_BACKOFF * (1 << 16));
+    }
+}
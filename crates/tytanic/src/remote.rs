@@ -0,0 +1,1470 @@
+//! Experimental distributed test execution on remote workers.
+//!
+//! A coordinator connects to each worker over TCP and exchanges newline
+//! delimited JSON messages. It first ships the project files, after which the
+//! worker compiles and renders individual unit tests on request and sends back
+//! the rendered pages and diagnostics. Comparison and exports happen on the
+//! coordinator, such that the results are reported like those of a local run.
+//!
+//! The protocol has no authentication or encryption and must only be used in
+//! trusted networks. Workers only accept projects given by a path on the
+//! worker if this is explicitly enabled, see [`Roots`].
+//!
+//! The same protocol is used by the local daemon, which keeps the worlds of the
+//! projects it receives by path, such that fonts, packages and unchanged files
+//! are only loaded once across runs.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::fs;
+use std::io;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use base64::Engine;
+use chrono::DateTime;
+use color_eyre::eyre;
+use color_eyre::eyre::ContextCompat;
+use color_eyre::eyre::WrapErr;
+use ecow::eco_vec;
+use ecow::EcoString;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde::Serialize;
+use tempdir::TempDir;
+use typst::diag::Severity;
+use typst::diag::SourceDiagnostic;
+use typst::diag::Warned;
+use typst::foundations::Dict;
+use typst::syntax::Span;
+use typst::World;
+use typst::WorldExt;
+use tytanic_core::cancel::CancellationToken;
+use tytanic_core::doc;
+use tytanic_core::doc::compile;
+use tytanic_core::doc::compile::Warnings;
+use tytanic_core::doc::render;
+use tytanic_core::doc::text::TextLayer;
+use tytanic_core::doc::Document;
+use tytanic_core::doc::LimitError;
+use tytanic_core::doc::Limits;
+use tytanic_core::project::Project;
+use tytanic_core::project::ShallowProject;
+use tytanic_core::suite::Suite;
+use tytanic_core::test::Annotation;
+use tytanic_core::test::Id;
+use tytanic_core::test::UnitTest;
+use tytanic_core::TOOL_NAME;
+
+use crate::cli::commands::FontOptions;
+use crate::cli::commands::PackageOptions;
+use crate::kit;
+use crate::world::SystemWorld;
+
+/// The port workers listen on and coordinators connect to by default.
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// The address the local daemon listens on and runs connect to by default.
+pub const DAEMON_ADDR: &str = "127.0.0.1:7879";
+
+/// The number of runs after which unused cache entries are evicted by the
+/// daemon.
+const EVICT_AGE: usize = 10;
+
+/// The worlds kept by the daemon, keyed by project root and timestamp.
+type Worlds = Mutex<HashMap<(PathBuf, i64), Arc<SystemWorld>>>;
+
+/// Which projects a worker accepts by path instead of receiving their files,
+/// see the `root` of [`Request::Project`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Roots {
+    /// Projects must always be sent as files.
+    None,
+
+    /// Projects may be given by a path within this directory, this is used by
+    /// workers in containers with the project mounted.
+    Within(PathBuf),
+
+    /// Projects may be given by any path, this is used by the local daemon.
+    Any,
+}
+
+impl Roots {
+    /// Resolves the given project root, returns an error if it is not
+    /// accepted.
+    pub fn resolve(&self, root: &str) -> eyre::Result<PathBuf> {
+        let base = match self {
+            Self::None => eyre::bail!("worker doesn't accept projects given by path"),
+            Self::Within(base) => Some(
+                base.canonicalize()
+                    .wrap_err_with(|| format!("couldn't resolve allowed root {base:?}"))?,
+            ),
+            Self::Any => None,
+        };
+
+        let root = Path::new(root)
+            .canonicalize()
+            .wrap_err_with(|| format!("couldn't resolve project root {root:?}"))?;
+
+        if let Some(base) = base {
+            if !root.starts_with(&base) {
+                eyre::bail!("project root {root:?} is outside of {base:?}");
+            }
+        }
+
+        Ok(root)
+    }
+}
+
+/// A message sent from the coordinator to a worker.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Request {
+    /// The project to run tests in, this must be the first message.
+    Project {
+        /// The version of the coordinator, must match that of the worker.
+        version: String,
+        /// The files of the project.
+        files: Vec<RemoteFile>,
+        /// The path of the project on the worker, if it is already available
+        /// there, `files` is ignored if this is set.
+        #[serde(default)]
+        root: Option<String>,
+        /// The timestamp used for compilation in seconds since the unix epoch.
+        timestamp: i64,
+        /// How to handle warnings.
+        warnings: Warnings,
+        /// The limits on the size of rendered documents.
+        #[serde(default)]
+        limits: Limits,
+    },
+
+    /// Compile and render a unit test.
+    Test {
+        /// The id of the test.
+        id: String,
+        /// The pixel per point to render the pages with.
+        pixel_per_pt: f32,
+    },
+}
+
+/// A message sent from a worker to the coordinator.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+pub enum Response {
+    /// The project was received, the worker is ready to run tests.
+    Ready,
+
+    /// The result of a test.
+    Test(Box<RemoteTest>),
+
+    /// The request could not be handled. The connection is closed after this
+    /// if the project could not be received, errors of single tests leave it
+    /// open.
+    Error {
+        /// The error message.
+        message: String,
+    },
+}
+
+/// A file of the project.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteFile {
+    /// The path relative to the project root using forward slashes.
+    pub path: String,
+    /// The base64 encoded contents.
+    pub data: String,
+}
+
+/// The result of a unit test compiled on a worker.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteTest {
+    /// The id of the test.
+    pub id: String,
+    /// The output document.
+    pub output: RemoteCompilation,
+    /// The reference document, this is only present for ephemeral tests.
+    pub reference: Option<RemoteCompilation>,
+}
+
+/// A single compilation on a worker.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteCompilation {
+    /// The base64 encoded PNG pages, `None` if compilation failed.
+    pub pages: Option<Vec<String>>,
+    /// The warnings emitted by the compiler.
+    pub warnings: Vec<RemoteDiagnostic>,
+    /// The errors emitted by the compiler.
+    pub errors: Vec<RemoteDiagnostic>,
+    /// The exceeded limit if the document was too large to be rendered, in
+    /// which case `pages` is `None`.
+    #[serde(default)]
+    pub too_large: Option<LimitError>,
+    /// The text layer of the document, this is only present for tests which
+    /// compare their text layer.
+    #[serde(default)]
+    pub text: Option<TextLayer>,
+}
+
+/// A diagnostic of a compilation on a worker.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RemoteDiagnostic {
+    /// The message, prefixed with the location of the diagnostic if it has
+    /// one.
+    pub message: String,
+    /// The hints of the diagnostic.
+    pub hints: Vec<String>,
+}
+
+impl RemoteCompilation {
+    /// Splits this compilation into its warnings and the decoded document or
+    /// compilation error.
+    pub fn into_document(
+        self,
+    ) -> eyre::Result<(Vec<SourceDiagnostic>, Result<Document, compile::Error>)> {
+        let warnings = self
+            .warnings
+            .into_iter()
+            .map(|diag| diag.into_source_diagnostic(Severity::Warning))
+            .collect();
+
+        let Some(pages) = self.pages else {
+            let errors = self
+                .errors
+                .into_iter()
+                .map(|diag| diag.into_source_diagnostic(Severity::Error))
+                .collect();
+
+            return Ok((warnings, Err(compile::Error(errors))));
+        };
+
+        let pages = pages
+            .iter()
+            .map(|page| {
+                let data = base64::engine::general_purpose::STANDARD.decode(page)?;
+                Ok(doc::decode_page(&data)?)
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        let doc = Document::new(pages);
+        let doc = match self.text {
+            Some(text) => doc.with_text_layer(text),
+            None => doc,
+        };
+
+        Ok((warnings, Ok(doc)))
+    }
+}
+
+impl RemoteDiagnostic {
+    fn new(world: &SystemWorld, diag: &SourceDiagnostic) -> Self {
+        let location = diag.span.id().and_then(|id| {
+            let range = world.range(diag.span)?;
+            let source = world.source(id).ok()?;
+            let line = source.byte_to_line(range.start)? + 1;
+            let column = source.byte_to_column(range.start)? + 1;
+
+            Some(format!(
+                "{}:{line}:{column}",
+                id.vpath().as_rootless_path().display(),
+            ))
+        });
+
+        Self {
+            message: match location {
+                Some(location) => format!("{location}: {}", diag.message),
+                None => diag.message.to_string(),
+            },
+            hints: diag.hints.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn into_source_diagnostic(self, severity: Severity) -> SourceDiagnostic {
+        SourceDiagnostic {
+            severity,
+            span: Span::detached(),
+            message: self.message.into(),
+            trace: eco_vec![],
+            hints: self.hints.into_iter().map(EcoString::from).collect(),
+        }
+    }
+}
+
+/// A newline delimited JSON connection.
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+        })
+    }
+
+    fn send<T: Serialize>(&mut self, message: &T) -> eyre::Result<()> {
+        serde_json::to_writer(&mut self.writer, message)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Sets the time after which receiving a message fails, see
+    /// [`TcpStream::set_read_timeout`].
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.reader.get_ref().set_read_timeout(timeout)
+    }
+
+    /// Receives the next message, returns `None` if the connection was closed.
+    fn recv<T: DeserializeOwned>(&mut self) -> eyre::Result<Option<T>> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(serde_json::from_str(&line)?))
+    }
+
+    /// Sends a request and waits for the response, worker errors are turned
+    /// into errors.
+    fn request(&mut self, request: &Request) -> eyre::Result<Response> {
+        self.send(request)?;
+
+        match self.recv()? {
+            Some(Response::Error { message }) => eyre::bail!("worker error: {message}"),
+            Some(response) => Ok(response),
+            None => eyre::bail!("worker closed the connection"),
+        }
+    }
+}
+
+/// Appends the default port to the given worker address if it has none.
+pub fn worker_addr(addr: &str) -> String {
+    if addr
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+    {
+        addr.to_owned()
+    } else {
+        format!("{addr}:{DEFAULT_PORT}")
+    }
+}
+
+/// Collects the files of the project which are needed to run its tests on a
+/// worker.
+///
+/// This excludes hidden files, temporary test directories and persistent
+/// references, as comparison happens on the coordinator.
+pub fn collect_files(project: &Project, suite: &Suite) -> io::Result<Vec<RemoteFile>> {
+    fn collect(
+        root: &Path,
+        dir: &Path,
+        skip: &HashSet<PathBuf>,
+        files: &mut Vec<RemoteFile>,
+    ) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+
+            if entry.file_name().to_string_lossy().starts_with('.') || skip.contains(&path) {
+                continue;
+            }
+
+            if entry.file_type()?.is_dir() {
+                collect(root, &path, skip, files)?;
+            } else {
+                let rel = path.strip_prefix(root).expect("path is in root");
+                files.push(RemoteFile {
+                    path: rel
+                        .components()
+                        .map(|c| c.as_os_str().to_string_lossy())
+                        .collect::<Vec<_>>()
+                        .join("/"),
+                    data: base64::engine::general_purpose::STANDARD.encode(fs::read(&path)?),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut skip = HashSet::new();
+    for test in suite.unit_tests() {
+        skip.insert(project.unit_test_out_dir(test.id()));
+        skip.insert(project.unit_test_diff_dir(test.id()));
+
+        if test.kind().is_persistent() {
+            skip.insert(project.unit_test_ref_dir(test.id()));
+        }
+    }
+
+    let mut files = vec![];
+    collect(project.root(), project.root(), &skip, &mut files)?;
+    Ok(files)
+}
+
+/// A test which is waiting to be run by a [`WorkerPool`].
+#[derive(Debug, Clone)]
+pub struct PendingTest {
+    /// The id of the test.
+    pub id: Id,
+    /// The pixel per point to render the pages with.
+    pub pixel_per_pt: f32,
+    /// The serial module of the test, tests of the same serial module are
+    /// never run concurrently.
+    pub serial: Option<String>,
+    /// The time after which the worker running the test is considered stuck
+    /// and the test is reported as timed out, if any.
+    pub timeout: Option<Duration>,
+}
+
+/// The outcome of a test run by a [`WorkerPool`].
+#[derive(Debug)]
+pub enum RemoteOutcome {
+    /// The worker compiled the test.
+    Done(Box<RemoteTest>),
+
+    /// The worker didn't respond within the timeout of the test.
+    TimedOut(Duration),
+
+    /// The worker couldn't run the test, or too many workers disconnected
+    /// while running it.
+    Failed(String),
+}
+
+/// How often a test is given to another worker after the worker running it
+/// disconnected, before it's reported as failed. This keeps a test which
+/// crashes workers from taking down all of them.
+const MAX_REQUEUES: usize = 2;
+
+/// The state shared between the worker threads of a [`WorkerPool`].
+#[derive(Debug, Default)]
+struct PoolState {
+    /// The tests which were not yet sent to any worker.
+    queue: VecDeque<PendingTest>,
+    /// The serial modules of which a test is currently running.
+    running: HashSet<String>,
+    /// The number of tests which are currently running.
+    in_flight: usize,
+    /// The received outcomes which were not yet taken.
+    done: HashMap<Id, RemoteOutcome>,
+    /// The number of workers which received the project.
+    ready: usize,
+    /// The number of workers which are still connected.
+    alive: usize,
+    /// The errors of disconnected workers.
+    errors: Vec<String>,
+    /// How often each test was given to another worker.
+    requeued: HashMap<Id, usize>,
+}
+
+impl PoolState {
+    /// Gives a test whose worker disconnected to another worker, or reports
+    /// it as failed if this happened too often, see [`MAX_REQUEUES`].
+    fn requeue(&mut self, pending: PendingTest, err: &eyre::Report) {
+        let requeued = self.requeued.entry(pending.id.clone()).or_default();
+        if *requeued >= MAX_REQUEUES {
+            let message = format!(
+                "{} workers disconnected while running the test, the last with: {err:#}",
+                *requeued + 1,
+            );
+            self.done.insert(pending.id, RemoteOutcome::Failed(message));
+        } else {
+            *requeued += 1;
+            self.queue.push_front(pending);
+        }
+    }
+}
+
+/// A pool of remote workers which run unit tests in the background.
+#[derive(Debug)]
+pub struct WorkerPool {
+    state: Arc<(Mutex<PoolState>, Condvar)>,
+}
+
+impl WorkerPool {
+    /// Connects to the given workers and starts distributing the given tests
+    /// among them in the background, in order.
+    pub fn start(
+        addrs: &[String],
+        project: Request,
+        tests: Vec<PendingTest>,
+        cancellation: &CancellationToken,
+    ) -> Self {
+        let state = Arc::new((
+            Mutex::new(PoolState {
+                queue: tests.into(),
+                alive: addrs.len(),
+                ..Default::default()
+            }),
+            Condvar::new(),
+        ));
+
+        let project = Arc::new(project);
+
+        for addr in addrs {
+            let addr = worker_addr(addr);
+            let state = Arc::clone(&state);
+            let project = Arc::clone(&project);
+            let cancellation = cancellation.clone();
+
+            thread::spawn(move || {
+                let res = Self::work(&addr, &project, &state, &cancellation);
+
+                let (lock, cond) = &*state;
+                let mut state = lock.lock().unwrap();
+                state.alive -= 1;
+                if let Err(err) = res {
+                    tracing::error!(%addr, ?err, "worker disconnected");
+                    state.errors.push(format!("{addr}: {err}"));
+                }
+                cond.notify_all();
+            });
+        }
+
+        Self { state }
+    }
+
+    /// Connects to the given worker and sends it the project.
+    fn connect(addr: &str, project: &Request) -> eyre::Result<Connection> {
+        let mut conn = Connection::new(TcpStream::connect(addr)?)?;
+        match conn.request(project)? {
+            Response::Ready => Ok(conn),
+            response => eyre::bail!("unexpected response: {response:?}"),
+        }
+    }
+
+    fn work(
+        addr: &str,
+        project: &Request,
+        state: &(Mutex<PoolState>, Condvar),
+        cancellation: &CancellationToken,
+    ) -> eyre::Result<()> {
+        let (lock, cond) = state;
+
+        let mut conn = Self::connect(addr, project)?;
+
+        tracing::debug!(%addr, "worker ready");
+        lock.lock().unwrap().ready += 1;
+        cond.notify_all();
+
+        loop {
+            if cancellation.is_cancelled() {
+                return Ok(());
+            }
+
+            let Some(pending) = Self::next(state, cancellation) else {
+                return Ok(());
+            };
+
+            // NOTE(tinger): Error responses concern only this test, so they
+            // are not turned into errors like in `Connection::request`.
+            let res = conn
+                .set_read_timeout(pending.timeout)
+                .map_err(Into::into)
+                .and_then(|()| {
+                    conn.send(&Request::Test {
+                        id: pending.id.to_string(),
+                        pixel_per_pt: pending.pixel_per_pt,
+                    })?;
+                    conn.recv::<Response>()?
+                        .context("worker closed the connection")
+                });
+
+            let mut state = lock.lock().unwrap();
+            state.in_flight -= 1;
+            if let Some(serial) = &pending.serial {
+                state.running.remove(serial);
+            }
+            cond.notify_all();
+
+            let test = match res {
+                Ok(Response::Test(test)) => test,
+                Ok(Response::Error { message }) => {
+                    tracing::warn!(%addr, id = %pending.id, %message, "test failed on worker");
+                    state
+                        .done
+                        .insert(pending.id, RemoteOutcome::Failed(message));
+                    continue;
+                }
+                Ok(response) => {
+                    let err = eyre::eyre!("unexpected response: {response:?}");
+                    state.requeue(pending, &err);
+                    return Err(err);
+                }
+                Err(err) if is_timeout(&err) => {
+                    let timeout = pending.timeout.unwrap_or_default();
+                    tracing::warn!(%addr, id = %pending.id, ?timeout, "test timed out");
+                    state
+                        .done
+                        .insert(pending.id, RemoteOutcome::TimedOut(timeout));
+                    drop(state);
+
+                    // NOTE(tinger): The worker is still busy with the test
+                    // and would send its result on this connection later, so
+                    // we continue on a new one.
+                    conn = Self::connect(addr, project)?;
+                    continue;
+                }
+                Err(err) => {
+                    // NOTE(tinger): Only transport errors disconnect the
+                    // worker, the test is given to another one.
+                    state.requeue(pending, &err);
+                    return Err(err);
+                }
+            };
+
+            state.done.insert(pending.id, RemoteOutcome::Done(test));
+        }
+    }
+
+    /// Takes the next test from the queue whose serial module is not currently
+    /// running, waits for running tests if only such tests are left or the
+    /// queue is empty.
+    fn next(
+        state: &(Mutex<PoolState>, Condvar),
+        cancellation: &CancellationToken,
+    ) -> Option<PendingTest> {
+        let (lock, cond) = state;
+        let mut state = lock.lock().unwrap();
+
+        loop {
+            // NOTE(tinger): Running tests may still be requeued if their
+            // worker disconnects.
+            if (state.queue.is_empty() && state.in_flight == 0) || cancellation.is_cancelled() {
+                return None;
+            }
+
+            let idx = state.queue.iter().position(|pending| {
+                pending
+                    .serial
+                    .as_ref()
+                    .is_none_or(|serial| !state.running.contains(serial))
+            });
+
+            if let Some(pending) = idx.and_then(|idx| state.queue.remove(idx)) {
+                if let Some(serial) = &pending.serial {
+                    state.running.insert(serial.clone());
+                }
+                state.in_flight += 1;
+
+                return Some(pending);
+            }
+
+            // NOTE(tinger): We time out to regularly check for cancellation.
+            state = cond
+                .wait_timeout(state, Duration::from_millis(100))
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Waits until at least one worker received the project, returns the
+    /// connection errors of all workers otherwise.
+    pub fn wait_ready(&self) -> Result<(), Vec<String>> {
+        let (lock, cond) = &*self.state;
+        let mut state = lock.lock().unwrap();
+
+        while state.ready == 0 {
+            if state.alive == 0 {
+                return Err(state.errors.clone());
+            }
+
+            state = cond.wait(state).unwrap();
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the outcome of the given test, returns an error if all
+    /// workers disconnected before it was run.
+    pub fn take(&self, id: &Id) -> eyre::Result<RemoteOutcome> {
+        let (lock, cond) = &*self.state;
+        let mut state = lock.lock().unwrap();
+
+        loop {
+            if let Some(test) = state.done.remove(id) {
+                return Ok(test);
+            }
+
+            if state.alive == 0 {
+                eyre::bail!(
+                    "no workers left to run test {id}:\n{}",
+                    state.errors.join("\n")
+                );
+            }
+
+            state = cond.wait(state).unwrap();
+        }
+    }
+}
+
+/// Whether the given error is caused by an expired read timeout, see
+/// [`Connection::set_read_timeout`].
+fn is_timeout(err: &eyre::Report) -> bool {
+    err.root_cause()
+        .downcast_ref::<io::Error>()
+        .is_some_and(|err| {
+            matches!(
+                err.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            )
+        })
+}
+
+/// Returns the pixel per point to render the given test with.
+pub fn pixel_per_pt(test: &UnitTest, default: f32) -> f32 {
+    let mut pixel_per_pt = default;
+    for annot in test.annotations() {
+        if let Annotation::Ppi(ppi) = annot {
+            pixel_per_pt = render::ppi_to_ppp(*ppi);
+        }
+    }
+
+    pixel_per_pt
+}
+
+/// Serves test requests from coordinators until the listener fails.
+///
+/// Projects given by path are only accepted as allowed by `roots`. If
+/// `keep_alive` is set, the worlds of projects which are given by path are
+/// kept for later connections.
+pub fn serve(
+    listener: TcpListener,
+    font: FontOptions,
+    package: PackageOptions,
+    roots: Roots,
+    keep_alive: bool,
+) -> io::Result<()> {
+    let font = Arc::new(font);
+    let package = Arc::new(package);
+    let roots = Arc::new(roots);
+    let worlds = keep_alive.then(|| Arc::new(Worlds::default()));
+
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let peer = stream.peer_addr()?;
+        let font = Arc::clone(&font);
+        let package = Arc::clone(&package);
+        let roots = Arc::clone(&roots);
+        let worlds = worlds.clone();
+
+        thread::spawn(move || {
+            tracing::info!(%peer, "coordinator connected");
+            match handle(stream, &font, &package, &roots, worlds.as_deref()) {
+                Ok(()) => tracing::info!(%peer, "coordinator disconnected"),
+                Err(err) => tracing::error!(%peer, ?err, "connection failed"),
+            }
+
+            if worlds.is_some() {
+                comemo::evict(EVICT_AGE);
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// A project received from a coordinator.
+struct RemoteProject {
+    // NOTE(tinger): The directory must outlive the project and world.
+    _dir: Option<TempDir>,
+    project: Project,
+    world: Arc<SystemWorld>,
+    warnings: Warnings,
+    limits: Limits,
+}
+
+fn handle(
+    stream: TcpStream,
+    font: &FontOptions,
+    package: &PackageOptions,
+    roots: &Roots,
+    worlds: Option<&Worlds>,
+) -> eyre::Result<()> {
+    let mut conn = Connection::new(stream)?;
+    let mut remote = None;
+
+    while let Some(request) = conn.recv::<Request>()? {
+        let res = match request {
+            Request::Project {
+                version,
+                files,
+                root,
+                timestamp,
+                warnings,
+                limits,
+            } => root
+                .map(|root| roots.resolve(&root))
+                .transpose()
+                .and_then(|root| {
+                    receive_project(version, files, root, timestamp, font, package, worlds)
+                })
+                .map(|(dir, project, world)| {
+                    remote = Some(RemoteProject {
+                        _dir: dir,
+                        project,
+                        world,
+                        warnings,
+                        limits,
+                    });
+                    Response::Ready
+                }),
+            Request::Test { id, pixel_per_pt } => remote
+                .as_ref()
+                .context("received test before project")
+                .and_then(|remote| run_test(remote, id, pixel_per_pt))
+                .map(|test| Response::Test(Box::new(test))),
+        };
+
+        match res {
+            Ok(response) => conn.send(&response)?,
+            Err(err) => {
+                conn.send(&Response::Error {
+                    message: format!("{err:#}"),
+                })?;
+
+                // NOTE(tinger): Errors of single tests don't affect the other
+                // tests of the project.
+                if remote.is_none() {
+                    return Err(err);
+                }
+
+                tracing::warn!(?err, "test failed");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn receive_project(
+    version: String,
+    files: Vec<RemoteFile>,
+    root: Option<PathBuf>,
+    timestamp: i64,
+    font: &FontOptions,
+    package: &PackageOptions,
+    worlds: Option<&Worlds>,
+) -> eyre::Result<(Option<TempDir>, Project, Arc<SystemWorld>)> {
+    if version != env!("CARGO_PKG_VERSION") {
+        eyre::bail!(
+            "version mismatch, coordinator uses {version}, worker uses {}",
+            env!("CARGO_PKG_VERSION"),
+        );
+    }
+
+    // NOTE(tinger): Worlds of received files are never kept, their temporary
+    // directory is removed once the connection is closed.
+    let (dir, root, worlds) = match root {
+        Some(root) => (None, root, worlds),
+        None => {
+            let dir = TempDir::new(TOOL_NAME)?;
+            write_files(dir.path(), files)?;
+            let root = dir.path().to_path_buf();
+            (Some(dir), root, None)
+        }
+    };
+
+    let project = ShallowProject::discover(&root, true)?
+        .context("received invalid project")?
+        .load()?;
+
+    let new_world = || -> eyre::Result<_> {
+        Ok(Arc::new(SystemWorld::new(
+            root.clone(),
+            kit::fonts_from_args(font),
+            kit::package_store_from_args(package),
+            DateTime::from_timestamp(timestamp, 0).context("invalid timestamp")?,
+        )?))
+    };
+
+    let world = match worlds {
+        Some(worlds) => {
+            let mut worlds = worlds.lock().unwrap();
+            match worlds.get(&(root.clone(), timestamp)) {
+                Some(world) => {
+                    tracing::debug!(?root, "reusing world");
+                    Arc::clone(world)
+                }
+                None => {
+                    let world = new_world()?;
+                    worlds.insert((root.clone(), timestamp), Arc::clone(&world));
+                    world
+                }
+            }
+        }
+        None => new_world()?,
+    };
+
+    Ok((dir, project, world))
+}
+
+/// Writes the received files into the given directory.
+fn write_files(dir: &Path, files: Vec<RemoteFile>) -> eyre::Result<()> {
+    for file in files {
+        let rel = Path::new(&file.path);
+        if !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+            eyre::bail!("invalid file path {:?}", file.path);
+        }
+
+        let path = dir.join(rel);
+        if let Some(parent) = path.parent() {
+            tytanic_utils::fs::create_dir(parent, true)?;
+        }
+
+        fs::write(
+            path,
+            base64::engine::general_purpose::STANDARD.decode(&file.data)?,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn run_test(remote: &RemoteProject, id: String, pixel_per_pt: f32) -> eyre::Result<RemoteTest> {
+    let id = Id::new(id)?;
+    let Some(test) = UnitTest::load(&remote.project, id.clone())? else {
+        eyre::bail!("test {id} not found");
+    };
+
+    // NOTE(tinger): Kept worlds may have cached files which changed since
+    // their last run.
+    remote.world.reset();
+
+    // NOTE(tinger): The pages of compile-only tests are never compared, so we
+    // don't render them.
+    let render = !test.kind().is_compile_only();
+    let text = test.text_tolerance().is_some();
+
+    let output = compile_remote(
+        remote,
+        test.load_source(&remote.project)?,
+        test.inputs(),
+        pixel_per_pt,
+        render,
+        text,
+    )?;
+    let reference = test
+        .load_reference_source(&remote.project)?
+        .map(|source| compile_remote(remote, source, test.inputs(), pixel_per_pt, render, text))
+        .transpose()?;
+
+    Ok(RemoteTest {
+        id: id.to_string(),
+        output,
+        reference,
+    })
+}
+
+fn compile_remote(
+    remote: &RemoteProject,
+    source: typst::syntax::Source,
+    inputs: Dict,
+    pixel_per_pt: f32,
+    render: bool,
+    text: bool,
+) -> eyre::Result<RemoteCompilation> {
+    let Warned { output, warnings } =
+        compile::compile(source, &*remote.world, remote.warnings, |w| {
+            w.augment_standard_library(true).inputs(inputs)
+        });
+
+    let warnings = warnings
+        .iter()
+        .map(|diag| RemoteDiagnostic::new(&remote.world, diag))
+        .collect();
+
+    Ok(match output {
+        Ok(_) if !render => RemoteCompilation {
+            pages: Some(vec![]),
+            warnings,
+            errors: vec![],
+            too_large: None,
+            text: None,
+        },
+        Ok(doc) => {
+            let text = text.then(|| TextLayer::extract(&doc));
+            match remote.limits.check(&doc, pixel_per_pt) {
+                Ok(()) => RemoteCompilation {
+                    pages: Some(
+                        Document::render(doc, pixel_per_pt)
+                            .buffers()
+                            .iter()
+                            .map(|page| {
+                                Ok(base64::engine::general_purpose::STANDARD
+                                    .encode(page.encode_png()?))
+                            })
+                            .collect::<eyre::Result<_>>()?,
+                    ),
+                    warnings,
+                    errors: vec![],
+                    too_large: None,
+                    text,
+                },
+                Err(err) => RemoteCompilation {
+                    pages: None,
+                    warnings,
+                    errors: vec![],
+                    too_large: Some(err),
+                    text: None,
+                },
+            }
+        }
+        Err(err) => RemoteCompilation {
+            pages: None,
+            warnings,
+            errors: err
+                .0
+                .iter()
+                .map(|diag| RemoteDiagnostic::new(&remote.world, diag))
+                .collect(),
+            too_large: None,
+            text: None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+    use tytanic_utils::fs::TempTestEnv;
+
+    use super::*;
+
+    /// Default font and package options as if no arguments were given.
+    #[derive(Parser)]
+    struct WorldArgs {
+        #[command(flatten)]
+        font: FontOptions,
+
+        #[command(flatten)]
+        package: PackageOptions,
+    }
+
+    /// Starts a worker listening on a random loopback port.
+    fn spawn_worker(roots: Roots, keep_alive: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let args = WorldArgs::parse_from(["tt"]);
+
+        thread::spawn(move || serve(listener, args.font, args.package, roots, keep_alive));
+
+        addr
+    }
+
+    /// Takes the result of a test which is expected to have been compiled.
+    fn take(pool: &WorkerPool, id: &Id) -> RemoteTest {
+        match pool.take(id).unwrap() {
+            RemoteOutcome::Done(test) => *test,
+            outcome => panic!("unexpected outcome: {outcome:?}"),
+        }
+    }
+
+    fn file(path: &str, data: &str) -> RemoteFile {
+        RemoteFile {
+            path: path.into(),
+            data: base64::engine::general_purpose::STANDARD.encode(data),
+        }
+    }
+
+    fn project_request(files: Vec<RemoteFile>, root: Option<String>) -> Request {
+        Request::Project {
+            version: env!("CARGO_PKG_VERSION").into(),
+            files,
+            root,
+            timestamp: 0,
+            warnings: Warnings::Emit,
+            limits: Limits::default(),
+        }
+    }
+
+    #[test]
+    fn test_protocol_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = Connection::new(TcpStream::connect(addr).unwrap()).unwrap();
+        let mut server = Connection::new(listener.accept().unwrap().0).unwrap();
+
+        client
+            .send(&project_request(vec![file("a/b.typ", "Hello")], None))
+            .unwrap();
+        client
+            .send(&Request::Test {
+                id: "a/b".into(),
+                pixel_per_pt: 2.0,
+            })
+            .unwrap();
+
+        match server.recv::<Request>().unwrap() {
+            Some(Request::Project {
+                version,
+                files,
+                root,
+                timestamp,
+                ..
+            }) => {
+                assert_eq!(version, env!("CARGO_PKG_VERSION"));
+                assert_eq!(files.len(), 1);
+                assert_eq!(files[0].path, "a/b.typ");
+                assert_eq!(root, None);
+                assert_eq!(timestamp, 0);
+            }
+            request => panic!("unexpected request: {request:?}"),
+        }
+
+        match server.recv::<Request>().unwrap() {
+            Some(Request::Test { id, pixel_per_pt }) => {
+                assert_eq!(id, "a/b");
+                assert_eq!(pixel_per_pt, 2.0);
+            }
+            request => panic!("unexpected request: {request:?}"),
+        }
+
+        server.send(&Response::Ready).unwrap();
+        server
+            .send(&Response::Error {
+                message: "oops".into(),
+            })
+            .unwrap();
+        drop(server);
+
+        assert!(matches!(
+            client.recv::<Response>().unwrap(),
+            Some(Response::Ready)
+        ));
+        assert!(matches!(
+            client.recv::<Response>().unwrap(),
+            Some(Response::Error { message }) if message == "oops"
+        ));
+        assert!(client.recv::<Response>().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_worker_loopback() {
+        let addr = spawn_worker(Roots::None, false);
+
+        let files = vec![
+            file(
+                "typst.toml",
+                "[package]\nname = \"remote\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n",
+            ),
+            file("lib.typ", ""),
+            file("tests/pass/test.typ", "Hello"),
+            file("tests/pass/ref.typ", "Hello"),
+            file("tests/fail/test.typ", "#panic()"),
+        ];
+
+        let pass = Id::new("pass").unwrap();
+        let fail = Id::new("fail").unwrap();
+        let pending = |id: &Id| PendingTest {
+            id: id.clone(),
+            pixel_per_pt: 1.0,
+            serial: None,
+            timeout: None,
+        };
+
+        let pool = WorkerPool::start(
+            &[addr],
+            project_request(files, None),
+            vec![pending(&pass), pending(&fail)],
+            &CancellationToken::new(),
+        );
+        pool.wait_ready().unwrap();
+
+        let test = take(&pool, &pass);
+        assert_eq!(test.id, "pass");
+        let (warnings, output) = test.output.into_document().unwrap();
+        let (_, reference) = test.reference.unwrap().into_document().unwrap();
+        assert!(warnings.is_empty());
+        let (output, reference) = (output.unwrap(), reference.unwrap());
+        assert_eq!(output.buffers().len(), 1);
+        assert_eq!(output.buffers(), reference.buffers());
+
+        let test = take(&pool, &fail);
+        let (_, doc) = test.output.into_document().unwrap();
+        assert_eq!(doc.unwrap_err().0.len(), 1);
+    }
+
+    #[test]
+    fn test_worker_timeout() {
+        let addr = spawn_worker(Roots::None, false);
+
+        let files = vec![
+            file(
+                "typst.toml",
+                "[package]\nname = \"remote\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n",
+            ),
+            file("lib.typ", ""),
+            file(
+                "tests/loop/test.typ",
+                "#for i in range(1000000) { for j in range(1000000) {} }",
+            ),
+            file("tests/pass/test.typ", "Hello"),
+        ];
+
+        let stuck = Id::new("loop").unwrap();
+        let pass = Id::new("pass").unwrap();
+        let timeout = Duration::from_millis(500);
+
+        let pool = WorkerPool::start(
+            &[addr],
+            project_request(files, None),
+            vec![
+                PendingTest {
+                    id: stuck.clone(),
+                    pixel_per_pt: 1.0,
+                    serial: None,
+                    timeout: Some(timeout),
+                },
+                PendingTest {
+                    id: pass.clone(),
+                    pixel_per_pt: 1.0,
+                    serial: None,
+                    timeout: Some(timeout),
+                },
+            ],
+            &CancellationToken::new(),
+        );
+        pool.wait_ready().unwrap();
+
+        assert!(matches!(
+            pool.take(&stuck).unwrap(),
+            RemoteOutcome::TimedOut(t) if t == timeout
+        ));
+
+        // NOTE(tinger): The worker is still stuck on the first connection,
+        // later tests must be run on a new one.
+        let (_, doc) = take(&pool, &pass).output.into_document().unwrap();
+        assert!(doc.is_ok());
+    }
+
+    #[test]
+    fn test_worker_test_error() {
+        let addr = spawn_worker(Roots::None, false);
+
+        let files = vec![
+            file(
+                "typst.toml",
+                "[package]\nname = \"remote\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n",
+            ),
+            file("lib.typ", ""),
+            file("tests/pass/test.typ", "Hello"),
+        ];
+
+        let missing = Id::new("missing").unwrap();
+        let pass = Id::new("pass").unwrap();
+        let pending = |id: &Id| PendingTest {
+            id: id.clone(),
+            pixel_per_pt: 1.0,
+            serial: None,
+            timeout: None,
+        };
+
+        let pool = WorkerPool::start(
+            &[addr],
+            project_request(files, None),
+            vec![pending(&missing), pending(&pass)],
+            &CancellationToken::new(),
+        );
+        pool.wait_ready().unwrap();
+
+        assert!(matches!(
+            pool.take(&missing).unwrap(),
+            RemoteOutcome::Failed(message) if message.contains("test missing not found")
+        ));
+
+        // NOTE(tinger): The worker must not be dropped for a test error.
+        let (_, doc) = take(&pool, &pass).output.into_document().unwrap();
+        assert!(doc.is_ok());
+    }
+
+    #[test]
+    fn test_worker_requeue_limit() {
+        // NOTE(tinger): This worker disconnects whenever it receives a test.
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut conn = Connection::new(stream.unwrap()).unwrap();
+                thread::spawn(move || {
+                    conn.recv::<Request>().unwrap();
+                    conn.send(&Response::Ready).unwrap();
+                    conn.recv::<Request>().unwrap();
+                });
+            }
+        });
+
+        let id = Id::new("crash").unwrap();
+        let pool = WorkerPool::start(
+            &vec![addr; MAX_REQUEUES + 2],
+            project_request(vec![], None),
+            vec![PendingTest {
+                id: id.clone(),
+                pixel_per_pt: 1.0,
+                serial: None,
+                timeout: None,
+            }],
+            &CancellationToken::new(),
+        );
+        pool.wait_ready().unwrap();
+
+        assert!(matches!(
+            pool.take(&id).unwrap(),
+            RemoteOutcome::Failed(message)
+                if message.starts_with(&format!("{} workers disconnected", MAX_REQUEUES + 1))
+        ));
+    }
+
+    #[test]
+    fn test_worker_rejects_root() {
+        let addr = spawn_worker(Roots::None, false);
+
+        let mut conn = Connection::new(TcpStream::connect(addr).unwrap()).unwrap();
+        let err = conn
+            .request(&project_request(vec![], Some("/".into())))
+            .unwrap_err();
+
+        assert!(
+            err.to_string()
+                .contains("doesn't accept projects given by path"),
+            "{err}",
+        );
+    }
+
+    #[test]
+    fn test_worker_rejects_version_mismatch() {
+        let addr = spawn_worker(Roots::None, false);
+
+        let mut conn = Connection::new(TcpStream::connect(addr).unwrap()).unwrap();
+        let err = conn
+            .request(&Request::Project {
+                version: "0.0.0".into(),
+                files: vec![],
+                root: None,
+                timestamp: 0,
+                warnings: Warnings::Emit,
+                limits: Limits::default(),
+            })
+            .unwrap_err();
+
+        assert!(err.to_string().contains("version mismatch"), "{err}");
+    }
+
+    #[test]
+    fn test_daemon_reuses_worlds() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file(
+                    "typst.toml",
+                    "[package]\nname = \"daemon\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n",
+                )
+                .setup_file_empty("lib.typ")
+            },
+            |root| {
+                let args = WorldArgs::parse_from(["tt"]);
+                let worlds = Worlds::default();
+                let receive = |timestamp| {
+                    let (dir, _, world) = receive_project(
+                        env!("CARGO_PKG_VERSION").into(),
+                        vec![],
+                        Some(root.to_path_buf()),
+                        timestamp,
+                        &args.font,
+                        &args.package,
+                        Some(&worlds),
+                    )
+                    .unwrap();
+                    assert!(dir.is_none());
+                    world
+                };
+
+                let first = receive(0);
+                assert!(Arc::ptr_eq(&first, &receive(0)));
+                assert!(!Arc::ptr_eq(&first, &receive(1)));
+                assert_eq!(worlds.lock().unwrap().len(), 2);
+            },
+        );
+    }
+
+    #[test]
+    fn test_daemon_picks_up_changes() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file(
+                    "typst.toml",
+                    "[package]\nname = \"daemon\"\nversion = \"0.1.0\"\nentrypoint = \"lib.typ\"\n",
+                )
+                .setup_file("lib.typ", "#let greet = [Hello]")
+                .setup_file("tests/test/test.typ", "#import \"/lib.typ\": greet\n#greet")
+            },
+            |root| {
+                let addr = spawn_worker(Roots::Any, true);
+                let id = Id::new("test").unwrap();
+                let run = || {
+                    let pool = WorkerPool::start(
+                        std::slice::from_ref(&addr),
+                        project_request(vec![], Some(root.display().to_string())),
+                        vec![PendingTest {
+                            id: id.clone(),
+                            pixel_per_pt: 1.0,
+                            serial: None,
+                            timeout: None,
+                        }],
+                        &CancellationToken::new(),
+                    );
+                    pool.wait_ready().unwrap();
+                    let (_, doc) = take(&pool, &id).output.into_document().unwrap();
+                    doc
+                };
+
+                assert!(run().is_ok());
+
+                // NOTE(tinger): The kept world must not return the cached
+                // sources of imported files from the previous run.
+                fs::write(root.join("lib.typ"), "#let greet = panic()").unwrap();
+                assert_eq!(run().unwrap_err().0.len(), 1);
+            },
+        );
+    }
+
+    #[test]
+    fn test_roots_resolve() {
+        TempTestEnv::run_no_check(
+            |root| {
+                root.setup_file_empty("allowed/project/typst.toml")
+                    .setup_file_empty("other/typst.toml")
+            },
+            |root| {
+                let project = root.join("allowed/project");
+                let other = root.join("other");
+                let escape = root.join("allowed/../other");
+                let roots = Roots::Within(root.join("allowed"));
+
+                assert!(Roots::None.resolve(&project.display().to_string()).is_err());
+                assert_eq!(
+                    roots.resolve(&project.display().to_string()).unwrap(),
+                    project.canonicalize().unwrap(),
+                );
+                assert!(roots.resolve(&other.display().to_string()).is_err());
+                assert!(roots.resolve(&escape.display().to_string()).is_err());
+                assert!(Roots::Any.resolve(&other.display().to_string()).is_ok());
+            },
+        );
+    }
+}
@@ -1,22 +1,36 @@
 //! Live reporting of test progress.
 
+use std::borrow::Cow;
 use std::io;
 use std::io::Write;
+use std::path::Path;
 use std::time::Duration;
 
 use color_eyre::eyre;
 use termcolor::Color;
+use termcolor::WriteColor;
+use tiny_skia::Pixmap;
 use tytanic_core::doc::compare;
 use tytanic_core::doc::compare::PageError;
+use tytanic_core::doc::render;
+use tytanic_core::doc::Document;
+use tytanic_core::project::Project;
+use tytanic_core::suite::GroupStatus;
 use tytanic_core::suite::SuiteResult;
+use tytanic_core::test::Id;
 use tytanic_core::test::Stage;
 use tytanic_core::test::Test;
 use tytanic_core::test::TestResult;
 use tytanic_utils::fmt::Term;
 
+use crate::cli::commands::OutputFormat;
+use crate::cli::commands::SummaryBy;
 use crate::cwrite;
+use crate::progress::Progress;
 use crate::ui;
 use crate::ui::CWrite;
+use crate::ui::ImageProtocol;
+use crate::ui::Indented;
 use crate::ui::Ui;
 use crate::world::SystemWorld;
 
@@ -26,20 +40,90 @@ const RUN_ANNOT_PADDING: usize = 10;
 /// A reporter for test output and test run status reporting.
 pub struct Reporter<'ui, 'p> {
     ui: &'ui Ui,
+    project: &'p Project,
     world: &'p SystemWorld,
 
     live: bool,
+    notes: bool,
+    passes: bool,
+    format: OutputFormat,
+    summary_by: Option<SummaryBy>,
+    progress: Option<Progress>,
+    preview: Option<ImageProtocol>,
 }
 
 impl<'ui, 'p> Reporter<'ui, 'p> {
-    pub fn new(ui: &'ui Ui, world: &'p SystemWorld, live: bool) -> Self {
-        Self { ui, world, live }
+    pub fn new(
+        ui: &'ui Ui,
+        project: &'p Project,
+        world: &'p SystemWorld,
+        live: bool,
+        notes: bool,
+    ) -> Self {
+        Self {
+            ui,
+            project,
+            world,
+            live,
+            notes,
+            passes: true,
+            format: OutputFormat::Human,
+            summary_by: None,
+            progress: None,
+            preview: None,
+        }
+    }
+
+    /// Sets the format of the human readable output.
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Additionally reports the results grouped as given after the summary.
+    pub fn with_summary_by(mut self, summary_by: Option<SummaryBy>) -> Self {
+        self.summary_by = summary_by;
+        self
+    }
+
+    /// Whether to report passing tests without any warnings.
+    pub fn with_passes(mut self, passes: bool) -> Self {
+        self.passes = passes;
+        self
+    }
+
+    /// Additionally emits machine readable progress events to the given
+    /// writer.
+    pub fn with_progress(mut self, progress: Option<Progress>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Shows inline previews of the reference, output and diff images of
+    /// tests which failed comparison using the given graphics protocol.
+    pub fn with_preview(mut self, preview: Option<ImageProtocol>) -> Self {
+        self.preview = preview;
+        self
     }
 }
 
 impl Reporter<'_, '_> {
+    /// Whether only failures should be reported, without run specific
+    /// information like durations.
+    fn is_quiet(&self) -> bool {
+        self.format != OutputFormat::Human
+    }
+
     /// Reports the start of a test run.
     pub fn report_start(&self, result: &SuiteResult) -> io::Result<()> {
+        if let Some(progress) = &self.progress {
+            progress.start(result)?;
+        }
+
+        if self.is_quiet() {
+            return Ok(());
+        }
+
         let mut w = ui::annotated(
             self.ui.stderr(),
             "Starting",
@@ -57,6 +141,12 @@ impl Reporter<'_, '_> {
             cwrite!(colored(w, Color::Yellow), "filtered")?;
         }
 
+        if let Some(estimate) = result.estimate() {
+            write!(w, ", est. ")?;
+            cwrite!(bold(w), "{}", fmt_estimate(estimate))?;
+            write!(w, " based on history")?;
+        }
+
         write!(w, " (run ID: ")?;
         cwrite!(bold(w), "{}", result.id())?;
         writeln!(w, ")")?;
@@ -66,6 +156,10 @@ impl Reporter<'_, '_> {
 
     /// Reports the end of a test run.
     pub fn report_end(&self, result: &SuiteResult) -> io::Result<()> {
+        if let Some(progress) = &self.progress {
+            progress.end(result)?;
+        }
+
         let mut w = self.ui.stderr();
 
         let color = if result.failed() == 0 {
@@ -76,71 +170,217 @@ impl Reporter<'_, '_> {
             Color::Yellow
         };
 
-        writeln!(w, "{:─>RUN_ANNOT_PADDING$}", "")?;
+        if !self.is_quiet() {
+            writeln!(w, "{:─>RUN_ANNOT_PADDING$}", "")?;
+        }
 
         let mut w = ui::annotated(w, "Summary", color, RUN_ANNOT_PADDING)?;
 
-        write!(w, "[")?;
-        {
-            let mut w = ui::colored(
-                &mut w,
-                duration_color(
-                    result
-                        .duration()
-                        .checked_div(result.run() as u32)
-                        .unwrap_or_default(),
-                ),
-            )?;
-            write_duration(&mut w, result.duration())?;
-            w.finish()?;
+        if !self.is_quiet() {
+            write!(w, "[")?;
+            {
+                let mut w = ui::colored(
+                    &mut w,
+                    duration_color(
+                        result
+                            .duration()
+                            .checked_div(result.run() as u32)
+                            .unwrap_or_default(),
+                    ),
+                )?;
+                write_duration(&mut w, result.duration())?;
+                w.finish()?;
+            }
+            write!(w, "] ")?;
         }
-        write!(w, "] ")?;
 
-        cwrite!(bold(w), "{}", result.run())?;
-        write!(w, "/")?;
-        cwrite!(bold(w), "{}", result.expected())?;
-        write!(w, " tests run: ")?;
-
-        if result.passed() == result.total() {
-            cwrite!(bold(w), "all {}", result.passed())?;
-            write!(w, " ")?;
-            cwrite!(colored(w, Color::Green), "passed")?;
-        } else if result.failed() == result.total() {
-            cwrite!(bold(w), "all {}", result.failed())?;
-            write!(w, " ")?;
-            cwrite!(colored(w, Color::Red), "failed")?;
-        } else {
-            cwrite!(bold(w), "{}", result.passed())?;
-            write!(w, " ")?;
-            cwrite!(colored(w, Color::Green), "passed")?;
+        write_counts(&mut w, result)?;
 
+        if result.skipped() != 0 {
             write!(w, ", ")?;
-            cwrite!(bold(w), "{}", result.failed())?;
+            cwrite!(bold(w), "{}", result.skipped())?;
             write!(w, " ")?;
-            cwrite!(colored(w, Color::Red), "failed")?;
+            cwrite!(colored(w, Color::Yellow), "skipped")?;
         }
 
-        if result.filtered() != 0 {
+        if result.warnings() != 0 {
             write!(w, ", ")?;
-            cwrite!(bold(w), "{}", result.filtered())?;
+            cwrite!(bold(w), "{}", result.warnings())?;
             write!(w, " ")?;
-            cwrite!(colored(w, Color::Yellow), "filtered")?;
+            cwrite!(
+                colored(w, Color::Yellow),
+                "{}",
+                Term::simple("warning").with(result.warnings())
+            )?;
         }
 
-        if result.skipped() != 0 {
-            write!(w, ", ")?;
-            cwrite!(bold(w), "{}", result.skipped())?;
-            write!(w, " ")?;
-            cwrite!(colored(w, Color::Yellow), "skipped")?;
+        writeln!(w)?;
+        drop(w);
+
+        if let Some(summary_by) = self.summary_by {
+            self.report_groups(result, summary_by)?;
         }
 
-        writeln!(w)?;
+        self.report_artifact_warnings(result)?;
 
         // TODO(tinger): Report failures, mean, and average time.
 
         Ok(())
     }
 
+    /// Reports the results of a test run grouped as given, one line per group.
+    fn report_groups(&self, result: &SuiteResult, summary_by: SummaryBy) -> io::Result<()> {
+        let groups = match summary_by {
+            SummaryBy::Module => result.modules(),
+        };
+
+        let width = groups.keys().map(|name| name.len()).max().unwrap_or(0);
+        for (name, group) in groups {
+            let color = match group.status() {
+                GroupStatus::Passed => Color::Green,
+                GroupStatus::Failed => Color::Red,
+                GroupStatus::Skipped => Color::Yellow,
+            };
+
+            let mut w = ui::annotated(
+                self.ui.stderr(),
+                group.status().name(),
+                color,
+                RUN_ANNOT_PADDING,
+            )?;
+
+            if !self.is_quiet() {
+                write!(w, "[")?;
+                {
+                    let mut w = ui::colored(&mut w, Color::Rgb(128, 128, 128))?;
+                    write_duration(&mut w, group.duration())?;
+                    w.finish()?;
+                }
+                write!(w, "] ")?;
+            }
+
+            cwrite!(bold(w), "{name:<width$}")?;
+            for (count, label, color) in [
+                (group.passed(), "passed", Color::Green),
+                (group.failed(), "failed", Color::Red),
+                (group.skipped(), "skipped", Color::Yellow),
+            ] {
+                write!(w, " ")?;
+                cwrite!(bold(w), "{count:>4}")?;
+                write!(w, " ")?;
+                cwrite!(colored(w, color), "{label}")?;
+            }
+            writeln!(w)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports the artifacts which could not be written during a test run.
+    fn report_artifact_warnings(&self, result: &SuiteResult) -> io::Result<()> {
+        let affected: Vec<_> = result
+            .results()
+            .iter()
+            .filter(|(_, test)| !test.artifact_warnings().is_empty())
+            .collect();
+
+        if affected.is_empty() {
+            return Ok(());
+        }
+
+        let mut w = self.ui.warn()?;
+        writeln!(
+            w,
+            "Couldn't write artifacts of {} {}",
+            affected.len(),
+            Term::simple("test").with(affected.len()),
+        )?;
+
+        for (id, test) in affected {
+            for warning in test.artifact_warnings() {
+                let path = warning.path();
+                let display = path.strip_prefix(self.project.root()).unwrap_or(path);
+
+                ui::write_test_id(&mut w, id)?;
+                write!(w, ": ")?;
+                cwrite!(colored(w, Color::Cyan), "{}", display.display())?;
+                writeln!(w, " ({})", warning.message())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reports an interim summary of an ongoing test run.
+    pub fn report_interim(&self, result: &SuiteResult) -> io::Result<()> {
+        let mut w = ui::annotated(self.ui.stderr(), "Interim", Color::Cyan, RUN_ANNOT_PADDING)?;
+
+        write!(w, "[")?;
+        {
+            let mut w = ui::colored(&mut w, Color::Rgb(128, 128, 128))?;
+            write_duration(&mut w, result.timestamp().elapsed())?;
+            w.finish()?;
+        }
+        write!(w, "] ")?;
+
+        write_counts(&mut w, result)?;
+        writeln!(w)?;
+
+        for (id, test) in result.results() {
+            if test.is_fail() {
+                write!(w, "failed: ")?;
+                ui::write_test_id(&mut w, id)?;
+                writeln!(w)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reports the command to update all given tests, if `copy` is `true` the
+    /// command is copied to the clipboard if possible, or printed to stdout
+    /// otherwise.
+    pub fn report_update_hint(&self, ids: &[&Id], copy: bool) -> io::Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let command = update_command(ids.iter().copied());
+
+        // NOTE(tinger): Stdout is reserved for progress events.
+        let copy = copy && self.format != OutputFormat::JsonLines;
+
+        if copy && !self.ui.can_live_report() {
+            writeln!(self.ui.stdout(), "{command}")?;
+            return Ok(());
+        }
+
+        if ids.len() > 1 {
+            let mut w = self.ui.hint()?;
+            write!(w, "Accept the new output of all failed tests using ")?;
+            cwrite!(colored(w, Color::Cyan), "{command}")?;
+            writeln!(w)?;
+        }
+
+        if copy {
+            ui::copy_to_clipboard(self.ui.stderr(), &command)?;
+            writeln!(
+                self.ui.hint()?,
+                "Copied the update command to the clipboard"
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports that a keyboard control was handled.
+    pub fn report_control(&self, message: &str) -> io::Result<()> {
+        let mut w = ui::annotated(self.ui.stderr(), "Control", Color::Cyan, RUN_ANNOT_PADDING)?;
+        writeln!(w, "{message}")?;
+
+        Ok(())
+    }
+
     /// Clears the last line, i.e the status output.
     pub fn clear_status(&self) -> io::Result<()> {
         if !self.live {
@@ -175,63 +415,76 @@ impl Reporter<'_, '_> {
         }
         write!(w, "] ")?;
 
-        cwrite!(bold(w), "{}", result.run())?;
-        write!(w, "/")?;
-        cwrite!(bold(w), "{}", result.expected())?;
-        write!(w, " tests run: ")?;
+        write_counts(&mut w, result)?;
 
-        if result.passed() == result.total() {
-            cwrite!(bold(w), "all {}", result.passed())?;
-            write!(w, " ")?;
-            cwrite!(colored(w, Color::Green), "passed")?;
-        } else if result.failed() == result.total() {
-            cwrite!(bold(w), "all {}", result.failed())?;
-            write!(w, " ")?;
-            cwrite!(colored(w, Color::Red), "failed")?;
-        } else {
-            cwrite!(bold(w), "{}", result.passed())?;
-            write!(w, " ")?;
-            cwrite!(colored(w, Color::Green), "passed")?;
+        writeln!(w)?;
 
-            write!(w, ", ")?;
-            cwrite!(bold(w), "{}", result.failed())?;
-            write!(w, " ")?;
-            cwrite!(colored(w, Color::Red), "failed")?;
-        }
+        Ok(())
+    }
 
-        if result.filtered() != 0 {
-            write!(w, ", ")?;
-            cwrite!(bold(w), "{}", result.filtered())?;
-            write!(w, " ")?;
-            cwrite!(colored(w, Color::Yellow), "filtered")?;
+    /// Reports the start of a single test.
+    pub fn report_test_start(&self, test: &Test) -> eyre::Result<()> {
+        if let Some(progress) = &self.progress {
+            progress.test_start(test.id())?;
         }
 
-        writeln!(w)?;
-
         Ok(())
     }
 
     /// Report a test result and show supplementary information.
     pub fn report_test_result(&self, test: &Test, result: &TestResult) -> eyre::Result<()> {
+        if let Some(progress) = &self.progress {
+            progress.test(self.project, self.world, test, result)?;
+        }
+
+        if self.is_quiet() && !result.is_fail() {
+            return Ok(());
+        }
+
+        if !self.passes
+            && result.is_pass()
+            && !matches!(result.stage(), Stage::ExpectedFailure { .. })
+            && result.warnings().is_empty()
+        {
+            return Ok(());
+        }
+
         let (annot, color) = match result.stage() {
             Stage::Skipped => ("skip", Color::Yellow),
             Stage::Filtered => ("filter", Color::Yellow),
-            Stage::FailedCompilation { .. } | Stage::FailedComparison(_) => ("fail", Color::Red),
+            Stage::FailedCompilation { .. }
+            | Stage::FailedComparison(_)
+            | Stage::FailedRendering { .. } => ("fail", Color::Red),
+            Stage::TimedOut { .. } => ("timeout", Color::Red),
+            Stage::ExpectedFailure { .. } => ("xfail", Color::Yellow),
+            Stage::UnexpectedPass { .. } => ("xpass", Color::Red),
             Stage::PassedCompilation => ("compile", Color::Green),
             Stage::PassedComparison => ("pass", Color::Green),
             Stage::Updated { .. } => ("update", Color::Green),
+            Stage::Cancelled => ("cancel", Color::Yellow),
         };
 
         let mut w = ui::annotated(self.ui.stderr(), annot, color, RUN_ANNOT_PADDING)?;
 
-        write!(w, "[")?;
-        {
-            let mut w = ui::colored(&mut w, duration_color(result.duration()))?;
-            write_duration(&mut w, result.duration())?;
-            w.finish()?;
+        if !self.is_quiet() {
+            write!(w, "[")?;
+            {
+                let mut w = ui::colored(&mut w, duration_color(result.duration()))?;
+                write_duration(&mut w, result.duration())?;
+                w.finish()?;
+            }
+            write!(w, "] ")?;
+        }
+
+        match test {
+            Test::Unit(_) if self.is_quiet() => ui::write_test_id(&mut w, test.id())?,
+            Test::Unit(unit) => {
+                ui::write_file_link(&mut w, &self.project.unit_test_dir(unit.id()), |w| {
+                    ui::write_test_id(w, test.id())
+                })?
+            }
+            Test::Template(_) => ui::write_test_id(&mut w, test.id())?,
         }
-        write!(w, "] ")?;
-        ui::write_test_id(&mut w, test.id())?;
         writeln!(w)?;
 
         ui::write_diagnostics(
@@ -242,6 +495,17 @@ impl Reporter<'_, '_> {
             result.errors().unwrap_or_default(),
         )?;
 
+        if self.notes && result.is_fail() {
+            if let Some(notes) = test
+                .as_unit_test()
+                .map(|unit| unit.load_notes(self.project))
+                .transpose()?
+                .flatten()
+            {
+                cwrite!(italic(w), "{notes}\n")?;
+            }
+        }
+
         match result.stage() {
             Stage::PassedCompilation | Stage::PassedComparison => {}
             Stage::FailedCompilation { reference, .. } => {
@@ -251,41 +515,65 @@ impl Reporter<'_, '_> {
                     if *reference { "reference" } else { "test" },
                 )?;
             }
-            Stage::FailedComparison(compare::Error {
-                output,
-                reference,
-                pages,
-            }) => {
-                if output != reference {
-                    writeln!(
-                        w,
-                        "Expected {reference} {}, got {output} {}",
-                        Term::simple("page").with(*reference),
-                        Term::simple("page").with(*output),
-                    )?;
-                } else {
-                    for (p, e) in pages {
-                        let p = p + 1;
-                        match e {
-                            PageError::Dimensions { output, reference } => {
-                                writeln!(w, "Page {p} had different dimensions")?;
-                                w.write_with(2, |w| {
-                                    writeln!(w, "Output: {}", output)?;
-                                    writeln!(w, "Reference: {}", reference)
-                                })?;
-                            }
-                            PageError::SimpleDeviations { deviations } => {
-                                writeln!(
-                                    w,
-                                    "Page {p} had {deviations} {}",
-                                    Term::simple("deviation").with(*deviations),
-                                )?;
-                            }
-                        }
-                    }
+            Stage::FailedComparison(error) => {
+                write_compare_error(&mut w, error)?;
+                if !self.is_quiet() {
+                    write_density_grids(&mut w, error)?;
+                }
+
+                let diff_dir = self.project.unit_test_diff_dir(test.id());
+                if diff_dir.try_exists()? {
+                    write!(w, "Diff images at ")?;
+                    let display = diff_dir
+                        .strip_prefix(self.project.root())
+                        .unwrap_or(&diff_dir);
+
+                    ui::write_file_link(&mut w, &diff_dir, |mut w| {
+                        cwrite!(colored(w, Color::Cyan), "{}", display.display())
+                    })?;
+                    writeln!(w)?;
                 }
+
+                if let Some(protocol) = self.preview {
+                    self.write_preview(&mut w, protocol, test.id(), error)?;
+                }
+
+                if test
+                    .as_unit_test()
+                    .is_some_and(|unit| unit.kind().is_persistent())
+                {
+                    write!(w, "Accept the new output using ")?;
+                    cwrite!(colored(w, Color::Cyan), "{}", update_command([test.id()]))?;
+                    writeln!(w)?;
+                }
+            }
+            Stage::FailedRendering { error, reference } => {
+                writeln!(
+                    w,
+                    "{} document is too large, {error}",
+                    if *reference { "Reference" } else { "Test" },
+                )?;
             }
-            Stage::Updated { .. } => {}
+            Stage::TimedOut { timeout, reference } => {
+                writeln!(
+                    w,
+                    "Compilation of {} timed out after {timeout:?}",
+                    if *reference { "reference" } else { "test" },
+                )?;
+            }
+            Stage::ExpectedFailure { reason, .. } => {
+                if let Some(reason) = reason {
+                    writeln!(w, "Failed as expected: {reason}")?;
+                }
+            }
+            Stage::UnexpectedPass { reason } => {
+                write!(w, "Test passed, but was expected to fail")?;
+                match reason {
+                    Some(reason) => writeln!(w, ": {reason}")?,
+                    None => writeln!(w)?,
+                }
+            }
+            Stage::Updated { .. } | Stage::Cancelled => {}
             _ => unreachable!(),
         }
 
@@ -293,7 +581,268 @@ impl Reporter<'_, '_> {
     }
 }
 
+impl Reporter<'_, '_> {
+    /// Writes a preview of the reference, output and diff images of the first
+    /// failing page of a test, images which are not on disk are left out.
+    fn write_preview<W: WriteColor>(
+        &self,
+        w: &mut Indented<W>,
+        protocol: ImageProtocol,
+        id: &Id,
+        error: &compare::Error,
+    ) -> io::Result<()> {
+        let page = preview_page(error);
+        let (labels, images) = load_preview(self.project, id, page);
+
+        if images.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(w, "Preview of page {page} ({})", labels.join(", "))?;
+        ui::write_image_preview(w, protocol, &images.iter().collect::<Vec<_>>())?;
+        writeln!(w)?;
+
+        Ok(())
+    }
+}
+
+/// Returns the 1-based number of the page to preview for a comparison failure,
+/// this is the first failing page.
+pub fn preview_page(error: &compare::Error) -> usize {
+    error.pages.first().map(|(idx, _)| *idx).unwrap_or(0) + 1
+}
+
+/// Loads the reference, output and diff images of the given page of a unit
+/// test together with their labels, images which are not on disk are left out.
+pub fn load_preview(project: &Project, id: &Id, page: usize) -> (Vec<&'static str>, Vec<Pixmap>) {
+    let dirs = [
+        ("reference", project.unit_test_ref_dir(id)),
+        ("output", project.unit_test_out_dir(id)),
+        ("diff", project.unit_test_diff_dir(id)),
+    ];
+
+    dirs.iter()
+        .filter_map(|(label, dir)| Some((*label, load_preview_page(dir, page)?)))
+        .unzip()
+}
+
+/// Loads a single page from the given directory for previews, SVG pages are
+/// rasterized using [`render::DEFAULT_PIXEL_PER_PT`].
+///
+/// Previews are best effort, pages which can't be loaded are ignored.
+fn load_preview_page(dir: &Path, page: usize) -> Option<Pixmap> {
+    Document::load_page(dir, page, render::DEFAULT_PIXEL_PER_PT)
+        .inspect_err(|error| tracing::debug!(?dir, page, %error, "couldn't load preview page"))
+        .ok()
+        .flatten()
+}
+
+/// Returns the command to update the given tests.
+pub fn update_command<'a, I>(ids: I) -> String
+where
+    I: IntoIterator<Item = &'a Id>,
+{
+    let mut command = String::from("tt update");
+    for id in ids {
+        command.push(' ');
+        command.push_str(&shell_quote(id.as_str()));
+    }
+
+    command
+}
+
+/// Quotes the given string for use as a single argument in POSIX shells,
+/// strings consisting only of characters without special meaning are returned
+/// unchanged.
+fn shell_quote(s: &str) -> Cow<'_, str> {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=,@+%".contains(c);
+
+    if !s.is_empty() && s.chars().all(is_plain) {
+        return Cow::Borrowed(s);
+    }
+
+    Cow::Owned(format!("'{}'", s.replace('\'', r"'\''")))
+}
+
+/// Writes the number of run, passed, failed and filtered tests.
+fn write_counts(mut w: &mut dyn WriteColor, result: &SuiteResult) -> io::Result<()> {
+    cwrite!(bold(w), "{}", result.run())?;
+    write!(w, "/")?;
+    cwrite!(bold(w), "{}", result.expected())?;
+    write!(w, " tests run: ")?;
+
+    if result.passed() == result.total() {
+        cwrite!(bold(w), "all {}", result.passed())?;
+        write!(w, " ")?;
+        cwrite!(colored(w, Color::Green), "passed")?;
+    } else if result.failed() == result.total() {
+        cwrite!(bold(w), "all {}", result.failed())?;
+        write!(w, " ")?;
+        cwrite!(colored(w, Color::Red), "failed")?;
+    } else {
+        cwrite!(bold(w), "{}", result.passed())?;
+        write!(w, " ")?;
+        cwrite!(colored(w, Color::Green), "passed")?;
+
+        write!(w, ", ")?;
+        cwrite!(bold(w), "{}", result.failed())?;
+        write!(w, " ")?;
+        cwrite!(colored(w, Color::Red), "failed")?;
+    }
+
+    if result.filtered() != 0 {
+        write!(w, ", ")?;
+        cwrite!(bold(w), "{}", result.filtered())?;
+        write!(w, " ")?;
+        cwrite!(colored(w, Color::Yellow), "filtered")?;
+    }
+
+    Ok(())
+}
+
 /// Writes a padded duration in human readable form
+/// Writes the page count or page errors of a comparison failure.
+pub fn write_compare_error<W: WriteColor>(
+    w: &mut Indented<W>,
+    error: &compare::Error,
+) -> io::Result<()> {
+    let compare::Error {
+        output,
+        reference,
+        pages,
+        ..
+    } = error;
+
+    if output != reference {
+        writeln!(
+            w,
+            "Expected {reference} {}, got {output} {}",
+            Term::simple("page").with(*reference),
+            Term::simple("page").with(*output),
+        )?;
+
+        return Ok(());
+    }
+
+    for (p, e) in pages {
+        let p = p + 1;
+        match e {
+            PageError::Dimensions { output, reference } => {
+                writeln!(w, "Page {p} had different dimensions")?;
+                w.write_with(2, |w| {
+                    writeln!(w, "Output: {}", output)?;
+                    writeln!(w, "Reference: {}", reference)
+                })?;
+            }
+            PageError::SimpleDeviations { deviations } => {
+                writeln!(
+                    w,
+                    "Page {p} had {deviations} {}",
+                    Term::simple("deviation").with(*deviations),
+                )?;
+            }
+            PageError::SampledDeviations {
+                deviations,
+                samples,
+                estimated,
+            } => {
+                writeln!(
+                    w,
+                    "Page {p} had about {estimated} {} ({deviations} of {samples} sampled pixels)",
+                    Term::simple("deviation").with(*estimated),
+                )?;
+            }
+            PageError::Ssim { ssim, threshold } => {
+                writeln!(
+                    w,
+                    "Page {p} had a structural similarity of {ssim:.4}, below {threshold}",
+                )?;
+            }
+            PageError::Text {
+                mismatches,
+                output,
+                reference,
+            } => {
+                writeln!(
+                    w,
+                    "Page {p} had {mismatches} differing text {}",
+                    Term::simple("run").with(*mismatches),
+                )?;
+                w.write_with(2, |w| {
+                    match output {
+                        Some(run) => writeln!(w, "Output: {run}")?,
+                        None => writeln!(w, "Output: no more text")?,
+                    }
+                    match reference {
+                        Some(run) => writeln!(w, "Reference: {run}"),
+                        None => writeln!(w, "Reference: no more text"),
+                    }
+                })?;
+            }
+            PageError::Fonts {
+                missing,
+                unexpected,
+            } => {
+                writeln!(w, "Page {p} used different fonts")?;
+                w.write_with(2, |w| {
+                    if !missing.is_empty() {
+                        writeln!(w, "Missing: {}", missing.join(", "))?;
+                    }
+                    if !unexpected.is_empty() {
+                        writeln!(w, "Unexpected: {}", unexpected.join(", "))?;
+                    }
+                    io::Result::Ok(())
+                })?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Writes a coarse heatmap of the deviation density of each failing page for
+/// which one is available.
+pub fn write_density_grids<W: WriteColor>(
+    mut w: &mut Indented<W>,
+    error: &compare::Error,
+) -> io::Result<()> {
+    for (p, grid) in &error.densities {
+        if grid.columns() == 0 || grid.rows() == 0 {
+            continue;
+        }
+
+        let border = "─".repeat(grid.columns() as usize);
+
+        writeln!(w, "Deviations on page {}", p + 1)?;
+        writeln!(w, "┌{border}┐")?;
+        for row in grid.row_densities() {
+            write!(w, "│")?;
+            for density in row {
+                match density_block(density) {
+                    Some((block, color)) => cwrite!(colored(w, color), "{block}")?,
+                    None => write!(w, " ")?,
+                }
+            }
+            writeln!(w, "│")?;
+        }
+        writeln!(w, "└{border}┘")?;
+    }
+
+    Ok(())
+}
+
+/// Returns the block character and color for a region with the given
+/// deviation density, or `None` if there are no deviations.
+fn density_block(density: f32) -> Option<(char, Color)> {
+    Some(match density {
+        0.0 => return None,
+        ..=0.01 => ('░', Color::Yellow),
+        ..=0.1 => ('▒', Color::Yellow),
+        ..=0.5 => ('▓', Color::Red),
+        _ => ('█', Color::Red),
+    })
+}
+
 fn write_duration(w: &mut dyn Write, duration: Duration) -> io::Result<()> {
     let s = duration.as_secs();
     let ms = duration.subsec_millis();
@@ -309,6 +858,15 @@ fn write_duration(w: &mut dyn Write, duration: Duration) -> io::Result<()> {
     Ok(())
 }
 
+/// Formats an estimated duration in human readable form, rounded to seconds.
+fn fmt_estimate(duration: Duration) -> String {
+    match duration.as_secs() {
+        0 => String::from("<1s"),
+        s @ 1..60 => format!("{s}s"),
+        s => format!("{}m {}s", s / 60, s % 60),
+    }
+}
+
 /// Returns the color to use for a test's duration.
 fn duration_color(duration: Duration) -> Color {
     match duration.as_secs() {
@@ -318,3 +876,25 @@ fn duration_color(duration: Duration) -> Color {
         _ => Color::Red,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("foo/bar-baz_1"), "foo/bar-baz_1");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("foo[a=1]"), "'foo[a=1]'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn test_update_command() {
+        let ids = [
+            Id::new("foo/bar").unwrap(),
+            Id::new("baz[size=a4]").unwrap(),
+        ];
+        assert_eq!(update_command(&ids), "tt update foo/bar 'baz[size=a4]'");
+    }
+}
@@ -1,35 +1,88 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::fmt::Debug;
-use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
+use codespan_reporting::term;
 use color_eyre::eyre;
 use color_eyre::eyre::ContextCompat;
 use color_eyre::eyre::WrapErr;
+use ecow::eco_format;
+use ecow::eco_vec;
+use ecow::EcoString;
+use termcolor::NoColor;
+use typst::diag::SourceDiagnostic;
 use typst::diag::Warned;
 use typst::layout::PagedDocument;
+use typst::syntax::package::PackageSpec;
 use typst::syntax::Source;
+use typst::syntax::Span;
+use tytanic_core::cancel::CancellationToken;
 use tytanic_core::config::Direction;
 use tytanic_core::doc::compare::Strategy;
+use tytanic_core::doc::compare::StrategyKind;
 use tytanic_core::doc::compile;
 use tytanic_core::doc::compile::Warnings;
+use tytanic_core::doc::pdf;
+use tytanic_core::doc::pdf::PDF_FILE;
 use tytanic_core::doc::render;
+use tytanic_core::doc::render::Alpha;
 use tytanic_core::doc::render::Origin;
+use tytanic_core::doc::text::TextLayer;
+use tytanic_core::doc::text::TEXT_FILE;
 use tytanic_core::doc::Document;
+use tytanic_core::doc::Limits;
+use tytanic_core::doc::SaveError;
+use tytanic_core::hook;
+use tytanic_core::hook::Hook;
 use tytanic_core::project::Project;
 use tytanic_core::suite::FilteredSuite;
 use tytanic_core::suite::SuiteResult;
 use tytanic_core::test::unit::Kind;
 use tytanic_core::test::Annotation;
+use tytanic_core::test::ArtifactWarning;
 use tytanic_core::test::Test;
 use tytanic_core::test::TestResult;
+use tytanic_core::Id;
 use tytanic_core::TemplateTest;
 use tytanic_core::UnitTest;
 
 use crate::cli::TestFailure;
+use crate::controls::Control;
+use crate::controls::Controls;
+use crate::crash;
+use crate::json::HeatmapJson;
+use crate::remote::RemoteCompilation;
+use crate::remote::RemoteOutcome;
+use crate::remote::RemoteTest;
+use crate::remote::WorkerPool;
 use crate::report::Reporter;
+use crate::ui;
 use crate::world::SystemWorld;
 use crate::DEFAULT_OPTIMIZE_OPTIONS;
 
+/// The name of the file in a test's output directory to which the diagnostics
+/// of failing tests are written.
+pub const DIAGNOSTICS_FILE: &str = "diagnostics.txt";
+
+/// The name of the file in a test's output directory which contains the hash of
+/// the test script the output was compiled from.
+pub const SOURCE_HASH_FILE: &str = "source-hash.txt";
+
+/// The name of the file in a test's difference directory to which the
+/// deviation heatmap is written, see `--diff-heatmap`.
+pub const HEATMAP_FILE: &str = "heatmap.json";
+
+/// Returns the hash of the current test script of the given test.
+pub fn source_hash(project: &Project, test: &UnitTest) -> io::Result<String> {
+    let source = std::fs::read(project.unit_test_script(test.id()))?;
+    Ok(format!("{:032x}", typst::utils::hash128(&source)))
+}
+
 #[derive(Debug, Clone)]
 pub enum Action {
     /// Compile and optionally compare tests.
@@ -56,6 +109,12 @@ pub struct RunnerConfig<'c> {
     /// The pixel-per-pt to use when rendering documents.
     pub pixel_per_pt: f32,
 
+    /// How transparent pixels are handled for diff images.
+    pub alpha: Alpha,
+
+    /// The limits on the size of rendered documents.
+    pub limits: Limits,
+
     /// The strategy to use when comparing documents.
     pub strategy: Option<Strategy>,
 
@@ -65,17 +124,47 @@ pub struct RunnerConfig<'c> {
     /// The origin at which to render diff images of different dimensions.
     pub origin: Origin,
 
+    /// The tile size in pixels at which to export deviation heatmaps next to
+    /// diff images, if at all.
+    pub heatmap: Option<u32>,
+
+    /// Whether to export output documents to PDF.
+    pub export_pdf: bool,
+
     /// The action to take for the test.
     pub action: Action,
 
-    /// A cancellation flag used to abort a test run.
-    pub cancellation: &'c AtomicBool,
+    /// A cancellation token used to abort a test run.
+    pub cancellation: &'c CancellationToken,
+
+    /// The time after which the compilation of a single test is aborted, if
+    /// any, this may be overridden by test annotations.
+    pub timeout: Option<Duration>,
+
+    /// The run-level metadata to attach to the suite result.
+    pub meta: BTreeMap<EcoString, EcoString>,
+
+    /// The keyboard controls to handle between tests, if any.
+    pub controls: Option<&'c Controls>,
+
+    /// The package spec under which the project is installed, if unit tests
+    /// should be compiled against the installed package.
+    pub installed: Option<PackageSpec>,
+
+    /// The remote workers to run unit tests on, if any.
+    pub remote: Option<&'c WorkerPool>,
+
+    /// Where to record the project files accessed by each test, if at all.
+    pub dependencies: Option<&'c RefCell<Dependencies>>,
 }
 
+/// The project files accessed by each test in its last run.
+pub type Dependencies = BTreeMap<Id, Vec<PathBuf>>;
+
 pub struct Runner<'c, 'p> {
     pub project: &'p Project,
     pub suite: &'p FilteredSuite,
-    pub world: &'p SystemWorld,
+    pub world: &'p Arc<SystemWorld>,
 
     pub result: SuiteResult,
     pub config: RunnerConfig<'c>,
@@ -85,12 +174,34 @@ impl<'c, 'p> Runner<'c, 'p> {
     pub fn new(
         project: &'p Project,
         suite: &'p FilteredSuite,
-        world: &'p SystemWorld,
+        world: &'p Arc<SystemWorld>,
         config: RunnerConfig<'c>,
     ) -> Self {
+        let mut result = SuiteResult::new(suite);
+        match config.strategy {
+            Some(Strategy::Simple {
+                color_space,
+                ignore_antialiasing,
+                ..
+            }) => {
+                result.set_meta("color-space", color_space.name());
+                if ignore_antialiasing {
+                    result.set_meta("ignore-antialiasing", "true");
+                }
+            }
+            Some(Strategy::Ssim { threshold, .. }) => {
+                result.set_meta("compare-strategy", StrategyKind::Ssim.name());
+                result.set_meta("threshold", threshold.to_string());
+            }
+            None => {}
+        }
+        for (key, val) in &config.meta {
+            result.set_meta(key.clone(), val.clone());
+        }
+
         Self {
             project,
-            result: SuiteResult::new(suite),
+            result,
             suite,
             world,
             config,
@@ -105,6 +216,18 @@ impl<'c, 'p> Runner<'c, 'p> {
         }
     }
 
+    /// The cancellation token for a single compilation, this is additionally
+    /// cancelled once the given timeout has passed.
+    fn cancellation(&self, timeout: Option<Duration>) -> CancellationToken {
+        match timeout {
+            Some(timeout) => self
+                .config
+                .cancellation
+                .with_deadline(Instant::now() + timeout),
+            None => self.config.cancellation.clone(),
+        }
+    }
+
     pub fn template_test<'s>(&'s self, test: &'p TemplateTest) -> TemplateTestRunner<'c, 's, 'p> {
         TemplateTestRunner {
             project_runner: self,
@@ -117,15 +240,29 @@ impl<'c, 'p> Runner<'c, 'p> {
         reporter.report_status(&self.result)?;
 
         for test in self.suite.matched() {
-            if self.config.cancellation.load(Ordering::SeqCst) {
+            self.handle_controls(reporter)?;
+
+            if self.config.cancellation.is_cancelled() {
                 return Ok(());
             }
 
+            if self.config.dependencies.is_some() {
+                self.world.reset();
+            }
+
+            reporter.report_test_start(test)?;
+
             let result = match test {
                 Test::Unit(test) => self.unit_test(test).run()?,
                 Test::Template(test) => self.template_test(test).run()?,
             };
 
+            if let Some(dependencies) = self.config.dependencies {
+                dependencies
+                    .borrow_mut()
+                    .insert(test.id().clone(), self.world.dependencies());
+            }
+
             reporter.clear_status()?;
 
             // TODO(tinger): Retrieve export var from action.
@@ -146,11 +283,75 @@ impl<'c, 'p> Runner<'c, 'p> {
         Ok(())
     }
 
+    /// Handles all pending keyboard controls, blocks while the run is paused.
+    fn handle_controls(&mut self, reporter: &Reporter) -> eyre::Result<()> {
+        let Some(controls) = self.config.controls else {
+            return Ok(());
+        };
+
+        let mut paused = false;
+
+        loop {
+            if self.config.cancellation.is_cancelled() {
+                return Ok(());
+            }
+
+            let control = if paused {
+                controls.wait(Duration::from_millis(100))?
+            } else {
+                controls.poll()?
+            };
+
+            let message = match control {
+                None if paused => continue,
+                None => return Ok(()),
+                Some(Control::ToggleFailFast) => {
+                    self.config.fail_fast = !self.config.fail_fast;
+                    if self.config.fail_fast {
+                        "fail-fast enabled"
+                    } else {
+                        "fail-fast disabled"
+                    }
+                }
+                Some(Control::TogglePause) => {
+                    paused = !paused;
+                    if paused {
+                        "paused, press p to resume"
+                    } else {
+                        "resumed"
+                    }
+                }
+                Some(Control::Summary) => {
+                    reporter.clear_status()?;
+                    reporter.report_interim(&self.result)?;
+                    reporter.report_status(&self.result)?;
+                    continue;
+                }
+                Some(Control::Cancel) => {
+                    self.config.cancellation.cancel();
+                    "canceled"
+                }
+            };
+
+            reporter.clear_status()?;
+            reporter.report_control(message)?;
+            reporter.report_status(&self.result)?;
+        }
+    }
+
     #[tracing::instrument(skip_all)]
     pub fn run(mut self, reporter: &Reporter) -> eyre::Result<SuiteResult> {
         self.result.start();
+        crash::set_run_id(self.result.id());
+        crash::debug_trigger()?;
         reporter.report_start(&self.result)?;
-        let res = self.run_inner(reporter);
+        let res = hook::run(self.project, Hook::Prepare, None)
+            .map_err(eyre::Report::from)
+            .and_then(|_| self.run_inner(reporter));
+
+        // NOTE(tinger): The cleanup hook is run even if the run failed, it
+        // likely undoes what the prepare hook did.
+        let res = res.and(hook::run(self.project, Hook::Cleanup, None).map_err(Into::into));
         self.result.end();
         reporter.report_end(&self.result)?;
 
@@ -176,9 +377,19 @@ impl UnitTestRunner<'_, '_, '_> {
 
         match self.project_runner.config.action {
             Action::Run => {
-                let output = self.load_out_src()?;
-                let output = self.compile_out_doc(output)?;
-                let output = self.render_out_doc(output)?;
+                let (remote_output, remote_reference) = self
+                    .take_remote()?
+                    .map(|remote| (remote.output, remote.reference))
+                    .unzip();
+
+                let output = match remote_output {
+                    Some(output) => self.load_remote_doc(output, false)?,
+                    None => {
+                        let output = self.load_out_src()?;
+                        let output = self.compile_out_doc(output)?;
+                        self.render_out_doc(output)?
+                    }
+                };
 
                 if export {
                     self.export_out_doc(&output)?;
@@ -186,15 +397,21 @@ impl UnitTestRunner<'_, '_, '_> {
 
                 match self.test.kind() {
                     Kind::Ephemeral => {
-                        let reference = self.load_ref_src()?;
-                        let reference = self.compile_ref_doc(reference)?;
-                        let reference = self.render_ref_doc(reference)?;
+                        let reference = match remote_reference.flatten() {
+                            Some(reference) => self.load_remote_doc(reference, true)?,
+                            None => {
+                                let reference = self.load_ref_src()?;
+                                let reference = self.compile_ref_doc(reference)?;
+                                self.render_ref_doc(reference)?
+                            }
+                        };
 
                         if export {
                             self.export_ref_doc(&reference)?;
 
                             let diff = self.render_diff_doc(&output, &reference, origin)?;
                             self.export_diff_doc(&diff)?;
+                            self.export_heatmap(&output, &reference)?;
                         }
 
                         if let Some(strategy) = strategy {
@@ -203,16 +420,20 @@ impl UnitTestRunner<'_, '_, '_> {
                             }
                         }
                     }
+                    Kind::Persistent if !export => {
+                        if let Some(strategy) = strategy {
+                            self.compare_ref(&output, strategy)?;
+                        }
+                    }
                     Kind::Persistent => {
                         let reference = self.load_ref_doc()?;
 
                         // TODO(tinger): Don't unconditionally export this
                         // perhaps? On the other hand without comparison we
                         // don't know whether this is meaningful or not.
-                        if export {
-                            let diff = self.render_diff_doc(&output, &reference, origin)?;
-                            self.export_diff_doc(&diff)?;
-                        }
+                        let diff = self.render_diff_doc(&output, &reference, origin)?;
+                        self.export_diff_doc(&diff)?;
+                        self.export_heatmap(&output, &reference)?;
 
                         if let Some(strategy) = strategy {
                             if let Err(err) = self.compare(&output, &reference, strategy) {
@@ -230,11 +451,19 @@ impl UnitTestRunner<'_, '_, '_> {
                     let output = self.compile_out_doc(output)?;
                     let output = self.render_out_doc(output)?;
 
-                    let needs_update = force || {
-                        let reference = self.load_ref_doc()?;
-                        let strategy = strategy.unwrap_or_default();
-                        self.compare(&output, &reference, strategy).is_err()
-                    };
+                    // NOTE(tinger): New instances of parametrized tests
+                    // don't have any references yet.
+                    let needs_update = force
+                        || !self
+                            .project_runner
+                            .project
+                            .unit_test_ref_dir(self.test.id())
+                            .try_exists()?
+                        || match self.compare_ref(&output, strategy.unwrap_or_default()) {
+                            Ok(()) => false,
+                            Err(err) if err.chain().any(|s| s.is::<TestFailure>()) => true,
+                            Err(err) => return Err(err),
+                        };
 
                     if needs_update {
                         self.test.create_reference_document(
@@ -255,6 +484,7 @@ impl UnitTestRunner<'_, '_, '_> {
 
                         let diff = self.render_diff_doc(&output, &reference, origin)?;
                         self.export_diff_doc(&diff)?;
+                        self.export_heatmap(&output, &reference)?;
                     }
                 }
                 Kind::CompileOnly => eyre::bail!("attempted to update compile-only test"),
@@ -278,24 +508,123 @@ impl UnitTestRunner<'_, '_, '_> {
             }
         }
 
+        self.apply_xfail();
+
         Ok(self.result)
     }
 
+    /// Turns the result of a test which is expected to fail into an expected
+    /// failure or unexpected pass.
+    fn apply_xfail(&mut self) {
+        if matches!(self.project_runner.config.action, Action::Update { .. }) {
+            return;
+        }
+
+        let Some(reason) = self
+            .test
+            .annotations()
+            .iter()
+            .find_map(|annot| match annot {
+                Annotation::XFail(reason) => Some(reason.clone()),
+                _ => None,
+            })
+        else {
+            return;
+        };
+
+        if self.result.is_fail() {
+            self.result.set_expected_failure(reason);
+        } else if self.result.is_pass() {
+            self.result.set_unexpected_pass(reason);
+        }
+    }
+
     pub fn prepare(&mut self) -> eyre::Result<()> {
+        hook::run(
+            self.project_runner.project,
+            Hook::PrepareEach,
+            Some(self.test),
+        )?;
+
         tracing::trace!(test = ?self.test.id(), "clearing temporary directories");
 
         if self.project_runner.config.export_ephemeral {
-            self.test
-                .create_temporary_directories(self.project_runner.project)?;
+            // NOTE(tinger): Failing to create these is not fatal, the exports
+            // into them will fail too and attach a warning with their path.
+            if let Err(err) = self
+                .test
+                .create_temporary_directories(self.project_runner.project)
+            {
+                tracing::warn!(test = ?self.test.id(), %err, "couldn't create temporary directories");
+            }
         }
 
         Ok(())
     }
 
     pub fn cleanup(&mut self) -> eyre::Result<()> {
+        self.export_diagnostics()?;
+
+        hook::run(
+            self.project_runner.project,
+            Hook::CleanupEach,
+            Some(self.test),
+        )?;
+
         Ok(())
     }
 
+    /// Writes the diagnostics of a failing test into its output directory,
+    /// removes stale diagnostics of a previous run otherwise.
+    pub fn export_diagnostics(&mut self) -> eyre::Result<()> {
+        let dir = self
+            .project_runner
+            .project
+            .unit_test_out_dir(self.test.id());
+        let path = dir.join(DIAGNOSTICS_FILE);
+
+        let errors = self.result.errors().unwrap_or_default();
+        if !self.result.is_fail() || (errors.is_empty() && self.result.warnings().is_empty()) {
+            if let Err(err) = tytanic_utils::fs::remove_file(&path) {
+                self.warn_artifact(path, err);
+            }
+            return Ok(());
+        }
+
+        tracing::trace!(test = ?self.test.id(), "saving diagnostics");
+
+        let mut w = NoColor::new(vec![]);
+        ui::write_diagnostics(
+            &mut w,
+            &term::Config::default(),
+            self.project_runner.world,
+            self.result.warnings(),
+            errors,
+        )?;
+
+        if let Err(err) = tytanic_utils::fs::create_dir(&dir, true)
+            .and_then(|_| std::fs::write(&path, w.into_inner()))
+        {
+            self.warn_artifact(path, err);
+        }
+
+        Ok(())
+    }
+
+    /// Attaches a warning about an artifact which couldn't be written to the
+    /// test result, these don't abort the test run.
+    fn warn_artifact<E>(&mut self, path: PathBuf, err: E)
+    where
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        tracing::warn!(test = ?self.test.id(), ?path, %err, "couldn't write artifact");
+
+        self.result.add_artifact_warning(ArtifactWarning::new(
+            path,
+            format!("{:#}", eyre::Report::new(err)),
+        ));
+    }
+
     pub fn load_out_src(&mut self) -> eyre::Result<Source> {
         tracing::trace!(test = ?self.test.id(), "loading output source");
         Ok(self.test.load_source(self.project_runner.project)?)
@@ -320,8 +649,9 @@ impl UnitTestRunner<'_, '_, '_> {
             eyre::bail!("attempted to load reference source for non-persistent test");
         }
 
+        let pixel_per_pt = self.pixel_per_pt();
         self.test
-            .load_reference_document(self.project_runner.project)
+            .load_reference_document_with(self.project_runner.project, pixel_per_pt)
             .wrap_err_with(|| {
                 format!(
                     "couldn't load reference document for test {}",
@@ -330,17 +660,79 @@ impl UnitTestRunner<'_, '_, '_> {
             })
     }
 
-    pub fn render_out_doc(&mut self, doc: PagedDocument) -> eyre::Result<Document> {
-        tracing::trace!(test = ?self.test.id(), "rendering output document");
+    /// Waits for the result of this test on the remote workers, if any.
+    pub fn take_remote(&mut self) -> eyre::Result<Option<RemoteTest>> {
+        let Some(pool) = self.project_runner.config.remote else {
+            return Ok(None);
+        };
 
-        let mut pixel_per_pt = self.project_runner.config.pixel_per_pt;
-        for annot in self.test.annotations().iter() {
-            if let Annotation::Ppi(ppi) = annot {
-                pixel_per_pt = render::ppi_to_ppp(*ppi)
+        tracing::trace!(test = ?self.test.id(), "waiting for remote result");
+
+        match pool.take(self.test.id()) {
+            Ok(RemoteOutcome::Done(remote)) => Ok(Some(*remote)),
+            Ok(RemoteOutcome::TimedOut(timeout)) => {
+                self.result.set_timed_out(timeout, false);
+                eyre::bail!(TestFailure);
+            }
+            Ok(RemoteOutcome::Failed(message)) => {
+                self.result
+                    .set_failed_test_compilation(compile::Error(eco_vec![
+                        SourceDiagnostic::error(
+                            Span::detached(),
+                            eco_format!("failed on remote worker: {message}"),
+                        )
+                    ]));
+                eyre::bail!(TestFailure);
             }
+            Err(_) if self.project_runner.config.cancellation.is_cancelled() => {
+                self.result.set_cancelled();
+                eyre::bail!(TestFailure);
+            }
+            Err(err) => Err(err),
         }
+    }
 
-        Ok(Document::render(doc, pixel_per_pt))
+    /// Loads a document which was compiled and rendered on a remote worker.
+    pub fn load_remote_doc(
+        &mut self,
+        mut remote: RemoteCompilation,
+        is_reference: bool,
+    ) -> eyre::Result<Document> {
+        tracing::trace!(test = ?self.test.id(), is_reference, "loading remote document");
+
+        let too_large = remote.too_large.take();
+        let (warnings, output) = remote.into_document()?;
+        self.result.set_warnings(warnings);
+
+        if let Some(err) = too_large {
+            self.result.set_failed_rendering(err, is_reference);
+            eyre::bail!(TestFailure);
+        }
+
+        match output {
+            Ok(doc) => {
+                if !is_reference {
+                    self.result.set_pages(doc.buffers().len());
+                }
+                self.result.set_passed_compilation();
+                Ok(doc)
+            }
+            Err(err) => {
+                if is_reference {
+                    self.result.set_failed_reference_compilation(err);
+                } else {
+                    self.result.set_failed_test_compilation(err);
+                }
+                eyre::bail!(TestFailure);
+            }
+        }
+    }
+
+    pub fn render_out_doc(&mut self, doc: PagedDocument) -> eyre::Result<Document> {
+        tracing::trace!(test = ?self.test.id(), "rendering output document");
+
+        self.result.set_pages(doc.pages.len());
+        self.render_inner(doc, false)
     }
 
     pub fn render_ref_doc(&mut self, doc: PagedDocument) -> eyre::Result<Document> {
@@ -350,6 +742,12 @@ impl UnitTestRunner<'_, '_, '_> {
             eyre::bail!("attempted to render reference for non-ephemeral test");
         }
 
+        self.render_inner(doc, true)
+    }
+
+    /// The pixel per pt ratio used for this test, this is overridden by its
+    /// `ppi` annotation.
+    fn pixel_per_pt(&self) -> f32 {
         let mut pixel_per_pt = self.project_runner.config.pixel_per_pt;
         for annot in self.test.annotations().iter() {
             if let Annotation::Ppi(ppi) = annot {
@@ -357,6 +755,17 @@ impl UnitTestRunner<'_, '_, '_> {
             }
         }
 
+        pixel_per_pt
+    }
+
+    fn render_inner(&mut self, doc: PagedDocument, is_reference: bool) -> eyre::Result<Document> {
+        let pixel_per_pt = self.pixel_per_pt();
+
+        if let Err(err) = self.project_runner.config.limits.check(&doc, pixel_per_pt) {
+            self.result.set_failed_rendering(err, is_reference);
+            eyre::bail!(TestFailure);
+        }
+
         Ok(Document::render(doc, pixel_per_pt))
     }
 
@@ -380,7 +789,12 @@ impl UnitTestRunner<'_, '_, '_> {
             }
         }
 
-        Ok(Document::render_diff(reference, output, origin))
+        Ok(Document::render_diff(
+            reference,
+            output,
+            origin,
+            self.project_runner.config.alpha,
+        ))
     }
 
     pub fn compile_out_doc(&mut self, output: Source) -> eyre::Result<PagedDocument> {
@@ -399,17 +813,59 @@ impl UnitTestRunner<'_, '_, '_> {
         self.compile_inner(reference, true)
     }
 
+    /// The timeout of this test, annotations take precedence over the config.
+    fn timeout(&self) -> Option<Duration> {
+        self.test.timeout().or(self.project_runner.config.timeout)
+    }
+
     fn compile_inner(&mut self, source: Source, is_reference: bool) -> eyre::Result<PagedDocument> {
-        let Warned { output, warnings } = compile::compile(
+        let timeout = self.timeout();
+        let cancellation = self.project_runner.cancellation(timeout);
+
+        let inputs = self.test.inputs();
+        let installed = self.project_runner.config.installed.clone().map(|package| {
+            (
+                package,
+                self.project_runner
+                    .project
+                    .config()
+                    .unit_tests_root
+                    .clone()
+                    .into(),
+            )
+        });
+
+        let compiled = compile::compile_detached(
             source,
-            self.project_runner.world,
+            Arc::clone(self.project_runner.world),
             self.project_runner.config.warnings,
+            cancellation.clone(),
             // NOTE(tinger): We only use augmentation here because package
             // rerouting should not happen for unit tests.
-            |w| w.augment_standard_library(true),
+            move |w| {
+                w.augment_standard_library(true)
+                    .inputs(inputs)
+                    .installed_package(installed)
+            },
         );
 
-        self.result.set_warnings(warnings);
+        if let Some(Warned { warnings, .. }) = &compiled {
+            self.result.set_warnings(warnings.clone());
+        }
+
+        if self.project_runner.config.cancellation.is_cancelled() {
+            self.result.set_cancelled();
+            eyre::bail!(TestFailure);
+        }
+
+        if let Some(timeout) = timeout.filter(|_| cancellation.is_expired()) {
+            self.result.set_timed_out(timeout, is_reference);
+            eyre::bail!(TestFailure);
+        }
+
+        let Some(Warned { output, .. }) = compiled else {
+            unreachable!("compilations are only abandoned once cancelled");
+        };
 
         let doc = match output {
             Ok(doc) => {
@@ -436,12 +892,14 @@ impl UnitTestRunner<'_, '_, '_> {
             eyre::bail!("attempted to save reference document for non-ephemeral test");
         }
 
-        reference.save(
-            self.project_runner
-                .project
-                .unit_test_ref_dir(self.test.id()),
-            None,
-        )?;
+        let dir = self
+            .project_runner
+            .project
+            .unit_test_ref_dir(self.test.id());
+
+        if let Err(err) = reference.save(&dir, None) {
+            self.warn_artifact(dir, err);
+        }
 
         Ok(())
     }
@@ -449,12 +907,41 @@ impl UnitTestRunner<'_, '_, '_> {
     pub fn export_out_doc(&mut self, output: &Document) -> eyre::Result<()> {
         tracing::trace!(test = ?self.test.id(), "saving output document");
 
-        output.save(
-            self.project_runner
-                .project
-                .unit_test_out_dir(self.test.id()),
-            None,
-        )?;
+        let dir = self
+            .project_runner
+            .project
+            .unit_test_out_dir(self.test.id());
+
+        if let Err(err) = output.save(&dir, None) {
+            self.warn_artifact(dir, err);
+            return Ok(());
+        }
+
+        // NOTE(tinger): Approved output must contain the text layer if the
+        // test compares it.
+        if self.test.text_tolerance().is_some() {
+            if let Err(err) = output.save_text(&dir) {
+                self.warn_artifact(dir.join(TEXT_FILE), err);
+            }
+        }
+
+        // NOTE(tinger): Remote output is never compiled in memory and can't
+        // be exported, the text layer is all that's needed to compare it.
+        if self.project_runner.config.export_pdf || self.test.compares_pdf() {
+            match output.save_pdf(&dir) {
+                Ok(()) | Err(SaveError::NoDocument) => {}
+                Err(err) => self.warn_artifact(dir.join(PDF_FILE), err),
+            }
+        }
+
+        // NOTE(tinger): This allows approving the output later on without
+        // recompiling it.
+        let path = dir.join(SOURCE_HASH_FILE);
+        if let Err(err) = source_hash(self.project_runner.project, self.test)
+            .and_then(|hash| std::fs::write(&path, hash))
+        {
+            self.warn_artifact(path, err);
+        }
 
         Ok(())
     }
@@ -466,12 +953,83 @@ impl UnitTestRunner<'_, '_, '_> {
             eyre::bail!("attempted to save difference document for compile-only test");
         }
 
-        doc.save(
-            self.project_runner
-                .project
-                .unit_test_diff_dir(self.test.id()),
-            None,
-        )?;
+        let dir = self
+            .project_runner
+            .project
+            .unit_test_diff_dir(self.test.id());
+
+        if let Err(err) = doc.save(&dir, None) {
+            self.warn_artifact(dir, err);
+        }
+
+        Ok(())
+    }
+
+    /// Applies the comparison annotations of this test to the given strategy.
+    fn resolve_strategy(&self, mut strategy: Strategy) -> Strategy {
+        for annot in self.test.annotations().iter() {
+            if let Annotation::Ssim(threshold) = annot {
+                strategy = Strategy::Ssim {
+                    threshold: threshold
+                        .or(match strategy {
+                            Strategy::Ssim { threshold, .. } => Some(threshold),
+                            _ => None,
+                        })
+                        .unwrap_or(self.project_runner.project.config().defaults.threshold),
+                    max_size_delta: strategy.max_size_delta(),
+                    alpha: strategy.alpha(),
+                };
+            }
+        }
+
+        for annot in self.test.annotations().iter() {
+            match (annot, &mut strategy) {
+                (Annotation::MaxDelta(set), Strategy::Simple { max_delta, .. }) => {
+                    *max_delta = *set
+                }
+                (Annotation::MaxDeviations(set), Strategy::Simple { max_deviation, .. }) => {
+                    *max_deviation = *set
+                }
+                (Annotation::Sample(set), Strategy::Simple { sample, .. }) => *sample = Some(*set),
+                (
+                    Annotation::MaxSizeDelta(set),
+                    Strategy::Simple { max_size_delta, .. } | Strategy::Ssim { max_size_delta, .. },
+                ) => *max_size_delta = *set,
+                _ => {}
+            }
+        }
+
+        strategy
+    }
+
+    /// Writes the deviation heatmap of the given documents into the
+    /// difference directory if requested, removes a stale heatmap of a
+    /// previous run otherwise.
+    pub fn export_heatmap(&mut self, output: &Document, reference: &Document) -> eyre::Result<()> {
+        let path = self
+            .project_runner
+            .project
+            .unit_test_diff_dir(self.test.id())
+            .join(HEATMAP_FILE);
+
+        let Some(tile_size) = self.project_runner.config.heatmap else {
+            if let Err(err) = tytanic_utils::fs::remove_file(&path) {
+                self.warn_artifact(path, err);
+            }
+            return Ok(());
+        };
+
+        tracing::trace!(test = ?self.test.id(), "saving deviation heatmap");
+
+        let strategy =
+            self.resolve_strategy(self.project_runner.config.strategy.unwrap_or_default());
+        let mask = self.test.load_mask(self.project_runner.project)?;
+        let heatmaps = Document::heatmaps(output, reference, strategy, &mask, tile_size);
+
+        let json = serde_json::to_vec_pretty(&HeatmapJson::new(tile_size, &heatmaps))?;
+        if let Err(err) = std::fs::write(&path, json) {
+            self.warn_artifact(path, err);
+        }
 
         Ok(())
     }
@@ -488,27 +1046,93 @@ impl UnitTestRunner<'_, '_, '_> {
             eyre::bail!("attempted to compare compile-only test");
         }
 
-        let Strategy::Simple {
-            mut max_delta,
-            mut max_deviation,
-        } = strategy;
+        if let Some(tolerance) = self.test.text_tolerance() {
+            return self.compare_text(output, reference, tolerance);
+        }
 
-        for annot in self.test.annotations().iter() {
-            match annot {
-                Annotation::MaxDelta(set) => max_delta = *set,
-                Annotation::MaxDeviations(set) => max_deviation = *set,
-                _ => {}
-            }
+        let strategy = self.resolve_strategy(strategy);
+        let mask = self.test.load_mask(self.project_runner.project)?;
+
+        if let Err(error) = Document::compare_masked(output, reference, strategy, &mask) {
+            self.result.set_failed_comparison(error);
+            eyre::bail!(TestFailure);
         }
 
-        if let Err(error) = Document::compare(
-            output,
-            reference,
-            Strategy::Simple {
-                max_delta,
-                max_deviation,
-            },
-        ) {
+        self.result.set_passed_comparison();
+
+        Ok(())
+    }
+
+    /// Compares the given document to the reference document on disk, the
+    /// reference pages are loaded one at a time if possible, see
+    /// [`Document::compare_pages`].
+    pub fn compare_ref(&mut self, output: &Document, strategy: Strategy) -> eyre::Result<()> {
+        let pages = self
+            .test
+            .load_reference_pages(self.project_runner.project)
+            .wrap_err_with(|| {
+                format!(
+                    "couldn't load reference document for test {}",
+                    self.test.id()
+                )
+            })?;
+
+        let Some(pages) = pages else {
+            let reference = self.load_ref_doc()?;
+            return self.compare(output, &reference, strategy);
+        };
+
+        tracing::trace!(test = ?self.test.id(), "comparing reference pages");
+
+        let strategy = self.resolve_strategy(strategy);
+        let mask = self.test.load_mask(self.project_runner.project)?;
+
+        let compared =
+            Document::compare_pages(output, pages, strategy, &mask).wrap_err_with(|| {
+                format!(
+                    "couldn't load reference document for test {}",
+                    self.test.id()
+                )
+            })?;
+
+        if let Err(error) = compared {
+            self.result.set_failed_comparison(error);
+            eyre::bail!(TestFailure);
+        }
+
+        self.result.set_passed_comparison();
+
+        Ok(())
+    }
+
+    /// Compares the text layers of the given documents, see
+    /// [`TextLayer::compare`], or their structure if the test compares PDFs,
+    /// see [`pdf::compare`].
+    pub fn compare_text(
+        &mut self,
+        output: &Document,
+        reference: &Document,
+        tolerance: f64,
+    ) -> eyre::Result<()> {
+        tracing::trace!(test = ?self.test.id(), "comparing text");
+
+        let output = output
+            .text_layer()
+            .context("output document has no text layer")?;
+        let reference = reference.text_layer().with_context(|| {
+            format!(
+                "reference document of test {} has no text layer, update it to store one",
+                self.test.id()
+            )
+        })?;
+
+        let compared = if self.test.compares_pdf() {
+            pdf::compare(&output, &reference, tolerance)
+        } else {
+            TextLayer::compare(&output, &reference, tolerance)
+        };
+
+        if let Err(error) = compared {
             self.result.set_failed_comparison(error);
             eyre::bail!(TestFailure);
         }
@@ -531,7 +1155,8 @@ impl TemplateTestRunner<'_, '_, '_> {
         match self.project_runner.config.action {
             Action::Run => {
                 let output = self.load_template_src()?;
-                let _output = self.compile_template(output)?;
+                let output = self.compile_template(output)?;
+                self.result.set_pages(output.pages.len());
 
                 // if export {
                 //     let output = self.render_template_doc(output)?;
@@ -574,23 +1199,48 @@ impl TemplateTestRunner<'_, '_, '_> {
     }
 
     pub fn compile_template(&mut self, source: Source) -> eyre::Result<PagedDocument> {
-        let Warned { output, warnings } = compile::compile(
+        let timeout = self.project_runner.config.timeout;
+        let cancellation = self.project_runner.cancellation(timeout);
+
+        // NOTE(tinger): If the package is installed it is resolved normally
+        // and must not be rerouted.
+        let package = self
+            .project_runner
+            .project
+            .package_spec()
+            .filter(|_| self.project_runner.config.installed.is_none());
+        let prefix = self
+            .project_runner
+            .project
+            .manifest()
+            .and_then(|m| m.template.as_ref())
+            .map(|t| t.path.as_str().into());
+
+        let compiled = compile::compile_detached(
             source,
-            self.project_runner.world,
+            Arc::clone(self.project_runner.world),
             self.project_runner.config.warnings,
-            |w| {
-                w.reroute_package(self.project_runner.project.package_spec())
-                    .root_prefix(
-                        self.project_runner
-                            .project
-                            .manifest()
-                            .and_then(|m| m.template.as_ref())
-                            .map(|t| t.path.as_str().into()),
-                    )
-            },
+            cancellation.clone(),
+            move |w| w.reroute_package(package).root_prefix(prefix),
         );
 
-        self.result.set_warnings(warnings);
+        if let Some(Warned { warnings, .. }) = &compiled {
+            self.result.set_warnings(warnings.clone());
+        }
+
+        if self.project_runner.config.cancellation.is_cancelled() {
+            self.result.set_cancelled();
+            eyre::bail!(TestFailure);
+        }
+
+        if let Some(timeout) = timeout.filter(|_| cancellation.is_expired()) {
+            self.result.set_timed_out(timeout, false);
+            eyre::bail!(TestFailure);
+        }
+
+        let Some(Warned { output, .. }) = compiled else {
+            unreachable!("compilations are only abandoned once cancelled");
+        };
 
         let doc = match output {
             Ok(doc) => {
@@ -0,0 +1,829 @@
+//! An interactive terminal interface for running and inspecting tests.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use codespan_reporting::term;
+use color_eyre::eyre;
+use ecow::EcoString;
+use ratatui::crossterm::event;
+use ratatui::crossterm::event::Event;
+use ratatui::crossterm::event::KeyCode;
+use ratatui::crossterm::event::KeyEventKind;
+use ratatui::crossterm::event::KeyModifiers;
+use ratatui::layout::Constraint;
+use ratatui::layout::Layout;
+use ratatui::style::Color;
+use ratatui::style::Modifier;
+use ratatui::style::Style;
+use ratatui::text::Line;
+use ratatui::text::Span;
+use ratatui::widgets::Block;
+use ratatui::widgets::List;
+use ratatui::widgets::ListItem;
+use ratatui::widgets::ListState;
+use ratatui::widgets::Paragraph;
+use ratatui::widgets::Wrap;
+use ratatui::DefaultTerminal;
+use ratatui::Frame;
+use termcolor::NoColor;
+use tiny_skia::Pixmap;
+use tytanic_core::doc;
+use tytanic_core::doc::compare;
+use tytanic_core::doc::compare::PageError;
+use tytanic_core::project::Project;
+use tytanic_core::suite::FilteredSuite;
+use tytanic_core::test::Id;
+use tytanic_core::test::Stage;
+use tytanic_core::test::Test;
+use tytanic_core::test::TestResult;
+use tytanic_utils::fmt::Term;
+
+use crate::json;
+use crate::report;
+use crate::runner::Action;
+use crate::runner::Runner;
+use crate::runner::RunnerConfig;
+use crate::ui;
+use crate::world::SystemWorld;
+
+/// The key bindings shown in the status line.
+const KEY_HINTS: &str = "r run  R run all  u update  o open  p preview  q quit";
+
+/// The maximum height of the image preview in the details pane in rows.
+const PREVIEW_MAX_ROWS: u16 = 20;
+
+/// The action bound to a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyAction {
+    /// Quit the app.
+    Quit,
+
+    /// Move the selection by the given number of rows.
+    Select(isize),
+
+    /// Scroll the details by the given number of lines.
+    Scroll(i16),
+
+    /// Run the selected tests.
+    Run,
+
+    /// Run all tests.
+    RunAll,
+
+    /// Update the selected tests.
+    Update,
+
+    /// Open the selected test or module.
+    Open,
+
+    /// Toggle the image preview.
+    TogglePreview,
+}
+
+impl KeyAction {
+    /// Returns the action bound to the given key, if any.
+    fn from_key(code: KeyCode, modifiers: KeyModifiers) -> Option<Self> {
+        Some(match code {
+            KeyCode::Char('q') | KeyCode::Esc => Self::Quit,
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => Self::Quit,
+            KeyCode::Down | KeyCode::Char('j') => Self::Select(1),
+            KeyCode::Up | KeyCode::Char('k') => Self::Select(-1),
+            KeyCode::Home | KeyCode::Char('g') => Self::Select(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => Self::Select(isize::MAX),
+            KeyCode::PageDown => Self::Scroll(10),
+            KeyCode::PageUp => Self::Scroll(-10),
+            KeyCode::Char('r') => Self::Run,
+            KeyCode::Char('R') => Self::RunAll,
+            KeyCode::Char('u') => Self::Update,
+            KeyCode::Char('o') => Self::Open,
+            KeyCode::Char('p') => Self::TogglePreview,
+            _ => return None,
+        })
+    }
+}
+
+/// A cached image preview of a failing page of a test.
+struct Preview {
+    /// The test this preview belongs to.
+    id: Id,
+
+    /// The 1-based number of the previewed page.
+    page: usize,
+
+    /// The labels of the images in the preview.
+    labels: Vec<&'static str>,
+
+    /// The rendered preview, `None` if no images were found.
+    image: Option<Pixmap>,
+}
+
+/// A row in the test tree.
+#[derive(Debug, Clone)]
+enum Row<'p> {
+    /// A module containing tests.
+    Module { module: EcoString, depth: usize },
+
+    /// A test.
+    Test { test: &'p Test, depth: usize },
+}
+
+impl Row<'_> {
+    /// Whether the given test is contained in this row.
+    fn contains(&self, test: &Test) -> bool {
+        match self {
+            Row::Module { module, .. } => test
+                .id()
+                .as_str()
+                .strip_prefix(module.as_str())
+                .is_some_and(|rest| rest.starts_with(Id::SEPARATOR)),
+            Row::Test { test: this, .. } => this.id() == test.id(),
+        }
+    }
+}
+
+/// The interactive TUI application.
+pub struct App<'c, 'p> {
+    project: &'p Project,
+    suite: &'p FilteredSuite,
+    world: &'p Arc<SystemWorld>,
+    config: RunnerConfig<'c>,
+
+    rows: Vec<Row<'p>>,
+    state: ListState,
+    results: BTreeMap<Id, TestResult>,
+    scroll: u16,
+    show_preview: bool,
+    preview: Option<Preview>,
+    status: String,
+    quit: bool,
+}
+
+impl<'c, 'p> App<'c, 'p> {
+    /// Creates a new app for the given suite.
+    pub fn new(
+        project: &'p Project,
+        suite: &'p FilteredSuite,
+        world: &'p Arc<SystemWorld>,
+        config: RunnerConfig<'c>,
+    ) -> Self {
+        let mut rows = vec![];
+        let mut seen = Vec::<EcoString>::new();
+
+        for test in suite.matched() {
+            let id = test.id();
+
+            // Emit all ancestor modules which were not yet emitted.
+            let mut modules: Vec<_> = id.ancestors().skip(1).collect();
+            modules.reverse();
+
+            for (depth, module) in modules.into_iter().enumerate() {
+                if !seen.iter().any(|m| m == module) {
+                    seen.push(module.into());
+                    rows.push(Row::Module {
+                        module: module.into(),
+                        depth,
+                    });
+                }
+            }
+
+            rows.push(Row::Test {
+                test,
+                depth: id.components().count() - 1,
+            });
+        }
+
+        let mut state = ListState::default();
+        if !rows.is_empty() {
+            state.select(Some(0));
+        }
+
+        Self {
+            project,
+            suite,
+            world,
+            config,
+            rows,
+            state,
+            results: BTreeMap::new(),
+            scroll: 0,
+            show_preview: true,
+            preview: None,
+            status: String::new(),
+            quit: false,
+        }
+    }
+}
+
+impl<'p> App<'_, 'p> {
+    /// Runs the app until the user quits, this takes care of setting up and
+    /// restoring the terminal.
+    pub fn run(mut self) -> eyre::Result<()> {
+        let mut terminal = ratatui::try_init()?;
+        let res = self.run_inner(&mut terminal);
+        ratatui::try_restore()?;
+
+        res
+    }
+
+    fn run_inner(&mut self, terminal: &mut DefaultTerminal) -> eyre::Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+
+                self.handle_key(terminal, key.code, key.modifiers)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_key(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        code: KeyCode,
+        modifiers: KeyModifiers,
+    ) -> eyre::Result<()> {
+        let Some(action) = KeyAction::from_key(code, modifiers) else {
+            return Ok(());
+        };
+
+        match action {
+            KeyAction::Quit => self.quit = true,
+            KeyAction::Select(offset) => self.select_offset(offset),
+            KeyAction::Scroll(lines) => self.scroll = self.scroll.saturating_add_signed(lines),
+            KeyAction::Run => {
+                let tests = self.selected_tests();
+                self.run_tests(terminal, tests, Action::Run)?;
+            }
+            KeyAction::RunAll => {
+                let tests = self.suite.matched().tests().collect();
+                self.run_tests(terminal, tests, Action::Run)?;
+            }
+            KeyAction::Update => {
+                let tests = self
+                    .selected_tests()
+                    .into_iter()
+                    .filter(|test| {
+                        test.as_unit_test()
+                            .is_some_and(|test| test.kind().is_persistent())
+                    })
+                    .collect();
+                self.run_tests(terminal, tests, Action::Update { force: true })?;
+            }
+            KeyAction::Open => self.open_selected(),
+            KeyAction::TogglePreview => self.show_preview = !self.show_preview,
+        }
+
+        Ok(())
+    }
+
+    fn select_offset(&mut self, offset: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+
+        let current = self.state.selected().unwrap_or(0);
+        self.state
+            .select(Some(offset_index(current, offset, self.rows.len())));
+        self.scroll = 0;
+    }
+
+    fn selected_row(&self) -> Option<&Row<'_>> {
+        self.state.selected().and_then(|idx| self.rows.get(idx))
+    }
+
+    fn selected_tests(&self) -> Vec<&'p Test> {
+        let Some(row) = self.selected_row() else {
+            return vec![];
+        };
+
+        let suite: &'p FilteredSuite = self.suite;
+        suite
+            .matched()
+            .tests()
+            .filter(|test| row.contains(test))
+            .collect()
+    }
+
+    fn run_tests(
+        &mut self,
+        terminal: &mut DefaultTerminal,
+        tests: Vec<&'p Test>,
+        action: Action,
+    ) -> eyre::Result<()> {
+        if tests.is_empty() {
+            self.status = "Nothing to do".into();
+            return Ok(());
+        }
+
+        // The images on disk may change with the results.
+        self.preview = None;
+
+        let mut config = self.config.clone();
+        config.action = action;
+        let runner = Runner::new(self.project, self.suite, self.world, config);
+
+        let total = tests.len();
+        for (idx, test) in tests.into_iter().enumerate() {
+            // Allow aborting long runs between tests.
+            if event::poll(Duration::ZERO)? {
+                if let Event::Key(key) = event::read()? {
+                    if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                        self.status = format!("Canceled after {idx}/{total} tests");
+                        return Ok(());
+                    }
+                }
+            }
+
+            self.status = format!("Running {}/{total}: {}", idx + 1, test.id());
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let res = match test {
+                Test::Unit(test) => runner.unit_test(test).run(),
+                Test::Template(test) => runner.template_test(test).run(),
+            };
+
+            match res {
+                Ok(result) => {
+                    self.results.insert(test.id().clone(), result);
+                }
+                Err(err) => {
+                    self.status = format!("Error while running {}: {err}", test.id());
+                    return Ok(());
+                }
+            }
+        }
+
+        let failed = self.results.values().filter(|r| r.is_fail()).count();
+        self.status = format!(
+            "Finished {total} {}, {failed} failing in total",
+            Term::simple("test").with(total),
+        );
+
+        Ok(())
+    }
+
+    fn open_selected(&mut self) {
+        let path = match self.selected_row() {
+            Some(Row::Test {
+                test: Test::Unit(test),
+                ..
+            }) => {
+                let diff = self.project.unit_test_diff_dir(test.id());
+                if diff.exists() {
+                    diff
+                } else {
+                    self.project.unit_test_dir(test.id())
+                }
+            }
+            Some(Row::Test {
+                test: Test::Template(_),
+                ..
+            }) => match self.project.template_root() {
+                Some(root) => root,
+                None => return,
+            },
+            Some(Row::Module { module, .. }) => {
+                self.project.unit_tests_root().join(module.as_str())
+            }
+            None => return,
+        };
+
+        self.status = match ui::open_path(&path) {
+            Ok(()) => format!("Opened {}", path.display()),
+            Err(err) => format!("Couldn't open {}: {err}", path.display()),
+        };
+    }
+}
+
+impl App<'_, '_> {
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [tree, details] =
+            Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .areas(main);
+
+        let items: Vec<_> = self
+            .rows
+            .iter()
+            .map(|row| match row {
+                Row::Module { module, depth } => ListItem::new(Line::from(vec![
+                    Span::raw("  ".repeat(*depth)),
+                    Span::styled(
+                        format!("{}/", module.rsplit(Id::SEPARATOR).next().unwrap_or(module)),
+                        Style::new().fg(Color::Cyan),
+                    ),
+                ])),
+                Row::Test { test, depth } => {
+                    let (symbol, color) = status_symbol(self.results.get(test.id()));
+                    ListItem::new(Line::from(vec![
+                        Span::raw("  ".repeat(*depth)),
+                        Span::styled(symbol, Style::new().fg(color)),
+                        Span::raw(" "),
+                        Span::styled(
+                            test.id().name().to_owned(),
+                            Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+                        ),
+                    ]))
+                }
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(Block::bordered().title(" Tests "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+
+        frame.render_stateful_widget(list, tree, &mut self.state);
+
+        let details = match self.update_preview() {
+            Some(Preview {
+                page,
+                labels,
+                image: Some(image),
+                ..
+            }) => {
+                let rows = (details.height / 2).min(PREVIEW_MAX_ROWS);
+                let [details, preview] =
+                    Layout::vertical([Constraint::Min(0), Constraint::Length(rows)]).areas(details);
+
+                let block =
+                    Block::bordered().title(format!(" Page {page} ({}) ", labels.join(", ")));
+                let inner = block.inner(preview);
+                let lines = half_block_lines(image, inner.width, inner.height);
+
+                frame.render_widget(Paragraph::new(lines).block(block), preview);
+                details
+            }
+            _ => details,
+        };
+
+        let details_text = self.details();
+        let paragraph = Paragraph::new(details_text)
+            .block(Block::bordered().title(" Details "))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+
+        frame.render_widget(paragraph, details);
+
+        let passed = self.results.values().filter(|r| r.is_pass()).count();
+        let failed = self.results.values().filter(|r| r.is_fail()).count();
+
+        let mut spans = vec![
+            Span::styled(format!(" {passed} passed"), Style::new().fg(Color::Green)),
+            Span::raw(", "),
+            Span::styled(format!("{failed} failed"), Style::new().fg(Color::Red)),
+            Span::raw(" │ "),
+        ];
+
+        if self.status.is_empty() {
+            spans.push(Span::styled(KEY_HINTS, Style::new().fg(Color::DarkGray)));
+        } else {
+            spans.push(Span::raw(self.status.as_str()));
+        }
+
+        frame.render_widget(Paragraph::new(Line::from(spans)), status);
+    }
+
+    /// Updates the cached preview for the selected test and returns it, if the
+    /// selected test failed a comparison and previews are shown.
+    fn update_preview(&mut self) -> Option<&Preview> {
+        if !self.show_preview {
+            return None;
+        }
+
+        let Some(Row::Test {
+            test: Test::Unit(test),
+            ..
+        }) = self.selected_row()
+        else {
+            return None;
+        };
+
+        let Some(Stage::FailedComparison(error)) = self.results.get(test.id()).map(|r| r.stage())
+        else {
+            return None;
+        };
+
+        if self.preview.as_ref().is_none_or(|p| &p.id != test.id()) {
+            let page = report::preview_page(error);
+            let (labels, images) = report::load_preview(self.project, test.id(), page);
+
+            self.preview = Some(Preview {
+                id: test.id().clone(),
+                page,
+                labels,
+                image: ui::render_preview(&images.iter().collect::<Vec<_>>()),
+            });
+        }
+
+        self.preview.as_ref()
+    }
+
+    fn details(&self) -> Vec<Line<'static>> {
+        let mut lines = vec![];
+
+        match self.selected_row() {
+            None => lines.push(Line::raw("No tests")),
+            Some(Row::Module { module, .. }) => {
+                let tests: Vec<_> = self
+                    .suite
+                    .matched()
+                    .tests()
+                    .filter(|test| self.selected_row().is_some_and(|row| row.contains(test)))
+                    .collect();
+
+                let passed = tests
+                    .iter()
+                    .filter(|t| self.results.get(t.id()).is_some_and(|r| r.is_pass()))
+                    .count();
+                let failed = tests
+                    .iter()
+                    .filter(|t| self.results.get(t.id()).is_some_and(|r| r.is_fail()))
+                    .count();
+
+                lines.push(Line::styled(
+                    format!("{module}/"),
+                    Style::new().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                ));
+                lines.push(Line::raw(""));
+                lines.push(Line::raw(format!(
+                    "{} {}, {passed} passed, {failed} failed",
+                    tests.len(),
+                    Term::simple("test").with(tests.len()),
+                )));
+            }
+            Some(Row::Test { test, .. }) => {
+                lines.push(Line::styled(
+                    test.id().to_string(),
+                    Style::new().fg(Color::Blue).add_modifier(Modifier::BOLD),
+                ));
+                lines.push(Line::raw(""));
+
+                match test {
+                    Test::Unit(unit) => {
+                        lines.push(Line::raw(format!("Kind: {}", unit.kind().as_str())));
+                        lines.push(Line::raw(format!(
+                            "Path: {}",
+                            self.relative(&self.project.unit_test_dir(unit.id()))
+                                .display()
+                        )));
+                    }
+                    Test::Template(_) => lines.push(Line::raw("Kind: template")),
+                }
+
+                let Some(result) = self.results.get(test.id()) else {
+                    lines.push(Line::raw("Status: not run"));
+                    return lines;
+                };
+
+                lines.push(Line::raw(format!(
+                    "Status: {}",
+                    json::stage_str(result.stage())
+                )));
+                lines.push(Line::raw(format!("Duration: {:?}", result.duration())));
+
+                if let Stage::FailedComparison(compare::Error {
+                    output,
+                    reference,
+                    pages,
+                    ..
+                }) = result.stage()
+                {
+                    lines.push(Line::raw(""));
+                    if output != reference {
+                        lines.push(Line::raw(format!(
+                            "Expected {reference} {}, got {output} {}",
+                            Term::simple("page").with(*reference),
+                            Term::simple("page").with(*output),
+                        )));
+                    }
+
+                    for (p, e) in pages {
+                        let p = p + 1;
+                        lines.push(Line::raw(match e {
+                            PageError::Dimensions { output, reference } => format!(
+                                "Page {p} had different dimensions (output: {output}, reference: {reference})"
+                            ),
+                            PageError::SimpleDeviations { deviations } => format!(
+                                "Page {p} had {deviations} {}",
+                                Term::simple("deviation").with(*deviations),
+                            ),
+                            PageError::SampledDeviations {
+                                deviations,
+                                samples,
+                                estimated,
+                            } => format!(
+                                "Page {p} had about {estimated} {} ({deviations} of {samples} sampled pixels)",
+                                Term::simple("deviation").with(*estimated),
+                            ),
+                            PageError::Ssim { ssim, threshold } => format!(
+                                "Page {p} had a structural similarity of {ssim:.4}, below {threshold}"
+                            ),
+                            PageError::Text { mismatches, .. } => format!(
+                                "Page {p} had {mismatches} differing text {}",
+                                Term::simple("run").with(*mismatches),
+                            ),
+                            PageError::Fonts { missing, unexpected } => format!(
+                                "Page {p} used different fonts (missing: {}, unexpected: {})",
+                                missing.join(", "),
+                                unexpected.join(", "),
+                            ),
+                        }));
+                    }
+
+                    let diff_dir = self.project.unit_test_diff_dir(test.id());
+                    if let Ok(paths) = doc::page_paths(&diff_dir, doc::PAGE_EXTENSION) {
+                        lines.push(Line::raw(""));
+                        lines.push(Line::raw("Diff images:"));
+
+                        for path in paths.values() {
+                            lines.push(Line::styled(
+                                format!("  {}", self.relative(path).display()),
+                                Style::new().fg(Color::Cyan),
+                            ));
+                        }
+                    }
+                }
+
+                let errors = result.errors().unwrap_or_default();
+                if !errors.is_empty() || !result.warnings().is_empty() {
+                    let mut w = NoColor::new(vec![]);
+                    let res = ui::write_diagnostics(
+                        &mut w,
+                        &term::Config::default(),
+                        self.world,
+                        result.warnings(),
+                        errors,
+                    );
+
+                    lines.push(Line::raw(""));
+                    match res {
+                        Ok(()) => lines.extend(
+                            String::from_utf8_lossy(&w.into_inner())
+                                .lines()
+                                .map(|line| Line::raw(line.to_owned())),
+                        ),
+                        Err(err) => {
+                            lines.push(Line::raw(format!("Couldn't render diagnostics: {err}")))
+                        }
+                    }
+                }
+            }
+        }
+
+        lines
+    }
+
+    fn relative<'a>(&self, path: &'a Path) -> &'a Path {
+        path.strip_prefix(self.project.root()).unwrap_or(path)
+    }
+}
+
+/// Returns the index reached by moving `offset` rows from `current`, clamped to
+/// a list of `len` rows.
+fn offset_index(current: usize, offset: isize, len: usize) -> usize {
+    current
+        .saturating_add_signed(offset)
+        .min(len.saturating_sub(1))
+}
+
+/// Renders an image into lines of half block characters fitting into the
+/// given number of columns and rows, each cell shows two vertically stacked
+/// pixels.
+///
+/// The image is scaled using nearest neighbor sampling, keeping its aspect
+/// ratio.
+fn half_block_lines(image: &Pixmap, cols: u16, rows: u16) -> Vec<Line<'static>> {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    let scale = f32::min(f32::from(cols) / width, f32::from(rows) * 2.0 / height);
+
+    let cols = ((width * scale) as u32).min(u32::from(cols));
+    let rows = ((height * scale / 2.0) as u32).min(u32::from(rows));
+
+    let pixel = |x: u32, y: u32| {
+        let x = ((x as f32 / scale) as u32).min(image.width() - 1);
+        let y = ((y as f32 / scale) as u32).min(image.height() - 1);
+
+        image
+            .pixel(x, y)
+            .map(|p| {
+                let p = p.demultiply();
+                Color::Rgb(p.red(), p.green(), p.blue())
+            })
+            .unwrap_or(Color::Reset)
+    };
+
+    (0..rows)
+        .map(|row| {
+            Line::from(
+                (0..cols)
+                    .map(|col| {
+                        Span::styled(
+                            "▀",
+                            Style::new()
+                                .fg(pixel(col, row * 2))
+                                .bg(pixel(col, row * 2 + 1)),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect()
+}
+
+/// Returns the symbol and color for a test's status.
+fn status_symbol(result: Option<&TestResult>) -> (&'static str, Color) {
+    match result.map(|r| r.stage()) {
+        None | Some(Stage::Skipped | Stage::Filtered | Stage::Cancelled) => ("·", Color::DarkGray),
+        Some(
+            Stage::FailedCompilation { .. }
+            | Stage::FailedComparison(_)
+            | Stage::FailedRendering { .. }
+            | Stage::TimedOut { .. }
+            | Stage::UnexpectedPass { .. },
+        ) => ("✗", Color::Red),
+        Some(Stage::ExpectedFailure { .. }) => ("✗", Color::Yellow),
+        Some(Stage::PassedCompilation | Stage::PassedComparison) => ("✓", Color::Green),
+        Some(Stage::Updated { .. }) => ("↻", Color::Green),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_action_from_key() {
+        let none = KeyModifiers::NONE;
+
+        assert_eq!(
+            KeyAction::from_key(KeyCode::Char('q'), none),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(
+            KeyAction::from_key(KeyCode::Char('c'), KeyModifiers::CONTROL),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(KeyAction::from_key(KeyCode::Char('c'), none), None);
+        assert_eq!(
+            KeyAction::from_key(KeyCode::Char('j'), none),
+            Some(KeyAction::Select(1))
+        );
+        assert_eq!(
+            KeyAction::from_key(KeyCode::Up, none),
+            Some(KeyAction::Select(-1))
+        );
+        assert_eq!(
+            KeyAction::from_key(KeyCode::PageUp, none),
+            Some(KeyAction::Scroll(-10))
+        );
+        assert_eq!(
+            KeyAction::from_key(KeyCode::Char('R'), none),
+            Some(KeyAction::RunAll)
+        );
+        assert_eq!(
+            KeyAction::from_key(KeyCode::Char('p'), none),
+            Some(KeyAction::TogglePreview)
+        );
+    }
+
+    #[test]
+    fn test_offset_index() {
+        assert_eq!(offset_index(0, -1, 5), 0);
+        assert_eq!(offset_index(0, 1, 5), 1);
+        assert_eq!(offset_index(3, 10, 5), 4);
+        assert_eq!(offset_index(3, isize::MIN, 5), 0);
+        assert_eq!(offset_index(3, isize::MAX, 5), 4);
+        assert_eq!(offset_index(0, 1, 0), 0);
+    }
+
+    #[test]
+    fn test_half_block_lines() {
+        let mut image = Pixmap::new(4, 4).unwrap();
+        image.fill(tiny_skia::Color::from_rgba8(255, 0, 0, 255));
+
+        // Limited by the rows, each row shows two pixels.
+        let lines = half_block_lines(&image, 10, 1);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].spans.len(), 2);
+        assert_eq!(lines[0].spans[0].style.fg, Some(Color::Rgb(255, 0, 0)));
+        assert_eq!(lines[0].spans[0].style.bg, Some(Color::Rgb(255, 0, 0)));
+
+        // Limited by the columns.
+        let lines = half_block_lines(&image, 2, 10);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].spans.len(), 2);
+
+        let lines = half_block_lines(&image, 4, 2);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].spans.len(), 4);
+    }
+}
@@ -1,5 +1,6 @@
 #![allow(dead_code)]
 
+use std::env;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Display;
@@ -9,7 +10,10 @@ use std::io::IsTerminal;
 use std::io::Stdin;
 use std::io::StdinLock;
 use std::io::Write;
+use std::iter;
+use std::path::Path;
 
+use base64::Engine;
 use codespan_reporting::diagnostic::Diagnostic;
 use codespan_reporting::diagnostic::Label;
 use codespan_reporting::term;
@@ -22,12 +26,17 @@ use termcolor::HyperlinkSpec;
 use termcolor::StandardStream;
 use termcolor::StandardStreamLock;
 use termcolor::WriteColor;
+use tiny_skia::FilterQuality;
+use tiny_skia::Pixmap;
+use tiny_skia::PixmapPaint;
+use tiny_skia::Transform;
 use typst::diag::Severity;
 use typst::diag::SourceDiagnostic;
 use typst::WorldExt;
 use typst_syntax::FileId;
 use typst_syntax::Span;
 use tytanic_core::test::Id;
+use url::Url;
 
 use crate::world::SystemWorld;
 
@@ -80,6 +89,9 @@ pub struct Ui {
 
     /// The diagnostic config to use for emitting typst source diagnostics.
     diagnostic_config: term::Config,
+
+    /// Whether prompts and live reporting are allowed at all.
+    interactive: bool,
 }
 
 /// Returns whether or not a given output stream is connected to a terminal.
@@ -101,20 +113,38 @@ impl Ui {
             stdout: StandardStream::stdout(check_terminal(io::stdout(), out)),
             stderr: StandardStream::stderr(check_terminal(io::stderr(), err)),
             diagnostic_config,
+            interactive: true,
         }
     }
+
+    /// Whether prompts and live reporting may be used if the streams are
+    /// connected to a terminal.
+    pub fn with_interactive(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
 }
 
 impl Ui {
     /// Whether a live status report can be printed and cleared using ANSI
     /// escape codes.
     pub fn can_live_report(&self) -> bool {
-        io::stderr().is_terminal()
+        self.interactive && io::stderr().is_terminal()
+    }
+
+    /// The graphics protocol which can be used to display images on stderr,
+    /// if any.
+    pub fn image_protocol(&self) -> Option<ImageProtocol> {
+        if !io::stderr().is_terminal() {
+            return None;
+        }
+
+        ImageProtocol::detect()
     }
 
     /// Whether a prompt can be displayed and confirmed by the user.
     pub fn can_prompt(&self) -> bool {
-        io::stdin().is_terminal() && io::stderr().is_terminal()
+        self.interactive && io::stdin().is_terminal() && io::stderr().is_terminal()
     }
 
     /// Returns the diagnostic config to use for displaying diagnostics.
@@ -353,6 +383,301 @@ pub fn write_test_id(mut w: &mut dyn WriteColor, id: &Id) -> io::Result<()> {
     Ok(())
 }
 
+/// Returns a `file://` URL for the given path, relative paths are resolved
+/// against the current directory.
+///
+/// Characters which are not allowed in URLs, like spaces, are
+/// percent-encoded.
+pub fn file_url(path: &Path) -> String {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+
+    Url::from_file_path(&path)
+        .map(String::from)
+        .unwrap_or_else(|()| path.to_string_lossy().into_owned())
+}
+
+/// Opens the given path with the default application of the system.
+pub fn open_path(path: &Path) -> io::Result<()> {
+    let mut cmd = if cfg!(target_os = "windows") {
+        let mut cmd = std::process::Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    } else if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+
+    cmd.arg(path)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()?;
+
+    Ok(())
+}
+
+/// Writes content as a hyperlink to the given path if the writer supports
+/// hyperlinks, otherwise writes the content as is.
+pub fn write_file_link<F>(w: &mut dyn WriteColor, path: &Path, f: F) -> io::Result<()>
+where
+    F: FnOnce(&mut dyn WriteColor) -> io::Result<()>,
+{
+    if !w.supports_hyperlinks() {
+        return f(w);
+    }
+
+    let url = file_url(path);
+    w.set_hyperlink(&HyperlinkSpec::open(url.as_bytes()))?;
+    f(w)?;
+    w.set_hyperlink(&HyperlinkSpec::close())?;
+
+    Ok(())
+}
+
+/// Copies the given text to the clipboard of the terminal connected to the
+/// writer using the OSC 52 escape sequence.
+///
+/// Terminals which don't support this sequence ignore it.
+pub fn copy_to_clipboard<W: Write>(mut w: W, text: &str) -> io::Result<()> {
+    let encoded = base64::engine::general_purpose::STANDARD.encode(text);
+    write!(w, "\x1b]52;c;{encoded}\x07")?;
+    w.flush()
+}
+
+/// The height in pixels of each image in an image preview.
+pub const PREVIEW_HEIGHT: u32 = 192;
+
+/// The gap in pixels between the images of an image preview.
+const PREVIEW_GAP: u32 = 8;
+
+/// The maximum size of a single kitty graphics escape sequence payload.
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// A terminal graphics protocol which can be used to display images inline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageProtocol {
+    /// The kitty graphics protocol, also supported by Ghostty.
+    Kitty,
+
+    /// The iTerm2 inline images protocol, also supported by WezTerm.
+    Iterm2,
+
+    /// The DEC sixel graphics format.
+    Sixel,
+}
+
+impl ImageProtocol {
+    /// Detects the graphics protocol supported by the current terminal from
+    /// the environment.
+    pub fn detect() -> Option<Self> {
+        Self::detect_with(|key| env::var(key).ok())
+    }
+
+    /// Detects the graphics protocol from the given environment variables.
+    fn detect_with<F: Fn(&str) -> Option<String>>(var: F) -> Option<Self> {
+        let term = var("TERM").unwrap_or_default();
+        let program = var("TERM_PROGRAM").unwrap_or_default();
+
+        // NOTE(tinger): Multiplexers don't forward these escape sequences
+        // without explicit passthrough, which we can't detect reliably.
+        if var("TMUX").is_some() || term.starts_with("screen") || term.starts_with("tmux") {
+            return None;
+        }
+
+        if var("KITTY_WINDOW_ID").is_some()
+            || term.contains("kitty")
+            || term.contains("ghostty")
+            || program == "ghostty"
+        {
+            return Some(Self::Kitty);
+        }
+
+        if program == "iTerm.app"
+            || program == "WezTerm"
+            || var("LC_TERMINAL").is_some_and(|t| t == "iTerm2")
+        {
+            return Some(Self::Iterm2);
+        }
+
+        if term.starts_with("foot") || term.starts_with("mlterm") || term.contains("sixel") {
+            return Some(Self::Sixel);
+        }
+
+        None
+    }
+}
+
+/// Writes the given images next to each other as a single inline image using
+/// the given graphics protocol.
+///
+/// The images are scaled to [`PREVIEW_HEIGHT`] and drawn on a gray
+/// background, such that transparent images and page borders are visible.
+/// Nothing is written if no images are given.
+pub fn write_image_preview(
+    w: &mut dyn Write,
+    protocol: ImageProtocol,
+    images: &[&Pixmap],
+) -> io::Result<()> {
+    let Some(preview) = render_preview(images) else {
+        return Ok(());
+    };
+
+    match protocol {
+        ImageProtocol::Kitty => write_kitty_image(w, &preview),
+        ImageProtocol::Iterm2 => write_iterm2_image(w, &preview),
+        ImageProtocol::Sixel => write_sixel_image(w, &preview),
+    }
+}
+
+/// Scales the given images to [`PREVIEW_HEIGHT`] and places them next to each
+/// other on a gray background, returns `None` if no images are given.
+pub fn render_preview(images: &[&Pixmap]) -> Option<Pixmap> {
+    let scales: Vec<_> = images
+        .iter()
+        .map(|image| PREVIEW_HEIGHT as f32 / image.height() as f32)
+        .collect();
+
+    let widths: Vec<_> = iter::zip(images, &scales)
+        .map(|(image, scale)| ((image.width() as f32 * scale).round() as u32).max(1))
+        .collect();
+
+    let gaps = PREVIEW_GAP * (images.len() as u32).saturating_sub(1);
+    let mut preview = Pixmap::new(widths.iter().sum::<u32>() + gaps, PREVIEW_HEIGHT)?;
+    preview.fill(tiny_skia::Color::from_rgba8(128, 128, 128, 255));
+
+    let paint = PixmapPaint {
+        quality: FilterQuality::Bilinear,
+        ..PixmapPaint::default()
+    };
+
+    let mut x = 0;
+    for ((image, scale), width) in images.iter().zip(scales).zip(widths) {
+        let transform = Transform::from_scale(scale, scale).post_translate(x as f32, 0.0);
+        preview.draw_pixmap(0, 0, image.as_ref(), &paint, transform, None);
+        x += width + PREVIEW_GAP;
+    }
+
+    Some(preview)
+}
+
+/// Writes an image using the kitty graphics protocol.
+fn write_kitty_image(w: &mut dyn Write, image: &Pixmap) -> io::Result<()> {
+    let data = image.encode_png().map_err(io::Error::other)?;
+    let data = base64::engine::general_purpose::STANDARD.encode(data);
+
+    let mut chunks = data.as_bytes().chunks(KITTY_CHUNK_SIZE).peekable();
+    let mut first = true;
+    while let Some(chunk) = chunks.next() {
+        let more = u8::from(chunks.peek().is_some());
+
+        // NOTE(tinger): The image is transmitted and displayed at once, `q=2`
+        // suppresses the responses of the terminal.
+        if first {
+            write!(w, "\x1b_Ga=T,f=100,q=2,m={more};")?;
+            first = false;
+        } else {
+            write!(w, "\x1b_Gm={more};")?;
+        }
+
+        w.write_all(chunk)?;
+        write!(w, "\x1b\\")?;
+    }
+
+    Ok(())
+}
+
+/// Writes an image using the iTerm2 inline images protocol.
+fn write_iterm2_image(w: &mut dyn Write, image: &Pixmap) -> io::Result<()> {
+    let data = image.encode_png().map_err(io::Error::other)?;
+    let size = data.len();
+    let data = base64::engine::general_purpose::STANDARD.encode(data);
+
+    write!(
+        w,
+        "\x1b]1337;File=inline=1;size={size};width={}px;height={}px:{data}\x07",
+        image.width(),
+        image.height(),
+    )
+}
+
+/// Writes an image as sixels, quantized to a 6x6x6 color cube.
+///
+/// The image is expected to be opaque.
+fn write_sixel_image(w: &mut dyn Write, image: &Pixmap) -> io::Result<()> {
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+
+    let indices: Vec<u8> = image
+        .pixels()
+        .iter()
+        .map(|pixel| {
+            let color = pixel.demultiply();
+            let level = |c: u8| (u16::from(c) * 5 + 127) / 255;
+            (level(color.red()) * 36 + level(color.green()) * 6 + level(color.blue())) as u8
+        })
+        .collect();
+
+    write!(w, "\x1bPq\"1;1;{width};{height}")?;
+    for idx in 0..216 {
+        let (r, g, b) = (idx / 36, idx / 6 % 6, idx % 6);
+        write!(w, "#{idx};2;{};{};{}", r * 20, g * 20, b * 20)?;
+    }
+
+    for band in (0..height).step_by(6) {
+        let rows = (height - band).min(6);
+        let pixels = &indices[band * width..(band + rows) * width];
+
+        let mut used = [false; 216];
+        for &idx in pixels {
+            used[idx as usize] = true;
+        }
+
+        let mut first = true;
+        for color in (0..216).filter(|&idx| used[idx as usize]) {
+            if !first {
+                write!(w, "$")?;
+            }
+            first = false;
+            write!(w, "#{color}")?;
+
+            let mut run: Option<(u8, usize)> = None;
+            for x in 0..width {
+                let bits = (0..rows)
+                    .filter(|row| pixels[row * width + x] == color)
+                    .fold(0, |bits, row| bits | (1 << row));
+                let sixel = 63 + bits;
+
+                run = match run {
+                    Some((prev, len)) if prev == sixel => Some((prev, len + 1)),
+                    Some((prev, len)) => {
+                        write_sixel_run(w, prev, len)?;
+                        Some((sixel, 1))
+                    }
+                    None => Some((sixel, 1)),
+                };
+            }
+
+            if let Some((sixel, len)) = run {
+                write_sixel_run(w, sixel, len)?;
+            }
+        }
+
+        write!(w, "-")?;
+    }
+
+    write!(w, "\x1b\\")
+}
+
+/// Writes a run of the same sixel, using a repeat introducer for longer runs.
+fn write_sixel_run(w: &mut dyn Write, sixel: u8, len: usize) -> io::Result<()> {
+    if len > 3 {
+        write!(w, "!{len}{}", sixel as char)
+    } else {
+        w.write_all(&vec![sixel; len])
+    }
+}
+
 /// Writes the given diagnostics.
 pub fn write_diagnostics(
     w: &mut dyn WriteColor,
@@ -670,6 +995,84 @@ mod tests {
 
     use super::*;
 
+    fn env<'a>(vars: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        |key| {
+            vars.iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_file_url() {
+        assert_eq!(file_url(Path::new("/a/b.png")), "file:///a/b.png");
+        assert_eq!(
+            file_url(Path::new("/tests/my test/#1%/diff")),
+            "file:///tests/my%20test/%231%25/diff",
+        );
+    }
+
+    #[test]
+    fn test_image_protocol_detect() {
+        assert_eq!(
+            ImageProtocol::detect_with(env(&[("TERM", "xterm-kitty")])),
+            Some(ImageProtocol::Kitty)
+        );
+        assert_eq!(
+            ImageProtocol::detect_with(env(&[("TERM_PROGRAM", "WezTerm")])),
+            Some(ImageProtocol::Iterm2)
+        );
+        assert_eq!(
+            ImageProtocol::detect_with(env(&[("TERM", "foot")])),
+            Some(ImageProtocol::Sixel)
+        );
+        assert_eq!(
+            ImageProtocol::detect_with(env(&[("TERM", "xterm-kitty"), ("TMUX", "/tmp/tmux")])),
+            None
+        );
+        assert_eq!(
+            ImageProtocol::detect_with(env(&[("TERM", "xterm-256color")])),
+            None
+        );
+    }
+
+    #[test]
+    fn test_write_image_preview() {
+        let mut image = Pixmap::new(4, 8).unwrap();
+        image.fill(tiny_skia::Color::WHITE);
+
+        let mut buf = vec![];
+        write_image_preview(&mut buf, ImageProtocol::Kitty, &[&image, &image]).unwrap();
+        let str = std::str::from_utf8(&buf).unwrap();
+        assert!(str.starts_with("\x1b_Ga=T,f=100,q=2,m="));
+        assert!(str.ends_with("\x1b\\"));
+
+        let mut buf = vec![];
+        write_image_preview(&mut buf, ImageProtocol::Iterm2, &[&image]).unwrap();
+        let str = std::str::from_utf8(&buf).unwrap();
+        assert!(str.starts_with("\x1b]1337;File=inline=1;"));
+        assert!(str.contains(&format!("height={PREVIEW_HEIGHT}px")));
+
+        let mut buf = vec![];
+        write_image_preview(&mut buf, ImageProtocol::Sixel, &[]).unwrap();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_write_sixel_image() {
+        let mut image = Pixmap::new(5, 7).unwrap();
+        image.fill(tiny_skia::Color::WHITE);
+
+        let mut buf = vec![];
+        write_sixel_image(&mut buf, &image).unwrap();
+        let str = std::str::from_utf8(&buf).unwrap();
+
+        // A full band of 5 white pixels followed by a single row.
+        assert!(str.starts_with("\x1bPq\"1;1;5;7#0;2;0;0;0"));
+        assert!(str.ends_with("#215!5~-#215!5@-\x1b\\"));
+    }
+
     #[test]
     fn test_indented() {
         let mut w = Indented::new(Ansi::new(vec![]), 2);
@@ -0,0 +1,93 @@
+//! Uploading of test run reports to an HTTP endpoint.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use base64::Engine;
+use color_eyre::eyre;
+use serde::Serialize;
+use tytanic_core::doc;
+use tytanic_core::project::Project;
+use tytanic_core::suite::SuiteResult;
+
+use crate::json::SuiteResultJson;
+
+/// The environment variable whose value is sent as the `Authorization` header
+/// of uploads.
+pub const AUTH_ENV: &str = "TYTANIC_UPLOAD_AUTH";
+
+/// The body of an upload, the JSON report and the artifacts of failed tests.
+#[derive(Debug, Serialize)]
+pub struct UploadJson<'r> {
+    pub report: SuiteResultJson<'r>,
+    pub artifacts: BTreeMap<&'r str, ArtifactsJson>,
+}
+
+/// The base64 encoded PNG pages of a failed test.
+#[derive(Debug, Serialize)]
+pub struct ArtifactsJson {
+    pub out: Vec<String>,
+    #[serde(rename = "ref")]
+    pub reference: Vec<String>,
+    pub diff: Vec<String>,
+}
+
+/// POSTs the report and the artifacts of all failed tests to the given URL.
+pub fn upload(
+    agent: &ureq::Agent,
+    url: &str,
+    project: &Project,
+    result: &SuiteResult,
+) -> eyre::Result<()> {
+    let mut artifacts = BTreeMap::new();
+    for (id, test) in result.results() {
+        if !test.is_fail() {
+            continue;
+        }
+
+        artifacts.insert(
+            id.as_str(),
+            ArtifactsJson {
+                out: read_pages(&project.unit_test_out_dir(id))?,
+                reference: read_pages(&project.unit_test_ref_dir(id))?,
+                diff: read_pages(&project.unit_test_diff_dir(id))?,
+            },
+        );
+    }
+
+    let body = serde_json::to_string(&UploadJson {
+        report: SuiteResultJson::new(result),
+        artifacts,
+    })?;
+
+    let mut request = agent.post(url).set("Content-Type", "application/json");
+    if let Some(auth) = std::env::var(AUTH_ENV).ok().filter(|val| !val.is_empty()) {
+        request = request.set("Authorization", &auth);
+    }
+
+    request.send_string(&body)?;
+
+    Ok(())
+}
+
+/// Reads and encodes the numbered PNG pages in the given directory in order,
+/// returns no pages if it doesn't exist.
+pub fn read_pages(dir: &Path) -> io::Result<Vec<String>> {
+    let pages = match doc::page_paths(dir, doc::PAGE_EXTENSION) {
+        Ok(pages) => pages,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(err),
+    };
+
+    let pages = pages
+        .into_values()
+        .map(fs::read)
+        .collect::<io::Result<Vec<_>>>()?;
+
+    Ok(pages
+        .into_iter()
+        .map(|data| base64::engine::general_purpose::STANDARD.encode(data))
+        .collect())
+}
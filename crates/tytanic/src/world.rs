@@ -29,10 +29,10 @@ use typst::text::FontBook;
 use typst::utils::LazyHash;
 use typst::Library;
 use typst::World;
-use typst_kit::download::ProgressSink;
 use typst_kit::fonts::FontSlot;
 use typst_kit::fonts::Fonts;
-use typst_kit::package::PackageStorage;
+
+use crate::registry::PackageStore;
 
 /// A world that provides access to the operating system.
 pub struct SystemWorld {
@@ -49,9 +49,11 @@ pub struct SystemWorld {
     /// Maps file ids to source files and buffers.
     slots: Mutex<HashMap<FileId, FileSlot>>,
     /// Holds information about where packages are stored.
-    package_storage: PackageStorage,
+    package_storage: PackageStore,
     /// The current date-time if requested.
     now: DateTime<Utc>,
+    /// Whether to display file names relative to the root.
+    relative_names: bool,
 }
 
 impl SystemWorld {
@@ -59,7 +61,7 @@ impl SystemWorld {
     pub fn new(
         root: PathBuf,
         fonts: Fonts,
-        package_storage: PackageStorage,
+        package_storage: PackageStore,
         now: DateTime<Utc>,
     ) -> io::Result<Self> {
         Ok(Self {
@@ -71,9 +73,17 @@ impl SystemWorld {
             slots: Mutex::new(HashMap::new()),
             package_storage,
             now,
+            relative_names: false,
         })
     }
 
+    /// Whether to display file names in diagnostics relative to the root
+    /// instead of as absolute paths.
+    pub fn with_relative_names(mut self, relative_names: bool) -> Self {
+        self.relative_names = relative_names;
+        self
+    }
+
     /// The root relative to which absolute paths are resolved.
     pub fn root(&self) -> &Path {
         &self.root
@@ -85,12 +95,24 @@ impl SystemWorld {
     }
 
     /// Reset the compilation state in preparation of a new compilation.
-    pub fn reset(&mut self) {
-        for slot in self.slots.get_mut().unwrap().values_mut() {
+    pub fn reset(&self) {
+        for slot in self.slots.lock().unwrap().values_mut() {
             slot.reset();
         }
     }
 
+    /// Returns the system paths of all project files which were accessed since
+    /// the last reset, files in packages are not included.
+    pub fn dependencies(&self) -> Vec<PathBuf> {
+        self.slots
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|slot| slot.accessed() && slot.id.package().is_none())
+            .filter_map(|slot| slot.id.vpath().resolve(&self.root))
+            .collect()
+    }
+
     /// Lookup a source file by id.
     #[track_caller]
     pub fn lookup(&self, id: FileId) -> Source {
@@ -182,11 +204,16 @@ impl FileSlot {
         self.file.reset();
     }
 
+    /// Whether the file was accessed in the current compilation.
+    fn accessed(&self) -> bool {
+        self.source.accessed || self.file.accessed
+    }
+
     /// Retrieve the source for this file.
     fn source(
         &mut self,
         project_root: &Path,
-        package_storage: &PackageStorage,
+        package_storage: &PackageStore,
     ) -> FileResult<Source> {
         self.source.get_or_init(
             || read(self.id, project_root, package_storage),
@@ -203,7 +230,7 @@ impl FileSlot {
     }
 
     /// Retrieve the file's bytes.
-    fn file(&mut self, project_root: &Path, package_storage: &PackageStorage) -> FileResult<Bytes> {
+    fn file(&mut self, project_root: &Path, package_storage: &PackageStore) -> FileResult<Bytes> {
         self.file.get_or_init(
             || read(self.id, project_root, package_storage),
             |data, _| Ok(Bytes::new(data)),
@@ -274,7 +301,7 @@ impl<T: Clone> SlotCell<T> {
 fn system_path(
     project_root: &Path,
     id: FileId,
-    package_storage: &PackageStorage,
+    package_storage: &PackageStore,
 ) -> FileResult<PathBuf> {
     // Determine the root path relative to which the file path
     // will be resolved.
@@ -282,7 +309,7 @@ fn system_path(
     let mut root = project_root;
     if let Some(spec) = id.package() {
         tracing::trace!(?spec, "preparing package");
-        buf = package_storage.prepare_package(spec, &mut ProgressSink)?;
+        buf = package_storage.prepare_package(spec)?;
         root = &buf;
     }
 
@@ -295,7 +322,7 @@ fn system_path(
 ///
 /// If the ID represents stdin it will read from standard input,
 /// otherwise it gets the file path of the ID and reads the file from disk.
-fn read(id: FileId, project_root: &Path, package_storage: &PackageStorage) -> FileResult<Vec<u8>> {
+fn read(id: FileId, project_root: &Path, package_storage: &PackageStore) -> FileResult<Vec<u8>> {
     read_from_disk(&system_path(project_root, id, package_storage)?)
 }
 
@@ -329,6 +356,8 @@ impl<'a> codespan_reporting::files::Files<'a> for SystemWorld {
         let vpath = id.vpath();
         Ok(if let Some(package) = id.package() {
             format!("{package}{}", vpath.as_rooted_path().display())
+        } else if self.relative_names {
+            vpath.as_rootless_path().to_string_lossy().into()
         } else {
             // Try to express the path relative to the working directory.
             vpath
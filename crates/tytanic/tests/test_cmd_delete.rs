@@ -42,7 +42,7 @@ fn test_new_delete_alias() {
     --- STDOUT:
 
     --- STDERR:
-    warning: Sub command alias remove|rm is deprecated
+    warning: Sub command alias remove is deprecated since 0.1.2 and will be removed in 0.3.0
     hint: Use delete instead
     Deleted 1 test
 
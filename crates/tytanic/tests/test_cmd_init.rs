@@ -0,0 +1,112 @@
+mod fixture;
+
+use std::fs;
+
+#[test]
+fn test_init_minimal() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["init"]);
+
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+    assert!(env.root().join("tests/example/test.typ").is_file());
+    assert!(!env.root().join("tests/example/ref").exists());
+    assert!(!env.root().join(".github").exists());
+}
+
+#[test]
+fn test_init_package() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["init", "--template", "package"]);
+
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+
+    let tests = env.root().join("tests/examples");
+    assert!(tests.join("compile-only/test.typ").is_file());
+    assert!(tests.join("ephemeral/ref.typ").is_file());
+    assert!(tests.join("persistent/ref/1.png").is_file());
+
+    let workflow = fs::read_to_string(env.root().join(".github/workflows/tests.yml")).unwrap();
+    assert!(workflow.contains("tt run --no-fail-fast"), "{workflow}");
+
+    let res = env.run_tytanic(["run", "--expression", "r:^examples/"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+}
+
+#[test]
+fn test_init_thesis() {
+    let env = fixture::Environment::new();
+    fs::write(env.root().join("main.typ"), "= Thesis\n").unwrap();
+
+    let res = env.run_tytanic(["--root", ".", "init", "--template", "thesis"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+
+    let tests = env.root().join("tests/layout");
+    assert!(tests.join("title-page/ref/1.png").is_file());
+    assert!(tests.join("headings/ref/1.png").is_file());
+    assert!(tests.join("outline/test.typ").is_file());
+
+    let manifest = fs::read_to_string(env.root().join("tests/tytanic.toml")).unwrap();
+    assert!(
+        manifest.contains(r#"entrypoints = ["main.typ"]"#),
+        "{manifest}"
+    );
+
+    // The document manifest makes the root discoverable without `--root`.
+    let res = env.run_tytanic(["run"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+}
+
+#[test]
+fn test_init_dir() {
+    let env = fixture::Environment::default_package();
+    let template = env.root().join("my-template");
+    fs::create_dir_all(template.join("custom/nested")).unwrap();
+    fs::write(template.join("custom/nested/test.typ"), "Hello\n").unwrap();
+    fs::write(template.join("custom/util.typ"), "#let x = 1\n").unwrap();
+
+    let res = env.run_tytanic(["init", "--template", "my-template"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+    assert!(
+        res.output().stderr().contains("Initialized 1 test"),
+        "{}",
+        res.output()
+    );
+
+    assert!(env.root().join("tests/custom/nested/test.typ").is_file());
+    assert!(env.root().join("tests/custom/util.typ").is_file());
+}
+
+#[test]
+fn test_init_conflict() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["init"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+
+    let res = env.run_tytanic(["init"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 2
+    --- STDOUT:
+
+    --- STDERR:
+    error: These tests already exist:
+             example
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_init_unknown_template() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["init", "--template", "nope"]);
+
+    assert_eq!(res.output().status().code(), Some(2), "{}", res.output());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("expected one of minimal, package or thesis"),
+        "{}",
+        res.output()
+    );
+}
@@ -23,3 +23,73 @@ fn test_status() {
     --- END
     ");
 }
+
+fn skipped_package() -> fixture::Environment {
+    let env = fixture::Environment::default_package();
+    std::fs::create_dir_all(env.root().join("tests/passing/skipped")).unwrap();
+    std::fs::write(
+        env.root().join("tests/passing/skipped/test.typ"),
+        "/// [skip]\n\nHello World\n",
+    )
+    .unwrap();
+    env
+}
+
+#[test]
+fn test_list_skip() {
+    let env = skipped_package();
+    let res = env.run_tytanic(["list", "--expression", "glob:passing/*"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    passing/compile    compile-only
+    passing/ephemeral  ephemeral   
+    passing/persistent persistent  
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_list_explicit_skip() {
+    let env = skipped_package();
+    let res = env.run_tytanic(["list", "passing/compile", "passing/skipped"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    warning: Test passing/skipped is marked as skipped, but was given explicitly
+    hint: Pass --respect-skip to leave out skipped tests
+    passing/compile compile-only
+    passing/skipped compile-only skip
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_list_explicit_respect_skip() {
+    let env = skipped_package();
+    let res = env.run_tytanic([
+        "list",
+        "--respect-skip",
+        "passing/compile",
+        "passing/skipped",
+    ]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    warning: Test passing/skipped is marked as skipped and was left out
+    passing/compile compile-only
+
+    --- END
+    ");
+}
@@ -42,7 +42,7 @@ fn test_new_add_alias() {
     --- STDOUT:
 
     --- STDERR:
-    warning: Sub command alias add is deprecated
+    warning: Sub command alias add is deprecated since 0.1.2 and will be removed in 0.3.0
     hint: Use new instead
     Added foo
 
@@ -0,0 +1,202 @@
+mod fixture;
+
+#[test]
+fn test_run_timeout() {
+    let env = fixture::Environment::default_package();
+    std::fs::create_dir_all(env.root().join("tests").join("loop")).unwrap();
+    std::fs::write(
+        env.root().join("tests").join("loop").join("test.typ"),
+        "#for i in range(1000000) { for j in range(1000000) {} }\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "--timeout", "1s", "--json", "loop"]);
+
+    assert_eq!(res.output().status().code(), Some(1), "{}", res.output());
+    assert!(
+        res.output().stdout().contains(r#""stage": "timed-out""#),
+        "{}",
+        res.output(),
+    );
+}
+
+#[test]
+fn test_run_html_report() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic([
+        "run",
+        "--html",
+        "report.html",
+        "failing/persistent-compare-failure",
+    ]);
+    assert_eq!(res.output().status().code(), Some(1), "{}", res.output());
+
+    let report = std::fs::read_to_string(env.root().join("report.html")).unwrap();
+    assert!(report.starts_with("<!DOCTYPE html>"), "{report}");
+    assert!(report.contains("<title>Test report for template</title>"));
+    assert!(
+        report.contains(r#"<section class="test fail" id="failing/persistent-compare-failure">"#)
+    );
+    assert!(report.contains("0 passed, 1 failed"));
+    assert!(report.contains(r#"alt="reference page 1""#));
+    assert!(report.contains(r#"alt="difference page 1""#));
+    assert!(report.trim_end().ends_with("</html>"));
+}
+
+#[test]
+fn test_run_export_pdf() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["run", "--export", "pdf", "passing/persistent"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+
+    let pdf = std::fs::read(env.root().join("tests/passing/persistent/out/document.pdf")).unwrap();
+    assert!(pdf.starts_with(b"%PDF-"));
+}
+
+#[test]
+fn test_run_compare_pdf() {
+    let env = fixture::Environment::default_package();
+    let test = env.root().join("tests").join("pdf");
+    std::fs::create_dir_all(test.join("ref")).unwrap();
+    std::fs::write(test.join("test.typ"), "/// [compare: pdf]\n\nHello\n").unwrap();
+
+    let res = env.run_tytanic(["update", "--force", "pdf"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+    assert!(test.join("ref/document.pdf").is_file());
+    assert!(test.join("ref/text.json").is_file());
+
+    let res = env.run_tytanic(["run", "pdf"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+    assert!(test.join("out/document.pdf").is_file());
+
+    std::fs::write(
+        test.join("test.typ"),
+        "/// [compare: pdf, 100pt]\n#set text(font: \"DejaVu Sans Mono\")\n\nHello\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic(["run", "pdf"]);
+    assert_eq!(res.output().status().code(), Some(1), "{}", res.output());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("Page 1 used different fonts"),
+        "{}",
+        res.output()
+    );
+}
+
+#[test]
+fn test_run_daemon() {
+    let env = fixture::Environment::default_package();
+
+    let addr = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap();
+
+    let mut daemon = std::process::Command::new(std::env!("CARGO_BIN_EXE_tt"))
+        .args(["daemon", "--listen", &addr.to_string()])
+        .current_dir(env.root())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let start = std::time::Instant::now();
+    while std::net::TcpStream::connect(addr).is_err() {
+        assert!(
+            start.elapsed() < std::time::Duration::from_secs(30),
+            "daemon didn't start listening"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+
+    let daemon_arg = format!("--daemon={addr}");
+    let passing = env.run_tytanic(["run", &daemon_arg, "--expression", "r:^passing/"]);
+    let failing = env.run_tytanic(["run", &daemon_arg, "failing/persistent-compare-failure"]);
+
+    daemon.kill().unwrap();
+    daemon.wait().unwrap();
+
+    assert_eq!(
+        passing.output().status().code(),
+        Some(0),
+        "{}",
+        passing.output()
+    );
+    assert_eq!(
+        failing.output().status().code(),
+        Some(1),
+        "{}",
+        failing.output()
+    );
+}
+
+#[test]
+fn test_run_missing_report_dir() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["run", "--html", "missing/report.html", "passing/compile"]);
+    assert_eq!(res.output().status().code(), Some(2), "{}", res.output());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("couldn't write missing/report.html"),
+        "{}",
+        res.output()
+    );
+    assert!(
+        !res.output().stderr().contains("crash report"),
+        "{}",
+        res.output()
+    );
+    assert!(!env.root().join("tests/.tytanic-cache/crash").exists());
+}
+
+#[test]
+fn test_run_crash_report() {
+    let env = fixture::Environment::default_package();
+    let dir = env.root().join("tests/.tytanic-cache/crash");
+
+    let res = env.run_tytanic_with(|cmd| {
+        cmd.env("TYTANIC_DEBUG_CRASH", "error")
+            .args(["run", "passing/compile"])
+    });
+    assert_eq!(res.output().status().code(), Some(2), "{}", res.output());
+    assert!(
+        res.output().stderr().contains("Attach the crash report at"),
+        "{}",
+        res.output()
+    );
+
+    let reports: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(reports.len(), 1);
+
+    let report = std::fs::read_to_string(reports[0].as_ref().unwrap().path()).unwrap();
+    assert!(report.starts_with("# tytanic crash report\n"), "{report}");
+    assert!(report.contains("Command line: "), "{report}");
+    assert!(!report.contains("Run ID: none"), "{report}");
+    assert!(
+        report.contains("\n## Error\ninternal error requested by TYTANIC_DEBUG_CRASH"),
+        "{report}"
+    );
+    assert!(report.contains("\n## Log\n"), "{report}");
+
+    let res = env.run_tytanic_with(|cmd| {
+        cmd.env("TYTANIC_DEBUG_CRASH", "panic")
+            .args(["run", "passing/compile"])
+    });
+    assert_eq!(res.output().status().code(), Some(101), "{}", res.output());
+    assert!(
+        res.output()
+            .stderr()
+            .contains("A crash report was written to"),
+        "{}",
+        res.output()
+    );
+
+    let reports: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(reports.len(), 2);
+}
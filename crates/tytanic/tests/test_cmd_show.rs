@@ -0,0 +1,150 @@
+mod fixture;
+
+#[test]
+fn test_show_not_found() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["show", "foo"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 2
+    --- STDOUT:
+
+    --- STDERR:
+    error: Unit test foo not found
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_show_missing_dirs() {
+    let env = fixture::Environment::default_package();
+    let res = env.run_tytanic(["show", "--diff", "passing/compile"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 2
+    --- STDOUT:
+
+    --- STDERR:
+    warning: tests/passing/compile/diff doesn't exist
+    error: Test passing/compile has no directories to open
+
+    --- END
+    ");
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod opener {
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use super::fixture;
+
+    /// A stub for the system opener which records the opened paths.
+    struct Opener {
+        env: fixture::Environment,
+    }
+
+    impl Opener {
+        fn new() -> Self {
+            let env = fixture::Environment::new();
+            let script = env.root().join("xdg-open");
+            fs::write(
+                &script,
+                format!(
+                    "#!/bin/sh\necho \"$1\" >> '{}'\n",
+                    env.root().join("opened.txt").display(),
+                ),
+            )
+            .unwrap();
+            fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+            Self { env }
+        }
+
+        fn path_var(&self) -> String {
+            format!(
+                "{}:{}",
+                self.env.root().display(),
+                std::env::var("PATH").unwrap_or_default(),
+            )
+        }
+
+        /// Waits for the given number of paths to be opened, the opener is
+        /// spawned without waiting for it to exit.
+        fn opened(&self, len: usize, root: &Path) -> Vec<PathBuf> {
+            let start = Instant::now();
+            loop {
+                let opened =
+                    fs::read_to_string(self.env.root().join("opened.txt")).unwrap_or_default();
+                let mut opened: Vec<_> = opened
+                    .lines()
+                    .map(|line| {
+                        Path::new(line)
+                            .strip_prefix(root)
+                            .expect("opened path is in project")
+                            .to_path_buf()
+                    })
+                    .collect();
+
+                if opened.len() >= len || start.elapsed() > Duration::from_secs(10) {
+                    opened.sort();
+                    return opened;
+                }
+
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+    }
+
+    #[test]
+    fn test_show() {
+        let env = fixture::Environment::default_package();
+        let opener = Opener::new();
+        let id = "failing/persistent-compare-failure";
+
+        let res = env.run_tytanic(["run", id]);
+        assert_eq!(res.output().status().code(), Some(1), "{}", res.output());
+
+        let res = env.run_tytanic_with(|cmd| cmd.env("PATH", opener.path_var()).args(["show", id]));
+        assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+
+        assert_eq!(
+            opener.opened(3, env.root()),
+            [
+                PathBuf::from("tests/failing/persistent-compare-failure/diff"),
+                PathBuf::from("tests/failing/persistent-compare-failure/out"),
+                PathBuf::from("tests/failing/persistent-compare-failure/ref"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_run_open_diff() {
+        let env = fixture::Environment::default_package();
+        let opener = Opener::new();
+
+        let res = env.run_tytanic_with(|cmd| {
+            cmd.env("PATH", opener.path_var()).args([
+                "run",
+                "--no-fail-fast",
+                "--open-diff",
+                "passing/persistent",
+                "failing/persistent-compare-failure",
+            ])
+        });
+        assert_eq!(res.output().status().code(), Some(1), "{}", res.output());
+
+        assert_eq!(
+            opener.opened(1, env.root()),
+            [PathBuf::from(
+                "tests/failing/persistent-compare-failure/diff"
+            )],
+        );
+    }
+}
@@ -16,6 +16,10 @@ fn test_status() {
        Tests ├ 3 persistent
              ├ 3 ephemeral
              └ 2 compile-only
+    warning: The manifest doesn't exclude test artifacts or large references from the package bundle, missing:
+               tests/**/out
+               tests/**/diff
+    hint: Run tt util fix-excludes to add them
 
     --- END
     ");
@@ -0,0 +1,128 @@
+mod fixture;
+
+use std::fs;
+
+#[test]
+fn test_mv_module_nested_into_existing() {
+    let env = fixture::Environment::default_package();
+    let tests = env.root().join("tests");
+    fs::create_dir_all(tests.join("nested/outer/inner")).unwrap();
+    fs::write(tests.join("nested/outer/test.typ"), "Outer\n").unwrap();
+    fs::write(tests.join("nested/outer/inner/test.typ"), "Inner\n").unwrap();
+    fs::write(tests.join("nested/shared.typ"), "Shared\n").unwrap();
+
+    let res = env.run_tytanic(["util", "mv-module", "nested", "passing"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+    warning: Found nested tests
+    hint: This is no longer supported, these tests will be ignored
+    hint: This will become a hard error in a future version
+    hint: You can run tt util migrate to automatically move the tests
+    Moving tests:
+      nested/outer/inner -> passing/outer/inner
+
+    Moved 1 test
+
+    --- END
+    ");
+
+    assert!(!tests.join("nested").exists());
+    assert!(tests.join("passing/outer/test.typ").is_file());
+    assert!(tests.join("passing/outer/inner/test.typ").is_file());
+    assert!(tests.join("passing/shared.typ").is_file());
+}
+
+#[test]
+fn test_mv_module_collision_rolls_back() {
+    let env = fixture::Environment::default_package();
+    let tests = env.root().join("tests");
+    fs::create_dir_all(tests.join("nested/a")).unwrap();
+    fs::create_dir_all(tests.join("nested/z")).unwrap();
+    fs::write(tests.join("nested/a/test.typ"), "A\n").unwrap();
+    fs::write(tests.join("nested/z/test.typ"), "Z\n").unwrap();
+    fs::write(tests.join("nested/shared.typ"), "Shared\n").unwrap();
+    fs::write(tests.join("passing/shared.typ"), "Other\n").unwrap();
+
+    let res = env.run_tytanic(["util", "mv-module", "nested", "passing"]);
+
+    assert_eq!(res.output().status().code(), Some(2), "{}", res.output());
+
+    assert!(tests.join("nested/a/test.typ").is_file());
+    assert!(tests.join("nested/z/test.typ").is_file());
+    assert!(tests.join("nested/shared.typ").is_file());
+    assert!(!tests.join("passing/a").exists());
+    assert!(!tests.join("passing/z").exists());
+}
+
+#[test]
+fn test_fix_excludes() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["util", "fix-excludes", "--confirm"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+
+    let manifest = fs::read_to_string(env.root().join("typst.toml")).unwrap();
+    assert!(
+        manifest.contains(r#"exclude = ["tests/**/out", "tests/**/diff"]"#),
+        "{manifest}",
+    );
+
+    let res = env.run_tytanic(["status"]);
+    assert!(
+        !res.output().stderr().contains("warning"),
+        "{}",
+        res.output()
+    );
+}
+
+#[test]
+fn test_completion_registration() {
+    let env = fixture::Environment::default_package();
+
+    let res = env.run_tytanic(["util", "completion", "bash"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+    assert!(
+        res.output().stdout().contains("TT_COMPLETE"),
+        "{}",
+        res.output()
+    );
+
+    let res = env.run_tytanic(["util", "completion", "--static", "bash"]);
+    assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+    assert!(
+        !res.output().stdout().contains("TT_COMPLETE"),
+        "{}",
+        res.output()
+    );
+}
+
+#[test]
+fn test_completion_dynamic() {
+    let env = fixture::Environment::default_package();
+    let manifest = env.root().join("typst.toml");
+    let mut content = fs::read_to_string(&manifest).unwrap();
+    content.push_str(
+        "\n[tool.tytanic.profile.ci]\nfail-fast = false\n\
+        \n[tool.tytanic.test-sets]\nsmoke = 'regex:^passing/'\n",
+    );
+    fs::write(&manifest, content).unwrap();
+
+    let complete = |args: &[&str]| {
+        let res = env
+            .run_tytanic_with(|cmd| cmd.env("TT_COMPLETE", "fish").args(["--", "tt"]).args(args));
+        assert_eq!(res.output().status().code(), Some(0), "{}", res.output());
+        res.output().stdout().to_owned()
+    };
+
+    insta::assert_snapshot!(complete(&["run", "pass"]), @r"
+    passing/compile
+    passing/ephemeral
+    passing/persistent
+    ");
+    insta::assert_snapshot!(complete(&["run", "-e", "all() & smo"]), @"all() & smoke(");
+    insta::assert_snapshot!(complete(&["--profile", ""]), @"ci");
+}
@@ -31,3 +31,65 @@ fn test_root_no_manifest() {
     --- END
     ");
 }
+
+#[test]
+fn test_root_vcs_fallback() {
+    let env = fixture::Environment::new();
+    std::fs::create_dir_all(env.root().join(".git")).unwrap();
+    std::fs::create_dir_all(env.root().join("chapters")).unwrap();
+
+    let res = env.run_tytanic_in("chapters", ["status"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 2
+    --- STDOUT:
+
+    --- STDERR:
+    error: Must be in a typst project
+    hint: You can pass the project root using --root <path>
+          You can use the repository root <TEMP_DIR> using --allow-no-manifest
+
+    --- END
+    ");
+
+    let res = env.run_tytanic_in("chapters", ["--allow-no-manifest", "status"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+     Project ┌ none
+         Vcs ├ Git
+    Template ├ none
+       Tests └ none
+
+    --- END
+    ");
+}
+
+#[test]
+fn test_root_document_manifest() {
+    let env = fixture::Environment::new();
+    std::fs::create_dir_all(env.root().join("tests").join("chapters")).unwrap();
+    std::fs::write(
+        env.root().join("tests").join("tytanic.toml"),
+        "[document]\nname = \"thesis\"\nentrypoints = [\"main.typ\"]\n",
+    )
+    .unwrap();
+
+    let res = env.run_tytanic_in("tests/chapters", ["status"]);
+
+    insta::assert_snapshot!(res.output(), @r"
+    --- CODE: 0
+    --- STDOUT:
+
+    --- STDERR:
+     Project ┌ thesis (document)
+         Vcs ├ none
+    Template ├ none
+       Tests └ none
+
+    --- END
+    ");
+}
@@ -25,6 +25,53 @@
       inputs.flake-parts.flakeModules.easyOverlay
     ];
 
+    flake.lib = {
+      # Creates a check derivation which runs the test suite of a Typst
+      # project, see the "Setting Up CI" guide for more information.
+      #
+      # - `src`: The project root.
+      # - `fontPaths`: Additional font directories.
+      # - `packagePath`: A directory of local packages, packages can't be
+      #   downloaded from within the sandbox.
+      # - `timestamp`: The timestamp used for compilation in seconds since the
+      #   unix epoch, such that documents using the current date are
+      #   reproducible.
+      # - `args`: Additional arguments passed to `tt run`.
+      mkCheck =
+        { pkgs
+        , src
+        , name ? "tytanic-check"
+        , fontPaths ? [ ]
+        , packagePath ? null
+        , timestamp ? 0
+        , args ? [ ]
+        }:
+        let
+          lib = pkgs.lib;
+          tytanic = self.packages.${pkgs.stdenv.hostPlatform.system}.default;
+        in
+        pkgs.runCommand name
+          ({
+            nativeBuildInputs = [ tytanic ];
+          } // lib.optionalAttrs (fontPaths != [ ]) {
+            TYPST_FONT_PATHS = lib.concatStringsSep ":" (map toString fontPaths);
+          })
+          ''
+            cp -r ${src} source
+            chmod -R u+w source
+            cd source
+
+            mkdir -p $out
+            tt run \
+              --format quiet-errors \
+              --color=never \
+              --summary $out/summary.json \
+              --timestamp ${toString timestamp} \
+              ${lib.optionalString (packagePath != null) "--package-path ${packagePath}"} \
+              ${lib.escapeShellArgs args}
+          '';
+    };
+
     perSystem = { self', pkgs, lib, system, ... }:
       let
         cargoToml = lib.importTOML ./Cargo.toml;